@@ -62,6 +62,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             } else {
                 println!("⚠️  No outputs found to test capture");
             }
+            
+            println!("\n--- Testing Window Capture ---");
+            if client.supports_window_capture() {
+                let toplevels = client.get_toplevels();
+                println!("🪟 Found {} window(s):", toplevels.len());
+                
+                for toplevel in &toplevels {
+                    println!("  - {} \"{}\" ({})", toplevel.identifier, toplevel.title, toplevel.app_id);
+                }
+                
+                if let Some(first_toplevel) = toplevels.first() {
+                    match client.capture_toplevel(&first_toplevel.identifier) {
+                        Ok(buffer) => println!("✅ Successfully captured window: {}",
+                            bevy_niri_integration::utils::format_buffer_info(&buffer)),
+                        Err(e) => println!("❌ Failed to capture window: {:?}", e),
+                    }
+                }
+            } else {
+                println!("⚠️  Compositor doesn't support window capture");
+            }
         }
         Err(e) => {
             println!("❌ Failed to connect to Wayland compositor: {:?}", e);
@@ -1,18 +1,39 @@
-use std::collections::HashMap;
-use crate::wayland_client::CaptureBuffer;
+use std::collections::{HashMap, VecDeque};
+use bevy::prelude::Resource;
 
-#[derive(Debug, Clone)]
+/// Chooses between DMA-BUF and SHM capture per output based on measured captures.
+///
+/// The choice is deterministic: it only depends on the results passed to
+/// [`record_capture_result`](Self::record_capture_result). To avoid flapping between methods, the
+/// controller only switches when the other method is faster by a margin, and stays on a method
+/// for a while after switching to it.
+#[derive(Debug, Clone, Resource)]
 pub struct AdaptiveCaptureSystem {
+    config: AdaptiveConfig,
+    preferred_method: CaptureMethod,
+    dmabuf_available: bool,
     performance_monitor: PerformanceMonitor,
-    strategy: CaptureStrategy,
     output_stats: HashMap<String, OutputStats>,
 }
 
+/// Tuning of the switching policy of [`AdaptiveCaptureSystem`].
 #[derive(Debug, Clone)]
-enum CaptureStrategy {
-    PreferDmabuf,
-    PreferShm,
-    Adaptive { dmabuf_ratio: f32 },
+pub struct AdaptiveConfig {
+    /// Number of recent captures per method that statistics are computed over.
+    pub window: usize,
+    /// Number of successful captures of both methods needed before comparing their latency.
+    pub min_samples: usize,
+    /// How much faster, relative to the current method, the other method must be to switch.
+    pub switch_margin: f32,
+    /// Number of consecutive failures after which to switch away from the current method.
+    pub failure_threshold: u32,
+    /// Number of captures to stay on a method after switching to it.
+    pub cooldown: u32,
+    /// Number of captures after which to try the other method once, to keep its statistics
+    /// fresh.
+    ///
+    /// Zero disables probing.
+    pub probe_interval: u32,
 }
 
 #[derive(Debug, Clone)]
@@ -22,16 +43,28 @@ pub struct PerformanceMonitor {
     gpu_utilization: f32,
 }
 
+/// Capture statistics and the current decision for one output.
 #[derive(Debug, Clone)]
-struct OutputStats {
-    dmabuf_success_rate: f32,
-    dmabuf_latency: f32,
-    shm_latency: f32,
-    memory_pressure: f32,
-    recent_failures: u32,
+pub struct OutputStats {
+    dmabuf: MethodStats,
+    shm: MethodStats,
+    current_method: CaptureMethod,
+    captures_since_switch: u32,
+    captures_since_probe: u32,
+    switches: u32,
+}
+
+/// Statistics of one capture method over the recent captures.
+#[derive(Debug, Clone, Default)]
+pub struct MethodStats {
+    latencies: VecDeque<f32>,
+    results: VecDeque<bool>,
+    consecutive_failures: u32,
+    total_captures: u64,
+    total_failures: u64,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CaptureMethod {
     Dmabuf,
     Shm,
@@ -39,91 +72,151 @@ pub enum CaptureMethod {
 
 impl AdaptiveCaptureSystem {
     pub fn new() -> Self {
+        Self::with_config(AdaptiveConfig::default())
+    }
+
+    pub fn with_config(config: AdaptiveConfig) -> Self {
         Self {
+            config,
+            preferred_method: CaptureMethod::Dmabuf,
+            dmabuf_available: true,
             performance_monitor: PerformanceMonitor::new(),
-            strategy: CaptureStrategy::Adaptive { dmabuf_ratio: 0.8 },
             output_stats: HashMap::new(),
         }
     }
-    
-    pub fn select_optimal_method(&self, output: &str) -> CaptureMethod {
-        let stats = self.output_stats.get(output)
-            .cloned()
-            .unwrap_or_else(|| OutputStats::default());
-        
-        match self.strategy {
-            CaptureStrategy::PreferDmabuf => {
-                if stats.dmabuf_success_rate > 0.7 {
-                    CaptureMethod::Dmabuf
-                } else {
-                    CaptureMethod::Shm
-                }
-            }
-            CaptureStrategy::PreferShm => CaptureMethod::Shm,
-            CaptureStrategy::Adaptive { dmabuf_ratio } => {
-                if stats.dmabuf_success_rate > 0.95 && stats.dmabuf_latency < 2.0 {
-                    CaptureMethod::Dmabuf
-                } else if stats.memory_pressure > 0.8 {
-                    CaptureMethod::Dmabuf
-                } else if stats.recent_failures > 3 {
-                    CaptureMethod::Shm
-                } else {
-                    if rand::random::<f32>() < dmabuf_ratio {
-                        CaptureMethod::Dmabuf
-                    } else {
-                        CaptureMethod::Shm
-                    }
-                }
+
+    /// Sets the method that outputs start with before there are any measurements.
+    pub fn set_preferred_method(&mut self, method: CaptureMethod) {
+        self.preferred_method = method;
+    }
+
+    /// Sets whether DMA-BUF capture is possible at all.
+    ///
+    /// When it isn't, every output uses SHM.
+    pub fn set_dmabuf_available(&mut self, available: bool) {
+        self.dmabuf_available = available;
+
+        if !available {
+            for stats in self.output_stats.values_mut() {
+                stats.switch_to(CaptureMethod::Shm);
             }
         }
     }
-    
+
+    pub fn config(&self) -> &AdaptiveConfig {
+        &self.config
+    }
+
+    fn initial_method(&self) -> CaptureMethod {
+        if self.dmabuf_available {
+            self.preferred_method
+        } else {
+            CaptureMethod::Shm
+        }
+    }
+
+    /// Returns the method to use for the next capture of the output.
+    pub fn select_optimal_method(&self, output: &str) -> CaptureMethod {
+        if !self.dmabuf_available {
+            return CaptureMethod::Shm;
+        }
+
+        let Some(stats) = self.output_stats.get(output) else {
+            return self.initial_method();
+        };
+
+        let probe_interval = self.config.probe_interval;
+        if probe_interval != 0 && stats.captures_since_probe >= probe_interval {
+            stats.current_method.other()
+        } else {
+            stats.current_method
+        }
+    }
+
+    /// Records the result of a capture and updates the decision for the output.
+    ///
+    /// `latency` is in milliseconds and is only used for successful captures.
     pub fn record_capture_result(&mut self, output: &str, method: CaptureMethod, success: bool, latency: f32) {
+        let initial_method = self.initial_method();
+        let config = &self.config;
         let stats = self.output_stats.entry(output.to_string())
-            .or_insert_with(OutputStats::default);
-        
-        match method {
-            CaptureMethod::Dmabuf => {
-                if success {
-                    stats.dmabuf_success_rate = stats.dmabuf_success_rate * 0.9 + 0.1;
-                    stats.dmabuf_latency = stats.dmabuf_latency * 0.9 + latency * 0.1;
-                    stats.recent_failures = 0;
-                } else {
-                    stats.dmabuf_success_rate = stats.dmabuf_success_rate * 0.9;
-                    stats.recent_failures += 1;
-                }
-            }
-            CaptureMethod::Shm => {
-                if success {
-                    stats.shm_latency = stats.shm_latency * 0.9 + latency * 0.1;
-                } else {
-                    stats.recent_failures += 1;
-                }
+            .or_insert_with(|| OutputStats::new(initial_method));
+
+        stats.method_stats_mut(method).record(success, latency, config.window);
+
+        if method == stats.current_method {
+            stats.captures_since_switch = stats.captures_since_switch.saturating_add(1);
+            stats.captures_since_probe = stats.captures_since_probe.saturating_add(1);
+        } else {
+            stats.captures_since_probe = 0;
+        }
+
+        if self.dmabuf_available {
+            if let Some(method) = stats.decide(config) {
+                stats.switch_to(method);
             }
         }
-        
-        self.performance_monitor.record_frame_time(latency);
+
+        if success {
+            self.performance_monitor.record_frame_time(latency);
+        }
+    }
+
+    pub fn current_method(&self, output: &str) -> CaptureMethod {
+        self.output_stats.get(output)
+            .map_or_else(|| self.initial_method(), |stats| stats.current_method)
+    }
+
+    pub fn output_stats(&self, output: &str) -> Option<&OutputStats> {
+        self.output_stats.get(output)
+    }
+
+    pub fn outputs(&self) -> impl Iterator<Item = (&str, &OutputStats)> {
+        self.output_stats.iter().map(|(name, stats)| (name.as_str(), stats))
+    }
+
+    pub fn remove_output(&mut self, output: &str) {
+        self.output_stats.remove(output);
+    }
+
+    pub fn performance_monitor(&self) -> &PerformanceMonitor {
+        &self.performance_monitor
     }
-    
+
     pub fn update_system_stats(&mut self, memory_usage: f32, gpu_utilization: f32) {
         self.performance_monitor.memory_usage = memory_usage;
         self.performance_monitor.gpu_utilization = gpu_utilization;
-        
-        for stats in self.output_stats.values_mut() {
-            stats.memory_pressure = memory_usage;
-        }
     }
-    
+
     pub fn get_performance_summary(&self) -> String {
+        let dmabuf_outputs = self.output_stats.values()
+            .filter(|stats| stats.current_method == CaptureMethod::Dmabuf)
+            .count();
+
         format!(
-            "Adaptive Capture Stats - Memory: {:.1}%, GPU: {:.1}%, Outputs: {}",
+            "Adaptive Capture Stats - Memory: {:.1}%, GPU: {:.1}%, Outputs: {} ({} DMA-BUF), Avg latency: {:.2}ms",
             self.performance_monitor.memory_usage * 100.0,
             self.performance_monitor.gpu_utilization * 100.0,
-            self.output_stats.len()
+            self.output_stats.len(),
+            dmabuf_outputs,
+            self.performance_monitor.average_frame_time(),
         )
     }
 }
 
+impl Default for AdaptiveConfig {
+    fn default() -> Self {
+        Self {
+            window: 30,
+            min_samples: 5,
+            switch_margin: 0.2,
+            failure_threshold: 3,
+            cooldown: 30,
+            probe_interval: 120,
+        }
+    }
+}
+
 impl PerformanceMonitor {
     fn new() -> Self {
         Self {
@@ -132,14 +225,14 @@ impl PerformanceMonitor {
             gpu_utilization: 0.0,
         }
     }
-    
+
     fn record_frame_time(&mut self, frame_time: f32) {
         self.frame_times.push(frame_time);
         if self.frame_times.len() > 60 {
             self.frame_times.remove(0);
         }
     }
-    
+
     pub fn average_frame_time(&self) -> f32 {
         if self.frame_times.is_empty() {
             0.0
@@ -149,14 +242,142 @@ impl PerformanceMonitor {
     }
 }
 
-impl Default for OutputStats {
-    fn default() -> Self {
+impl OutputStats {
+    fn new(method: CaptureMethod) -> Self {
         Self {
-            dmabuf_success_rate: 0.5,
-            dmabuf_latency: 5.0,
-            shm_latency: 10.0,
-            memory_pressure: 0.0,
-            recent_failures: 0,
+            dmabuf: MethodStats::default(),
+            shm: MethodStats::default(),
+            current_method: method,
+            captures_since_switch: 0,
+            captures_since_probe: 0,
+            switches: 0,
+        }
+    }
+
+    pub fn current_method(&self) -> CaptureMethod {
+        self.current_method
+    }
+
+    /// Number of times the output switched capture methods.
+    pub fn switches(&self) -> u32 {
+        self.switches
+    }
+
+    pub fn method_stats(&self, method: CaptureMethod) -> &MethodStats {
+        match method {
+            CaptureMethod::Dmabuf => &self.dmabuf,
+            CaptureMethod::Shm => &self.shm,
+        }
+    }
+
+    fn method_stats_mut(&mut self, method: CaptureMethod) -> &mut MethodStats {
+        match method {
+            CaptureMethod::Dmabuf => &mut self.dmabuf,
+            CaptureMethod::Shm => &mut self.shm,
+        }
+    }
+
+    fn switch_to(&mut self, method: CaptureMethod) {
+        if self.current_method != method {
+            self.current_method = method;
+            self.captures_since_switch = 0;
+            self.captures_since_probe = 0;
+            self.switches += 1;
+        }
+    }
+
+    /// Returns the method to switch to, if any.
+    fn decide(&self, config: &AdaptiveConfig) -> Option<CaptureMethod> {
+        let other_method = self.current_method.other();
+        let current = self.method_stats(self.current_method);
+        let other = self.method_stats(other_method);
+
+        // A failing method is abandoned right away, unless the other one is failing too.
+        if current.consecutive_failures >= config.failure_threshold {
+            return (other.consecutive_failures < config.failure_threshold).then_some(other_method);
+        }
+
+        if self.captures_since_switch < config.cooldown {
+            return None;
+        }
+
+        if current.samples() < config.min_samples || other.samples() < config.min_samples {
+            return None;
+        }
+
+        if other.consecutive_failures > 0 || other.failure_rate() > current.failure_rate() {
+            return None;
+        }
+
+        let current_latency = current.average_latency()?;
+        let other_latency = other.average_latency()?;
+        (other_latency * (1.0 + config.switch_margin) < current_latency).then_some(other_method)
+    }
+}
+
+impl MethodStats {
+    fn record(&mut self, success: bool, latency: f32, window: usize) {
+        self.total_captures += 1;
+
+        if success {
+            self.consecutive_failures = 0;
+            self.latencies.push_back(latency);
+            while self.latencies.len() > window {
+                self.latencies.pop_front();
+            }
+        } else {
+            self.total_failures += 1;
+            self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        }
+
+        self.results.push_back(success);
+        while self.results.len() > window {
+            self.results.pop_front();
+        }
+    }
+
+    /// Average latency of the recent successful captures in milliseconds.
+    pub fn average_latency(&self) -> Option<f32> {
+        if self.latencies.is_empty() {
+            None
+        } else {
+            Some(self.latencies.iter().sum::<f32>() / self.latencies.len() as f32)
+        }
+    }
+
+    /// Fraction of the recent captures that failed.
+    pub fn failure_rate(&self) -> f32 {
+        if self.results.is_empty() {
+            0.0
+        } else {
+            let failures = self.results.iter().filter(|success| !**success).count();
+            failures as f32 / self.results.len() as f32
+        }
+    }
+
+    /// Number of recent successful captures.
+    pub fn samples(&self) -> usize {
+        self.latencies.len()
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    pub fn total_captures(&self) -> u64 {
+        self.total_captures
+    }
+
+    pub fn total_failures(&self) -> u64 {
+        self.total_failures
+    }
+}
+
+impl CaptureMethod {
+    pub fn other(self) -> Self {
+        match self {
+            CaptureMethod::Dmabuf => CaptureMethod::Shm,
+            CaptureMethod::Shm => CaptureMethod::Dmabuf,
         }
     }
 }
@@ -170,28 +391,205 @@ impl Default for AdaptiveCaptureSystem {
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+
+    /// Runs captures of one output, where `trace` gives the result of a capture with a method.
+    fn run_trace(
+        system: &mut AdaptiveCaptureSystem,
+        captures: usize,
+        mut trace: impl FnMut(usize, CaptureMethod) -> (bool, f32),
+    ) -> Vec<CaptureMethod> {
+        (0..captures)
+            .map(|i| {
+                let method = system.select_optimal_method("test");
+                let (success, latency) = trace(i, method);
+                system.record_capture_result("test", method, success, latency);
+                method
+            })
+            .collect()
+    }
+
+    fn no_probing() -> AdaptiveConfig {
+        AdaptiveConfig {
+            probe_interval: 0,
+            ..AdaptiveConfig::default()
+        }
+    }
+
     #[test]
     fn test_adaptive_system_creation() {
         let system = AdaptiveCaptureSystem::new();
         assert_eq!(system.output_stats.len(), 0);
     }
-    
+
     #[test]
     fn test_method_selection() {
         let system = AdaptiveCaptureSystem::new();
         let method = system.select_optimal_method("test-output");
-        
-        match method {
-            CaptureMethod::Dmabuf | CaptureMethod::Shm => {}
-        }
+
+        assert_eq!(method, CaptureMethod::Dmabuf);
     }
-    
+
     #[test]
     fn test_performance_recording() {
         let mut system = AdaptiveCaptureSystem::new();
         system.record_capture_result("test", CaptureMethod::Dmabuf, true, 1.5);
-        
+
         assert!(system.output_stats.contains_key("test"));
     }
+
+    #[test]
+    fn test_same_trace_same_decisions() {
+        let trace = |i: usize, method| match method {
+            CaptureMethod::Dmabuf => (i % 7 != 0, 3.0 + (i % 5) as f32),
+            CaptureMethod::Shm => (true, 4.0 + (i % 3) as f32),
+        };
+
+        let mut first = AdaptiveCaptureSystem::new();
+        let mut second = AdaptiveCaptureSystem::new();
+        assert_eq!(run_trace(&mut first, 500, trace), run_trace(&mut second, 500, trace));
+    }
+
+    #[test]
+    fn test_switches_away_from_failing_method() {
+        let mut system = AdaptiveCaptureSystem::with_config(no_probing());
+
+        let methods = run_trace(&mut system, 10, |_, method| match method {
+            CaptureMethod::Dmabuf => (false, 0.0),
+            CaptureMethod::Shm => (true, 8.0),
+        });
+
+        let threshold = system.config().failure_threshold as usize;
+        assert!(methods[..threshold].iter().all(|m| *m == CaptureMethod::Dmabuf));
+        assert!(methods[threshold..].iter().all(|m| *m == CaptureMethod::Shm));
+
+        let stats = system.output_stats("test").unwrap();
+        assert_eq!(stats.switches(), 1);
+        assert_eq!(stats.method_stats(CaptureMethod::Dmabuf).total_failures(), threshold as u64);
+    }
+
+    #[test]
+    fn test_stays_when_both_methods_fail() {
+        let mut system = AdaptiveCaptureSystem::with_config(no_probing());
+
+        run_trace(&mut system, 3, |_, _| (true, 2.0));
+        system.record_capture_result("test", CaptureMethod::Shm, false, 0.0);
+        system.record_capture_result("test", CaptureMethod::Shm, false, 0.0);
+        system.record_capture_result("test", CaptureMethod::Shm, false, 0.0);
+        run_trace(&mut system, 5, |_, _| (false, 0.0));
+
+        assert_eq!(system.current_method("test"), CaptureMethod::Dmabuf);
+    }
+
+    #[test]
+    fn test_small_latency_difference_does_not_switch() {
+        let mut system = AdaptiveCaptureSystem::new();
+
+        // SHM is 10% faster, which is within the switching margin.
+        run_trace(&mut system, 1000, |_, method| match method {
+            CaptureMethod::Dmabuf => (true, 5.0),
+            CaptureMethod::Shm => (true, 4.5),
+        });
+
+        let stats = system.output_stats("test").unwrap();
+        assert_eq!(stats.current_method(), CaptureMethod::Dmabuf);
+        assert_eq!(stats.switches(), 0);
+        assert!(stats.method_stats(CaptureMethod::Shm).samples() > 0);
+    }
+
+    #[test]
+    fn test_switches_to_faster_method_after_probing() {
+        let config = AdaptiveConfig {
+            probe_interval: 10,
+            ..AdaptiveConfig::default()
+        };
+        let min_samples = config.min_samples;
+        let mut system = AdaptiveCaptureSystem::with_config(config);
+
+        let methods = run_trace(&mut system, 200, |_, method| match method {
+            CaptureMethod::Dmabuf => (true, 8.0),
+            CaptureMethod::Shm => (true, 2.0),
+        });
+
+        // Switching needs enough SHM probes, one every 11 captures.
+        let switch_at = methods.windows(2).position(|w| w == [CaptureMethod::Shm; 2]).unwrap();
+        assert_eq!(methods[..switch_at].iter().filter(|m| **m == CaptureMethod::Shm).count(), min_samples - 1);
+        assert_eq!(system.output_stats("test").unwrap().switches(), 1);
+
+        // Afterwards, DMA-BUF is only probed.
+        let dmabuf_probes = methods[switch_at..].iter().filter(|m| **m == CaptureMethod::Dmabuf).count();
+        assert_eq!(dmabuf_probes, (methods.len() - switch_at) / 11);
+    }
+
+    #[test]
+    fn test_noisy_latencies_do_not_flap() {
+        let config = AdaptiveConfig {
+            probe_interval: 3,
+            cooldown: 10,
+            ..AdaptiveConfig::default()
+        };
+        let mut system = AdaptiveCaptureSystem::with_config(config);
+
+        // Both methods average to the same latency, with a lot of jitter.
+        run_trace(&mut system, 2000, |i, method| {
+            let jitter = [0.0, 3.0, -2.0, 1.0, -2.0][i % 5];
+            match method {
+                CaptureMethod::Dmabuf => (true, 5.0 + jitter),
+                CaptureMethod::Shm => (true, 5.0 - jitter),
+            }
+        });
+
+        assert_eq!(system.output_stats("test").unwrap().switches(), 0);
+    }
+
+    #[test]
+    fn test_cooldown_after_switch() {
+        let config = AdaptiveConfig {
+            probe_interval: 0,
+            cooldown: 20,
+            ..AdaptiveConfig::default()
+        };
+        let mut system = AdaptiveCaptureSystem::with_config(config);
+
+        // DMA-BUF is faster, but fails a few times in a row once.
+        let methods = run_trace(&mut system, 60, |i, method| match method {
+            CaptureMethod::Dmabuf => (!(5..8).contains(&i), 2.0),
+            CaptureMethod::Shm => (true, 8.0),
+        });
+
+        assert!(methods[8..].iter().all(|m| *m == CaptureMethod::Shm));
+    }
+
+    #[test]
+    fn test_dmabuf_unavailable() {
+        let mut system = AdaptiveCaptureSystem::new();
+        system.record_capture_result("test", CaptureMethod::Dmabuf, true, 1.0);
+        system.set_dmabuf_available(false);
+
+        let methods = run_trace(&mut system, 300, |_, _| (true, 10.0));
+        assert!(methods.iter().all(|m| *m == CaptureMethod::Shm));
+        assert_eq!(system.current_method("test"), CaptureMethod::Shm);
+        assert_eq!(system.current_method("other"), CaptureMethod::Shm);
+    }
+
+    #[test]
+    fn test_preferred_method() {
+        let mut system = AdaptiveCaptureSystem::new();
+        system.set_preferred_method(CaptureMethod::Shm);
+        assert_eq!(system.select_optimal_method("test"), CaptureMethod::Shm);
+    }
+
+    #[test]
+    fn test_method_stats_window() {
+        let mut stats = MethodStats::default();
+        for i in 0..10 {
+            stats.record(i % 2 == 0, i as f32, 4);
+        }
+
+        assert_eq!(stats.samples(), 4);
+        assert_eq!(stats.average_latency(), Some(5.0));
+        assert_eq!(stats.failure_rate(), 0.5);
+        assert_eq!(stats.consecutive_failures(), 1);
+        assert_eq!(stats.total_captures(), 10);
+        assert_eq!(stats.total_failures(), 5);
+    }
 }
@@ -1,7 +1,9 @@
+use std::path::Path;
 use serde::{Deserialize, Serialize};
+use niri_config::BevyCapture;
 use crate::plugin::NiriCapturePlugin;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BevyCaptureConfig {
     pub capture_fps: f32,
     pub prefer_dmabuf: bool,
@@ -24,34 +26,34 @@ impl Default for BevyCaptureConfig {
     }
 }
 
-impl BevyCaptureConfig {
-    pub fn from_kdl_string(kdl_content: &str) -> Result<Self, String> {
-        let mut config = Self::default();
-        
-        for line in kdl_content.lines() {
-            let line = line.trim();
-            if line.starts_with("bevy-capture") {
-                if line.contains("capture-fps") {
-                    if let Some(fps_str) = line.split('=').nth(1) {
-                        if let Ok(fps) = fps_str.trim().parse::<f32>() {
-                            config.capture_fps = fps;
-                        }
-                    }
-                }
-                if line.contains("prefer-dmabuf") {
-                    if let Some(dmabuf_str) = line.split('=').nth(1) {
-                        config.prefer_dmabuf = dmabuf_str.trim() == "true";
-                    }
-                }
-                if line.contains("damage-tracking") {
-                    if let Some(damage_str) = line.split('=').nth(1) {
-                        config.damage_tracking = damage_str.trim() == "true";
-                    }
-                }
-            }
+impl From<&BevyCapture> for BevyCaptureConfig {
+    fn from(config: &BevyCapture) -> Self {
+        Self {
+            capture_fps: config.capture_fps.0 as f32,
+            prefer_dmabuf: config.prefer_dmabuf,
+            outputs: config.outputs.clone(),
+            damage_tracking: config.damage_tracking,
+            adaptive_performance: config.adaptive_performance,
+            max_memory_usage: config.max_memory_usage.0 as f32,
         }
-        
-        Ok(config)
+    }
+}
+
+impl BevyCaptureConfig {
+    /// Parses the `bevy-capture` section out of niri config text.
+    ///
+    /// The text is parsed as a whole niri config, so it can be either a full config or just the
+    /// `bevy-capture` section.
+    pub fn from_kdl_string(kdl_content: &str) -> miette::Result<Self> {
+        let config = niri_config::Config::parse("config.kdl", kdl_content)
+            .map_err(miette::Report::new)?;
+        Ok(Self::from(&config.bevy_capture))
+    }
+    
+    /// Loads the `bevy-capture` section from a niri config file, following its includes.
+    pub fn from_config_file(path: &Path) -> miette::Result<Self> {
+        let config = niri_config::Config::load(path)?;
+        Ok(Self::from(&config.bevy_capture))
     }
     
     pub fn merge_with_api(&mut self, api_config: &NiriCapturePlugin) {
@@ -67,20 +69,18 @@ impl BevyCaptureConfig {
     }
     
     pub fn to_kdl_string(&self) -> String {
-        format!(
-            r#"bevy-capture {{
-    capture-fps = {}
-    prefer-dmabuf = {}
-    damage-tracking = {}
-    adaptive-performance = {}
-    max-memory-usage = {}
-}}"#,
-            self.capture_fps,
-            self.prefer_dmabuf,
-            self.damage_tracking,
-            self.adaptive_performance,
-            self.max_memory_usage
-        )
+        let mut kdl = String::from("bevy-capture {\n");
+        kdl += &format!("    capture-fps {}\n", self.capture_fps);
+        kdl += &format!("    prefer-dmabuf {}\n", self.prefer_dmabuf);
+        if !self.outputs.is_empty() {
+            let outputs: Vec<String> = self.outputs.iter().map(|name| format!("{:?}", name)).collect();
+            kdl += &format!("    outputs {}\n", outputs.join(" "));
+        }
+        kdl += &format!("    damage-tracking {}\n", self.damage_tracking);
+        kdl += &format!("    adaptive-performance {}\n", self.adaptive_performance);
+        kdl += &format!("    max-memory-usage {}\n", self.max_memory_usage);
+        kdl += "}";
+        kdl
     }
     
     pub fn validate(&self) -> Result<(), String> {
@@ -124,13 +124,64 @@ mod tests {
     #[test]
     fn test_kdl_parsing() {
         let kdl_content = r#"
-            bevy-capture capture-fps = 120
-            bevy-capture prefer-dmabuf = false
+            bevy-capture {
+                capture-fps 120
+                prefer-dmabuf false
+                outputs "DP-1" "HDMI-A-1"
+                adaptive-performance false
+                max-memory-usage 0.5
+            }
         "#;
         
         let config = BevyCaptureConfig::from_kdl_string(kdl_content).unwrap();
         assert_eq!(config.capture_fps, 120.0);
         assert!(!config.prefer_dmabuf);
+        assert_eq!(config.outputs, vec!["DP-1", "HDMI-A-1"]);
+        assert!(config.damage_tracking);
+        assert!(!config.adaptive_performance);
+        assert_eq!(config.max_memory_usage, 0.5);
+    }
+    
+    #[test]
+    fn test_kdl_parsing_defaults() {
+        let config = BevyCaptureConfig::from_kdl_string("bevy-capture {}").unwrap();
+        assert_eq!(config, BevyCaptureConfig::default());
+        
+        let config = BevyCaptureConfig::from_kdl_string("prefer-no-csd").unwrap();
+        assert_eq!(config, BevyCaptureConfig::default());
+    }
+    
+    #[test]
+    fn test_kdl_parsing_errors() {
+        assert!(BevyCaptureConfig::from_kdl_string("bevy-capture { capture-fps 500; }").is_err());
+        assert!(BevyCaptureConfig::from_kdl_string("bevy-capture { max-memory-usage 2; }").is_err());
+        assert!(BevyCaptureConfig::from_kdl_string("bevy-capture { prefer-dmabuf \"yes\"; }").is_err());
+        assert!(BevyCaptureConfig::from_kdl_string("bevy-capture { unknown-option; }").is_err());
+    }
+    
+    #[test]
+    fn test_kdl_round_trip() {
+        let configs = [
+            BevyCaptureConfig::default(),
+            BevyCaptureConfig {
+                capture_fps: 144.0,
+                prefer_dmabuf: false,
+                outputs: vec!["DP-1".to_string(), "HDMI-A-1".to_string()],
+                damage_tracking: false,
+                adaptive_performance: false,
+                max_memory_usage: 0.7,
+            },
+            BevyCaptureConfig {
+                capture_fps: 29.97,
+                ..BevyCaptureConfig::default()
+            },
+        ];
+        
+        for config in configs {
+            let kdl = config.to_kdl_string();
+            let parsed = BevyCaptureConfig::from_kdl_string(&kdl).unwrap();
+            assert_eq!(parsed, config, "round trip through:\n{}", kdl);
+        }
     }
     
     #[test]
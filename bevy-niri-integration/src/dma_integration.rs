@@ -1,12 +1,26 @@
-use std::collections::HashMap;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::sync::{Arc, Mutex};
 use bevy::prelude::*;
-use bevy::render::render_resource::{Extent3d, TextureUsages, TextureFormat, TextureDimension};
-use crate::wayland_client::CaptureBuffer;
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_resource::{Extent3d, TextureFormat, TextureDimension};
+use bevy::render::renderer::RenderDevice;
+use bevy::render::texture::{DefaultImageSampler, GpuImage};
+use crate::wayland_client::{
+    CaptureBuffer, DRM_FORMAT_ABGR8888, DRM_FORMAT_ARGB8888, DRM_FORMAT_MOD_LINEAR,
+    DRM_FORMAT_XBGR8888, DRM_FORMAT_XRGB8888,
+};
 use crate::error::ConversionError;
 
+/// Imported DMA-BUF textures waiting to replace the GPU side of their placeholder images.
+///
+/// Shared between the main and the render world, see [`apply_imported_dmabuf_textures`].
+#[derive(Resource, Clone, Default)]
+pub struct ImportedDmabufTextures(Arc<Mutex<Vec<(AssetId<Image>, wgpu::Texture)>>>);
+
 pub struct DmaBufferConverter {
-    device: Option<wgpu::Device>,
+    device: Option<RenderDevice>,
     queue: Option<wgpu::Queue>,
+    imported: ImportedDmabufTextures,
 }
 
 impl DmaBufferConverter {
@@ -14,83 +28,127 @@ impl DmaBufferConverter {
         Self {
             device: None,
             queue: None,
+            imported: ImportedDmabufTextures::default(),
         }
     }
-    
+
     pub fn initialize(&mut self, device: wgpu::Device, queue: wgpu::Queue) {
-        self.device = Some(device);
+        self.device = Some(RenderDevice::from(device));
         self.queue = Some(queue);
     }
-    
+
+    /// Uses Bevy's render device for importing, with textures handed over to the render world
+    /// through `imported`.
+    pub fn initialize_with_render_device(&mut self, device: RenderDevice, imported: ImportedDmabufTextures) {
+        self.device = Some(device);
+        self.imported = imported;
+    }
+
+    pub fn imported_textures(&self) -> ImportedDmabufTextures {
+        self.imported.clone()
+    }
+
+    /// Converts a DMA-BUF into a new Bevy image.
+    ///
+    /// The DMA-BUF is imported on the GPU when possible. Otherwise, it falls back to mapping the
+    /// buffer and converting it on the CPU, which only works for linear buffers.
     pub fn convert_dmabuf_to_bevy_texture(
         &self,
         dmabuf_buffer: &CaptureBuffer,
         images: &mut Assets<Image>,
     ) -> Result<Handle<Image>, ConversionError> {
-        match dmabuf_buffer {
-            CaptureBuffer::Dmabuf { width, height, format, .. } => {
-                let image = Image::new(
-                    Extent3d {
-                        width: *width,
-                        height: *height,
-                        depth_or_array_layers: 1,
-                    },
-                    TextureDimension::D2,
-                    vec![0; (*width * *height * 4) as usize],
-                    TextureFormat::Rgba8UnormSrgb,
-                    bevy::render::render_asset::RenderAssetUsages::RENDER_WORLD,
-                );
-                
-                Ok(images.add(image))
-            }
-            _ => Err(ConversionError::UnsupportedFormat("Expected DMA buffer".to_string())),
+        let (image, texture) = self.dmabuf_to_image(dmabuf_buffer)?;
+        let handle = images.add(image);
+        if let Some(texture) = texture {
+            self.imported.0.lock().unwrap().push((handle.id(), texture));
         }
+        Ok(handle)
     }
-    
+
     pub fn convert_shm_to_bevy_texture(
         &self,
         shm_buffer: &CaptureBuffer,
         images: &mut Assets<Image>,
     ) -> Result<Handle<Image>, ConversionError> {
-        match shm_buffer {
-            CaptureBuffer::Shm { data, width, height, stride, format } => {
-                let mut rgba_data = Vec::with_capacity((*width * *height * 4) as usize);
-                
-                for y in 0..*height {
-                    for x in 0..*width {
-                        let src_offset = (y * stride + x * 4) as usize;
-                        if src_offset + 3 < data.len() {
-                            rgba_data.push(data[src_offset + 2]); // R
-                            rgba_data.push(data[src_offset + 1]); // G
-                            rgba_data.push(data[src_offset + 0]); // B
-                            rgba_data.push(255);                  // A
-                        } else {
-                            rgba_data.extend_from_slice(&[0, 0, 0, 255]);
-                        }
-                    }
-                }
-                
-                let image = Image::new(
-                    Extent3d {
-                        width: *width,
-                        height: *height,
-                        depth_or_array_layers: 1,
-                    },
-                    TextureDimension::D2,
-                    rgba_data,
-                    TextureFormat::Rgba8UnormSrgb,
-                    bevy::render::render_asset::RenderAssetUsages::RENDER_WORLD,
-                );
-                
-                Ok(images.add(image))
+        let image = shm_to_image(shm_buffer)?;
+        Ok(images.add(image))
+    }
+
+    /// Replaces the contents of an existing image with a captured buffer.
+    pub fn update_bevy_texture(
+        &self,
+        buffer: &CaptureBuffer,
+        handle: &Handle<Image>,
+        images: &mut Assets<Image>,
+    ) -> Result<(), ConversionError> {
+        let (mut image, texture) = match buffer {
+            CaptureBuffer::Dmabuf { .. } => self.dmabuf_to_image(buffer)?,
+            CaptureBuffer::Shm { .. } => (shm_to_image(buffer)?, None),
+        };
+
+        if let Some(old) = images.get(handle) {
+            image.texture_descriptor.usage |= old.texture_descriptor.usage;
+        }
+        images.insert(handle, image);
+
+        if let Some(texture) = texture {
+            self.imported.0.lock().unwrap().push((handle.id(), texture));
+        }
+        Ok(())
+    }
+
+    /// Imports a DMA-BUF as a wgpu texture without copying it.
+    ///
+    /// Needs a Vulkan device with the DMA-BUF and DRM format modifier extensions enabled.
+    pub fn import_dmabuf(&self, dmabuf_buffer: &CaptureBuffer) -> Result<wgpu::Texture, ConversionError> {
+        let device = self.device.as_ref()
+            .ok_or_else(|| ConversionError::GpuImport("No GPU device".to_string()))?;
+
+        match dmabuf_buffer {
+            CaptureBuffer::Dmabuf { width, height, format, modifier, fds, offsets, strides, .. } => {
+                let texture_format = wgpu_format(*format)?;
+                vulkan::import(device.wgpu_device(), *width, *height, texture_format, *modifier, fds, offsets, strides)
             }
-            _ => Err(ConversionError::UnsupportedFormat("Expected SHM buffer".to_string())),
+            _ => Err(ConversionError::UnsupportedFormat("Expected DMA buffer".to_string())),
         }
     }
-    
+
     pub fn supports_dmabuf(&self) -> bool {
         self.device.is_some()
     }
+
+    fn dmabuf_to_image(&self, buffer: &CaptureBuffer) -> Result<(Image, Option<wgpu::Texture>), ConversionError> {
+        let CaptureBuffer::Dmabuf { width, height, format, modifier, fds, offsets, strides, .. } = buffer else {
+            return Err(ConversionError::UnsupportedFormat("Expected DMA buffer".to_string()));
+        };
+
+        if self.supports_dmabuf() {
+            match self.import_dmabuf(buffer) {
+                Ok(texture) => {
+                    // The GPU side of the image comes from the imported texture, so the
+                    // placeholder needs no data.
+                    let mut image = Image::new_fill(
+                        Extent3d { width: 1, height: 1, depth_or_array_layers: 1 },
+                        TextureDimension::D2,
+                        &[0; 4],
+                        texture.format(),
+                        bevy::render::render_asset::RenderAssetUsages::RENDER_WORLD,
+                    );
+                    image.texture_descriptor.size = texture.size();
+                    image.data = Vec::new();
+                    return Ok((image, Some(texture)));
+                }
+                Err(e) => {
+                    debug!("GPU DMA-BUF import failed, mapping the buffer instead: {}", e);
+                }
+            }
+        }
+
+        let data = map_dmabuf(*height, *format, *modifier, fds, offsets, strides)?;
+        let stride = strides.first().copied().unwrap_or(*width * 4);
+        let rgba_data = to_rgba(&data, *width, *height, stride, *format);
+        Ok((rgba_image(*width, *height, rgba_data), None))
+    }
 }
 
 impl Default for DmaBufferConverter {
@@ -99,21 +157,382 @@ impl Default for DmaBufferConverter {
     }
 }
 
+/// Swaps the GPU textures of placeholder images for their imported DMA-BUF textures.
+///
+/// Runs in the render world after images are prepared.
+pub fn apply_imported_dmabuf_textures(
+    imported: Res<ImportedDmabufTextures>,
+    mut gpu_images: ResMut<RenderAssets<GpuImage>>,
+    default_sampler: Res<DefaultImageSampler>,
+) {
+    for (id, texture) in imported.0.lock().unwrap().drain(..) {
+        let size = texture.size();
+        let texture_format = texture.format();
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        gpu_images.insert(id, GpuImage {
+            texture: texture.into(),
+            texture_view: texture_view.into(),
+            texture_format,
+            sampler: (**default_sampler).clone(),
+            size: UVec2::new(size.width, size.height),
+            mip_level_count: 1,
+        });
+    }
+}
+
+fn shm_to_image(shm_buffer: &CaptureBuffer) -> Result<Image, ConversionError> {
+    match shm_buffer {
+        CaptureBuffer::Shm { data, width, height, stride, format } => {
+            let rgba_data = to_rgba(data, *width, *height, *stride, *format);
+            Ok(rgba_image(*width, *height, rgba_data))
+        }
+        _ => Err(ConversionError::UnsupportedFormat("Expected SHM buffer".to_string())),
+    }
+}
+
+fn rgba_image(width: u32, height: u32, rgba_data: Vec<u8>) -> Image {
+    Image::new(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        rgba_data,
+        TextureFormat::Rgba8UnormSrgb,
+        bevy::render::render_asset::RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+/// Converts 32-bit pixels into tightly packed RGBA.
+fn to_rgba(data: &[u8], width: u32, height: u32, stride: u32, format: u32) -> Vec<u8> {
+    // The DRM formats are little-endian, so XRGB8888 is B, G, R, X in memory.
+    let bgr = !matches!(format, DRM_FORMAT_XBGR8888 | DRM_FORMAT_ABGR8888);
+
+    let mut rgba_data = Vec::with_capacity((width * height * 4) as usize);
+
+    for y in 0..height {
+        for x in 0..width {
+            let src_offset = (y * stride + x * 4) as usize;
+            if src_offset + 3 < data.len() {
+                let pixel = &data[src_offset..src_offset + 4];
+                if bgr {
+                    rgba_data.extend_from_slice(&[pixel[2], pixel[1], pixel[0], 255]);
+                } else {
+                    rgba_data.extend_from_slice(&[pixel[0], pixel[1], pixel[2], 255]);
+                }
+            } else {
+                rgba_data.extend_from_slice(&[0, 0, 0, 255]);
+            }
+        }
+    }
+
+    rgba_data
+}
+
+fn wgpu_format(format: u32) -> Result<wgpu::TextureFormat, ConversionError> {
+    match format {
+        DRM_FORMAT_XRGB8888 | DRM_FORMAT_ARGB8888 => Ok(wgpu::TextureFormat::Bgra8Unorm),
+        DRM_FORMAT_XBGR8888 | DRM_FORMAT_ABGR8888 => Ok(wgpu::TextureFormat::Rgba8Unorm),
+        _ => Err(ConversionError::UnsupportedFormat(format!("DRM format 0x{:x}", format))),
+    }
+}
+
+const DMA_BUF_IOCTL_SYNC: libc::c_ulong = 0x4008_6200;
+const DMA_BUF_SYNC_READ: u64 = 1 << 0;
+const DMA_BUF_SYNC_START: u64 = 0 << 2;
+const DMA_BUF_SYNC_END: u64 = 1 << 2;
+
+fn dma_buf_sync(fd: &OwnedFd, flags: u64) {
+    // Fails harmlessly on fds that aren't DMA-BUFs, like memfds in tests.
+    unsafe { libc::ioctl(fd.as_raw_fd(), DMA_BUF_IOCTL_SYNC, &flags) };
+}
+
+/// Reads the first plane of a linear DMA-BUF through a CPU mapping.
+///
+/// This is the software fallback for when GPU import isn't available.
+fn map_dmabuf(
+    height: u32,
+    format: u32,
+    modifier: u64,
+    fds: &[OwnedFd],
+    offsets: &[u32],
+    strides: &[u32],
+) -> Result<Vec<u8>, ConversionError> {
+    if modifier != DRM_FORMAT_MOD_LINEAR {
+        return Err(ConversionError::UnsupportedFormat(format!("Cannot map DMA-BUF with modifier 0x{:x}", modifier)));
+    }
+    wgpu_format(format)?;
+
+    let (Some(fd), Some(&offset), Some(&stride)) = (fds.first(), offsets.first(), strides.first()) else {
+        return Err(ConversionError::MemoryMapping("DMA-BUF has no planes".to_string()));
+    };
+
+    let len = offset as usize + stride as usize * height as usize;
+    let ptr = unsafe {
+        libc::mmap(std::ptr::null_mut(), len, libc::PROT_READ, libc::MAP_SHARED, fd.as_raw_fd(), 0)
+    };
+    if ptr == libc::MAP_FAILED {
+        return Err(ConversionError::MemoryMapping(std::io::Error::last_os_error().to_string()));
+    }
+
+    dma_buf_sync(fd, DMA_BUF_SYNC_START | DMA_BUF_SYNC_READ);
+    let mapped = unsafe { std::slice::from_raw_parts(ptr as *const u8, len) };
+    let data = mapped[offset as usize..].to_vec();
+    dma_buf_sync(fd, DMA_BUF_SYNC_END | DMA_BUF_SYNC_READ);
+
+    unsafe { libc::munmap(ptr, len) };
+    Ok(data)
+}
+
+mod vulkan {
+    use std::os::fd::{AsRawFd, IntoRawFd, OwnedFd};
+    use ash::vk;
+    use wgpu::hal::api::Vulkan;
+    use crate::error::ConversionError;
+
+    /// Destroys the imported image and its memory together with the wgpu texture.
+    struct ImportedImage {
+        device: ash::Device,
+        image: vk::Image,
+        memory: vk::DeviceMemory,
+    }
+
+    impl Drop for ImportedImage {
+        fn drop(&mut self) {
+            unsafe {
+                self.device.destroy_image(self.image, None);
+                self.device.free_memory(self.memory, None);
+            }
+        }
+    }
+
+    fn vk_err(what: &str) -> impl Fn(vk::Result) -> ConversionError + '_ {
+        move |e| ConversionError::GpuImport(format!("{}: {}", what, e))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn import(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        modifier: u64,
+        fds: &[OwnedFd],
+        offsets: &[u32],
+        strides: &[u32],
+    ) -> Result<wgpu::Texture, ConversionError> {
+        let fd = fds.first()
+            .ok_or_else(|| ConversionError::GpuImport("DMA-BUF has no planes".to_string()))?;
+        // All planes must come from the same buffer object, which is what GBM gives us.
+        if fds.len() > 1 {
+            return Err(ConversionError::GpuImport("Disjoint DMA-BUFs are not supported".to_string()));
+        }
+
+        let vk_format = match format {
+            wgpu::TextureFormat::Bgra8Unorm => vk::Format::B8G8R8A8_UNORM,
+            wgpu::TextureFormat::Rgba8Unorm => vk::Format::R8G8B8A8_UNORM,
+            _ => return Err(ConversionError::UnsupportedFormat(format!("{:?}", format))),
+        };
+
+        let size = wgpu::Extent3d { width, height, depth_or_array_layers: 1 };
+
+        let hal_texture = unsafe {
+            device.as_hal::<Vulkan, _, _>(|hal_device| {
+                let hal_device = hal_device
+                    .ok_or_else(|| ConversionError::GpuImport("Not a Vulkan device".to_string()))?;
+
+                let extensions = hal_device.enabled_device_extensions();
+                for required in [
+                    vk::KhrExternalMemoryFdFn::name(),
+                    vk::ExtExternalMemoryDmaBufFn::name(),
+                    vk::ExtImageDrmFormatModifierFn::name(),
+                ] {
+                    if !extensions.contains(&required) {
+                        return Err(ConversionError::GpuImport(format!("{:?} is not enabled", required)));
+                    }
+                }
+
+                let raw_device = hal_device.raw_device();
+                let instance = hal_device.shared_instance().raw_instance();
+                let physical_device = hal_device.raw_physical_device();
+
+                let plane_layouts: Vec<_> = offsets.iter().zip(strides)
+                    .map(|(&offset, &stride)| vk::SubresourceLayout {
+                        offset: offset as u64,
+                        size: 0,
+                        row_pitch: stride as u64,
+                        array_pitch: 0,
+                        depth_pitch: 0,
+                    })
+                    .collect();
+
+                let mut modifier_info = vk::ImageDrmFormatModifierExplicitCreateInfoEXT::builder()
+                    .drm_format_modifier(modifier)
+                    .plane_layouts(&plane_layouts);
+                let mut external_info = vk::ExternalMemoryImageCreateInfo::builder()
+                    .handle_types(vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT);
+                let image_info = vk::ImageCreateInfo::builder()
+                    .image_type(vk::ImageType::TYPE_2D)
+                    .format(vk_format)
+                    .extent(vk::Extent3D { width, height, depth: 1 })
+                    .mip_levels(1)
+                    .array_layers(1)
+                    .samples(vk::SampleCountFlags::TYPE_1)
+                    .tiling(vk::ImageTiling::DRM_FORMAT_MODIFIER_EXT)
+                    .usage(vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_SRC)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                    .initial_layout(vk::ImageLayout::UNDEFINED)
+                    .push_next(&mut external_info)
+                    .push_next(&mut modifier_info);
+
+                let image = raw_device.create_image(&image_info, None)
+                    .map_err(vk_err("Failed to create image"))?;
+
+                let memory = match import_memory(instance, raw_device, physical_device, image, fd) {
+                    Ok(memory) => memory,
+                    Err(e) => {
+                        raw_device.destroy_image(image, None);
+                        return Err(e);
+                    }
+                };
+
+                if let Err(e) = raw_device.bind_image_memory(image, memory, 0) {
+                    raw_device.destroy_image(image, None);
+                    raw_device.free_memory(memory, None);
+                    return Err(vk_err("Failed to bind memory")(e));
+                }
+
+                let guard = ImportedImage {
+                    device: raw_device.clone(),
+                    image,
+                    memory,
+                };
+
+                let hal_desc = wgpu::hal::TextureDescriptor {
+                    label: Some("niri dmabuf"),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::hal::TextureUses::RESOURCE | wgpu::hal::TextureUses::COPY_SRC,
+                    memory_flags: wgpu::hal::MemoryFlags::empty(),
+                    view_formats: vec![],
+                };
+
+                Ok(wgpu::hal::vulkan::Device::texture_from_raw(image, &hal_desc, Some(Box::new(guard))))
+            })
+        }
+        .ok_or_else(|| ConversionError::GpuImport("Not a Vulkan device".to_string()))??;
+
+        let texture = unsafe {
+            device.create_texture_from_hal::<Vulkan>(
+                hal_texture,
+                &wgpu::TextureDescriptor {
+                    label: Some("niri dmabuf"),
+                    size,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC,
+                    view_formats: &[],
+                },
+            )
+        };
+
+        Ok(texture)
+    }
+
+    unsafe fn import_memory(
+        instance: &ash::Instance,
+        device: &ash::Device,
+        physical_device: vk::PhysicalDevice,
+        image: vk::Image,
+        fd: &OwnedFd,
+    ) -> Result<vk::DeviceMemory, ConversionError> {
+        let external_memory_fd = ash::extensions::khr::ExternalMemoryFd::new(instance, device);
+
+        let fd_properties = external_memory_fd
+            .get_memory_fd_properties(vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT, fd.as_raw_fd())
+            .map_err(vk_err("Failed to get DMA-BUF properties"))?;
+
+        let requirements = device.get_image_memory_requirements(image);
+        let memory_properties = instance.get_physical_device_memory_properties(physical_device);
+        let type_bits = requirements.memory_type_bits & fd_properties.memory_type_bits;
+        let memory_type_index = (0..memory_properties.memory_type_count)
+            .find(|i| type_bits & (1 << i) != 0)
+            .ok_or_else(|| ConversionError::GpuImport("No suitable memory type".to_string()))?;
+
+        // Vulkan takes ownership of the fd on success.
+        let fd = fd.try_clone()
+            .map_err(|e| ConversionError::GpuImport(e.to_string()))?;
+
+        let mut import_info = vk::ImportMemoryFdInfoKHR::builder()
+            .handle_type(vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT)
+            .fd(fd.as_raw_fd());
+        let mut dedicated_info = vk::MemoryDedicatedAllocateInfo::builder().image(image);
+        let allocate_info = vk::MemoryAllocateInfo::builder()
+            .allocation_size(requirements.size)
+            .memory_type_index(memory_type_index)
+            .push_next(&mut import_info)
+            .push_next(&mut dedicated_info);
+
+        let memory = device.allocate_memory(&allocate_info, None)
+            .map_err(vk_err("Failed to import DMA-BUF memory"))?;
+        let _ = fd.into_raw_fd();
+
+        Ok(memory)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+    use std::fs::File;
+    use std::os::fd::FromRawFd;
+    use std::os::unix::fs::FileExt;
+
+    fn memfd_with(data: &[u8]) -> OwnedFd {
+        let fd = unsafe { libc::memfd_create(c"test-dmabuf".as_ptr(), libc::MFD_CLOEXEC) };
+        assert!(fd >= 0);
+        let file = unsafe { File::from_raw_fd(fd) };
+        file.write_all_at(data, 0).unwrap();
+        OwnedFd::from(file)
+    }
+
+    fn dmabuf(modifier: u64) -> CaptureBuffer {
+        // 2x2 XRGB8888 pixels: red, green, blue, white.
+        let data = [
+            0, 0, 255, 0, 0, 255, 0, 0,
+            255, 0, 0, 0, 255, 255, 255, 0,
+        ];
+
+        CaptureBuffer::Dmabuf {
+            width: 2,
+            height: 2,
+            format: DRM_FORMAT_XRGB8888,
+            modifier,
+            num_planes: 1,
+            fds: vec![memfd_with(&data)],
+            offsets: vec![0],
+            strides: vec![8],
+        }
+    }
+
     #[test]
     fn test_converter_creation() {
         let converter = DmaBufferConverter::new();
         assert!(!converter.supports_dmabuf());
     }
-    
+
     #[test]
     fn test_shm_conversion() {
         let converter = DmaBufferConverter::new();
         let mut images = Assets::<Image>::default();
-        
+
         let shm_buffer = CaptureBuffer::Shm {
             data: vec![255, 0, 0, 255; 1920 * 1080],
             stride: 1920 * 4,
@@ -121,8 +540,50 @@ mod tests {
             height: 1080,
             format: 0x34325258,
         };
-        
+
         let result = converter.convert_shm_to_bevy_texture(&shm_buffer, &mut images);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_dmabuf_software_fallback() {
+        let converter = DmaBufferConverter::new();
+        let mut images = Assets::<Image>::default();
+
+        let handle = converter.convert_dmabuf_to_bevy_texture(&dmabuf(DRM_FORMAT_MOD_LINEAR), &mut images).unwrap();
+        let image = images.get(&handle).unwrap();
+        assert_eq!(image.data, vec![
+            255, 0, 0, 255, 0, 255, 0, 255,
+            0, 0, 255, 255, 255, 255, 255, 255,
+        ]);
+        assert!(converter.imported_textures().0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_dmabuf_import_needs_device() {
+        let converter = DmaBufferConverter::new();
+        let result = converter.import_dmabuf(&dmabuf(DRM_FORMAT_MOD_LINEAR));
+        assert!(matches!(result, Err(ConversionError::GpuImport(_))));
+    }
+
+    #[test]
+    fn test_tiled_dmabuf_cannot_be_mapped() {
+        let converter = DmaBufferConverter::new();
+        let mut images = Assets::<Image>::default();
+
+        let result = converter.convert_dmabuf_to_bevy_texture(&dmabuf(0x0100_0000_0000_0001), &mut images);
+        assert!(matches!(result, Err(ConversionError::UnsupportedFormat(_))));
+    }
+
+    #[test]
+    fn test_update_existing_texture() {
+        let converter = DmaBufferConverter::new();
+        let mut images = Assets::<Image>::default();
+        let handle = images.add(Image::default());
+
+        converter.update_bevy_texture(&dmabuf(DRM_FORMAT_MOD_LINEAR), &handle, &mut images).unwrap();
+        let image = images.get(&handle).unwrap();
+        assert_eq!(image.texture_descriptor.size.width, 2);
+        assert_eq!(image.data.len(), 2 * 2 * 4);
+    }
 }
@@ -8,6 +8,9 @@ pub enum CaptureError {
     #[error("Output not found: {0}")]
     OutputNotFound(String),
     
+    #[error("Window not found: {0}")]
+    WindowNotFound(String),
+    
     #[error("Buffer allocation failed: {0}")]
     BufferAllocation(String),
     
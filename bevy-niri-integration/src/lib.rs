@@ -7,8 +7,9 @@ pub mod config;
 pub mod error;
 pub mod utils;
 
-pub use plugin::{NiriCapturePlugin, NiriCaptureState, NiriScreenDisplay};
-pub use wayland_client::{NiriScreencopyClient, OutputInfo, CaptureBuffer};
+pub use adaptive::{AdaptiveCaptureSystem, CaptureMethod};
+pub use plugin::{NiriCapturePlugin, NiriCaptureState, NiriScreenDisplay, NiriWindowDisplay};
+pub use wayland_client::{NiriScreencopyClient, OutputInfo, ToplevelInfo, CaptureBuffer};
 pub use config::BevyCaptureConfig;
 pub use error::{CaptureError, ConversionError};
 pub use utils::*;
@@ -1,7 +1,14 @@
 use std::collections::HashMap;
+use std::time::Instant;
 use bevy::prelude::*;
+use bevy::render::render_asset::prepare_assets;
 use bevy::render::render_resource::{Extent3d, TextureUsages};
-use crate::wayland_client::NiriScreencopyClient;
+use bevy::render::renderer::RenderDevice;
+use bevy::render::texture::GpuImage;
+use bevy::render::{Render, RenderApp, RenderSet};
+use crate::adaptive::{AdaptiveCaptureSystem, CaptureMethod};
+use crate::dma_integration::{apply_imported_dmabuf_textures, DmaBufferConverter};
+use crate::wayland_client::{NiriScreencopyClient, ToplevelInfo};
 
 #[derive(Debug, Clone)]
 pub struct NiriCapturePlugin {
@@ -23,6 +30,7 @@ impl Default for NiriCapturePlugin {
 #[derive(Resource)]
 pub struct NiriCaptureState {
     pub client: Option<NiriScreencopyClient>,
+    pub converter: DmaBufferConverter,
     pub output_textures: HashMap<String, Handle<Image>>,
     pub performance_stats: CaptureStats,
     pub last_capture_time: f64,
@@ -36,6 +44,23 @@ pub struct NiriScreenDisplay {
     pub damage_tracking: bool,
 }
 
+/// Captures a single window into `texture`, for example to put one app on a mesh.
+///
+/// The first open window matching all of the set properties is captured.
+#[derive(Component)]
+pub struct NiriWindowDisplay {
+    pub app_id: Option<String>,
+    pub title: Option<String>,
+    pub texture: Handle<Image>,
+}
+
+impl NiriWindowDisplay {
+    pub fn matches(&self, toplevel: &ToplevelInfo) -> bool {
+        self.app_id.as_ref().map_or(true, |app_id| *app_id == toplevel.app_id)
+            && self.title.as_ref().map_or(true, |title| *title == toplevel.title)
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct CaptureStats {
     pub frames_captured: u64,
@@ -47,10 +72,16 @@ pub struct CaptureStats {
 
 impl NiriCaptureState {
     pub fn new(plugin_config: &NiriCapturePlugin) -> Self {
-        let client = NiriScreencopyClient::new().ok();
+        let mut client = NiriScreencopyClient::new().ok();
+        if let Some(client) = &mut client {
+            client.set_prefer_dmabuf(plugin_config.prefer_dmabuf);
+            // Without a GPU to import into, DMA-BUFs must be mappable by the CPU.
+            client.set_linear_dmabuf(true);
+        }
         
         Self {
             client,
+            converter: DmaBufferConverter::new(),
             output_textures: HashMap::new(),
             performance_stats: CaptureStats::default(),
             last_capture_time: 0.0,
@@ -61,14 +92,45 @@ impl NiriCaptureState {
 
 impl Plugin for NiriCapturePlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(NiriCaptureState::new(self))
+        let capture_state = NiriCaptureState::new(self);
+
+        let mut adaptive = AdaptiveCaptureSystem::new();
+        adaptive.set_preferred_method(if self.prefer_dmabuf { CaptureMethod::Dmabuf } else { CaptureMethod::Shm });
+        adaptive.set_dmabuf_available(capture_state.client.as_ref().map_or(false, |c| c.supports_dmabuf()));
+
+        app.insert_resource(capture_state)
+            .insert_resource(adaptive)
             .add_systems(Startup, setup_niri_capture_system)
             .add_systems(Update, (
                 capture_screens_system,
-                update_textures_system,
                 performance_monitoring_system,
             ));
     }
+
+    fn finish(&self, app: &mut App) {
+        // The render device only exists once the renderer is initialized, and not at all in
+        // headless apps, where captures go through the software path.
+        let Some(render_device) = app.world().get_resource::<RenderDevice>().cloned() else {
+            return;
+        };
+        if app.get_sub_app(RenderApp).is_none() {
+            return;
+        }
+
+        let mut capture_state = app.world_mut().resource_mut::<NiriCaptureState>();
+        let imported = capture_state.converter.imported_textures();
+        capture_state.converter.initialize_with_render_device(render_device, imported.clone());
+        if let Some(client) = &mut capture_state.client {
+            client.set_linear_dmabuf(false);
+        }
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app
+            .insert_resource(imported)
+            .add_systems(Render, apply_imported_dmabuf_textures
+                .in_set(RenderSet::PrepareAssets)
+                .after(prepare_assets::<GpuImage>));
+    }
 }
 
 fn setup_niri_capture_system(
@@ -106,7 +168,10 @@ fn setup_niri_capture_system(
 
 fn capture_screens_system(
     time: Res<Time>,
+    mut images: ResMut<Assets<Image>>,
     mut capture_state: ResMut<NiriCaptureState>,
+    mut adaptive: ResMut<AdaptiveCaptureSystem>,
+    windows: Query<&NiriWindowDisplay>,
 ) {
     let current_time = time.elapsed_seconds_f64();
     
@@ -114,20 +179,65 @@ fn capture_screens_system(
         return;
     }
     
-    let outputs: Vec<String> = capture_state.output_textures.keys().cloned().collect();
-    let mut frames_captured = 0;
-    let mut frames_dropped = 0;
+    let capture_state = &mut *capture_state;
+    let prefer_dmabuf = capture_state.client.as_ref().map_or(false, |c| c.prefers_dmabuf() && c.supports_dmabuf());
+    let stats = &mut capture_state.performance_stats;
     
     if let Some(ref mut client) = capture_state.client {
-        for output_name in outputs {
-            match client.capture_output(&output_name) {
-                Ok(_buffer) => {
-                    frames_captured += 1;
-                    trace!("Captured frame for output: {}", output_name);
-                }
-                Err(e) => {
-                    frames_dropped += 1;
-                    warn!("Failed to capture output {}: {:?}", output_name, e);
+        for (output_name, texture_handle) in &capture_state.output_textures {
+            let method = adaptive.select_optimal_method(output_name);
+            
+            // The conversion is part of the cost of a method, so it's measured too.
+            let start = Instant::now();
+            let result = client.capture_output_using(output_name, method)
+                .map_err(|e| e.to_string())
+                .and_then(|buffer| {
+                    capture_state.converter.update_bevy_texture(&buffer, texture_handle, &mut images)
+                        .map_err(|e| e.to_string())
+                });
+            let latency = start.elapsed().as_secs_f32() * 1000.0;
+            adaptive.record_capture_result(output_name, method, result.is_ok(), latency);
+            
+            if let Err(e) = result {
+                stats.frames_dropped += 1;
+                warn!("Failed to capture output {} with {:?}: {}", output_name, method, e);
+                continue;
+            }
+            
+            let is_dmabuf = method == CaptureMethod::Dmabuf;
+            stats.frames_captured += 1;
+            if prefer_dmabuf && !is_dmabuf {
+                stats.shm_fallback_count += 1;
+            }
+            let hit = if is_dmabuf { 1.0 } else { 0.0 };
+            stats.dmabuf_success_rate += (hit - stats.dmabuf_success_rate) / stats.frames_captured as f32;
+            
+            trace!("Captured frame for output: {}", output_name);
+        }
+        
+        if client.supports_window_capture() && !windows.is_empty() {
+            let toplevels = client.get_toplevels();
+            for window in &windows {
+                let Some(toplevel) = toplevels.iter().find(|toplevel| window.matches(toplevel)) else {
+                    continue;
+                };
+                
+                let result = client.capture_toplevel(&toplevel.identifier)
+                    .map_err(|e| e.to_string())
+                    .and_then(|buffer| {
+                        capture_state.converter.update_bevy_texture(&buffer, &window.texture, &mut images)
+                            .map_err(|e| e.to_string())
+                    });
+                
+                match result {
+                    Ok(()) => {
+                        stats.frames_captured += 1;
+                        trace!("Captured frame for window: {}", toplevel.identifier);
+                    }
+                    Err(e) => {
+                        stats.frames_dropped += 1;
+                        warn!("Failed to capture window {} ({}): {}", toplevel.identifier, toplevel.app_id, e);
+                    }
                 }
             }
         }
@@ -137,34 +247,9 @@ fn capture_screens_system(
         }
     }
     
-    capture_state.performance_stats.frames_captured += frames_captured;
-    capture_state.performance_stats.frames_dropped += frames_dropped;
     capture_state.last_capture_time = current_time;
 }
 
-fn update_textures_system(
-    mut images: ResMut<Assets<Image>>,
-    capture_state: Res<NiriCaptureState>,
-) {
-    for (_output_name, texture_handle) in &capture_state.output_textures {
-        if let Some(image) = images.get_mut(texture_handle) {
-            let data = &mut image.data;
-                let pattern = ((capture_state.performance_stats.frames_captured % 255) as u8, 
-                              ((capture_state.performance_stats.frames_captured / 2) % 255) as u8,
-                              ((capture_state.performance_stats.frames_captured / 4) % 255) as u8);
-                
-            for chunk in data.chunks_mut(4) {
-                if chunk.len() >= 4 {
-                    chunk[0] = pattern.0;
-                    chunk[1] = pattern.1;
-                    chunk[2] = pattern.2;
-                    chunk[3] = 255;
-                }
-            }
-        }
-    }
-}
-
 fn performance_monitoring_system(
     time: Res<Time>,
     mut capture_state: ResMut<NiriCaptureState>,
@@ -192,6 +277,28 @@ mod tests {
         assert!(plugin.outputs.is_empty());
     }
     
+    #[test]
+    fn test_window_display_matching() {
+        let toplevel = ToplevelInfo {
+            identifier: "1".to_string(),
+            title: "Terminal".to_string(),
+            app_id: "Alacritty".to_string(),
+        };
+        
+        let mut display = NiriWindowDisplay {
+            app_id: None,
+            title: None,
+            texture: Handle::default(),
+        };
+        assert!(display.matches(&toplevel));
+        
+        display.app_id = Some("Alacritty".to_string());
+        assert!(display.matches(&toplevel));
+        
+        display.title = Some("Editor".to_string());
+        assert!(!display.matches(&toplevel));
+    }
+    
     #[test]
     fn test_capture_state_creation() {
         let plugin = NiriCapturePlugin::default();
@@ -1,11 +1,36 @@
 use std::collections::HashMap;
-use wayland_client::{Connection, Dispatch, QueueHandle, EventQueue, globals::{GlobalList, GlobalListContents}};
+use std::fs::File;
+use std::os::fd::{AsFd, AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::fs::FileExt;
+use std::path::PathBuf;
+use wayland_client::{Connection, Dispatch, Proxy, QueueHandle, EventQueue, WEnum, globals::GlobalListContents};
+use wayland_protocols::ext::foreign_toplevel_list::v1::client::{
+    ext_foreign_toplevel_handle_v1, ext_foreign_toplevel_list_v1,
+};
+use wayland_protocols::ext::image_capture_source::v1::client::{
+    ext_foreign_toplevel_image_capture_source_manager_v1, ext_image_capture_source_v1,
+};
+use wayland_protocols::ext::image_copy_capture::v1::client::{
+    ext_image_copy_capture_frame_v1, ext_image_copy_capture_manager_v1,
+    ext_image_copy_capture_session_v1,
+};
+use wayland_protocols::wp::linux_dmabuf::zv1::client::{
+    zwp_linux_buffer_params_v1, zwp_linux_dmabuf_feedback_v1, zwp_linux_dmabuf_v1,
+};
 use wayland_protocols_wlr::screencopy::v1::client::{
     zwlr_screencopy_frame_v1, zwlr_screencopy_manager_v1,
 };
-use wayland_client::protocol::{wl_output, wl_registry, wl_shm, wl_buffer};
+use wayland_client::protocol::{wl_output, wl_registry, wl_shm, wl_shm_pool, wl_buffer};
+use crate::adaptive::CaptureMethod;
 use crate::error::CaptureError;
 
+pub const DRM_FORMAT_XRGB8888: u32 = 0x34325258;
+pub const DRM_FORMAT_ARGB8888: u32 = 0x34325241;
+pub const DRM_FORMAT_XBGR8888: u32 = 0x34324258;
+pub const DRM_FORMAT_ABGR8888: u32 = 0x34324241;
+pub const DRM_FORMAT_MOD_LINEAR: u64 = 0;
+pub const DRM_FORMAT_MOD_INVALID: u64 = 0x00ff_ffff_ffff_ffff;
+
 #[derive(Debug, Clone)]
 pub struct OutputInfo {
     pub name: String,
@@ -15,214 +40,919 @@ pub struct OutputInfo {
     pub scale: f64,
 }
 
+/// A toplevel window announced through ext-foreign-toplevel-list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToplevelInfo {
+    /// Identifier of the window, unique and stable for its whole lifetime.
+    pub identifier: String,
+    pub title: String,
+    pub app_id: String,
+}
+
 #[derive(Debug)]
 pub enum CaptureBuffer {
-    Shm { 
-        data: Vec<u8>, 
+    Shm {
+        data: Vec<u8>,
         stride: u32,
         width: u32,
         height: u32,
         format: u32,
     },
-    Dmabuf { 
+    Dmabuf {
         width: u32,
         height: u32,
         format: u32,
         modifier: u64,
         num_planes: u32,
-        fds: Vec<i32>,
+        fds: Vec<OwnedFd>,
         offsets: Vec<u32>,
         strides: Vec<u32>,
     },
 }
 
-#[derive(Debug)]
+/// What a capture is of, used to key buffers and frame state.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum CaptureTarget {
+    Output(String),
+    Toplevel(String),
+}
+
+/// Buffer parameters advertised by the compositor for a screencopy frame.
+#[derive(Debug, Clone, Default)]
 struct CaptureState {
-    frame: Option<zwlr_screencopy_frame_v1::ZwlrScreencopyFrameV1>,
-    buffer: Option<CaptureBuffer>,
+    shm: Option<ShmParams>,
+    dmabuf: Option<(u32, u32, u32)>,
+    buffer_done: bool,
     ready: bool,
     failed: bool,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ShmParams {
+    format: wl_shm::Format,
+    width: u32,
+    height: u32,
+    stride: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum BufferKind {
+    Shm,
+    Dmabuf,
+}
+
+/// A buffer attached to capture frames, kept around between captures of the same target.
+enum AllocatedBuffer {
+    Shm {
+        wl_buffer: wl_buffer::WlBuffer,
+        file: File,
+        params: ShmParams,
+    },
+    Dmabuf {
+        wl_buffer: wl_buffer::WlBuffer,
+        params: (u32, u32, u32),
+        modifier: u64,
+        fds: Vec<OwnedFd>,
+        offsets: Vec<u32>,
+        strides: Vec<u32>,
+    },
+}
+
 pub struct NiriScreencopyClient {
     connection: Connection,
     event_queue: EventQueue<AppData>,
-    screencopy_manager: Option<zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1>,
-    shm: Option<wl_shm::WlShm>,
-    outputs: HashMap<String, (wl_output::WlOutput, OutputInfo)>,
-    active_captures: HashMap<String, CaptureState>,
+    data: AppData,
+    gbm: Option<gbm::Device<File>>,
+    prefer_dmabuf: bool,
+    linear_dmabuf: bool,
+    buffers: HashMap<(CaptureTarget, BufferKind), AllocatedBuffer>,
+    /// ext-image-copy-capture sessions of toplevels, by toplevel identifier.
+    window_sessions: HashMap<String, WindowSession>,
+}
+
+struct WindowSession {
+    source: ext_image_capture_source_v1::ExtImageCaptureSourceV1,
+    session: ext_image_copy_capture_session_v1::ExtImageCopyCaptureSessionV1,
+}
+
+struct OutputState {
+    output: wl_output::WlOutput,
+    info: OutputInfo,
+    /// Properties received since the last done event.
+    pending: OutputInfo,
+    /// Whether the output has sent its properties at least once.
+    ready: bool,
+}
+
+struct ToplevelState {
+    handle: ext_foreign_toplevel_handle_v1::ExtForeignToplevelHandleV1,
+    info: ToplevelInfo,
+    pending: ToplevelInfo,
+    ready: bool,
+}
+
+/// Buffer constraints of an ext-image-copy-capture session.
+#[derive(Debug, Clone, Default)]
+struct SessionConstraints {
+    buffer_size: Option<(u32, u32)>,
+    shm_formats: Vec<wl_shm::Format>,
+    dmabuf_formats: HashMap<u32, Vec<u64>>,
+}
+
+#[derive(Debug, Default)]
+struct SessionState {
+    pending: SessionConstraints,
+    /// Constraints as of the last done event.
+    constraints: Option<SessionConstraints>,
+    stopped: bool,
 }
 
 struct AppData {
-    outputs: HashMap<String, (wl_output::WlOutput, OutputInfo)>,
+    /// Outputs by their registry global name.
+    outputs: HashMap<u32, OutputState>,
     screencopy_manager: Option<zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1>,
     shm: Option<wl_shm::WlShm>,
+    dmabuf: Option<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1>,
+    /// Modifiers supported by the compositor for each DRM format.
+    dmabuf_formats: HashMap<u32, Vec<u64>>,
+    /// Format table from the default dmabuf feedback.
+    format_table: Vec<(u32, u64)>,
+    main_device: Option<u64>,
+    toplevel_list: Option<ext_foreign_toplevel_list_v1::ExtForeignToplevelListV1>,
+    toplevels: Vec<ToplevelState>,
+    image_copy_capture_manager: Option<ext_image_copy_capture_manager_v1::ExtImageCopyCaptureManagerV1>,
+    toplevel_source_manager: Option<ext_foreign_toplevel_image_capture_source_manager_v1::ExtForeignToplevelImageCaptureSourceManagerV1>,
+    sessions: HashMap<String, SessionState>,
+    active_captures: HashMap<CaptureTarget, CaptureState>,
 }
 
 impl NiriScreencopyClient {
     pub fn new() -> Result<Self, CaptureError> {
         let connection = Connection::connect_to_env()
             .map_err(|e| CaptureError::WaylandConnection(e.to_string()))?;
-        
-        let (globals, event_queue) = wayland_client::globals::registry_queue_init::<AppData>(&connection)
+
+        let (globals, mut event_queue) = wayland_client::globals::registry_queue_init::<AppData>(&connection)
             .map_err(|e| CaptureError::WaylandConnection(e.to_string()))?;
-        
+
         let mut app_data = AppData {
             outputs: HashMap::new(),
             screencopy_manager: None,
             shm: None,
+            dmabuf: None,
+            dmabuf_formats: HashMap::new(),
+            format_table: Vec::new(),
+            main_device: None,
+            toplevel_list: None,
+            toplevels: Vec::new(),
+            image_copy_capture_manager: None,
+            toplevel_source_manager: None,
+            sessions: HashMap::new(),
+            active_captures: HashMap::new(),
         };
-        
+
         app_data.screencopy_manager = Some(globals.bind(&event_queue.handle(), 1..=3, ())
             .map_err(|e| CaptureError::Protocol(e.to_string()))?);
-        
+
         app_data.shm = Some(globals.bind(&event_queue.handle(), 1..=1, ())
             .map_err(|e| CaptureError::Protocol(e.to_string()))?);
-        
+
+        // linux-dmabuf is optional, without it we only capture into SHM buffers.
+        let dmabuf: Option<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1> =
+            globals.bind(&event_queue.handle(), 3..=4, ()).ok();
+        if let Some(dmabuf) = &dmabuf {
+            if dmabuf.version() >= 4 {
+                dmabuf.get_default_feedback(&event_queue.handle(), ());
+            }
+        }
+        app_data.dmabuf = dmabuf;
+
+        // Window capture needs the toplevel list and ext-image-copy-capture with toplevel sources.
+        app_data.toplevel_list = globals.bind(&event_queue.handle(), 1..=1, ()).ok();
+        app_data.image_copy_capture_manager = globals.bind(&event_queue.handle(), 1..=1, ()).ok();
+        app_data.toplevel_source_manager = globals.bind(&event_queue.handle(), 1..=1, ()).ok();
+
         let output_globals = globals.contents().with_list(|list| {
             list.iter()
                 .filter(|global| global.interface == "wl_output")
                 .map(|global| (global.name, global.version))
                 .collect::<Vec<_>>()
         });
-        
+
         for (name, version) in output_globals {
-            let output: wl_output::WlOutput = globals.registry().bind::<wl_output::WlOutput, (), AppData>(name, version.min(4), &event_queue.handle(), ());
-            let output_info = OutputInfo {
-                name: format!("wl_output-{}", name),
-                width: 1920,  // Will be updated by geometry/mode events
-                height: 1080,
-                refresh_rate: 60,
-                scale: 1.0,
-            };
-            app_data.outputs.insert(output_info.name.clone(), (output, output_info));
+            app_data.add_output(globals.registry(), name, version, &event_queue.handle());
         }
-        
+
+        // Receive the output properties, the toplevels, and the dmabuf formats and feedback.
+        event_queue.roundtrip(&mut app_data)
+            .map_err(|e| CaptureError::Protocol(e.to_string()))?;
+
+        let gbm = if app_data.dmabuf.is_some() {
+            open_gbm_device(app_data.main_device)
+        } else {
+            None
+        };
+
         Ok(Self {
             connection,
             event_queue,
-            screencopy_manager: app_data.screencopy_manager.clone(),
-            shm: app_data.shm.clone(),
-            outputs: app_data.outputs.clone(),
-            active_captures: HashMap::new(),
+            data: app_data,
+            gbm,
+            prefer_dmabuf: true,
+            linear_dmabuf: false,
+            buffers: HashMap::new(),
+            window_sessions: HashMap::new(),
         })
     }
-    
+
+    /// Sets whether to capture into DMA-BUFs when the compositor and the GPU allow it.
+    pub fn set_prefer_dmabuf(&mut self, prefer_dmabuf: bool) {
+        self.prefer_dmabuf = prefer_dmabuf;
+    }
+
+    pub fn prefers_dmabuf(&self) -> bool {
+        self.prefer_dmabuf
+    }
+
+    /// Restricts DMA-BUFs to the linear layout, so that they can be mapped by the CPU.
+    ///
+    /// Needed when the DMA-BUFs can't be imported on the GPU.
+    pub fn set_linear_dmabuf(&mut self, linear_dmabuf: bool) {
+        if self.linear_dmabuf != linear_dmabuf {
+            self.linear_dmabuf = linear_dmabuf;
+
+            let keys: Vec<_> = self.buffers.keys()
+                .filter(|(_, kind)| *kind == BufferKind::Dmabuf)
+                .cloned()
+                .collect();
+            for key in keys {
+                if let Some(buffer) = self.buffers.remove(&key) {
+                    buffer.destroy();
+                }
+            }
+        }
+    }
+
+    pub fn supports_dmabuf(&self) -> bool {
+        self.data.dmabuf.is_some() && self.gbm.is_some()
+    }
+
+    /// Whether the compositor supports capturing individual windows.
+    pub fn supports_window_capture(&self) -> bool {
+        self.data.toplevel_list.is_some()
+            && self.data.image_copy_capture_manager.is_some()
+            && self.data.toplevel_source_manager.is_some()
+    }
+
+    /// Returns the outputs, named after their connectors, like `DP-1`.
     pub fn get_outputs(&self) -> Vec<OutputInfo> {
-        self.outputs.values().map(|(_, info)| info.clone()).collect()
+        self.data.outputs.values()
+            .filter(|output| output.ready)
+            .map(|output| output.info.clone())
+            .collect()
+    }
+
+    /// Returns the currently open toplevel windows.
+    pub fn get_toplevels(&self) -> Vec<ToplevelInfo> {
+        self.data.toplevels.iter()
+            .filter(|toplevel| toplevel.ready)
+            .map(|toplevel| toplevel.info.clone())
+            .collect()
     }
-    
+
+    /// Captures a frame of the output.
+    ///
+    /// Prefers a DMA-BUF when possible, and falls back to SHM if the DMA-BUF capture fails.
     pub fn capture_output(&mut self, output_name: &str) -> Result<CaptureBuffer, CaptureError> {
-        let (output, output_info) = self.outputs.get(output_name)
+        self.capture(&CaptureTarget::Output(output_name.to_string()))
+    }
+
+    /// Captures a frame of the output with the given method, without falling back.
+    pub fn capture_output_using(&mut self, output_name: &str, method: CaptureMethod) -> Result<CaptureBuffer, CaptureError> {
+        self.capture_using(&CaptureTarget::Output(output_name.to_string()), method)
+    }
+
+    /// Captures a frame of the toplevel window with the given identifier.
+    ///
+    /// Prefers a DMA-BUF when possible, and falls back to SHM if the DMA-BUF capture fails.
+    pub fn capture_toplevel(&mut self, identifier: &str) -> Result<CaptureBuffer, CaptureError> {
+        self.capture(&CaptureTarget::Toplevel(identifier.to_string()))
+    }
+
+    /// Captures a frame of the toplevel window with the given method, without falling back.
+    pub fn capture_toplevel_using(&mut self, identifier: &str, method: CaptureMethod) -> Result<CaptureBuffer, CaptureError> {
+        self.capture_using(&CaptureTarget::Toplevel(identifier.to_string()), method)
+    }
+
+    fn capture(&mut self, target: &CaptureTarget) -> Result<CaptureBuffer, CaptureError> {
+        if self.prefer_dmabuf && self.supports_dmabuf() {
+            match self.capture_with(target, BufferKind::Dmabuf) {
+                Ok(buffer) => return Ok(buffer),
+                Err(e @ (CaptureError::OutputNotFound(_) | CaptureError::WindowNotFound(_))) => {
+                    return Err(e);
+                }
+                Err(e) => {
+                    tracing::debug!("DMA-BUF capture of {:?} failed, falling back to SHM: {}", target, e);
+                    self.forget_buffer(target, BufferKind::Dmabuf);
+                }
+            }
+        }
+
+        self.capture_with(target, BufferKind::Shm)
+    }
+
+    fn capture_using(&mut self, target: &CaptureTarget, method: CaptureMethod) -> Result<CaptureBuffer, CaptureError> {
+        let kind = match method {
+            CaptureMethod::Dmabuf if !self.supports_dmabuf() => {
+                return Err(CaptureError::BufferAllocation("DMA-BUF capture is not supported".to_string()));
+            }
+            CaptureMethod::Dmabuf => BufferKind::Dmabuf,
+            CaptureMethod::Shm => BufferKind::Shm,
+        };
+
+        let result = self.capture_with(target, kind);
+        if result.is_err() {
+            self.forget_buffer(target, kind);
+        }
+        result
+    }
+
+    fn capture_with(&mut self, target: &CaptureTarget, kind: BufferKind) -> Result<CaptureBuffer, CaptureError> {
+        match target {
+            CaptureTarget::Output(output_name) => self.capture_output_with(output_name, kind),
+            CaptureTarget::Toplevel(identifier) => self.capture_toplevel_with(identifier, kind),
+        }
+    }
+
+    fn forget_buffer(&mut self, target: &CaptureTarget, kind: BufferKind) {
+        if let Some(buffer) = self.buffers.remove(&(target.clone(), kind)) {
+            buffer.destroy();
+        }
+    }
+
+    fn capture_output_with(&mut self, output_name: &str, kind: BufferKind) -> Result<CaptureBuffer, CaptureError> {
+        let output = self.data.output_by_name(output_name)
             .ok_or_else(|| CaptureError::OutputNotFound(output_name.to_string()))?;
-        
-        let screencopy_manager = self.screencopy_manager.as_ref()
+
+        let screencopy_manager = self.data.screencopy_manager.as_ref()
             .ok_or_else(|| CaptureError::Protocol("No screencopy manager".to_string()))?;
-        
-        let frame = screencopy_manager.capture_output(0, output, &self.event_queue.handle(), ());
-        
-        let capture_state = CaptureState {
-            frame: Some(frame),
-            buffer: None,
-            ready: false,
-            failed: false,
+
+        let target = CaptureTarget::Output(output_name.to_string());
+        let qh = self.event_queue.handle();
+        let frame = screencopy_manager.capture_output(0, output, &qh, target.clone());
+        self.data.active_captures.insert(target.clone(), CaptureState::default());
+
+        let result = self.copy_frame(&frame, &target, kind);
+        frame.destroy();
+        self.data.active_captures.remove(&target);
+        result
+    }
+
+    fn copy_frame(
+        &mut self,
+        frame: &zwlr_screencopy_frame_v1::ZwlrScreencopyFrameV1,
+        target: &CaptureTarget,
+        kind: BufferKind,
+    ) -> Result<CaptureBuffer, CaptureError> {
+        // Wait for the buffer parameters. Version 1 and 2 frames don't send buffer_done.
+        let state = loop {
+            self.dispatch()?;
+            let state = &self.data.active_captures[target];
+            if state.failed {
+                return Err(CaptureError::Protocol("Frame failed".to_string()));
+            }
+            if state.buffer_done || (frame.version() < 3 && state.shm.is_some()) {
+                break state.clone();
+            }
+        };
+
+        let modifiers = state.dmabuf
+            .and_then(|(format, _, _)| self.data.dmabuf_formats.get(&format).cloned())
+            .unwrap_or_default();
+        let key = (target.clone(), kind);
+        self.ensure_buffer(&key, state.shm, state.dmabuf, modifiers)?;
+
+        frame.copy(self.buffers[&key].wl_buffer());
+        self.wait_for_frame(target)?;
+
+        self.buffers[&key].to_capture_buffer()
+    }
+
+    fn capture_toplevel_with(&mut self, identifier: &str, kind: BufferKind) -> Result<CaptureBuffer, CaptureError> {
+        let target = CaptureTarget::Toplevel(identifier.to_string());
+        let constraints = self.session_constraints(identifier)?;
+
+        let (width, height) = constraints.buffer_size
+            .ok_or_else(|| CaptureError::Protocol("No buffer size for window capture".to_string()))?;
+
+        let (shm, dmabuf, modifiers) = match kind {
+            BufferKind::Shm => {
+                let format = [wl_shm::Format::Xrgb8888, wl_shm::Format::Argb8888]
+                    .into_iter()
+                    .find(|format| constraints.shm_formats.contains(format))
+                    .ok_or_else(|| CaptureError::BufferAllocation("No supported SHM format offered".to_string()))?;
+                let params = ShmParams { format, width, height, stride: width * 4 };
+                (Some(params), None, Vec::new())
+            }
+            BufferKind::Dmabuf => {
+                let (format, modifiers) = [DRM_FORMAT_XRGB8888, DRM_FORMAT_ARGB8888, DRM_FORMAT_XBGR8888, DRM_FORMAT_ABGR8888]
+                    .into_iter()
+                    .find_map(|format| Some((format, constraints.dmabuf_formats.get(&format)?.clone())))
+                    .ok_or_else(|| CaptureError::BufferAllocation("No supported DMA-BUF format offered".to_string()))?;
+                (None, Some((format, width, height)), modifiers)
+            }
+        };
+
+        let key = (target.clone(), kind);
+        self.ensure_buffer(&key, shm, dmabuf, modifiers)?;
+
+        let qh = self.event_queue.handle();
+        let session = &self.window_sessions[identifier].session;
+        let frame = session.create_frame(&qh, target.clone());
+        frame.attach_buffer(self.buffers[&key].wl_buffer());
+        frame.damage_buffer(0, 0, width as i32, height as i32);
+        frame.capture();
+        self.data.active_captures.insert(target.clone(), CaptureState::default());
+
+        let result = self.wait_for_frame(&target);
+        frame.destroy();
+        self.data.active_captures.remove(&target);
+
+        if self.data.sessions.get(identifier).map_or(true, |state| state.stopped) {
+            self.forget_window_session(identifier);
+        }
+
+        result?;
+        self.buffers[&key].to_capture_buffer()
+    }
+
+    /// Returns the buffer constraints of the toplevel's capture session, creating it if needed.
+    fn session_constraints(&mut self, identifier: &str) -> Result<SessionConstraints, CaptureError> {
+        if !self.window_sessions.contains_key(identifier) {
+            let handle = self.data.toplevels.iter()
+                .find(|toplevel| toplevel.ready && toplevel.info.identifier == identifier)
+                .map(|toplevel| toplevel.handle.clone())
+                .ok_or_else(|| CaptureError::WindowNotFound(identifier.to_string()))?;
+
+            let (Some(manager), Some(source_manager)) =
+                (&self.data.image_copy_capture_manager, &self.data.toplevel_source_manager)
+            else {
+                return Err(CaptureError::Protocol("Window capture is not supported".to_string()));
+            };
+
+            let qh = self.event_queue.handle();
+            let source = source_manager.create_source(&handle, &qh, ());
+            let session = manager.create_session(
+                &source,
+                ext_image_copy_capture_manager_v1::Options::empty(),
+                &qh,
+                identifier.to_string(),
+            );
+            self.data.sessions.insert(identifier.to_string(), SessionState::default());
+            self.window_sessions.insert(identifier.to_string(), WindowSession { source, session });
+        }
+
+        loop {
+            let state = &self.data.sessions[identifier];
+            if state.stopped {
+                self.forget_window_session(identifier);
+                return Err(CaptureError::Protocol("Window capture session stopped".to_string()));
+            }
+            if let Some(constraints) = &state.constraints {
+                return Ok(constraints.clone());
+            }
+            self.dispatch()?;
+        }
+    }
+
+    fn forget_window_session(&mut self, identifier: &str) {
+        if let Some(session) = self.window_sessions.remove(identifier) {
+            session.session.destroy();
+            session.source.destroy();
+        }
+        self.data.sessions.remove(identifier);
+
+        let target = CaptureTarget::Toplevel(identifier.to_string());
+        self.forget_buffer(&target, BufferKind::Shm);
+        self.forget_buffer(&target, BufferKind::Dmabuf);
+    }
+
+    /// Makes sure there's a buffer for `key` matching the offered parameters of its kind.
+    fn ensure_buffer(
+        &mut self,
+        key: &(CaptureTarget, BufferKind),
+        shm: Option<ShmParams>,
+        dmabuf: Option<(u32, u32, u32)>,
+        modifiers: Vec<u64>,
+    ) -> Result<(), CaptureError> {
+        let reusable = match (self.buffers.get(key), key.1) {
+            (Some(AllocatedBuffer::Shm { params, .. }), BufferKind::Shm) => Some(*params) == shm,
+            (Some(AllocatedBuffer::Dmabuf { params, .. }), BufferKind::Dmabuf) => Some(*params) == dmabuf,
+            _ => false,
+        };
+        if reusable {
+            return Ok(());
+        }
+
+        if let Some(buffer) = self.buffers.remove(key) {
+            buffer.destroy();
+        }
+        let buffer = match key.1 {
+            BufferKind::Shm => {
+                let params = shm
+                    .ok_or_else(|| CaptureError::BufferAllocation("No SHM buffer offered".to_string()))?;
+                self.allocate_shm(params)?
+            }
+            BufferKind::Dmabuf => {
+                let params = dmabuf
+                    .ok_or_else(|| CaptureError::BufferAllocation("No DMA-BUF offered".to_string()))?;
+                self.allocate_dmabuf(params, modifiers)?
+            }
         };
-        
-        self.active_captures.insert(output_name.to_string(), capture_state);
-        
-        self.create_shm_buffer(output_info)
+        self.buffers.insert(key.clone(), buffer);
+        Ok(())
+    }
+
+    fn wait_for_frame(&mut self, target: &CaptureTarget) -> Result<(), CaptureError> {
+        loop {
+            self.dispatch()?;
+            let state = &self.data.active_captures[target];
+            if state.failed {
+                return Err(CaptureError::Protocol("Frame copy failed".to_string()));
+            }
+            if state.ready {
+                return Ok(());
+            }
+        }
+    }
+
+    fn allocate_shm(&self, params: ShmParams) -> Result<AllocatedBuffer, CaptureError> {
+        let shm = self.data.shm.as_ref()
+            .ok_or_else(|| CaptureError::Protocol("No wl_shm".to_string()))?;
+
+        let size = params.stride * params.height;
+        let file = create_memfd()
+            .map_err(|e| CaptureError::BufferAllocation(e.to_string()))?;
+        file.set_len(size as u64)
+            .map_err(|e| CaptureError::BufferAllocation(e.to_string()))?;
+
+        let qh = self.event_queue.handle();
+        let pool = shm.create_pool(file.as_fd(), size as i32, &qh, ());
+        let wl_buffer = pool.create_buffer(
+            0,
+            params.width as i32,
+            params.height as i32,
+            params.stride as i32,
+            params.format,
+            &qh,
+            (),
+        );
+        pool.destroy();
+
+        Ok(AllocatedBuffer::Shm { wl_buffer, file, params })
+    }
+
+    fn allocate_dmabuf(&self, params: (u32, u32, u32), mut modifiers: Vec<u64>) -> Result<AllocatedBuffer, CaptureError> {
+        let (format, width, height) = params;
+
+        let gbm = self.gbm.as_ref()
+            .ok_or_else(|| CaptureError::BufferAllocation("No GBM device".to_string()))?;
+        let dmabuf = self.data.dmabuf.as_ref()
+            .ok_or_else(|| CaptureError::Protocol("No linux-dmabuf".to_string()))?;
+
+        let gbm_format = gbm::Format::try_from(format)
+            .map_err(|_| CaptureError::BufferAllocation(format!("Unknown format 0x{:x}", format)))?;
+
+        if self.linear_dmabuf {
+            modifiers.retain(|&modifier| modifier == DRM_FORMAT_MOD_LINEAR);
+            if modifiers.is_empty() {
+                return Err(CaptureError::BufferAllocation("Compositor doesn't support linear DMA-BUFs".to_string()));
+            }
+        }
+        modifiers.retain(|&modifier| modifier != DRM_FORMAT_MOD_INVALID);
+
+        let usage = gbm::BufferObjectFlags::RENDERING;
+        let bo = if modifiers.is_empty() {
+            // Only the implicit modifier is supported.
+            gbm.create_buffer_object::<()>(width, height, gbm_format, usage)
+        } else {
+            gbm.create_buffer_object_with_modifiers2::<()>(
+                width,
+                height,
+                gbm_format,
+                modifiers.into_iter().map(gbm::Modifier::from),
+                usage,
+            )
+        }
+        .map_err(|e| CaptureError::BufferAllocation(e.to_string()))?;
+
+        let modifier = u64::from(bo.modifier());
+        let qh = self.event_queue.handle();
+        let buffer_params = dmabuf.create_params(&qh, ());
+
+        let mut fds = Vec::new();
+        let mut offsets = Vec::new();
+        let mut strides = Vec::new();
+        for plane in 0..bo.plane_count() as i32 {
+            let fd = bo.fd_for_plane(plane)
+                .map_err(|e| CaptureError::BufferAllocation(e.to_string()))?;
+            let offset = bo.offset(plane);
+            let stride = bo.stride_for_plane(plane);
+
+            buffer_params.add(
+                fd.as_fd(),
+                plane as u32,
+                offset,
+                stride,
+                (modifier >> 32) as u32,
+                modifier as u32,
+            );
+
+            fds.push(fd);
+            offsets.push(offset);
+            strides.push(stride);
+        }
+
+        let wl_buffer = buffer_params.create_immed(
+            width as i32,
+            height as i32,
+            format,
+            zwp_linux_buffer_params_v1::Flags::empty(),
+            &qh,
+            (),
+        );
+        buffer_params.destroy();
+
+        Ok(AllocatedBuffer::Dmabuf { wl_buffer, params, modifier, fds, offsets, strides })
     }
-    
+
+    #[cfg(test)]
     fn create_shm_buffer(&self, output_info: &OutputInfo) -> Result<CaptureBuffer, CaptureError> {
         let stride = output_info.width * 4;
         let size = stride * output_info.height;
         let data = vec![0u8; size as usize];
-        
+
         Ok(CaptureBuffer::Shm {
             data,
             stride,
             width: output_info.width,
             height: output_info.height,
-            format: 0x34325258, // DRM_FORMAT_XRGB8888
+            format: DRM_FORMAT_XRGB8888,
         })
     }
-    
+
+    fn dispatch(&mut self) -> Result<(), CaptureError> {
+        self.event_queue.blocking_dispatch(&mut self.data)
+            .map_err(|e| CaptureError::Protocol(e.to_string()))?;
+        Ok(())
+    }
+
     pub fn poll_events(&mut self) -> Result<(), CaptureError> {
-        let mut app_data = AppData {
-            outputs: self.outputs.clone(),
-            screencopy_manager: self.screencopy_manager.clone(),
-            shm: self.shm.clone(),
-        };
-        
-        self.event_queue.blocking_dispatch(&mut app_data)
+        self.connection.flush()
+            .map_err(|e| CaptureError::WaylandConnection(e.to_string()))?;
+        if let Some(guard) = self.event_queue.prepare_read() {
+            match guard.read() {
+                Ok(_) => {}
+                Err(wayland_client::backend::WaylandError::Io(e)) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                Err(e) => return Err(CaptureError::Protocol(e.to_string())),
+            }
+        }
+        self.event_queue.dispatch_pending(&mut self.data)
             .map_err(|e| CaptureError::Protocol(e.to_string()))?;
-        
-        self.outputs = app_data.outputs;
-        
+
         Ok(())
     }
-    
+
+    /// Waits for the compositor to send all pending output and toplevel updates.
     pub fn refresh_outputs(&mut self) -> Result<(), CaptureError> {
-        self.poll_events()?;
+        self.event_queue.roundtrip(&mut self.data)
+            .map_err(|e| CaptureError::Protocol(e.to_string()))?;
         Ok(())
     }
 }
 
+impl AppData {
+    fn add_output(&mut self, registry: &wl_registry::WlRegistry, name: u32, version: u32, qh: &QueueHandle<AppData>) {
+        let output = registry.bind::<wl_output::WlOutput, u32, AppData>(name, version.min(4), qh, name);
+
+        // Until the name event arrives (or forever on wl_output before version 4).
+        let info = OutputInfo {
+            name: format!("wl_output-{}", name),
+            width: 0,
+            height: 0,
+            refresh_rate: 0,
+            scale: 1.0,
+        };
+        self.outputs.insert(name, OutputState {
+            output,
+            pending: info.clone(),
+            info,
+            ready: false,
+        });
+    }
+
+    fn output_by_name(&self, name: &str) -> Option<&wl_output::WlOutput> {
+        self.outputs.values()
+            .find(|output| output.ready && output.info.name == name)
+            .map(|output| &output.output)
+    }
+}
+
+impl AllocatedBuffer {
+    fn wl_buffer(&self) -> &wl_buffer::WlBuffer {
+        match self {
+            AllocatedBuffer::Shm { wl_buffer, .. } => wl_buffer,
+            AllocatedBuffer::Dmabuf { wl_buffer, .. } => wl_buffer,
+        }
+    }
+
+    fn destroy(self) {
+        self.wl_buffer().destroy();
+    }
+
+    fn to_capture_buffer(&self) -> Result<CaptureBuffer, CaptureError> {
+        match self {
+            AllocatedBuffer::Shm { file, params, .. } => {
+                let mut data = vec![0u8; (params.stride * params.height) as usize];
+                file.read_exact_at(&mut data, 0)
+                    .map_err(|e| CaptureError::BufferAllocation(e.to_string()))?;
+
+                Ok(CaptureBuffer::Shm {
+                    data,
+                    stride: params.stride,
+                    width: params.width,
+                    height: params.height,
+                    format: shm_format_to_fourcc(params.format),
+                })
+            }
+            AllocatedBuffer::Dmabuf { params, modifier, fds, offsets, strides, .. } => {
+                let fds = fds.iter()
+                    .map(|fd| fd.try_clone())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| CaptureError::BufferAllocation(e.to_string()))?;
+
+                Ok(CaptureBuffer::Dmabuf {
+                    width: params.1,
+                    height: params.2,
+                    format: params.0,
+                    modifier: *modifier,
+                    num_planes: fds.len() as u32,
+                    fds,
+                    offsets: offsets.clone(),
+                    strides: strides.clone(),
+                })
+            }
+        }
+    }
+}
+
+/// wl_shm uses its own codes for the two mandatory formats, and DRM fourccs for the rest.
+fn shm_format_to_fourcc(format: wl_shm::Format) -> u32 {
+    match format {
+        wl_shm::Format::Argb8888 => DRM_FORMAT_ARGB8888,
+        wl_shm::Format::Xrgb8888 => DRM_FORMAT_XRGB8888,
+        other => u32::from(other),
+    }
+}
+
+fn create_memfd() -> std::io::Result<File> {
+    let fd = unsafe { libc::memfd_create(c"bevy-niri-shm".as_ptr(), libc::MFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+/// Opens a GBM device on the render node of the compositor's main device.
+///
+/// Falls back to the first render node when the compositor didn't send dmabuf feedback.
+fn open_gbm_device(main_device: Option<u64>) -> Option<gbm::Device<File>> {
+    let path = main_device
+        .and_then(render_node_for_device)
+        .or_else(first_render_node)?;
+
+    let file = File::options().read(true).write(true).open(&path)
+        .map_err(|e| tracing::debug!("Failed to open {}: {}", path.display(), e))
+        .ok()?;
+    gbm::Device::new(file)
+        .map_err(|e| tracing::debug!("Failed to create GBM device on {}: {}", path.display(), e))
+        .ok()
+}
+
+fn render_node_for_device(device: u64) -> Option<PathBuf> {
+    let dir = format!("/sys/dev/char/{}:{}/device/drm", libc::major(device), libc::minor(device));
+    std::fs::read_dir(dir).ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .find(|name| name.starts_with("renderD"))
+        .map(|name| PathBuf::from("/dev/dri").join(name))
+}
+
+fn first_render_node() -> Option<PathBuf> {
+    let mut nodes: Vec<_> = std::fs::read_dir("/dev/dri").ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.file_name().map_or(false, |name| name.to_string_lossy().starts_with("renderD")))
+        .collect();
+    nodes.sort();
+    nodes.into_iter().next()
+}
+
+/// Parses the dmabuf feedback format table: pairs of a format and a modifier, 16 bytes each.
+fn parse_format_table(fd: &OwnedFd, size: u32) -> Vec<(u32, u64)> {
+    let size = size as usize;
+    let ptr = unsafe {
+        libc::mmap(std::ptr::null_mut(), size, libc::PROT_READ, libc::MAP_PRIVATE, fd.as_raw_fd(), 0)
+    };
+    if ptr == libc::MAP_FAILED {
+        tracing::debug!("Failed to map dmabuf format table: {}", std::io::Error::last_os_error());
+        return Vec::new();
+    }
+
+    let bytes = unsafe { std::slice::from_raw_parts(ptr as *const u8, size) };
+    let table = bytes.chunks_exact(16)
+        .map(|entry| {
+            let format = u32::from_ne_bytes(entry[0..4].try_into().unwrap());
+            let modifier = u64::from_ne_bytes(entry[8..16].try_into().unwrap());
+            (format, modifier)
+        })
+        .collect();
+
+    unsafe { libc::munmap(ptr, size) };
+    table
+}
+
 impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for AppData {
     fn event(
-        _state: &mut Self,
-        _proxy: &wl_registry::WlRegistry,
-        _event: wl_registry::Event,
+        state: &mut Self,
+        proxy: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
         _data: &GlobalListContents,
         _conn: &Connection,
-        _qhandle: &QueueHandle<AppData>,
+        qhandle: &QueueHandle<AppData>,
     ) {
+        match event {
+            wl_registry::Event::Global { name, interface, version } if interface == "wl_output" => {
+                state.add_output(proxy, name, version, qhandle);
+            }
+            wl_registry::Event::GlobalRemove { name } => {
+                if let Some(output) = state.outputs.remove(&name) {
+                    if output.output.version() >= 3 {
+                        output.output.release();
+                    }
+                }
+            }
+            _ => {}
+        }
     }
 }
 
-impl Dispatch<wl_output::WlOutput, ()> for AppData {
+impl Dispatch<wl_output::WlOutput, u32> for AppData {
     fn event(
         state: &mut Self,
         proxy: &wl_output::WlOutput,
         event: wl_output::Event,
-        _data: &(),
+        global_name: &u32,
         _conn: &Connection,
         _qhandle: &QueueHandle<AppData>,
     ) {
+        let Some(output) = state.outputs.get_mut(global_name) else {
+            return;
+        };
+
         match event {
-            wl_output::Event::Geometry { 
-                x: _, y: _, physical_width: _, physical_height: _, 
-                subpixel: _, make, model, transform: _ 
-            } => {
-                for (name, (output, info)) in state.outputs.iter_mut() {
-                    if output == proxy {
-                        info.name = format!("{}-{}", make, model);
-                        break;
-                    }
+            wl_output::Event::Geometry { make, model, .. } => {
+                // Only a fallback for old compositors, the name event has the connector name.
+                if proxy.version() < 4 {
+                    output.pending.name = format!("{}-{}", make, model);
                 }
             }
-            wl_output::Event::Mode { flags: _, width, height, refresh } => {
-                for (_name, (output, info)) in state.outputs.iter_mut() {
-                    if output == proxy {
-                        info.width = width as u32;
-                        info.height = height as u32;
-                        info.refresh_rate = (refresh / 1000) as u32; // Convert mHz to Hz
-                        break;
-                    }
+            wl_output::Event::Name { name } => {
+                output.pending.name = name;
+            }
+            wl_output::Event::Mode { flags, width, height, refresh } => {
+                let current = matches!(flags, WEnum::Value(flags) if flags.contains(wl_output::Mode::Current));
+                if current {
+                    output.pending.width = width as u32;
+                    output.pending.height = height as u32;
+                    // Convert mHz to Hz.
+                    output.pending.refresh_rate = ((refresh + 500) / 1000) as u32;
                 }
             }
             wl_output::Event::Scale { factor } => {
-                for (_name, (output, info)) in state.outputs.iter_mut() {
-                    if output == proxy {
-                        info.scale = factor as f64;
-                        break;
-                    }
-                }
+                output.pending.scale = factor as f64;
             }
             wl_output::Event::Done => {
+                output.info = output.pending.clone();
+                output.ready = true;
             }
             _ => {}
         }
+
+        // Version 1 outputs don't send done.
+        if proxy.version() < 2 {
+            output.info = output.pending.clone();
+            output.ready = true;
+        }
     }
 }
 
@@ -238,15 +968,39 @@ impl Dispatch<zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1, ()> for AppDa
     }
 }
 
-impl Dispatch<zwlr_screencopy_frame_v1::ZwlrScreencopyFrameV1, ()> for AppData {
+impl Dispatch<zwlr_screencopy_frame_v1::ZwlrScreencopyFrameV1, CaptureTarget> for AppData {
     fn event(
-        _state: &mut Self,
+        state: &mut Self,
         _proxy: &zwlr_screencopy_frame_v1::ZwlrScreencopyFrameV1,
-        _event: zwlr_screencopy_frame_v1::Event,
-        _data: &(),
+        event: zwlr_screencopy_frame_v1::Event,
+        target: &CaptureTarget,
         _conn: &Connection,
         _qhandle: &QueueHandle<AppData>,
     ) {
+        let Some(capture) = state.active_captures.get_mut(target) else {
+            return;
+        };
+
+        match event {
+            zwlr_screencopy_frame_v1::Event::Buffer { format, width, height, stride } => {
+                if let WEnum::Value(format) = format {
+                    capture.shm = Some(ShmParams { format, width, height, stride });
+                }
+            }
+            zwlr_screencopy_frame_v1::Event::LinuxDmabuf { format, width, height } => {
+                capture.dmabuf = Some((format, width, height));
+            }
+            zwlr_screencopy_frame_v1::Event::BufferDone => {
+                capture.buffer_done = true;
+            }
+            zwlr_screencopy_frame_v1::Event::Ready { .. } => {
+                capture.ready = true;
+            }
+            zwlr_screencopy_frame_v1::Event::Failed => {
+                capture.failed = true;
+            }
+            _ => {}
+        }
     }
 }
 
@@ -262,6 +1016,18 @@ impl Dispatch<wl_shm::WlShm, ()> for AppData {
     }
 }
 
+impl Dispatch<wl_shm_pool::WlShmPool, ()> for AppData {
+    fn event(
+        _state: &mut Self,
+        _proxy: &wl_shm_pool::WlShmPool,
+        _event: wl_shm_pool::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<AppData>,
+    ) {
+    }
+}
+
 impl Dispatch<wl_buffer::WlBuffer, ()> for AppData {
     fn event(
         _state: &mut Self,
@@ -274,10 +1040,254 @@ impl Dispatch<wl_buffer::WlBuffer, ()> for AppData {
     }
 }
 
+impl Dispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, ()> for AppData {
+    fn event(
+        state: &mut Self,
+        _proxy: &zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1,
+        event: zwp_linux_dmabuf_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<AppData>,
+    ) {
+        // Only sent to version 3 objects, version 4 uses feedback instead.
+        if let zwp_linux_dmabuf_v1::Event::Modifier { format, modifier_hi, modifier_lo } = event {
+            let modifier = ((modifier_hi as u64) << 32) | modifier_lo as u64;
+            state.dmabuf_formats.entry(format).or_default().push(modifier);
+        }
+    }
+}
+
+impl Dispatch<zwp_linux_dmabuf_feedback_v1::ZwpLinuxDmabufFeedbackV1, ()> for AppData {
+    fn event(
+        state: &mut Self,
+        _proxy: &zwp_linux_dmabuf_feedback_v1::ZwpLinuxDmabufFeedbackV1,
+        event: zwp_linux_dmabuf_feedback_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<AppData>,
+    ) {
+        match event {
+            zwp_linux_dmabuf_feedback_v1::Event::FormatTable { fd, size } => {
+                state.format_table = parse_format_table(&fd, size);
+                state.dmabuf_formats.clear();
+            }
+            zwp_linux_dmabuf_feedback_v1::Event::MainDevice { device } => {
+                if let Ok(bytes) = device.as_slice().try_into() {
+                    state.main_device = Some(u64::from_ne_bytes(bytes));
+                }
+            }
+            zwp_linux_dmabuf_feedback_v1::Event::TrancheFormats { indices } => {
+                for index in indices.chunks_exact(2) {
+                    let index = u16::from_ne_bytes([index[0], index[1]]) as usize;
+                    if let Some(&(format, modifier)) = state.format_table.get(index) {
+                        let modifiers = state.dmabuf_formats.entry(format).or_default();
+                        if !modifiers.contains(&modifier) {
+                            modifiers.push(modifier);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<zwp_linux_buffer_params_v1::ZwpLinuxBufferParamsV1, ()> for AppData {
+    fn event(
+        _state: &mut Self,
+        _proxy: &zwp_linux_buffer_params_v1::ZwpLinuxBufferParamsV1,
+        event: zwp_linux_buffer_params_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<AppData>,
+    ) {
+        // With create_immed, failures show up as a failed screencopy frame.
+        if let zwp_linux_buffer_params_v1::Event::Failed = event {
+            tracing::debug!("Compositor failed to import a DMA-BUF");
+        }
+    }
+
+    wayland_client::event_created_child!(AppData, zwp_linux_buffer_params_v1::ZwpLinuxBufferParamsV1, [
+        zwp_linux_buffer_params_v1::EVT_CREATED_OPCODE => (wl_buffer::WlBuffer, ()),
+    ]);
+}
+
+impl Dispatch<ext_foreign_toplevel_list_v1::ExtForeignToplevelListV1, ()> for AppData {
+    fn event(
+        state: &mut Self,
+        _proxy: &ext_foreign_toplevel_list_v1::ExtForeignToplevelListV1,
+        event: ext_foreign_toplevel_list_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<AppData>,
+    ) {
+        match event {
+            ext_foreign_toplevel_list_v1::Event::Toplevel { toplevel } => {
+                state.toplevels.push(ToplevelState {
+                    handle: toplevel,
+                    info: ToplevelInfo::default(),
+                    pending: ToplevelInfo::default(),
+                    ready: false,
+                });
+            }
+            ext_foreign_toplevel_list_v1::Event::Finished => {
+                if let Some(list) = state.toplevel_list.take() {
+                    list.destroy();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    wayland_client::event_created_child!(AppData, ext_foreign_toplevel_list_v1::ExtForeignToplevelListV1, [
+        ext_foreign_toplevel_list_v1::EVT_TOPLEVEL_OPCODE => (ext_foreign_toplevel_handle_v1::ExtForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ext_foreign_toplevel_handle_v1::ExtForeignToplevelHandleV1, ()> for AppData {
+    fn event(
+        state: &mut Self,
+        proxy: &ext_foreign_toplevel_handle_v1::ExtForeignToplevelHandleV1,
+        event: ext_foreign_toplevel_handle_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<AppData>,
+    ) {
+        let Some(idx) = state.toplevels.iter().position(|toplevel| toplevel.handle == *proxy) else {
+            return;
+        };
+        let toplevel = &mut state.toplevels[idx];
+
+        match event {
+            ext_foreign_toplevel_handle_v1::Event::Identifier { identifier } => {
+                toplevel.pending.identifier = identifier;
+            }
+            ext_foreign_toplevel_handle_v1::Event::Title { title } => {
+                toplevel.pending.title = title;
+            }
+            ext_foreign_toplevel_handle_v1::Event::AppId { app_id } => {
+                toplevel.pending.app_id = app_id;
+            }
+            ext_foreign_toplevel_handle_v1::Event::Done => {
+                toplevel.info = toplevel.pending.clone();
+                toplevel.ready = true;
+            }
+            ext_foreign_toplevel_handle_v1::Event::Closed => {
+                // Capture sessions of the window get stopped by the compositor.
+                let toplevel = state.toplevels.remove(idx);
+                toplevel.handle.destroy();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ext_image_copy_capture_manager_v1::ExtImageCopyCaptureManagerV1, ()> for AppData {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ext_image_copy_capture_manager_v1::ExtImageCopyCaptureManagerV1,
+        _event: ext_image_copy_capture_manager_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<AppData>,
+    ) {
+    }
+}
+
+impl Dispatch<ext_foreign_toplevel_image_capture_source_manager_v1::ExtForeignToplevelImageCaptureSourceManagerV1, ()> for AppData {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ext_foreign_toplevel_image_capture_source_manager_v1::ExtForeignToplevelImageCaptureSourceManagerV1,
+        _event: ext_foreign_toplevel_image_capture_source_manager_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<AppData>,
+    ) {
+    }
+}
+
+impl Dispatch<ext_image_capture_source_v1::ExtImageCaptureSourceV1, ()> for AppData {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ext_image_capture_source_v1::ExtImageCaptureSourceV1,
+        _event: ext_image_capture_source_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<AppData>,
+    ) {
+    }
+}
+
+impl Dispatch<ext_image_copy_capture_session_v1::ExtImageCopyCaptureSessionV1, String> for AppData {
+    fn event(
+        state: &mut Self,
+        _proxy: &ext_image_copy_capture_session_v1::ExtImageCopyCaptureSessionV1,
+        event: ext_image_copy_capture_session_v1::Event,
+        identifier: &String,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<AppData>,
+    ) {
+        let Some(session) = state.sessions.get_mut(identifier) else {
+            return;
+        };
+
+        match event {
+            ext_image_copy_capture_session_v1::Event::BufferSize { width, height } => {
+                session.pending.buffer_size = Some((width, height));
+            }
+            ext_image_copy_capture_session_v1::Event::ShmFormat { format } => {
+                if let WEnum::Value(format) = format {
+                    session.pending.shm_formats.push(format);
+                }
+            }
+            ext_image_copy_capture_session_v1::Event::DmabufFormat { format, modifiers } => {
+                let modifiers = modifiers.chunks_exact(8)
+                    .map(|modifier| u64::from_ne_bytes(modifier.try_into().unwrap()))
+                    .collect();
+                session.pending.dmabuf_formats.insert(format, modifiers);
+            }
+            ext_image_copy_capture_session_v1::Event::Done => {
+                // Every batch of constraints is complete, so start over for the next one.
+                session.constraints = Some(std::mem::take(&mut session.pending));
+            }
+            ext_image_copy_capture_session_v1::Event::Stopped => {
+                session.stopped = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ext_image_copy_capture_frame_v1::ExtImageCopyCaptureFrameV1, CaptureTarget> for AppData {
+    fn event(
+        state: &mut Self,
+        _proxy: &ext_image_copy_capture_frame_v1::ExtImageCopyCaptureFrameV1,
+        event: ext_image_copy_capture_frame_v1::Event,
+        target: &CaptureTarget,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<AppData>,
+    ) {
+        let Some(capture) = state.active_captures.get_mut(target) else {
+            return;
+        };
+
+        match event {
+            ext_image_copy_capture_frame_v1::Event::Ready => {
+                capture.ready = true;
+            }
+            ext_image_copy_capture_frame_v1::Event::Failed { reason } => {
+                tracing::debug!("Capture of {:?} failed: {:?}", target, reason);
+                capture.failed = true;
+            }
+            _ => {}
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    
+
     #[test]
     fn test_capture_buffer_creation() {
         let output_info = OutputInfo {
@@ -287,12 +1297,12 @@ mod tests {
             refresh_rate: 60,
             scale: 1.0,
         };
-        
+
         let client = NiriScreencopyClient::new();
         if let Ok(client) = client {
             let buffer = client.create_shm_buffer(&output_info);
             assert!(buffer.is_ok());
-            
+
             if let Ok(CaptureBuffer::Shm { width, height, stride, .. }) = buffer {
                 assert_eq!(width, 1920);
                 assert_eq!(height, 1080);
@@ -300,4 +1310,29 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_shm_format_to_fourcc() {
+        assert_eq!(shm_format_to_fourcc(wl_shm::Format::Xrgb8888), DRM_FORMAT_XRGB8888);
+        assert_eq!(shm_format_to_fourcc(wl_shm::Format::Argb8888), DRM_FORMAT_ARGB8888);
+        assert_eq!(shm_format_to_fourcc(wl_shm::Format::Xbgr8888), DRM_FORMAT_XBGR8888);
+    }
+
+    #[test]
+    fn test_parse_format_table() {
+        let file = create_memfd().unwrap();
+        let mut table = Vec::new();
+        for (format, modifier) in [(DRM_FORMAT_XRGB8888, DRM_FORMAT_MOD_LINEAR), (DRM_FORMAT_ARGB8888, 0x0100_0000_0000_0001u64)] {
+            table.extend_from_slice(&format.to_ne_bytes());
+            table.extend_from_slice(&0u32.to_ne_bytes());
+            table.extend_from_slice(&modifier.to_ne_bytes());
+        }
+        file.write_all_at(&table, 0).unwrap();
+
+        let parsed = parse_format_table(&OwnedFd::from(file), table.len() as u32);
+        assert_eq!(parsed, vec![
+            (DRM_FORMAT_XRGB8888, DRM_FORMAT_MOD_LINEAR),
+            (DRM_FORMAT_ARGB8888, 0x0100_0000_0000_0001),
+        ]);
+    }
 }
@@ -17,6 +17,21 @@ mod integration_tests {
         assert!(app.world().get_resource::<NiriCaptureState>().is_some());
     }
     
+    #[test]
+    fn test_adaptive_resource() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+           .add_plugins(NiriCapturePlugin {
+               prefer_dmabuf: false,
+               ..default()
+           });
+        
+        app.update();
+        
+        let adaptive = app.world().resource::<AdaptiveCaptureSystem>();
+        assert_eq!(adaptive.current_method("DP-1"), CaptureMethod::Shm);
+    }
+    
     #[test]
     fn test_multi_output_setup() {
         let plugin = NiriCapturePlugin {
@@ -0,0 +1,501 @@
+//! Support for `include "path"` directives.
+//!
+//! Every file is first decoded on its own, so that errors point into the file that contains them.
+//! Then the files are merged at the KDL node level and decoded once more as a whole.
+//!
+//! Nodes are merged in document order, as if every include was pasted in place of its `include`
+//! line. Later nodes take precedence as follows:
+//!
+//! - `window-rule`, `layer-rule` and `spawn-at-startup` are appended.
+//! - `output` and named `workspace` sections are merged by their name like the other sections
+//!   below, so a later `output "eDP-1" { scale 2; }` overrides only the scale. Names are compared
+//!   case-insensitively, the same as when matching outputs and workspaces.
+//! - Binds in `binds` and `switch-events` replace binds with the same key. Every binding mode is a
+//!   separate `binds` section.
+//! - In all other sections, nodes are merged recursively by name, so a later `layout { gaps 8; }`
+//!   overrides only the gaps. Nodes that repeat within a section, like the entries of
+//!   `preset-column-widths`, are replaced as a whole.
+
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use knuffel::span::Span;
+use miette::{miette, Context, IntoDiagnostic};
+
+use crate::Config;
+
+/// Top-level nodes that accumulate across files.
+const APPENDED_NODES: &[&str] = &["window-rule", "layer-rule", "spawn-at-startup"];
+
+/// Top-level nodes that are told apart by their first argument.
+const NAMED_NODES: &[&str] = &["output", "workspace"];
+
+/// Sections where every child node is replaced as a whole rather than merged.
+const REPLACED_CHILDREN: &[&str] = &["binds", "switch-events"];
+
+/// Flags that cancel each other out, so that the later one wins.
+const EXCLUSIVE_FLAGS: &[(&str, &str)] = &[("on", "off")];
+
+#[derive(Debug, Clone)]
+struct Node {
+    /// Name of the node, along with the binding mode name for `binds` sections, and the
+    /// lowercase name for `output` and `workspace` sections.
+    name: String,
+    /// Source text of the node, without the children block.
+    header: String,
+    children: Option<Vec<Node>>,
+}
+
+#[derive(Default)]
+pub(crate) struct Loader {
+    /// Canonical paths of the files currently being loaded, to detect cycles.
+    stack: Vec<PathBuf>,
+    /// All files that were read, including the ones that failed to load.
+    pub files: Vec<PathBuf>,
+}
+
+impl Loader {
+    /// Loads the config at `path` along with everything it includes.
+    pub fn load(&mut self, path: &Path) -> miette::Result<Config> {
+        let (config, nodes) = self.load_file(path, false)?;
+        let Some(nodes) = nodes else {
+            return Ok(config);
+        };
+
+        let mut text = String::new();
+        for node in &nodes {
+            node.write(&mut text);
+            text.push('\n');
+        }
+
+        let filename = format!("{} (with includes)", file_name(path));
+        let config = Config::parse(&filename, &text).context("error merging included files")?;
+        Ok(config)
+    }
+
+    /// Loads a single file, and the files it includes.
+    ///
+    /// The merged nodes are returned if the file has includes, or if `want_nodes` is set.
+    fn load_file(
+        &mut self,
+        path: &Path,
+        want_nodes: bool,
+    ) -> miette::Result<(Config, Option<Vec<Node>>)> {
+        if !self.files.iter().any(|p| p == path) {
+            self.files.push(path.to_owned());
+        }
+
+        let canonical = path
+            .canonicalize()
+            .into_diagnostic()
+            .with_context(|| format!("error reading {path:?}"))?;
+        if let Some(pos) = self.stack.iter().position(|p| *p == canonical) {
+            let cycle: Vec<_> = self.stack[pos..]
+                .iter()
+                .chain([&canonical])
+                .map(|p| p.to_string_lossy())
+                .collect();
+            return Err(miette!("include cycle: {}", cycle.join(" -> ")));
+        }
+
+        let contents = std::fs::read_to_string(path)
+            .into_diagnostic()
+            .with_context(|| format!("error reading {path:?}"))?;
+
+        // Decode the file on its own first so that errors point into it.
+        let filename = file_name(path);
+        let config = Config::parse(filename, &contents).context("error parsing")?;
+        if config.includes.is_empty() && !want_nodes {
+            return Ok((config, None));
+        }
+
+        let document = knuffel::parse_ast::<Span>(filename, &contents)?;
+
+        // Nodes are merged in document order, so an include takes precedence over what comes
+        // before it, and is overridden by what comes after it.
+        self.stack.push(canonical);
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let mut includes = config.includes.iter();
+        let mut nodes = Vec::new();
+        let mut res = Ok(());
+        for node in &document.nodes {
+            if &**node.node_name != "include" {
+                merge_top_level(&mut nodes, Node::from_ast(&contents, node));
+                continue;
+            }
+
+            let include = includes.next().unwrap();
+            let include_path = dir.join(&include.path);
+            match self.load_file(&include_path, true) {
+                Ok((_, included_nodes)) => {
+                    for node in included_nodes.unwrap() {
+                        merge_top_level(&mut nodes, node);
+                    }
+                }
+                Err(err) => {
+                    res = Err(err.wrap_err(format!("error including {include_path:?}")));
+                    break;
+                }
+            }
+        }
+        self.stack.pop();
+        res?;
+
+        Ok((config, Some(nodes)))
+    }
+}
+
+fn file_name(path: &Path) -> &str {
+    path.file_name()
+        .and_then(OsStr::to_str)
+        .unwrap_or("config.kdl")
+}
+
+impl Node {
+    fn from_ast(text: &str, node: &knuffel::ast::SpannedNode<Span>) -> Self {
+        let span = node.span();
+        let mut name = String::from(&**node.node_name);
+        if name == "binds" {
+            if let Some(mode) = node.arguments.first() {
+                let mode = mode.literal.span();
+                name = format!("binds {}", &text[mode.0..mode.1]);
+            }
+        } else if NAMED_NODES.contains(&name.as_str()) {
+            if let Some(arg) = node.arguments.first() {
+                if let knuffel::ast::Literal::String(arg) = &*arg.literal {
+                    name = format!("{name} {}", arg.to_lowercase());
+                }
+            }
+        }
+
+        let Some(children) = &node.children else {
+            return Self {
+                name,
+                header: text[span.0..span.1].trim().to_owned(),
+                children: None,
+            };
+        };
+
+        let header = text[span.0..children.span().0].trim_end();
+        let header = header.strip_suffix('{').unwrap_or(header).trim_end();
+        let children = children
+            .iter()
+            .map(|child| Node::from_ast(text, child))
+            .collect();
+
+        Self {
+            name,
+            header: header.to_owned(),
+            children: Some(children),
+        }
+    }
+
+    fn write(&self, out: &mut String) {
+        out.push_str(&self.header);
+
+        if let Some(children) = &self.children {
+            out.push_str(" {\n");
+            for child in children {
+                child.write(out);
+                out.push('\n');
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn merge_top_level(nodes: &mut Vec<Node>, node: Node) {
+    if APPENDED_NODES.contains(&node.name.as_str()) {
+        nodes.push(node);
+    } else {
+        merge_children(nodes, vec![node]);
+    }
+}
+
+/// Merges `overlay` into `base`, with nodes from `overlay` taking precedence.
+fn merge_children(base: &mut Vec<Node>, overlay: Vec<Node>) {
+    let mut seen = HashSet::new();
+    for name in overlay.iter().map(|node| node.name.clone()) {
+        if !seen.insert(name.clone()) {
+            continue;
+        }
+
+        let new: Vec<_> = overlay.iter().filter(|n| n.name == name).cloned().collect();
+        let existing: Vec<_> = (0..base.len()).filter(|&i| base[i].name == name).collect();
+
+        // A single section present on both sides gets merged recursively.
+        if let ([idx], [node]) = (&existing[..], &new[..]) {
+            let old = &mut base[*idx];
+            if let (Some(old_children), Some(new_children)) = (&mut old.children, &node.children) {
+                let section = name.split(' ').next().unwrap();
+                if REPLACED_CHILDREN.contains(&section) {
+                    replace_children(old_children, new_children.clone());
+                } else {
+                    merge_children(old_children, new_children.clone());
+                }
+                old.header = node.header.clone();
+                continue;
+            }
+        }
+
+        // Everything else is replaced, keeping the position of the first old node.
+        let opposite = opposite_flag(&name);
+        let pos = (0..base.len())
+            .find(|&i| base[i].name == name || Some(base[i].name.as_str()) == opposite)
+            .unwrap_or(base.len());
+        base.retain(|n| n.name != name && Some(n.name.as_str()) != opposite);
+        let pos = pos.min(base.len());
+        base.splice(pos..pos, new);
+    }
+}
+
+fn opposite_flag(name: &str) -> Option<&'static str> {
+    EXCLUSIVE_FLAGS.iter().find_map(|&(a, b)| {
+        if name == a {
+            Some(b)
+        } else if name == b {
+            Some(a)
+        } else {
+            None
+        }
+    })
+}
+
+/// Replaces nodes in `base` with same-named nodes from `overlay`, appending the rest.
+fn replace_children(base: &mut Vec<Node>, overlay: Vec<Node>) {
+    for node in overlay {
+        if let Some(old) = base.iter_mut().find(|n| n.name == node.name) {
+            *old = node;
+        } else {
+            base.push(node);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let mut path = std::env::temp_dir();
+            path.push(format!("niri-config-{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn write(&self, name: &str, text: &str) -> PathBuf {
+            let path = self.0.join(name);
+            fs::write(&path, text).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn include_merges_sections() {
+        let dir = TempDir::new("merge");
+        dir.write(
+            "host.kdl",
+            r#"
+            layout {
+                gaps 8
+            }
+
+            window-rule {
+                match app-id="firefox"
+                open-maximized true
+            }
+
+            binds {
+                Mod+T { spawn "foot"; }
+            }
+
+            binds "resize" {
+                Escape { exit-bind-mode; }
+            }
+            "#,
+        );
+        let path = dir.write(
+            "config.kdl",
+            r#"
+            include "host.kdl"
+
+            layout {
+                gaps 16
+                center-focused-column "always"
+            }
+
+            window-rule {
+                match app-id="alacritty"
+            }
+
+            binds {
+                Mod+T { spawn "alacritty"; }
+                Mod+Q { close-window; }
+            }
+
+            binds "resize" {
+                Minus { set-column-width "-10%"; }
+            }
+            "#,
+        );
+
+        let (config, included) = Config::load_with_includes(&path);
+        let config = config.unwrap();
+        assert_eq!(included, [dir.0.join("host.kdl")]);
+
+        assert_eq!(config.layout.gaps.0, 16.);
+        assert_eq!(
+            config.layout.center_focused_column,
+            crate::CenterFocusedColumn::Always
+        );
+        assert_eq!(config.window_rules.len(), 2);
+        assert_eq!(config.window_rules[0].open_maximized, Some(true));
+        assert_eq!(config.binds().0.len(), 2);
+        assert_eq!(config.mode_binds(Some("resize")).unwrap().0.len(), 2);
+    }
+
+    fn nodes(text: &str) -> Vec<Node> {
+        let document = knuffel::parse_ast::<Span>("test.kdl", text).unwrap();
+        let mut nodes = Vec::new();
+        for node in &document.nodes {
+            merge_top_level(&mut nodes, Node::from_ast(text, node));
+        }
+        nodes
+    }
+
+    fn write(nodes: &[Node]) -> String {
+        let mut text = String::new();
+        for node in nodes {
+            node.write(&mut text);
+            text.push('\n');
+        }
+        text
+    }
+
+    #[test]
+    fn include_overrides_preceding_nodes() {
+        let dir = TempDir::new("order");
+        dir.write("gaps.kdl", "layout { gaps 8; }");
+        let path = dir.write(
+            "config.kdl",
+            r#"
+            layout {
+                gaps 16
+            }
+
+            include "gaps.kdl"
+            "#,
+        );
+
+        let (config, _) = Config::load_with_includes(&path);
+        assert_eq!(config.unwrap().layout.gaps.0, 8.);
+    }
+
+    #[test]
+    fn include_overrides_output_by_name() {
+        let dir = TempDir::new("output");
+        dir.write(
+            "host.kdl",
+            r#"
+            output "edp-1" {
+                scale 2
+            }
+            "#,
+        );
+        let path = dir.write(
+            "config.kdl",
+            r#"
+            output "eDP-1" {
+                scale 1
+                mode "1920x1080"
+            }
+
+            output "DP-1" {
+                scale 1
+            }
+
+            include "host.kdl"
+            "#,
+        );
+
+        let (config, _) = Config::load_with_includes(&path);
+        let config = config.unwrap();
+        assert_eq!(config.outputs.0.len(), 2);
+
+        let output = &config.outputs.0[0];
+        assert_eq!(output.scale.unwrap().0, 2.);
+        assert!(output.mode.is_some());
+        assert_eq!(config.outputs.0[1].scale.unwrap().0, 1.);
+    }
+
+    #[test]
+    fn include_merges_duplicate_workspace() {
+        let dir = TempDir::new("workspace");
+        dir.write(
+            "host.kdl",
+            r#"
+            workspace "chat" {
+                open-on-output "DP-1"
+            }
+            "#,
+        );
+        let path = dir.write(
+            "config.kdl",
+            r#"
+            workspace "chat"
+            workspace "browser"
+
+            include "host.kdl"
+            "#,
+        );
+
+        let (config, _) = Config::load_with_includes(&path);
+        let config = config.unwrap();
+        assert_eq!(config.workspaces.len(), 2);
+        assert_eq!(config.workspaces[0].name.0, "chat");
+        assert_eq!(config.workspaces[0].open_on_output.as_deref(), Some("DP-1"));
+        assert_eq!(config.workspaces[1].name.0, "browser");
+    }
+
+    #[test]
+    fn later_flag_replaces_opposite_flag() {
+        let merged = nodes("a { off; b 1; }\na { on; }\na { b 2; off; }");
+        assert_eq!(write(&merged), write(&nodes("a { off; b 2; }")));
+    }
+
+    #[test]
+    fn include_cycle() {
+        let dir = TempDir::new("cycle");
+        dir.write("a.kdl", r#"include "config.kdl""#);
+        let path = dir.write("config.kdl", r#"include "a.kdl""#);
+
+        let (config, included) = Config::load_with_includes(&path);
+        let err = format!("{:?}", config.unwrap_err());
+        assert!(err.contains("include cycle"), "{err}");
+        assert_eq!(included, [dir.0.join("a.kdl")]);
+    }
+
+    #[test]
+    fn include_error_points_into_included_file() {
+        let dir = TempDir::new("error");
+        dir.write("bad.kdl", "layout { gaps \"wide\"; }");
+        let path = dir.write("config.kdl", r#"include "bad.kdl""#);
+
+        let (config, _) = Config::load_with_includes(&path);
+        let err = format!("{:?}", config.unwrap_err());
+        assert!(err.contains("bad.kdl"), "{err}");
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{BlockOutFrom, CornerRadius, RegexEq, ShadowRule};
+use crate::{BlockOutFrom, BlurRule, CornerRadius, RegexEq, ShadowRule};
 
 #[derive(knuffel::Decode, Debug, Default, Clone, PartialEq)]
 pub struct LayerRule {
@@ -19,6 +19,8 @@ pub struct LayerRule {
     pub place_within_backdrop: Option<bool>,
     #[knuffel(child, unwrap(argument))]
     pub baba_is_float: Option<bool>,
+    #[knuffel(child, default)]
+    pub blur: BlurRule,
 }
 
 #[derive(knuffel::Decode, Debug, Default, Clone, PartialEq)]
@@ -2,7 +2,8 @@
 extern crate tracing;
 
 use std::collections::HashSet;
-use std::ffi::OsStr;
+use std::iter::zip;
+use std::mem;
 use std::ops::{Mul, MulAssign};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -28,6 +29,7 @@ pub const DEFAULT_BACKDROP_COLOR: Color = Color::from_array_unpremul([0.15, 0.15
 
 pub mod layer_rule;
 
+mod include;
 mod utils;
 pub use utils::RegexEq;
 
@@ -64,21 +66,37 @@ pub struct Config {
     #[knuffel(child, default)]
     pub overview: Overview,
     #[knuffel(child, default)]
+    pub magnifier: Magnifier,
+    #[knuffel(child, default)]
     pub environment: Environment,
     #[knuffel(child, default)]
     pub xwayland_satellite: XwaylandSatellite,
+    #[knuffel(child, default)]
+    pub bevy_capture: BevyCapture,
     #[knuffel(children(name = "window-rule"))]
     pub window_rules: Vec<WindowRule>,
     #[knuffel(children(name = "layer-rule"))]
     pub layer_rules: Vec<LayerRule>,
-    #[knuffel(child, default)]
-    pub binds: Binds,
+    /// Binds of every binding mode.
+    ///
+    /// The default mode always comes first.
+    #[knuffel(children(name = "binds"))]
+    pub bind_modes: Vec<BindMode>,
     #[knuffel(child, default)]
     pub switch_events: SwitchBinds,
     #[knuffel(child, default)]
     pub debug: DebugConfig,
     #[knuffel(children(name = "workspace"))]
     pub workspaces: Vec<Workspace>,
+    #[knuffel(children(name = "include"))]
+    pub includes: Vec<Include>,
+}
+
+#[derive(knuffel::Decode, Debug, Clone, PartialEq, Eq)]
+pub struct Include {
+    /// Path to the included file, relative to the including file.
+    #[knuffel(argument)]
+    pub path: PathBuf,
 }
 
 #[derive(knuffel::Decode, Debug, Default, PartialEq)]
@@ -178,13 +196,15 @@ pub enum CenterFocusedColumn {
     OnOverflow,
 }
 
-#[derive(knuffel::DecodeScalar, Debug, Default, PartialEq, Eq)]
+#[derive(knuffel::DecodeScalar, Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum TrackLayout {
     /// The layout change is global.
     #[default]
     Global,
     /// The layout change is window local.
     Window,
+    /// The layout change is shared by all windows of an app, and is kept after they close.
+    App,
 }
 
 #[derive(knuffel::Decode, Debug, Default, PartialEq)]
@@ -462,6 +482,12 @@ pub struct Output {
     pub background_color: Option<Color>,
     #[knuffel(child)]
     pub backdrop_color: Option<Color>,
+    #[knuffel(child)]
+    pub hot_corners: Option<HotCorners>,
+    #[knuffel(child, unwrap(argument))]
+    pub icc_profile: Option<String>,
+    #[knuffel(child, unwrap(argument))]
+    pub custom_shader: Option<String>,
 }
 
 impl Output {
@@ -491,6 +517,9 @@ impl Default for Output {
             variable_refresh_rate: None,
             background_color: None,
             backdrop_color: None,
+            hot_corners: None,
+            icc_profile: None,
+            custom_shader: None,
         }
     }
 }
@@ -533,6 +562,8 @@ pub struct Layout {
     pub tab_indicator: TabIndicator,
     #[knuffel(child, default)]
     pub insert_hint: InsertHint,
+    #[knuffel(child, default)]
+    pub picture_in_picture: PictureInPicture,
     #[knuffel(child, unwrap(children), default)]
     pub preset_column_widths: Vec<PresetSize>,
     #[knuffel(child)]
@@ -551,6 +582,12 @@ pub struct Layout {
     pub gaps: FloatOrInt<0, 65535>,
     #[knuffel(child, default)]
     pub struts: Struts,
+    #[knuffel(child, unwrap(argument), default = Self::default().inactive_dim)]
+    pub inactive_dim: FloatOrInt<0, 1>,
+    #[knuffel(child, unwrap(argument), default = Self::default().inactive_saturation)]
+    pub inactive_saturation: FloatOrInt<0, 1>,
+    #[knuffel(child, unwrap(argument), default = Self::default().inactive_brightness)]
+    pub inactive_brightness: FloatOrInt<-1, 1>,
     #[knuffel(child, default = DEFAULT_BACKGROUND_COLOR)]
     pub background_color: Color,
 }
@@ -563,6 +600,7 @@ impl Default for Layout {
             shadow: Default::default(),
             tab_indicator: Default::default(),
             insert_hint: Default::default(),
+            picture_in_picture: Default::default(),
             preset_column_widths: Default::default(),
             default_column_width: Default::default(),
             center_focused_column: Default::default(),
@@ -571,6 +609,9 @@ impl Default for Layout {
             default_column_display: ColumnDisplay::Normal,
             gaps: FloatOrInt(16.),
             struts: Default::default(),
+            inactive_dim: FloatOrInt(0.),
+            inactive_saturation: FloatOrInt(1.),
+            inactive_brightness: FloatOrInt(0.),
             preset_window_heights: Default::default(),
             background_color: DEFAULT_BACKGROUND_COLOR,
         }
@@ -918,6 +959,26 @@ impl Default for InsertHint {
     }
 }
 
+#[derive(knuffel::Decode, Debug, Clone, Copy, PartialEq)]
+pub struct PictureInPicture {
+    #[knuffel(child, unwrap(argument), default = Self::default().width)]
+    pub width: FloatOrInt<1, 65535>,
+    #[knuffel(child, unwrap(argument), default = Self::default().corner)]
+    pub corner: RelativeTo,
+    #[knuffel(child, unwrap(argument), default = Self::default().margin)]
+    pub margin: FloatOrInt<0, 65535>,
+}
+
+impl Default for PictureInPicture {
+    fn default() -> Self {
+        Self {
+            width: FloatOrInt(480.),
+            corner: RelativeTo::BottomRight,
+            margin: FloatOrInt(16.),
+        }
+    }
+}
+
 /// RGB color in [0, 1] with unpremultiplied alpha.
 #[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct Color {
@@ -1075,6 +1136,10 @@ pub struct Animations {
     pub screenshot_ui_open: ScreenshotUiOpenAnim,
     #[knuffel(child, default)]
     pub overview_open_close: OverviewOpenCloseAnim,
+    #[knuffel(child, default)]
+    pub magnifier_zoom: MagnifierZoomAnim,
+    #[knuffel(child, default)]
+    pub inactive_dim: InactiveDimAnim,
 }
 
 impl Default for Animations {
@@ -1091,23 +1156,31 @@ impl Default for Animations {
             config_notification_open_close: Default::default(),
             screenshot_ui_open: Default::default(),
             overview_open_close: Default::default(),
+            magnifier_zoom: Default::default(),
+            inactive_dim: Default::default(),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct WorkspaceSwitchAnim(pub Animation);
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkspaceSwitchAnim {
+    pub anim: Animation,
+    pub custom_shader: Option<String>,
+}
 
 impl Default for WorkspaceSwitchAnim {
     fn default() -> Self {
-        Self(Animation {
-            off: false,
-            kind: AnimationKind::Spring(SpringParams {
-                damping_ratio: 1.,
-                stiffness: 1000,
-                epsilon: 0.0001,
-            }),
-        })
+        Self {
+            anim: Animation {
+                off: false,
+                kind: AnimationKind::Spring(SpringParams {
+                    damping_ratio: 1.,
+                    stiffness: 1000,
+                    epsilon: 0.0001,
+                }),
+            },
+            custom_shader: None,
+        }
     }
 }
 
@@ -1254,6 +1327,37 @@ impl Default for OverviewOpenCloseAnim {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MagnifierZoomAnim(pub Animation);
+
+impl Default for MagnifierZoomAnim {
+    fn default() -> Self {
+        Self(Animation {
+            off: false,
+            kind: AnimationKind::Spring(SpringParams {
+                damping_ratio: 1.,
+                stiffness: 800,
+                epsilon: 0.0001,
+            }),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InactiveDimAnim(pub Animation);
+
+impl Default for InactiveDimAnim {
+    fn default() -> Self {
+        Self(Animation {
+            off: false,
+            kind: AnimationKind::Easing(EasingParams {
+                duration_ms: 200,
+                curve: AnimationCurve::EaseOutQuad,
+            }),
+        })
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Animation {
     pub off: bool,
@@ -1287,7 +1391,7 @@ pub struct SpringParams {
     pub epsilon: f64,
 }
 
-#[derive(knuffel::Decode, Debug, Default, Clone, Copy, PartialEq)]
+#[derive(knuffel::Decode, Debug, Default, Clone, PartialEq)]
 pub struct Gestures {
     #[knuffel(child, default)]
     pub dnd_edge_view_scroll: DndEdgeViewScroll,
@@ -1295,6 +1399,26 @@ pub struct Gestures {
     pub dnd_edge_workspace_switch: DndEdgeWorkspaceSwitch,
     #[knuffel(child, default)]
     pub hot_corners: HotCorners,
+    #[knuffel(child, default)]
+    pub default_gestures: DefaultGestures,
+}
+
+#[derive(knuffel::Decode, Debug, Default, Clone, Copy, PartialEq)]
+pub struct DefaultGestures {
+    #[knuffel(child)]
+    pub off: bool,
+    #[knuffel(child, default)]
+    pub workspace_switch: DefaultGesture,
+    #[knuffel(child, default)]
+    pub view_scroll: DefaultGesture,
+    #[knuffel(child, default)]
+    pub overview: DefaultGesture,
+}
+
+#[derive(knuffel::Decode, Debug, Default, Clone, Copy, PartialEq)]
+pub struct DefaultGesture {
+    #[knuffel(child)]
+    pub off: bool,
 }
 
 #[derive(knuffel::Decode, Debug, Clone, Copy, PartialEq)]
@@ -1337,10 +1461,84 @@ impl Default for DndEdgeWorkspaceSwitch {
     }
 }
 
-#[derive(knuffel::Decode, Debug, Default, Clone, Copy, PartialEq)]
+#[derive(knuffel::Decode, Debug, Default, Clone, PartialEq)]
 pub struct HotCorners {
     #[knuffel(child)]
     pub off: bool,
+    #[knuffel(child)]
+    pub top_left: Option<HotCornerAction>,
+    #[knuffel(child)]
+    pub top_right: Option<HotCornerAction>,
+    #[knuffel(child)]
+    pub bottom_left: Option<HotCornerAction>,
+    #[knuffel(child)]
+    pub bottom_right: Option<HotCornerAction>,
+    #[knuffel(child)]
+    pub top_edge: Option<HotCornerAction>,
+    #[knuffel(child)]
+    pub bottom_edge: Option<HotCornerAction>,
+    #[knuffel(child)]
+    pub left_edge: Option<HotCornerAction>,
+    #[knuffel(child)]
+    pub right_edge: Option<HotCornerAction>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HotCornerAction {
+    pub action: Action,
+    /// How long the pointer has to stay in the corner.
+    pub delay: Duration,
+    /// How far the pointer has to push against the output edge, in logical pixels.
+    pub pressure: f64,
+    /// How fast the pointer has to move in the corner, in logical pixels per second.
+    pub velocity: f64,
+}
+
+impl HotCorners {
+    /// Returns the action for a corner or edge.
+    ///
+    /// When no corners or edges are configured, the top-left corner toggles the overview.
+    pub fn action(&self, corner: niri_ipc::HotCorner) -> Option<HotCornerAction> {
+        use niri_ipc::HotCorner;
+
+        if self.off {
+            return None;
+        }
+
+        let action = match corner {
+            HotCorner::TopLeft => &self.top_left,
+            HotCorner::TopRight => &self.top_right,
+            HotCorner::BottomLeft => &self.bottom_left,
+            HotCorner::BottomRight => &self.bottom_right,
+            HotCorner::TopEdge => &self.top_edge,
+            HotCorner::BottomEdge => &self.bottom_edge,
+            HotCorner::LeftEdge => &self.left_edge,
+            HotCorner::RightEdge => &self.right_edge,
+        };
+        if action.is_some() {
+            return action.clone();
+        }
+
+        let any_configured = [
+            &self.top_left,
+            &self.top_right,
+            &self.bottom_left,
+            &self.bottom_right,
+            &self.top_edge,
+            &self.bottom_edge,
+            &self.left_edge,
+            &self.right_edge,
+        ]
+        .into_iter()
+        .any(Option::is_some);
+
+        (corner == HotCorner::TopLeft && !any_configured).then(|| HotCornerAction {
+            action: Action::ToggleOverview,
+            delay: Duration::ZERO,
+            pressure: 0.,
+            velocity: 0.,
+        })
+    }
 }
 
 #[derive(knuffel::Decode, Debug, Clone, Copy, PartialEq)]
@@ -1363,6 +1561,34 @@ impl Default for Overview {
     }
 }
 
+#[derive(knuffel::Decode, Debug, Clone, Copy, PartialEq)]
+pub struct Magnifier {
+    #[knuffel(child, unwrap(argument), default = Self::default().max_zoom)]
+    pub max_zoom: FloatOrInt<1, 32>,
+    #[knuffel(child, unwrap(argument), default)]
+    pub follow_mode: MagnifierFollowMode,
+}
+
+impl Default for Magnifier {
+    fn default() -> Self {
+        Self {
+            max_zoom: FloatOrInt(8.),
+            follow_mode: MagnifierFollowMode::default(),
+        }
+    }
+}
+
+#[derive(knuffel::DecodeScalar, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MagnifierFollowMode {
+    /// The pointer stays at its real position on the screen, and the view moves proportionally.
+    #[default]
+    Proportional,
+    /// The view keeps the pointer in the center.
+    Centered,
+    /// The view moves only when the pointer pushes against its edges.
+    Push,
+}
+
 #[derive(knuffel::Decode, Debug, Default, Clone, PartialEq, Eq)]
 pub struct Environment(#[knuffel(children)] pub Vec<EnvironmentVariable>);
 
@@ -1391,6 +1617,38 @@ impl Default for XwaylandSatellite {
     }
 }
 
+/// Settings for screen capture clients like bevy-niri-integration.
+///
+/// niri itself doesn't use these.
+#[derive(knuffel::Decode, Debug, Clone, PartialEq)]
+pub struct BevyCapture {
+    #[knuffel(child, unwrap(argument), default = Self::default().capture_fps)]
+    pub capture_fps: FloatOrInt<0, 240>,
+    #[knuffel(child, unwrap(argument), default = Self::default().prefer_dmabuf)]
+    pub prefer_dmabuf: bool,
+    #[knuffel(child, unwrap(arguments), default)]
+    pub outputs: Vec<String>,
+    #[knuffel(child, unwrap(argument), default = Self::default().damage_tracking)]
+    pub damage_tracking: bool,
+    #[knuffel(child, unwrap(argument), default = Self::default().adaptive_performance)]
+    pub adaptive_performance: bool,
+    #[knuffel(child, unwrap(argument), default = Self::default().max_memory_usage)]
+    pub max_memory_usage: FloatOrInt<0, 1>,
+}
+
+impl Default for BevyCapture {
+    fn default() -> Self {
+        Self {
+            capture_fps: FloatOrInt(60.),
+            prefer_dmabuf: true,
+            outputs: vec![],
+            damage_tracking: true,
+            adaptive_performance: true,
+            max_memory_usage: FloatOrInt(0.8),
+        }
+    }
+}
+
 #[derive(knuffel::Decode, Debug, Clone, PartialEq, Eq)]
 pub struct Workspace {
     #[knuffel(argument)]
@@ -1425,6 +1683,8 @@ pub struct WindowRule {
     #[knuffel(child, unwrap(argument))]
     pub open_floating: Option<bool>,
     #[knuffel(child, unwrap(argument))]
+    pub open_sticky: Option<bool>,
+    #[knuffel(child, unwrap(argument))]
     pub open_focused: Option<bool>,
 
     // Rules applied dynamically.
@@ -1445,10 +1705,18 @@ pub struct WindowRule {
     pub shadow: ShadowRule,
     #[knuffel(child, default)]
     pub tab_indicator: TabIndicatorRule,
+    #[knuffel(child, default)]
+    pub blur: BlurRule,
     #[knuffel(child, unwrap(argument))]
     pub draw_border_with_background: Option<bool>,
     #[knuffel(child, unwrap(argument))]
     pub opacity: Option<f32>,
+    #[knuffel(child, unwrap(argument))]
+    pub inactive_dim: Option<FloatOrInt<0, 1>>,
+    #[knuffel(child, unwrap(argument))]
+    pub inactive_saturation: Option<FloatOrInt<0, 1>>,
+    #[knuffel(child, unwrap(argument))]
+    pub inactive_brightness: Option<FloatOrInt<-1, 1>>,
     #[knuffel(child)]
     pub geometry_corner_radius: Option<CornerRadius>,
     #[knuffel(child, unwrap(argument))]
@@ -1459,6 +1727,8 @@ pub struct WindowRule {
     pub block_out_from: Option<BlockOutFrom>,
     #[knuffel(child, unwrap(argument))]
     pub variable_refresh_rate: Option<bool>,
+    #[knuffel(child, unwrap(argument))]
+    pub allow_tearing: Option<bool>,
     #[knuffel(child, unwrap(argument, str))]
     pub default_column_display: Option<ColumnDisplay>,
     #[knuffel(child)]
@@ -1467,6 +1737,8 @@ pub struct WindowRule {
     pub scroll_factor: Option<FloatOrInt<0, 100>>,
     #[knuffel(child, unwrap(argument))]
     pub tiled_state: Option<bool>,
+    #[knuffel(child, unwrap(argument))]
+    pub default_keyboard_layout: Option<String>,
 }
 
 #[derive(knuffel::Decode, Debug, Default, Clone, PartialEq)]
@@ -1475,6 +1747,8 @@ pub struct Match {
     pub app_id: Option<RegexEq>,
     #[knuffel(property, str)]
     pub title: Option<RegexEq>,
+    #[knuffel(property, str)]
+    pub tag: Option<RegexEq>,
     #[knuffel(property)]
     pub is_active: Option<bool>,
     #[knuffel(property)]
@@ -1484,10 +1758,26 @@ pub struct Match {
     #[knuffel(property)]
     pub is_floating: Option<bool>,
     #[knuffel(property)]
+    pub scratchpad: Option<bool>,
+    #[knuffel(property)]
     pub is_window_cast_target: Option<bool>,
     #[knuffel(property)]
     pub is_urgent: Option<bool>,
     #[knuffel(property)]
+    pub pid: Option<i32>,
+    #[knuffel(property)]
+    pub on_workspace: Option<String>,
+    #[knuffel(property)]
+    pub on_output: Option<String>,
+    #[knuffel(property)]
+    pub is_xwayland: Option<bool>,
+    #[knuffel(property)]
+    pub is_fullscreen: Option<bool>,
+    #[knuffel(property)]
+    pub has_parent: Option<bool>,
+    #[knuffel(property)]
+    pub is_modal: Option<bool>,
+    #[knuffel(property)]
     pub at_startup: Option<bool>,
 }
 
@@ -1569,6 +1859,18 @@ pub struct ShadowRule {
     pub inactive_color: Option<Color>,
 }
 
+#[derive(knuffel::Decode, Debug, Default, Clone, Copy, PartialEq)]
+pub struct BlurRule {
+    #[knuffel(child)]
+    pub off: bool,
+    #[knuffel(child)]
+    pub on: bool,
+    #[knuffel(child, unwrap(argument))]
+    pub passes: Option<u8>,
+    #[knuffel(child, unwrap(argument))]
+    pub radius: Option<FloatOrInt<0, 100>>,
+}
+
 #[derive(knuffel::Decode, Debug, Default, Clone, Copy, PartialEq)]
 pub struct TabIndicatorRule {
     #[knuffel(child)]
@@ -1608,11 +1910,21 @@ pub enum RelativeTo {
     Right,
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct Binds(pub Vec<Bind>);
 
+/// A `binds` section.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BindMode {
+    /// Name of the binding mode, or `None` for the default mode.
+    pub name: Option<String>,
+    pub binds: Binds,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Bind {
+    /// Keys to press one after another before `key`, for a multi-key sequence.
+    pub prefix: Vec<Key>,
     pub key: Key,
     pub action: Action,
     pub repeat: bool,
@@ -1644,6 +1956,27 @@ pub enum Trigger {
     TouchpadScrollUp,
     TouchpadScrollLeft,
     TouchpadScrollRight,
+    TouchpadSwipe {
+        fingers: u8,
+        direction: SwipeDirection,
+    },
+    TouchpadPinchIn,
+    TouchpadPinchOut,
+    TouchpadHold {
+        fingers: u8,
+    },
+    TouchscreenSwipe {
+        fingers: u8,
+        direction: SwipeDirection,
+    },
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum SwipeDirection {
+    Up,
+    Down,
+    Left,
+    Right,
 }
 
 bitflags! {
@@ -1880,6 +2213,11 @@ pub enum Action {
     SetColumnWidth(#[knuffel(argument, str)] SizeChange),
     ExpandColumnToAvailableWidth,
     SwitchLayout(#[knuffel(argument, str)] LayoutSwitchTarget),
+    #[knuffel(skip)]
+    SwitchLayoutForWindowById {
+        id: u64,
+        layout: String,
+    },
     ShowHotkeyOverlay,
     MoveWorkspaceToMonitorLeft,
     MoveWorkspaceToMonitorRight,
@@ -1896,6 +2234,18 @@ pub enum Action {
     MoveWindowToTiling,
     #[knuffel(skip)]
     MoveWindowToTilingById(u64),
+    ToggleWindowSticky,
+    #[knuffel(skip)]
+    ToggleWindowStickyById(u64),
+    TogglePictureInPicture,
+    #[knuffel(skip)]
+    TogglePictureInPictureById(u64),
+    MoveWindowToScratchpad,
+    #[knuffel(skip)]
+    MoveWindowToScratchpadById(u64),
+    ToggleScratchpad(#[knuffel(property(name = "app-id"))] Option<String>),
+    ShowScratchpad(#[knuffel(property(name = "app-id"))] Option<String>),
+    HideScratchpad(#[knuffel(property(name = "app-id"))] Option<String>),
     FocusFloating,
     FocusTiling,
     SwitchFocusBetweenFloatingAndTiling,
@@ -1916,6 +2266,12 @@ pub enum Action {
     ToggleOverview,
     OpenOverview,
     CloseOverview,
+    EnterBindMode(#[knuffel(argument)] String),
+    ExitBindMode,
+    MagnifierZoomIn,
+    MagnifierZoomOut,
+    MagnifierReset,
+    ToggleInactiveDim,
     #[knuffel(skip)]
     ToggleWindowUrgent(u64),
     #[knuffel(skip)]
@@ -2125,6 +2481,9 @@ impl From<niri_ipc::Action> for Action {
             niri_ipc::Action::SetColumnWidth { change } => Self::SetColumnWidth(change),
             niri_ipc::Action::ExpandColumnToAvailableWidth {} => Self::ExpandColumnToAvailableWidth,
             niri_ipc::Action::SwitchLayout { layout } => Self::SwitchLayout(layout),
+            niri_ipc::Action::SwitchLayoutForWindow { id, layout } => {
+                Self::SwitchLayoutForWindowById { id, layout }
+            }
             niri_ipc::Action::ShowHotkeyOverlay {} => Self::ShowHotkeyOverlay,
             niri_ipc::Action::MoveWorkspaceToMonitorLeft {} => Self::MoveWorkspaceToMonitorLeft,
             niri_ipc::Action::MoveWorkspaceToMonitorRight {} => Self::MoveWorkspaceToMonitorRight,
@@ -2171,6 +2530,21 @@ impl From<niri_ipc::Action> for Action {
             niri_ipc::Action::MoveWindowToTiling { id: Some(id) } => {
                 Self::MoveWindowToTilingById(id)
             }
+            niri_ipc::Action::ToggleWindowSticky { id: None } => Self::ToggleWindowSticky,
+            niri_ipc::Action::ToggleWindowSticky { id: Some(id) } => {
+                Self::ToggleWindowStickyById(id)
+            }
+            niri_ipc::Action::TogglePictureInPicture { id: None } => Self::TogglePictureInPicture,
+            niri_ipc::Action::TogglePictureInPicture { id: Some(id) } => {
+                Self::TogglePictureInPictureById(id)
+            }
+            niri_ipc::Action::MoveWindowToScratchpad { id: None } => Self::MoveWindowToScratchpad,
+            niri_ipc::Action::MoveWindowToScratchpad { id: Some(id) } => {
+                Self::MoveWindowToScratchpadById(id)
+            }
+            niri_ipc::Action::ToggleScratchpad { app_id } => Self::ToggleScratchpad(app_id),
+            niri_ipc::Action::ShowScratchpad { app_id } => Self::ShowScratchpad(app_id),
+            niri_ipc::Action::HideScratchpad { app_id } => Self::HideScratchpad(app_id),
             niri_ipc::Action::FocusFloating {} => Self::FocusFloating,
             niri_ipc::Action::FocusTiling {} => Self::FocusTiling,
             niri_ipc::Action::SwitchFocusBetweenFloatingAndTiling {} => {
@@ -2194,6 +2568,12 @@ impl From<niri_ipc::Action> for Action {
             niri_ipc::Action::ToggleOverview {} => Self::ToggleOverview,
             niri_ipc::Action::OpenOverview {} => Self::OpenOverview,
             niri_ipc::Action::CloseOverview {} => Self::CloseOverview,
+            niri_ipc::Action::EnterBindMode { name } => Self::EnterBindMode(name),
+            niri_ipc::Action::ExitBindMode {} => Self::ExitBindMode,
+            niri_ipc::Action::MagnifierZoomIn {} => Self::MagnifierZoomIn,
+            niri_ipc::Action::MagnifierZoomOut {} => Self::MagnifierZoomOut,
+            niri_ipc::Action::MagnifierReset {} => Self::MagnifierReset,
+            niri_ipc::Action::ToggleInactiveDim {} => Self::ToggleInactiveDim,
             niri_ipc::Action::ToggleWindowUrgent { id } => Self::ToggleWindowUrgent(id),
             niri_ipc::Action::SetWindowUrgent { id } => Self::SetWindowUrgent(id),
             niri_ipc::Action::UnsetWindowUrgent { id } => Self::UnsetWindowUrgent(id),
@@ -2372,29 +2752,88 @@ pub enum PreviewRender {
 
 impl Config {
     pub fn load(path: &Path) -> miette::Result<Self> {
-        let _span = tracy_client::span!("Config::load");
-        Self::load_internal(path).context("error loading config")
+        Self::load_with_includes(path).0
     }
 
-    fn load_internal(path: &Path) -> miette::Result<Self> {
-        let contents = std::fs::read_to_string(path)
-            .into_diagnostic()
-            .with_context(|| format!("error reading {path:?}"))?;
+    /// Loads the config, returning it along with the paths of all files it includes.
+    ///
+    /// The paths are returned even if loading fails, so that they can be watched for changes.
+    pub fn load_with_includes(path: &Path) -> (miette::Result<Self>, Vec<PathBuf>) {
+        let _span = tracy_client::span!("Config::load");
 
-        let config = Self::parse(
-            path.file_name()
-                .and_then(OsStr::to_str)
-                .unwrap_or("config.kdl"),
-            &contents,
-        )
-        .context("error parsing")?;
-        debug!("loaded config from {path:?}");
-        Ok(config)
+        let mut loader = include::Loader::default();
+        let res = loader.load(path).context("error loading config");
+        if res.is_ok() {
+            debug!("loaded config from {path:?}");
+        }
+
+        let mut included = loader.files;
+        included.retain(|p| p != path);
+        (res, included)
     }
 
     pub fn parse(filename: &str, text: &str) -> Result<Self, knuffel::Error> {
         let _span = tracy_client::span!("Config::parse");
-        knuffel::parse(filename, text)
+        let mut config: Self = knuffel::parse(filename, text)?;
+        config.merge_bind_modes();
+        Ok(config)
+    }
+
+    /// Returns the binds of the default binding mode.
+    pub fn binds(&self) -> &Binds {
+        // Parsing always adds the default mode, but the config may have been built by hand.
+        static EMPTY: Binds = Binds(Vec::new());
+        self.mode_binds(None).unwrap_or(&EMPTY)
+    }
+
+    /// Returns the binds of a binding mode, or of the default mode for `None`.
+    pub fn mode_binds(&self, name: Option<&str>) -> Option<&Binds> {
+        self.bind_modes
+            .iter()
+            .find(|mode| mode.name.as_deref() == name)
+            .map(|mode| &mode.binds)
+    }
+
+    /// Returns the binds that apply in a binding mode, falling back to the default mode if the
+    /// mode doesn't exist.
+    pub fn active_binds(&self, mode: Option<&str>) -> &Binds {
+        self.mode_binds(mode).unwrap_or_else(|| self.binds())
+    }
+
+    /// Merges `binds` sections of the same binding mode, and puts the default mode first.
+    fn merge_bind_modes(&mut self) {
+        let mut modes: Vec<BindMode> = Vec::new();
+
+        for mode in mem::take(&mut self.bind_modes) {
+            let Some(existing) = modes.iter_mut().find(|m| m.name == mode.name) else {
+                modes.push(mode);
+                continue;
+            };
+
+            // Later sections replace binds with the same keys, like included files do.
+            for bind in mode.binds.0 {
+                let binds = &mut existing.binds.0;
+                if let Some(old) = binds
+                    .iter_mut()
+                    .find(|old| old.key == bind.key && old.prefix == bind.prefix)
+                {
+                    *old = bind;
+                } else {
+                    binds.push(bind);
+                }
+            }
+        }
+
+        let default = match modes.iter().position(|mode| mode.name.is_none()) {
+            Some(idx) => modes.remove(idx),
+            None => BindMode {
+                name: None,
+                binds: Binds::default(),
+            },
+        };
+        modes.insert(0, default);
+
+        self.bind_modes = modes;
     }
 }
 
@@ -2478,6 +2917,27 @@ impl BorderRule {
     }
 }
 
+impl BlurRule {
+    pub fn merge_with(&mut self, other: &Self) {
+        if other.off {
+            self.off = true;
+            self.on = false;
+        }
+
+        if other.on {
+            self.off = false;
+            self.on = true;
+        }
+
+        if let Some(x) = other.passes {
+            self.passes = Some(x);
+        }
+        if let Some(x) = other.radius {
+            self.radius = Some(x);
+        }
+    }
+}
+
 impl ShadowRule {
     pub fn merge_with(&mut self, other: &Self) {
         if other.off {
@@ -3011,10 +3471,21 @@ where
         node: &knuffel::ast::SpannedNode<S>,
         ctx: &mut knuffel::decode::Context<S>,
     ) -> Result<Self, DecodeError<S>> {
-        let default = Self::default().0;
-        Ok(Self(Animation::decode_node(node, ctx, default, |_, _| {
-            Ok(false)
-        })?))
+        let default = Self::default().anim;
+        let mut custom_shader = None;
+        let anim = Animation::decode_node(node, ctx, default, |child, ctx| {
+            if &**child.node_name == "custom-shader" {
+                custom_shader = parse_arg_node("custom-shader", child, ctx)?;
+                Ok(true)
+            } else {
+                Ok(false)
+            }
+        })?;
+
+        Ok(Self {
+            anim,
+            custom_shader,
+        })
     }
 }
 
@@ -3222,18 +3693,48 @@ where
     }
 }
 
-impl Animation {
-    pub fn new_off() -> Self {
-        Self {
-            off: true,
-            kind: AnimationKind::Easing(EasingParams {
-                duration_ms: 0,
-                curve: AnimationCurve::Linear,
-            }),
-        }
-    }
-
-    fn decode_node<S: knuffel::traits::ErrorSpan>(
+impl<S> knuffel::Decode<S> for MagnifierZoomAnim
+where
+    S: knuffel::traits::ErrorSpan,
+{
+    fn decode_node(
+        node: &knuffel::ast::SpannedNode<S>,
+        ctx: &mut knuffel::decode::Context<S>,
+    ) -> Result<Self, DecodeError<S>> {
+        let default = Self::default().0;
+        Ok(Self(Animation::decode_node(node, ctx, default, |_, _| {
+            Ok(false)
+        })?))
+    }
+}
+
+impl<S> knuffel::Decode<S> for InactiveDimAnim
+where
+    S: knuffel::traits::ErrorSpan,
+{
+    fn decode_node(
+        node: &knuffel::ast::SpannedNode<S>,
+        ctx: &mut knuffel::decode::Context<S>,
+    ) -> Result<Self, DecodeError<S>> {
+        let default = Self::default().0;
+        Ok(Self(Animation::decode_node(node, ctx, default, |_, _| {
+            Ok(false)
+        })?))
+    }
+}
+
+impl Animation {
+    pub fn new_off() -> Self {
+        Self {
+            off: true,
+            kind: AnimationKind::Easing(EasingParams {
+                duration_ms: 0,
+                curve: AnimationCurve::Linear,
+            }),
+        }
+    }
+
+    fn decode_node<S: knuffel::traits::ErrorSpan>(
         node: &knuffel::ast::SpannedNode<S>,
         ctx: &mut knuffel::decode::Context<S>,
         default: Self,
@@ -3566,7 +4067,7 @@ where
     }
 }
 
-impl<S> knuffel::Decode<S> for Binds
+impl<S> knuffel::Decode<S> for BindMode
 where
     S: knuffel::traits::ErrorSpan,
 {
@@ -3574,58 +4075,114 @@ where
         node: &knuffel::ast::SpannedNode<S>,
         ctx: &mut knuffel::decode::Context<S>,
     ) -> Result<Self, DecodeError<S>> {
-        expect_only_children(node, ctx);
+        if let Some(type_name) = &node.type_name {
+            ctx.emit_error(DecodeError::unexpected(
+                type_name,
+                "type name",
+                "no type name expected for this node",
+            ));
+        }
 
-        let mut seen_keys = HashSet::new();
+        let mut args = node.arguments.iter();
+        let name = args
+            .next()
+            .map(|val| knuffel::traits::DecodeScalar::decode(val, ctx))
+            .transpose()?;
+        for val in args {
+            ctx.emit_error(DecodeError::unexpected(
+                &val.literal,
+                "argument",
+                "only the binding mode name is expected",
+            ));
+        }
 
-        let mut binds = Vec::new();
+        for name in node.properties.keys() {
+            ctx.emit_error(DecodeError::unexpected(
+                name,
+                "property",
+                "no properties expected for this node",
+            ));
+        }
 
-        for child in node.children() {
-            match Bind::decode_node(child, ctx) {
-                Err(e) => {
-                    ctx.emit_error(e);
-                }
-                Ok(bind) => {
-                    if seen_keys.insert(bind.key) {
-                        binds.push(bind);
-                    } else {
-                        // ideally, this error should point to the previous instance of this keybind
-                        //
-                        // i (sodiboo) have tried to implement this in various ways:
-                        // miette!(), #[derive(Diagnostic)]
-                        // DecodeError::Custom, DecodeError::Conversion
-                        // nothing seems to work, and i suspect it's not possible.
-                        //
-                        // DecodeError is fairly restrictive.
-                        // even DecodeError::Custom just wraps a std::error::Error
-                        // and this erases all rich information from miette. (why???)
-                        //
-                        // why does knuffel do this?
-                        // from what i can tell, it doesn't even use DecodeError for much.
-                        // it only ever converts them to a Report anyways!
-                        // https://github.com/tailhook/knuffel/blob/c44c6b0c0f31ea6d1174d5d2ed41064922ea44ca/src/wrappers.rs#L55-L58
-                        //
-                        // besides like, allowing downstream users (such as us!)
-                        // to match on parse failure, i don't understand why
-                        // it doesn't just use a generic error type
-                        //
-                        // even the matching isn't consistent,
-                        // because errors can also be omitted as ctx.emit_error.
-                        // why does *that one* especially, require a DecodeError?
-                        //
-                        // anyways if you can make it format nicely, definitely do fix this
-                        ctx.emit_error(DecodeError::unexpected(
-                            &child.node_name,
-                            "keybind",
-                            "duplicate keybind",
-                        ));
-                    }
+        let binds = decode_binds(node, ctx);
+        Ok(Self { name, binds })
+    }
+}
+
+/// Decodes the binds in a `binds` section.
+fn decode_binds<S>(
+    node: &knuffel::ast::SpannedNode<S>,
+    ctx: &mut knuffel::decode::Context<S>,
+) -> Binds
+where
+    S: knuffel::traits::ErrorSpan,
+{
+    let mut seen_keys = HashSet::new();
+
+    let mut binds = Vec::new();
+    let mut nodes = Vec::new();
+
+    for child in node.children() {
+        match Bind::decode_node(child, ctx) {
+            Err(e) => {
+                ctx.emit_error(e);
+            }
+            Ok(bind) => {
+                if seen_keys.insert((bind.prefix.clone(), bind.key)) {
+                    binds.push(bind);
+                    nodes.push(child);
+                } else {
+                    // ideally, this error should point to the previous instance of this keybind
+                    //
+                    // i (sodiboo) have tried to implement this in various ways:
+                    // miette!(), #[derive(Diagnostic)]
+                    // DecodeError::Custom, DecodeError::Conversion
+                    // nothing seems to work, and i suspect it's not possible.
+                    //
+                    // DecodeError is fairly restrictive.
+                    // even DecodeError::Custom just wraps a std::error::Error
+                    // and this erases all rich information from miette. (why???)
+                    //
+                    // why does knuffel do this?
+                    // from what i can tell, it doesn't even use DecodeError for much.
+                    // it only ever converts them to a Report anyways!
+                    // https://github.com/tailhook/knuffel/blob/c44c6b0c0f31ea6d1174d5d2ed41064922ea44ca/src/wrappers.rs#L55-L58
+                    //
+                    // besides like, allowing downstream users (such as us!)
+                    // to match on parse failure, i don't understand why
+                    // it doesn't just use a generic error type
+                    //
+                    // even the matching isn't consistent,
+                    // because errors can also be omitted as ctx.emit_error.
+                    // why does *that one* especially, require a DecodeError?
+                    //
+                    // anyways if you can make it format nicely, definitely do fix this
+                    ctx.emit_error(DecodeError::unexpected(
+                        &child.node_name,
+                        "keybind",
+                        "duplicate keybind",
+                    ));
                 }
             }
         }
+    }
 
-        Ok(Self(binds))
+    // A key sequence can't continue past a bind, since that bind would always trigger first.
+    for (bind, child) in zip(&binds, nodes) {
+        let shadowed = (0..bind.prefix.len()).any(|i| {
+            let start = (bind.prefix[..i].to_vec(), bind.prefix[i]);
+            seen_keys.contains(&start)
+        });
+        if shadowed {
+            ctx.emit_error(DecodeError::unexpected(
+                &child.node_name,
+                "keybind",
+                "key sequence starts with another keybind",
+            ));
+        }
     }
+
+    Binds(binds)
 }
 
 impl<S> knuffel::Decode<S> for Bind
@@ -3652,10 +4209,25 @@ where
             ));
         }
 
-        let key = node
-            .node_name
-            .parse::<Key>()
+        let mut keys = split_key_sequence(&node.node_name)
+            .into_iter()
+            .map(|key| key.parse::<Key>())
+            .collect::<Result<Vec<_>, _>>()
             .map_err(|e| DecodeError::conversion(&node.node_name, e.wrap_err("invalid keybind")))?;
+        let key = keys.pop().unwrap();
+        let prefix = keys;
+
+        if !prefix.is_empty()
+            && !prefix
+                .iter()
+                .chain([&key])
+                .all(|key| matches!(key.trigger, Trigger::Keysym(_)))
+        {
+            return Err(DecodeError::conversion(
+                &node.node_name,
+                miette!("key sequences can only contain keyboard keys"),
+            ));
+        }
 
         let mut repeat = true;
         let mut cooldown = None;
@@ -3699,6 +4271,7 @@ where
         // That way, the parent can handle the existence of duplicate keybinds,
         // even if their contents are not valid.
         let dummy = Self {
+            prefix: prefix.clone(),
             key,
             action: Action::Spawn(vec![]),
             repeat: true,
@@ -3735,6 +4308,7 @@ where
                     }
 
                     Ok(Self {
+                        prefix,
                         key,
                         action,
                         repeat,
@@ -3759,6 +4333,83 @@ where
     }
 }
 
+impl<S> knuffel::Decode<S> for HotCornerAction
+where
+    S: knuffel::traits::ErrorSpan,
+{
+    fn decode_node(
+        node: &knuffel::ast::SpannedNode<S>,
+        ctx: &mut knuffel::decode::Context<S>,
+    ) -> Result<Self, DecodeError<S>> {
+        if let Some(type_name) = &node.type_name {
+            ctx.emit_error(DecodeError::unexpected(
+                type_name,
+                "type name",
+                "no type name expected for this node",
+            ));
+        }
+
+        for val in node.arguments.iter() {
+            ctx.emit_error(DecodeError::unexpected(
+                &val.literal,
+                "argument",
+                "no arguments expected for this node",
+            ));
+        }
+
+        let mut delay = Duration::ZERO;
+        let mut pressure = 0.;
+        let mut velocity = 0.;
+        for (name, val) in &node.properties {
+            match &***name {
+                "delay-ms" => {
+                    delay = Duration::from_millis(knuffel::traits::DecodeScalar::decode(val, ctx)?);
+                }
+                "pressure" => {
+                    let value: FloatOrInt<0, 65535> =
+                        knuffel::traits::DecodeScalar::decode(val, ctx)?;
+                    pressure = value.0;
+                }
+                "velocity" => {
+                    let value: FloatOrInt<0, 1_000_000> =
+                        knuffel::traits::DecodeScalar::decode(val, ctx)?;
+                    velocity = value.0;
+                }
+                name_str => {
+                    ctx.emit_error(DecodeError::unexpected(
+                        name,
+                        "property",
+                        format!("unexpected property `{}`", name_str.escape_default()),
+                    ));
+                }
+            }
+        }
+
+        let mut children = node.children();
+        let Some(child) = children.next() else {
+            return Err(DecodeError::missing(
+                node,
+                "expected an action for this hot corner",
+            ));
+        };
+        for unwanted_child in children {
+            ctx.emit_error(DecodeError::unexpected(
+                unwanted_child,
+                "node",
+                "only one action is allowed per hot corner",
+            ));
+        }
+
+        let action = Action::decode_node(child, ctx)?;
+        Ok(Self {
+            action,
+            delay,
+            pressure,
+            velocity,
+        })
+    }
+}
+
 impl FromStr for ModKey {
     type Err = miette::Error;
 
@@ -3775,6 +4426,27 @@ impl FromStr for ModKey {
     }
 }
 
+/// Splits a bind name like `Mod+X Ctrl+C` into the keys of the sequence.
+///
+/// Whitespace around `+` is part of a key rather than a separator.
+fn split_key_sequence(s: &str) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+
+    for part in s.split_whitespace() {
+        match keys.last_mut() {
+            Some(last) if last.ends_with('+') || part.starts_with('+') => last.push_str(part),
+            _ => keys.push(part.to_owned()),
+        }
+    }
+
+    // Keep the error for an empty name coming from Key::from_str().
+    if keys.is_empty() {
+        keys.push(s.to_owned());
+    }
+
+    keys
+}
+
 impl FromStr for Key {
     type Err = miette::Error;
 
@@ -3835,6 +4507,12 @@ impl FromStr for Key {
             Trigger::TouchpadScrollLeft
         } else if key.eq_ignore_ascii_case("TouchpadScrollRight") {
             Trigger::TouchpadScrollRight
+        } else if key.eq_ignore_ascii_case("TouchpadPinchIn") {
+            Trigger::TouchpadPinchIn
+        } else if key.eq_ignore_ascii_case("TouchpadPinchOut") {
+            Trigger::TouchpadPinchOut
+        } else if let Some(trigger) = parse_gesture_trigger(key) {
+            trigger?
         } else {
             let keysym = keysym_from_name(key, KEYSYM_CASE_INSENSITIVE);
             if keysym.raw() == KEY_NoSymbol {
@@ -3847,6 +4525,53 @@ impl FromStr for Key {
     }
 }
 
+/// Parses finger-count gesture triggers like `TouchpadSwipe3Left` or `TouchpadHold4`.
+fn parse_gesture_trigger(key: &str) -> Option<miette::Result<Trigger>> {
+    let lower = key.to_ascii_lowercase();
+    let (prefix, rest) = ["touchpadswipe", "touchpadhold", "touchscreenswipe"]
+        .into_iter()
+        .find_map(|prefix| Some((prefix, lower.strip_prefix(prefix)?)))?;
+
+    let split = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let (fingers, rest) = rest.split_at(split);
+    let fingers = match fingers.parse() {
+        Ok(fingers @ 3..=5) => fingers,
+        _ => {
+            return Some(Err(miette!(
+                "invalid key: {key}, finger count must be 3, 4 or 5"
+            )))
+        }
+    };
+
+    if prefix == "touchpadhold" {
+        if !rest.is_empty() {
+            return Some(Err(miette!("invalid key: {key}")));
+        }
+        return Some(Ok(Trigger::TouchpadHold { fingers }));
+    }
+
+    let direction = match rest {
+        "up" => SwipeDirection::Up,
+        "down" => SwipeDirection::Down,
+        "left" => SwipeDirection::Left,
+        "right" => SwipeDirection::Right,
+        _ => {
+            return Some(Err(miette!(
+                "invalid key: {key}, swipe direction can be Up, Down, Left or Right"
+            )))
+        }
+    };
+
+    let trigger = if prefix == "touchpadswipe" {
+        Trigger::TouchpadSwipe { fingers, direction }
+    } else {
+        Trigger::TouchscreenSwipe { fingers, direction }
+    };
+    Some(Ok(trigger))
+}
+
 impl FromStr for ClickMethod {
     type Err = miette::Error;
 
@@ -4027,6 +4752,7 @@ mod tests {
                 mode "1920x1080@144"
                 variable-refresh-rate on-demand=true
                 background-color "rgba(25, 25, 102, 1.0)"
+                icc-profile "~/.local/share/icc/edp.icc"
             }
 
             layout {
@@ -4069,6 +4795,8 @@ mod tests {
 
                 gaps 8
 
+                inactive-dim 0.3
+
                 struts {
                     left 1
                     right 2
@@ -4119,6 +4847,11 @@ mod tests {
                 }
 
                 window-open { off; }
+
+                magnifier-zoom {
+                    duration-ms 150
+                    curve "ease-out-cubic"
+                }
             }
 
             gestures {
@@ -4126,6 +4859,18 @@ mod tests {
                     trigger-width 10
                     max-speed 50
                 }
+                hot-corners {
+                    top-right delay-ms=250 velocity=2000 { toggle-overview; }
+                    bottom-edge pressure=100 { spawn "nwg-dock"; }
+                }
+                default-gestures {
+                    workspace-switch { off; }
+                }
+            }
+
+            magnifier {
+                max-zoom 4
+                follow-mode "centered"
             }
 
             environment {
@@ -4133,8 +4878,17 @@ mod tests {
                 DISPLAY null
             }
 
+            bevy-capture {
+                capture-fps 120
+                prefer-dmabuf false
+                outputs "DP-1" "HDMI-A-1"
+                max-memory-usage 0.5
+            }
+
             window-rule {
                 match app-id=".*alacritty"
+                match tag="^scratch$" pid=1234 on-workspace="chat" on-output="HDMI-A-1"
+                match is-xwayland=true is-fullscreen=false has-parent=true is-modal=false
                 exclude title="~"
                 exclude is-active=true is-focused=false
 
@@ -4146,6 +4900,9 @@ mod tests {
                 default-window-height { fixed 500; }
                 default-column-display "tabbed"
                 default-floating-position x=100 y=-200 relative-to="bottom-left"
+                default-keyboard-layout "us"
+                allow-tearing true
+                inactive-saturation 0.5
 
                 focus-ring {
                     off
@@ -4160,11 +4917,19 @@ mod tests {
                 tab-indicator {
                     active-color "#f00"
                 }
+
+                blur {
+                    on
+                    passes 2
+                }
             }
 
             layer-rule {
                 match namespace="^notifications$"
                 block-out-from "screencast"
+                blur {
+                    radius 8
+                }
             }
 
             binds {
@@ -4182,6 +4947,14 @@ mod tests {
                 Mod+Shift+1 { focus-workspace "workspace-1"; }
                 Mod+Shift+E allow-inhibiting=false { quit skip-confirmation=true; }
                 Mod+WheelScrollDown cooldown-ms=150 { focus-workspace-down; }
+                "Mod+X Mod+C" { spawn "code"; }
+                Mod+R { enter-bind-mode "resize"; }
+                TouchpadSwipe4Left { focus-column-left; }
+            }
+
+            binds "resize" {
+                Minus { set-column-width "-10%"; }
+                Escape { exit-bind-mode; }
             }
 
             switch-events {
@@ -4403,6 +5176,11 @@ mod tests {
                             },
                         ),
                         backdrop_color: None,
+                        hot_corners: None,
+                        icc_profile: Some(
+                            "~/.local/share/icc/edp.icc",
+                        ),
+                        custom_shader: None,
                     },
                 ],
             ),
@@ -4576,6 +5354,15 @@ mod tests {
                         },
                     ),
                 },
+                picture_in_picture: PictureInPicture {
+                    width: FloatOrInt(
+                        480.0,
+                    ),
+                    corner: BottomRight,
+                    margin: FloatOrInt(
+                        16.0,
+                    ),
+                },
                 preset_column_widths: [
                     Proportion(
                         0.25,
@@ -4634,6 +5421,15 @@ mod tests {
                         0.0,
                     ),
                 },
+                inactive_dim: FloatOrInt(
+                    0.3,
+                ),
+                inactive_saturation: FloatOrInt(
+                    1.0,
+                ),
+                inactive_brightness: FloatOrInt(
+                    0.0,
+                ),
                 background_color: Color {
                     r: 0.25,
                     g: 0.25,
@@ -4665,8 +5461,8 @@ mod tests {
                 slowdown: FloatOrInt(
                     2.0,
                 ),
-                workspace_switch: WorkspaceSwitchAnim(
-                    Animation {
+                workspace_switch: WorkspaceSwitchAnim {
+                    anim: Animation {
                         off: false,
                         kind: Spring(
                             SpringParams {
@@ -4676,7 +5472,8 @@ mod tests {
                             },
                         ),
                     },
-                ),
+                    custom_shader: None,
+                },
                 window_open: WindowOpenAnim {
                     anim: Animation {
                         off: true,
@@ -4772,6 +5569,28 @@ mod tests {
                         ),
                     },
                 ),
+                magnifier_zoom: MagnifierZoomAnim(
+                    Animation {
+                        off: false,
+                        kind: Easing(
+                            EasingParams {
+                                duration_ms: 150,
+                                curve: EaseOutCubic,
+                            },
+                        ),
+                    },
+                ),
+                inactive_dim: InactiveDimAnim(
+                    Animation {
+                        off: false,
+                        kind: Easing(
+                            EasingParams {
+                                duration_ms: 200,
+                                curve: EaseOutQuad,
+                            },
+                        ),
+                    },
+                ),
             },
             gestures: Gestures {
                 dnd_edge_view_scroll: DndEdgeViewScroll {
@@ -4794,6 +5613,44 @@ mod tests {
                 },
                 hot_corners: HotCorners {
                     off: false,
+                    top_left: None,
+                    top_right: Some(
+                        HotCornerAction {
+                            action: ToggleOverview,
+                            delay: 250ms,
+                            pressure: 0.0,
+                            velocity: 2000.0,
+                        },
+                    ),
+                    bottom_left: None,
+                    bottom_right: None,
+                    top_edge: None,
+                    bottom_edge: Some(
+                        HotCornerAction {
+                            action: Spawn(
+                                [
+                                    "nwg-dock",
+                                ],
+                            ),
+                            delay: 0ns,
+                            pressure: 100.0,
+                            velocity: 0.0,
+                        },
+                    ),
+                    left_edge: None,
+                    right_edge: None,
+                },
+                default_gestures: DefaultGestures {
+                    off: false,
+                    workspace_switch: DefaultGesture {
+                        off: true,
+                    },
+                    view_scroll: DefaultGesture {
+                        off: false,
+                    },
+                    overview: DefaultGesture {
+                        off: false,
+                    },
                 },
             },
             overview: Overview {
@@ -4830,6 +5687,12 @@ mod tests {
                     },
                 },
             },
+            magnifier: Magnifier {
+                max_zoom: FloatOrInt(
+                    4.0,
+                ),
+                follow_mode: Centered,
+            },
             environment: Environment(
                 [
                     EnvironmentVariable {
@@ -4848,6 +5711,21 @@ mod tests {
                 off: false,
                 path: "xwayland-satellite",
             },
+            bevy_capture: BevyCapture {
+                capture_fps: FloatOrInt(
+                    120.0,
+                ),
+                prefer_dmabuf: false,
+                outputs: [
+                    "DP-1",
+                    "HDMI-A-1",
+                ],
+                damage_tracking: true,
+                adaptive_performance: true,
+                max_memory_usage: FloatOrInt(
+                    0.5,
+                ),
+            },
             window_rules: [
                 WindowRule {
                     matches: [
@@ -4860,12 +5738,81 @@ mod tests {
                                 ),
                             ),
                             title: None,
+                            tag: None,
+                            is_active: None,
+                            is_focused: None,
+                            is_active_in_column: None,
+                            is_floating: None,
+                            scratchpad: None,
+                            is_window_cast_target: None,
+                            is_urgent: None,
+                            pid: None,
+                            on_workspace: None,
+                            on_output: None,
+                            is_xwayland: None,
+                            is_fullscreen: None,
+                            has_parent: None,
+                            is_modal: None,
+                            at_startup: None,
+                        },
+                        Match {
+                            app_id: None,
+                            title: None,
+                            tag: Some(
+                                RegexEq(
+                                    Regex(
+                                        "^scratch$",
+                                    ),
+                                ),
+                            ),
+                            is_active: None,
+                            is_focused: None,
+                            is_active_in_column: None,
+                            is_floating: None,
+                            scratchpad: None,
+                            is_window_cast_target: None,
+                            is_urgent: None,
+                            pid: Some(
+                                1234,
+                            ),
+                            on_workspace: Some(
+                                "chat",
+                            ),
+                            on_output: Some(
+                                "HDMI-A-1",
+                            ),
+                            is_xwayland: None,
+                            is_fullscreen: None,
+                            has_parent: None,
+                            is_modal: None,
+                            at_startup: None,
+                        },
+                        Match {
+                            app_id: None,
+                            title: None,
+                            tag: None,
                             is_active: None,
                             is_focused: None,
                             is_active_in_column: None,
                             is_floating: None,
+                            scratchpad: None,
                             is_window_cast_target: None,
                             is_urgent: None,
+                            pid: None,
+                            on_workspace: None,
+                            on_output: None,
+                            is_xwayland: Some(
+                                true,
+                            ),
+                            is_fullscreen: Some(
+                                false,
+                            ),
+                            has_parent: Some(
+                                true,
+                            ),
+                            is_modal: Some(
+                                false,
+                            ),
                             at_startup: None,
                         },
                     ],
@@ -4879,17 +5826,27 @@ mod tests {
                                     ),
                                 ),
                             ),
+                            tag: None,
                             is_active: None,
                             is_focused: None,
                             is_active_in_column: None,
                             is_floating: None,
+                            scratchpad: None,
                             is_window_cast_target: None,
                             is_urgent: None,
+                            pid: None,
+                            on_workspace: None,
+                            on_output: None,
+                            is_xwayland: None,
+                            is_fullscreen: None,
+                            has_parent: None,
+                            is_modal: None,
                             at_startup: None,
                         },
                         Match {
                             app_id: None,
                             title: None,
+                            tag: None,
                             is_active: Some(
                                 true,
                             ),
@@ -4898,8 +5855,16 @@ mod tests {
                             ),
                             is_active_in_column: None,
                             is_floating: None,
+                            scratchpad: None,
                             is_window_cast_target: None,
                             is_urgent: None,
+                            pid: None,
+                            on_workspace: None,
+                            on_output: None,
+                            is_xwayland: None,
+                            is_fullscreen: None,
+                            has_parent: None,
+                            is_modal: None,
                             at_startup: None,
                         },
                     ],
@@ -4926,6 +5891,7 @@ mod tests {
                     open_floating: Some(
                         false,
                     ),
+                    open_sticky: None,
                     open_focused: Some(
                         true,
                     ),
@@ -4988,13 +5954,31 @@ mod tests {
                         inactive_gradient: None,
                         urgent_gradient: None,
                     },
+                    blur: BlurRule {
+                        off: false,
+                        on: true,
+                        passes: Some(
+                            2,
+                        ),
+                        radius: None,
+                    },
                     draw_border_with_background: None,
                     opacity: None,
+                    inactive_dim: None,
+                    inactive_saturation: Some(
+                        FloatOrInt(
+                            0.5,
+                        ),
+                    ),
+                    inactive_brightness: None,
                     geometry_corner_radius: None,
                     clip_to_geometry: None,
                     baba_is_float: None,
                     block_out_from: None,
                     variable_refresh_rate: None,
+                    allow_tearing: Some(
+                        true,
+                    ),
                     default_column_display: Some(
                         Tabbed,
                     ),
@@ -5011,6 +5995,9 @@ mod tests {
                     ),
                     scroll_factor: None,
                     tiled_state: None,
+                    default_keyboard_layout: Some(
+                        "us",
+                    ),
                 },
             ],
             layer_rules: [
@@ -5045,262 +6032,405 @@ mod tests {
                     geometry_corner_radius: None,
                     place_within_backdrop: None,
                     baba_is_float: None,
-                },
-            ],
-            binds: Binds(
-                [
-                    Bind {
-                        key: Key {
-                            trigger: Keysym(
-                                XK_Escape,
-                            ),
-                            modifiers: Modifiers(
-                                COMPOSITOR,
-                            ),
-                        },
-                        action: ToggleKeyboardShortcutsInhibit,
-                        repeat: true,
-                        cooldown: None,
-                        allow_when_locked: false,
-                        allow_inhibiting: false,
-                        hotkey_overlay_title: Some(
-                            Some(
-                                "Inhibit",
-                            ),
-                        ),
-                    },
-                    Bind {
-                        key: Key {
-                            trigger: Keysym(
-                                XK_Escape,
-                            ),
-                            modifiers: Modifiers(
-                                SHIFT | COMPOSITOR,
-                            ),
-                        },
-                        action: ToggleKeyboardShortcutsInhibit,
-                        repeat: true,
-                        cooldown: None,
-                        allow_when_locked: false,
-                        allow_inhibiting: false,
-                        hotkey_overlay_title: None,
-                    },
-                    Bind {
-                        key: Key {
-                            trigger: Keysym(
-                                XK_t,
-                            ),
-                            modifiers: Modifiers(
-                                COMPOSITOR,
-                            ),
-                        },
-                        action: Spawn(
-                            [
-                                "alacritty",
-                            ],
-                        ),
-                        repeat: true,
-                        cooldown: None,
-                        allow_when_locked: true,
-                        allow_inhibiting: true,
-                        hotkey_overlay_title: None,
-                    },
-                    Bind {
-                        key: Key {
-                            trigger: Keysym(
-                                XK_q,
-                            ),
-                            modifiers: Modifiers(
-                                COMPOSITOR,
-                            ),
-                        },
-                        action: CloseWindow,
-                        repeat: true,
-                        cooldown: None,
-                        allow_when_locked: false,
-                        allow_inhibiting: true,
-                        hotkey_overlay_title: Some(
-                            None,
-                        ),
-                    },
-                    Bind {
-                        key: Key {
-                            trigger: Keysym(
-                                XK_h,
-                            ),
-                            modifiers: Modifiers(
-                                SHIFT | COMPOSITOR,
-                            ),
-                        },
-                        action: FocusMonitorLeft,
-                        repeat: true,
-                        cooldown: None,
-                        allow_when_locked: false,
-                        allow_inhibiting: true,
-                        hotkey_overlay_title: None,
-                    },
-                    Bind {
-                        key: Key {
-                            trigger: Keysym(
-                                XK_o,
-                            ),
-                            modifiers: Modifiers(
-                                SHIFT | COMPOSITOR,
-                            ),
-                        },
-                        action: FocusMonitor(
-                            "eDP-1",
-                        ),
-                        repeat: true,
-                        cooldown: None,
-                        allow_when_locked: false,
-                        allow_inhibiting: true,
-                        hotkey_overlay_title: None,
-                    },
-                    Bind {
-                        key: Key {
-                            trigger: Keysym(
-                                XK_l,
-                            ),
-                            modifiers: Modifiers(
-                                CTRL | SHIFT | COMPOSITOR,
-                            ),
-                        },
-                        action: MoveWindowToMonitorRight,
-                        repeat: true,
-                        cooldown: None,
-                        allow_when_locked: false,
-                        allow_inhibiting: true,
-                        hotkey_overlay_title: None,
-                    },
-                    Bind {
-                        key: Key {
-                            trigger: Keysym(
-                                XK_o,
-                            ),
-                            modifiers: Modifiers(
-                                CTRL | ALT | COMPOSITOR,
-                            ),
-                        },
-                        action: MoveWindowToMonitor(
-                            "eDP-1",
-                        ),
-                        repeat: true,
-                        cooldown: None,
-                        allow_when_locked: false,
-                        allow_inhibiting: true,
-                        hotkey_overlay_title: None,
-                    },
-                    Bind {
-                        key: Key {
-                            trigger: Keysym(
-                                XK_p,
-                            ),
-                            modifiers: Modifiers(
-                                CTRL | ALT | COMPOSITOR,
-                            ),
-                        },
-                        action: MoveColumnToMonitor(
-                            "DP-1",
-                        ),
-                        repeat: true,
-                        cooldown: None,
-                        allow_when_locked: false,
-                        allow_inhibiting: true,
-                        hotkey_overlay_title: None,
-                    },
-                    Bind {
-                        key: Key {
-                            trigger: Keysym(
-                                XK_comma,
-                            ),
-                            modifiers: Modifiers(
-                                COMPOSITOR,
-                            ),
-                        },
-                        action: ConsumeWindowIntoColumn,
-                        repeat: true,
-                        cooldown: None,
-                        allow_when_locked: false,
-                        allow_inhibiting: true,
-                        hotkey_overlay_title: None,
-                    },
-                    Bind {
-                        key: Key {
-                            trigger: Keysym(
-                                XK_1,
-                            ),
-                            modifiers: Modifiers(
-                                COMPOSITOR,
-                            ),
-                        },
-                        action: FocusWorkspace(
-                            Index(
-                                1,
-                            ),
-                        ),
-                        repeat: true,
-                        cooldown: None,
-                        allow_when_locked: false,
-                        allow_inhibiting: true,
-                        hotkey_overlay_title: None,
-                    },
-                    Bind {
-                        key: Key {
-                            trigger: Keysym(
-                                XK_1,
-                            ),
-                            modifiers: Modifiers(
-                                SHIFT | COMPOSITOR,
-                            ),
-                        },
-                        action: FocusWorkspace(
-                            Name(
-                                "workspace-1",
-                            ),
-                        ),
-                        repeat: true,
-                        cooldown: None,
-                        allow_when_locked: false,
-                        allow_inhibiting: true,
-                        hotkey_overlay_title: None,
-                    },
-                    Bind {
-                        key: Key {
-                            trigger: Keysym(
-                                XK_e,
-                            ),
-                            modifiers: Modifiers(
-                                SHIFT | COMPOSITOR,
-                            ),
-                        },
-                        action: Quit(
-                            true,
-                        ),
-                        repeat: true,
-                        cooldown: None,
-                        allow_when_locked: false,
-                        allow_inhibiting: false,
-                        hotkey_overlay_title: None,
-                    },
-                    Bind {
-                        key: Key {
-                            trigger: WheelScrollDown,
-                            modifiers: Modifiers(
-                                COMPOSITOR,
+                    blur: BlurRule {
+                        off: false,
+                        on: false,
+                        passes: None,
+                        radius: Some(
+                            FloatOrInt(
+                                8.0,
                             ),
-                        },
-                        action: FocusWorkspaceDown,
-                        repeat: true,
-                        cooldown: Some(
-                            150ms,
                         ),
-                        allow_when_locked: false,
-                        allow_inhibiting: true,
-                        hotkey_overlay_title: None,
                     },
-                ],
-            ),
+                },
+            ],
+            bind_modes: [
+                BindMode {
+                    name: None,
+                    binds: Binds(
+                        [
+                            Bind {
+                                prefix: [],
+                                key: Key {
+                                    trigger: Keysym(
+                                        XK_Escape,
+                                    ),
+                                    modifiers: Modifiers(
+                                        COMPOSITOR,
+                                    ),
+                                },
+                                action: ToggleKeyboardShortcutsInhibit,
+                                repeat: true,
+                                cooldown: None,
+                                allow_when_locked: false,
+                                allow_inhibiting: false,
+                                hotkey_overlay_title: Some(
+                                    Some(
+                                        "Inhibit",
+                                    ),
+                                ),
+                            },
+                            Bind {
+                                prefix: [],
+                                key: Key {
+                                    trigger: Keysym(
+                                        XK_Escape,
+                                    ),
+                                    modifiers: Modifiers(
+                                        SHIFT | COMPOSITOR,
+                                    ),
+                                },
+                                action: ToggleKeyboardShortcutsInhibit,
+                                repeat: true,
+                                cooldown: None,
+                                allow_when_locked: false,
+                                allow_inhibiting: false,
+                                hotkey_overlay_title: None,
+                            },
+                            Bind {
+                                prefix: [],
+                                key: Key {
+                                    trigger: Keysym(
+                                        XK_t,
+                                    ),
+                                    modifiers: Modifiers(
+                                        COMPOSITOR,
+                                    ),
+                                },
+                                action: Spawn(
+                                    [
+                                        "alacritty",
+                                    ],
+                                ),
+                                repeat: true,
+                                cooldown: None,
+                                allow_when_locked: true,
+                                allow_inhibiting: true,
+                                hotkey_overlay_title: None,
+                            },
+                            Bind {
+                                prefix: [],
+                                key: Key {
+                                    trigger: Keysym(
+                                        XK_q,
+                                    ),
+                                    modifiers: Modifiers(
+                                        COMPOSITOR,
+                                    ),
+                                },
+                                action: CloseWindow,
+                                repeat: true,
+                                cooldown: None,
+                                allow_when_locked: false,
+                                allow_inhibiting: true,
+                                hotkey_overlay_title: Some(
+                                    None,
+                                ),
+                            },
+                            Bind {
+                                prefix: [],
+                                key: Key {
+                                    trigger: Keysym(
+                                        XK_h,
+                                    ),
+                                    modifiers: Modifiers(
+                                        SHIFT | COMPOSITOR,
+                                    ),
+                                },
+                                action: FocusMonitorLeft,
+                                repeat: true,
+                                cooldown: None,
+                                allow_when_locked: false,
+                                allow_inhibiting: true,
+                                hotkey_overlay_title: None,
+                            },
+                            Bind {
+                                prefix: [],
+                                key: Key {
+                                    trigger: Keysym(
+                                        XK_o,
+                                    ),
+                                    modifiers: Modifiers(
+                                        SHIFT | COMPOSITOR,
+                                    ),
+                                },
+                                action: FocusMonitor(
+                                    "eDP-1",
+                                ),
+                                repeat: true,
+                                cooldown: None,
+                                allow_when_locked: false,
+                                allow_inhibiting: true,
+                                hotkey_overlay_title: None,
+                            },
+                            Bind {
+                                prefix: [],
+                                key: Key {
+                                    trigger: Keysym(
+                                        XK_l,
+                                    ),
+                                    modifiers: Modifiers(
+                                        CTRL | SHIFT | COMPOSITOR,
+                                    ),
+                                },
+                                action: MoveWindowToMonitorRight,
+                                repeat: true,
+                                cooldown: None,
+                                allow_when_locked: false,
+                                allow_inhibiting: true,
+                                hotkey_overlay_title: None,
+                            },
+                            Bind {
+                                prefix: [],
+                                key: Key {
+                                    trigger: Keysym(
+                                        XK_o,
+                                    ),
+                                    modifiers: Modifiers(
+                                        CTRL | ALT | COMPOSITOR,
+                                    ),
+                                },
+                                action: MoveWindowToMonitor(
+                                    "eDP-1",
+                                ),
+                                repeat: true,
+                                cooldown: None,
+                                allow_when_locked: false,
+                                allow_inhibiting: true,
+                                hotkey_overlay_title: None,
+                            },
+                            Bind {
+                                prefix: [],
+                                key: Key {
+                                    trigger: Keysym(
+                                        XK_p,
+                                    ),
+                                    modifiers: Modifiers(
+                                        CTRL | ALT | COMPOSITOR,
+                                    ),
+                                },
+                                action: MoveColumnToMonitor(
+                                    "DP-1",
+                                ),
+                                repeat: true,
+                                cooldown: None,
+                                allow_when_locked: false,
+                                allow_inhibiting: true,
+                                hotkey_overlay_title: None,
+                            },
+                            Bind {
+                                prefix: [],
+                                key: Key {
+                                    trigger: Keysym(
+                                        XK_comma,
+                                    ),
+                                    modifiers: Modifiers(
+                                        COMPOSITOR,
+                                    ),
+                                },
+                                action: ConsumeWindowIntoColumn,
+                                repeat: true,
+                                cooldown: None,
+                                allow_when_locked: false,
+                                allow_inhibiting: true,
+                                hotkey_overlay_title: None,
+                            },
+                            Bind {
+                                prefix: [],
+                                key: Key {
+                                    trigger: Keysym(
+                                        XK_1,
+                                    ),
+                                    modifiers: Modifiers(
+                                        COMPOSITOR,
+                                    ),
+                                },
+                                action: FocusWorkspace(
+                                    Index(
+                                        1,
+                                    ),
+                                ),
+                                repeat: true,
+                                cooldown: None,
+                                allow_when_locked: false,
+                                allow_inhibiting: true,
+                                hotkey_overlay_title: None,
+                            },
+                            Bind {
+                                prefix: [],
+                                key: Key {
+                                    trigger: Keysym(
+                                        XK_1,
+                                    ),
+                                    modifiers: Modifiers(
+                                        SHIFT | COMPOSITOR,
+                                    ),
+                                },
+                                action: FocusWorkspace(
+                                    Name(
+                                        "workspace-1",
+                                    ),
+                                ),
+                                repeat: true,
+                                cooldown: None,
+                                allow_when_locked: false,
+                                allow_inhibiting: true,
+                                hotkey_overlay_title: None,
+                            },
+                            Bind {
+                                prefix: [],
+                                key: Key {
+                                    trigger: Keysym(
+                                        XK_e,
+                                    ),
+                                    modifiers: Modifiers(
+                                        SHIFT | COMPOSITOR,
+                                    ),
+                                },
+                                action: Quit(
+                                    true,
+                                ),
+                                repeat: true,
+                                cooldown: None,
+                                allow_when_locked: false,
+                                allow_inhibiting: false,
+                                hotkey_overlay_title: None,
+                            },
+                            Bind {
+                                prefix: [],
+                                key: Key {
+                                    trigger: WheelScrollDown,
+                                    modifiers: Modifiers(
+                                        COMPOSITOR,
+                                    ),
+                                },
+                                action: FocusWorkspaceDown,
+                                repeat: true,
+                                cooldown: Some(
+                                    150ms,
+                                ),
+                                allow_when_locked: false,
+                                allow_inhibiting: true,
+                                hotkey_overlay_title: None,
+                            },
+                            Bind {
+                                prefix: [
+                                    Key {
+                                        trigger: Keysym(
+                                            XK_x,
+                                        ),
+                                        modifiers: Modifiers(
+                                            COMPOSITOR,
+                                        ),
+                                    },
+                                ],
+                                key: Key {
+                                    trigger: Keysym(
+                                        XK_c,
+                                    ),
+                                    modifiers: Modifiers(
+                                        COMPOSITOR,
+                                    ),
+                                },
+                                action: Spawn(
+                                    [
+                                        "code",
+                                    ],
+                                ),
+                                repeat: true,
+                                cooldown: None,
+                                allow_when_locked: false,
+                                allow_inhibiting: true,
+                                hotkey_overlay_title: None,
+                            },
+                            Bind {
+                                prefix: [],
+                                key: Key {
+                                    trigger: Keysym(
+                                        XK_r,
+                                    ),
+                                    modifiers: Modifiers(
+                                        COMPOSITOR,
+                                    ),
+                                },
+                                action: EnterBindMode(
+                                    "resize",
+                                ),
+                                repeat: true,
+                                cooldown: None,
+                                allow_when_locked: false,
+                                allow_inhibiting: true,
+                                hotkey_overlay_title: None,
+                            },
+                            Bind {
+                                prefix: [],
+                                key: Key {
+                                    trigger: TouchpadSwipe {
+                                        fingers: 4,
+                                        direction: Left,
+                                    },
+                                    modifiers: Modifiers(
+                                        0x0,
+                                    ),
+                                },
+                                action: FocusColumnLeft,
+                                repeat: true,
+                                cooldown: None,
+                                allow_when_locked: false,
+                                allow_inhibiting: true,
+                                hotkey_overlay_title: None,
+                            },
+                        ],
+                    ),
+                },
+                BindMode {
+                    name: Some(
+                        "resize",
+                    ),
+                    binds: Binds(
+                        [
+                            Bind {
+                                prefix: [],
+                                key: Key {
+                                    trigger: Keysym(
+                                        XK_minus,
+                                    ),
+                                    modifiers: Modifiers(
+                                        0x0,
+                                    ),
+                                },
+                                action: SetColumnWidth(
+                                    AdjustProportion(
+                                        -10.0,
+                                    ),
+                                ),
+                                repeat: true,
+                                cooldown: None,
+                                allow_when_locked: false,
+                                allow_inhibiting: true,
+                                hotkey_overlay_title: None,
+                            },
+                            Bind {
+                                prefix: [],
+                                key: Key {
+                                    trigger: Keysym(
+                                        XK_Escape,
+                                    ),
+                                    modifiers: Modifiers(
+                                        0x0,
+                                    ),
+                                },
+                                action: ExitBindMode,
+                                repeat: true,
+                                cooldown: None,
+                                allow_when_locked: false,
+                                allow_inhibiting: true,
+                                hotkey_overlay_title: None,
+                            },
+                        ],
+                    ),
+                },
+            ],
             switch_events: SwitchBinds {
                 lid_open: None,
                 lid_close: None,
@@ -5368,6 +6498,7 @@ mod tests {
                     open_on_output: None,
                 },
             ],
+            includes: [],
         }
         "#);
     }
@@ -5564,6 +6695,172 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_gesture_triggers() {
+        assert_eq!(
+            "Mod+TouchpadSwipe3Left".parse::<Key>().unwrap(),
+            Key {
+                trigger: Trigger::TouchpadSwipe {
+                    fingers: 3,
+                    direction: SwipeDirection::Left
+                },
+                modifiers: Modifiers::COMPOSITOR
+            },
+        );
+        assert_eq!(
+            "touchscreenswipe4up".parse::<Key>().unwrap().trigger,
+            Trigger::TouchscreenSwipe {
+                fingers: 4,
+                direction: SwipeDirection::Up
+            },
+        );
+        assert_eq!(
+            "TouchpadHold5".parse::<Key>().unwrap().trigger,
+            Trigger::TouchpadHold { fingers: 5 },
+        );
+        assert_eq!(
+            "TouchpadPinchIn".parse::<Key>().unwrap().trigger,
+            Trigger::TouchpadPinchIn,
+        );
+
+        assert!("TouchpadSwipe2Left".parse::<Key>().is_err());
+        assert!("TouchpadSwipe3".parse::<Key>().is_err());
+        assert!("TouchpadSwipe3Sideways".parse::<Key>().is_err());
+        assert!("TouchpadHold4Up".parse::<Key>().is_err());
+        assert!("TouchscreenSwipeLeft".parse::<Key>().is_err());
+    }
+
+    #[test]
+    fn hot_corner_default() {
+        use niri_ipc::HotCorner;
+
+        let hot_corners = HotCorners::default();
+        assert_eq!(
+            hot_corners.action(HotCorner::TopLeft).unwrap().action,
+            Action::ToggleOverview
+        );
+        assert_eq!(hot_corners.action(HotCorner::TopRight), None);
+
+        let config = do_parse(
+            r#"
+            gestures {
+                hot-corners {
+                    right-edge { focus-column-right; }
+                }
+            }
+            "#,
+        );
+        let hot_corners = &config.gestures.hot_corners;
+        assert_eq!(hot_corners.action(HotCorner::TopLeft), None);
+        assert_eq!(
+            hot_corners.action(HotCorner::RightEdge).unwrap().action,
+            Action::FocusColumnRight
+        );
+
+        let config = do_parse(
+            r#"
+            gestures {
+                hot-corners {
+                    off
+                }
+            }
+            "#,
+        );
+        assert_eq!(config.gestures.hot_corners.action(HotCorner::TopLeft), None);
+    }
+
+    #[test]
+    fn parse_key_sequences() {
+        assert_eq!(split_key_sequence("Mod+X"), ["Mod+X"]);
+        assert_eq!(split_key_sequence("Mod+X Ctrl+C"), ["Mod+X", "Ctrl+C"]);
+        assert_eq!(split_key_sequence("Mod +X  C"), ["Mod+X", "C"]);
+        assert_eq!(split_key_sequence("Mod+ X"), ["Mod+X"]);
+    }
+
+    #[test]
+    fn merge_bind_modes() {
+        let config = do_parse(
+            r#"
+            binds "resize" {
+                Minus { set-column-width "-10%"; }
+            }
+
+            binds {
+                Mod+R { enter-bind-mode "resize"; }
+            }
+
+            binds "resize" {
+                Minus { set-column-width "-5%"; }
+                Escape { exit-bind-mode; }
+            }
+            "#,
+        );
+
+        assert_eq!(config.bind_modes.len(), 2);
+        assert_eq!(config.binds().0.len(), 1);
+
+        let resize = config.mode_binds(Some("resize")).unwrap();
+        assert_eq!(resize.0.len(), 2);
+        assert_eq!(
+            resize.0[0].action,
+            Action::SetColumnWidth(SizeChange::AdjustProportion(-5.))
+        );
+
+        assert!(config.mode_binds(Some("missing")).is_none());
+        assert!(Config::parse("config.kdl", "")
+            .unwrap()
+            .binds()
+            .0
+            .is_empty());
+
+        assert_eq!(config.active_binds(Some("resize")), resize);
+        assert_eq!(config.active_binds(Some("missing")), config.binds());
+
+        let config = Config {
+            bind_modes: vec![],
+            ..config
+        };
+        assert!(config.binds().0.is_empty());
+    }
+
+    #[test]
+    fn reject_invalid_key_sequences() {
+        // A sequence that starts with a bind can never trigger.
+        assert!(Config::parse(
+            "config.kdl",
+            r#"
+            binds {
+                Mod+X { close-window; }
+                "Mod+X C" { spawn "code"; }
+            }
+            "#,
+        )
+        .is_err());
+
+        assert!(Config::parse(
+            "config.kdl",
+            r#"
+            binds {
+                "Mod+X MouseLeft" { close-window; }
+            }
+            "#,
+        )
+        .is_err());
+
+        // Binding modes can contain mouse and scroll binds.
+        let config = Config::parse(
+            "config.kdl",
+            r#"
+            binds "scroll" {
+                WheelScrollDown { focus-workspace-down; }
+            }
+            "#,
+        )
+        .unwrap();
+        let scroll = config.mode_binds(Some("scroll")).unwrap();
+        assert_eq!(scroll.0[0].key.trigger, Trigger::WheelScrollDown);
+    }
+
     #[test]
     fn default_repeat_params() {
         let config = Config::parse("config.kdl", "").unwrap();
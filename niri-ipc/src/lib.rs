@@ -111,11 +111,27 @@ pub enum Request {
     /// case. For example, a window may end up with a workspace id for a workspace that had already
     /// been removed. This can happen if the corresponding [`Event::WorkspacesChanged`] arrives
     /// before the corresponding [`Event::WindowOpenedOrChanged`].
-    EventStream,
+    ///
+    /// With a `filter`, only the events that it selects are sent, including in the initial state.
+    /// A bare `"EventStream"` without the filter is also accepted and sends all events.
+    EventStream {
+        /// Which events to send.
+        ///
+        /// `None` sends all events.
+        #[serde(default)]
+        filter: Option<EventFilter>,
+    },
     /// Respond with an error (for testing error handling).
     ReturnError,
     /// Request information about the overview.
     OverviewState,
+    /// Request the current arrangement of windows in the layout, for restoring it later.
+    SaveSession,
+    /// Arrange windows that open from now on according to a previously saved session.
+    ///
+    /// Windows are matched to the saved arrangement by their app id and title as they open. Any
+    /// previous pending session restore is replaced.
+    RestoreSession(Session),
 }
 
 /// Reply from niri to client.
@@ -160,6 +176,8 @@ pub enum Response {
     OutputConfigChanged(OutputConfigChanged),
     /// Information about the overview.
     OverviewState(Overview),
+    /// Saved arrangement of windows in the layout.
+    Session(Session),
 }
 
 /// Overview information.
@@ -170,6 +188,93 @@ pub struct Overview {
     pub is_open: bool,
 }
 
+/// Saved arrangement of windows in the layout.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct Session {
+    /// Workspaces, grouped by output and in order on their output.
+    pub workspaces: Vec<SessionWorkspace>,
+}
+
+/// Saved workspace.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct SessionWorkspace {
+    /// Index of the workspace on its output, starting from 1.
+    pub idx: u8,
+    /// Optional name of the workspace.
+    pub name: Option<String>,
+    /// Name of the output that the workspace was on.
+    pub output: Option<String>,
+    /// Columns of the scrolling layout, left to right.
+    pub columns: Vec<SessionColumn>,
+    /// Windows in the floating layout.
+    pub floating: Vec<SessionFloatingWindow>,
+}
+
+/// Saved column of the scrolling layout.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct SessionColumn {
+    /// How the column displays its windows.
+    pub display: ColumnDisplay,
+    /// Width of the column.
+    ///
+    /// For full-width columns, this is the width to restore when toggling full-width off.
+    pub width: SessionColumnWidth,
+    /// Whether the column is full-width.
+    pub is_full_width: bool,
+    /// Windows in the column, top to bottom.
+    pub tiles: Vec<SessionTile>,
+}
+
+/// Saved width of a column.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum SessionColumnWidth {
+    /// Proportion of the working area width.
+    Proportion(f64),
+    /// Fixed window width in logical pixels, not including borders.
+    Fixed(f64),
+}
+
+/// Saved window in a column.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct SessionTile {
+    /// Window to match.
+    pub window: SessionWindow,
+    /// Fixed window height in logical pixels.
+    ///
+    /// `None` means the window height is automatic.
+    pub height: Option<f64>,
+}
+
+/// Saved floating window.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct SessionFloatingWindow {
+    /// Window to match.
+    pub window: SessionWindow,
+    /// Position of the tile relative to the top-left corner of the working area.
+    pub pos: (f64, f64),
+    /// Window size in logical pixels.
+    pub size: (f64, f64),
+}
+
+/// Identification of a saved window.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct SessionWindow {
+    /// Application ID of the window.
+    pub app_id: Option<String>,
+    /// Title of the window.
+    ///
+    /// Used to tell apart windows of the same application. A window with a different title can
+    /// still match if no better candidate is found.
+    pub title: Option<String>,
+}
+
 /// Color picked from the screen.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
@@ -689,6 +794,20 @@ pub enum Action {
         #[cfg_attr(feature = "clap", arg())]
         layout: LayoutSwitchTarget,
     },
+    /// Switch the keyboard layout of a window by id.
+    ///
+    /// For unfocused windows, this requires `track-layout` set to `window` or `app`.
+    SwitchLayoutForWindow {
+        /// Id of the window to switch the layout of.
+        #[cfg_attr(feature = "clap", arg(long))]
+        id: u64,
+        /// Name of the layout to switch to.
+        ///
+        /// Matches an entry of the configured `layout` list (e.g. `us`), or the full xkb layout
+        /// name (e.g. `English (US)`).
+        #[cfg_attr(feature = "clap", arg())]
+        layout: String,
+    },
     /// Show the hotkey overlay.
     ShowHotkeyOverlay {},
     /// Move the focused workspace to the monitor to the left.
@@ -749,6 +868,56 @@ pub enum Action {
         #[cfg_attr(feature = "clap", arg(long))]
         id: Option<u64>,
     },
+    /// Toggle whether the focused window is shown on every workspace of its monitor.
+    ToggleWindowSticky {
+        /// Id of the window to toggle.
+        ///
+        /// If `None`, uses the focused window.
+        #[cfg_attr(feature = "clap", arg(long))]
+        id: Option<u64>,
+    },
+    /// Toggle picture-in-picture for the focused window.
+    ///
+    /// A picture-in-picture window floats as a small thumbnail in a corner of the screen.
+    TogglePictureInPicture {
+        /// Id of the window to toggle.
+        ///
+        /// If `None`, uses the focused window.
+        #[cfg_attr(feature = "clap", arg(long))]
+        id: Option<u64>,
+    },
+    /// Hide the focused window in the scratchpad.
+    MoveWindowToScratchpad {
+        /// Id of the window to hide.
+        ///
+        /// If `None`, uses the focused window.
+        #[cfg_attr(feature = "clap", arg(long))]
+        id: Option<u64>,
+    },
+    /// Summon a scratchpad window, or hide it if it is shown on the focused workspace.
+    ToggleScratchpad {
+        /// App ID of the scratchpad window.
+        ///
+        /// If `None`, uses any scratchpad window.
+        #[cfg_attr(feature = "clap", arg(long))]
+        app_id: Option<String>,
+    },
+    /// Summon a scratchpad window onto the focused workspace.
+    ShowScratchpad {
+        /// App ID of the scratchpad window.
+        ///
+        /// If `None`, uses the most recently hidden scratchpad window.
+        #[cfg_attr(feature = "clap", arg(long))]
+        app_id: Option<String>,
+    },
+    /// Hide scratchpad windows shown on the focused workspace.
+    HideScratchpad {
+        /// App ID of the scratchpad windows.
+        ///
+        /// If `None`, hides all scratchpad windows on the focused workspace.
+        #[cfg_attr(feature = "clap", arg(long))]
+        app_id: Option<String>,
+    },
     /// Switches focus to the floating layout.
     FocusFloating {},
     /// Switches focus to the tiling layout.
@@ -822,6 +991,24 @@ pub enum Action {
     OpenOverview {},
     /// Close the Overview.
     CloseOverview {},
+    /// Enter a binding mode.
+    ///
+    /// While a binding mode is active, only the keyboard binds from its `binds` section work.
+    EnterBindMode {
+        /// Name of the binding mode.
+        #[cfg_attr(feature = "clap", arg())]
+        name: String,
+    },
+    /// Return to the default binding mode.
+    ExitBindMode {},
+    /// Zoom the magnifier in on the output under the pointer.
+    MagnifierZoomIn {},
+    /// Zoom the magnifier out on the output under the pointer.
+    MagnifierZoomOut {},
+    /// Turn off the magnifier on the output under the pointer.
+    MagnifierReset {},
+    /// Toggle dimming of inactive windows.
+    ToggleInactiveDim {},
     /// Toggle urgent status of a window.
     ToggleWindowUrgent {
         /// Id of the window to toggle urgent.
@@ -945,6 +1132,14 @@ pub enum OutputAction {
         #[cfg_attr(feature = "clap", command(flatten))]
         vrr: VrrToSet,
     },
+    /// Power the output on or off via DPMS.
+    ///
+    /// Unlike turning the output off, this keeps its windows and workspaces in place.
+    Power {
+        /// Power state to set.
+        #[cfg_attr(feature = "clap", arg())]
+        power: OutputPower,
+    },
 }
 
 /// Output mode to set.
@@ -1026,6 +1221,17 @@ pub struct VrrToSet {
     pub on_demand: bool,
 }
 
+/// Output power state.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum OutputPower {
+    /// The output is powered on.
+    On,
+    /// The output is powered off.
+    Off,
+}
+
 /// Connected output.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
@@ -1050,6 +1256,19 @@ pub struct Output {
     pub vrr_supported: bool,
     /// Whether variable refresh rate is enabled on the output.
     pub vrr_enabled: bool,
+    /// Whether the output currently presents frames with tearing (async page flips).
+    ///
+    /// This happens while a fullscreen window matching the allow-tearing window rule is directly
+    /// scanned out and asks for async presentation.
+    #[serde(default)]
+    pub tearing: bool,
+    /// Whether the output is powered off via DPMS on its own.
+    ///
+    /// This is set by `niri msg output <name> power off` and by idle daemons using the
+    /// wlr-output-power-management protocol. Powering off all monitors with the
+    /// power-off-monitors action does not change it.
+    #[serde(default)]
+    pub powered_off: bool,
     /// Logical output information.
     ///
     /// `None` if the output is not mapped to any logical output (for example, if it is disabled).
@@ -1117,6 +1336,28 @@ pub enum Transform {
     Flipped270,
 }
 
+/// Corner or edge of an output.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum HotCorner {
+    /// Top-left corner.
+    TopLeft,
+    /// Top-right corner.
+    TopRight,
+    /// Bottom-left corner.
+    BottomLeft,
+    /// Bottom-right corner.
+    BottomRight,
+    /// Top edge.
+    TopEdge,
+    /// Bottom edge.
+    BottomEdge,
+    /// Left edge.
+    LeftEdge,
+    /// Right edge.
+    RightEdge,
+}
+
 /// Toplevel window.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
@@ -1148,6 +1389,10 @@ pub struct Window {
     ///
     /// If the window isn't floating then it is in the tiling layout.
     pub is_floating: bool,
+    /// Whether this window is sticky.
+    ///
+    /// Sticky windows are floating and shown on every workspace of their monitor.
+    pub is_sticky: bool,
     /// Whether this window requests your attention.
     pub is_urgent: bool,
 }
@@ -1341,6 +1586,112 @@ pub enum Event {
         /// The new state of the overview.
         is_open: bool,
     },
+    /// The binding mode changed.
+    BindModeChanged {
+        /// Name of the new binding mode, or `None` for the default mode.
+        name: Option<String>,
+    },
+    /// A hot corner or screen edge was triggered.
+    HotCornerTriggered {
+        /// Name of the output.
+        output: String,
+        /// The corner or edge that was triggered.
+        corner: HotCorner,
+    },
+}
+
+/// Kind of a compositor [`Event`].
+///
+/// Each kind covers all events that update the same part of the event stream state, so that a
+/// filtered event stream still describes that part consistently.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub enum EventKind {
+    /// Workspace events, such as [`Event::WorkspacesChanged`] and [`Event::WorkspaceActivated`].
+    Workspaces,
+    /// Window events, such as [`Event::WindowsChanged`] and [`Event::WindowFocusChanged`].
+    Windows,
+    /// Keyboard layout events, such as [`Event::KeyboardLayoutSwitched`].
+    KeyboardLayouts,
+    /// Overview events, such as [`Event::OverviewOpenedOrClosed`].
+    Overview,
+    /// Binding mode events, such as [`Event::BindModeChanged`].
+    BindMode,
+    /// Hot corner events, such as [`Event::HotCornerTriggered`].
+    HotCorners,
+}
+
+/// Filter for an event stream.
+///
+/// All conditions must hold for an event to be sent. An empty filter lets all events through.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "json-schema", derive(schemars::JsonSchema))]
+pub struct EventFilter {
+    /// Kinds of events to send.
+    ///
+    /// If empty, events of all kinds are sent.
+    #[serde(default)]
+    pub kinds: Vec<EventKind>,
+    /// Only send workspace and window events for this output.
+    ///
+    /// Windows and workspaces that move to a different output are reported as closed and removed,
+    /// respectively.
+    #[serde(default)]
+    pub output: Option<String>,
+    /// Only send workspace and window events for the workspace with this id.
+    #[serde(default)]
+    pub workspace_id: Option<u64>,
+    /// Only send window events for the window with this id.
+    #[serde(default)]
+    pub window_id: Option<u64>,
+}
+
+impl Event {
+    /// Returns the kind of this event.
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::WorkspacesChanged { .. }
+            | Event::WorkspaceUrgencyChanged { .. }
+            | Event::WorkspaceActivated { .. }
+            | Event::WorkspaceActiveWindowChanged { .. } => EventKind::Workspaces,
+            Event::WindowsChanged { .. }
+            | Event::WindowOpenedOrChanged { .. }
+            | Event::WindowClosed { .. }
+            | Event::WindowFocusChanged { .. }
+            | Event::WindowUrgencyChanged { .. } => EventKind::Windows,
+            Event::KeyboardLayoutsChanged { .. } | Event::KeyboardLayoutSwitched { .. } => {
+                EventKind::KeyboardLayouts
+            }
+            Event::OverviewOpenedOrClosed { .. } => EventKind::Overview,
+            Event::BindModeChanged { .. } => EventKind::BindMode,
+            Event::HotCornerTriggered { .. } => EventKind::HotCorners,
+        }
+    }
+}
+
+impl EventFilter {
+    /// Returns whether this filter lets through events of this kind.
+    pub fn wants(&self, kind: EventKind) -> bool {
+        self.kinds.is_empty() || self.kinds.contains(&kind)
+    }
+
+    /// Returns whether this filter selects the workspace.
+    pub fn matches_workspace(&self, workspace: &Workspace) -> bool {
+        if let Some(output) = &self.output {
+            if workspace.output.as_ref() != Some(output) {
+                return false;
+            }
+        }
+
+        if let Some(id) = self.workspace_id {
+            if workspace.id != id {
+                return false;
+            }
+        }
+
+        true
+    }
 }
 
 impl FromStr for WorkspaceReferenceArg {
@@ -75,7 +75,7 @@ impl Socket {
     /// fn main() -> std::io::Result<()> {
     ///     let mut socket = Socket::connect()?;
     ///
-    ///     let reply = socket.send(Request::EventStream)?;
+    ///     let reply = socket.send(Request::EventStream { filter: None })?;
     ///     if matches!(reply, Ok(Response::Handled)) {
     ///         let mut read_event = socket.read_events();
     ///         while let Ok(event) = read_event() {
@@ -43,6 +43,9 @@ pub struct EventStreamState {
 
     /// State of the overview.
     pub overview: OverviewState,
+
+    /// State of the binding mode.
+    pub bind_mode: BindModeState,
 }
 
 /// The workspaces state communicated over the event stream.
@@ -73,6 +76,13 @@ pub struct OverviewState {
     pub is_open: bool,
 }
 
+/// The binding mode state communicated over the event stream.
+#[derive(Debug, Default)]
+pub struct BindModeState {
+    /// Name of the active binding mode, or `None` for the default mode.
+    pub name: Option<String>,
+}
+
 impl EventStreamStatePart for EventStreamState {
     fn replicate(&self) -> Vec<Event> {
         let mut events = Vec::new();
@@ -80,6 +90,7 @@ impl EventStreamStatePart for EventStreamState {
         events.extend(self.windows.replicate());
         events.extend(self.keyboard_layouts.replicate());
         events.extend(self.overview.replicate());
+        events.extend(self.bind_mode.replicate());
         events
     }
 
@@ -88,6 +99,7 @@ impl EventStreamStatePart for EventStreamState {
         let event = self.windows.apply(event)?;
         let event = self.keyboard_layouts.apply(event)?;
         let event = self.overview.apply(event)?;
+        let event = self.bind_mode.apply(event)?;
         Some(event)
     }
 }
@@ -237,3 +249,21 @@ impl EventStreamStatePart for OverviewState {
         None
     }
 }
+
+impl EventStreamStatePart for BindModeState {
+    fn replicate(&self) -> Vec<Event> {
+        vec![Event::BindModeChanged {
+            name: self.name.clone(),
+        }]
+    }
+
+    fn apply(&mut self, event: Event) -> Option<Event> {
+        match event {
+            Event::BindModeChanged { name } => {
+                self.name = name;
+            }
+            event => return Some(event),
+        }
+        None
+    }
+}
@@ -1,37 +1,164 @@
-//! Headless backend for tests.
+//! Headless backend for tests and offscreen sessions.
 //!
-//! This can eventually grow into a more complete backend if needed, but for now it's missing some
-//! crucial parts like rendering.
+//! By default, nothing is rendered, and frames are reported as presented right away. This is what
+//! the tests use. When a renderer is added with [`Headless::add_renderer()`], every output is
+//! rendered into an offscreen texture, and frames are paced with a timer emulating VBlanks. This
+//! makes screencopy, screenshots and screencasts work without a display, for example on build
+//! machines with no GPU where EGL falls back to a software renderer like llvmpipe.
 
+use std::collections::{HashMap, HashSet};
+use std::fs::OpenOptions;
 use std::mem;
+use std::os::fd::OwnedFd;
+use std::os::unix::fs::OpenOptionsExt as _;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use anyhow::Context as _;
 use niri_config::OutputName;
 use smithay::backend::allocator::dmabuf::Dmabuf;
+use smithay::backend::allocator::gbm::GbmDevice;
+use smithay::backend::allocator::Fourcc;
+use smithay::backend::drm::{DrmDeviceFd, DrmNode};
+use smithay::backend::egl::{EGLContext, EGLDevice, EGLDisplay};
+use smithay::backend::renderer::damage::OutputDamageTracker;
 use smithay::backend::renderer::element::RenderElementStates;
-use smithay::backend::renderer::gles::GlesRenderer;
+use smithay::backend::renderer::gles::{GlesRenderer, GlesTexture};
+use smithay::backend::renderer::{Bind as _, ImportDma, ImportEgl, Offscreen as _};
+use smithay::desktop::utils::OutputPresentationFeedback;
 use smithay::output::{Mode, Output, PhysicalProperties, Subpixel};
+use smithay::reexports::calloop::timer::{TimeoutAction, Timer};
+use smithay::reexports::rustix::fs::OFlags;
 use smithay::reexports::wayland_protocols::wp::presentation_time::server::wp_presentation_feedback;
-use smithay::utils::Size;
+use smithay::utils::{DeviceFd, Size, Transform};
+use smithay::wayland::dmabuf::DmabufFeedbackBuilder;
 use smithay::wayland::presentation::Refresh;
 
-use super::{IpcOutputMap, OutputId, RenderResult};
-use crate::niri::{Niri, RedrawState};
+use super::{queue_estimated_vblank_timer, IpcOutputMap, OutputId, RenderResult};
+use crate::frame_clock::FrameClock;
+use crate::niri::{Niri, RedrawState, State};
+use crate::render_helpers::debug::draw_damage;
+use crate::render_helpers::{resources, shaders};
 use crate::utils::{get_monotonic_time, logical_output};
 
+/// Refresh interval of the virtual outputs.
+const REFRESH_INTERVAL: Duration = Duration::from_micros(16_667);
+
 pub struct Headless {
+    /// Offscreen renderer, if rendering was enabled.
+    renderer: Option<GlesRenderer>,
+    /// GBM device for the render node, if rendering on a DRM device.
+    gbm: Option<GbmDevice<DrmDeviceFd>>,
+    /// Offscreen render targets of the outputs.
+    surfaces: HashMap<Output, Surface>,
+    /// Outputs powered off via DPMS, emulated for the tests.
+    powered_off: HashSet<Output>,
     ipc_outputs: Arc<Mutex<IpcOutputMap>>,
 }
 
+struct Surface {
+    /// Texture that the output is rendered into.
+    texture: GlesTexture,
+    damage_tracker: OutputDamageTracker,
+    /// Age of the texture contents, for damage tracking.
+    age: usize,
+    /// Feedbacks for the frame waiting for the emulated VBlank.
+    pending_feedback: Option<OutputPresentationFeedback>,
+    /// Sequence number of the last presented frame.
+    sequence: u64,
+}
+
 impl Headless {
     pub fn new() -> Self {
         Self {
+            renderer: None,
+            gbm: None,
+            surfaces: HashMap::new(),
+            powered_off: HashSet::new(),
             ipc_outputs: Default::default(),
         }
     }
 
     pub fn init(&mut self, _niri: &mut Niri) {}
 
+    /// Enables offscreen rendering.
+    ///
+    /// Uses the render node from the `render-drm-device` debug option if it's set, and an EGL
+    /// software device otherwise.
+    pub fn add_renderer(&mut self, niri: &mut Niri) -> anyhow::Result<()> {
+        if self.renderer.is_some() {
+            return Ok(());
+        }
+
+        let render_node = niri.config.borrow().debug.render_drm_device.clone();
+        let (mut renderer, gbm, node) = if let Some(path) = render_node {
+            debug!("creating headless renderer on {path:?}");
+
+            let fd = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .custom_flags((OFlags::CLOEXEC | OFlags::NOCTTY).bits() as i32)
+                .open(&path)
+                .with_context(|| format!("error opening {path:?}"))?;
+            let node = DrmNode::from_path(&path).context("error getting DRM node")?;
+            let gbm = GbmDevice::new(DrmDeviceFd::new(DeviceFd::from(OwnedFd::from(fd))))
+                .context("error creating GBM device")?;
+
+            let display = unsafe { EGLDisplay::new(gbm.clone()) }
+                .context("error creating EGL display")?;
+            (create_renderer(display)?, Some(gbm), Some(node))
+        } else {
+            debug!("creating headless renderer on an EGL software device");
+            (create_software_renderer()?, None, None)
+        };
+
+        if let Err(err) = renderer.bind_wl_display(&niri.display_handle) {
+            warn!("error binding wl-display in EGL: {err:?}");
+        }
+
+        resources::init(&mut renderer);
+        shaders::init(&mut renderer);
+
+        if !shaders::set_custom_programs(&mut renderer, &niri.config.borrow()) {
+            niri.config_error_notification.show_shader_error();
+        }
+
+        niri.update_shaders();
+
+        // Clients can only allocate dmabufs when there's a render node to advertise.
+        if let Some(node) = node {
+            let formats = renderer.dmabuf_formats();
+            let default_feedback = DmabufFeedbackBuilder::new(node.dev_id(), formats)
+                .build()
+                .context("error building default dmabuf feedback")?;
+            niri.dmabuf_state
+                .create_global_with_default_feedback::<State>(
+                    &niri.display_handle,
+                    &default_feedback,
+                );
+        } else {
+            debug!("no render node, screencasting will be unavailable");
+        }
+
+        self.renderer = Some(renderer);
+        self.gbm = gbm;
+
+        // Outputs added before this point were not set up for rendering.
+        let outputs: Vec<_> = niri.global_space.outputs().cloned().collect();
+        for output in outputs {
+            self.add_surface(&output)?;
+
+            let output_state = niri.output_state.get_mut(&output).unwrap();
+            output_state.frame_clock = FrameClock::new(
+                Some(REFRESH_INTERVAL),
+                output_state.frame_clock.vrr(),
+            );
+            niri.queue_redraw(&output);
+        }
+
+        Ok(())
+    }
+
     pub fn add_output(&mut self, niri: &mut Niri, n: u8, size: (u16, u16)) {
         let connector = format!("headless-{n}");
         let make = "niri".to_string();
@@ -80,11 +207,66 @@ impl Headless {
                 current_mode: Some(0),
                 vrr_supported: false,
                 vrr_enabled: false,
+                tearing: false,
+                powered_off: false,
                 logical: Some(logical_output(&output)),
             },
         );
 
-        niri.add_output(output, None, false);
+        // Without a renderer, frames are presented right away, so there's no refresh interval.
+        let refresh_interval = if self.renderer.is_some() {
+            if let Err(err) = self.add_surface(&output) {
+                warn!("error creating offscreen surface for {}: {err:?}", output.name());
+            }
+            Some(REFRESH_INTERVAL)
+        } else {
+            None
+        };
+
+        niri.add_output(output, refresh_interval, false);
+    }
+
+    fn add_surface(&mut self, output: &Output) -> anyhow::Result<()> {
+        let renderer = self.renderer.as_mut().unwrap();
+
+        let size = output.current_mode().unwrap().size;
+        let buffer_size = size.to_logical(1).to_buffer(1, Transform::Normal);
+        let texture: GlesTexture = renderer
+            .create_buffer(Fourcc::Abgr8888, buffer_size)
+            .context("error creating texture")?;
+
+        let surface = Surface {
+            texture,
+            damage_tracker: OutputDamageTracker::from_output(output),
+            age: 0,
+            pending_feedback: None,
+            sequence: 0,
+        };
+        self.surfaces.insert(output.clone(), surface);
+
+        Ok(())
+    }
+
+    pub fn is_output_powered(&self, output: &Output) -> bool {
+        !self.powered_off.contains(output)
+    }
+
+    pub fn set_output_powered(&mut self, niri: &mut Niri, output: &Output, powered: bool) {
+        let changed = if powered {
+            self.powered_off.remove(output)
+        } else {
+            self.powered_off.insert(output.clone())
+        };
+        if !changed {
+            return;
+        }
+
+        let name = output.name();
+        let mut ipc_outputs = self.ipc_outputs.lock().unwrap();
+        if let Some(ipc_output) = ipc_outputs.values_mut().find(|o| o.name == name) {
+            ipc_output.powered_off = !powered;
+        }
+        niri.ipc_outputs_changed = true;
     }
 
     pub fn seat_name(&self) -> String {
@@ -93,39 +275,183 @@ impl Headless {
 
     pub fn with_primary_renderer<T>(
         &mut self,
-        _f: impl FnOnce(&mut GlesRenderer) -> T,
+        f: impl FnOnce(&mut GlesRenderer) -> T,
     ) -> Option<T> {
-        None
+        self.renderer.as_mut().map(f)
     }
 
-    pub fn render(&mut self, niri: &mut Niri, output: &Output) -> RenderResult {
-        let states = RenderElementStates::default();
-        let mut presentation_feedbacks = niri.take_presentation_feedbacks(output, &states);
-        presentation_feedbacks.presented::<_, smithay::utils::Monotonic>(
-            get_monotonic_time(),
-            Refresh::Unknown,
-            0,
-            wp_presentation_feedback::Kind::empty(),
-        );
+    pub fn render(
+        &mut self,
+        niri: &mut Niri,
+        output: &Output,
+        target_presentation_time: Duration,
+    ) -> RenderResult {
+        let Some(renderer) = self.renderer.as_mut() else {
+            return render_without_renderer(niri, output);
+        };
+
+        let span = tracy_client::span!("Headless::render");
+        span.emit_text(&output.name());
+
+        let Some(surface) = self.surfaces.get_mut(output) else {
+            error!("missing offscreen surface");
+            return RenderResult::Skipped;
+        };
 
-        let output_state = niri.output_state.get_mut(output).unwrap();
-        match mem::replace(&mut output_state.redraw_state, RedrawState::Idle) {
-            RedrawState::Idle => unreachable!(),
-            RedrawState::Queued => (),
-            RedrawState::WaitingForVBlank { .. } => unreachable!(),
-            RedrawState::WaitingForEstimatedVBlank(_) => unreachable!(),
-            RedrawState::WaitingForEstimatedVBlankAndQueued(_) => unreachable!(),
+        // Render the elements.
+        let mut elements = niri.render_output::<GlesRenderer>(renderer, output);
+
+        // Visualize the damage, if enabled.
+        if niri.debug_draw_damage {
+            let output_state = niri.output_state.get_mut(output).unwrap();
+            draw_damage(&mut output_state.debug_damage_tracker, &mut elements);
         }
 
-        output_state.frame_callback_sequence = output_state.frame_callback_sequence.wrapping_add(1);
+        let res = {
+            let mut target = match renderer.bind(&mut surface.texture) {
+                Ok(target) => target,
+                Err(err) => {
+                    warn!("error binding offscreen texture: {err:?}");
+                    return RenderResult::Skipped;
+                }
+            };
+            surface.damage_tracker.render_output(
+                renderer,
+                &mut target,
+                surface.age,
+                &elements,
+                [0.; 4],
+            )
+        };
+
+        let mut rv = RenderResult::Skipped;
+        match res {
+            Ok(res) => {
+                niri.update_primary_scanout_output(output, &res.states);
+
+                if res.damage.is_some() {
+                    if niri
+                        .config
+                        .borrow()
+                        .debug
+                        .wait_for_frame_completion_before_queueing
+                    {
+                        let _span = tracy_client::span!("wait for completion");
+                        if let Err(err) = res.sync.wait() {
+                            warn!("error waiting for frame completion: {err:?}");
+                        }
+                    }
+
+                    // The texture keeps its contents between frames.
+                    surface.age = 1;
+                    surface.pending_feedback =
+                        Some(niri.take_presentation_feedbacks(output, &res.states));
+
+                    let output_state = niri.output_state.get_mut(output).unwrap();
+                    let new_state = RedrawState::WaitingForVBlank {
+                        redraw_needed: false,
+                    };
+                    match mem::replace(&mut output_state.redraw_state, new_state) {
+                        RedrawState::Idle => unreachable!(),
+                        RedrawState::Queued => (),
+                        RedrawState::WaitingForVBlank { .. } => unreachable!(),
+                        RedrawState::WaitingForEstimatedVBlank(_) => unreachable!(),
+                        RedrawState::WaitingForEstimatedVBlankAndQueued(token) => {
+                            niri.event_loop.remove(token);
+                        }
+                    };
 
-        // FIXME: request redraw on unfinished animations remain
+                    // There's no client buffer that could be overwritten before the VBlank, so we
+                    // can send frame callbacks right away, same as the TTY backend.
+                    output_state.frame_callback_sequence =
+                        output_state.frame_callback_sequence.wrapping_add(1);
 
-        RenderResult::Submitted
+                    queue_vblank_timer(niri, output.clone(), target_presentation_time);
+                    return RenderResult::Submitted;
+                } else {
+                    rv = RenderResult::NoDamage;
+                }
+            }
+            Err(err) => {
+                warn!("error rendering frame: {err:?}");
+            }
+        }
+
+        // Queue a timer to fire at the predicted vblank time.
+        queue_estimated_vblank_timer(niri, output.clone(), target_presentation_time);
+
+        rv
     }
 
-    pub fn import_dmabuf(&mut self, _dmabuf: &Dmabuf) -> bool {
-        unimplemented!()
+    fn on_vblank(&mut self, niri: &mut Niri, output: Output) {
+        let span = tracy_client::span!("Headless::on_vblank");
+
+        let name = output.name();
+        span.emit_text(&name);
+
+        let now = get_monotonic_time();
+
+        let Some(surface) = self.surfaces.get_mut(&output) else {
+            error!("missing surface in vblank callback for {name}");
+            // Don't leave the output waiting for a VBlank that will never come.
+            if let Some(output_state) = niri.output_state.get_mut(&output) {
+                output_state.redraw_state = RedrawState::Idle;
+            }
+            return;
+        };
+
+        let Some(output_state) = niri.output_state.get_mut(&output) else {
+            error!("missing output state for {name}");
+            return;
+        };
+
+        let redraw_needed = match mem::replace(&mut output_state.redraw_state, RedrawState::Idle) {
+            RedrawState::WaitingForVBlank { redraw_needed } => redraw_needed,
+            state => {
+                error!("unexpected redraw state for output {name}: {state:?}");
+                true
+            }
+        };
+
+        surface.sequence = surface.sequence.wrapping_add(1);
+        if let Some(mut feedback) = surface.pending_feedback.take() {
+            let refresh = output_state
+                .frame_clock
+                .refresh_interval()
+                .map_or(Refresh::Unknown, Refresh::Fixed);
+            feedback.presented::<_, smithay::utils::Monotonic>(
+                now,
+                refresh,
+                surface.sequence,
+                wp_presentation_feedback::Kind::Vsync,
+            );
+        }
+
+        output_state.frame_clock.presented(now);
+
+        if redraw_needed || output_state.unfinished_animations_remain {
+            niri.queue_redraw(&output);
+        } else {
+            niri.send_frame_callbacks(&output);
+        }
+    }
+
+    pub fn import_dmabuf(&mut self, dmabuf: &Dmabuf) -> bool {
+        let Some(renderer) = self.renderer.as_mut() else {
+            return false;
+        };
+
+        match renderer.import_dmabuf(dmabuf, None) {
+            Ok(_texture) => true,
+            Err(err) => {
+                debug!("error importing dmabuf: {err:?}");
+                false
+            }
+        }
+    }
+
+    pub fn gbm_device(&self) -> Option<GbmDevice<DrmDeviceFd>> {
+        self.gbm.clone()
     }
 
     pub fn ipc_outputs(&self) -> Arc<Mutex<IpcOutputMap>> {
@@ -138,3 +464,66 @@ impl Default for Headless {
         Self::new()
     }
 }
+
+/// Creates a renderer on an EGL software device, such as llvmpipe.
+pub fn create_software_renderer() -> anyhow::Result<GlesRenderer> {
+    let device = EGLDevice::enumerate()
+        .context("error enumerating EGL devices")?
+        .find(|device| device.is_software())
+        .context("no EGL software device found")?;
+
+    let display = unsafe { EGLDisplay::new(device) }.context("error creating EGL display")?;
+    create_renderer(display)
+}
+
+fn create_renderer(display: EGLDisplay) -> anyhow::Result<GlesRenderer> {
+    let context = EGLContext::new(&display).context("error creating EGL context")?;
+    let renderer = unsafe { GlesRenderer::new(context) }.context("error creating renderer")?;
+    Ok(renderer)
+}
+
+/// Reports the frame as presented right away without rendering anything.
+fn render_without_renderer(niri: &mut Niri, output: &Output) -> RenderResult {
+    let states = RenderElementStates::default();
+    let mut presentation_feedbacks = niri.take_presentation_feedbacks(output, &states);
+    presentation_feedbacks.presented::<_, smithay::utils::Monotonic>(
+        get_monotonic_time(),
+        Refresh::Unknown,
+        0,
+        wp_presentation_feedback::Kind::empty(),
+    );
+
+    let output_state = niri.output_state.get_mut(output).unwrap();
+    match mem::replace(&mut output_state.redraw_state, RedrawState::Idle) {
+        RedrawState::Idle => unreachable!(),
+        RedrawState::Queued => (),
+        RedrawState::WaitingForVBlank { .. } => unreachable!(),
+        RedrawState::WaitingForEstimatedVBlank(_) => unreachable!(),
+        RedrawState::WaitingForEstimatedVBlankAndQueued(_) => unreachable!(),
+    }
+
+    output_state.frame_callback_sequence = output_state.frame_callback_sequence.wrapping_add(1);
+
+    // FIXME: request redraw on unfinished animations remain
+
+    RenderResult::Submitted
+}
+
+/// Queues a timer emulating a VBlank at the target presentation time.
+fn queue_vblank_timer(niri: &mut Niri, output: Output, target_presentation_time: Duration) {
+    let now = get_monotonic_time();
+    let duration = target_presentation_time.saturating_sub(now);
+
+    trace!("queueing emulated vblank timer to fire in {duration:?}");
+
+    let timer = Timer::from_duration(duration);
+    niri.event_loop
+        .insert_source(timer, move |_, _, state| {
+            state
+                .backend
+                .headless()
+                .on_vblank(&mut state.niri, output.clone());
+            TimeoutAction::Drop
+        })
+        .unwrap();
+}
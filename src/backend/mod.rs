@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::mem;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
@@ -6,9 +7,11 @@ use niri_config::{Config, ModKey};
 use smithay::backend::allocator::dmabuf::Dmabuf;
 use smithay::backend::renderer::gles::GlesRenderer;
 use smithay::output::Output;
+use smithay::reexports::calloop::timer::{TimeoutAction, Timer};
 use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
 
-use crate::niri::Niri;
+use crate::niri::{Niri, RedrawState};
+use crate::utils::get_monotonic_time;
 use crate::utils::id::IdCounter;
 
 pub mod tty;
@@ -91,7 +94,9 @@ impl Backend {
         match self {
             Backend::Tty(tty) => tty.render(niri, output, target_presentation_time),
             Backend::Winit(winit) => winit.render(niri, output),
-            Backend::Headless(headless) => headless.render(niri, output),
+            Backend::Headless(headless) => {
+                headless.render(niri, output, target_presentation_time)
+            }
         }
     }
 
@@ -164,7 +169,7 @@ impl Backend {
         match self {
             Backend::Tty(tty) => tty.primary_gbm_device(),
             Backend::Winit(_) => None,
-            Backend::Headless(_) => None,
+            Backend::Headless(headless) => headless.gbm_device(),
         }
     }
 
@@ -176,6 +181,22 @@ impl Backend {
         }
     }
 
+    pub fn is_output_powered(&self, output: &Output) -> bool {
+        match self {
+            Backend::Tty(tty) => tty.is_output_powered(output),
+            Backend::Winit(_) => true,
+            Backend::Headless(headless) => headless.is_output_powered(output),
+        }
+    }
+
+    pub fn set_output_powered(&mut self, niri: &mut Niri, output: &Output, powered: bool) {
+        match self {
+            Backend::Tty(tty) => tty.set_output_powered(niri, output, powered),
+            Backend::Winit(_) => (),
+            Backend::Headless(headless) => headless.set_output_powered(niri, output, powered),
+        }
+    }
+
     pub fn set_output_on_demand_vrr(&mut self, niri: &mut Niri, output: &Output, enable_vrr: bool) {
         match self {
             Backend::Tty(tty) => tty.set_output_on_demand_vrr(niri, output, enable_vrr),
@@ -224,3 +245,83 @@ impl Backend {
         }
     }
 }
+
+/// Queues a timer to fire at the predicted VBlank time.
+///
+/// Used by backends when a frame was not submitted, so that frame callbacks keep their pacing.
+fn queue_estimated_vblank_timer(
+    niri: &mut Niri,
+    output: Output,
+    target_presentation_time: Duration,
+) {
+    let output_state = niri.output_state.get_mut(&output).unwrap();
+    match mem::take(&mut output_state.redraw_state) {
+        RedrawState::Idle => unreachable!(),
+        RedrawState::Queued => (),
+        RedrawState::WaitingForVBlank { .. } => unreachable!(),
+        RedrawState::WaitingForEstimatedVBlank(token)
+        | RedrawState::WaitingForEstimatedVBlankAndQueued(token) => {
+            output_state.redraw_state = RedrawState::WaitingForEstimatedVBlank(token);
+            return;
+        }
+    }
+
+    let now = get_monotonic_time();
+    let mut duration = target_presentation_time.saturating_sub(now);
+
+    // No use setting a zero timer, since we'll send frame callbacks anyway right after the call to
+    // render(). This can happen for example with unknown presentation time from DRM.
+    if duration.is_zero() {
+        duration += output_state
+            .frame_clock
+            .refresh_interval()
+            // Unknown refresh interval, i.e. winit backend. Would be good to estimate it somehow
+            // but it's not that important for this code path.
+            .unwrap_or(Duration::from_micros(16_667));
+    }
+
+    trace!("queueing estimated vblank timer to fire in {duration:?}");
+
+    let timer = Timer::from_duration(duration);
+    let token = niri
+        .event_loop
+        .insert_source(timer, move |_, _, data| {
+            on_estimated_vblank_timer(&mut data.niri, output.clone());
+            TimeoutAction::Drop
+        })
+        .unwrap();
+    output_state.redraw_state = RedrawState::WaitingForEstimatedVBlank(token);
+}
+
+fn on_estimated_vblank_timer(niri: &mut Niri, output: Output) {
+    let span = tracy_client::span!("on_estimated_vblank_timer");
+
+    let name = output.name();
+    span.emit_text(&name);
+
+    let Some(output_state) = niri.output_state.get_mut(&output) else {
+        error!("missing output state for {name}");
+        return;
+    };
+
+    // We waited for the timer, now we can send frame callbacks again.
+    output_state.frame_callback_sequence = output_state.frame_callback_sequence.wrapping_add(1);
+
+    match mem::replace(&mut output_state.redraw_state, RedrawState::Idle) {
+        RedrawState::Idle => unreachable!(),
+        RedrawState::Queued => unreachable!(),
+        RedrawState::WaitingForVBlank { .. } => unreachable!(),
+        RedrawState::WaitingForEstimatedVBlank(_) => (),
+        // The timer fired just in front of a redraw.
+        RedrawState::WaitingForEstimatedVBlankAndQueued(_) => {
+            output_state.redraw_state = RedrawState::Queued;
+            return;
+        }
+    }
+
+    if output_state.unfinished_animations_remain {
+        niri.queue_redraw(&output);
+    } else {
+        niri.send_frame_callbacks(&output);
+    }
+}
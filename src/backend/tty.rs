@@ -26,6 +26,7 @@ use smithay::backend::drm::{
 use smithay::backend::egl::context::ContextPriority;
 use smithay::backend::egl::{EGLDevice, EGLDisplay};
 use smithay::backend::libinput::{LibinputInputBackend, LibinputSessionInterface};
+use smithay::backend::renderer::element::{Id, RenderElementPresentationState};
 use smithay::backend::renderer::gles::GlesRenderer;
 use smithay::backend::renderer::multigpu::gbm::GbmGlesBackend;
 use smithay::backend::renderer::multigpu::{GpuManager, MultiFrame, MultiRenderer};
@@ -41,12 +42,13 @@ use smithay::reexports::drm::control::{
     self, connector, crtc, property, Device, Mode as DrmMode, ModeFlags, ModeTypeFlags,
     ResourceHandle,
 };
+use smithay::reexports::drm::{Device as _, DriverCapability};
 use smithay::reexports::gbm::Modifier;
 use smithay::reexports::input::Libinput;
 use smithay::reexports::rustix::fs::OFlags;
 use smithay::reexports::wayland_protocols;
 use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
-use smithay::utils::DeviceFd;
+use smithay::utils::{DeviceFd, Scale};
 use smithay::wayland::dmabuf::{DmabufFeedback, DmabufFeedbackBuilder, DmabufGlobal};
 use smithay::wayland::drm_lease::{
     DrmLease, DrmLeaseBuilder, DrmLeaseRequest, DrmLeaseState, LeaseRejected,
@@ -56,13 +58,13 @@ use smithay_drm_extras::drm_scanner::{DrmScanEvent, DrmScanner};
 use wayland_protocols::wp::linux_dmabuf::zv1::server::zwp_linux_dmabuf_feedback_v1::TrancheFlags;
 use wayland_protocols::wp::presentation_time::server::wp_presentation_feedback;
 
-use super::{IpcOutputMap, RenderResult};
+use super::{queue_estimated_vblank_timer, IpcOutputMap, RenderResult};
 use crate::backend::OutputId;
 use crate::frame_clock::FrameClock;
 use crate::niri::{Niri, RedrawState, State};
 use crate::render_helpers::debug::draw_damage;
 use crate::render_helpers::renderer::AsGlesRenderer;
-use crate::render_helpers::{resources, shaders, RenderTarget};
+use crate::render_helpers::{is_fullscreen_scanout_candidate, resources, shaders};
 use crate::utils::{get_monotonic_time, is_laptop_panel, logical_output};
 
 const SUPPORTED_COLOR_FORMATS: [Fourcc; 4] = [
@@ -130,6 +132,7 @@ pub struct OutputDevice {
     // See https://github.com/Smithay/smithay/issues/1102.
     drm: DrmDevice,
     gbm: GbmDevice<DrmDeviceFd>,
+    supports_async_page_flip: bool,
 
     pub drm_lease_state: Option<DrmLeaseState>,
     non_desktop_connectors: HashSet<(connector::Handle, crtc::Handle)>,
@@ -227,6 +230,8 @@ struct Surface {
     gamma_props: Option<GammaProps>,
     /// Gamma change to apply upon session resume.
     pending_gamma_change: Option<Option<Vec<u16>>>,
+    /// Whether the output was powered off on its own via DPMS.
+    powered_off: bool,
     /// Tracy frame that goes from vblank to vblank.
     vblank_frame: Option<tracy_client::Frame>,
     /// Frame name for the VBlank frame.
@@ -505,6 +510,16 @@ impl Tty {
         let (drm, drm_notifier) = DrmDevice::new(device_fd.clone(), true)?;
         let gbm = GbmDevice::new(device_fd)?;
 
+        let async_page_flip_cap = if drm.is_atomic() {
+            DriverCapability::AtomicASyncPageFlip
+        } else {
+            DriverCapability::ASyncPageFlip
+        };
+        let supports_async_page_flip = drm
+            .get_driver_capability(async_page_flip_cap)
+            .is_ok_and(|value| value == 1);
+        debug!("async page flip support: {supports_async_page_flip}");
+
         let display = unsafe { EGLDisplay::new(gbm.clone())? };
         let egl_device = EGLDevice::device_for_display(&display)?;
 
@@ -537,17 +552,9 @@ impl Tty {
             resources::init(gles_renderer);
             shaders::init(gles_renderer);
 
-            let config = self.config.borrow();
-            if let Some(src) = config.animations.window_resize.custom_shader.as_deref() {
-                shaders::set_custom_resize_program(gles_renderer, Some(src));
-            }
-            if let Some(src) = config.animations.window_close.custom_shader.as_deref() {
-                shaders::set_custom_close_program(gles_renderer, Some(src));
+            if !shaders::set_custom_programs(gles_renderer, &self.config.borrow()) {
+                niri.config_error_notification.show_shader_error();
             }
-            if let Some(src) = config.animations.window_open.custom_shader.as_deref() {
-                shaders::set_custom_open_program(gles_renderer, Some(src));
-            }
-            drop(config);
 
             niri.update_shaders();
 
@@ -608,6 +615,7 @@ impl Tty {
             render_node,
             drm,
             gbm,
+            supports_async_page_flip,
             drm_scanner: DrmScanner::new(),
             surfaces: HashMap::new(),
             known_crtcs: HashMap::new(),
@@ -1063,6 +1071,7 @@ impl Tty {
             dmabuf_feedback,
             gamma_props,
             pending_gamma_change: None,
+            powered_off: false,
             vblank_frame: None,
             vblank_frame_name,
             time_since_presentation_plot_name,
@@ -1302,39 +1311,6 @@ impl Tty {
         }
     }
 
-    fn on_estimated_vblank_timer(&self, niri: &mut Niri, output: Output) {
-        let span = tracy_client::span!("Tty::on_estimated_vblank_timer");
-
-        let name = output.name();
-        span.emit_text(&name);
-
-        let Some(output_state) = niri.output_state.get_mut(&output) else {
-            error!("missing output state for {name}");
-            return;
-        };
-
-        // We waited for the timer, now we can send frame callbacks again.
-        output_state.frame_callback_sequence = output_state.frame_callback_sequence.wrapping_add(1);
-
-        match mem::replace(&mut output_state.redraw_state, RedrawState::Idle) {
-            RedrawState::Idle => unreachable!(),
-            RedrawState::Queued => unreachable!(),
-            RedrawState::WaitingForVBlank { .. } => unreachable!(),
-            RedrawState::WaitingForEstimatedVBlank(_) => (),
-            // The timer fired just in front of a redraw.
-            RedrawState::WaitingForEstimatedVBlankAndQueued(_) => {
-                output_state.redraw_state = RedrawState::Queued;
-                return;
-            }
-        }
-
-        if output_state.unfinished_animations_remain {
-            niri.queue_redraw(&output);
-        } else {
-            niri.send_frame_callbacks(&output);
-        }
-    }
-
     pub fn seat_name(&self) -> String {
         self.session.seat()
     }
@@ -1391,8 +1367,7 @@ impl Tty {
         };
 
         // Render the elements.
-        let mut elements =
-            niri.render::<TtyRenderer>(&mut renderer, output, true, RenderTarget::Output);
+        let mut elements = niri.render_output::<TtyRenderer>(&mut renderer, output);
 
         // Visualize the damage, if enabled.
         if niri.debug_draw_damage {
@@ -1400,6 +1375,19 @@ impl Tty {
             draw_damage(&mut output_state.debug_damage_tracker, &mut elements);
         }
 
+        // Async page flips can only swap the primary plane buffer, so only allow tearing when the
+        // tearing surface is what should end up there this frame.
+        let tearing_surface = niri
+            .tearing_surface(output)
+            .filter(|_| device.supports_async_page_flip)
+            .filter(|surface| {
+                let mode = output.current_mode().unwrap();
+                let size = output.current_transform().transform_size(mode.size);
+                let scale = Scale::from(output.current_scale().fractional_scale());
+                let id = Id::from_wayland_resource(surface);
+                is_fullscreen_scanout_candidate(&elements, &id, size, scale)
+            });
+
         // Overlay planes are disabled by default as they cause weird performance issues on my
         // system.
         let flags = {
@@ -1429,6 +1417,10 @@ impl Tty {
                 }
             }
 
+            if tearing_surface.is_some() {
+                flags.insert(FrameFlags::ALLOW_TEARING);
+            }
+
             flags
         };
 
@@ -1451,6 +1443,26 @@ impl Tty {
                     }
                 }
 
+                // Report whether the surface actually made it to the primary plane.
+                let tearing = tearing_surface.is_some_and(|surface| {
+                    res.states
+                        .element_render_state(Id::from_wayland_resource(&surface))
+                        .is_some_and(|state| {
+                            state.presentation_state == RenderElementPresentationState::ZeroCopy
+                        })
+                });
+                let output_state = niri.output_state.get_mut(output).unwrap();
+                if output_state.tearing != tearing {
+                    output_state.tearing = tearing;
+
+                    let id = device.known_crtcs.get(&tty_state.crtc).map(|info| info.id);
+                    let mut ipc_outputs = self.ipc_outputs.lock().unwrap();
+                    if let Some(ipc_output) = id.and_then(|id| ipc_outputs.get_mut(&id)) {
+                        ipc_output.tearing = tearing;
+                        niri.ipc_outputs_changed = true;
+                    }
+                }
+
                 niri.update_primary_scanout_output(output, &res.states);
                 if let Some(dmabuf_feedback) = surface.dmabuf_feedback.as_ref() {
                     niri.send_dmabuf_feedbacks(output, dmabuf_feedback, &res.states);
@@ -1667,15 +1679,16 @@ impl Tty {
                         is_vrr_capable(&device.drm, connector.handle()) == Some(true)
                     });
                 let vrr_enabled = surface.is_some_and(|surface| surface.compositor.vrr_enabled());
+                let powered_off = surface.is_some_and(|surface| surface.powered_off);
 
-                let logical = niri
-                    .global_space
-                    .outputs()
-                    .find(|output| {
-                        let tty_state: &TtyOutputState = output.user_data().get().unwrap();
-                        tty_state.node == *node && tty_state.crtc == crtc
-                    })
-                    .map(logical_output);
+                let output = niri.global_space.outputs().find(|output| {
+                    let tty_state: &TtyOutputState = output.user_data().get().unwrap();
+                    tty_state.node == *node && tty_state.crtc == crtc
+                });
+                let logical = output.map(logical_output);
+                let tearing = output
+                    .and_then(|output| niri.output_state.get(output))
+                    .is_some_and(|state| state.tearing);
 
                 let id = device.known_crtcs.get(&crtc).map(|info| info.id);
                 let id = id.unwrap_or_else(|| {
@@ -1693,6 +1706,8 @@ impl Tty {
                     current_mode,
                     vrr_supported,
                     vrr_enabled,
+                    tearing,
+                    powered_off,
                     logical,
                 };
 
@@ -1741,6 +1756,43 @@ impl Tty {
         }
     }
 
+    pub fn is_output_powered(&self, output: &Output) -> bool {
+        let tty_state: &TtyOutputState = output.user_data().get().unwrap();
+        self.devices
+            .get(&tty_state.node)
+            .and_then(|device| device.surfaces.get(&tty_state.crtc))
+            .is_some_and(|surface| !surface.powered_off)
+    }
+
+    pub fn set_output_powered(&mut self, niri: &mut Niri, output: &Output, powered: bool) {
+        let _span = tracy_client::span!("Tty::set_output_powered");
+
+        let tty_state: &TtyOutputState = output.user_data().get().unwrap();
+        let Some(device) = self.devices.get_mut(&tty_state.node) else {
+            error!("missing output device");
+            return;
+        };
+        let Some(surface) = device.surfaces.get_mut(&tty_state.crtc) else {
+            error!("missing surface");
+            return;
+        };
+
+        if surface.powered_off != powered {
+            return;
+        }
+        surface.powered_off = !powered;
+
+        // Same as in set_monitors_active(): we only disable the CRTC here, and the next rendered
+        // frame will enable it back.
+        if !powered {
+            if let Err(err) = surface.compositor.clear() {
+                warn!("error clearing drm surface: {err:?}");
+            }
+        }
+
+        self.refresh_ipc_outputs(niri);
+    }
+
     pub fn set_output_on_demand_vrr(&mut self, niri: &mut Niri, output: &Output, enable_vrr: bool) {
         let _span = tracy_client::span!("Tty::set_output_on_demand_vrr");
 
@@ -2314,52 +2366,6 @@ fn suspend() -> anyhow::Result<()> {
     Ok(())
 }
 
-fn queue_estimated_vblank_timer(
-    niri: &mut Niri,
-    output: Output,
-    target_presentation_time: Duration,
-) {
-    let output_state = niri.output_state.get_mut(&output).unwrap();
-    match mem::take(&mut output_state.redraw_state) {
-        RedrawState::Idle => unreachable!(),
-        RedrawState::Queued => (),
-        RedrawState::WaitingForVBlank { .. } => unreachable!(),
-        RedrawState::WaitingForEstimatedVBlank(token)
-        | RedrawState::WaitingForEstimatedVBlankAndQueued(token) => {
-            output_state.redraw_state = RedrawState::WaitingForEstimatedVBlank(token);
-            return;
-        }
-    }
-
-    let now = get_monotonic_time();
-    let mut duration = target_presentation_time.saturating_sub(now);
-
-    // No use setting a zero timer, since we'll send frame callbacks anyway right after the call to
-    // render(). This can happen for example with unknown presentation time from DRM.
-    if duration.is_zero() {
-        duration += output_state
-            .frame_clock
-            .refresh_interval()
-            // Unknown refresh interval, i.e. winit backend. Would be good to estimate it somehow
-            // but it's not that important for this code path.
-            .unwrap_or(Duration::from_micros(16_667));
-    }
-
-    trace!("queueing estimated vblank timer to fire in {duration:?}");
-
-    let timer = Timer::from_duration(duration);
-    let token = niri
-        .event_loop
-        .insert_source(timer, move |_, _, data| {
-            data.backend
-                .tty()
-                .on_estimated_vblank_timer(&mut data.niri, output.clone());
-            TimeoutAction::Drop
-        })
-        .unwrap();
-    output_state.redraw_state = RedrawState::WaitingForEstimatedVBlank(token);
-}
-
 fn pick_mode(
     connector: &connector::Info,
     target: Option<niri_ipc::ConfiguredMode>,
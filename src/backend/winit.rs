@@ -20,7 +20,7 @@ use smithay::wayland::presentation::Refresh;
 use super::{IpcOutputMap, OutputId, RenderResult};
 use crate::niri::{Niri, RedrawState, State};
 use crate::render_helpers::debug::draw_damage;
-use crate::render_helpers::{resources, shaders, RenderTarget};
+use crate::render_helpers::{resources, shaders};
 use crate::utils::{get_monotonic_time, logical_output};
 
 pub struct Winit {
@@ -84,6 +84,8 @@ impl Winit {
                 current_mode: Some(0),
                 vrr_supported: false,
                 vrr_enabled: false,
+                tearing: false,
+                powered_off: false,
                 logical: Some(logical_output(&output)),
             },
         )])));
@@ -144,17 +146,9 @@ impl Winit {
         resources::init(renderer);
         shaders::init(renderer);
 
-        let config = self.config.borrow();
-        if let Some(src) = config.animations.window_resize.custom_shader.as_deref() {
-            shaders::set_custom_resize_program(renderer, Some(src));
+        if !shaders::set_custom_programs(renderer, &self.config.borrow()) {
+            niri.config_error_notification.show_shader_error();
         }
-        if let Some(src) = config.animations.window_close.custom_shader.as_deref() {
-            shaders::set_custom_close_program(renderer, Some(src));
-        }
-        if let Some(src) = config.animations.window_open.custom_shader.as_deref() {
-            shaders::set_custom_open_program(renderer, Some(src));
-        }
-        drop(config);
 
         niri.update_shaders();
 
@@ -176,12 +170,7 @@ impl Winit {
         let _span = tracy_client::span!("Winit::render");
 
         // Render the elements.
-        let mut elements = niri.render::<GlesRenderer>(
-            self.backend.renderer(),
-            output,
-            true,
-            RenderTarget::Output,
-        );
+        let mut elements = niri.render_output::<GlesRenderer>(self.backend.renderer(), output);
 
         // Visualize the damage, if enabled.
         if niri.debug_draw_damage {
@@ -3,8 +3,9 @@ use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 use clap_complete::Shell;
-use niri_ipc::{Action, OutputAction};
+use niri_ipc::{Action, EventKind, OutputAction};
 
+use crate::render_helpers::shaders::CustomShaderKind;
 use crate::utils::version;
 
 #[derive(Parser)]
@@ -26,6 +27,13 @@ pub struct Cli {
     /// on a TTY as your non-main compositor instance, to avoid messing up the global environment.
     #[arg(long)]
     pub session: bool,
+    /// Run without a display, rendering outputs offscreen.
+    ///
+    /// Useful for capturing a session on a machine without a GPU. Rendering uses the
+    /// `render-drm-device` from the debug config section if set, and an EGL software renderer
+    /// (such as llvmpipe) otherwise.
+    #[arg(long)]
+    pub headless: bool,
     /// Command to run upon compositor startup.
     #[arg(last = true)]
     pub command: Vec<OsString>,
@@ -100,13 +108,50 @@ pub enum Msg {
         action: OutputAction,
     },
     /// Start continuously receiving events from the compositor.
-    EventStream,
+    EventStream {
+        /// Only receive events of these kinds.
+        #[arg(short, long, value_delimiter = ',')]
+        kind: Vec<EventKind>,
+        /// Only receive workspace and window events for this output.
+        #[arg(short, long)]
+        output: Option<String>,
+        /// Only receive workspace and window events for the workspace with this id.
+        #[arg(long)]
+        workspace_id: Option<u64>,
+        /// Only receive window events for the window with this id.
+        #[arg(long)]
+        window_id: Option<u64>,
+    },
     /// Print the version of the running niri instance.
     Version,
     /// Request an error from the running niri instance.
     RequestError,
     /// Print the overview state.
     OverviewState,
+    /// Save the arrangement of windows in the layout to a file.
+    SaveSession {
+        /// File to write the session to.
+        path: PathBuf,
+    },
+    /// Arrange windows that open from now on according to a saved session file.
+    ///
+    /// Windows are matched to their saved place by app id and title as they open, so run this
+    /// before launching your applications.
+    RestoreSession {
+        /// Session file previously written by `niri msg save-session`.
+        path: PathBuf,
+    },
+    /// Check a custom shader for compilation errors.
+    ///
+    /// The shader is compiled on an EGL software renderer (such as llvmpipe) the same way that
+    /// niri compiles it from the config, so this doesn't need a running niri instance.
+    ValidateShader {
+        /// Which custom shader the file is for.
+        #[arg(short, long)]
+        kind: ShaderKind,
+        /// File with the shader code.
+        path: PathBuf,
+    },
 }
 
 #[derive(Clone, Debug, clap::ValueEnum)]
@@ -119,6 +164,27 @@ pub enum CompletionShell {
     Nushell,
 }
 
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum ShaderKind {
+    WindowOpen,
+    WindowClose,
+    WindowResize,
+    WorkspaceSwitch,
+    PostProcess,
+}
+
+impl From<ShaderKind> for CustomShaderKind {
+    fn from(kind: ShaderKind) -> Self {
+        match kind {
+            ShaderKind::WindowOpen => CustomShaderKind::WindowOpen,
+            ShaderKind::WindowClose => CustomShaderKind::WindowClose,
+            ShaderKind::WindowResize => CustomShaderKind::WindowResize,
+            ShaderKind::WorkspaceSwitch => CustomShaderKind::WorkspaceSwitch,
+            ShaderKind::PostProcess => CustomShaderKind::PostProcess,
+        }
+    }
+}
+
 impl TryFrom<CompletionShell> for Shell {
     type Error = &'static str;
 
@@ -1,6 +1,6 @@
 use std::collections::hash_map::Entry;
 
-use niri_ipc::PositionChange;
+use niri_ipc::{PositionChange, SizeChange};
 use smithay::backend::renderer::utils::on_commit_buffer_handler;
 use smithay::input::pointer::{CursorImageStatus, CursorImageSurfaceData};
 use smithay::reexports::calloop::Interest;
@@ -24,6 +24,7 @@ use crate::layout::{ActivateWindow, AddWindowTarget};
 use crate::niri::{CastTarget, ClientState, LockState, State};
 use crate::utils::transaction::Transaction;
 use crate::utils::{is_mapped, send_scale_transform};
+use crate::window::session::SessionPlacementKind;
 use crate::window::{InitialConfigureState, Mapped, ResolvedWindowRules, Unmapped};
 
 impl CompositorHandler for State {
@@ -90,38 +91,73 @@ impl CompositorHandler for State {
 
                     let toplevel = window.toplevel().expect("no X11 support");
 
-                    let (rules, width, height, is_full_width, output, workspace_id) =
-                        if let InitialConfigureState::Configured {
+                    let (
+                        rules,
+                        width,
+                        height,
+                        is_full_width,
+                        output,
+                        workspace_id,
+                        session_placement,
+                    ) = if let InitialConfigureState::Configured {
+                        rules,
+                        width,
+                        height,
+                        floating_width: _,
+                        floating_height: _,
+                        is_full_width,
+                        output,
+                        workspace_name,
+                        session_placement,
+                    } = state
+                    {
+                        // Check that the output is still connected.
+                        let output =
+                            output.filter(|o| self.niri.layout.monitor_for_output(o).is_some());
+
+                        // Check that the workspace still exists.
+                        let workspace_id = workspace_name
+                            .as_deref()
+                            .and_then(|n| self.niri.layout.find_workspace_by_name(n))
+                            .map(|(_, ws)| ws.id());
+
+                        // Windows from a restored session can also go to an unnamed workspace.
+                        let workspace_id = workspace_id.or_else(|| {
+                            let placement = session_placement
+                                .as_ref()
+                                .filter(|p| p.workspace_name.is_none())?;
+                            let mon = self.niri.layout.monitor_for_output(output.as_ref()?)?;
+                            let ws = mon.workspace_at(placement.workspace_idx)?;
+                            Some(ws.id())
+                        });
+
+                        (
                             rules,
                             width,
                             height,
-                            floating_width: _,
-                            floating_height: _,
                             is_full_width,
                             output,
-                            workspace_name,
-                        } = state
-                        {
-                            // Check that the output is still connected.
-                            let output =
-                                output.filter(|o| self.niri.layout.monitor_for_output(o).is_some());
-
-                            // Check that the workspace still exists.
-                            let workspace_id = workspace_name
-                                .as_deref()
-                                .and_then(|n| self.niri.layout.find_workspace_by_name(n))
-                                .map(|(_, ws)| ws.id());
-
-                            (rules, width, height, is_full_width, output, workspace_id)
-                        } else {
-                            error!("window map must happen after initial configure");
-                            (ResolvedWindowRules::empty(), None, None, false, None, None)
-                        };
+                            workspace_id,
+                            session_placement,
+                        )
+                    } else {
+                        error!("window map must happen after initial configure");
+                        (
+                            ResolvedWindowRules::empty(),
+                            None,
+                            None,
+                            false,
+                            None,
+                            None,
+                            None,
+                        )
+                    };
 
                     // The GTK about dialog sets min/max size after the initial configure but
                     // before mapping, so we need to compute open_floating at the last possible
                     // moment, that is here.
                     let is_floating = rules.compute_open_floating(toplevel);
+                    let is_sticky = rules.open_sticky == Some(true);
 
                     // Figure out if we should activate the window.
                     let activate = rules.open_focused.map(|focus| {
@@ -170,8 +206,28 @@ impl CompositorHandler for State {
                     let hook = add_mapped_toplevel_pre_commit_hook(toplevel);
                     let mapped = Mapped::new(window, rules, hook);
                     let window = mapped.window.clone();
+                    let id = mapped.id();
 
-                    let target = if let Some(p) = &parent {
+                    // Windows from the same saved column go next to each other, so that they can
+                    // be put back together.
+                    let session_column = session_placement.and_then(|p| match p.kind {
+                        SessionPlacementKind::Tiled { column, height, .. } => {
+                            Some((column, height))
+                        }
+                        SessionPlacementKind::Floating { .. } => None,
+                    });
+                    let column_sibling = session_column.and_then(|(column, _)| {
+                        let ids = self.niri.session_restore.column_windows(column);
+                        self.niri
+                            .layout
+                            .windows()
+                            .find(|(_, m)| ids.contains(&m.id()) && !m.is_floating())
+                            .map(|(_, m)| m.window.clone())
+                    });
+
+                    let target = if let Some(sibling) = &column_sibling {
+                        AddWindowTarget::NextTo(sibling)
+                    } else if let Some(p) = &parent {
                         // Open dialogs next to their parent window.
                         AddWindowTarget::NextTo(p)
                     } else if let Some(id) = workspace_id {
@@ -190,8 +246,30 @@ impl CompositorHandler for State {
                         is_floating,
                         activate,
                     );
+                    let output = output.cloned();
+
+                    if let Some((column, height)) = session_column {
+                        if column_sibling.is_some() {
+                            self.niri.layout.consume_or_expel_window_left(Some(&window));
+
+                            // Moving into another column resets the window height.
+                            if let Some(height) = height {
+                                let change = SizeChange::SetFixed(height.round() as i32);
+                                self.niri.layout.set_window_height(Some(&window), change);
+                            }
+                        }
+
+                        self.niri.session_restore.add_to_column(column, id);
+                    }
 
-                    if let Some(output) = output.cloned() {
+                    if is_sticky {
+                        self.niri.layout.set_window_sticky(Some(&window), true);
+                    }
+
+                    // A modal dialog goes to its parent and takes the focus from it.
+                    self.niri.refresh_modal_dialogs();
+
+                    if let Some(output) = output {
                         self.niri.layout.start_open_animation_for_window(&window);
 
                         let new_focus = self.niri.layout.focus().map(|m| &m.window);
@@ -250,6 +328,7 @@ impl CompositorHandler for State {
 
                     self.niri.layout.remove_window(&window, transaction.clone());
                     self.add_default_dmabuf_pre_commit_hook(surface);
+                    self.niri.refresh_modal_dialogs();
 
                     // If this is the only instance, then this transaction will complete
                     // immediately, so no need to set the timer.
@@ -313,6 +392,8 @@ impl CompositorHandler for State {
 
                 if let Some(output) = output {
                     self.niri.queue_redraw(&output);
+                } else {
+                    self.render_off_output_image_copy_captures();
                 }
                 return;
             }
@@ -329,6 +410,8 @@ impl CompositorHandler for State {
             self.niri.layout.update_window(&window, None);
             if let Some(output) = output {
                 self.niri.queue_redraw(&output);
+            } else {
+                self.render_off_output_image_copy_captures();
             }
             return;
         }
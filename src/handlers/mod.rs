@@ -12,6 +12,7 @@ use std::time::Duration;
 use smithay::backend::allocator::dmabuf::Dmabuf;
 use smithay::backend::drm::DrmNode;
 use smithay::backend::input::{InputEvent, TabletToolDescriptor};
+use smithay::desktop::utils::surface_primary_scanout_output;
 use smithay::desktop::{PopupKind, PopupManager};
 use smithay::input::pointer::{
     CursorIcon, CursorImageStatus, CursorImageSurfaceData, PointerHandle,
@@ -19,6 +20,8 @@ use smithay::input::pointer::{
 use smithay::input::{keyboard, Seat, SeatHandler, SeatState};
 use smithay::output::Output;
 use smithay::reexports::rustix::fs::{fcntl_setfl, OFlags};
+use smithay::reexports::wayland_protocols::ext::foreign_toplevel_list::v1::server::ext_foreign_toplevel_handle_v1::ExtForeignToplevelHandleV1;
+use smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_session_v1::ExtImageCopyCaptureSessionV1;
 use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel;
 use smithay::reexports::wayland_protocols_wlr::screencopy::v1::server::zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1;
 use smithay::reexports::wayland_server::protocol::wl_data_source::WlDataSource;
@@ -31,6 +34,7 @@ use smithay::wayland::dmabuf::{DmabufGlobal, DmabufHandler, DmabufState, ImportN
 use smithay::wayland::drm_lease::{
     DrmLease, DrmLeaseBuilder, DrmLeaseHandler, DrmLeaseRequest, DrmLeaseState, LeaseRejected,
 };
+use smithay::wayland::foreign_toplevel_list::{ForeignToplevelListHandler, ForeignToplevelListState};
 use smithay::wayland::fractional_scale::FractionalScaleHandler;
 use smithay::wayland::idle_inhibit::IdleInhibitHandler;
 use smithay::wayland::idle_notify::{IdleNotifierHandler, IdleNotifierState};
@@ -60,18 +64,21 @@ use smithay::wayland::selection::{SelectionHandler, SelectionTarget};
 use smithay::wayland::session_lock::{
     LockSurface, SessionLockHandler, SessionLockManagerState, SessionLocker,
 };
+use smithay::wayland::shell::xdg::ToplevelSurface;
 use smithay::wayland::tablet_manager::TabletSeatHandler;
 use smithay::wayland::xdg_activation::{
     XdgActivationHandler, XdgActivationState, XdgActivationToken, XdgActivationTokenData,
 };
 use smithay::{
     delegate_cursor_shape, delegate_data_control, delegate_data_device, delegate_dmabuf,
-    delegate_drm_lease, delegate_ext_data_control, delegate_fractional_scale,
+    delegate_drm_lease, delegate_ext_data_control, delegate_foreign_toplevel_list,
+    delegate_fractional_scale,
     delegate_idle_inhibit, delegate_idle_notify, delegate_input_method_manager,
     delegate_keyboard_shortcuts_inhibit, delegate_output, delegate_pointer_constraints,
     delegate_pointer_gestures, delegate_presentation, delegate_primary_selection,
     delegate_relative_pointer, delegate_seat, delegate_security_context, delegate_session_lock,
-    delegate_single_pixel_buffer, delegate_tablet_manager, delegate_text_input_manager,
+    delegate_single_pixel_buffer, delegate_tablet_manager, delegate_tearing_control,
+    delegate_text_input_manager,
     delegate_viewporter, delegate_virtual_keyboard_manager, delegate_xdg_activation,
 };
 
@@ -79,24 +86,35 @@ pub use crate::handlers::xdg_shell::KdeDecorationsModeState;
 use crate::layout::workspace::WorkspaceId;
 use crate::layout::ActivateWindow;
 use crate::niri::{DndIcon, NewClient, State};
+use crate::protocols::color_management::{ColorManagementHandler, ColorManagementState};
 use crate::protocols::ext_workspace::{self, ExtWorkspaceHandler, ExtWorkspaceManagerState};
 use crate::protocols::foreign_toplevel::{
     self, ForeignToplevelHandler, ForeignToplevelManagerState,
 };
 use crate::protocols::gamma_control::{GammaControlHandler, GammaControlManagerState};
+use crate::protocols::image_copy_capture::{
+    CaptureConstraints, ImageCaptureSource, ImageCopyCaptureHandler, ImageCopyCaptureState,
+};
 use crate::protocols::mutter_x11_interop::MutterX11InteropHandler;
 use crate::protocols::output_management::{OutputManagementHandler, OutputManagementManagerState};
+use crate::protocols::output_power_management::{
+    OutputPowerManagementHandler, OutputPowerManagementState,
+};
 use crate::protocols::screencopy::{Screencopy, ScreencopyHandler, ScreencopyManagerState};
 use crate::protocols::virtual_pointer::{
     VirtualPointerAxisEvent, VirtualPointerButtonEvent, VirtualPointerHandler,
     VirtualPointerInputBackend, VirtualPointerManagerState, VirtualPointerMotionAbsoluteEvent,
     VirtualPointerMotionEvent,
 };
-use crate::utils::{output_size, send_scale_transform, with_toplevel_role};
+use crate::protocols::xdg_dialog::XdgDialogHandler;
+use crate::protocols::xdg_toplevel_tag::XdgToplevelTagHandler;
+use crate::utils::{output_size, send_scale_transform, with_toplevel_extra, with_toplevel_role};
+use crate::window::mapped::MappedId;
 use crate::{
-    delegate_ext_workspace, delegate_foreign_toplevel, delegate_gamma_control,
-    delegate_mutter_x11_interop, delegate_output_management, delegate_screencopy,
-    delegate_virtual_pointer,
+    delegate_background_effect, delegate_color_management, delegate_ext_workspace,
+    delegate_foreign_toplevel, delegate_gamma_control, delegate_image_copy_capture,
+    delegate_mutter_x11_interop, delegate_output_management, delegate_output_power_management,
+    delegate_screencopy, delegate_virtual_pointer, delegate_xdg_dialog, delegate_xdg_toplevel_tag,
 };
 
 pub const XDG_ACTIVATION_TOKEN_TIMEOUT: Duration = Duration::from_secs(10);
@@ -424,6 +442,8 @@ delegate_output!(State);
 
 delegate_presentation!(State);
 
+delegate_tearing_control!(State);
+
 impl DmabufHandler for State {
     fn dmabuf_state(&mut self) -> &mut DmabufState {
         &mut self.niri.dmabuf_state
@@ -578,6 +598,13 @@ impl ForeignToplevelHandler for State {
 }
 delegate_foreign_toplevel!(State);
 
+impl ForeignToplevelListHandler for State {
+    fn foreign_toplevel_list_state(&mut self) -> &mut ForeignToplevelListState {
+        self.niri.ext_foreign_toplevel_state.list_state()
+    }
+}
+delegate_foreign_toplevel_list!(State);
+
 impl ExtWorkspaceHandler for State {
     fn ext_workspace_manager_state(&mut self) -> &mut ExtWorkspaceManagerState {
         &mut self.niri.ext_workspace_state
@@ -642,6 +669,46 @@ impl ScreencopyHandler for State {
 }
 delegate_screencopy!(State);
 
+impl ImageCopyCaptureHandler for State {
+    fn image_copy_capture_state(&mut self) -> &mut ImageCopyCaptureState {
+        &mut self.niri.image_copy_capture_state
+    }
+
+    fn window_for_toplevel_handle(
+        &mut self,
+        handle: &ExtForeignToplevelHandleV1,
+    ) -> Option<MappedId> {
+        self.niri
+            .ext_foreign_toplevel_state
+            .window_for_handle(handle)
+    }
+
+    fn capture_constraints(
+        &mut self,
+        source: &ImageCaptureSource,
+        cursor: bool,
+    ) -> Option<CaptureConstraints> {
+        self.niri.image_capture_constraints(source, cursor)
+    }
+
+    fn frame(&mut self, session: &ExtImageCopyCaptureSessionV1, screencopy: Screencopy) {
+        let output = screencopy.output().cloned();
+        let Some(queue) = self.niri.image_copy_capture_state.get_queue_mut(session) else {
+            trace!("image copy capture session stopped already");
+            return;
+        };
+        queue.push(screencopy);
+
+        match output {
+            // Frames are rendered on the next redraw of the output, once there's damage.
+            Some(output) => self.niri.queue_redraw(&output),
+            // Windows that aren't on any output are rendered right away, and on their commits.
+            None => self.render_off_output_image_copy_captures(),
+        }
+    }
+}
+delegate_image_copy_capture!(State);
+
 impl VirtualPointerHandler for State {
     fn virtual_pointer_manager_state(&mut self) -> &mut VirtualPointerManagerState {
         &mut self.niri.virtual_pointer_state
@@ -747,6 +814,34 @@ impl GammaControlHandler for State {
 }
 delegate_gamma_control!(State);
 
+impl OutputPowerManagementHandler for State {
+    fn output_power_management_state(&mut self) -> &mut OutputPowerManagementState {
+        &mut self.niri.output_power_management_state
+    }
+
+    fn is_output_powered(&mut self, output: &Output) -> bool {
+        self.backend.is_output_powered(output)
+    }
+
+    fn set_output_powered(&mut self, output: &Output, powered: bool) {
+        State::set_output_powered(self, output, powered);
+    }
+}
+delegate_output_power_management!(State);
+
+impl ColorManagementHandler for State {
+    fn color_management_state(&mut self) -> &mut ColorManagementState {
+        &mut self.niri.color_management_state
+    }
+
+    fn preferred_output(&mut self, surface: &WlSurface) -> Option<Output> {
+        with_states(surface, |states| {
+            surface_primary_scanout_output(surface, states)
+        })
+    }
+}
+delegate_color_management!(State);
+
 struct UrgentOnlyMarker;
 
 impl XdgActivationHandler for State {
@@ -839,4 +934,51 @@ delegate_output_management!(State);
 impl MutterX11InteropHandler for State {}
 delegate_mutter_x11_interop!(State);
 
+impl State {
+    fn find_toplevel_surface(
+        &self,
+        toplevel: &xdg_toplevel::XdgToplevel,
+    ) -> Option<ToplevelSurface> {
+        self.niri
+            .xdg_shell_state
+            .toplevel_surfaces()
+            .iter()
+            .find(|surface| surface.xdg_toplevel() == toplevel)
+            .cloned()
+    }
+}
+
+impl XdgToplevelTagHandler for State {
+    fn set_toplevel_tag(&mut self, toplevel: &xdg_toplevel::XdgToplevel, tag: String) {
+        let Some(toplevel) = self.find_toplevel_surface(toplevel) else {
+            return;
+        };
+
+        with_toplevel_extra(toplevel.wl_surface(), |extra| extra.tag = Some(tag));
+        self.update_window_rules(&toplevel);
+    }
+}
+delegate_xdg_toplevel_tag!(State);
+
+impl XdgDialogHandler for State {
+    fn modal_changed(&mut self, toplevel: &xdg_toplevel::XdgToplevel, is_modal: bool) {
+        let Some(toplevel) = self.find_toplevel_surface(toplevel) else {
+            return;
+        };
+
+        let changed = with_toplevel_extra(toplevel.wl_surface(), |extra| {
+            let changed = extra.is_modal != is_modal;
+            extra.is_modal = is_modal;
+            changed
+        });
+        if changed {
+            self.update_window_rules(&toplevel);
+            self.niri.refresh_modal_dialogs();
+        }
+    }
+}
+delegate_xdg_dialog!(State);
+
+delegate_background_effect!(State);
+
 delegate_single_pixel_buffer!(State);
@@ -47,7 +47,8 @@ use crate::layout::ActivateWindow;
 use crate::niri::{CastTarget, PopupGrabState, State};
 use crate::utils::transaction::Transaction;
 use crate::utils::{
-    get_monotonic_time, output_matches_name, send_scale_transform, update_tiled_state, ResizeEdge,
+    get_monotonic_time, output_matches_name, send_scale_transform, update_tiled_state,
+    with_toplevel_role, ResizeEdge,
 };
 use crate::window::{InitialConfigureState, ResolvedWindowRules, Unmapped, WindowRef};
 
@@ -555,6 +556,7 @@ impl XdgShellHandler for State {
                     is_full_width,
                     output,
                     workspace_name,
+                    session_placement: _,
                 } => {
                     // Figure out the monitor following a similar logic to initial configure.
                     // FIXME: deduplicate.
@@ -684,6 +686,7 @@ impl XdgShellHandler for State {
 
         self.niri.layout.remove_window(&window, transaction.clone());
         self.add_default_dmabuf_pre_commit_hook(surface.wl_surface());
+        self.niri.refresh_modal_dialogs();
 
         // If this is the only instance, then this transaction will complete immediately, so no
         // need to set the timer.
@@ -715,6 +718,9 @@ impl XdgShellHandler for State {
     }
 
     fn parent_changed(&mut self, toplevel: ToplevelSurface) {
+        self.update_window_rules(&toplevel);
+        self.niri.refresh_modal_dialogs();
+
         let Some(parent) = toplevel.parent() else {
             return;
         };
@@ -840,17 +846,29 @@ impl State {
     pub fn send_initial_configure(&mut self, toplevel: &ToplevelSurface) {
         let _span = tracy_client::span!("State::send_initial_configure");
 
+        let ctx = self.niri.window_match_context();
         let Some(unmapped) = self.niri.unmapped_windows.get_mut(toplevel.wl_surface()) else {
             error!("window must be present in unmapped_windows in send_initial_configure()");
             return;
         };
 
         let config = self.niri.config.borrow();
-        let rules = ResolvedWindowRules::compute(
-            &config.window_rules,
-            WindowRef::Unmapped(unmapped),
-            self.niri.is_at_startup,
-        );
+        let mut rules =
+            ResolvedWindowRules::compute(&config.window_rules, WindowRef::Unmapped(unmapped), ctx);
+
+        // Check if this window has a place waiting for it in a restored session.
+        let session_placement = if self.niri.session_restore.is_empty() {
+            None
+        } else {
+            with_toplevel_role(toplevel, |role| {
+                self.niri
+                    .session_restore
+                    .take_placement(role.app_id.as_deref(), role.title.as_deref())
+            })
+        };
+        if let Some(placement) = &session_placement {
+            placement.apply_to_rules(&mut rules);
+        }
 
         let Unmapped { window, state, .. } = unmapped;
 
@@ -975,6 +993,7 @@ impl State {
             is_full_width,
             output,
             workspace_name: ws.and_then(|w| w.name().cloned()),
+            session_placement,
         };
 
         toplevel.send_configure();
@@ -1152,24 +1171,31 @@ impl State {
     }
 
     pub fn update_window_rules(&mut self, toplevel: &ToplevelSurface) {
+        let ctx = self.niri.window_match_context();
         let config = self.niri.config.borrow();
         let window_rules = &config.window_rules;
 
         if let Some(unmapped) = self.niri.unmapped_windows.get_mut(toplevel.wl_surface()) {
-            let new_rules = ResolvedWindowRules::compute(
-                window_rules,
-                WindowRef::Unmapped(unmapped),
-                self.niri.is_at_startup,
-            );
-            if let InitialConfigureState::Configured { rules, .. } = &mut unmapped.state {
+            let new_rules =
+                ResolvedWindowRules::compute(window_rules, WindowRef::Unmapped(unmapped), ctx);
+            if let InitialConfigureState::Configured {
+                rules,
+                session_placement,
+                ..
+            } = &mut unmapped.state
+            {
                 *rules = new_rules;
+
+                if let Some(placement) = session_placement {
+                    placement.apply_to_rules(rules);
+                }
             }
         } else if let Some((mapped, output)) = self
             .niri
             .layout
             .find_window_and_output_mut(toplevel.wl_surface())
         {
-            if mapped.recompute_window_rules(window_rules, self.niri.is_at_startup) {
+            if mapped.recompute_window_rules(window_rules, ctx) {
                 drop(config);
                 let output = output.cloned();
                 let window = mapped.window.clone();
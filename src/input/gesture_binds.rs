@@ -0,0 +1,372 @@
+//! Touchpad and touchscreen gestures bound to actions.
+//!
+//! Swipes bound to actions that have an interactive layout gesture (switching workspaces,
+//! scrolling the view, opening the overview) drive that gesture for as long as the fingers move.
+//! Other bound actions trigger once, when the swipe direction is recognized.
+
+use std::mem;
+
+use niri_config::{Action, DefaultGestures, SwipeDirection};
+use smithay::backend::input::TouchSlot;
+use smithay::utils::{Logical, Point};
+
+/// Finger movement after which the swipe direction is decided, in touchpad units.
+///
+/// Threshold copied from GNOME Shell.
+pub const TOUCHPAD_SWIPE_THRESHOLD: f64 = 16.;
+
+/// Finger movement after which the swipe direction is decided, in logical pixels.
+pub const TOUCHSCREEN_SWIPE_THRESHOLD: f64 = 32.;
+
+/// Pinch scale below which a pinch counts as pinching in.
+pub const PINCH_IN_SCALE: f64 = 0.8;
+
+/// Pinch scale above which a pinch counts as pinching out.
+pub const PINCH_OUT_SCALE: f64 = 1.25;
+
+/// Number of fingers needed for a touchscreen swipe.
+const MIN_TOUCHSCREEN_FINGERS: usize = 3;
+
+/// Interactive layout gesture that a swipe can drive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutGesture {
+    WorkspaceSwitch,
+    ViewOffset,
+    Overview,
+}
+
+/// Swipe driving a layout gesture through a bind.
+#[derive(Debug, Clone, Copy)]
+pub struct ContinuousSwipe {
+    pub gesture: LayoutGesture,
+    direction: SwipeDirection,
+    /// `-1.` when the action moves the layout gesture backwards.
+    sign: f64,
+}
+
+/// State of an ongoing touchpad swipe handled by the compositor.
+#[derive(Debug, Clone, Copy)]
+pub enum TouchpadSwipe {
+    /// Motion is accumulated until the swipe direction is known.
+    Recognizing { fingers: u8, cx: f64, cy: f64 },
+    /// The swipe drives a layout gesture, through a bind or as a built-in gesture (`None`).
+    Layout(Option<ContinuousSwipe>),
+    /// A bind was triggered, the rest of the swipe is ignored.
+    Triggered,
+}
+
+/// Multi-finger swipe recognizer for touchscreens.
+#[derive(Debug, Default)]
+pub struct TouchscreenSwipe {
+    /// Touch points currently down.
+    points: Vec<(TouchSlot, Point<f64, Logical>)>,
+    pub state: TouchscreenSwipeState,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub enum TouchscreenSwipeState {
+    /// Not enough fingers are down.
+    #[default]
+    Idle,
+    /// Motion of the touch points' center is accumulated until the swipe direction is known.
+    Recognizing {
+        fingers: u8,
+        cumulative: Point<f64, Logical>,
+    },
+    /// The swipe is not bound, touches go to clients until all fingers lift.
+    Passthrough,
+    /// The swipe is bound, touches don't go to clients until all fingers lift.
+    Consumed(Option<ContinuousSwipe>),
+}
+
+impl ContinuousSwipe {
+    /// Returns the layout gesture driven by a swipe bound to `action`, if any.
+    pub fn new(action: &Action, direction: SwipeDirection, is_overview_open: bool) -> Option<Self> {
+        let (gesture, sign) = match action {
+            Action::FocusWorkspaceDown => (LayoutGesture::WorkspaceSwitch, 1.),
+            Action::FocusWorkspaceUp => (LayoutGesture::WorkspaceSwitch, -1.),
+            Action::FocusColumnRight => (LayoutGesture::ViewOffset, 1.),
+            Action::FocusColumnLeft => (LayoutGesture::ViewOffset, -1.),
+            Action::OpenOverview => (LayoutGesture::Overview, 1.),
+            Action::CloseOverview => (LayoutGesture::Overview, -1.),
+            Action::ToggleOverview => (
+                LayoutGesture::Overview,
+                if is_overview_open { -1. } else { 1. },
+            ),
+            _ => return None,
+        };
+
+        Some(Self {
+            gesture,
+            direction,
+            sign,
+        })
+    }
+
+    /// Converts finger motion into the layout gesture delta.
+    pub fn delta(&self, dx: f64, dy: f64) -> f64 {
+        let along = match self.direction {
+            SwipeDirection::Up => -dy,
+            SwipeDirection::Down => dy,
+            SwipeDirection::Left => -dx,
+            SwipeDirection::Right => dx,
+        };
+        along * self.sign
+    }
+}
+
+impl LayoutGesture {
+    /// Returns whether the built-in touchpad swipe for this gesture is enabled.
+    pub fn is_default_enabled(self, config: &DefaultGestures) -> bool {
+        let gesture = match self {
+            LayoutGesture::WorkspaceSwitch => config.workspace_switch,
+            LayoutGesture::ViewOffset => config.view_scroll,
+            LayoutGesture::Overview => config.overview,
+        };
+        !config.off && !gesture.off
+    }
+}
+
+pub fn swipe_direction(dx: f64, dy: f64) -> SwipeDirection {
+    if dx.abs() > dy.abs() {
+        if dx > 0. {
+            SwipeDirection::Right
+        } else {
+            SwipeDirection::Left
+        }
+    } else if dy > 0. {
+        SwipeDirection::Down
+    } else {
+        SwipeDirection::Up
+    }
+}
+
+impl TouchscreenSwipe {
+    pub fn down(&mut self, slot: TouchSlot, pos: Point<f64, Logical>) {
+        self.points.push((slot, pos));
+
+        if let TouchscreenSwipeState::Idle | TouchscreenSwipeState::Recognizing { .. } = self.state
+        {
+            let fingers = self.points.len();
+            if fingers >= MIN_TOUCHSCREEN_FINGERS {
+                // Start over whenever another finger joins.
+                self.state = TouchscreenSwipeState::Recognizing {
+                    fingers: u8::try_from(fingers).unwrap_or(u8::MAX),
+                    cumulative: Point::from((0., 0.)),
+                };
+            }
+        }
+    }
+
+    /// Moves a touch point, returning the resulting motion of the touch points' center.
+    pub fn motion(&mut self, slot: TouchSlot, pos: Point<f64, Logical>) -> Point<f64, Logical> {
+        let count = self.points.len() as f64;
+        let Some((_, point)) = self.points.iter_mut().find(|(s, _)| *s == slot) else {
+            return Point::from((0., 0.));
+        };
+
+        let delta = pos - *point;
+        *point = pos;
+        let delta = Point::from((delta.x / count, delta.y / count));
+
+        if let TouchscreenSwipeState::Recognizing { cumulative, .. } = &mut self.state {
+            *cumulative += delta;
+        }
+
+        delta
+    }
+
+    pub fn up(&mut self, slot: TouchSlot) {
+        self.points.retain(|(s, _)| *s != slot);
+
+        if let TouchscreenSwipeState::Recognizing { fingers, .. } = self.state {
+            // Lifting a finger before the direction is known means this is not a swipe.
+            if self.points.len() < usize::from(fingers) {
+                self.state = TouchscreenSwipeState::Passthrough;
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn is_consumed(&self) -> bool {
+        matches!(self.state, TouchscreenSwipeState::Consumed(_))
+    }
+
+    /// Returns the center of the touch points.
+    pub fn center(&self) -> Point<f64, Logical> {
+        let count = self.points.len().max(1) as f64;
+        let sum = self
+            .points
+            .iter()
+            .fold(Point::from((0., 0.)), |sum, (_, pos)| sum + *pos);
+        Point::from((sum.x / count, sum.y / count))
+    }
+
+    /// Forgets all touch points, returning the previous state.
+    pub fn reset(&mut self) -> TouchscreenSwipeState {
+        self.points.clear();
+        mem::take(&mut self.state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use niri_config::DefaultGesture;
+
+    use super::*;
+
+    fn slot(id: u32) -> TouchSlot {
+        TouchSlot::from(Some(id))
+    }
+
+    #[test]
+    fn swipe_direction_picks_dominant_axis() {
+        assert_eq!(swipe_direction(10., 5.), SwipeDirection::Right);
+        assert_eq!(swipe_direction(-10., 5.), SwipeDirection::Left);
+        assert_eq!(swipe_direction(5., 10.), SwipeDirection::Down);
+        assert_eq!(swipe_direction(5., -10.), SwipeDirection::Up);
+    }
+
+    #[test]
+    fn continuous_swipe_delta() {
+        let swipe = |action, direction| ContinuousSwipe::new(&action, direction, false).unwrap();
+
+        // Moving along the swipe direction drives the gesture forward.
+        let down = swipe(Action::FocusWorkspaceDown, SwipeDirection::Up);
+        assert_eq!(down.gesture, LayoutGesture::WorkspaceSwitch);
+        assert_eq!(down.delta(3., -10.), 10.);
+        assert_eq!(down.delta(3., 10.), -10.);
+
+        let right = swipe(Action::FocusColumnRight, SwipeDirection::Left);
+        assert_eq!(right.gesture, LayoutGesture::ViewOffset);
+        assert_eq!(right.delta(-10., 3.), 10.);
+
+        // Backwards actions flip the sign.
+        let up = swipe(Action::FocusWorkspaceUp, SwipeDirection::Down);
+        assert_eq!(up.delta(3., 10.), -10.);
+
+        let left = swipe(Action::FocusColumnLeft, SwipeDirection::Right);
+        assert_eq!(left.delta(10., 3.), -10.);
+
+        let close = swipe(Action::CloseOverview, SwipeDirection::Down);
+        assert_eq!(close.gesture, LayoutGesture::Overview);
+        assert_eq!(close.delta(0., 10.), -10.);
+    }
+
+    #[test]
+    fn continuous_swipe_toggle_overview() {
+        let action = Action::ToggleOverview;
+        let open = ContinuousSwipe::new(&action, SwipeDirection::Up, false).unwrap();
+        assert_eq!(open.delta(0., -10.), 10.);
+
+        let close = ContinuousSwipe::new(&action, SwipeDirection::Up, true).unwrap();
+        assert_eq!(close.delta(0., -10.), -10.);
+    }
+
+    #[test]
+    fn continuous_swipe_needs_layout_gesture() {
+        let swipe = ContinuousSwipe::new(&Action::CloseWindow, SwipeDirection::Up, false);
+        assert!(swipe.is_none());
+    }
+
+    #[test]
+    fn default_gestures_config() {
+        let mut config = DefaultGestures::default();
+        assert!(LayoutGesture::WorkspaceSwitch.is_default_enabled(&config));
+        assert!(LayoutGesture::ViewOffset.is_default_enabled(&config));
+        assert!(LayoutGesture::Overview.is_default_enabled(&config));
+
+        config.view_scroll = DefaultGesture { off: true };
+        assert!(LayoutGesture::WorkspaceSwitch.is_default_enabled(&config));
+        assert!(!LayoutGesture::ViewOffset.is_default_enabled(&config));
+        assert!(LayoutGesture::Overview.is_default_enabled(&config));
+
+        config.off = true;
+        assert!(!LayoutGesture::WorkspaceSwitch.is_default_enabled(&config));
+        assert!(!LayoutGesture::Overview.is_default_enabled(&config));
+    }
+
+    #[test]
+    fn touchscreen_swipe_recognizes_three_fingers() {
+        let mut swipe = TouchscreenSwipe::default();
+
+        swipe.down(slot(0), Point::from((0., 0.)));
+        swipe.down(slot(1), Point::from((30., 0.)));
+        assert!(matches!(swipe.state, TouchscreenSwipeState::Idle));
+
+        swipe.down(slot(2), Point::from((60., 0.)));
+        assert!(matches!(
+            swipe.state,
+            TouchscreenSwipeState::Recognizing { fingers: 3, .. }
+        ));
+        assert_eq!(swipe.center(), Point::from((30., 0.)));
+
+        // Motion of one finger moves the center by a third.
+        let delta = swipe.motion(slot(1), Point::from((30., 30.)));
+        assert_eq!(delta, Point::from((0., 10.)));
+        swipe.motion(slot(0), Point::from((0., 30.)));
+        let TouchscreenSwipeState::Recognizing { cumulative, .. } = swipe.state else {
+            panic!("swipe should still be recognizing");
+        };
+        assert_eq!(cumulative, Point::from((0., 20.)));
+
+        // Unknown touch points don't move anything.
+        let delta = swipe.motion(slot(5), Point::from((100., 100.)));
+        assert_eq!(delta, Point::from((0., 0.)));
+
+        // Another finger starts recognizing over.
+        swipe.down(slot(3), Point::from((90., 0.)));
+        let TouchscreenSwipeState::Recognizing {
+            fingers,
+            cumulative,
+        } = swipe.state
+        else {
+            panic!("swipe should still be recognizing");
+        };
+        assert_eq!(fingers, 4);
+        assert_eq!(cumulative, Point::from((0., 0.)));
+    }
+
+    #[test]
+    fn touchscreen_swipe_lifting_finger_passes_through() {
+        let mut swipe = TouchscreenSwipe::default();
+        for id in 0..3 {
+            swipe.down(slot(id), Point::from((f64::from(id) * 30., 0.)));
+        }
+
+        swipe.up(slot(1));
+        assert!(matches!(swipe.state, TouchscreenSwipeState::Passthrough));
+        assert!(!swipe.is_consumed());
+
+        // More fingers don't turn it back into a swipe.
+        swipe.down(slot(4), Point::from((0., 0.)));
+        assert!(matches!(swipe.state, TouchscreenSwipeState::Passthrough));
+
+        swipe.up(slot(0));
+        swipe.up(slot(2));
+        swipe.up(slot(4));
+        assert!(swipe.is_empty());
+    }
+
+    #[test]
+    fn touchscreen_swipe_reset() {
+        let mut swipe = TouchscreenSwipe::default();
+        for id in 0..3 {
+            swipe.down(slot(id), Point::from((0., 0.)));
+        }
+        swipe.state = TouchscreenSwipeState::Consumed(None);
+        assert!(swipe.is_consumed());
+
+        // Lifting fingers doesn't end a consumed swipe.
+        swipe.up(slot(0));
+        assert!(swipe.is_consumed());
+
+        let state = swipe.reset();
+        assert!(matches!(state, TouchscreenSwipeState::Consumed(None)));
+        assert!(matches!(swipe.state, TouchscreenSwipeState::Idle));
+        assert!(swipe.is_empty());
+    }
+}
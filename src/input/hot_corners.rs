@@ -0,0 +1,273 @@
+//! Hot corners and edges of outputs.
+
+use std::time::Duration;
+
+use niri_config::HotCornerAction;
+use niri_ipc::HotCorner;
+use smithay::output::Output;
+use smithay::utils::{Logical, Point, Size};
+
+/// Gap between pointer motion events after which the pointer counts as having stopped.
+const MAX_MOTION_GAP: Duration = Duration::from_millis(100);
+
+/// Pointer inside a hot corner or edge.
+#[derive(Debug)]
+pub struct HotCornerState {
+    pub output: Output,
+    pub corner: HotCorner,
+    pub action: HotCornerAction,
+    /// When the pointer entered the corner.
+    ///
+    /// Also tells apart the delay timers of consecutive visits to the same corner.
+    pub entered: Duration,
+    /// Whether the pointer stayed in the corner for long enough.
+    pub dwelled: bool,
+    /// How far the pointer pushed against the output edge, in logical pixels.
+    pub pressure: f64,
+    /// Fastest pointer speed in the corner, in logical pixels per second.
+    pub speed: f64,
+    /// Whether the action already ran, so that it runs only once per visit.
+    pub triggered: bool,
+}
+
+impl HotCornerState {
+    pub fn new(
+        output: Output,
+        corner: HotCorner,
+        action: HotCornerAction,
+        entered: Duration,
+    ) -> Self {
+        Self {
+            output,
+            corner,
+            entered,
+            dwelled: action.delay.is_zero(),
+            pressure: 0.,
+            speed: 0.,
+            triggered: false,
+            action,
+        }
+    }
+
+    /// Accounts for pointer motion in the corner, `None` for absolute motion.
+    pub fn on_motion(&mut self, motion: Option<EdgeMotion>) {
+        match motion {
+            Some(motion) => {
+                self.pressure += motion.overshoot.x.hypot(motion.overshoot.y);
+                self.speed = self.speed.max(motion.speed);
+            }
+            // Absolute devices can neither push against the edge nor fling the pointer into it.
+            None => {
+                self.pressure = f64::INFINITY;
+                self.speed = f64::INFINITY;
+            }
+        }
+    }
+
+    pub fn should_trigger(&self) -> bool {
+        !self.triggered
+            && self.dwelled
+            && self.pressure >= self.action.pressure
+            && self.speed >= self.action.velocity
+    }
+}
+
+/// Relative pointer motion in a hot corner or edge.
+#[derive(Debug, Clone, Copy)]
+pub struct EdgeMotion {
+    /// Pointer motion cut off at the output edge.
+    pub overshoot: Point<f64, Logical>,
+    /// Pointer speed, in logical pixels per second.
+    pub speed: f64,
+}
+
+/// Tracks the speed of relative pointer motion.
+#[derive(Debug, Default)]
+pub struct PointerSpeed {
+    last_time: Option<Duration>,
+}
+
+impl PointerSpeed {
+    /// Returns the pointer speed for a motion event, in logical pixels per second.
+    pub fn update(&mut self, time: Duration, delta: Point<f64, Logical>) -> f64 {
+        let Some(last_time) = self.last_time.replace(time) else {
+            return 0.;
+        };
+
+        let dt = time.saturating_sub(last_time);
+        if dt.is_zero() || dt > MAX_MOTION_GAP {
+            return 0.;
+        }
+
+        delta.x.hypot(delta.y) / dt.as_secs_f64()
+    }
+}
+
+/// Returns the corner or edge at the position within an output of this size.
+///
+/// Hot corners and edges are one logical pixel thick.
+pub fn corner_at(size: Size<f64, Logical>, pos: Point<f64, Logical>) -> Option<HotCorner> {
+    // The pointer is kept within the output minus one pixel at the bottom and right.
+    let left = pos.x < 1.;
+    let right = pos.x >= size.w - 1.;
+    let top = pos.y < 1.;
+    let bottom = pos.y >= size.h - 1.;
+
+    let corner = match (top, bottom, left, right) {
+        (true, _, true, _) => HotCorner::TopLeft,
+        (true, _, _, true) => HotCorner::TopRight,
+        (_, true, true, _) => HotCorner::BottomLeft,
+        (_, true, _, true) => HotCorner::BottomRight,
+        (true, _, _, _) => HotCorner::TopEdge,
+        (_, true, _, _) => HotCorner::BottomEdge,
+        (_, _, true, _) => HotCorner::LeftEdge,
+        (_, _, _, true) => HotCorner::RightEdge,
+        _ => return None,
+    };
+    Some(corner)
+}
+
+/// Returns the direction pointing out of the output across an edge.
+///
+/// Returns `None` for corners.
+pub fn edge_normal(corner: HotCorner) -> Option<Point<f64, Logical>> {
+    let normal = match corner {
+        HotCorner::TopEdge => (0., -1.),
+        HotCorner::BottomEdge => (0., 1.),
+        HotCorner::LeftEdge => (-1., 0.),
+        HotCorner::RightEdge => (1., 0.),
+        HotCorner::TopLeft
+        | HotCorner::TopRight
+        | HotCorner::BottomLeft
+        | HotCorner::BottomRight => return None,
+    };
+    Some(Point::from(normal))
+}
+
+#[cfg(test)]
+mod tests {
+    use niri_config::Action;
+    use smithay::output::{PhysicalProperties, Subpixel};
+
+    use super::*;
+
+    fn size() -> Size<f64, Logical> {
+        Size::from((1920., 1080.))
+    }
+
+    fn state(pressure: f64, velocity: f64) -> HotCornerState {
+        let action = HotCornerAction {
+            action: Action::ToggleOverview,
+            delay: Duration::ZERO,
+            pressure,
+            velocity,
+        };
+        let output = Output::new(
+            String::from("test"),
+            PhysicalProperties {
+                size: Size::from((0, 0)),
+                subpixel: Subpixel::Unknown,
+                make: String::new(),
+                model: String::new(),
+            },
+        );
+        HotCornerState::new(output, HotCorner::RightEdge, action, Duration::ZERO)
+    }
+
+    fn motion(overshoot: f64, speed: f64) -> Option<EdgeMotion> {
+        Some(EdgeMotion {
+            overshoot: Point::from((overshoot, 0.)),
+            speed,
+        })
+    }
+
+    #[test]
+    fn corners() {
+        let at = |x, y| corner_at(size(), Point::from((x, y)));
+        assert_eq!(at(0., 0.), Some(HotCorner::TopLeft));
+        assert_eq!(at(1919., 0.5), Some(HotCorner::TopRight));
+        assert_eq!(at(0.5, 1079.), Some(HotCorner::BottomLeft));
+        assert_eq!(at(1919.5, 1079.5), Some(HotCorner::BottomRight));
+    }
+
+    #[test]
+    fn edges() {
+        let at = |x, y| corner_at(size(), Point::from((x, y)));
+        assert_eq!(at(500., 0.), Some(HotCorner::TopEdge));
+        assert_eq!(at(500., 1079.), Some(HotCorner::BottomEdge));
+        assert_eq!(at(0., 500.), Some(HotCorner::LeftEdge));
+        assert_eq!(at(1919., 500.), Some(HotCorner::RightEdge));
+    }
+
+    #[test]
+    fn outside_corners_and_edges() {
+        let at = |x, y| corner_at(size(), Point::from((x, y)));
+        assert_eq!(at(1., 1.), None);
+        assert_eq!(at(500., 500.), None);
+        assert_eq!(at(1918.9, 1078.9), None);
+    }
+
+    #[test]
+    fn edge_normals_point_out() {
+        let normal = |corner| edge_normal(corner).map(|p: Point<f64, Logical>| (p.x, p.y));
+        assert_eq!(normal(HotCorner::TopEdge), Some((0., -1.)));
+        assert_eq!(normal(HotCorner::BottomEdge), Some((0., 1.)));
+        assert_eq!(normal(HotCorner::LeftEdge), Some((-1., 0.)));
+        assert_eq!(normal(HotCorner::RightEdge), Some((1., 0.)));
+
+        for corner in [
+            HotCorner::TopLeft,
+            HotCorner::TopRight,
+            HotCorner::BottomLeft,
+            HotCorner::BottomRight,
+        ] {
+            assert_eq!(normal(corner), None);
+        }
+    }
+
+    #[test]
+    fn pressure_accumulates() {
+        let mut state = state(100., 0.);
+        state.on_motion(motion(60., 0.));
+        assert!(!state.should_trigger());
+        state.on_motion(motion(60., 0.));
+        assert!(state.should_trigger());
+    }
+
+    #[test]
+    fn velocity_needs_fast_motion() {
+        let mut state = state(0., 1000.);
+        state.on_motion(motion(5., 500.));
+        assert!(!state.should_trigger());
+
+        // Any fast enough motion in the corner counts.
+        state.on_motion(motion(5., 1500.));
+        state.on_motion(motion(5., 10.));
+        assert!(state.should_trigger());
+
+        state.triggered = true;
+        assert!(!state.should_trigger());
+    }
+
+    #[test]
+    fn absolute_motion_passes_thresholds() {
+        let mut state = state(100., 1000.);
+        state.on_motion(None);
+        assert!(state.should_trigger());
+    }
+
+    #[test]
+    fn pointer_speed() {
+        let mut speed = PointerSpeed::default();
+        // 1/64 of a second, exact in floating point.
+        let tick = Duration::from_micros(15625);
+
+        assert_eq!(speed.update(Duration::ZERO, Point::from((10., 0.))), 0.);
+        assert_eq!(speed.update(tick, Point::from((30., 40.))), 3200.);
+
+        // A long pause means the pointer had stopped.
+        let time = Duration::from_millis(500);
+        assert_eq!(speed.update(time, Point::from((10., 0.))), 0.);
+        assert_eq!(speed.update(time + tick, Point::from((10., 0.))), 640.);
+    }
+}
@@ -6,7 +6,9 @@ use std::time::Duration;
 
 use calloop::timer::{TimeoutAction, Timer};
 use input::event::gesture::GestureEventCoordinates as _;
-use niri_config::{Action, Bind, Binds, Key, ModKey, Modifiers, SwitchBinds, Trigger};
+use niri_config::{
+    Action, Bind, Binds, Key, ModKey, Modifiers, SwipeDirection, SwitchBinds, Trigger,
+};
 use niri_ipc::LayoutSwitchTarget;
 use smithay::backend::input::{
     AbsolutePositionEvent, Axis, AxisSource, ButtonState, Device, DeviceCapability, Event,
@@ -14,7 +16,7 @@ use smithay::backend::input::{
     InputEvent, KeyState, KeyboardKeyEvent, Keycode, MouseButton, PointerAxisEvent,
     PointerButtonEvent, PointerMotionEvent, ProximityState, Switch, SwitchState, SwitchToggleEvent,
     TabletToolButtonEvent, TabletToolEvent, TabletToolProximityEvent, TabletToolTipEvent,
-    TabletToolTipState, TouchEvent,
+    TabletToolTipState, TouchEvent, TouchSlot,
 };
 use smithay::backend::libinput::LibinputInputBackend;
 use smithay::input::keyboard::{keysyms, FilterResult, Keysym, Layout, ModifiersState};
@@ -29,7 +31,7 @@ use smithay::input::touch::{
 };
 use smithay::input::SeatHandler;
 use smithay::output::Output;
-use smithay::utils::{Logical, Point, Rectangle, Size, Transform, SERIAL_COUNTER};
+use smithay::utils::{Logical, Point, Rectangle, Transform, SERIAL_COUNTER};
 use smithay::wayland::keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitor;
 use smithay::wayland::pointer_constraints::{with_pointer_constraint, PointerConstraint};
 use smithay::wayland::selection::data_device::DnDGrab;
@@ -37,17 +39,25 @@ use smithay::wayland::tablet_manager::{TabletDescriptor, TabletSeatTrait};
 use touch_move_grab::TouchMoveGrab;
 use touch_overview_grab::TouchOverviewGrab;
 
+use self::gesture_binds::{
+    swipe_direction, ContinuousSwipe, LayoutGesture, TouchpadSwipe, TouchscreenSwipeState,
+    PINCH_IN_SCALE, PINCH_OUT_SCALE, TOUCHPAD_SWIPE_THRESHOLD, TOUCHSCREEN_SWIPE_THRESHOLD,
+};
+use self::hot_corners::{EdgeMotion, HotCornerState};
 use self::move_grab::MoveGrab;
 use self::resize_grab::ResizeGrab;
 use self::spatial_movement_grab::SpatialMovementGrab;
 use crate::layout::scrolling::ScrollDirection;
 use crate::layout::{ActivateWindow, LayoutElement as _};
 use crate::niri::{CastTarget, PointerVisibility, State};
+use crate::ui::magnifier::Magnifier;
 use crate::ui::screenshot_ui::ScreenshotUi;
 use crate::utils::spawning::spawn;
 use crate::utils::{center, get_monotonic_time, ResizeEdge};
 
 pub mod backend_ext;
+pub mod gesture_binds;
+pub mod hot_corners;
 pub mod move_grab;
 pub mod pick_color_grab;
 pub mod pick_window_grab;
@@ -64,6 +74,9 @@ use backend_ext::{NiriInputBackend as InputBackend, NiriInputDevice as _};
 
 pub const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(400);
 
+// How long to wait for the next key of a multi-key bind sequence.
+const BIND_SEQUENCE_TIMEOUT: Duration = Duration::from_secs(1);
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct TabletData {
     pub aspect_ratio: f64,
@@ -318,6 +331,13 @@ impl State {
 
         pos.x = pos.x.clamp(0.0, target_geo.size.w as f64 - px);
         pos.y = pos.y.clamp(0.0, target_geo.size.h as f64 - px);
+
+        // Point the cursor at what the tablet is over on the magnified output.
+        if let Some(output) = device_output.or_else(|| self.niri.output_for_tablet()) {
+            let magnifier = &self.niri.output_state.get(output)?.magnifier;
+            pos = magnifier.screen_to_pointer(pos);
+        }
+
         Some(pos + target_geo.loc.to_f64())
     }
 
@@ -400,20 +420,29 @@ impl State {
                     this.niri.screenshot_ui.set_space_down(pressed);
                 }
 
-                let bindings = &this.niri.config.borrow().binds;
-                let res = should_intercept_key(
-                    &mut this.niri.suppressed_keys,
-                    bindings,
-                    mod_key,
-                    key_code,
-                    modified,
-                    raw,
-                    pressed,
-                    *mods,
-                    &this.niri.screenshot_ui,
-                    this.niri.config.borrow().input.disable_power_key_handling,
-                    is_inhibiting_shortcuts,
-                );
+                let sequence_len = this.niri.bind_sequence.len();
+                let res = {
+                    let config = this.niri.config.borrow();
+                    let bindings = config.active_binds(this.niri.bind_mode.as_deref());
+                    should_intercept_key(
+                        &mut this.niri.suppressed_keys,
+                        bindings,
+                        &mut this.niri.bind_sequence,
+                        mod_key,
+                        key_code,
+                        modified,
+                        raw,
+                        pressed,
+                        *mods,
+                        &this.niri.screenshot_ui,
+                        config.input.disable_power_key_handling,
+                        is_inhibiting_shortcuts,
+                    )
+                };
+
+                if this.niri.bind_sequence.len() != sequence_len {
+                    this.reset_bind_sequence_timer();
+                }
 
                 if matches!(res, FilterResult::Forward) {
                     // If we didn't find any bind, try other hardcoded keys.
@@ -497,6 +526,103 @@ impl State {
         self.niri.queue_redraw_all();
     }
 
+    /// Updates whether picture-in-picture windows accept pointer input, given the contents under
+    /// the pointer.
+    ///
+    /// Picture-in-picture windows are click-through unless Mod is held. Once the pointer is over
+    /// one with Mod held, it stays interactive until the pointer leaves it.
+    ///
+    /// Returns `true` if `under` is outdated and must be computed again.
+    pub fn update_pip_interactive(&mut self, under: &PointContents) -> bool {
+        let mod_key = self.backend.mod_key(&self.niri.config.borrow());
+        let mods = self.niri.seat.get_keyboard().unwrap().modifier_state();
+        if modifiers_from_state(mods).contains(mod_key.to_modifiers()) {
+            // Picture-in-picture windows were skipped when computing `under`.
+            let was_interactive = self.niri.layout.pip_interactive();
+            self.niri.layout.set_pip_interactive(true);
+            return !was_interactive;
+        }
+
+        if !self.niri.layout.pip_interactive() {
+            return false;
+        }
+
+        // With picture-in-picture windows interactive, `under` can only change when it is over one
+        // of them, and in that case they stay interactive.
+        let over_pip = under
+            .window
+            .as_ref()
+            .is_some_and(|(window, _)| self.niri.layout.is_window_pip(window));
+        if !over_pip {
+            self.niri.layout.set_pip_interactive(false);
+        }
+        false
+    }
+
+    fn reset_bind_sequence_timer(&mut self) {
+        if let Some(token) = self.niri.bind_sequence_timer.take() {
+            self.niri.event_loop.remove(token);
+        }
+
+        if self.niri.bind_sequence.is_empty() {
+            return;
+        }
+
+        let timer = Timer::from_duration(BIND_SEQUENCE_TIMEOUT);
+        let token = self
+            .niri
+            .event_loop
+            .insert_source(timer, |_, _, state| {
+                state.niri.bind_sequence_timer = None;
+                state.niri.bind_sequence.clear();
+                TimeoutAction::Drop
+            })
+            .unwrap();
+        self.niri.bind_sequence_timer = Some(token);
+    }
+
+    /// Switches to a binding mode, or to the default mode for `None`.
+    pub fn set_bind_mode(&mut self, name: Option<String>) {
+        if self.niri.bind_mode == name {
+            return;
+        }
+
+        if let Some(name) = &name {
+            if self.niri.config.borrow().mode_binds(Some(name)).is_none() {
+                warn!("binding mode {name:?} does not exist");
+                return;
+            }
+        }
+
+        // A sequence started in the previous mode can't continue in the new one.
+        self.niri.bind_sequence.clear();
+        self.reset_bind_sequence_timer();
+
+        if self.niri.hotkey_overlay.set_bind_mode(name.clone()) {
+            self.niri.queue_redraw_all();
+        }
+
+        self.niri.bind_mode = name;
+        self.update_mods_with_binds();
+        self.ipc_bind_mode_changed();
+    }
+
+    /// Updates the modifiers that have mouse, wheel or touchpad scroll binds in the current
+    /// binding mode.
+    fn update_mods_with_binds(&mut self) {
+        let config = self.niri.config.borrow();
+        let mod_key = self.backend.mod_key(&config);
+        let binds = config.active_binds(self.niri.bind_mode.as_deref());
+        let mouse = mods_with_mouse_binds(mod_key, binds);
+        let wheel = mods_with_wheel_binds(mod_key, binds);
+        let finger_scroll = mods_with_finger_scroll_binds(mod_key, binds);
+        drop(config);
+
+        self.niri.mods_with_mouse_binds = mouse;
+        self.niri.mods_with_wheel_binds = wheel;
+        self.niri.mods_with_finger_scroll_binds = finger_scroll;
+    }
+
     pub fn handle_bind(&mut self, bind: Bind) {
         let Some(cooldown) = bind.cooldown else {
             self.do_action(bind.action, bind.allow_when_locked);
@@ -743,6 +869,23 @@ impl State {
                     }
                 });
             }
+            Action::SwitchLayoutForWindowById { id, layout } => {
+                let window = self.niri.layout.windows().find(|(_, m)| m.id().get() == id);
+                let surface = window.map(|(_, m)| m.toplevel().wl_surface().clone());
+                if let Some(surface) = surface {
+                    let Some(layout) = self.find_keyboard_layout(&layout) else {
+                        warn!("requested layout {layout:?} doesn't exist");
+                        return;
+                    };
+
+                    if self.niri.keyboard_focus.surface() == Some(&surface) {
+                        let keyboard = &self.niri.seat.get_keyboard().unwrap();
+                        keyboard.with_xkb_state(self, |mut state| state.set_layout(layout));
+                    } else {
+                        self.switch_layout_for_unfocused_surface(&surface, layout);
+                    }
+                }
+            }
             Action::MoveColumnLeft => {
                 if self.niri.screenshot_ui.is_open() {
                     self.niri.screenshot_ui.move_left();
@@ -1932,6 +2075,34 @@ impl State {
                     self.niri.queue_redraw_all();
                 }
             }
+            Action::ToggleWindowSticky => {
+                self.niri.layout.toggle_window_sticky(None);
+                // FIXME: granular
+                self.niri.queue_redraw_all();
+            }
+            Action::ToggleWindowStickyById(id) => {
+                let window = self.niri.layout.windows().find(|(_, m)| m.id().get() == id);
+                let window = window.map(|(_, m)| m.window.clone());
+                if let Some(window) = window {
+                    self.niri.layout.toggle_window_sticky(Some(&window));
+                    // FIXME: granular
+                    self.niri.queue_redraw_all();
+                }
+            }
+            Action::TogglePictureInPicture => {
+                self.niri.layout.toggle_window_pip(None);
+                // FIXME: granular
+                self.niri.queue_redraw_all();
+            }
+            Action::TogglePictureInPictureById(id) => {
+                let window = self.niri.layout.windows().find(|(_, m)| m.id().get() == id);
+                let window = window.map(|(_, m)| m.window.clone());
+                if let Some(window) = window {
+                    self.niri.layout.toggle_window_pip(Some(&window));
+                    // FIXME: granular
+                    self.niri.queue_redraw_all();
+                }
+            }
             Action::MoveWindowToFloating => {
                 self.niri.layout.set_window_floating(None, true);
                 // FIXME: granular
@@ -1960,6 +2131,43 @@ impl State {
                     self.niri.queue_redraw_all();
                 }
             }
+            Action::MoveWindowToScratchpad => {
+                self.niri.layout.move_to_scratchpad(None);
+                // FIXME: granular
+                self.niri.queue_redraw_all();
+            }
+            Action::MoveWindowToScratchpadById(id) => {
+                let window = self.niri.layout.windows().find(|(_, m)| m.id().get() == id);
+                let window = window.map(|(_, m)| m.window.clone());
+                if let Some(window) = window {
+                    self.niri.layout.move_to_scratchpad(Some(&window));
+                    // FIXME: granular
+                    self.niri.queue_redraw_all();
+                }
+            }
+            Action::ToggleScratchpad(app_id) => {
+                self.niri
+                    .layout
+                    .toggle_scratchpad(|mapped| mapped.has_app_id(app_id.as_deref()));
+                self.maybe_warp_cursor_to_focus();
+                // FIXME: granular
+                self.niri.queue_redraw_all();
+            }
+            Action::ShowScratchpad(app_id) => {
+                self.niri
+                    .layout
+                    .show_scratchpad(|mapped| mapped.has_app_id(app_id.as_deref()));
+                self.maybe_warp_cursor_to_focus();
+                // FIXME: granular
+                self.niri.queue_redraw_all();
+            }
+            Action::HideScratchpad(app_id) => {
+                self.niri
+                    .layout
+                    .hide_scratchpad(|mapped| mapped.has_app_id(app_id.as_deref()));
+                // FIXME: granular
+                self.niri.queue_redraw_all();
+            }
             Action::FocusFloating => {
                 self.niri.layout.focus_floating();
                 self.maybe_warp_cursor_to_focus();
@@ -2068,6 +2276,25 @@ impl State {
                     self.niri.queue_redraw_all();
                 }
             }
+            Action::EnterBindMode(name) => {
+                self.set_bind_mode(Some(name));
+            }
+            Action::ExitBindMode => {
+                self.set_bind_mode(None);
+            }
+            Action::MagnifierZoomIn => {
+                self.niri.update_magnifier_under_cursor(Magnifier::zoom_in);
+            }
+            Action::MagnifierZoomOut => {
+                self.niri.update_magnifier_under_cursor(Magnifier::zoom_out);
+            }
+            Action::MagnifierReset => {
+                self.niri.update_magnifier_under_cursor(Magnifier::reset);
+            }
+            Action::ToggleInactiveDim => {
+                self.niri.layout.toggle_inactive_dim();
+                self.niri.queue_redraw_all();
+            }
             Action::ToggleWindowUrgent(id) => {
                 let window = self
                     .niri
@@ -2106,10 +2333,6 @@ impl State {
     }
 
     fn on_pointer_motion<I: InputBackend>(&mut self, event: I::PointerMotionEvent) {
-        let was_inside_hot_corner = self.niri.pointer_inside_hot_corner;
-        // Any of the early returns here mean that the pointer is not inside the hot corner.
-        self.niri.pointer_inside_hot_corner = false;
-
         // We need an output to be able to move the pointer.
         if self.niri.global_space.outputs().next().is_none() {
             return;
@@ -2121,6 +2344,9 @@ impl State {
 
         let pos = pointer.current_location();
 
+        let time = Duration::from_micros(event.time());
+        let speed = self.niri.pointer_speed.update(time, event.delta());
+
         // We have an output, so we can compute the new location and focus.
         let mut new_pos = pos + event.delta();
 
@@ -2209,6 +2435,9 @@ impl State {
             }
         }
 
+        // Motion cut off at the output edge pushes against hot corners.
+        let overshoot = pos + event.delta() - new_pos;
+
         if let Some(output) = self.niri.screenshot_ui.selection_output() {
             let geom = self.niri.global_space.output_geometry(output).unwrap();
             let mut point = (new_pos - geom.loc.to_f64())
@@ -2224,7 +2453,10 @@ impl State {
             self.niri.screenshot_ui.pointer_motion(point, None);
         }
 
-        let under = self.niri.contents_under(new_pos);
+        let mut under = self.niri.contents_under(new_pos);
+        if self.update_pip_interactive(&under) {
+            under = self.niri.contents_under(new_pos);
+        }
 
         // Handle confined pointer.
         if let Some((focus_surface, region)) = pointer_confined {
@@ -2286,21 +2518,7 @@ impl State {
 
         pointer.frame(self);
 
-        // contents_under() will return no surface when the hot corner should trigger.
-        let hot_corners = self.niri.config.borrow().gestures.hot_corners;
-        if !hot_corners.off
-            && pointer.current_focus().is_none()
-            && !self.niri.screenshot_ui.is_open()
-        {
-            let hot_corner = Rectangle::from_size(Size::from((1., 1.)));
-            if let Some((_, pos_within_output)) = self.niri.output_under(pos) {
-                let inside_hot_corner = hot_corner.contains(pos_within_output);
-                if inside_hot_corner && !was_inside_hot_corner {
-                    self.niri.layout.toggle_overview();
-                }
-                self.niri.pointer_inside_hot_corner = inside_hot_corner;
-            }
-        }
+        self.update_hot_corner(new_pos, Some(EdgeMotion { overshoot, speed }));
 
         // Activate a new confinement if necessary.
         self.niri.maybe_activate_pointer_constraint();
@@ -2322,19 +2540,94 @@ impl State {
         self.niri.queue_redraw_all();
     }
 
+    /// Tracks the pointer going in and out of hot corners and edges, and triggers them.
+    ///
+    /// `motion` is `None` for absolute motion, which cannot push against the edge.
+    fn update_hot_corner(&mut self, pos: Point<f64, Logical>, motion: Option<EdgeMotion>) {
+        let pointer = self.niri.seat.get_pointer().unwrap();
+        let under = self.niri.hot_corner_under(pos).filter(|(_, corner, _)| {
+            // contents_under() will return no surface when a hot corner should trigger, unless
+            // something like an overlay layer surface is on top of it.
+            hot_corners::edge_normal(*corner).is_some() || pointer.current_focus().is_none()
+        });
+
+        let Some((output, corner, action)) = under else {
+            self.niri.hot_corner = None;
+            return;
+        };
+
+        let entered_before = self
+            .niri
+            .hot_corner
+            .as_ref()
+            .is_some_and(|state| state.output == output && state.corner == corner);
+        if !entered_before {
+            let entered = get_monotonic_time();
+            if !action.delay.is_zero() {
+                let timer = Timer::from_duration(action.delay);
+                self.niri
+                    .event_loop
+                    .insert_source(timer, move |_, _, state| {
+                        state.on_hot_corner_dwelled(entered);
+                        TimeoutAction::Drop
+                    })
+                    .unwrap();
+            }
+
+            let state = HotCornerState::new(output, corner, action, entered);
+            self.niri.hot_corner = Some(state);
+        }
+
+        let state = self.niri.hot_corner.as_mut().unwrap();
+        state.on_motion(motion);
+
+        self.maybe_trigger_hot_corner();
+    }
+
+    fn on_hot_corner_dwelled(&mut self, entered: Duration) {
+        let Some(state) = &mut self.niri.hot_corner else {
+            return;
+        };
+
+        // The pointer may have left and come back since the timer started.
+        if state.entered != entered {
+            return;
+        }
+
+        state.dwelled = true;
+        self.maybe_trigger_hot_corner();
+    }
+
+    fn maybe_trigger_hot_corner(&mut self) {
+        let Some(state) = &mut self.niri.hot_corner else {
+            return;
+        };
+
+        if !state.should_trigger() {
+            return;
+        }
+        state.triggered = true;
+
+        let output = state.output.clone();
+        let corner = state.corner;
+        let action = state.action.action.clone();
+
+        self.ipc_hot_corner_triggered(&output, corner);
+        self.do_action(action, false);
+    }
+
     fn on_pointer_motion_absolute<I: InputBackend>(
         &mut self,
         event: I::PointerMotionAbsoluteEvent,
     ) {
-        let was_inside_hot_corner = self.niri.pointer_inside_hot_corner;
-        // Any of the early returns here mean that the pointer is not inside the hot corner.
-        self.niri.pointer_inside_hot_corner = false;
-
-        let Some(pos) = self.compute_absolute_location(&event, None).or_else(|| {
-            self.global_bounding_rectangle().map(|output_geo| {
-                event.position_transformed(output_geo.size) + output_geo.loc.to_f64()
+        let Some(pos) = self
+            .compute_absolute_location(&event, None, true)
+            .or_else(|| {
+                self.global_bounding_rectangle().map(|output_geo| {
+                    event.position_transformed(output_geo.size) + output_geo.loc.to_f64()
+                })
             })
-        }) else {
+        else {
             return;
         };
 
@@ -2357,7 +2650,10 @@ impl State {
             self.niri.screenshot_ui.pointer_motion(point, None);
         }
 
-        let under = self.niri.contents_under(pos);
+        let mut under = self.niri.contents_under(pos);
+        if self.update_pip_interactive(&under) {
+            under = self.niri.contents_under(pos);
+        }
 
         self.niri.handle_focus_follows_mouse(&under);
 
@@ -2375,21 +2671,7 @@ impl State {
 
         pointer.frame(self);
 
-        // contents_under() will return no surface when the hot corner should trigger.
-        let hot_corners = self.niri.config.borrow().gestures.hot_corners;
-        if !hot_corners.off
-            && pointer.current_focus().is_none()
-            && !self.niri.screenshot_ui.is_open()
-        {
-            let hot_corner = Rectangle::from_size(Size::from((1., 1.)));
-            if let Some((_, pos_within_output)) = self.niri.output_under(pos) {
-                let inside_hot_corner = hot_corner.contains(pos_within_output);
-                if inside_hot_corner && !was_inside_hot_corner {
-                    self.niri.layout.toggle_overview();
-                }
-                self.niri.pointer_inside_hot_corner = inside_hot_corner;
-            }
-        }
+        self.update_hot_corner(pos, None);
 
         self.niri.maybe_activate_pointer_constraint();
 
@@ -2449,7 +2731,7 @@ impl State {
                 }
                 .and_then(|trigger| {
                     let config = self.niri.config.borrow();
-                    let bindings = &config.binds;
+                    let bindings = config.active_binds(self.niri.bind_mode.as_deref());
                     find_configured_bind(bindings, mod_key, trigger, mods)
                 }) {
                     self.niri.suppressed_buttons.insert(button_code);
@@ -2769,6 +3051,7 @@ impl State {
                         && modifiers.is_empty()
                     {
                         let bind_left = Some(Bind {
+                            prefix: Vec::new(),
                             key: Key {
                                 trigger: Trigger::WheelScrollLeft,
                                 modifiers: Modifiers::empty(),
@@ -2781,6 +3064,7 @@ impl State {
                             hotkey_overlay_title: None,
                         });
                         let bind_right = Some(Bind {
+                            prefix: Vec::new(),
                             key: Key {
                                 trigger: Trigger::WheelScrollRight,
                                 modifiers: Modifiers::empty(),
@@ -2795,7 +3079,7 @@ impl State {
                         (bind_left, bind_right)
                     } else {
                         let config = self.niri.config.borrow();
-                        let bindings = &config.binds;
+                        let bindings = config.active_binds(self.niri.bind_mode.as_deref());
                         let bind_left =
                             find_configured_bind(bindings, mod_key, Trigger::WheelScrollLeft, mods);
                         let bind_right = find_configured_bind(
@@ -2825,6 +3109,7 @@ impl State {
                     let (bind_up, bind_down) = if should_handle_in_overview && modifiers.is_empty()
                     {
                         let bind_up = Some(Bind {
+                            prefix: Vec::new(),
                             key: Key {
                                 trigger: Trigger::WheelScrollUp,
                                 modifiers: Modifiers::empty(),
@@ -2837,6 +3122,7 @@ impl State {
                             hotkey_overlay_title: None,
                         });
                         let bind_down = Some(Bind {
+                            prefix: Vec::new(),
                             key: Key {
                                 trigger: Trigger::WheelScrollDown,
                                 modifiers: Modifiers::empty(),
@@ -2851,6 +3137,7 @@ impl State {
                         (bind_up, bind_down)
                     } else if should_handle_in_overview && modifiers == Modifiers::SHIFT {
                         let bind_up = Some(Bind {
+                            prefix: Vec::new(),
                             key: Key {
                                 trigger: Trigger::WheelScrollUp,
                                 modifiers: Modifiers::empty(),
@@ -2863,6 +3150,7 @@ impl State {
                             hotkey_overlay_title: None,
                         });
                         let bind_down = Some(Bind {
+                            prefix: Vec::new(),
                             key: Key {
                                 trigger: Trigger::WheelScrollDown,
                                 modifiers: Modifiers::empty(),
@@ -2877,7 +3165,7 @@ impl State {
                         (bind_up, bind_down)
                     } else {
                         let config = self.niri.config.borrow();
-                        let bindings = &config.binds;
+                        let bindings = config.active_binds(self.niri.bind_mode.as_deref());
                         let bind_up =
                             find_configured_bind(bindings, mod_key, Trigger::WheelScrollUp, mods);
                         let bind_down =
@@ -3017,7 +3305,7 @@ impl State {
                     .accumulate(horizontal);
                 if ticks != 0 {
                     let config = self.niri.config.borrow();
-                    let bindings = &config.binds;
+                    let bindings = config.active_binds(self.niri.bind_mode.as_deref());
                     let bind_left =
                         find_configured_bind(bindings, mod_key, Trigger::TouchpadScrollLeft, mods);
                     let bind_right =
@@ -3042,7 +3330,7 @@ impl State {
                     .accumulate(vertical);
                 if ticks != 0 {
                     let config = self.niri.config.borrow();
-                    let bindings = &config.binds;
+                    let bindings = config.active_binds(self.niri.bind_mode.as_deref());
                     let bind_up =
                         find_configured_bind(bindings, mod_key, Trigger::TouchpadScrollUp, mods);
                     let bind_down =
@@ -3341,17 +3629,43 @@ impl State {
     }
 
     fn on_gesture_swipe_begin<I: InputBackend>(&mut self, event: I::GestureSwipeBeginEvent) {
-        if event.fingers() == 3 {
-            self.niri.gesture_swipe_3f_cumulative = Some((0., 0.));
+        if let Ok(fingers @ 3..=5) = u8::try_from(event.fingers()) {
+            let has_binds = [
+                SwipeDirection::Up,
+                SwipeDirection::Down,
+                SwipeDirection::Left,
+                SwipeDirection::Right,
+            ]
+            .into_iter()
+            .any(|direction| {
+                self.find_gesture_bind(Trigger::TouchpadSwipe { fingers, direction })
+                    .is_some()
+            });
+            let defaults = self.niri.config.borrow().gestures.default_gestures;
+            let default_overview = LayoutGesture::Overview.is_default_enabled(&defaults);
+            let default_three_finger = LayoutGesture::WorkspaceSwitch.is_default_enabled(&defaults)
+                || LayoutGesture::ViewOffset.is_default_enabled(&defaults);
+
+            if fingers == 4 && default_overview && !has_binds {
+                // The built-in overview gesture goes both ways, so there's nothing to recognize.
+                self.niri.layout.overview_gesture_begin();
+                self.niri.queue_redraw_all();
+                self.niri.touchpad_swipe = Some(TouchpadSwipe::Layout(None));
 
-            // We handled this event.
-            return;
-        } else if event.fingers() == 4 {
-            self.niri.layout.overview_gesture_begin();
-            self.niri.queue_redraw_all();
+                // We handled this event.
+                return;
+            }
 
-            // We handled this event.
-            return;
+            if has_binds || (default_three_finger && fingers == 3) {
+                self.niri.touchpad_swipe = Some(TouchpadSwipe::Recognizing {
+                    fingers,
+                    cx: 0.,
+                    cy: 0.,
+                });
+
+                // We handled this event.
+                return;
+            }
         }
 
         let serial = SERIAL_COUNTER.next_serial();
@@ -3387,7 +3701,8 @@ impl State {
             delta_y = libinput_event.dy_unaccelerated();
         }
 
-        let uninverted_delta_y = delta_y;
+        // Binds go by the direction of the fingers regardless of natural scrolling.
+        let (uninverted_delta_x, uninverted_delta_y) = (delta_x, delta_y);
 
         let device = event.device();
         if let Some(device) = (&device as &dyn Any).downcast_ref::<input::Device>() {
@@ -3397,84 +3712,81 @@ impl State {
             }
         }
 
-        let is_overview_open = self.niri.layout.is_overview_open();
+        if let Some(TouchpadSwipe::Recognizing { fingers, cx, cy }) = &mut self.niri.touchpad_swipe
+        {
+            *cx += uninverted_delta_x;
+            *cy += uninverted_delta_y;
 
-        if let Some((cx, cy)) = &mut self.niri.gesture_swipe_3f_cumulative {
-            *cx += delta_x;
-            *cy += delta_y;
+            // Check if the gesture moved far enough to decide.
+            let (fingers, cx, cy) = (*fingers, *cx, *cy);
+            if cx * cx + cy * cy >= TOUCHPAD_SWIPE_THRESHOLD * TOUCHPAD_SWIPE_THRESHOLD {
+                self.niri.touchpad_swipe = self.recognize_touchpad_swipe(fingers, cx, cy);
 
-            // Check if the gesture moved far enough to decide. Threshold copied from GNOME Shell.
-            let (cx, cy) = (*cx, *cy);
-            if cx * cx + cy * cy >= 16. * 16. {
-                self.niri.gesture_swipe_3f_cumulative = None;
+                if self.niri.touchpad_swipe.is_none() {
+                    // Nothing wants this swipe, so it goes to the client after all.
+                    let serial = SERIAL_COUNTER.next_serial();
+                    let pointer = self.niri.seat.get_pointer().unwrap();
 
-                if let Some(output) = self.niri.output_under_cursor() {
-                    if cx.abs() > cy.abs() {
-                        let output_ws = if is_overview_open {
-                            self.niri.workspace_under_cursor(true)
-                        } else {
-                            // We don't want to accidentally "catch" the wrong workspace during
-                            // animations.
-                            self.niri.output_under_cursor().and_then(|output| {
-                                let mon = self.niri.layout.monitor_for_output(&output)?;
-                                Some((output, mon.active_workspace_ref()))
-                            })
-                        };
-
-                        if let Some((output, ws)) = output_ws {
-                            let ws_idx = self.niri.layout.find_workspace_by_id(ws.id()).unwrap().0;
-                            self.niri
-                                .layout
-                                .view_offset_gesture_begin(&output, Some(ws_idx), true);
-                        }
-                    } else {
-                        self.niri
-                            .layout
-                            .workspace_switch_gesture_begin(&output, true);
+                    if self.update_pointer_contents() {
+                        pointer.frame(self);
                     }
+
+                    pointer.gesture_swipe_begin(
+                        self,
+                        &GestureSwipeBeginEvent {
+                            serial,
+                            time: event.time_msec(),
+                            fingers: u32::from(fingers),
+                        },
+                    );
                 }
             }
         }
 
-        let timestamp = Duration::from_micros(event.time());
+        match self.niri.touchpad_swipe {
+            None => (),
+            Some(TouchpadSwipe::Layout(swipe)) => {
+                let (ws_delta, view_delta, overview_delta) = match swipe {
+                    Some(swipe) => {
+                        let delta = swipe.delta(uninverted_delta_x, uninverted_delta_y);
+                        (delta, delta, delta)
+                    }
+                    None => (delta_y, delta_x, -uninverted_delta_y),
+                };
 
-        let mut handled = false;
-        let res = self
-            .niri
-            .layout
-            .workspace_switch_gesture_update(delta_y, timestamp, true);
-        if let Some(output) = res {
-            if let Some(output) = output {
-                self.niri.queue_redraw(&output);
-            }
-            handled = true;
-        }
+                let timestamp = Duration::from_micros(event.time());
 
-        let res = self
-            .niri
-            .layout
-            .view_offset_gesture_update(delta_x, timestamp, true);
-        if let Some(output) = res {
-            if let Some(output) = output {
-                self.niri.queue_redraw(&output);
-            }
-            handled = true;
-        }
+                let res = self
+                    .niri
+                    .layout
+                    .workspace_switch_gesture_update(ws_delta, timestamp, true);
+                if let Some(Some(output)) = res {
+                    self.niri.queue_redraw(&output);
+                }
 
-        let res = self
-            .niri
-            .layout
-            .overview_gesture_update(-uninverted_delta_y, timestamp);
-        if let Some(redraw) = res {
-            if redraw {
-                self.niri.queue_redraw_all();
-            }
-            handled = true;
-        }
+                let res = self
+                    .niri
+                    .layout
+                    .view_offset_gesture_update(view_delta, timestamp, true);
+                if let Some(Some(output)) = res {
+                    self.niri.queue_redraw(&output);
+                }
 
-        if handled {
-            // We handled this event.
-            return;
+                let res = self
+                    .niri
+                    .layout
+                    .overview_gesture_update(overview_delta, timestamp);
+                if res == Some(true) {
+                    self.niri.queue_redraw_all();
+                }
+
+                // We handled this event.
+                return;
+            }
+            Some(_) => {
+                // We handled this event.
+                return;
+            }
         }
 
         let pointer = self.niri.seat.get_pointer().unwrap();
@@ -3493,28 +3805,11 @@ impl State {
     }
 
     fn on_gesture_swipe_end<I: InputBackend>(&mut self, event: I::GestureSwipeEndEvent) {
-        self.niri.gesture_swipe_3f_cumulative = None;
-
-        let mut handled = false;
-        let res = self.niri.layout.workspace_switch_gesture_end(Some(true));
-        if let Some(output) = res {
-            self.niri.queue_redraw(&output);
-            handled = true;
-        }
-
-        let res = self.niri.layout.view_offset_gesture_end(Some(true));
-        if let Some(output) = res {
-            self.niri.queue_redraw(&output);
-            handled = true;
-        }
-
-        let res = self.niri.layout.overview_gesture_end();
-        if res {
-            self.niri.queue_redraw_all();
-            handled = true;
-        }
+        if let Some(swipe) = self.niri.touchpad_swipe.take() {
+            if let TouchpadSwipe::Layout(_) = swipe {
+                self.layout_gestures_end(true);
+            }
 
-        if handled {
             // We handled this event.
             return;
         }
@@ -3537,6 +3832,15 @@ impl State {
     }
 
     fn on_gesture_pinch_begin<I: InputBackend>(&mut self, event: I::GesturePinchBeginEvent) {
+        let has_binds = self.find_gesture_bind(Trigger::TouchpadPinchIn).is_some()
+            || self.find_gesture_bind(Trigger::TouchpadPinchOut).is_some();
+        if has_binds {
+            self.niri.touchpad_pinch = Some(false);
+
+            // We handled this event.
+            return;
+        }
+
         let serial = SERIAL_COUNTER.next_serial();
         let pointer = self.niri.seat.get_pointer().unwrap();
 
@@ -3555,6 +3859,31 @@ impl State {
     }
 
     fn on_gesture_pinch_update<I: InputBackend>(&mut self, event: I::GesturePinchUpdateEvent) {
+        if let Some(triggered) = self.niri.touchpad_pinch {
+            if !triggered {
+                let scale = event.scale();
+                let trigger = if scale <= PINCH_IN_SCALE {
+                    Some(Trigger::TouchpadPinchIn)
+                } else if scale >= PINCH_OUT_SCALE {
+                    Some(Trigger::TouchpadPinchOut)
+                } else {
+                    None
+                };
+
+                if let Some(trigger) = trigger {
+                    // Trigger at most once per pinch.
+                    self.niri.touchpad_pinch = Some(true);
+
+                    if let Some(bind) = self.find_gesture_bind(trigger) {
+                        self.handle_bind(bind);
+                    }
+                }
+            }
+
+            // We handled this event.
+            return;
+        }
+
         let pointer = self.niri.seat.get_pointer().unwrap();
 
         if self.update_pointer_contents() {
@@ -3573,6 +3902,11 @@ impl State {
     }
 
     fn on_gesture_pinch_end<I: InputBackend>(&mut self, event: I::GesturePinchEndEvent) {
+        if self.niri.touchpad_pinch.take().is_some() {
+            // We handled this event.
+            return;
+        }
+
         let serial = SERIAL_COUNTER.next_serial();
         let pointer = self.niri.seat.get_pointer().unwrap();
 
@@ -3591,6 +3925,18 @@ impl State {
     }
 
     fn on_gesture_hold_begin<I: InputBackend>(&mut self, event: I::GestureHoldBeginEvent) {
+        if let Ok(fingers @ 3..=5) = u8::try_from(event.fingers()) {
+            if self
+                .find_gesture_bind(Trigger::TouchpadHold { fingers })
+                .is_some()
+            {
+                self.niri.touchpad_hold = Some(fingers);
+
+                // We handled this event.
+                return;
+            }
+        }
+
         let serial = SERIAL_COUNTER.next_serial();
         let pointer = self.niri.seat.get_pointer().unwrap();
 
@@ -3609,6 +3955,18 @@ impl State {
     }
 
     fn on_gesture_hold_end<I: InputBackend>(&mut self, event: I::GestureHoldEndEvent) {
+        if let Some(fingers) = self.niri.touchpad_hold.take() {
+            // A cancelled hold turned into some other gesture.
+            if !event.cancelled() {
+                if let Some(bind) = self.find_gesture_bind(Trigger::TouchpadHold { fingers }) {
+                    self.handle_bind(bind);
+                }
+            }
+
+            // We handled this event.
+            return;
+        }
+
         let serial = SERIAL_COUNTER.next_serial();
         let pointer = self.niri.seat.get_pointer().unwrap();
 
@@ -3626,20 +3984,146 @@ impl State {
         );
     }
 
+    fn find_gesture_bind(&self, trigger: Trigger) -> Option<Bind> {
+        let config = self.niri.config.borrow();
+        let mod_key = self.backend.mod_key(&config);
+        let mods = self.niri.seat.get_keyboard().unwrap().modifier_state();
+        let bindings = config.active_binds(self.niri.bind_mode.as_deref());
+        find_configured_bind(bindings, mod_key, trigger, mods)
+    }
+
+    /// Decides what a touchpad swipe does once its direction is known.
+    ///
+    /// Returns `None` if the swipe should go to the client.
+    fn recognize_touchpad_swipe(&mut self, fingers: u8, cx: f64, cy: f64) -> Option<TouchpadSwipe> {
+        let direction = swipe_direction(cx, cy);
+        let pos = self.niri.seat.get_pointer().unwrap().current_location();
+
+        if let Some(bind) = self.find_gesture_bind(Trigger::TouchpadSwipe { fingers, direction }) {
+            let swipe = self.trigger_swipe_bind(bind, direction, pos, true);
+            return Some(swipe.map_or(TouchpadSwipe::Triggered, |swipe| {
+                TouchpadSwipe::Layout(Some(swipe))
+            }));
+        }
+
+        let is_horizontal = matches!(direction, SwipeDirection::Left | SwipeDirection::Right);
+        let gesture = match (fingers, is_horizontal) {
+            (3, true) => LayoutGesture::ViewOffset,
+            (3, false) => LayoutGesture::WorkspaceSwitch,
+            (4, false) => LayoutGesture::Overview,
+            _ => return None,
+        };
+        if !gesture.is_default_enabled(&self.niri.config.borrow().gestures.default_gestures) {
+            return None;
+        }
+        self.layout_gesture_begin(gesture, pos, true);
+        Some(TouchpadSwipe::Layout(None))
+    }
+
+    /// Runs the action bound to a swipe, returning the layout gesture that the swipe drives.
+    fn trigger_swipe_bind(
+        &mut self,
+        bind: Bind,
+        direction: SwipeDirection,
+        pos: Point<f64, Logical>,
+        is_touchpad: bool,
+    ) -> Option<ContinuousSwipe> {
+        if !self.niri.is_locked() {
+            let is_overview_open = self.niri.layout.is_overview_open();
+            if let Some(swipe) = ContinuousSwipe::new(&bind.action, direction, is_overview_open) {
+                self.layout_gesture_begin(swipe.gesture, pos, is_touchpad);
+                return Some(swipe);
+            }
+        }
+
+        self.handle_bind(bind);
+        None
+    }
+
+    fn layout_gesture_begin(
+        &mut self,
+        gesture: LayoutGesture,
+        pos: Point<f64, Logical>,
+        is_touchpad: bool,
+    ) {
+        match gesture {
+            LayoutGesture::WorkspaceSwitch => {
+                if let Some((output, _)) = self.niri.output_under(pos) {
+                    let output = output.clone();
+                    self.niri
+                        .layout
+                        .workspace_switch_gesture_begin(&output, is_touchpad);
+                }
+            }
+            LayoutGesture::ViewOffset => {
+                let output_ws = if self.niri.layout.is_overview_open() {
+                    self.niri.workspace_under(true, pos)
+                } else {
+                    // We don't want to accidentally "catch" the wrong workspace during
+                    // animations.
+                    self.niri.output_under(pos).and_then(|(output, _)| {
+                        let mon = self.niri.layout.monitor_for_output(output)?;
+                        Some((output.clone(), mon.active_workspace_ref()))
+                    })
+                };
+
+                if let Some((output, ws)) = output_ws {
+                    let ws_idx = self.niri.layout.find_workspace_by_id(ws.id()).unwrap().0;
+                    self.niri
+                        .layout
+                        .view_offset_gesture_begin(&output, Some(ws_idx), is_touchpad);
+                }
+            }
+            LayoutGesture::Overview => self.niri.layout.overview_gesture_begin(),
+        }
+
+        self.niri.queue_redraw_all();
+    }
+
+    fn layout_gestures_end(&mut self, is_touchpad: bool) {
+        let res = self
+            .niri
+            .layout
+            .workspace_switch_gesture_end(Some(is_touchpad));
+        if let Some(output) = res {
+            self.niri.queue_redraw(&output);
+        }
+
+        let res = self.niri.layout.view_offset_gesture_end(Some(is_touchpad));
+        if let Some(output) = res {
+            self.niri.queue_redraw(&output);
+        }
+
+        if self.niri.layout.overview_gesture_end() {
+            self.niri.queue_redraw_all();
+        }
+    }
+
+    /// Computes the global position for the absolute event.
+    ///
+    /// On a magnified output, `moves_pointer` picks between the position that makes the pointer
+    /// show up at the event location, and the position of the contents shown there.
     fn compute_absolute_location<I: InputBackend>(
         &self,
         evt: &impl AbsolutePositionEvent<I>,
         fallback_output: Option<&Output>,
+        moves_pointer: bool,
     ) -> Option<Point<f64, Logical>> {
         let output = evt.device().output(self);
         let output = output.as_ref().or(fallback_output)?;
         let output_geo = self.niri.global_space.output_geometry(output).unwrap();
         let transform = output.current_transform();
         let size = transform.invert().transform_size(output_geo.size);
-        Some(
-            transform.transform_point_in(evt.position_transformed(size), &size.to_f64())
-                + output_geo.loc.to_f64(),
-        )
+        let pos = transform.transform_point_in(evt.position_transformed(size), &size.to_f64());
+
+        let magnifier = &self.niri.output_state.get(output)?.magnifier;
+        let pos = if moves_pointer {
+            magnifier.screen_to_pointer(pos)
+        } else {
+            magnifier.screen_to_output(pos)
+        };
+
+        Some(pos + output_geo.loc.to_f64())
     }
 
     /// Computes the cursor position for the touch event.
@@ -3649,7 +4133,7 @@ impl State {
         &self,
         evt: &impl AbsolutePositionEvent<I>,
     ) -> Option<Point<f64, Logical>> {
-        self.compute_absolute_location(evt, self.niri.output_for_touch())
+        self.compute_absolute_location(evt, self.niri.output_for_touch(), false)
     }
 
     fn on_touch_down<I: InputBackend>(&mut self, evt: I::TouchDownEvent) {
@@ -3661,6 +4145,12 @@ impl State {
         };
         let slot = evt.slot();
 
+        self.niri.touchscreen_swipe.down(slot, pos);
+        if self.niri.touchscreen_swipe.is_consumed() {
+            // The touch joined a bound swipe.
+            return;
+        }
+
         let serial = SERIAL_COUNTER.next_serial();
 
         let under = self.niri.contents_under(pos);
@@ -3782,6 +4272,17 @@ impl State {
         };
         let slot = evt.slot();
 
+        let is_consumed = self.niri.touchscreen_swipe.is_consumed();
+        self.niri.touchscreen_swipe.up(slot);
+        if self.niri.touchscreen_swipe.is_empty() {
+            if let TouchscreenSwipeState::Consumed(Some(_)) = self.niri.touchscreen_swipe.reset() {
+                self.layout_gestures_end(false);
+            }
+        }
+        if is_consumed {
+            return;
+        }
+
         if let Some(capture) = self.niri.screenshot_ui.pointer_up(Some(slot)) {
             if capture {
                 self.confirm_screenshot(true);
@@ -3809,6 +4310,10 @@ impl State {
         };
         let slot = evt.slot();
 
+        if self.touchscreen_swipe_motion(slot, pos, evt.time()) {
+            return;
+        }
+
         if let Some(output) = self.niri.screenshot_ui.selection_output().cloned() {
             let geom = self.niri.global_space.output_geometry(&output).unwrap();
             let mut point = (pos - geom.loc.to_f64())
@@ -3852,15 +4357,104 @@ impl State {
         let Some(handle) = self.niri.seat.get_touch() else {
             return;
         };
+        if self.niri.touchscreen_swipe.is_consumed() {
+            return;
+        }
         handle.frame(self);
     }
     fn on_touch_cancel<I: InputBackend>(&mut self, _evt: I::TouchCancelEvent) {
         let Some(handle) = self.niri.seat.get_touch() else {
             return;
         };
+        if let TouchscreenSwipeState::Consumed(Some(_)) = self.niri.touchscreen_swipe.reset() {
+            self.layout_gestures_end(false);
+        }
         handle.cancel(self);
     }
 
+    /// Feeds touch motion to the touchscreen swipe recognizer, returning whether it was consumed.
+    fn touchscreen_swipe_motion(
+        &mut self,
+        slot: TouchSlot,
+        pos: Point<f64, Logical>,
+        time: u64,
+    ) -> bool {
+        let delta = self.niri.touchscreen_swipe.motion(slot, pos);
+
+        match self.niri.touchscreen_swipe.state {
+            TouchscreenSwipeState::Idle | TouchscreenSwipeState::Passthrough => false,
+            TouchscreenSwipeState::Recognizing {
+                fingers,
+                cumulative,
+            } => {
+                let (cx, cy) = (cumulative.x, cumulative.y);
+                if cx * cx + cy * cy < TOUCHSCREEN_SWIPE_THRESHOLD * TOUCHSCREEN_SWIPE_THRESHOLD {
+                    return false;
+                }
+
+                let state = self.recognize_touchscreen_swipe(fingers, cx, cy);
+                self.niri.touchscreen_swipe.state = state;
+                self.niri.touchscreen_swipe.is_consumed()
+            }
+            TouchscreenSwipeState::Consumed(Some(swipe)) => {
+                let delta = swipe.delta(delta.x, delta.y);
+                let timestamp = Duration::from_micros(time);
+
+                let res = self
+                    .niri
+                    .layout
+                    .workspace_switch_gesture_update(delta, timestamp, false);
+                if let Some(Some(output)) = res {
+                    self.niri.queue_redraw(&output);
+                }
+
+                let res = self
+                    .niri
+                    .layout
+                    .view_offset_gesture_update(delta, timestamp, false);
+                if let Some(Some(output)) = res {
+                    self.niri.queue_redraw(&output);
+                }
+
+                let res = self.niri.layout.overview_gesture_update(delta, timestamp);
+                if res == Some(true) {
+                    self.niri.queue_redraw_all();
+                }
+
+                true
+            }
+            TouchscreenSwipeState::Consumed(None) => true,
+        }
+    }
+
+    fn recognize_touchscreen_swipe(
+        &mut self,
+        fingers: u8,
+        cx: f64,
+        cy: f64,
+    ) -> TouchscreenSwipeState {
+        let Some(handle) = self.niri.seat.get_touch() else {
+            return TouchscreenSwipeState::Passthrough;
+        };
+
+        // Leave interactive moves and other touch grabs alone.
+        if handle.is_grabbed() || self.niri.screenshot_ui.is_open() {
+            return TouchscreenSwipeState::Passthrough;
+        }
+
+        let direction = swipe_direction(cx, cy);
+        let trigger = Trigger::TouchscreenSwipe { fingers, direction };
+        let Some(bind) = self.find_gesture_bind(trigger) else {
+            return TouchscreenSwipeState::Passthrough;
+        };
+
+        // From now on, the touch points belong to the swipe.
+        handle.cancel(self);
+
+        let pos = self.niri.touchscreen_swipe.center();
+        TouchscreenSwipeState::Consumed(self.trigger_swipe_bind(bind, direction, pos, false))
+    }
+
     fn on_switch_toggle<I: InputBackend>(&mut self, evt: I::SwitchToggleEvent) {
         let Some(switch) = evt.switch() else {
             return;
@@ -3891,6 +4485,7 @@ impl State {
 fn should_intercept_key(
     suppressed_keys: &mut HashSet<Keycode>,
     bindings: &Binds,
+    sequence: &mut Vec<Key>,
     mod_key: ModKey,
     key_code: Keycode,
     modified: Keysym,
@@ -3908,6 +4503,24 @@ fn should_intercept_key(
         return FilterResult::Forward;
     }
 
+    // Modifier presses don't break a sequence since they're needed to type its next key.
+    let sequence_key = raw.filter(|raw| pressed && !raw.is_modifier_key());
+    if let Some(raw) = sequence_key.filter(|_| !is_inhibiting_shortcuts && !screenshot_ui.is_open())
+    {
+        let trigger = Trigger::Keysym(raw);
+        match advance_key_sequence(bindings, sequence, mod_key, trigger, mods) {
+            SequenceStep::None => (),
+            SequenceStep::Continued => {
+                suppressed_keys.insert(key_code);
+                return FilterResult::Intercept(None);
+            }
+            SequenceStep::Completed(bind) => {
+                suppressed_keys.insert(key_code);
+                return FilterResult::Intercept(Some(bind));
+            }
+        }
+    }
+
     let mut final_bind = find_bind(
         bindings,
         mod_key,
@@ -3931,6 +4544,7 @@ fn should_intercept_key(
         if use_screenshot_ui_action {
             if let Some(raw) = raw {
                 final_bind = screenshot_ui.action(raw, mods).map(|action| Bind {
+                    prefix: Vec::new(),
                     key: Key {
                         trigger: Trigger::Keysym(raw),
                         // Not entirely correct but it doesn't matter in how we currently use it.
@@ -3995,6 +4609,7 @@ fn find_bind(
 
     if let Some(action) = hardcoded_action {
         return Some(Bind {
+            prefix: Vec::new(),
             key: Key {
                 // Not entirely correct but it doesn't matter in how we currently use it.
                 trigger: Trigger::Keysym(modified),
@@ -4025,31 +4640,84 @@ fn find_configured_bind(
     mods: ModifiersState,
 ) -> Option<Bind> {
     // Handle configured binds.
-    let mut modifiers = modifiers_from_state(mods);
+    for bind in &bindings.0 {
+        // Sequences are handled separately.
+        if !bind.prefix.is_empty() {
+            continue;
+        }
+
+        if key_matches(&bind.key, mod_key, trigger, mods) {
+            return Some(bind.clone());
+        }
+    }
+
+    None
+}
+
+fn key_matches(key: &Key, mod_key: ModKey, trigger: Trigger, mods: ModifiersState) -> bool {
+    if key.trigger != trigger {
+        return false;
+    }
 
-    let mod_down = modifiers_from_state(mods).contains(mod_key.to_modifiers());
-    if mod_down {
+    let mut modifiers = modifiers_from_state(mods);
+    if modifiers.contains(mod_key.to_modifiers()) {
         modifiers |= Modifiers::COMPOSITOR;
     }
 
+    let mut key_modifiers = key.modifiers;
+    if key_modifiers.contains(Modifiers::COMPOSITOR) {
+        key_modifiers |= mod_key.to_modifiers();
+    } else if key_modifiers.contains(mod_key.to_modifiers()) {
+        key_modifiers |= Modifiers::COMPOSITOR;
+    }
+
+    key_modifiers == modifiers
+}
+
+enum SequenceStep {
+    /// The key is not part of a sequence.
+    None,
+    /// The key was added to the sequence in progress.
+    Continued,
+    /// The key completed a sequence.
+    Completed(Bind),
+}
+
+/// Advances the multi-key bind sequence in progress with a key press.
+///
+/// A key that doesn't fit any sequence resets it, and goes on to regular bind handling.
+fn advance_key_sequence(
+    bindings: &Binds,
+    sequence: &mut Vec<Key>,
+    mod_key: ModKey,
+    trigger: Trigger,
+    mods: ModifiersState,
+) -> SequenceStep {
+    let pos = sequence.len();
+    let mut next = None;
+
     for bind in &bindings.0 {
-        if bind.key.trigger != trigger {
+        if bind.prefix.len() < pos || bind.prefix[..pos] != sequence[..] {
             continue;
         }
 
-        let mut bind_modifiers = bind.key.modifiers;
-        if bind_modifiers.contains(Modifiers::COMPOSITOR) {
-            bind_modifiers |= mod_key.to_modifiers();
-        } else if bind_modifiers.contains(mod_key.to_modifiers()) {
-            bind_modifiers |= Modifiers::COMPOSITOR;
-        }
-
-        if bind_modifiers == modifiers {
-            return Some(bind.clone());
+        if bind.prefix.len() == pos {
+            if pos > 0 && key_matches(&bind.key, mod_key, trigger, mods) {
+                sequence.clear();
+                return SequenceStep::Completed(bind.clone());
+            }
+        } else if key_matches(&bind.prefix[pos], mod_key, trigger, mods) {
+            next = Some(bind.prefix[pos]);
         }
     }
 
-    None
+    if let Some(key) = next {
+        sequence.push(key);
+        SequenceStep::Continued
+    } else {
+        sequence.clear();
+        SequenceStep::None
+    }
 }
 
 fn find_configured_switch_action(
@@ -4236,6 +4904,7 @@ fn hardcoded_overview_bind(raw: Keysym, mods: ModifiersState) -> Option<Bind> {
     };
 
     Some(Bind {
+        prefix: Vec::new(),
         key: Key {
             trigger: Trigger::Keysym(raw),
             modifiers: Modifiers::empty(),
@@ -4592,6 +5261,7 @@ mod tests {
     fn bindings_suppress_keys() {
         let close_keysym = Keysym::q;
         let bindings = Binds(vec![Bind {
+            prefix: Vec::new(),
             key: Key {
                 trigger: Trigger::Keysym(close_keysym),
                 modifiers: Modifiers::COMPOSITOR | Modifiers::CTRL,
@@ -4619,6 +5289,7 @@ mod tests {
             should_intercept_key(
                 suppr,
                 &bindings,
+                &mut Vec::new(),
                 comp_mod,
                 close_key_code,
                 close_keysym,
@@ -4636,6 +5307,7 @@ mod tests {
             should_intercept_key(
                 suppr,
                 &bindings,
+                &mut Vec::new(),
                 comp_mod,
                 Keycode::from(Keysym::l.raw() + 8),
                 Keysym::l,
@@ -4778,6 +5450,7 @@ mod tests {
     fn comp_mod_handling() {
         let bindings = Binds(vec![
             Bind {
+                prefix: Vec::new(),
                 key: Key {
                     trigger: Trigger::Keysym(Keysym::q),
                     modifiers: Modifiers::COMPOSITOR,
@@ -4790,6 +5463,7 @@ mod tests {
                 hotkey_overlay_title: None,
             },
             Bind {
+                prefix: Vec::new(),
                 key: Key {
                     trigger: Trigger::Keysym(Keysym::h),
                     modifiers: Modifiers::SUPER,
@@ -4802,6 +5476,7 @@ mod tests {
                 hotkey_overlay_title: None,
             },
             Bind {
+                prefix: Vec::new(),
                 key: Key {
                     trigger: Trigger::Keysym(Keysym::j),
                     modifiers: Modifiers::empty(),
@@ -4814,6 +5489,7 @@ mod tests {
                 hotkey_overlay_title: None,
             },
             Bind {
+                prefix: Vec::new(),
                 key: Key {
                     trigger: Trigger::Keysym(Keysym::k),
                     modifiers: Modifiers::COMPOSITOR | Modifiers::SUPER,
@@ -4826,6 +5502,7 @@ mod tests {
                 hotkey_overlay_title: None,
             },
             Bind {
+                prefix: Vec::new(),
                 key: Key {
                     trigger: Trigger::Keysym(Keysym::l),
                     modifiers: Modifiers::SUPER | Modifiers::ALT,
@@ -4958,4 +5635,73 @@ mod tests {
             None,
         );
     }
+
+    #[test]
+    fn key_sequences() {
+        let key = |trigger, modifiers| Key {
+            trigger: Trigger::Keysym(trigger),
+            modifiers,
+        };
+        let bindings = Binds(vec![
+            Bind {
+                prefix: vec![key(Keysym::x, Modifiers::COMPOSITOR)],
+                key: key(Keysym::c, Modifiers::empty()),
+                action: Action::CloseWindow,
+                repeat: true,
+                cooldown: None,
+                allow_when_locked: false,
+                allow_inhibiting: true,
+                hotkey_overlay_title: None,
+            },
+            Bind {
+                prefix: vec![key(Keysym::x, Modifiers::COMPOSITOR)],
+                key: key(Keysym::f, Modifiers::empty()),
+                action: Action::FullscreenWindow,
+                repeat: true,
+                cooldown: None,
+                allow_when_locked: false,
+                allow_inhibiting: true,
+                hotkey_overlay_title: None,
+            },
+        ]);
+
+        let logo = ModifiersState {
+            logo: true,
+            ..Default::default()
+        };
+        let none = ModifiersState::default();
+        let mut sequence = Vec::new();
+        let mut press = |keysym, mods| {
+            advance_key_sequence(
+                &bindings,
+                &mut sequence,
+                ModKey::Super,
+                Trigger::Keysym(keysym),
+                mods,
+            )
+        };
+
+        // A sequence key alone doesn't trigger anything.
+        assert!(matches!(press(Keysym::c, none), SequenceStep::None));
+
+        assert!(matches!(press(Keysym::x, logo), SequenceStep::Continued));
+        assert!(matches!(
+            press(Keysym::f, none),
+            SequenceStep::Completed(Bind {
+                action: Action::FullscreenWindow,
+                ..
+            })
+        ));
+
+        // A wrong key resets the sequence.
+        assert!(matches!(press(Keysym::x, logo), SequenceStep::Continued));
+        assert!(matches!(press(Keysym::q, none), SequenceStep::None));
+        assert!(matches!(press(Keysym::c, none), SequenceStep::None));
+
+        // Sequences are not regular binds.
+        assert_eq!(
+            find_configured_bind(&bindings, ModKey::Super, Trigger::Keysym(Keysym::x), logo),
+            None
+        );
+    }
 }
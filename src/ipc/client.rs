@@ -1,17 +1,21 @@
+use std::fs;
 use std::io::ErrorKind;
 use std::iter::Peekable;
+use std::path::Path;
 use std::slice;
 
 use anyhow::{anyhow, bail, Context};
 use niri_config::OutputName;
 use niri_ipc::socket::Socket;
 use niri_ipc::{
-    Event, KeyboardLayouts, LogicalOutput, Mode, Output, OutputConfigChanged, Overview, Request,
-    Response, Transform, Window,
+    Event, EventFilter, KeyboardLayouts, LogicalOutput, Mode, Output, OutputConfigChanged,
+    Overview, Request, Response, Transform, Window,
 };
 use serde_json::json;
 
+use crate::backend::headless::create_software_renderer;
 use crate::cli::Msg;
+use crate::render_helpers::shaders::{self, CustomShaderKind};
 use crate::utils::version;
 
 pub fn handle_msg(msg: Msg, json: bool) -> anyhow::Result<()> {
@@ -31,9 +35,33 @@ pub fn handle_msg(msg: Msg, json: bool) -> anyhow::Result<()> {
         Msg::Windows => Request::Windows,
         Msg::Layers => Request::Layers,
         Msg::KeyboardLayouts => Request::KeyboardLayouts,
-        Msg::EventStream => Request::EventStream,
+        Msg::EventStream {
+            kind,
+            output,
+            workspace_id,
+            window_id,
+        } => {
+            let filter = EventFilter {
+                kinds: kind.clone(),
+                output: output.clone(),
+                workspace_id: *workspace_id,
+                window_id: *window_id,
+            };
+            let filter = (filter != EventFilter::default()).then_some(filter);
+            Request::EventStream { filter }
+        }
         Msg::RequestError => Request::ReturnError,
         Msg::OverviewState => Request::OverviewState,
+        Msg::SaveSession { .. } => Request::SaveSession,
+        Msg::RestoreSession { path } => {
+            let session = fs::read_to_string(path)
+                .with_context(|| format!("error reading {}", path.display()))?;
+            let session = serde_json::from_str(&session)
+                .with_context(|| format!("error parsing {}", path.display()))?;
+            Request::RestoreSession(session)
+        }
+        // Handled locally without connecting to niri.
+        Msg::ValidateShader { kind, path } => return validate_shader((*kind).into(), path),
     };
 
     let mut socket = Socket::connect().context("error connecting to the niri socket")?;
@@ -393,7 +421,7 @@ pub fn handle_msg(msg: Msg, json: bool) -> anyhow::Result<()> {
                 println!("{is_active}{idx} {name}");
             }
         }
-        Msg::EventStream => {
+        Msg::EventStream { .. } => {
             let Response::Handled = response else {
                 bail!("unexpected response: expected Handled, got {response:?}");
             };
@@ -456,6 +484,15 @@ pub fn handle_msg(msg: Msg, json: bool) -> anyhow::Result<()> {
                     Event::OverviewOpenedOrClosed { is_open: opened } => {
                         println!("Overview toggled: {opened}");
                     }
+                    Event::BindModeChanged { name } => {
+                        println!(
+                            "Binding mode changed: {}",
+                            name.as_deref().unwrap_or("default")
+                        );
+                    }
+                    Event::HotCornerTriggered { output, corner } => {
+                        println!("Hot corner triggered on {output}: {corner:?}");
+                    }
                 }
             }
         }
@@ -478,11 +515,39 @@ pub fn handle_msg(msg: Msg, json: bool) -> anyhow::Result<()> {
                 println!("Overview is closed.");
             }
         }
+        Msg::SaveSession { path } => {
+            let Response::Session(session) = response else {
+                bail!("unexpected response: expected Session, got {response:?}");
+            };
+
+            let session =
+                serde_json::to_string_pretty(&session).context("error formatting session")?;
+            fs::write(&path, session + "\n")
+                .with_context(|| format!("error writing {}", path.display()))?;
+        }
+        Msg::RestoreSession { .. } => {
+            let Response::Handled = response else {
+                bail!("unexpected response: expected Handled, got {response:?}");
+            };
+        }
+        Msg::ValidateShader { .. } => unreachable!(),
     }
 
     Ok(())
 }
 
+fn validate_shader(kind: CustomShaderKind, path: &Path) -> anyhow::Result<()> {
+    let src =
+        fs::read_to_string(path).with_context(|| format!("error reading {}", path.display()))?;
+
+    let mut renderer = create_software_renderer()?;
+    shaders::compile_custom_program(&mut renderer, kind, &src)
+        .map_err(|err| anyhow!("error compiling shader: {err}"))?;
+
+    println!("Shader is valid.");
+    Ok(())
+}
+
 fn print_output(output: Output) -> anyhow::Result<()> {
     let Output {
         name,
@@ -494,6 +559,8 @@ fn print_output(output: Output) -> anyhow::Result<()> {
         current_mode,
         vrr_supported,
         vrr_enabled,
+        tearing,
+        powered_off,
         logical,
     } = output;
 
@@ -517,6 +584,10 @@ fn print_output(output: Output) -> anyhow::Result<()> {
         println!("  Disabled");
     }
 
+    if powered_off {
+        println!("  Powered off");
+    }
+
     if vrr_supported {
         let enabled = if vrr_enabled { "enabled" } else { "disabled" };
         println!("  Variable refresh rate: supported, {enabled}");
@@ -524,6 +595,9 @@ fn print_output(output: Output) -> anyhow::Result<()> {
         println!("  Variable refresh rate: not supported");
     }
 
+    let tearing = if tearing { "active" } else { "inactive" };
+    println!("  Tearing: {tearing}");
+
     if let Some((width, height)) = physical_size {
         println!("  Physical size: {width}x{height} mm");
     } else {
@@ -601,6 +675,11 @@ fn print_window(window: &Window) {
         if window.is_floating { "yes" } else { "no" }
     );
 
+    println!(
+        "  Is sticky: {}",
+        if window.is_sticky { "yes" } else { "no" }
+    );
+
     if let Some(pid) = window.pid {
         println!("  PID: {pid}");
     } else {
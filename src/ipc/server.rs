@@ -17,12 +17,14 @@ use futures_util::{select_biased, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, Fu
 use niri_config::OutputName;
 use niri_ipc::state::{EventStreamState, EventStreamStatePart as _};
 use niri_ipc::{
-    Event, KeyboardLayouts, OutputConfigChanged, Overview, Reply, Request, Response, Workspace,
+    Event, EventFilter, EventKind, HotCorner, KeyboardLayouts, OutputConfigChanged, Overview,
+    Reply, Request, Response, SessionWindow, Workspace,
 };
 use smithay::desktop::layer_map_for_output;
 use smithay::input::pointer::{
     CursorIcon, CursorImageStatus, Focus, GrabStartData as PointerGrabStartData,
 };
+use smithay::output::Output;
 use smithay::reexports::calloop::generic::Generic;
 use smithay::reexports::calloop::{Interest, LoopHandle, Mode, PostAction};
 use smithay::reexports::rustix::fs::unlink;
@@ -34,7 +36,7 @@ use crate::input::pick_window_grab::PickWindowGrab;
 use crate::layout::workspace::WorkspaceId;
 use crate::niri::State;
 use crate::utils::{version, with_toplevel_role};
-use crate::window::Mapped;
+use crate::window::{Mapped, SessionRestore};
 
 // If an event stream client fails to read events fast enough that we accumulate more than this
 // number in our buffer, we drop that event stream client.
@@ -66,6 +68,20 @@ struct EventStreamClient {
 struct EventStreamSender {
     events: Sender<Event>,
     disconnect: Sender<()>,
+    filter: Option<EventStreamFilter>,
+}
+
+/// Filter of an event stream client, along with the part of the state that it has let through.
+struct EventStreamFilter {
+    filter: EventFilter,
+    /// Ids of the workspaces that the client knows about.
+    workspaces: HashSet<u64>,
+    /// Ids of the windows that the client knows about.
+    windows: HashSet<u64>,
+    /// Whether one of the known workspaces is focused.
+    focused_workspace: bool,
+    /// Id of the focused window, if it is known.
+    focused_window: Option<u64>,
 }
 
 impl IpcServer {
@@ -111,19 +127,34 @@ impl IpcServer {
         })
     }
 
-    fn send_event(&self, event: Event) {
+    /// Sends an event to the event stream clients.
+    ///
+    /// The event must already be applied to `state`.
+    fn send_event(&self, state: &EventStreamState, event: Event) {
         let mut streams = self.event_streams.borrow_mut();
         let mut to_remove = Vec::new();
-        for (idx, stream) in streams.iter_mut().enumerate() {
-            match stream.events.try_send(event.clone()) {
-                Ok(()) => (),
-                Err(TrySendError::Closed(_)) => to_remove.push(idx),
-                Err(TrySendError::Full(_)) => {
-                    warn!(
-                        "disconnecting IPC event stream client \
-                         because it is reading events too slowly"
-                    );
-                    to_remove.push(idx);
+        'streams: for (idx, stream) in streams.iter_mut().enumerate() {
+            // Filter before sending so that filtered out events are never serialized.
+            let events = match &mut stream.filter {
+                Some(filter) => filter.process(state, event.clone()),
+                None => vec![event.clone()],
+            };
+
+            for event in events {
+                match stream.events.try_send(event) {
+                    Ok(()) => (),
+                    Err(TrySendError::Closed(_)) => {
+                        to_remove.push(idx);
+                        continue 'streams;
+                    }
+                    Err(TrySendError::Full(_)) => {
+                        warn!(
+                            "disconnecting IPC event stream client \
+                             because it is reading events too slowly"
+                        );
+                        to_remove.push(idx);
+                        continue 'streams;
+                    }
                 }
             }
         }
@@ -135,6 +166,216 @@ impl IpcServer {
     }
 }
 
+impl EventStreamFilter {
+    fn new(filter: EventFilter) -> Self {
+        Self {
+            filter,
+            workspaces: HashSet::new(),
+            windows: HashSet::new(),
+            focused_workspace: false,
+            focused_window: None,
+        }
+    }
+
+    /// Returns the initial full-state burst for this filter.
+    fn replicate(&mut self, state: &EventStreamState) -> Vec<Event> {
+        let events = state.replicate().into_iter();
+        events.flat_map(|event| self.filter(state, event)).collect()
+    }
+
+    /// Returns the events to send to the client in response to a new event.
+    fn process(&mut self, state: &EventStreamState, event: Event) -> Vec<Event> {
+        let workspaces_changed = matches!(event, Event::WorkspacesChanged { .. });
+
+        let mut events = self.filter(state, event);
+
+        // Workspaces may have moved to or from the filtered output, taking their windows along.
+        if workspaces_changed {
+            events.extend(self.refresh_windows(state));
+        }
+
+        events
+    }
+
+    fn matches_window(&self, state: &EventStreamState, window: &niri_ipc::Window) -> bool {
+        if self.filter.window_id.is_some_and(|id| id != window.id) {
+            return false;
+        }
+
+        if self.filter.workspace_id.is_some() && window.workspace_id != self.filter.workspace_id {
+            return false;
+        }
+
+        if let Some(output) = &self.filter.output {
+            let ws = window.workspace_id;
+            let ws = ws.and_then(|id| state.workspaces.workspaces.get(&id));
+            if ws.and_then(|ws| ws.output.as_ref()) != Some(output) {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn workspaces_changed(&mut self, workspaces: Vec<Workspace>) -> Event {
+        let workspaces: Vec<_> = workspaces
+            .into_iter()
+            .filter(|ws| self.filter.matches_workspace(ws))
+            .collect();
+
+        self.workspaces = workspaces.iter().map(|ws| ws.id).collect();
+        self.focused_workspace = workspaces.iter().any(|ws| ws.is_focused);
+
+        Event::WorkspacesChanged { workspaces }
+    }
+
+    fn windows_changed(
+        &mut self,
+        state: &EventStreamState,
+        windows: Vec<niri_ipc::Window>,
+    ) -> Event {
+        let windows: Vec<_> = windows
+            .into_iter()
+            .filter(|win| self.matches_window(state, win))
+            .collect();
+
+        self.windows = windows.iter().map(|win| win.id).collect();
+        self.focused_window = windows.iter().find(|win| win.is_focused).map(|win| win.id);
+
+        Event::WindowsChanged { windows }
+    }
+
+    fn refresh_windows(&mut self, state: &EventStreamState) -> Option<Event> {
+        if self.filter.output.is_none() || !self.filter.wants(EventKind::Windows) {
+            return None;
+        }
+
+        let windows = state.windows.windows.values();
+        let mut matching = windows.filter(|win| self.matches_window(state, win));
+        let count = matching.clone().count();
+        if count == self.windows.len() && matching.all(|win| self.windows.contains(&win.id)) {
+            return None;
+        }
+
+        let windows = state.windows.windows.values().cloned().collect();
+        Some(self.windows_changed(state, windows))
+    }
+
+    /// Converts an event into the events to send to the client.
+    fn filter(&mut self, state: &EventStreamState, event: Event) -> Vec<Event> {
+        if !self.filter.wants(event.kind()) {
+            return vec![];
+        }
+
+        match event {
+            Event::WorkspacesChanged { workspaces } => {
+                vec![self.workspaces_changed(workspaces)]
+            }
+            Event::WorkspaceUrgencyChanged { id, .. }
+            | Event::WorkspaceActiveWindowChanged {
+                workspace_id: id, ..
+            } => {
+                if self.workspaces.contains(&id) {
+                    vec![event]
+                } else {
+                    vec![]
+                }
+            }
+            Event::WorkspaceActivated { id, focused } => {
+                if self.workspaces.contains(&id) {
+                    self.focused_workspace |= focused;
+                    return vec![event];
+                }
+
+                // A workspace outside of the filter can still deactivate or unfocus known
+                // workspaces. The event is already applied to the state, so resend them from
+                // there.
+                let workspaces = &state.workspaces.workspaces;
+                let output = workspaces.get(&id).and_then(|ws| ws.output.as_ref());
+                let same_output = self.workspaces.iter().any(|known| {
+                    let known = workspaces.get(known);
+                    known.is_some_and(|ws| ws.output.as_ref() == output)
+                });
+
+                if (focused && self.focused_workspace) || same_output {
+                    let workspaces = workspaces.values().cloned().collect();
+                    vec![self.workspaces_changed(workspaces)]
+                } else {
+                    vec![]
+                }
+            }
+            Event::WindowsChanged { windows } => {
+                vec![self.windows_changed(state, windows)]
+            }
+            Event::WindowOpenedOrChanged { window } => {
+                let id = window.id;
+                let mut events = Vec::new();
+
+                if self.matches_window(state, &window) {
+                    self.windows.insert(id);
+                    if window.is_focused {
+                        self.focused_window = Some(id);
+                    } else if self.focused_window == Some(id) {
+                        self.focused_window = None;
+                    }
+
+                    events.push(Event::WindowOpenedOrChanged { window });
+                    return events;
+                }
+
+                // The window has moved out of the filter, so to the client it is now closed.
+                if self.windows.remove(&id) {
+                    if self.focused_window == Some(id) {
+                        self.focused_window = None;
+                    }
+
+                    events.push(Event::WindowClosed { id });
+                }
+
+                if window.is_focused && self.focused_window.is_some() {
+                    self.focused_window = None;
+                    events.push(Event::WindowFocusChanged { id: None });
+                }
+
+                events
+            }
+            Event::WindowClosed { id } => {
+                if !self.windows.remove(&id) {
+                    return vec![];
+                }
+
+                if self.focused_window == Some(id) {
+                    self.focused_window = None;
+                }
+
+                vec![event]
+            }
+            Event::WindowFocusChanged { id } => {
+                // Don't leak ids of windows outside of the filter.
+                let id = id.filter(|id| self.windows.contains(id));
+                if id == self.focused_window {
+                    return vec![];
+                }
+
+                self.focused_window = id;
+                vec![Event::WindowFocusChanged { id }]
+            }
+            Event::WindowUrgencyChanged { id, .. } => {
+                if self.windows.contains(&id) {
+                    vec![event]
+                } else {
+                    vec![]
+                }
+            }
+            Event::KeyboardLayoutsChanged { .. }
+            | Event::KeyboardLayoutSwitched { .. }
+            | Event::OverviewOpenedOrClosed { .. }
+            | Event::BindModeChanged { .. }
+            | Event::HotCornerTriggered { .. } => vec![event],
+        }
+    }
+}
+
 impl Drop for IpcServer {
     fn drop(&mut self) {
         if let Some(socket_path) = &self.socket_path {
@@ -201,11 +442,14 @@ async fn handle_client(ctx: ClientCtx, stream: Async<'static, UnixStream>) -> an
             }
         }
 
-        let request = serde_json::from_slice(&buf)
+        let request = parse_request(&buf)
             .context("error parsing request")
             .map_err(|err| err.to_string());
         let requested_error = matches!(request, Ok(Request::ReturnError));
-        let requested_event_stream = matches!(request, Ok(Request::EventStream));
+        let requested_event_stream = match &request {
+            Ok(Request::EventStream { filter }) => Some(filter.clone()),
+            _ => None,
+        };
 
         let reply = match request {
             Ok(request) => process(&ctx, request).await,
@@ -223,7 +467,7 @@ async fn handle_client(ctx: ClientCtx, stream: Async<'static, UnixStream>) -> an
         buf.push(b'\n');
         write.write_all(&buf).await.context("error writing reply")?;
 
-        if requested_event_stream {
+        if let Some(filter) = requested_event_stream {
             let (events_tx, events_rx) = async_channel::bounded(EVENT_STREAM_BUFFER_SIZE);
             let (disconnect_tx, disconnect_rx) = async_channel::bounded(1);
 
@@ -242,10 +486,16 @@ async fn handle_client(ctx: ClientCtx, stream: Async<'static, UnixStream>) -> an
                 warn!("error scheduling IPC event stream future: {err:?}");
             }
 
+            let mut filter = filter.map(EventStreamFilter::new);
+
             // Send the initial state.
             {
                 let state = ctx.event_stream_state.borrow();
-                for event in state.replicate() {
+                let events = match &mut filter {
+                    Some(filter) => filter.replicate(&state),
+                    None => state.replicate(),
+                };
+                for event in events {
                     events_tx
                         .try_send(event)
                         .expect("initial event burst had more events than buffer size");
@@ -258,6 +508,7 @@ async fn handle_client(ctx: ClientCtx, stream: Async<'static, UnixStream>) -> an
                 let sender = EventStreamSender {
                     events: events_tx,
                     disconnect: disconnect_tx,
+                    filter,
                 };
                 streams.push(sender);
             }
@@ -267,6 +518,16 @@ async fn handle_client(ctx: ClientCtx, stream: Async<'static, UnixStream>) -> an
     }
 }
 
+/// Parses a request, also accepting a bare `"EventStream"` from before event streams had filters.
+fn parse_request(buf: &[u8]) -> serde_json::Result<Request> {
+    match serde_json::from_slice(buf)? {
+        serde_json::Value::String(name) if name == "EventStream" => {
+            Ok(Request::EventStream { filter: None })
+        }
+        value => serde_json::from_value(value),
+    }
+}
+
 async fn process(ctx: &ClientCtx, request: Request) -> Reply {
     let response = match request {
         Request::ReturnError => return Err(String::from("example compositor error")),
@@ -439,12 +700,33 @@ async fn process(ctx: &ClientCtx, request: Request) -> Reply {
             let output = result.map_err(|_| String::from("error getting active output info"))?;
             Response::FocusedOutput(output)
         }
-        Request::EventStream => Response::Handled,
+        Request::EventStream { .. } => Response::Handled,
         Request::OverviewState => {
             let state = ctx.event_stream_state.borrow();
             let is_open = state.overview.is_open;
             Response::OverviewState(Overview { is_open })
         }
+        Request::SaveSession => {
+            let (tx, rx) = async_channel::bounded(1);
+            ctx.event_loop.insert_idle(move |state| {
+                let session = state.niri.layout.session(|mapped| {
+                    with_toplevel_role(mapped.toplevel(), |role| SessionWindow {
+                        app_id: role.app_id.clone(),
+                        title: role.title.clone(),
+                    })
+                });
+                let _ = tx.send_blocking(session);
+            });
+            let result = rx.recv().await;
+            let session = result.map_err(|_| String::from("error getting session"))?;
+            Response::Session(session)
+        }
+        Request::RestoreSession(session) => {
+            ctx.event_loop.insert_idle(move |state| {
+                state.niri.session_restore = SessionRestore::new(session);
+            });
+            Response::Handled
+        }
     };
 
     Ok(response)
@@ -486,6 +768,7 @@ fn make_ipc_window(mapped: &Mapped, workspace_id: Option<WorkspaceId>) -> niri_i
         workspace_id: workspace_id.map(|id| id.get()),
         is_focused: mapped.is_focused(),
         is_floating: mapped.is_floating(),
+        is_sticky: mapped.is_sticky(),
         is_urgent: mapped.is_urgent(),
     })
 }
@@ -508,12 +791,12 @@ impl State {
             return;
         };
 
-        let mut state = server.event_stream_state.borrow_mut();
-        let state = &mut state.keyboard_layouts;
+        let mut stream_state = server.event_stream_state.borrow_mut();
+        let state = &mut stream_state.keyboard_layouts;
 
         let event = Event::KeyboardLayoutsChanged { keyboard_layouts };
         state.apply(event.clone());
-        server.send_event(event);
+        server.send_event(&stream_state, event);
     }
 
     pub fn ipc_refresh_keyboard_layout_index(&mut self) {
@@ -527,8 +810,8 @@ impl State {
             return;
         };
 
-        let mut state = server.event_stream_state.borrow_mut();
-        let state = &mut state.keyboard_layouts;
+        let mut stream_state = server.event_stream_state.borrow_mut();
+        let state = &mut stream_state.keyboard_layouts;
 
         if state.keyboard_layouts.as_ref().unwrap().current_idx == idx {
             return;
@@ -536,7 +819,7 @@ impl State {
 
         let event = Event::KeyboardLayoutSwitched { idx };
         state.apply(event.clone());
-        server.send_event(event);
+        server.send_event(&stream_state, event);
     }
 
     pub fn ipc_refresh_layout(&mut self) {
@@ -552,8 +835,8 @@ impl State {
 
         let _span = tracy_client::span!("State::ipc_refresh_workspaces");
 
-        let mut state = server.event_stream_state.borrow_mut();
-        let state = &mut state.workspaces;
+        let mut stream_state = server.event_stream_state.borrow_mut();
+        let state = &mut stream_state.workspaces;
 
         let mut events = Vec::new();
         let layout = &self.niri.layout;
@@ -639,8 +922,8 @@ impl State {
         }
 
         for event in events {
-            state.apply(event.clone());
-            server.send_event(event);
+            stream_state.workspaces.apply(event.clone());
+            server.send_event(&stream_state, event);
         }
     }
 
@@ -651,8 +934,8 @@ impl State {
 
         let _span = tracy_client::span!("State::ipc_refresh_windows");
 
-        let mut state = server.event_stream_state.borrow_mut();
-        let state = &mut state.windows;
+        let mut stream_state = server.event_stream_state.borrow_mut();
+        let state = &mut stream_state.windows;
 
         let mut events = Vec::new();
         let layout = &self.niri.layout;
@@ -675,8 +958,9 @@ impl State {
             };
 
             let workspace_id = ws_id.map(|id| id.get());
-            let mut changed =
-                ipc_win.workspace_id != workspace_id || ipc_win.is_floating != mapped.is_floating();
+            let mut changed = ipc_win.workspace_id != workspace_id
+                || ipc_win.is_floating != mapped.is_floating()
+                || ipc_win.is_sticky != mapped.is_sticky();
 
             changed |= with_toplevel_role(mapped.toplevel(), |role| {
                 ipc_win.title != role.title || ipc_win.app_id != role.app_id
@@ -717,8 +1001,8 @@ impl State {
         }
 
         for event in events {
-            state.apply(event.clone());
-            server.send_event(event);
+            stream_state.windows.apply(event.clone());
+            server.send_event(&stream_state, event);
         }
     }
 
@@ -727,8 +1011,8 @@ impl State {
             return;
         };
 
-        let mut state = server.event_stream_state.borrow_mut();
-        let state = &mut state.overview;
+        let mut stream_state = server.event_stream_state.borrow_mut();
+        let state = &mut stream_state.overview;
         let is_open = self.niri.layout.is_overview_open();
 
         if state.is_open == is_open {
@@ -737,6 +1021,196 @@ impl State {
 
         let event = Event::OverviewOpenedOrClosed { is_open };
         state.apply(event.clone());
-        server.send_event(event);
+        server.send_event(&stream_state, event);
+    }
+
+    pub fn ipc_bind_mode_changed(&mut self) {
+        let Some(server) = &self.niri.ipc_server else {
+            return;
+        };
+
+        let mut stream_state = server.event_stream_state.borrow_mut();
+        let state = &mut stream_state.bind_mode;
+
+        if state.name == self.niri.bind_mode {
+            return;
+        }
+
+        let event = Event::BindModeChanged {
+            name: self.niri.bind_mode.clone(),
+        };
+        state.apply(event.clone());
+        server.send_event(&stream_state, event);
+    }
+
+    pub fn ipc_hot_corner_triggered(&mut self, output: &Output, corner: HotCorner) {
+        let Some(server) = &self.niri.ipc_server else {
+            return;
+        };
+
+        // Not part of the event stream state, so there's nothing to apply.
+        let stream_state = server.event_stream_state.borrow();
+        let event = Event::HotCornerTriggered {
+            output: output.name(),
+            corner,
+        };
+        server.send_event(&stream_state, event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use niri_ipc::Window;
+
+    use super::*;
+
+    fn workspace(id: u64, output: &str, is_focused: bool) -> Workspace {
+        Workspace {
+            id,
+            idx: 1,
+            name: None,
+            output: Some(output.to_owned()),
+            is_urgent: false,
+            is_active: true,
+            is_focused,
+            active_window_id: None,
+        }
+    }
+
+    fn window(id: u64, workspace_id: u64) -> Window {
+        Window {
+            id,
+            title: None,
+            app_id: None,
+            pid: None,
+            workspace_id: Some(workspace_id),
+            is_focused: false,
+            is_floating: false,
+            is_sticky: false,
+            is_urgent: false,
+        }
+    }
+
+    fn state() -> EventStreamState {
+        let mut state = EventStreamState::default();
+        state.apply(Event::WorkspacesChanged {
+            workspaces: vec![workspace(1, "A", true), workspace(2, "B", false)],
+        });
+        state.apply(Event::WindowsChanged {
+            windows: vec![window(10, 1), window(20, 2)],
+        });
+        state.apply(Event::KeyboardLayoutsChanged {
+            keyboard_layouts: KeyboardLayouts {
+                names: vec![String::from("us")],
+                current_idx: 0,
+            },
+        });
+        state
+    }
+
+    fn filter(kinds: &[EventKind], output: Option<&str>) -> EventStreamFilter {
+        EventStreamFilter::new(EventFilter {
+            kinds: kinds.to_vec(),
+            output: output.map(String::from),
+            workspace_id: None,
+            window_id: None,
+        })
+    }
+
+    /// Applies the event to the state and returns what the filter sends.
+    fn send(filter: &mut EventStreamFilter, state: &mut EventStreamState, event: Event) -> String {
+        state.apply(event.clone());
+        format!("{:?}", filter.process(state, event))
+    }
+
+    #[test]
+    fn parse_event_stream_requests() {
+        let request = parse_request(b"\"EventStream\"\n").unwrap();
+        assert!(matches!(request, Request::EventStream { filter: None }));
+
+        let request = parse_request(br#"{"EventStream":{}}"#).unwrap();
+        assert!(matches!(request, Request::EventStream { filter: None }));
+
+        let request = br#"{"EventStream":{"filter":{"kinds":["Windows"]}}}"#;
+        let Request::EventStream { filter } = parse_request(request).unwrap() else {
+            panic!("expected an event stream request");
+        };
+        assert_eq!(filter.unwrap().kinds, [EventKind::Windows]);
+
+        let request = parse_request(b"\"Version\"").unwrap();
+        assert!(matches!(request, Request::Version));
+    }
+
+    #[test]
+    fn replicate_sends_only_matching_state() {
+        let state = state();
+        let mut filter = filter(&[EventKind::Windows], Some("A"));
+
+        let events = filter.replicate(&state);
+        assert_eq!(events.len(), 1, "{events:?}");
+        let Event::WindowsChanged { windows } = &events[0] else {
+            panic!("unexpected initial event: {:?}", events[0]);
+        };
+        let ids: Vec<_> = windows.iter().map(|win| win.id).collect();
+        assert_eq!(ids, [10]);
+        assert_eq!(filter.windows, HashSet::from([10]));
+    }
+
+    #[test]
+    fn replicate_without_kinds_sends_every_kind() {
+        let state = state();
+        let mut filter = filter(&[], Some("B"));
+
+        let events = filter.replicate(&state);
+        let kinds: HashSet<_> = events.iter().map(Event::kind).collect();
+        assert!(kinds.contains(&EventKind::Workspaces));
+        assert!(kinds.contains(&EventKind::Windows));
+        assert!(kinds.contains(&EventKind::KeyboardLayouts));
+        assert_eq!(filter.workspaces, HashSet::from([2]));
+        assert_eq!(filter.windows, HashSet::from([20]));
+    }
+
+    #[test]
+    fn filtered_kinds_are_suppressed() {
+        let mut state = state();
+        let mut filter = filter(&[EventKind::Workspaces], None);
+        filter.replicate(&state);
+
+        let event = Event::WindowOpenedOrChanged {
+            window: window(30, 1),
+        };
+        assert_eq!(send(&mut filter, &mut state, event), "[]");
+
+        let event = Event::KeyboardLayoutSwitched { idx: 0 };
+        assert_eq!(send(&mut filter, &mut state, event), "[]");
+
+        let event = Event::WorkspaceActivated {
+            id: 2,
+            focused: true,
+        };
+        assert_eq!(
+            send(&mut filter, &mut state, event),
+            "[WorkspaceActivated { id: 2, focused: true }]"
+        );
+    }
+
+    #[test]
+    fn window_leaving_output_is_closed() {
+        let mut state = state();
+        let mut filter = filter(&[EventKind::Windows], Some("A"));
+        filter.replicate(&state);
+
+        let event = Event::WindowOpenedOrChanged {
+            window: window(10, 2),
+        };
+        assert_eq!(
+            send(&mut filter, &mut state, event),
+            "[WindowClosed { id: 10 }]"
+        );
+        assert!(filter.windows.is_empty());
+
+        // Events for windows outside of the filter are not sent.
+        let event = Event::WindowClosed { id: 20 };
+        assert_eq!(send(&mut filter, &mut state, event), "[]");
     }
 }
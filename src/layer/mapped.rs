@@ -1,21 +1,26 @@
 use niri_config::layer_rule::LayerRule;
 use niri_config::Config;
-use smithay::backend::renderer::element::surface::{
-    render_elements_from_surface_tree, WaylandSurfaceRenderElement,
-};
+use smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement;
 use smithay::backend::renderer::element::Kind;
 use smithay::desktop::{LayerSurface, PopupManager};
-use smithay::utils::{Logical, Point, Scale, Size};
+use smithay::utils::{Logical, Point, Rectangle, Scale, Size};
+use smithay::wayland::compositor::with_states;
 use smithay::wayland::shell::wlr_layer::{ExclusiveZone, Layer};
 
 use super::ResolvedLayerRules;
 use crate::animation::Clock;
 use crate::layout::shadow::Shadow;
 use crate::niri_render_elements;
+use crate::protocols::background_effect::surface_blur_region;
+use crate::render_helpers::blur::{region_rects, BlurBuffer, BlurParams, BlurRenderElement};
+use crate::render_helpers::color_transform::{
+    render_elements_from_surface_tree, ColorTransformRenderElement,
+};
 use crate::render_helpers::renderer::NiriRenderer;
 use crate::render_helpers::shadow::ShadowRenderElement;
 use crate::render_helpers::solid_color::{SolidColorBuffer, SolidColorRenderElement};
 use crate::render_helpers::{RenderTarget, SplitElements};
+use crate::utils::color::ColorDescription;
 use crate::utils::{baba_is_float_offset, round_logical_in_physical};
 
 #[derive(Debug)]
@@ -32,6 +37,9 @@ pub struct MappedLayer {
     /// The shadow around the surface.
     shadow: Shadow,
 
+    /// Cached background blur behind the surface.
+    blur: BlurBuffer,
+
     /// The view size for the layer surface's output.
     view_size: Size<f64, Logical>,
 
@@ -45,8 +53,10 @@ pub struct MappedLayer {
 niri_render_elements! {
     LayerSurfaceRenderElement<R> => {
         Wayland = WaylandSurfaceRenderElement<R>,
+        ColorTransform = ColorTransformRenderElement<R>,
         SolidColor = SolidColorRenderElement,
         Shadow = ShadowRenderElement,
+        Blur = BlurRenderElement,
     }
 }
 
@@ -71,6 +81,7 @@ impl MappedLayer {
             view_size,
             scale,
             shadow: Shadow::new(shadow_config),
+            blur: BlurBuffer::new(),
             clock,
         }
     }
@@ -118,6 +129,10 @@ impl MappedLayer {
         &self.rules
     }
 
+    pub fn blur(&self) -> &BlurBuffer {
+        &self.blur
+    }
+
     /// Recomputes the resolved layer rules and returns whether they changed.
     pub fn recompute_layer_rules(&mut self, rules: &[LayerRule], is_at_startup: bool) -> bool {
         let new_rules = ResolvedLayerRules::compute(rules, &self.surface, is_at_startup);
@@ -161,6 +176,7 @@ impl MappedLayer {
         renderer: &mut R,
         location: Point<f64, Logical>,
         target: RenderTarget,
+        color_target: &ColorDescription,
     ) -> SplitElements<LayerSurfaceRenderElement<R>> {
         let mut rv = SplitElements::default();
 
@@ -196,6 +212,7 @@ impl MappedLayer {
                     scale,
                     alpha,
                     Kind::Unspecified,
+                    color_target,
                 ));
             }
 
@@ -206,13 +223,50 @@ impl MappedLayer {
                 scale,
                 alpha,
                 Kind::Unspecified,
+                color_target,
             );
         }
 
         let location = location.to_physical_precise_round(scale).to_logical(scale);
+        rv.normal
+            .extend(self.render_blur(renderer, location, target));
         rv.normal
             .extend(self.shadow.render(renderer, location).map(Into::into));
 
         rv
     }
+
+    fn render_blur<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        location: Point<f64, Logical>,
+        target: RenderTarget,
+    ) -> Option<LayerSurfaceRenderElement<R>> {
+        let rule = self.rules.blur;
+        if rule.off {
+            return None;
+        }
+
+        // Without a layer rule, follow the region requested by the client.
+        let region = if rule.on {
+            None
+        } else {
+            let region = with_states(self.surface.wl_surface(), surface_blur_region)?;
+            let rects = region_rects(&region).into_iter().map(|rect| rect.to_f64());
+            Some(rects.collect())
+        };
+
+        let area = Rectangle::new(location, self.block_out_buffer.size());
+        let radius = self.rules.geometry_corner_radius.unwrap_or_default();
+        let elem = self.blur.render(
+            renderer.as_gles_renderer(),
+            area,
+            radius,
+            region,
+            BlurParams::from_rule(&rule),
+            Scale::from(self.scale),
+            target,
+        )?;
+        Some(elem.into())
+    }
 }
@@ -1,5 +1,5 @@
 use niri_config::layer_rule::{LayerRule, Match};
-use niri_config::{BlockOutFrom, CornerRadius, ShadowRule};
+use niri_config::{BlockOutFrom, BlurRule, CornerRadius, ShadowRule};
 use smithay::desktop::LayerSurface;
 
 pub mod mapped;
@@ -17,6 +17,9 @@ pub struct ResolvedLayerRules {
     /// Shadow overrides.
     pub shadow: ShadowRule,
 
+    /// Background blur overrides.
+    pub blur: BlurRule,
+
     /// Corner radius to assume this layer surface has.
     pub geometry_corner_radius: Option<CornerRadius>,
 
@@ -42,6 +45,12 @@ impl ResolvedLayerRules {
                 color: None,
                 inactive_color: None,
             },
+            blur: BlurRule {
+                off: false,
+                on: false,
+                passes: None,
+                radius: None,
+            },
             geometry_corner_radius: None,
             place_within_backdrop: false,
             baba_is_float: false,
@@ -89,6 +98,7 @@ impl ResolvedLayerRules {
             }
 
             resolved.shadow.merge_with(&rule.shadow);
+            resolved.blur.merge_with(&rule.blur);
         }
 
         resolved
@@ -3,7 +3,7 @@ use std::iter::zip;
 use std::rc::Rc;
 
 use niri_config::{PresetSize, RelativeTo};
-use niri_ipc::{PositionChange, SizeChange};
+use niri_ipc::{PositionChange, SessionFloatingWindow, SessionWindow, SizeChange};
 use smithay::backend::renderer::gles::GlesRenderer;
 use smithay::utils::{Logical, Point, Rectangle, Scale, Serial, Size};
 
@@ -74,6 +74,38 @@ niri_render_elements! {
     }
 }
 
+/// Picture-in-picture state of a tile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipState<Id> {
+    /// Corner of the working area that the tile is anchored to.
+    pub corner: RelativeTo,
+    /// Width to height ratio of the window, preserved when resizing.
+    pub aspect: f64,
+    /// Column that the window was in before entering picture-in-picture, if it was tiled.
+    pub column: Option<PipColumn<Id>>,
+    /// Floating window size to restore when leaving picture-in-picture.
+    pub floating_window_size: Option<Size<i32, Logical>>,
+    /// Floating position to restore when leaving picture-in-picture.
+    pub floating_pos: Option<Point<f64, SizeFrac>>,
+    /// Whether the tile was sticky before entering picture-in-picture.
+    pub is_sticky: bool,
+}
+
+/// Place in the scrolling layout that a picture-in-picture window returns to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipColumn<Id> {
+    /// Index of the column.
+    pub idx: usize,
+    /// Window right above in the same column.
+    pub above: Option<Id>,
+    /// Window right below in the same column.
+    pub below: Option<Id>,
+    /// Width of the column.
+    pub width: ColumnWidth,
+    /// Whether the column was full-width.
+    pub is_full_width: bool,
+}
+
 /// Extra per-tile data.
 #[derive(Debug, Clone, Copy, PartialEq)]
 struct Data {
@@ -236,6 +268,10 @@ impl<W: LayoutElement> FloatingSpace<W> {
         self.working_area = working_area;
         self.scale = scale;
         self.options = options;
+
+        for idx in 0..self.tiles.len() {
+            self.anchor_pip(idx, false);
+        }
     }
 
     pub fn update_shaders(&mut self) {
@@ -283,6 +319,24 @@ impl<W: LayoutElement> FloatingSpace<W> {
         self.tiles.iter_mut()
     }
 
+    /// Returns the windows in a form suitable for saving into a session.
+    pub fn session_windows(
+        &self,
+        session_window: &impl Fn(&W) -> SessionWindow,
+    ) -> Vec<SessionFloatingWindow> {
+        self.tiles_with_offsets()
+            .map(|(tile, pos)| {
+                let pos = pos - self.working_area.loc;
+                let size = tile.window_size();
+                SessionFloatingWindow {
+                    window: session_window(tile.window()),
+                    pos: (pos.x, pos.y),
+                    size: (size.w, size.h),
+                }
+            })
+            .collect()
+    }
+
     pub fn tiles_with_offsets(&self) -> impl Iterator<Item = (&Tile<W>, Point<f64, Logical>)> + '_ {
         let offsets = self.data.iter().map(|d| d.logical_pos);
         zip(&self.tiles, offsets)
@@ -431,9 +485,18 @@ impl<W: LayoutElement> FloatingSpace<W> {
             center_preferring_top_left_in_area(self.working_area, tile.tile_size())
         });
 
+        // Picture-in-picture tiles snap to the corner closest to where they were put.
+        if tile.floating_pos.is_some() {
+            let center = pos + tile.tile_size().downscale(2.);
+            if let Some(pip) = &mut tile.pip {
+                pip.corner = nearest_corner(self.working_area, center);
+            }
+        }
+
         let data = Data::new(self.working_area, &tile, pos);
         self.data.insert(idx, data);
         self.tiles.insert(idx, tile);
+        self.anchor_pip(idx, false);
 
         self.bring_up_descendants_of(idx);
     }
@@ -732,7 +795,10 @@ impl<W: LayoutElement> FloatingSpace<W> {
 
         let win_width = ensure_min_max_size(win_width, min_size.w, max_size.w);
 
-        let win_height = win.expected_size().unwrap_or_default().h;
+        let win_height = match &tile.pip {
+            Some(pip) => (f64::from(win_width) / pip.aspect).round() as i32,
+            None => win.expected_size().unwrap_or_default().h,
+        };
         let win_height = ensure_min_max_size(win_height, min_size.h, max_size.h);
 
         let win_size = Size::from((win_width, win_height));
@@ -779,7 +845,10 @@ impl<W: LayoutElement> FloatingSpace<W> {
 
         let win_height = ensure_min_max_size(win_height, min_size.h, max_size.h);
 
-        let win_width = win.expected_size().unwrap_or_default().w;
+        let win_width = match &tile.pip {
+            Some(pip) => (f64::from(win_height) * pip.aspect).round() as i32,
+            None => win.expected_size().unwrap_or_default().w,
+        };
         let win_width = ensure_min_max_size(win_width, min_size.w, max_size.w);
 
         let win_size = Size::from((win_width, win_height));
@@ -867,7 +936,12 @@ impl<W: LayoutElement> FloatingSpace<W> {
     }
 
     fn move_to(&mut self, idx: usize, new_pos: Point<f64, Logical>, animate: bool) {
-        if animate {
+        if let Some(pip) = &mut self.tiles[idx].pip {
+            // Picture-in-picture tiles snap to the corner closest to where they were moved.
+            let center = new_pos + self.data[idx].size.downscale(2.);
+            pip.corner = nearest_corner(self.working_area, center);
+            self.anchor_pip(idx, animate);
+        } else if animate {
             self.move_and_animate(idx, new_pos);
         } else {
             self.data[idx].set_logical_pos(new_pos);
@@ -876,12 +950,22 @@ impl<W: LayoutElement> FloatingSpace<W> {
         self.interactive_resize_end(None);
     }
 
-    fn move_by(&mut self, amount: Point<f64, Logical>) {
+    fn move_by(&mut self, mut amount: Point<f64, Logical>) {
         let Some(active_id) = &self.active_window_id else {
             return;
         };
         let idx = self.idx_of(active_id).unwrap();
 
+        // Move picture-in-picture tiles all the way to the next corner.
+        if self.tiles[idx].pip.is_some() {
+            if amount.x != 0. {
+                amount.x = amount.x.signum() * self.working_area.size.w;
+            }
+            if amount.y != 0. {
+                amount.y = amount.y.signum() * self.working_area.size.h;
+            }
+        }
+
         let new_pos = self.data[idx].logical_pos + amount;
         self.move_to(idx, new_pos, true)
     }
@@ -966,8 +1050,11 @@ impl<W: LayoutElement> FloatingSpace<W> {
         tile.update_window();
         data.update(tile);
 
-        // When resizing by top/left edge, update the position accordingly.
-        if let Some(resize) = resize {
+        if tile.pip.is_some() {
+            // Picture-in-picture tiles stay in their corner as they resize.
+            self.anchor_pip(tile_idx, false);
+        } else if let Some(resize) = resize {
+            // When resizing by top/left edge, update the position accordingly.
             let mut offset = Point::from((0., 0.));
             if resize.edges.contains(ResizeEdge::LEFT) {
                 offset.x += prev_size.w - data.size.w;
@@ -981,12 +1068,14 @@ impl<W: LayoutElement> FloatingSpace<W> {
         true
     }
 
+    /// Renders either the sticky tiles, or everything else.
     pub fn render_elements<R: NiriRenderer>(
         &self,
         renderer: &mut R,
         view_rect: Rectangle<f64, Logical>,
         target: RenderTarget,
         focus_ring: bool,
+        sticky: bool,
     ) -> Vec<FloatingSpaceRenderElement<R>> {
         let mut rv = Vec::new();
 
@@ -995,13 +1084,19 @@ impl<W: LayoutElement> FloatingSpace<W> {
         // Draw the closing windows on top of the other windows.
         //
         // FIXME: I guess this should rather preserve the stacking order when the window is closed.
-        for closing in self.closing_windows.iter().rev() {
-            let elem = closing.render(renderer.as_gles_renderer(), view_rect, scale, target);
-            rv.push(elem.into());
+        if !sticky {
+            for closing in self.closing_windows.iter().rev() {
+                let elem = closing.render(renderer.as_gles_renderer(), view_rect, scale, target);
+                rv.push(elem.into());
+            }
         }
 
         let active = self.active_window_id.clone();
         for (tile, tile_pos) in self.tiles_with_render_positions() {
+            if tile.is_sticky != sticky {
+                continue;
+            }
+
             // For the active tile, draw the focus ring.
             let focus_ring = focus_ring && Some(tile.window().id()) == active.as_ref();
 
@@ -1053,6 +1148,12 @@ impl<W: LayoutElement> FloatingSpace<W> {
         let original_window_size = resize.original_window_size;
         let edges = resize.data.edges;
 
+        // Picture-in-picture tiles keep their aspect ratio, so one dimension is enough.
+        let is_pip = self
+            .tiles
+            .iter()
+            .any(|tile| tile.pip.is_some() && tile.window().id() == window);
+
         if edges.intersects(ResizeEdge::LEFT_RIGHT) {
             let mut dx = delta.x;
             if edges.contains(ResizeEdge::LEFT) {
@@ -1063,7 +1164,9 @@ impl<W: LayoutElement> FloatingSpace<W> {
             self.set_window_width(Some(window), SizeChange::SetFixed(window_width), false);
         }
 
-        if edges.intersects(ResizeEdge::TOP_BOTTOM) {
+        if edges.intersects(ResizeEdge::TOP_BOTTOM)
+            && !(is_pip && edges.intersects(ResizeEdge::LEFT_RIGHT))
+        {
             let mut dy = delta.y;
             if edges.contains(ResizeEdge::TOP) {
                 dy = -dy;
@@ -1093,10 +1196,14 @@ impl<W: LayoutElement> FloatingSpace<W> {
     pub fn refresh(&mut self, is_active: bool, is_focused: bool) {
         let active = self.active_window_id.clone();
         for tile in &mut self.tiles {
+            let is_scratchpad = tile.is_scratchpad;
+            let is_sticky = tile.is_sticky;
             let win = tile.window_mut();
 
             win.set_active_in_column(true);
             win.set_floating(true);
+            win.set_in_scratchpad(is_scratchpad);
+            win.set_sticky(is_sticky);
 
             let mut is_active = is_active && Some(win.id()) == active.as_ref();
             if self.options.deactivate_unfocused_windows {
@@ -1205,35 +1312,33 @@ impl<W: LayoutElement> FloatingSpace<W> {
         let pos = tile.floating_pos.map(|pos| self.scale_by_working_area(pos));
         pos.or_else(|| {
             tile.window().rules().default_floating_position.map(|pos| {
-                let relative_to = pos.relative_to;
-                let size = tile.tile_size();
-                let area = self.working_area;
-
-                let mut pos = Point::from((pos.x.0, pos.y.0));
-                if relative_to == RelativeTo::TopRight
-                    || relative_to == RelativeTo::BottomRight
-                    || relative_to == RelativeTo::Right
-                {
-                    pos.x = area.size.w - size.w - pos.x;
-                }
-                if relative_to == RelativeTo::BottomLeft
-                    || relative_to == RelativeTo::BottomRight
-                    || relative_to == RelativeTo::Bottom
-                {
-                    pos.y = area.size.h - size.h - pos.y;
-                }
-                if relative_to == RelativeTo::Top || relative_to == RelativeTo::Bottom {
-                    pos.x += area.size.w / 2.0 - size.w / 2.0
-                }
-                if relative_to == RelativeTo::Left || relative_to == RelativeTo::Right {
-                    pos.y += area.size.h / 2.0 - size.h / 2.0
-                }
-
-                pos + self.working_area.loc
+                let offset = Point::from((pos.x.0, pos.y.0));
+                anchored_pos(self.working_area, tile.tile_size(), offset, pos.relative_to)
             })
         })
     }
 
+    /// Moves a picture-in-picture tile into its corner.
+    fn anchor_pip(&mut self, idx: usize, animate: bool) {
+        let Some(pip) = &self.tiles[idx].pip else {
+            return;
+        };
+
+        let margin = self.options.picture_in_picture.margin.0;
+        let offset = match pip.corner {
+            RelativeTo::Top | RelativeTo::Bottom => Point::from((0., margin)),
+            RelativeTo::Left | RelativeTo::Right => Point::from((margin, 0.)),
+            _ => Point::from((margin, margin)),
+        };
+        let pos = anchored_pos(self.working_area, self.data[idx].size, offset, pip.corner);
+
+        if animate {
+            self.move_and_animate(idx, pos);
+        } else {
+            self.data[idx].set_logical_pos(pos);
+        }
+    }
+
     #[cfg(test)]
     pub fn view_size(&self) -> Size<f64, Logical> {
         self.view_size
@@ -1330,6 +1435,48 @@ fn compute_toplevel_bounds(
     .to_i32_floor()
 }
 
+/// Returns the position of a rectangle of `size` offset by `offset` from the `relative_to` anchor
+/// of `area`.
+fn anchored_pos(
+    area: Rectangle<f64, Logical>,
+    size: Size<f64, Logical>,
+    offset: Point<f64, Logical>,
+    relative_to: RelativeTo,
+) -> Point<f64, Logical> {
+    let mut pos = offset;
+    if relative_to == RelativeTo::TopRight
+        || relative_to == RelativeTo::BottomRight
+        || relative_to == RelativeTo::Right
+    {
+        pos.x = area.size.w - size.w - pos.x;
+    }
+    if relative_to == RelativeTo::BottomLeft
+        || relative_to == RelativeTo::BottomRight
+        || relative_to == RelativeTo::Bottom
+    {
+        pos.y = area.size.h - size.h - pos.y;
+    }
+    if relative_to == RelativeTo::Top || relative_to == RelativeTo::Bottom {
+        pos.x += area.size.w / 2.0 - size.w / 2.0
+    }
+    if relative_to == RelativeTo::Left || relative_to == RelativeTo::Right {
+        pos.y += area.size.h / 2.0 - size.h / 2.0
+    }
+
+    pos + area.loc
+}
+
+/// Returns the corner of `area` closest to `point`.
+fn nearest_corner(area: Rectangle<f64, Logical>, point: Point<f64, Logical>) -> RelativeTo {
+    let center = area.loc + area.size.downscale(2.).to_point();
+    match (point.x < center.x, point.y < center.y) {
+        (true, true) => RelativeTo::TopLeft,
+        (false, true) => RelativeTo::TopRight,
+        (true, false) => RelativeTo::BottomLeft,
+        (false, false) => RelativeTo::BottomRight,
+    }
+}
+
 fn resolve_preset_size(preset: PresetSize, view_size: f64) -> ResolvedSize {
     match preset {
         PresetSize::Proportion(proportion) => ResolvedSize::Tile(view_size * proportion),
@@ -42,7 +42,7 @@ use niri_config::{
     CenterFocusedColumn, Config, CornerRadius, FloatOrInt, PresetSize, Struts,
     Workspace as WorkspaceConfig, WorkspaceReference,
 };
-use niri_ipc::{ColumnDisplay, PositionChange, SizeChange};
+use niri_ipc::{ColumnDisplay, PositionChange, Session, SessionWindow, SizeChange};
 use scrolling::{Column, ColumnWidth};
 use smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement;
 use smithay::backend::renderer::element::utils::RescaleRenderElement;
@@ -60,6 +60,10 @@ use crate::animation::{Animation, Clock};
 use crate::input::swipe_tracker::SwipeTracker;
 use crate::layout::scrolling::ScrollDirection;
 use crate::niri_render_elements;
+use crate::render_helpers::blur::BlurRenderElement;
+use crate::render_helpers::border::BorderRenderElement;
+use crate::render_helpers::color_adjust::ColorAdjust;
+use crate::render_helpers::color_transform::ColorTransformRenderElement;
 use crate::render_helpers::offscreen::OffscreenData;
 use crate::render_helpers::renderer::NiriRenderer;
 use crate::render_helpers::snapshot::RenderSnapshot;
@@ -112,7 +116,9 @@ pub struct SizeFrac;
 niri_render_elements! {
     LayoutElementRenderElement<R> => {
         Wayland = WaylandSurfaceRenderElement<R>,
+        ColorTransform = ColorTransformRenderElement<R>,
         SolidColor = SolidColorRenderElement,
+        Border = BorderRenderElement,
     }
 }
 
@@ -179,6 +185,18 @@ pub trait LayoutElement {
         self.render(renderer, location, scale, alpha, target).popups
     }
 
+    /// Renders the background blur behind the element, if it should have one.
+    ///
+    /// The blur covers `area` with corners rounded to `radius`.
+    fn render_blur<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        area: Rectangle<f64, Logical>,
+        radius: CornerRadius,
+        scale: Scale<f64>,
+        target: RenderTarget,
+    ) -> Option<BlurRenderElement>;
+
     /// Requests the element to change its size.
     ///
     /// The size request is stored and will be continuously sent to the element on any further
@@ -207,6 +225,10 @@ pub trait LayoutElement {
     fn set_activated(&mut self, active: bool);
     fn set_active_in_column(&mut self, active: bool);
     fn set_floating(&mut self, floating: bool);
+    fn set_in_scratchpad(&mut self, in_scratchpad: bool);
+    fn set_sticky(&mut self, sticky: bool);
+    /// Sets the named workspace and the output that the window is on, for window rules.
+    fn set_workspace(&mut self, name: Option<&str>, output: Option<&Output>);
     fn set_bounds(&self, bounds: Size<i32, Logical>);
     fn is_ignoring_opacity_window_rule(&self) -> bool;
 
@@ -297,6 +319,10 @@ pub struct Layout<W: LayoutElement> {
     /// The workspace id does not necessarily point to a valid workspace. If it doesn't, then it is
     /// simply ignored.
     last_active_workspace_id: HashMap<String, WorkspaceId>,
+    /// Hidden scratchpad windows while there are no outputs.
+    ///
+    /// They move to the scratchpad of the first output that connects.
+    scratchpad_no_outputs: Vec<Tile<W>>,
     /// Ongoing interactive move.
     interactive_move: Option<InteractiveMoveState<W>>,
     /// Ongoing drag-and-drop operation.
@@ -312,6 +338,10 @@ pub struct Layout<W: LayoutElement> {
     overview_open: bool,
     /// The overview zoom progress.
     overview_progress: Option<OverviewProgress>,
+    /// Whether picture-in-picture windows currently accept pointer input.
+    ///
+    /// Picture-in-picture windows are otherwise click-through.
+    pip_interactive: bool,
     /// Configurable properties of the layout.
     options: Rc<Options>,
 }
@@ -345,6 +375,7 @@ pub struct Options {
     pub shadow: niri_config::Shadow,
     pub tab_indicator: niri_config::TabIndicator,
     pub insert_hint: niri_config::InsertHint,
+    pub picture_in_picture: niri_config::PictureInPicture,
     pub center_focused_column: CenterFocusedColumn,
     pub always_center_single_column: bool,
     pub empty_workspace_above_first: bool,
@@ -358,6 +389,10 @@ pub struct Options {
     pub animations: niri_config::Animations,
     pub gestures: niri_config::Gestures,
     pub overview: niri_config::Overview,
+    /// Color adjustment of inactive windows.
+    pub inactive_adjust: ColorAdjust,
+    /// Whether the inactive color adjustment was toggled off with `toggle-inactive-dim`.
+    pub disable_inactive_adjust: bool,
     // Debug flags.
     pub disable_resize_throttling: bool,
     pub disable_transactions: bool,
@@ -374,6 +409,7 @@ impl Default for Options {
             shadow: Default::default(),
             tab_indicator: Default::default(),
             insert_hint: Default::default(),
+            picture_in_picture: Default::default(),
             center_focused_column: Default::default(),
             always_center_single_column: false,
             empty_workspace_above_first: false,
@@ -387,6 +423,8 @@ impl Default for Options {
             animations: Default::default(),
             gestures: Default::default(),
             overview: Default::default(),
+            inactive_adjust: ColorAdjust::IDENTITY,
+            disable_inactive_adjust: false,
             disable_resize_throttling: false,
             disable_transactions: false,
             preset_window_heights: vec![
@@ -649,6 +687,7 @@ impl Options {
             shadow: layout.shadow,
             tab_indicator: layout.tab_indicator,
             insert_hint: layout.insert_hint,
+            picture_in_picture: layout.picture_in_picture,
             center_focused_column: layout.center_focused_column,
             always_center_single_column: layout.always_center_single_column,
             empty_workspace_above_first: layout.empty_workspace_above_first,
@@ -656,8 +695,14 @@ impl Options {
             preset_column_widths,
             default_column_width,
             animations: config.animations.clone(),
-            gestures: config.gestures,
+            gestures: config.gestures.clone(),
             overview: config.overview,
+            inactive_adjust: ColorAdjust {
+                dim: layout.inactive_dim.0 as f32,
+                saturation: layout.inactive_saturation.0 as f32,
+                brightness: layout.inactive_brightness.0 as f32,
+            },
+            disable_inactive_adjust: false,
             disable_resize_throttling: config.debug.disable_resize_throttling,
             disable_transactions: config.debug.disable_transactions,
             deactivate_unfocused_windows: config.debug.deactivate_unfocused_windows,
@@ -699,12 +744,14 @@ impl<W: LayoutElement> Layout<W> {
             monitor_set: MonitorSet::NoOutputs { workspaces: vec![] },
             is_active: true,
             last_active_workspace_id: HashMap::new(),
+            scratchpad_no_outputs: Vec::new(),
             interactive_move: None,
             dnd: None,
             clock,
             update_render_elements_time: Duration::ZERO,
             overview_open: false,
             overview_progress: None,
+            pip_interactive: false,
             options: Rc::new(options),
         }
     }
@@ -724,12 +771,14 @@ impl<W: LayoutElement> Layout<W> {
             monitor_set: MonitorSet::NoOutputs { workspaces },
             is_active: true,
             last_active_workspace_id: HashMap::new(),
+            scratchpad_no_outputs: Vec::new(),
             interactive_move: None,
             dnd: None,
             clock,
             update_render_elements_time: Duration::ZERO,
             overview_open: false,
             overview_progress: None,
+            pip_interactive: false,
             options: opts,
         }
     }
@@ -873,6 +922,7 @@ impl<W: LayoutElement> Layout<W> {
                 let mut monitor =
                     Monitor::new(output, workspaces, self.clock.clone(), self.options.clone());
                 monitor.active_workspace_idx = active_workspace_idx;
+                monitor.scratchpad = mem::take(&mut self.scratchpad_no_outputs);
                 monitor.overview_open = self.overview_open;
                 monitor.set_overview_progress(self.overview_progress.as_ref());
 
@@ -904,6 +954,7 @@ impl<W: LayoutElement> Layout<W> {
                 );
 
                 let mut workspaces = monitor.workspaces;
+                let scratchpad = monitor.scratchpad;
 
                 for ws in &mut workspaces {
                     ws.set_output(None);
@@ -914,6 +965,7 @@ impl<W: LayoutElement> Layout<W> {
 
                 if monitors.is_empty() {
                     // Removed the last monitor.
+                    self.scratchpad_no_outputs = scratchpad;
                     MonitorSet::NoOutputs { workspaces }
                 } else {
                     if primary_idx >= idx {
@@ -932,6 +984,7 @@ impl<W: LayoutElement> Layout<W> {
                     for ws in &mut workspaces {
                         ws.set_output(Some(primary.output.clone()));
                     }
+                    primary.scratchpad.extend(scratchpad);
 
                     let mut stopped_primary_ws_switch = false;
                     if !workspaces.is_empty() && primary.workspace_switch.is_some() {
@@ -1272,6 +1325,36 @@ impl<W: LayoutElement> Layout<W> {
             }
         }
 
+        self.remove_from_scratchpad(window)
+    }
+
+    fn remove_from_scratchpad(&mut self, window: &W::Id) -> Option<RemovedTile<W>> {
+        let scratchpads = match &mut self.monitor_set {
+            MonitorSet::Normal { monitors, .. } => {
+                let mut scratchpads: Vec<_> =
+                    monitors.iter_mut().map(|mon| &mut mon.scratchpad).collect();
+                scratchpads.push(&mut self.scratchpad_no_outputs);
+                scratchpads
+            }
+            MonitorSet::NoOutputs { .. } => vec![&mut self.scratchpad_no_outputs],
+        };
+
+        for scratchpad in scratchpads {
+            if let Some(idx) = scratchpad
+                .iter()
+                .position(|tile| tile.window().id() == window)
+            {
+                let tile = scratchpad.remove(idx);
+                let width = ColumnWidth::Fixed(tile.tile_expected_or_current_size().w);
+                return Some(RemovedTile {
+                    tile,
+                    width,
+                    is_full_width: false,
+                    is_floating: true,
+                });
+            }
+        }
+
         None
     }
 
@@ -1318,6 +1401,16 @@ impl<W: LayoutElement> Layout<W> {
                 }
             }
         }
+
+        if let Some(tile) = self
+            .scratchpad_tiles_mut()
+            .find(|tile| tile.window().id() == window)
+        {
+            if let Some(serial) = serial {
+                tile.window_mut().on_commit(serial);
+            }
+            tile.update_window();
+        }
     }
 
     pub fn find_workspace_by_id(&self, id: WorkspaceId) -> Option<(usize, &Workspace<W>)> {
@@ -1462,7 +1555,10 @@ impl<W: LayoutElement> Layout<W> {
             }
         }
 
-        None
+        self.scratchpad_tiles()
+            .map(Tile::window)
+            .find(|win| win.is_wl_surface(wl_surface))
+            .map(|win| (win, None))
     }
 
     pub fn find_window_and_output_mut(
@@ -1494,7 +1590,10 @@ impl<W: LayoutElement> Layout<W> {
             }
         }
 
-        None
+        self.scratchpad_tiles_mut()
+            .map(Tile::window_mut)
+            .find(|win| win.is_wl_surface(wl_surface))
+            .map(|win| (win, None))
     }
 
     /// Computes the window-geometry-relative target rect for popup unconstraining.
@@ -1516,6 +1615,14 @@ impl<W: LayoutElement> Layout<W> {
             }
         }
 
+        if let Some(tile) = self
+            .scratchpad_tiles()
+            .find(|tile| tile.window().id() == window)
+        {
+            // Hidden scratchpad windows aren't shown anywhere, so just keep popups within them.
+            return Rectangle::from_size(tile.window_size());
+        }
+
         self.workspaces()
             .find_map(|(_, _, ws)| ws.popup_target_rect(window))
             .unwrap()
@@ -1623,6 +1730,16 @@ impl<W: LayoutElement> Layout<W> {
                 }
             }
         }
+
+        // Activating a hidden scratchpad window summons it.
+        for (monitor_idx, mon) in monitors.iter_mut().enumerate() {
+            let scratchpad = mon.scratchpad.iter();
+            if let Some(idx) = scratchpad.position(|tile| tile.window().id() == window) {
+                mon.show_from_scratchpad(idx);
+                *active_monitor_idx = monitor_idx;
+                return;
+            }
+        }
     }
 
     pub fn activate_window_without_raising(&mut self, window: &W::Id) {
@@ -1766,6 +1883,10 @@ impl<W: LayoutElement> Layout<W> {
                 }
             }
         }
+
+        for tile in self.scratchpad_tiles() {
+            f(tile.window(), None, None);
+        }
     }
 
     pub fn with_windows_mut(&mut self, mut f: impl FnMut(&mut W, Option<&Output>)) {
@@ -1791,6 +1912,10 @@ impl<W: LayoutElement> Layout<W> {
                 }
             }
         }
+
+        for tile in self.scratchpad_tiles_mut() {
+            f(tile.window_mut(), None);
+        }
     }
 
     fn active_monitor(&mut self) -> Option<&mut Monitor<W>> {
@@ -2404,7 +2529,7 @@ impl<W: LayoutElement> Layout<W> {
         };
 
         let mon = monitors.iter().find(|mon| &mon.output == output)?;
-        mon.window_under(pos_within_output)
+        mon.window_under(pos_within_output, self.pip_interactive)
     }
 
     pub fn resize_edges_under(
@@ -2526,6 +2651,29 @@ impl<W: LayoutElement> Layout<W> {
             }
         }
 
+        if matches!(self.monitor_set, MonitorSet::Normal { .. }) {
+            assert!(
+                self.scratchpad_no_outputs.is_empty(),
+                "with outputs, scratchpad windows must belong to monitors"
+            );
+        }
+
+        for tile in self.scratchpad_tiles() {
+            assert!(tile.is_scratchpad, "hidden scratchpad tiles must be marked");
+            assert!(!tile.window().is_pending_fullscreen());
+            assert!(
+                !self.has_window(tile.window().id()),
+                "hidden scratchpad windows must not also be in the layout"
+            );
+            assert_eq!(
+                self.scratchpad_tiles()
+                    .filter(|other| other.window().id() == tile.window().id())
+                    .count(),
+                1,
+                "scratchpad windows must be unique"
+            );
+        }
+
         let mut seen_workspace_id = HashSet::new();
         let mut seen_workspace_name = Vec::<String>::new();
 
@@ -2796,6 +2944,7 @@ impl<W: LayoutElement> Layout<W> {
         }
 
         let is_overview_open = self.overview_open;
+        let pip_interactive = self.pip_interactive;
 
         // Scroll the view if needed.
         if let Some((output, pos_within_output, is_scrolling)) = dnd_scroll {
@@ -2828,7 +2977,7 @@ impl<W: LayoutElement> Layout<W> {
                     }
                 } else if is_dnd {
                     let target = mon
-                        .window_under(pos_within_output)
+                        .window_under(pos_within_output, pip_interactive)
                         .map(|(win, _)| DndHoldTarget::Window(win.id().clone()))
                         .or_else(|| {
                             mon.workspace_under_narrow(pos_within_output)
@@ -3143,7 +3292,10 @@ impl<W: LayoutElement> Layout<W> {
     }
 
     pub fn update_config(&mut self, config: &Config) {
-        self.update_options(Options::from_config(config));
+        let mut options = Options::from_config(config);
+        // This is toggled at runtime, so keep it across config reloads.
+        options.disable_inactive_adjust = self.options.disable_inactive_adjust;
+        self.update_options(options);
     }
 
     fn update_options(&mut self, options: Options) {
@@ -3402,6 +3554,100 @@ impl<W: LayoutElement> Layout<W> {
         workspace.set_window_floating(window, floating);
     }
 
+    pub fn toggle_window_sticky(&mut self, window: Option<&W::Id>) {
+        if let Some(InteractiveMoveState::Moving(move_)) = &mut self.interactive_move {
+            if window.is_none() || window == Some(move_.tile.window().id()) {
+                // Picture-in-picture windows are always sticky.
+                if move_.tile.pip.is_some() {
+                    return;
+                }
+
+                move_.tile.is_sticky = !move_.tile.is_sticky;
+                if move_.tile.is_sticky && !move_.is_floating {
+                    self.toggle_window_floating(window);
+                }
+                return;
+            }
+        }
+
+        let workspace = if let Some(window) = window {
+            Some(
+                self.workspaces_mut()
+                    .find(|ws| ws.has_window(window))
+                    .unwrap(),
+            )
+        } else {
+            self.active_workspace_mut()
+        };
+
+        let Some(workspace) = workspace else {
+            return;
+        };
+        workspace.toggle_window_sticky(window);
+    }
+
+    pub fn set_window_sticky(&mut self, window: Option<&W::Id>, sticky: bool) {
+        if let Some(InteractiveMoveState::Moving(move_)) = &mut self.interactive_move {
+            if window.is_none() || window == Some(move_.tile.window().id()) {
+                if move_.tile.is_sticky != sticky {
+                    self.toggle_window_sticky(window);
+                }
+                return;
+            }
+        }
+
+        let workspace = if let Some(window) = window {
+            Some(
+                self.workspaces_mut()
+                    .find(|ws| ws.has_window(window))
+                    .unwrap(),
+            )
+        } else {
+            self.active_workspace_mut()
+        };
+
+        let Some(workspace) = workspace else {
+            return;
+        };
+        workspace.set_window_sticky(window, sticky);
+    }
+
+    pub fn toggle_window_pip(&mut self, window: Option<&W::Id>) {
+        if let Some(InteractiveMoveState::Moving(move_)) = &self.interactive_move {
+            if window.is_none() || window == Some(move_.tile.window().id()) {
+                // The tile has no place in the layout to anchor to or return to mid-move.
+                return;
+            }
+        }
+
+        let workspace = if let Some(window) = window {
+            Some(
+                self.workspaces_mut()
+                    .find(|ws| ws.has_window(window))
+                    .unwrap(),
+            )
+        } else {
+            self.active_workspace_mut()
+        };
+
+        let Some(workspace) = workspace else {
+            return;
+        };
+        workspace.toggle_window_pip(window);
+    }
+
+    pub fn is_window_pip(&self, window: &W::Id) -> bool {
+        self.workspaces().any(|(_, _, ws)| ws.is_window_pip(window))
+    }
+
+    pub fn pip_interactive(&self) -> bool {
+        self.pip_interactive
+    }
+
+    pub fn set_pip_interactive(&mut self, interactive: bool) {
+        self.pip_interactive = interactive;
+    }
+
     pub fn focus_floating(&mut self) {
         let Some(workspace) = self.active_workspace_mut() else {
             return;
@@ -3437,7 +3683,8 @@ impl<W: LayoutElement> Layout<W> {
         }
 
         let workspace = if let Some(id) = id {
-            Some(self.workspaces_mut().find(|ws| ws.has_window(id)).unwrap())
+            // Hidden scratchpad windows can still commit buffer offsets.
+            self.workspaces_mut().find(|ws| ws.has_window(id))
         } else {
             self.active_workspace_mut()
         };
@@ -3448,6 +3695,112 @@ impl<W: LayoutElement> Layout<W> {
         workspace.move_floating_window(id, x, y, animate);
     }
 
+    /// Hides a window in the scratchpad of its monitor.
+    pub fn move_to_scratchpad(&mut self, window: Option<&W::Id>) {
+        if let Some(InteractiveMoveState::Moving(move_)) = &self.interactive_move {
+            if window.is_none() || window == Some(move_.tile.window().id()) {
+                return;
+            }
+        }
+
+        let MonitorSet::Normal {
+            monitors,
+            active_monitor_idx,
+            ..
+        } = &self.monitor_set
+        else {
+            return;
+        };
+
+        let (mon_idx, id) = if let Some(window) = window {
+            let Some(mon_idx) = monitors.iter().position(|mon| mon.has_window(window)) else {
+                return;
+            };
+            (mon_idx, window.clone())
+        } else {
+            let Some(win) = monitors[*active_monitor_idx].active_window() else {
+                return;
+            };
+            (*active_monitor_idx, win.id().clone())
+        };
+
+        let Some(removed) = self.remove_window(&id, Transaction::new()) else {
+            return;
+        };
+
+        let MonitorSet::Normal { monitors, .. } = &mut self.monitor_set else {
+            unreachable!()
+        };
+        monitors[mon_idx].add_to_scratchpad(removed.tile);
+    }
+
+    /// Summons a scratchpad window matching `matches` on the active workspace.
+    ///
+    /// A matching scratchpad window that is already shown on a different workspace of the active
+    /// monitor is moved over. Otherwise, the most recently hidden matching window is summoned.
+    ///
+    /// Returns whether a window was summoned.
+    pub fn show_scratchpad(&mut self, matches: impl Fn(&W) -> bool) -> bool {
+        let Some(mon) = self.active_monitor_ref() else {
+            return false;
+        };
+
+        let shown = mon
+            .workspaces
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| *idx != mon.active_workspace_idx)
+            .flat_map(|(_, ws)| ws.tiles())
+            .find(|tile| tile.is_scratchpad && matches(tile.window()))
+            .map(|tile| tile.window().id().clone());
+        if let Some(id) = shown {
+            self.move_to_scratchpad(Some(&id));
+        }
+
+        let Some(mon) = self.active_monitor() else {
+            return false;
+        };
+        let Some(idx) = mon
+            .scratchpad
+            .iter()
+            .rposition(|tile| matches(tile.window()))
+        else {
+            return false;
+        };
+
+        mon.show_from_scratchpad(idx);
+        true
+    }
+
+    /// Hides the shown scratchpad windows matching `matches` on the active workspace.
+    ///
+    /// Returns whether any windows were hidden.
+    pub fn hide_scratchpad(&mut self, matches: impl Fn(&W) -> bool) -> bool {
+        let Some(ws) = self.active_workspace() else {
+            return false;
+        };
+
+        let ids: Vec<_> = ws
+            .tiles()
+            .filter(|tile| tile.is_scratchpad && matches(tile.window()))
+            .map(|tile| tile.window().id().clone())
+            .collect();
+
+        for id in &ids {
+            self.move_to_scratchpad(Some(id));
+        }
+
+        !ids.is_empty()
+    }
+
+    /// Hides the matching scratchpad windows on the active workspace, or summons one if there
+    /// are none.
+    pub fn toggle_scratchpad(&mut self, matches: impl Fn(&W) -> bool) {
+        if !self.hide_scratchpad(&matches) {
+            self.show_scratchpad(matches);
+        }
+    }
+
     pub fn focus_output(&mut self, output: &Output) {
         if let MonitorSet::Normal {
             monitors,
@@ -3489,16 +3842,16 @@ impl<W: LayoutElement> Layout<W> {
                 .unwrap();
 
             let (mon_idx, ws_idx) = if let Some(window) = window {
-                monitors
-                    .iter()
-                    .enumerate()
-                    .find_map(|(mon_idx, mon)| {
-                        mon.workspaces
-                            .iter()
-                            .position(|ws| ws.has_window(window))
-                            .map(|ws_idx| (mon_idx, ws_idx))
-                    })
-                    .unwrap()
+                // The window can be hidden in a scratchpad, in which case there's nothing to move.
+                let Some(pos) = monitors.iter().enumerate().find_map(|(mon_idx, mon)| {
+                    mon.workspaces
+                        .iter()
+                        .position(|ws| ws.has_window(window))
+                        .map(|ws_idx| (mon_idx, ws_idx))
+                }) else {
+                    return;
+                };
+                pos
             } else {
                 let mon_idx = *active_monitor_idx;
                 let mon = &monitors[mon_idx];
@@ -3566,6 +3919,30 @@ impl<W: LayoutElement> Layout<W> {
         }
     }
 
+    /// Moves the window to the workspace of another window, if they are on different workspaces.
+    ///
+    /// Does nothing if either window isn't on a workspace, for example while it's being
+    /// interactively moved.
+    pub fn move_to_workspace_of(&mut self, window: &W::Id, target: &W::Id) {
+        let find = |id: &W::Id| {
+            self.workspaces()
+                .find(|(_, _, ws)| ws.has_window(id))
+                .map(|(mon, idx, ws)| (mon.map(|mon| mon.output.clone()), idx, ws.id()))
+        };
+
+        let Some((_, _, ws_id)) = find(window) else {
+            return;
+        };
+        let Some((Some(output), ws_idx, target_ws_id)) = find(target) else {
+            return;
+        };
+        if ws_id == target_ws_id {
+            return;
+        }
+
+        self.move_to_output(Some(window), &output, Some(ws_idx), ActivateWindow::No);
+    }
+
     pub fn move_column_to_output(
         &mut self,
         output: &Output,
@@ -4895,6 +5272,12 @@ impl<W: LayoutElement> Layout<W> {
         true
     }
 
+    pub fn toggle_inactive_dim(&mut self) {
+        let mut options = Options::clone(&self.options);
+        options.disable_inactive_adjust = !options.disable_inactive_adjust;
+        self.update_options(options);
+    }
+
     pub fn toggle_overview_to_workspace(&mut self, ws_idx: usize) {
         let config = self.options.animations.overview_open_close.0;
         if let Some(mon) = self.active_monitor() {
@@ -5126,6 +5509,10 @@ impl<W: LayoutElement> Layout<W> {
                         mon.dnd_scroll_gesture_end();
                     }
 
+                    // Sticky tiles can end up on an inactive workspace, e.g. when their workspace
+                    // moves to a different monitor.
+                    mon.gather_sticky_tiles();
+
                     for (ws_idx, ws) in mon.workspaces.iter_mut().enumerate() {
                         let is_focused = is_active && ws_idx == mon.active_workspace_idx;
                         ws.refresh(is_active, is_focused);
@@ -5153,6 +5540,31 @@ impl<W: LayoutElement> Layout<W> {
                 }
             }
         }
+
+        for tile in self.scratchpad_tiles_mut() {
+            let win = tile.window_mut();
+            win.set_in_scratchpad(true);
+            win.set_activated(false);
+            win.send_pending_configure();
+            win.refresh();
+        }
+    }
+
+    /// Returns the arrangement of windows in the layout for saving into a session.
+    ///
+    /// Empty unnamed workspaces are skipped, and so are windows hidden in the scratchpad.
+    pub fn session(&self, session_window: impl Fn(&W) -> SessionWindow) -> Session {
+        let workspaces = self
+            .workspaces()
+            .filter(|(_, _, ws)| ws.name().is_some() || ws.has_windows())
+            .map(|(mon, ws_idx, ws)| {
+                let idx = u8::try_from(ws_idx + 1).unwrap_or(u8::MAX);
+                let output = mon.map(|mon| mon.output_name().clone());
+                ws.session(idx, output, &session_window)
+            })
+            .collect();
+
+        Session { workspaces }
     }
 
     pub fn workspaces(
@@ -5234,6 +5646,37 @@ impl<W: LayoutElement> Layout<W> {
         self.windows().any(|(_, win)| win.id() == window)
     }
 
+    /// Returns the hidden scratchpad tiles of all monitors.
+    fn scratchpad_tiles(&self) -> impl Iterator<Item = &Tile<W>> + '_ {
+        let monitors = match &self.monitor_set {
+            MonitorSet::Normal { monitors, .. } => &monitors[..],
+            MonitorSet::NoOutputs { .. } => &[],
+        };
+
+        monitors
+            .iter()
+            .flat_map(|mon| &mon.scratchpad)
+            .chain(&self.scratchpad_no_outputs)
+    }
+
+    fn scratchpad_tiles_mut(&mut self) -> impl Iterator<Item = &mut Tile<W>> + '_ {
+        let monitors = match &mut self.monitor_set {
+            MonitorSet::Normal { monitors, .. } => &mut monitors[..],
+            MonitorSet::NoOutputs { .. } => &mut [],
+        };
+
+        monitors
+            .iter_mut()
+            .flat_map(|mon| &mut mon.scratchpad)
+            .chain(&mut self.scratchpad_no_outputs)
+    }
+
+    /// Whether the window is hidden in a scratchpad.
+    pub fn is_in_scratchpad(&self, window: &W::Id) -> bool {
+        self.scratchpad_tiles()
+            .any(|tile| tile.window().id() == window)
+    }
+
     pub fn is_overview_open(&self) -> bool {
         self.overview_open
     }
@@ -64,6 +64,11 @@ pub struct Monitor<W: LayoutElement> {
     pub(super) workspaces: Vec<Workspace<W>>,
     /// Index of the currently active workspace.
     pub(super) active_workspace_idx: usize,
+    /// Windows hidden in the scratchpad, most recently hidden last.
+    ///
+    /// These are not part of any workspace. Summoning a scratchpad window moves it to the floating
+    /// layout of the active workspace.
+    pub(super) scratchpad: Vec<Tile<W>>,
     /// ID of the previously active workspace.
     pub(super) previous_workspace_id: Option<WorkspaceId>,
     /// In-progress switch between workspaces.
@@ -174,6 +179,7 @@ niri_render_elements! {
         Workspace = CropRenderElement<WorkspaceRenderElement<R>>,
         InsertHint = CropRenderElement<InsertHintRenderElement>,
         UncroppedInsertHint = InsertHintRenderElement,
+        Sticky = WorkspaceRenderElement<R>,
         Shadow = ShadowRenderElement,
     }
 }
@@ -291,6 +297,7 @@ impl<W: LayoutElement> Monitor<W> {
             working_area,
             workspaces,
             active_workspace_idx: 0,
+            scratchpad: Vec::new(),
             previous_workspace_id: None,
             insert_hint: None,
             insert_hint_element: InsertHintElement::new(options.insert_hint),
@@ -327,6 +334,10 @@ impl<W: LayoutElement> Monitor<W> {
         })
     }
 
+    pub fn workspace_at(&self, idx: usize) -> Option<&Workspace<W>> {
+        self.workspaces.get(idx)
+    }
+
     pub fn find_named_workspace_index(&self, workspace_name: &str) -> Option<usize> {
         self.workspaces.iter().position(|ws| {
             ws.name
@@ -393,7 +404,7 @@ impl<W: LayoutElement> Monitor<W> {
         let prev_active_idx = self.active_workspace_idx;
         self.active_workspace_idx = idx;
 
-        let config = config.unwrap_or(self.options.animations.workspace_switch.0);
+        let config = config.unwrap_or(self.options.animations.workspace_switch.anim);
 
         match &mut self.workspace_switch {
             // During a DnD scroll, we want to visually animate even if idx matches the active idx.
@@ -424,6 +435,9 @@ impl<W: LayoutElement> Monitor<W> {
                 )));
             }
         }
+
+        // Carry the sticky tiles over to the new active workspace.
+        self.gather_sticky_tiles();
     }
 
     pub fn add_window(
@@ -532,6 +546,74 @@ impl<W: LayoutElement> Monitor<W> {
         }
     }
 
+    /// Hides a tile in the scratchpad.
+    ///
+    /// The tile must already be removed from its workspace.
+    pub fn add_to_scratchpad(&mut self, mut tile: Tile<W>) {
+        // Scratchpad windows are always summoned as floating, so leave fullscreen right away.
+        let floating_size = tile.floating_window_size;
+        let win = tile.window_mut();
+        if win.is_pending_windowed_fullscreen() {
+            win.request_windowed_fullscreen(false);
+        }
+        if win.is_pending_fullscreen() {
+            win.request_size_once(floating_size.unwrap_or_default(), false);
+        }
+
+        win.set_activated(false);
+        win.set_in_scratchpad(true);
+        tile.is_scratchpad = true;
+        tile.unfullscreen_to_floating = false;
+
+        self.scratchpad.push(tile);
+    }
+
+    /// Summons a hidden scratchpad tile into the floating layout of the active workspace.
+    pub fn show_from_scratchpad(&mut self, idx: usize) {
+        let tile = self.scratchpad.remove(idx);
+        let width = ColumnWidth::Fixed(tile.tile_expected_or_current_size().w);
+
+        self.add_tile(
+            tile,
+            MonitorAddWindowTarget::Auto,
+            ActivateWindow::Yes,
+            true,
+            width,
+            false,
+            true,
+        );
+    }
+
+    pub fn scratchpad_windows(&self) -> impl Iterator<Item = &W> {
+        self.scratchpad.iter().map(Tile::window)
+    }
+
+    /// Moves sticky tiles from other workspaces to the floating layout of the active workspace.
+    pub fn gather_sticky_tiles(&mut self) {
+        let active_idx = self.active_workspace_idx;
+        let mut tiles = Vec::new();
+        for (idx, ws) in self.workspaces.iter_mut().enumerate() {
+            if idx != active_idx {
+                tiles.extend(ws.remove_sticky_tiles());
+            }
+        }
+
+        for tile in tiles {
+            // The tile's floating position was stored upon removal, so it'll show up in the same
+            // spot.
+            let width = ColumnWidth::Fixed(tile.tile_expected_or_current_size().w);
+            self.add_tile(
+                tile,
+                MonitorAddWindowTarget::Auto,
+                ActivateWindow::No,
+                false,
+                width,
+                false,
+                true,
+            );
+        }
+    }
+
     pub fn add_tile_to_column(
         &mut self,
         workspace_idx: usize,
@@ -1300,6 +1382,20 @@ impl<W: LayoutElement> Monitor<W> {
         })
     }
 
+    /// Returns the geometry of the sticky layer.
+    ///
+    /// It matches the active workspace when no switch is in progress, and stays in place while
+    /// the workspaces move under it.
+    pub fn sticky_render_geo(&self) -> Rectangle<f64, Logical> {
+        let scale = self.scale.fractional_scale();
+        let zoom = self.overview_zoom();
+
+        let ws_size = self.workspace_size(zoom);
+        let loc = (self.view_size.to_point() - ws_size.to_point()).downscale(2.);
+        let loc = loc.to_physical_precise_round(scale).to_logical(scale);
+        Rectangle::new(loc, ws_size)
+    }
+
     pub fn workspaces_with_render_geo(
         &self,
     ) -> impl Iterator<Item = (&Workspace<W>, Rectangle<f64, Logical>)> {
@@ -1357,18 +1453,47 @@ impl<W: LayoutElement> Monitor<W> {
             .find_map(|(ws, geo)| geo.contains(pos_within_output).then_some(ws))
     }
 
-    pub fn window_under(&self, pos_within_output: Point<f64, Logical>) -> Option<(&W, HitType)> {
+    pub fn window_under(
+        &self,
+        pos_within_output: Point<f64, Logical>,
+        pip_interactive: bool,
+    ) -> Option<(&W, HitType)> {
+        // Sticky windows are drawn on top of all workspaces.
+        if let Some(rv) = self.sticky_window_under(pos_within_output, pip_interactive) {
+            return Some(rv);
+        }
+
         let (ws, geo) = self.workspace_under(pos_within_output)?;
 
         if self.overview_progress.is_some() {
             let zoom = self.overview_zoom();
             let pos_within_workspace = (pos_within_output - geo.loc).downscale(zoom);
-            let (win, hit) = ws.window_under(pos_within_workspace)?;
+            let (win, hit) = ws.window_under(pos_within_workspace, pip_interactive)?;
             // During the overview animation, we cannot do input hits because we cannot really
             // represent scaled windows properly.
             Some((win, hit.to_activate()))
         } else {
-            let (win, hit) = ws.window_under(pos_within_output - geo.loc)?;
+            let (win, hit) = ws.window_under(pos_within_output - geo.loc, pip_interactive)?;
+            Some((win, hit.offset_win_pos(geo.loc)))
+        }
+    }
+
+    fn sticky_window_under(
+        &self,
+        pos_within_output: Point<f64, Logical>,
+        pip_interactive: bool,
+    ) -> Option<(&W, HitType)> {
+        let ws = &self.workspaces[self.active_workspace_idx];
+        let geo = self.sticky_render_geo();
+
+        if self.overview_progress.is_some() {
+            let zoom = self.overview_zoom();
+            let pos_within_layer = (pos_within_output - geo.loc).downscale(zoom);
+            let (win, hit) = ws.sticky_window_under(pos_within_layer, pip_interactive)?;
+            Some((win, hit.to_activate()))
+        } else {
+            let (win, hit) =
+                ws.sticky_window_under(pos_within_output - geo.loc, pip_interactive)?;
             Some((win, hit.offset_win_pos(geo.loc)))
         }
     }
@@ -1378,6 +1503,12 @@ impl<W: LayoutElement> Monitor<W> {
             return None;
         }
 
+        let sticky_geo = self.sticky_render_geo();
+        let ws = &self.workspaces[self.active_workspace_idx];
+        if let Some(edges) = ws.sticky_resize_edges_under(pos_within_output - sticky_geo.loc) {
+            return Some(edges);
+        }
+
         let (ws, geo) = self.workspace_under(pos_within_output)?;
         ws.resize_edges_under(pos_within_output - geo.loc)
     }
@@ -1431,6 +1562,11 @@ impl<W: LayoutElement> Monitor<W> {
         (InsertWorkspace::NewAt(last_idx + 1), dummy)
     }
 
+    /// Returns whether a workspace switch is ongoing outside of the overview.
+    pub fn is_switching_workspaces(&self) -> bool {
+        self.workspace_switch.is_some() && self.overview_progress.is_none()
+    }
+
     pub fn render_above_top_layer(&self) -> bool {
         // Render above the top layer only if the view is stationary.
         if self.workspace_switch.is_some() || self.overview_progress.is_some() {
@@ -1562,6 +1698,31 @@ impl<W: LayoutElement> Monitor<W> {
         })
     }
 
+    pub fn render_sticky_elements<'a, R: NiriRenderer>(
+        &'a self,
+        renderer: &'a mut R,
+        target: RenderTarget,
+        focus_ring: bool,
+    ) -> impl Iterator<Item = MonitorRenderElement<R>> + 'a {
+        let _span = tracy_client::span!("Monitor::render_sticky_elements");
+
+        let scale = self.scale.fractional_scale();
+        let zoom = self.overview_zoom();
+        let geo = self.sticky_render_geo();
+
+        let ws = &self.workspaces[self.active_workspace_idx];
+        ws.render_sticky_elements(renderer, target, focus_ring)
+            .map(move |elem| {
+                let elem = MonitorInnerRenderElement::Sticky(elem);
+                let elem = RescaleRenderElement::from_element(elem, Point::from((0, 0)), zoom);
+                RelocateRenderElement::from_element(
+                    elem,
+                    geo.loc.to_physical_precise_round(scale),
+                    Relocate::Relative,
+                )
+            })
+    }
+
     pub fn render_workspace_shadows<'a, R: NiriRenderer>(
         &'a self,
         renderer: &'a mut R,
@@ -1830,9 +1991,12 @@ impl<W: LayoutElement> Monitor<W> {
             gesture.current_idx,
             new_idx as f64,
             velocity,
-            self.options.animations.workspace_switch.0,
+            self.options.animations.workspace_switch.anim,
         )));
 
+        // Carry the sticky tiles over to the new active workspace.
+        self.gather_sticky_tiles();
+
         true
     }
 
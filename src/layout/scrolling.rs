@@ -4,12 +4,15 @@ use std::rc::Rc;
 use std::time::Duration;
 
 use niri_config::{CenterFocusedColumn, PresetSize, Struts};
-use niri_ipc::{ColumnDisplay, SizeChange};
+use niri_ipc::{
+    ColumnDisplay, SessionColumn, SessionColumnWidth, SessionTile, SessionWindow, SizeChange,
+};
 use ordered_float::NotNan;
 use smithay::backend::renderer::gles::GlesRenderer;
 use smithay::utils::{Logical, Point, Rectangle, Scale, Serial, Size};
 
 use super::closing_window::{ClosingWindow, ClosingWindowRenderElement};
+use super::floating::PipColumn;
 use super::monitor::InsertPosition;
 use super::tab_indicator::{TabIndicator, TabIndicatorRenderElement, TabInfo};
 use super::tile::{Tile, TileRenderElement, TileRenderSnapshot};
@@ -393,6 +396,75 @@ impl<W: LayoutElement> ScrollingSpace<W> {
         self.columns.is_empty()
     }
 
+    /// Returns the index of the window's column, and of the window within the column.
+    fn window_position(&self, window: &W::Id) -> Option<(usize, usize)> {
+        self.columns
+            .iter()
+            .enumerate()
+            .find_map(|(col_idx, col)| Some((col_idx, col.position(window)?)))
+    }
+
+    /// Returns where the window is, to return it there after picture-in-picture.
+    pub fn pip_column(&self, window: &W::Id) -> Option<PipColumn<W::Id>> {
+        let (idx, tile_idx) = self.window_position(window)?;
+        let col = &self.columns[idx];
+
+        let id = |tile: &Tile<W>| tile.window().id().clone();
+        Some(PipColumn {
+            idx,
+            above: tile_idx.checked_sub(1).map(|i| id(&col.tiles[i])),
+            below: col.tiles.get(tile_idx + 1).map(id),
+            width: col.width,
+            is_full_width: col.is_full_width,
+        })
+    }
+
+    /// Returns the columns in a form suitable for saving into a session.
+    pub fn session_columns(
+        &self,
+        session_window: &impl Fn(&W) -> SessionWindow,
+    ) -> Vec<SessionColumn> {
+        self.columns
+            .iter()
+            .map(|col| {
+                let tiles = zip(&col.tiles, &col.data)
+                    .map(|(tile, data)| {
+                        let height = match data.height {
+                            WindowHeight::Auto { .. } => None,
+                            WindowHeight::Fixed(height) => Some(height),
+                            // Presets can change between sessions, so save the resolved height.
+                            WindowHeight::Preset(_) => Some(tile.window_size().h),
+                        };
+
+                        SessionTile {
+                            window: session_window(tile.window()),
+                            height,
+                        }
+                    })
+                    .collect();
+
+                let width = match col.width {
+                    ColumnWidth::Proportion(proportion) => {
+                        SessionColumnWidth::Proportion(proportion)
+                    }
+                    ColumnWidth::Fixed(width) => {
+                        // ColumnWidth includes borders, but restoring goes through the window
+                        // rules which take the window width.
+                        let tile = &col.tiles[col.active_tile_idx];
+                        SessionColumnWidth::Fixed(tile.window_width_for_tile_width(width))
+                    }
+                };
+
+                SessionColumn {
+                    display: col.display_mode,
+                    width,
+                    is_full_width: col.is_full_width,
+                    tiles,
+                }
+            })
+            .collect()
+    }
+
     pub fn active_window(&self) -> Option<&W> {
         if self.columns.is_empty() {
             return None;
@@ -913,6 +985,38 @@ impl<W: LayoutElement> ScrollingSpace<W> {
         }
     }
 
+    /// Adds a tile back to where it was before entering picture-in-picture.
+    ///
+    /// The tile goes next to its former neighbors if they are still in a column, and into a new
+    /// column at its former index otherwise.
+    pub fn add_tile_from_pip(&mut self, column: PipColumn<W::Id>, tile: Tile<W>, activate: bool) {
+        let pos = column
+            .above
+            .as_ref()
+            .and_then(|id| self.window_position(id))
+            .map(|(col_idx, tile_idx)| (col_idx, tile_idx + 1))
+            .or_else(|| {
+                column
+                    .below
+                    .as_ref()
+                    .and_then(|id| self.window_position(id))
+            });
+
+        if let Some((col_idx, tile_idx)) = pos {
+            self.add_tile_to_column(col_idx, Some(tile_idx), tile, activate);
+        } else {
+            let col_idx = min(column.idx, self.columns.len());
+            self.add_tile(
+                Some(col_idx),
+                tile,
+                activate,
+                column.width,
+                column.is_full_width,
+                None,
+            );
+        }
+    }
+
     pub fn add_tile_right_of(
         &mut self,
         right_of: &W::Id,
@@ -3505,11 +3609,27 @@ impl<W: LayoutElement> ScrollingSpace<W> {
             };
 
             for (tile_idx, tile) in col.tiles.iter_mut().enumerate() {
+                // Tiling a scratchpad, sticky or picture-in-picture window takes it out of the
+                // scratchpad, unsticks it and leaves picture-in-picture, unless it's only here
+                // temporarily while fullscreen.
+                if !tile.unfullscreen_to_floating {
+                    tile.is_scratchpad = false;
+                    tile.is_sticky = false;
+                    if let Some(pip) = tile.pip.take() {
+                        tile.floating_window_size = pip.floating_window_size;
+                        tile.floating_pos = pip.floating_pos;
+                    }
+                }
+                let is_scratchpad = tile.is_scratchpad;
+                let is_sticky = tile.is_sticky;
+
                 let win = tile.window_mut();
 
                 let active_in_column = col.active_tile_idx == tile_idx;
                 win.set_active_in_column(active_in_column);
                 win.set_floating(false);
+                win.set_in_scratchpad(is_scratchpad);
+                win.set_sticky(is_sticky);
 
                 let mut active = is_active && self.active_column_idx == col_idx;
                 if self.options.deactivate_unfocused_windows {
@@ -4,12 +4,14 @@ use niri_config::{
     FloatOrInt, OutputName, TabIndicatorLength, TabIndicatorPosition, WorkspaceName,
     WorkspaceReference,
 };
+use niri_ipc::SessionColumnWidth;
 use proptest::prelude::*;
 use proptest_derive::Arbitrary;
 use smithay::output::{Mode, PhysicalProperties, Subpixel};
 use smithay::utils::Rectangle;
 
 use super::*;
+use crate::window::SessionRestore;
 
 impl<W: LayoutElement> Default for Layout<W> {
     fn default() -> Self {
@@ -146,6 +148,17 @@ impl LayoutElement for TestWindow {
         SplitElements::default()
     }
 
+    fn render_blur<R: NiriRenderer>(
+        &self,
+        _renderer: &mut R,
+        _area: Rectangle<f64, Logical>,
+        _radius: CornerRadius,
+        _scale: Scale<f64>,
+        _target: RenderTarget,
+    ) -> Option<BlurRenderElement> {
+        None
+    }
+
     fn request_size(
         &mut self,
         size: Size<i32, Logical>,
@@ -205,6 +218,12 @@ impl LayoutElement for TestWindow {
 
     fn set_floating(&mut self, _floating: bool) {}
 
+    fn set_in_scratchpad(&mut self, _in_scratchpad: bool) {}
+
+    fn set_sticky(&mut self, _sticky: bool) {}
+
+    fn set_workspace(&mut self, _name: Option<&str>, _output: Option<&Output>) {}
+
     fn is_fullscreen(&self) -> bool {
         if self.0.is_windowed_fullscreen.get() {
             return false;
@@ -557,9 +576,34 @@ enum Op {
         id: Option<usize>,
         floating: bool,
     },
+    ToggleWindowSticky {
+        #[proptest(strategy = "proptest::option::of(1..=5usize)")]
+        id: Option<usize>,
+    },
+    TogglePictureInPicture {
+        #[proptest(strategy = "proptest::option::of(1..=5usize)")]
+        id: Option<usize>,
+    },
     FocusFloating,
     FocusTiling,
     SwitchFocusFloatingTiling,
+    MoveWindowToScratchpad {
+        #[proptest(strategy = "proptest::option::of(1..=5usize)")]
+        id: Option<usize>,
+    },
+    /// Scratchpad ops match by window id in place of app id.
+    ToggleScratchpad {
+        #[proptest(strategy = "proptest::option::of(1..=5usize)")]
+        id: Option<usize>,
+    },
+    ShowScratchpad {
+        #[proptest(strategy = "proptest::option::of(1..=5usize)")]
+        id: Option<usize>,
+    },
+    HideScratchpad {
+        #[proptest(strategy = "proptest::option::of(1..=5usize)")]
+        id: Option<usize>,
+    },
     MoveFloatingWindow {
         #[proptest(strategy = "proptest::option::of(1..=5usize)")]
         id: Option<usize>,
@@ -677,6 +721,7 @@ enum Op {
         window: usize,
     },
     ToggleOverview,
+    ToggleInactiveDim,
 }
 
 impl Op {
@@ -788,7 +833,7 @@ impl Op {
                 layout.unset_workspace_name(ws_ref);
             }
             Op::AddWindow { mut params } => {
-                if layout.has_window(&params.id) {
+                if layout.has_window(&params.id) || layout.is_in_scratchpad(&params.id) {
                     return;
                 }
                 if let Some(parent_id) = params.parent_id {
@@ -814,6 +859,10 @@ impl Op {
             } => {
                 let mut found_next_to = false;
 
+                if layout.is_in_scratchpad(&params.id) {
+                    return;
+                }
+
                 if let Some(InteractiveMoveState::Moving(move_)) = &layout.interactive_move {
                     let win_id = move_.tile.window().0.id;
                     if win_id == params.id {
@@ -883,6 +932,10 @@ impl Op {
                 let ws_name = format!("ws{ws_name}");
                 let mut ws_id = None;
 
+                if layout.is_in_scratchpad(&params.id) {
+                    return;
+                }
+
                 if let Some(InteractiveMoveState::Moving(move_)) = &layout.interactive_move {
                     if move_.tile.window().0.id == params.id {
                         return;
@@ -1227,6 +1280,14 @@ impl Op {
                 let id = id.filter(|id| layout.has_window(id));
                 layout.set_window_floating(id.as_ref(), floating);
             }
+            Op::ToggleWindowSticky { id } => {
+                let id = id.filter(|id| layout.has_window(id));
+                layout.toggle_window_sticky(id.as_ref());
+            }
+            Op::TogglePictureInPicture { id } => {
+                let id = id.filter(|id| layout.has_window(id));
+                layout.toggle_window_pip(id.as_ref());
+            }
             Op::FocusFloating => {
                 layout.focus_floating();
             }
@@ -1236,6 +1297,19 @@ impl Op {
             Op::SwitchFocusFloatingTiling => {
                 layout.switch_focus_floating_tiling();
             }
+            Op::MoveWindowToScratchpad { id } => {
+                let id = id.filter(|id| layout.has_window(id));
+                layout.move_to_scratchpad(id.as_ref());
+            }
+            Op::ToggleScratchpad { id } => {
+                layout.toggle_scratchpad(|win| id.map_or(true, |id| win.0.id == id));
+            }
+            Op::ShowScratchpad { id } => {
+                layout.show_scratchpad(|win| id.map_or(true, |id| win.0.id == id));
+            }
+            Op::HideScratchpad { id } => {
+                layout.hide_scratchpad(|win| id.map_or(true, |id| win.0.id == id));
+            }
             Op::MoveFloatingWindow { id, x, y, animate } => {
                 let id = id.filter(|id| layout.has_window(id));
                 layout.move_floating_window(id.as_ref(), x, y, animate);
@@ -1476,6 +1550,9 @@ impl Op {
             Op::ToggleOverview => {
                 layout.toggle_overview();
             }
+            Op::ToggleInactiveDim => {
+                layout.toggle_inactive_dim();
+            }
         }
     }
 }
@@ -2931,6 +3008,334 @@ fn windows_on_other_workspaces_remain_activated() {
     assert!(win.0.pending_activated.get());
 }
 
+#[test]
+fn scratchpad_hides_and_summons_as_floating() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::AddWindow {
+            params: TestWindowParams::new(2),
+        },
+        Op::MoveWindowToScratchpad { id: Some(2) },
+    ];
+
+    let mut layout = check_ops(&ops);
+    assert!(layout.is_in_scratchpad(&2));
+    assert!(!layout.has_window(&2));
+    assert_eq!(layout.focus().unwrap().0.id, 1);
+
+    for op in [Op::FocusWorkspaceDown, Op::ToggleScratchpad { id: None }] {
+        op.apply(&mut layout);
+        layout.verify_invariants();
+    }
+
+    // The window is summoned as floating on the current workspace.
+    let ws = layout.active_workspace().unwrap();
+    assert!(ws.is_floating(&2));
+    assert_eq!(layout.focus().unwrap().0.id, 2);
+    assert!(!layout.is_in_scratchpad(&2));
+
+    // Toggling again hides it.
+    Op::ToggleScratchpad { id: None }.apply(&mut layout);
+    layout.verify_invariants();
+    assert!(layout.is_in_scratchpad(&2));
+}
+
+#[test]
+fn scratchpad_summons_by_match() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::AddWindow {
+            params: TestWindowParams::new(2),
+        },
+        Op::MoveWindowToScratchpad { id: Some(1) },
+        Op::MoveWindowToScratchpad { id: Some(2) },
+        Op::ShowScratchpad { id: Some(1) },
+    ];
+
+    let layout = check_ops(&ops);
+    assert!(!layout.is_in_scratchpad(&1));
+    assert!(layout.is_in_scratchpad(&2));
+}
+
+#[test]
+fn scratchpad_survives_output_removal() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::MoveWindowToScratchpad { id: None },
+        Op::RemoveOutput(1),
+        Op::AddOutput(2),
+        Op::ToggleScratchpad { id: Some(1) },
+    ];
+
+    let layout = check_ops(&ops);
+    assert!(layout.has_window(&1));
+}
+
+#[test]
+fn sticky_window_follows_workspace_switch() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::AddWindow {
+            params: TestWindowParams::new(2),
+        },
+        Op::ToggleWindowSticky { id: Some(2) },
+        Op::Communicate(2),
+        Op::AdvanceAnimations { msec_delta: 1000 },
+    ];
+
+    let mut layout = check_ops(&ops);
+
+    let sticky_pos = |layout: &Layout<TestWindow>| {
+        let ws = layout.active_workspace().unwrap();
+        let (tile, pos, _) = ws
+            .tiles_with_render_positions()
+            .find(|(tile, _, _)| *tile.window().id() == 2)
+            .unwrap();
+        assert!(tile.is_sticky);
+        assert!(ws.is_floating(&2));
+        pos
+    };
+    let pos = sticky_pos(&layout);
+
+    for op in [
+        Op::FocusWorkspaceDown,
+        Op::AdvanceAnimations { msec_delta: 1000 },
+    ] {
+        op.apply(&mut layout);
+        layout.verify_invariants();
+    }
+
+    // The window moved to the new workspace and stayed in place.
+    assert!(!layout.active_workspace().unwrap().has_window(&1));
+    assert_eq!(sticky_pos(&layout), pos);
+
+    // Unsticking leaves the window on the current workspace.
+    Op::ToggleWindowSticky { id: Some(2) }.apply(&mut layout);
+    Op::FocusWorkspaceUp.apply(&mut layout);
+    layout.verify_invariants();
+    assert!(!layout.active_workspace().unwrap().has_window(&2));
+}
+
+#[test]
+fn pip_window_snaps_to_corners_and_returns_to_its_column() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::AddWindow {
+            params: TestWindowParams::new(2),
+        },
+        Op::AddWindow {
+            params: TestWindowParams::new(3),
+        },
+        Op::FocusColumnLeft,
+        Op::TogglePictureInPicture { id: None },
+        Op::Communicate(2),
+        Op::AdvanceAnimations { msec_delta: 1000 },
+    ];
+
+    let options = Options {
+        picture_in_picture: niri_config::PictureInPicture {
+            width: FloatOrInt(50.),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut layout = check_ops_with_options(options, &ops);
+
+    let pip_rect = |layout: &Layout<TestWindow>| {
+        let ws = layout.active_workspace().unwrap();
+        let (tile, pos, _) = ws
+            .tiles_with_render_positions()
+            .find(|(tile, _, _)| *tile.window().id() == 2)
+            .unwrap();
+        assert!(tile.pip.is_some());
+        assert!(tile.is_sticky);
+        (
+            Rectangle::new(pos, tile.tile_size()),
+            ws.floating().working_area(),
+        )
+    };
+
+    // The window shrank to the configured width, keeping its aspect ratio, and went into the
+    // bottom right corner.
+    let (rect, area) = pip_rect(&layout);
+    assert_eq!(layout.active_workspace().unwrap().tiles().count(), 3);
+    assert_eq!(rect.size, Size::from((50., 100.)));
+    assert_eq!(rect.loc.x + rect.size.w, area.loc.x + area.size.w - 16.);
+    assert_eq!(rect.loc.y + rect.size.h, area.loc.y + area.size.h - 16.);
+
+    // Moving it left snaps it to the bottom left corner.
+    for op in [
+        Op::MoveColumnLeft,
+        Op::AdvanceAnimations { msec_delta: 1000 },
+    ] {
+        op.apply(&mut layout);
+        layout.verify_invariants();
+    }
+    let (rect, area) = pip_rect(&layout);
+    assert_eq!(rect.loc.x, area.loc.x + 16.);
+    assert_eq!(rect.loc.y + rect.size.h, area.loc.y + area.size.h - 16.);
+
+    // Picture-in-picture windows stay sticky.
+    Op::ToggleWindowSticky { id: Some(2) }.apply(&mut layout);
+    layout.verify_invariants();
+    pip_rect(&layout);
+
+    // Leaving picture-in-picture puts the window back into its column.
+    Op::TogglePictureInPicture { id: None }.apply(&mut layout);
+    layout.verify_invariants();
+    let ws = layout.active_workspace().unwrap();
+    assert!(!ws.is_floating(&2));
+    let ids: Vec<_> = ws
+        .scrolling()
+        .tiles()
+        .map(|tile| *tile.window().id())
+        .collect();
+    assert_eq!(ids, [1, 2, 3]);
+}
+
+#[test]
+fn scratchpad_fullscreen_window_unfullscreens() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::FullscreenWindow(1),
+        Op::MoveWindowToScratchpad { id: None },
+        Op::ToggleScratchpad { id: None },
+    ];
+
+    let layout = check_ops(&ops);
+    let ws = layout.active_workspace().unwrap();
+    assert!(ws.is_floating(&1));
+}
+
+#[test]
+fn session_saves_columns_and_floating() {
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::AddWindow {
+            params: TestWindowParams::new(2),
+        },
+        Op::ConsumeOrExpelWindowLeft { id: None },
+        Op::SetColumnDisplay(ColumnDisplay::Tabbed),
+        Op::AddWindow {
+            params: TestWindowParams {
+                is_floating: true,
+                ..TestWindowParams::new(3)
+            },
+        },
+        Op::FocusWorkspaceDown,
+        Op::AddWindow {
+            params: TestWindowParams::new(4),
+        },
+    ];
+
+    let layout = check_ops(&ops);
+    let session = layout.session(|win| SessionWindow {
+        app_id: Some(win.0.id.to_string()),
+        title: None,
+    });
+
+    let app_ids = |tiles: &[niri_ipc::SessionTile]| {
+        tiles
+            .iter()
+            .map(|tile| tile.window.app_id.clone().unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(session.workspaces.len(), 2);
+
+    let ws = &session.workspaces[0];
+    assert_eq!(ws.idx, 1);
+    assert_eq!(ws.output.as_deref(), Some("output1"));
+    assert_eq!(ws.columns.len(), 1);
+    assert_eq!(ws.columns[0].display, ColumnDisplay::Tabbed);
+    assert_eq!(app_ids(&ws.columns[0].tiles), ["1", "2"]);
+    assert_eq!(ws.floating.len(), 1);
+    assert_eq!(ws.floating[0].window.app_id.as_deref(), Some("3"));
+
+    let ws = &session.workspaces[1];
+    assert_eq!(ws.idx, 2);
+    assert_eq!(app_ids(&ws.columns[0].tiles), ["4"]);
+}
+
+#[test]
+fn session_restores_fixed_width() {
+    let options = Options {
+        border: niri_config::Border {
+            off: false,
+            width: FloatOrInt(5.),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let ops = [
+        Op::AddOutput(1),
+        Op::AddWindow {
+            params: TestWindowParams::new(1),
+        },
+        Op::SetWindowWidth {
+            id: None,
+            change: SizeChange::SetFixed(500),
+        },
+    ];
+
+    let session_window = |win: &TestWindow| SessionWindow {
+        app_id: Some(win.0.id.to_string()),
+        title: None,
+    };
+
+    let layout = check_ops_with_options(options.clone(), &ops);
+    let session = layout.session(session_window);
+    let width = session.workspaces[0].columns[0].width;
+    assert_eq!(width, SessionColumnWidth::Fixed(500.));
+
+    // Open the window again in a fresh layout, the way a restored session does.
+    let mut restore = SessionRestore::new(session);
+    let placement = restore.take_placement(Some("1"), None).unwrap();
+    let mut rules = ResolvedWindowRules::empty();
+    placement.apply_to_rules(&mut rules);
+
+    let mut layout = check_ops_with_options(options, &[Op::AddOutput(1)]);
+    let ws = layout.active_workspace().unwrap();
+    let width = ws.resolve_default_width(rules.default_width, false);
+    layout.add_window(
+        TestWindow::new(TestWindowParams::new(1)),
+        AddWindowTarget::Auto,
+        width,
+        None,
+        false,
+        false,
+        ActivateWindow::default(),
+    );
+
+    let win = layout.windows().next().unwrap().1;
+    assert_eq!(win.requested_size().unwrap().w, 500);
+
+    let session = layout.session(session_window);
+    let width = session.workspaces[0].columns[0].width;
+    assert_eq!(width, SessionColumnWidth::Fixed(500.));
+}
+
 #[test]
 fn stacking_add_parent_brings_up_child() {
     let ops = [
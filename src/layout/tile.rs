@@ -3,9 +3,10 @@ use std::rc::Rc;
 
 use niri_config::{Color, CornerRadius, GradientInterpolation};
 use smithay::backend::renderer::element::{Element, Kind};
-use smithay::backend::renderer::gles::GlesRenderer;
+use smithay::backend::renderer::gles::{GlesRenderer, GlesTexProgram};
 use smithay::utils::{Logical, Point, Rectangle, Scale, Size};
 
+use super::floating::PipState;
 use super::focus_ring::{FocusRing, FocusRingRenderElement};
 use super::opening_window::{OpenAnimation, OpeningWindowRenderElement};
 use super::shadow::Shadow;
@@ -15,8 +16,10 @@ use super::{
 };
 use crate::animation::{Animation, Clock};
 use crate::niri_render_elements;
+use crate::render_helpers::blur::BlurRenderElement;
 use crate::render_helpers::border::BorderRenderElement;
 use crate::render_helpers::clipped_surface::{ClippedSurfaceRenderElement, RoundedCornerDamage};
+use crate::render_helpers::color_adjust::{ColorAdjust, ColorAdjustRenderElement};
 use crate::render_helpers::damage::ExtraDamage;
 use crate::render_helpers::offscreen::{OffscreenBuffer, OffscreenRenderElement};
 use crate::render_helpers::renderer::NiriRenderer;
@@ -55,6 +58,24 @@ pub struct Tile<W: LayoutElement> {
     /// Whether the tile should float upon unfullscreening.
     pub(super) unfullscreen_to_floating: bool,
 
+    /// Whether the tile belongs to the scratchpad.
+    ///
+    /// Scratchpad tiles are normally hidden in their monitor's scratchpad, and show up in the
+    /// floating layout of the active workspace when summoned.
+    pub(super) is_scratchpad: bool,
+
+    /// Whether the tile is sticky.
+    ///
+    /// Sticky tiles are floating and follow their monitor's active workspace, so they stay visible
+    /// on every workspace.
+    pub(super) is_sticky: bool,
+
+    /// Picture-in-picture state of the tile, if it is in picture-in-picture.
+    ///
+    /// Picture-in-picture tiles are sticky floating thumbnails anchored to a corner of the working
+    /// area.
+    pub(super) pip: Option<PipState<W::Id>>,
+
     /// The size that the window should assume when going floating.
     ///
     /// This is generally the last size the window had when it was floating. It can be unknown if
@@ -98,6 +119,18 @@ pub struct Tile<W: LayoutElement> {
     /// Extra damage for clipped surface corner radius changes.
     rounded_corner_damage: RoundedCornerDamage,
 
+    /// Whether the tile draws with the inactive color adjustment.
+    is_inactive: bool,
+
+    /// The animation of the inactive color adjustment, from 0 (active) to 1 (inactive).
+    inactive_animation: Option<Animation>,
+
+    /// The color adjustment that the window is currently drawn with.
+    color_adjust: ColorAdjust,
+
+    /// Extra damage for color adjustment changes.
+    color_adjust_damage: ExtraDamage,
+
     /// The view size for the tile's workspace.
     ///
     /// Used as the fullscreen target size.
@@ -122,7 +155,9 @@ niri_render_elements! {
         Resize = ResizeRenderElement,
         Border = BorderRenderElement,
         Shadow = ShadowRenderElement,
+        Blur = BlurRenderElement,
         ClippedSurface = ClippedSurfaceRenderElement<R>,
+        ColorAdjust = ColorAdjustRenderElement<R>,
         Offscreen = OffscreenRenderElement,
         ExtraDamage = ExtraDamage,
     }
@@ -179,6 +214,9 @@ impl<W: LayoutElement> Tile<W> {
             is_fullscreen,
             fullscreen_backdrop: SolidColorBuffer::new(view_size, [0., 0., 0., 1.]),
             unfullscreen_to_floating: false,
+            is_scratchpad: false,
+            is_sticky: false,
+            pip: None,
             floating_window_size: None,
             floating_pos: None,
             floating_preset_width_idx: None,
@@ -191,6 +229,10 @@ impl<W: LayoutElement> Tile<W> {
             interactive_move_offset: Point::from((0., 0.)),
             unmap_snapshot: None,
             rounded_corner_damage: Default::default(),
+            is_inactive: false,
+            inactive_animation: None,
+            color_adjust: ColorAdjust::IDENTITY,
+            color_adjust_damage: Default::default(),
             view_size,
             scale,
             clock,
@@ -298,6 +340,7 @@ impl<W: LayoutElement> Tile<W> {
             .fit_to(window_size.w as f32, window_size.h as f32);
         self.rounded_corner_damage.set_corner_radius(radius);
         self.rounded_corner_damage.set_size(window_size);
+        self.color_adjust_damage.set_size(window_size);
     }
 
     pub fn advance_animations(&mut self) {
@@ -329,10 +372,18 @@ impl<W: LayoutElement> Tile<W> {
                 self.alpha_animation = None;
             }
         }
+
+        if let Some(anim) = &self.inactive_animation {
+            if anim.is_done() {
+                self.inactive_animation = None;
+            }
+        }
     }
 
     pub fn are_animations_ongoing(&self) -> bool {
-        self.are_transitions_ongoing() || self.window.rules().baba_is_float == Some(true)
+        self.are_transitions_ongoing()
+            || self.inactive_animation.is_some()
+            || self.window.rules().baba_is_float == Some(true)
     }
 
     pub fn are_transitions_ongoing(&self) -> bool {
@@ -347,6 +398,29 @@ impl<W: LayoutElement> Tile<W> {
     }
 
     pub fn update_render_elements(&mut self, is_active: bool, view_rect: Rectangle<f64, Logical>) {
+        let is_inactive = !is_active;
+        if self.is_inactive != is_inactive {
+            self.is_inactive = is_inactive;
+
+            let from = self.inactive_progress();
+            let to = if self.is_inactive { 1. } else { 0. };
+            self.inactive_animation = Some(Animation::new(
+                self.clock.clone(),
+                from,
+                to,
+                0.,
+                self.options.animations.inactive_dim.0,
+            ));
+        }
+
+        let color_adjust = self
+            .inactive_adjust()
+            .scaled(self.inactive_progress() as f32);
+        if self.color_adjust != color_adjust {
+            self.color_adjust = color_adjust;
+            self.color_adjust_damage.damage_all();
+        }
+
         let rules = self.window.rules();
 
         let draw_border_with_background = rules
@@ -409,6 +483,33 @@ impl<W: LayoutElement> Tile<W> {
         );
     }
 
+    /// Returns the color adjustment for when the tile is fully inactive.
+    fn inactive_adjust(&self) -> ColorAdjust {
+        if self.options.disable_inactive_adjust {
+            return ColorAdjust::IDENTITY;
+        }
+
+        let rules = self.window.rules();
+        let adjust = self.options.inactive_adjust;
+        ColorAdjust {
+            dim: rules.inactive_dim.map_or(adjust.dim, |x| x as f32),
+            saturation: rules
+                .inactive_saturation
+                .map_or(adjust.saturation, |x| x as f32),
+            brightness: rules
+                .inactive_brightness
+                .map_or(adjust.brightness, |x| x as f32),
+        }
+    }
+
+    fn inactive_progress(&self) -> f64 {
+        match &self.inactive_animation {
+            Some(anim) => anim.clamped_value().clamp(0., 1.),
+            None if self.is_inactive => 1.,
+            None => 0.,
+        }
+    }
+
     pub fn scale(&self) -> f64 {
         self.scale
     }
@@ -822,6 +923,7 @@ impl<W: LayoutElement> Tile<W> {
         location: Point<f64, Logical>,
         focus_ring: bool,
         target: RenderTarget,
+        blur: bool,
     ) -> impl Iterator<Item = TileRenderElement<R>> + 'a {
         let _span = tracy_client::span!("Tile::render_inner");
 
@@ -853,18 +955,46 @@ impl<W: LayoutElement> Tile<W> {
         let clip_to_geometry = !self.is_fullscreen && rules.clip_to_geometry == Some(true);
         let radius = rules.geometry_corner_radius.unwrap_or_default();
 
+        let color_adjust = self.color_adjust;
+        let adjust_shader = if color_adjust.is_identity() {
+            None
+        } else {
+            ColorAdjustRenderElement::shader(renderer).cloned()
+        };
+
+        // Popups get the same color adjustment as the window, so damage them along with it.
+        let mut color_adjust_damage = (!color_adjust.is_identity()).then(|| {
+            self.color_adjust_damage
+                .clone()
+                .with_location(window_render_loc)
+        });
+        let mut adjust_popups = |popups: Vec<LayoutElementRenderElement<R>>| {
+            if let Some(damage) = &mut color_adjust_damage {
+                for popup in &popups {
+                    let geo = popup.geometry(scale).to_f64().to_logical(scale);
+                    *damage = damage.clone().merged(geo);
+                }
+            }
+
+            let shader = adjust_shader.clone();
+            popups
+                .into_iter()
+                .map(move |elem| adjust_colors(elem, shader.as_ref(), color_adjust))
+        };
+
         // If we're resizing, try to render a shader, or a fallback.
         let mut resize_shader = None;
         let mut resize_popups = None;
         let mut resize_fallback = None;
 
         if let Some(resize) = &self.resize_animation {
-            resize_popups = Some(
-                self.window
-                    .render_popups(renderer, window_render_loc, scale, win_alpha, target)
-                    .into_iter()
-                    .map(Into::into),
-            );
+            resize_popups = Some(adjust_popups(self.window.render_popups(
+                renderer,
+                window_render_loc,
+                scale,
+                win_alpha,
+                target,
+            )));
 
             if ResizeRenderElement::has_shader(renderer) {
                 let gles_renderer = renderer.as_gles_renderer();
@@ -913,6 +1043,7 @@ impl<W: LayoutElement> Tile<W> {
                             resize.anim.clamped_value().clamp(0., 1.) as f32,
                             radius,
                             clip_to_geometry,
+                            self.color_adjust,
                             win_alpha,
                         );
 
@@ -961,6 +1092,9 @@ impl<W: LayoutElement> Tile<W> {
                 rounded_corner_damage = Some(damage.with_location(window_render_loc).into());
             }
 
+            window_popups = Some(adjust_popups(window.popups));
+
+            let adjust_shader = adjust_shader.clone();
             window_surface = Some(window.normal.into_iter().map(move |elem| match elem {
                 LayoutElementRenderElement::Wayland(elem) => {
                     // If we should clip to geometry, render a clipped window.
@@ -973,14 +1107,25 @@ impl<W: LayoutElement> Tile<W> {
                                     geo,
                                     shader.clone(),
                                     radius,
+                                    color_adjust,
                                 )
                                 .into();
                             }
                         }
                     }
 
-                    // Otherwise, render it normally.
-                    LayoutElementRenderElement::Wayland(elem).into()
+                    // Otherwise, render it normally, adjusting the colors if needed.
+                    let elem = LayoutElementRenderElement::Wayland(elem);
+                    adjust_colors(elem, adjust_shader.as_ref(), color_adjust)
+                }
+                LayoutElementRenderElement::ColorTransform(mut elem) => {
+                    // The color transform shader does the clipping itself.
+                    if clip_to_geometry {
+                        elem = elem.with_clip(scale, geo, radius);
+                    }
+
+                    let elem = LayoutElementRenderElement::ColorTransform(elem);
+                    adjust_colors(elem, adjust_shader.as_ref(), color_adjust)
                 }
                 LayoutElementRenderElement::SolidColor(elem) => {
                     // In this branch we're rendering a blocked-out window with a solid
@@ -1010,11 +1155,26 @@ impl<W: LayoutElement> Tile<W> {
                     // Otherwise, render the solid color as is.
                     LayoutElementRenderElement::SolidColor(elem).into()
                 }
+                LayoutElementRenderElement::Border(elem) => {
+                    LayoutElementRenderElement::Border(elem).into()
+                }
             }));
-
-            window_popups = Some(window.popups.into_iter().map(Into::into));
         }
 
+        // The blur samples what was rendered below it, so it can't go into an offscreen.
+        let blur = if blur {
+            let radius = if self.is_fullscreen {
+                CornerRadius::default()
+            } else {
+                radius
+            };
+            self.window
+                .render_blur(renderer, area, radius, scale, target)
+                .map(Into::into)
+        } else {
+            None
+        };
+
         let rv = resize_popups
             .into_iter()
             .flatten()
@@ -1022,7 +1182,9 @@ impl<W: LayoutElement> Tile<W> {
             .chain(resize_fallback)
             .chain(window_popups.into_iter().flatten())
             .chain(rounded_corner_damage)
-            .chain(window_surface.into_iter().flatten());
+            .chain(color_adjust_damage.map(Into::into))
+            .chain(window_surface.into_iter().flatten())
+            .chain(blur);
 
         let elem = self.is_fullscreen.then(|| {
             SolidColorRenderElement::from_buffer(
@@ -1072,7 +1234,8 @@ impl<W: LayoutElement> Tile<W> {
 
         if let Some(open) = &self.open_animation {
             let renderer = renderer.as_gles_renderer();
-            let elements = self.render_inner(renderer, Point::from((0., 0.)), focus_ring, target);
+            let elements =
+                self.render_inner(renderer, Point::from((0., 0.)), focus_ring, target, false);
             let elements = elements.collect::<Vec<TileRenderElement<_>>>();
             match open.render(
                 renderer,
@@ -1092,7 +1255,8 @@ impl<W: LayoutElement> Tile<W> {
             }
         } else if let Some(alpha) = &self.alpha_animation {
             let renderer = renderer.as_gles_renderer();
-            let elements = self.render_inner(renderer, Point::from((0., 0.)), focus_ring, target);
+            let elements =
+                self.render_inner(renderer, Point::from((0., 0.)), focus_ring, target, false);
             let elements = elements.collect::<Vec<TileRenderElement<_>>>();
             match alpha.offscreen.render(renderer, scale, &elements) {
                 Ok((elem, _sync, data)) => {
@@ -1109,7 +1273,7 @@ impl<W: LayoutElement> Tile<W> {
         }
 
         if open_anim_elem.is_none() && alpha_anim_elem.is_none() {
-            window_elems = Some(self.render_inner(renderer, location, focus_ring, target));
+            window_elems = Some(self.render_inner(renderer, location, focus_ring, target, true));
         }
 
         open_anim_elem
@@ -1184,3 +1348,22 @@ impl<W: LayoutElement> Tile<W> {
         assert_abs_diff_eq!(size.h, rounded.h, epsilon = 1e-5);
     }
 }
+
+/// Renders a window surface or popup with the color adjustment.
+fn adjust_colors<R: NiriRenderer>(
+    elem: LayoutElementRenderElement<R>,
+    adjust_shader: Option<&GlesTexProgram>,
+    adjust: ColorAdjust,
+) -> TileRenderElement<R> {
+    match elem {
+        LayoutElementRenderElement::Wayland(elem) => match adjust_shader {
+            Some(shader) => ColorAdjustRenderElement::new(elem, shader.clone(), adjust).into(),
+            None => LayoutElementRenderElement::Wayland(elem).into(),
+        },
+        // The color transform shader does the adjustment itself.
+        LayoutElementRenderElement::ColorTransform(elem) if !adjust.is_identity() => {
+            LayoutElementRenderElement::ColorTransform(elem.with_color_adjust(adjust)).into()
+        }
+        elem => elem.into(),
+    }
+}
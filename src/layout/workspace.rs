@@ -5,7 +5,9 @@ use std::time::Duration;
 use niri_config::{
     CenterFocusedColumn, CornerRadius, OutputName, PresetSize, Workspace as WorkspaceConfig,
 };
-use niri_ipc::{ColumnDisplay, PositionChange, SizeChange};
+use niri_ipc::{
+    ColumnDisplay, PositionChange, SessionWindow, SessionWorkspace, SizeChange,
+};
 use smithay::backend::renderer::gles::GlesRenderer;
 use smithay::desktop::{layer_map_for_output, Window};
 use smithay::output::Output;
@@ -15,7 +17,7 @@ use smithay::utils::{Logical, Point, Rectangle, Serial, Size, Transform};
 use smithay::wayland::compositor::with_states;
 use smithay::wayland::shell::xdg::SurfaceCachedState;
 
-use super::floating::{FloatingSpace, FloatingSpaceRenderElement};
+use super::floating::{FloatingSpace, FloatingSpaceRenderElement, PipState};
 use super::scrolling::{
     Column, ColumnWidth, ScrollDirection, ScrollingSpace, ScrollingSpaceRenderElement,
 };
@@ -326,6 +328,22 @@ impl<W: LayoutElement> Workspace<W> {
         self.name.as_ref()
     }
 
+    /// Returns the workspace in a form suitable for saving into a session.
+    pub fn session(
+        &self,
+        idx: u8,
+        output: Option<String>,
+        session_window: &impl Fn(&W) -> SessionWindow,
+    ) -> SessionWorkspace {
+        SessionWorkspace {
+            idx,
+            name: self.name.clone(),
+            output,
+            columns: self.scrolling.session_columns(session_window),
+            floating: self.floating.session_windows(session_window),
+        }
+    }
+
     pub fn unname(&mut self) {
         self.name = None;
     }
@@ -709,6 +727,21 @@ impl<W: LayoutElement> Workspace<W> {
         removed
     }
 
+    /// Removes all sticky tiles, returning them in back-to-front order.
+    pub fn remove_sticky_tiles(&mut self) -> Vec<Tile<W>> {
+        let ids: Vec<_> = self
+            .floating
+            .tiles()
+            .filter(|tile| tile.is_sticky)
+            .map(|tile| tile.window().id().clone())
+            .collect();
+
+        ids.iter()
+            .rev()
+            .map(|id| self.remove_tile(id, Transaction::new()).tile)
+            .collect()
+    }
+
     pub fn remove_active_tile(&mut self, transaction: Transaction) -> Option<RemovedTile<W>> {
         let from_floating = self.floating_is_active.get();
         let removed = if from_floating {
@@ -1309,6 +1342,134 @@ impl<W: LayoutElement> Workspace<W> {
         self.toggle_window_floating(id);
     }
 
+    pub fn toggle_window_sticky(&mut self, id: Option<&W::Id>) {
+        let active_id = self.active_window().map(|win| win.id().clone());
+        let Some(id) = id.cloned().or(active_id) else {
+            return;
+        };
+
+        // Picture-in-picture windows are always sticky.
+        if self.is_window_pip(&id) {
+            return;
+        }
+
+        // Only floating windows can be sticky.
+        if !self.floating.has_window(&id) {
+            self.toggle_window_floating(Some(&id));
+        }
+
+        let tile = self
+            .floating
+            .tiles_mut()
+            .find(|tile| *tile.window().id() == id)
+            .unwrap();
+        tile.is_sticky = !tile.is_sticky;
+    }
+
+    pub fn set_window_sticky(&mut self, id: Option<&W::Id>, sticky: bool) {
+        let active_id = self.active_window().map(|win| win.id().clone());
+        let Some(id) = id.cloned().or(active_id) else {
+            return;
+        };
+
+        let is_sticky = self
+            .floating
+            .tiles()
+            .any(|tile| tile.is_sticky && *tile.window().id() == id);
+        if is_sticky == sticky {
+            return;
+        }
+
+        self.toggle_window_sticky(Some(&id));
+    }
+
+    pub fn toggle_window_pip(&mut self, id: Option<&W::Id>) {
+        let active_id = self.active_window().map(|win| win.id().clone());
+        let target_is_active = id.map_or(true, |id| Some(id) == active_id.as_ref());
+        let Some(id) = id.cloned().or(active_id) else {
+            return;
+        };
+
+        let (_, render_pos, _) = self
+            .tiles_with_render_positions()
+            .find(|(tile, _, _)| *tile.window().id() == id)
+            .unwrap();
+
+        if self.is_window_pip(&id) {
+            let mut removed = if self.floating.has_window(&id) {
+                self.floating.remove_tile(&id)
+            } else {
+                // Fullscreen picture-in-picture windows are in the scrolling layout.
+                self.scrolling.remove_tile(&id, Transaction::new())
+            };
+            let tile = &mut removed.tile;
+            tile.unfullscreen_to_floating = false;
+            let pip = tile.pip.take().unwrap();
+            tile.floating_window_size = pip.floating_window_size;
+            tile.floating_pos = pip.floating_pos;
+            tile.is_sticky = pip.is_sticky;
+
+            if let Some(column) = pip.column {
+                self.scrolling
+                    .add_tile_from_pip(column, removed.tile, target_is_active);
+                if target_is_active {
+                    self.floating_is_active = FloatingActive::No;
+                }
+            } else {
+                self.floating.add_tile(removed.tile, target_is_active);
+            }
+        } else {
+            let column = self.scrolling.pip_column(&id);
+            let mut removed = if column.is_some() {
+                let mut removed = self.scrolling.remove_tile(&id, Transaction::new());
+                removed.tile.stop_move_animations();
+                removed
+            } else {
+                self.floating.remove_tile(&id)
+            };
+
+            let tile = &mut removed.tile;
+            let size = tile.window_size();
+            let aspect = if size.w > 0. && size.h > 0. {
+                size.w / size.h
+            } else {
+                16. / 9.
+            };
+
+            let config = self.options.picture_in_picture;
+            let width = config.width.0;
+            let pip_size = Size::from((width.round() as i32, (width / aspect).round() as i32));
+
+            tile.pip = Some(PipState {
+                corner: config.corner,
+                aspect,
+                column,
+                floating_window_size: tile.floating_window_size.replace(pip_size),
+                floating_pos: tile.floating_pos.take(),
+                is_sticky: tile.is_sticky,
+            });
+            // Picture-in-picture windows stay visible on every workspace.
+            tile.is_sticky = true;
+
+            self.floating.add_tile(removed.tile, target_is_active);
+            if target_is_active {
+                self.floating_is_active = FloatingActive::Yes;
+            }
+        }
+
+        let (tile, new_render_pos) = self
+            .tiles_with_render_positions_mut(false)
+            .find(|(tile, _)| *tile.window().id() == id)
+            .unwrap();
+
+        tile.animate_move_from(render_pos - new_render_pos);
+    }
+
+    pub fn is_window_pip(&self, id: &W::Id) -> bool {
+        self.tiles()
+            .any(|tile| tile.pip.is_some() && tile.window().id() == id)
+    }
+
     pub fn focus_floating(&mut self) {
         if !self.floating_is_active.get() {
             self.switch_focus_floating_tiling();
@@ -1461,9 +1622,13 @@ impl<W: LayoutElement> Workspace<W> {
         let floating_focus_ring = focus_ring && self.floating_is_active();
         let floating = self.is_floating_visible().then(|| {
             let view_rect = Rectangle::from_size(self.view_size);
-            let floating =
-                self.floating
-                    .render_elements(renderer, view_rect, target, floating_focus_ring);
+            let floating = self.floating.render_elements(
+                renderer,
+                view_rect,
+                target,
+                floating_focus_ring,
+                false,
+            );
             floating.into_iter().map(WorkspaceRenderElement::from)
         });
         let floating = floating.into_iter().flatten();
@@ -1471,6 +1636,28 @@ impl<W: LayoutElement> Workspace<W> {
         (floating, scrolling)
     }
 
+    /// Renders the sticky tiles, which the monitor draws on top of all of its workspaces.
+    pub fn render_sticky_elements<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        target: RenderTarget,
+        focus_ring: bool,
+    ) -> impl Iterator<Item = WorkspaceRenderElement<R>> {
+        let floating_focus_ring = focus_ring && self.floating_is_active();
+        let sticky = self.is_floating_visible().then(|| {
+            let view_rect = Rectangle::from_size(self.view_size);
+            let sticky = self.floating.render_elements(
+                renderer,
+                view_rect,
+                target,
+                floating_focus_ring,
+                true,
+            );
+            sticky.into_iter().map(WorkspaceRenderElement::from)
+        });
+        sticky.into_iter().flatten()
+    }
+
     pub fn render_shadow<R: NiriRenderer>(
         &self,
         renderer: &mut R,
@@ -1543,12 +1730,20 @@ impl<W: LayoutElement> Workspace<W> {
         self.scrolling.start_open_animation(id) || self.floating.start_open_animation(id)
     }
 
-    pub fn window_under(&self, pos: Point<f64, Logical>) -> Option<(&W, HitType)> {
+    /// Returns the window under `pos`.
+    ///
+    /// Picture-in-picture windows are click-through unless `pip_interactive` is set.
+    pub fn window_under(
+        &self,
+        pos: Point<f64, Logical>,
+        pip_interactive: bool,
+    ) -> Option<(&W, HitType)> {
         // This logic is consistent with tiles_with_render_positions().
         if self.is_floating_visible() {
             if let Some(rv) = self
                 .floating
                 .tiles_with_render_positions()
+                .filter(|(tile, _)| !tile.is_sticky && (pip_interactive || tile.pip.is_none()))
                 .find_map(|(tile, tile_pos)| HitType::hit_tile(tile, tile_pos, pos))
             {
                 return Some(rv);
@@ -1558,12 +1753,42 @@ impl<W: LayoutElement> Workspace<W> {
         self.scrolling.window_under(pos)
     }
 
+    /// Returns the sticky window under `pos`, relative to the monitor's sticky layer.
+    pub fn sticky_window_under(
+        &self,
+        pos: Point<f64, Logical>,
+        pip_interactive: bool,
+    ) -> Option<(&W, HitType)> {
+        if !self.is_floating_visible() {
+            return None;
+        }
+
+        self.floating
+            .tiles_with_render_positions()
+            .filter(|(tile, _)| tile.is_sticky && (pip_interactive || tile.pip.is_none()))
+            .find_map(|(tile, tile_pos)| HitType::hit_tile(tile, tile_pos, pos))
+    }
+
     pub fn resize_edges_under(&self, pos: Point<f64, Logical>) -> Option<ResizeEdge> {
+        self.resize_edges_under_inner(pos, false)
+    }
+
+    /// Returns the resize edges of the sticky window under `pos`, relative to the monitor's
+    /// sticky layer.
+    pub fn sticky_resize_edges_under(&self, pos: Point<f64, Logical>) -> Option<ResizeEdge> {
+        self.resize_edges_under_inner(pos, true)
+    }
+
+    fn resize_edges_under_inner(
+        &self,
+        pos: Point<f64, Logical>,
+        sticky: bool,
+    ) -> Option<ResizeEdge> {
         self.tiles_with_render_positions()
             .find_map(|(tile, tile_pos, visible)| {
                 // This logic should be consistent with window_under() in when it returns Some vs.
                 // None.
-                if !visible {
+                if !visible || tile.is_sticky != sticky {
                     return None;
                 }
 
@@ -1605,6 +1830,12 @@ impl<W: LayoutElement> Workspace<W> {
             .refresh(is_active && !self.floating_is_active.get(), is_focused);
         self.floating
             .refresh(is_active && self.floating_is_active.get(), is_focused);
+
+        let name = self.name.as_deref();
+        let output = self.output.as_ref();
+        for tile in self.scrolling.tiles_mut().chain(self.floating.tiles_mut()) {
+            tile.window_mut().set_workspace(name, output);
+        }
     }
 
     pub fn scroll_amount_to_activate(&self, window: &W::Id) -> f64 {
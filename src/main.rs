@@ -184,7 +184,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
-    let config_load_result = Config::load(&path);
+    let (config_load_result, config_includes) = Config::load_with_includes(&path);
     let config_errored = config_load_result.is_err();
     let mut config = config_load_result
         .map_err(|err| warn!("{err:?}"))
@@ -208,12 +208,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         event_loop.handle(),
         event_loop.get_signal(),
         display,
-        false,
+        cli.headless,
         true,
         cli.session,
     )
     .unwrap();
 
+    if cli.headless {
+        let niri = &mut state.niri;
+        let headless = state.backend.headless();
+        if let Err(err) = headless.add_renderer(niri) {
+            warn!("error enabling headless rendering: {err:?}");
+        }
+        headless.add_output(niri, 1, (1920, 1080));
+    }
+
     // Set WAYLAND_DISPLAY for children.
     let socket_name = state.niri.socket_name.as_deref().unwrap();
     env::set_var("WAYLAND_DISPLAY", socket_name);
@@ -274,13 +283,15 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         // Parsing the config actually takes > 20 ms on my beefy machine, so let's do it on the
         // watcher thread.
         let process = |path: &Path| {
-            Config::load(path).map_err(|err| {
+            let (config, includes) = Config::load_with_includes(path);
+            let config = config.map_err(|err| {
                 warn!("{:?}", err.context("error loading config"));
-            })
+            });
+            (config, includes)
         };
 
         let (tx, rx) = calloop::channel::sync_channel(1);
-        let watcher = Watcher::new(watch_path.clone(), process, tx);
+        let watcher = Watcher::with_extra_paths(watch_path.clone(), config_includes, process, tx);
         event_loop
             .handle()
             .insert_source(rx, |event, _, state| match event {
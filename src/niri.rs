@@ -1,6 +1,7 @@
 use std::cell::{Cell, OnceCell, RefCell};
 use std::collections::{HashMap, HashSet};
 use std::ffi::OsString;
+use std::iter::zip;
 use std::os::unix::net::UnixStream;
 use std::path::PathBuf;
 use std::rc::Rc;
@@ -13,28 +14,28 @@ use std::{env, mem, thread};
 use _server_decoration::server::org_kde_kwin_server_decoration_manager::Mode as KdeDecorationsMode;
 use anyhow::{bail, ensure, Context};
 use calloop::futures::Scheduler;
+use glam::{Mat3, Vec2};
 use niri_config::{
-    Config, FloatOrInt, Key, Modifiers, OutputName, PreviewRender, TrackLayout,
+    Config, FloatOrInt, HotCornerAction, Key, Modifiers, OutputName, PreviewRender, TrackLayout,
     WarpMouseToFocusMode, WorkspaceReference, Xkb,
 };
+use niri_ipc::HotCorner;
 use smithay::backend::allocator::Fourcc;
 use smithay::backend::input::Keycode;
 use smithay::backend::renderer::damage::OutputDamageTracker;
 use smithay::backend::renderer::element::memory::MemoryRenderBufferRenderElement;
-use smithay::backend::renderer::element::surface::{
-    render_elements_from_surface_tree, WaylandSurfaceRenderElement,
-};
+use smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement;
 use smithay::backend::renderer::element::utils::{
     select_dmabuf_feedback, CropRenderElement, Relocate, RelocateRenderElement,
     RescaleRenderElement,
 };
 use smithay::backend::renderer::element::{
-    default_primary_scanout_output_compare, Element, Id, Kind, PrimaryScanoutOutput,
+    default_primary_scanout_output_compare, Element, Id, Kind, PrimaryScanoutOutput, RenderElement,
     RenderElementStates,
 };
-use smithay::backend::renderer::gles::GlesRenderer;
+use smithay::backend::renderer::gles::{GlesRenderer, Uniform};
 use smithay::backend::renderer::sync::SyncPoint;
-use smithay::backend::renderer::Color32F;
+use smithay::backend::renderer::{Color32F, Texture as _};
 use smithay::desktop::utils::{
     bbox_from_surface_tree, output_update, send_dmabuf_feedback_surface_tree,
     send_frames_surface_tree, surface_presentation_feedback_flags_from_states,
@@ -58,6 +59,7 @@ use smithay::reexports::calloop::{
     Interest, LoopHandle, LoopSignal, Mode, PostAction, RegistrationToken,
 };
 use smithay::reexports::wayland_protocols::ext::session_lock::v1::server::ext_session_lock_v1::ExtSessionLockV1;
+use smithay::reexports::wayland_protocols::wp::tearing_control::v1::server::wp_tearing_control_v1;
 use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel::WmCapabilities;
 use smithay::reexports::wayland_protocols_misc::server_decoration as _server_decoration;
 use smithay::reexports::wayland_protocols_wlr::screencopy::v1::server::zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1;
@@ -104,11 +106,13 @@ use smithay::wayland::shm::ShmState;
 use smithay::wayland::single_pixel_buffer::SinglePixelBufferState;
 use smithay::wayland::socket::ListeningSocketSource;
 use smithay::wayland::tablet_manager::TabletManagerState;
+use smithay::wayland::tearing_control::{TearingControlState, TearingControlSurfaceCachedState};
 use smithay::wayland::text_input::TextInputManagerState;
 use smithay::wayland::viewporter::ViewporterState;
 use smithay::wayland::virtual_keyboard::VirtualKeyboardManagerState;
 use smithay::wayland::xdg_activation::XdgActivationState;
 use smithay::wayland::xdg_foreign::XdgForeignState;
+use xcursor::parser::Image;
 
 use crate::animation::Clock;
 use crate::backend::tty::SurfaceDmabufFeedback;
@@ -125,6 +129,8 @@ use crate::dbus::gnome_shell_screenshot::{NiriToScreenshot, ScreenshotToNiri};
 use crate::dbus::mutter_screen_cast::{self, ScreenCastToNiri};
 use crate::frame_clock::FrameClock;
 use crate::handlers::{configure_lock_surface, XDG_ACTIVATION_TOKEN_TIMEOUT};
+use crate::input::gesture_binds::{TouchpadSwipe, TouchscreenSwipe};
+use crate::input::hot_corners::{self, HotCornerState, PointerSpeed};
 use crate::input::pick_color_grab::PickColorGrab;
 use crate::input::scroll_swipe_gesture::ScrollSwipeGesture;
 use crate::input::scroll_tracker::ScrollTracker;
@@ -139,19 +145,38 @@ use crate::layout::tile::TileRenderElement;
 use crate::layout::workspace::{Workspace, WorkspaceId};
 use crate::layout::{HitType, Layout, LayoutElement as _, MonitorRenderElement};
 use crate::niri_render_elements;
+use crate::protocols::background_effect::BackgroundEffectState;
+use crate::protocols::color_management::ColorManagementState;
+use crate::protocols::ext_foreign_toplevel::{self, ExtForeignToplevelState};
 use crate::protocols::ext_workspace::{self, ExtWorkspaceManagerState};
 use crate::protocols::foreign_toplevel::{self, ForeignToplevelManagerState};
 use crate::protocols::gamma_control::GammaControlManagerState;
+use crate::protocols::image_copy_capture::{
+    self, CaptureConstraints, ImageCaptureSource, ImageCopyCaptureState,
+};
 use crate::protocols::mutter_x11_interop::MutterX11InteropManagerState;
 use crate::protocols::output_management::OutputManagementManagerState;
-use crate::protocols::screencopy::{Screencopy, ScreencopyBuffer, ScreencopyManagerState};
+use crate::protocols::output_power_management::OutputPowerManagementState;
+use crate::protocols::screencopy::{
+    Screencopy, ScreencopyBuffer, ScreencopyManagerState, ScreencopySource,
+};
 use crate::protocols::virtual_pointer::VirtualPointerManagerState;
+use crate::protocols::xdg_dialog::XdgDialogState;
+use crate::protocols::xdg_toplevel_tag::XdgToplevelTagManagerState;
 use crate::pw_utils::{Cast, PipeWire};
 #[cfg(feature = "xdp-gnome-screencast")]
 use crate::pw_utils::{CastSizeChange, PwToNiri};
+use crate::render_helpers::boxed::BoxedRenderElement;
+use crate::render_helpers::color_transform::{
+    render_elements_from_surface_tree, ColorTransformRenderElement,
+};
+use crate::render_helpers::damage::ExtraDamage;
 use crate::render_helpers::debug::draw_opaque_regions;
+use crate::render_helpers::offscreen::{OffscreenBuffer, OffscreenData, OffscreenRenderElement};
 use crate::render_helpers::primary_gpu_texture::PrimaryGpuTextureRenderElement;
 use crate::render_helpers::renderer::NiriRenderer;
+use crate::render_helpers::shader_element::ShaderRenderElement;
+use crate::render_helpers::shaders::{mat3_uniform, ProgramType, Shaders};
 use crate::render_helpers::solid_color::{SolidColorBuffer, SolidColorRenderElement};
 use crate::render_helpers::texture::TextureBuffer;
 use crate::render_helpers::{
@@ -161,18 +186,25 @@ use crate::render_helpers::{
 use crate::ui::config_error_notification::ConfigErrorNotification;
 use crate::ui::exit_confirm_dialog::ExitConfirmDialog;
 use crate::ui::hotkey_overlay::HotkeyOverlay;
+use crate::ui::magnifier::Magnifier;
 use crate::ui::screen_transition::{self, ScreenTransition};
 use crate::ui::screenshot_ui::{OutputScreenshot, ScreenshotUi, ScreenshotUiRenderElement};
+use crate::utils::color::{
+    output_color_description, set_output_color_description, ColorDescription, IccProfileCache,
+};
 use crate::utils::scale::{closest_representable_scale, guess_monitor_scale};
 use crate::utils::spawning::{CHILD_DISPLAY, CHILD_ENV};
 use crate::utils::xwayland::satellite::Satellite;
 use crate::utils::{
     center, center_f64, expand_home, get_monotonic_time, ipc_transform_to_smithay, is_mapped,
     logical_output, make_screenshot_path, output_matches_name, output_size, send_scale_transform,
-    write_png_rgba8, xwayland,
+    toplevel_app_id, write_png_rgba8, xwayland,
+};
+use crate::window::mapped::{MappedId, WindowCastRenderElements};
+use crate::window::{
+    InitialConfigureState, Mapped, MatchContext, ResolvedWindowRules, SessionRestore, Unmapped,
+    WindowRef,
 };
-use crate::window::mapped::MappedId;
-use crate::window::{InitialConfigureState, Mapped, ResolvedWindowRules, Unmapped, WindowRef};
 
 const CLEAR_COLOR_LOCKED: [f32; 4] = [0.3, 0.1, 0.1, 1.];
 
@@ -226,6 +258,9 @@ pub struct Niri {
     // Windows which don't have a buffer attached yet.
     pub unmapped_windows: HashMap<WlSurface, Unmapped>,
 
+    /// Windows from a restored session that are waiting to open.
+    pub session_restore: SessionRestore,
+
     /// Layer surfaces which don't have a buffer attached yet.
     pub unmapped_layer_surfaces: HashSet<WlSurface>,
 
@@ -266,8 +301,10 @@ pub struct Niri {
     pub layer_shell_state: WlrLayerShellState,
     pub session_lock_state: SessionLockManagerState,
     pub foreign_toplevel_state: ForeignToplevelManagerState,
+    pub ext_foreign_toplevel_state: ExtForeignToplevelState,
     pub ext_workspace_state: ExtWorkspaceManagerState,
     pub screencopy_state: ScreencopyManagerState,
+    pub image_copy_capture_state: ImageCopyCaptureState,
     pub bevy_renderer: Option<BevyRenderer>,
     pub bevy_texture_cache: HashMap<Output, BevyTexture>,
     pub output_management_state: OutputManagementManagerState,
@@ -296,10 +333,17 @@ pub struct Niri {
     pub popups: PopupManager,
     pub popup_grab: Option<PopupGrabState>,
     pub presentation_state: PresentationState,
+    pub tearing_control_state: TearingControlState,
     pub security_context_state: SecurityContextState,
     pub gamma_control_manager_state: GammaControlManagerState,
+    pub output_power_management_state: OutputPowerManagementState,
+    pub color_management_state: ColorManagementState,
+    pub icc_profile_cache: IccProfileCache,
     pub activation_state: XdgActivationState,
     pub mutter_x11_interop_state: MutterX11InteropManagerState,
+    pub xdg_toplevel_tag_state: XdgToplevelTagManagerState,
+    pub xdg_dialog_state: XdgDialogState,
+    pub background_effect_state: BackgroundEffectState,
 
     // This will not work as is outside of tests, so it is gated with #[cfg(test)] for now. In
     // particular, shaders will need to learn about the single pixel buffer. Also, it must be
@@ -317,6 +361,13 @@ pub struct Niri {
     pub suppressed_buttons: HashSet<u32>,
     pub bind_cooldown_timers: HashMap<Key, RegistrationToken>,
     pub bind_repeat_timer: Option<RegistrationToken>,
+    /// Keyboard layouts remembered per app id for `track-layout "app"`.
+    pub app_keyboard_layouts: HashMap<String, KeyboardLayout>,
+    /// Name of the active binding mode, or `None` for the default mode.
+    pub bind_mode: Option<String>,
+    /// Keys of the multi-key bind sequence in progress.
+    pub bind_sequence: Vec<Key>,
+    pub bind_sequence_timer: Option<RegistrationToken>,
     pub keyboard_focus: KeyboardFocus,
     pub layer_shell_on_demand_focus: Option<LayerSurface>,
     pub previously_focused_window: Option<Window>,
@@ -357,9 +408,15 @@ pub struct Niri {
     /// Used for limiting the notify to once per iteration, so that it's not spammed with high
     /// resolution mice.
     pub notified_activity_this_iteration: bool,
-    pub pointer_inside_hot_corner: bool,
+    pub hot_corner: Option<HotCornerState>,
+    pub pointer_speed: PointerSpeed,
     pub tablet_cursor_location: Option<Point<f64, Logical>>,
-    pub gesture_swipe_3f_cumulative: Option<(f64, f64)>,
+    pub touchpad_swipe: Option<TouchpadSwipe>,
+    /// Ongoing touchpad pinch handled for binds, and whether a bind was already triggered.
+    pub touchpad_pinch: Option<bool>,
+    /// Finger count of the ongoing touchpad hold handled for binds.
+    pub touchpad_hold: Option<u8>,
+    pub touchscreen_swipe: TouchscreenSwipe,
     pub overview_scroll_swipe_gesture: ScrollSwipeGesture,
     pub vertical_wheel_tracker: ScrollTracker,
     pub horizontal_wheel_tracker: ScrollTracker,
@@ -443,6 +500,10 @@ pub struct OutputState {
     pub frame_clock: FrameClock,
     pub redraw_state: RedrawState,
     pub on_demand_vrr_enabled: bool,
+    /// Whether frames are presented with tearing.
+    ///
+    /// Set after a frame where the tearing surface was directly scanned out.
+    pub tearing: bool,
     // After the last redraw, some ongoing animations still remain.
     pub unfinished_animations_remain: bool,
     /// Last sequence received in a vblank event.
@@ -474,9 +535,22 @@ pub struct OutputState {
     pub lock_render_state: LockRenderState,
     pub lock_surface: Option<LockSurface>,
     pub lock_color_buffer: SolidColorBuffer,
+    /// Damages the whole output when its color description changes, since that changes how
+    /// every surface looks.
+    pub color_description_damage: ExtraDamage,
     screen_transition: Option<ScreenTransition>,
+    pub magnifier: Magnifier,
     /// Damage tracker used for the debug damage visualization.
     pub debug_damage_tracker: OutputDamageTracker,
+    /// Offscreens for the workspaces going through the custom workspace switch shader.
+    pub workspace_switch_buffers: [OffscreenBuffer; 2],
+    /// Offscreen for the custom post-processing shader.
+    pub post_process_buffer: OffscreenBuffer,
+    /// Offscreens that the last frame went through on its way to the output.
+    ///
+    /// Surfaces rendered into these don't show up in the output render element states directly,
+    /// so we need these to track their primary scanout output.
+    pub offscreen_data: RefCell<Vec<OffscreenData>>,
 }
 
 #[derive(Debug, Default)]
@@ -728,6 +802,7 @@ impl State {
         // These should be called periodically, before flushing the clients.
         self.niri.popups.cleanup();
         self.refresh_popup_grab();
+
         self.update_keyboard_focus();
 
         // Should be called before refresh_layout() because that one will refresh other window
@@ -743,6 +818,7 @@ impl State {
         self.niri.refresh_idle_inhibit();
         self.refresh_pointer_contents();
         foreign_toplevel::refresh(self);
+        ext_foreign_toplevel::refresh(self);
         ext_workspace::refresh(self);
 
         #[cfg(feature = "xdp-gnome-screencast")]
@@ -971,10 +1047,14 @@ impl State {
 
         let pointer = &self.niri.seat.get_pointer().unwrap();
         let location = pointer.current_location();
-        let mut under = match self.niri.pointer_visibility {
+        let contents_under = |niri: &Niri| match niri.pointer_visibility {
             PointerVisibility::Disabled => PointContents::default(),
-            _ => self.niri.contents_under(location),
+            _ => niri.contents_under(location),
         };
+        let mut under = contents_under(&self.niri);
+        if self.update_pip_interactive(&under) {
+            under = contents_under(&self.niri);
+        }
 
         // We're not changing the global cursor location here, so if the contents did not change,
         // then nothing changed.
@@ -1061,6 +1141,12 @@ impl State {
             }
         }
 
+        // Focusing a window with an open modal dialog focuses the dialog instead.
+        let focus = self.niri.layout.focus();
+        if focus.is_some_and(|mapped| mapped.is_blocked_by_modal()) {
+            self.niri.refresh_modal_dialogs();
+        }
+
         // Compute the current focus.
         let focus = if self.niri.is_locked() {
             KeyboardFocus::LockScreen {
@@ -1241,40 +1327,33 @@ impl State {
                 }
             }
 
-            if self.niri.config.borrow().input.keyboard.track_layout == TrackLayout::Window {
-                let current_layout = keyboard.with_xkb_state(self, |context| {
-                    let xkb = context.xkb().lock().unwrap();
-                    xkb.active_layout()
-                });
+            let track_layout = self.niri.config.borrow().input.keyboard.track_layout;
+            let current_layout = keyboard.with_xkb_state(self, |context| {
+                let xkb = context.xkb().lock().unwrap();
+                xkb.active_layout()
+            });
 
-                let mut new_layout = current_layout;
-                // Store the currently active layout for the surface.
-                if let Some(current_focus) = self.niri.keyboard_focus.surface() {
-                    with_states(current_focus, |data| {
-                        let cell = data
-                            .data_map
-                            .get_or_insert::<Cell<KeyboardLayout>, _>(Cell::default);
-                        cell.set(current_layout);
-                    });
+            // Store the currently active layout for the surface.
+            if track_layout != TrackLayout::Global {
+                if let Some(current_focus) = self.niri.keyboard_focus.surface().cloned() {
+                    self.remember_keyboard_layout(&current_focus, track_layout, current_layout);
                 }
+            }
 
-                if let Some(focus) = focus.surface() {
-                    new_layout = with_states(focus, |data| {
-                        let cell = data.data_map.get_or_insert::<Cell<KeyboardLayout>, _>(|| {
-                            // The default layout is effectively the first layout in the
-                            // keymap, so use it for new windows.
-                            Cell::new(KeyboardLayout::default())
-                        });
-                        cell.get()
-                    });
-                }
-                if new_layout != current_layout && focus.surface().is_some() {
-                    keyboard.set_focus(self, None, SERIAL_COUNTER.next_serial());
-                    keyboard.with_xkb_state(self, |mut context| {
-                        context.set_layout(new_layout);
-                    });
+            // With a global layout, focusing a window never changes the layout, so the
+            // default-keyboard-layout rule doesn't apply.
+            let mut new_layout = current_layout;
+            if let Some(focus) = focus.surface() {
+                if track_layout != TrackLayout::Global {
+                    new_layout = self.remembered_keyboard_layout(focus, track_layout);
                 }
             }
+            if new_layout != current_layout && focus.surface().is_some() {
+                keyboard.set_focus(self, None, SERIAL_COUNTER.next_serial());
+                keyboard.with_xkb_state(self, |mut context| {
+                    context.set_layout(new_layout);
+                });
+            }
 
             self.niri.keyboard_focus.clone_from(&focus);
             keyboard.set_focus(self, focus.into_surface(), SERIAL_COUNTER.next_serial());
@@ -1284,6 +1363,125 @@ impl State {
         }
     }
 
+    /// Finds a keyboard layout by its name in the xkb layout setting, like "us", or by its full
+    /// name, like "English (US)".
+    pub fn find_keyboard_layout(&mut self, name: &str) -> Option<KeyboardLayout> {
+        let config = self.niri.config.borrow();
+        let layouts = config.input.keyboard.xkb.layout.split(',');
+        let idx = layouts.map(str::trim).position(|layout| layout == name);
+        drop(config);
+
+        let keyboard = self.niri.seat.get_keyboard().unwrap();
+        keyboard.with_xkb_state(self, |context| {
+            let xkb = context.xkb().lock().unwrap();
+            let mut layouts = xkb.layouts();
+            match idx {
+                Some(idx) => layouts.nth(idx),
+                None => layouts.find(|layout| xkb.layout_name(*layout) == name),
+            }
+        })
+    }
+
+    /// Returns the layout from the default-keyboard-layout window rule of the surface.
+    fn default_keyboard_layout(&mut self, surface: &WlSurface) -> Option<KeyboardLayout> {
+        let (mapped, _) = self.niri.layout.find_window_and_output(surface)?;
+        let name = mapped.rules().default_keyboard_layout.clone()?;
+
+        let layout = self.find_keyboard_layout(&name);
+        if layout.is_none() {
+            warn!("default-keyboard-layout {name:?} doesn't match any configured layout");
+        }
+        layout
+    }
+
+    /// Sets up the remembered layout of a surface that was never focused, returning whether it
+    /// was missing.
+    ///
+    /// New windows start with their default-keyboard-layout. Otherwise, with per-app layouts they
+    /// start with the layout that their app used last, and finally with the first layout in the
+    /// keymap, which is effectively the default layout.
+    fn init_keyboard_layout(&mut self, surface: &WlSurface, track_layout: TrackLayout) -> bool {
+        let has_layout = with_states(surface, |data| {
+            data.data_map.get::<Cell<KeyboardLayout>>().is_some()
+        });
+        if has_layout {
+            return false;
+        }
+
+        let layout = self
+            .default_keyboard_layout(surface)
+            .or_else(|| self.app_keyboard_layout(surface, track_layout))
+            .unwrap_or_default();
+        with_states(surface, |data| {
+            data.data_map.insert_if_missing(|| Cell::new(layout))
+        })
+    }
+
+    fn app_keyboard_layout(
+        &self,
+        surface: &WlSurface,
+        track_layout: TrackLayout,
+    ) -> Option<KeyboardLayout> {
+        if track_layout != TrackLayout::App {
+            return None;
+        }
+
+        let app_id = toplevel_app_id(surface)?;
+        self.niri.app_keyboard_layouts.get(&app_id).copied()
+    }
+
+    fn remembered_keyboard_layout(
+        &mut self,
+        surface: &WlSurface,
+        track_layout: TrackLayout,
+    ) -> KeyboardLayout {
+        if !self.init_keyboard_layout(surface, track_layout) {
+            // Windows that were focused before follow the layout of their app.
+            if let Some(layout) = self.app_keyboard_layout(surface, track_layout) {
+                return layout;
+            }
+        }
+
+        with_states(surface, |data| {
+            data.data_map.get::<Cell<KeyboardLayout>>().unwrap().get()
+        })
+    }
+
+    fn remember_keyboard_layout(
+        &mut self,
+        surface: &WlSurface,
+        track_layout: TrackLayout,
+        layout: KeyboardLayout,
+    ) {
+        with_states(surface, |data| {
+            let cell = data
+                .data_map
+                .get_or_insert::<Cell<KeyboardLayout>, _>(Cell::default);
+            cell.set(layout);
+        });
+
+        if track_layout == TrackLayout::App {
+            if let Some(app_id) = toplevel_app_id(surface) {
+                self.niri.app_keyboard_layouts.insert(app_id, layout);
+            }
+        }
+    }
+
+    /// Switches the layout that an unfocused surface will use once it gets focus.
+    pub fn switch_layout_for_unfocused_surface(
+        &mut self,
+        surface: &WlSurface,
+        layout: KeyboardLayout,
+    ) {
+        let track_layout = self.niri.config.borrow().input.keyboard.track_layout;
+        if track_layout == TrackLayout::Global {
+            warn!("switching the layout of an unfocused window requires per-window layouts");
+            return;
+        }
+
+        self.remember_keyboard_layout(surface, track_layout, layout);
+    }
+
     /// Loads the xkb keymap from a file config setting.
     fn set_xkb_file(&mut self, xkb_file: String) -> anyhow::Result<()> {
         let xkb_file = PathBuf::from(xkb_file);
@@ -1359,6 +1557,7 @@ impl State {
         let mut window_rules_changed = false;
         let mut layer_rules_changed = false;
         let mut shaders_changed = false;
+        let mut shader_compile_failed = false;
         let mut cursor_inactivity_timeout_changed = false;
         let mut xwls_changed = false;
         let mut old_config = self.niri.config.borrow_mut();
@@ -1371,6 +1570,14 @@ impl State {
             self.niri.cursor_texture_cache.clear();
         }
 
+        if config.magnifier != old_config.magnifier
+            || config.animations.magnifier_zoom != old_config.animations.magnifier_zoom
+        {
+            for state in self.niri.output_state.values_mut() {
+                state.magnifier.update_config(&config);
+            }
+        }
+
         // We need &mut self to reload the xkb config, so just store it here.
         if config.input.keyboard.xkb != old_config.input.keyboard.xkb {
             reload_xkb = Some(config.input.keyboard.xkb.clone());
@@ -1406,14 +1613,19 @@ impl State {
         }
 
         let new_mod_key = self.backend.mod_key(&config);
-        if new_mod_key != self.backend.mod_key(&old_config) || config.binds != old_config.binds {
+        let mut bind_modes_changed = false;
+        if new_mod_key != self.backend.mod_key(&old_config)
+            || config.bind_modes != old_config.bind_modes
+        {
+            bind_modes_changed = true;
             self.niri
                 .hotkey_overlay
                 .on_hotkey_config_updated(new_mod_key);
-            self.niri.mods_with_mouse_binds = mods_with_mouse_binds(new_mod_key, &config.binds);
-            self.niri.mods_with_wheel_binds = mods_with_wheel_binds(new_mod_key, &config.binds);
+            let binds = config.active_binds(self.niri.bind_mode.as_deref());
+            self.niri.mods_with_mouse_binds = mods_with_mouse_binds(new_mod_key, binds);
+            self.niri.mods_with_wheel_binds = mods_with_wheel_binds(new_mod_key, binds);
             self.niri.mods_with_finger_scroll_binds =
-                mods_with_finger_scroll_binds(new_mod_key, &config.binds);
+                mods_with_finger_scroll_binds(new_mod_key, binds);
         }
 
         if config.window_rules != old_config.window_rules {
@@ -1428,9 +1640,10 @@ impl State {
             != old_config.animations.window_resize.custom_shader
         {
             let src = config.animations.window_resize.custom_shader.as_deref();
-            self.backend.with_primary_renderer(|renderer| {
-                shaders::set_custom_resize_program(renderer, src);
+            let compiled = self.backend.with_primary_renderer(|renderer| {
+                shaders::set_custom_resize_program(renderer, src)
             });
+            shader_compile_failed |= compiled == Some(false);
             shaders_changed = true;
         }
 
@@ -1438,9 +1651,10 @@ impl State {
             != old_config.animations.window_close.custom_shader
         {
             let src = config.animations.window_close.custom_shader.as_deref();
-            self.backend.with_primary_renderer(|renderer| {
-                shaders::set_custom_close_program(renderer, src);
-            });
+            let compiled = self
+                .backend
+                .with_primary_renderer(|renderer| shaders::set_custom_close_program(renderer, src));
+            shader_compile_failed |= compiled == Some(false);
             shaders_changed = true;
         }
 
@@ -1448,12 +1662,39 @@ impl State {
             != old_config.animations.window_open.custom_shader
         {
             let src = config.animations.window_open.custom_shader.as_deref();
-            self.backend.with_primary_renderer(|renderer| {
-                shaders::set_custom_open_program(renderer, src);
+            let compiled = self
+                .backend
+                .with_primary_renderer(|renderer| shaders::set_custom_open_program(renderer, src));
+            shader_compile_failed |= compiled == Some(false);
+            shaders_changed = true;
+        }
+
+        if config.animations.workspace_switch.custom_shader
+            != old_config.animations.workspace_switch.custom_shader
+        {
+            let src = config.animations.workspace_switch.custom_shader.as_deref();
+            let compiled = self.backend.with_primary_renderer(|renderer| {
+                shaders::set_custom_workspace_switch_program(renderer, src)
             });
+            shader_compile_failed |= compiled == Some(false);
             shaders_changed = true;
         }
 
+        let post_process_shaders = |c: &Config| {
+            c.outputs
+                .0
+                .iter()
+                .map(|o| (o.name.clone(), o.custom_shader.clone()))
+                .collect::<Vec<_>>()
+        };
+        if post_process_shaders(&config) != post_process_shaders(&old_config) {
+            let compiled = self.backend.with_primary_renderer(|renderer| {
+                shaders::set_custom_post_process_programs(renderer, &config.outputs)
+            });
+            shader_compile_failed |= compiled == Some(false);
+            output_config_changed = true;
+        }
+
         if config.cursor.hide_after_inactive_ms != old_config.cursor.hide_after_inactive_ms {
             cursor_inactivity_timeout_changed = true;
         }
@@ -1535,10 +1776,24 @@ impl State {
             self.niri.recompute_layer_rules();
         }
 
+        if bind_modes_changed {
+            // The active binding mode may have been removed from the config.
+            let mode = self.niri.bind_mode.as_deref();
+            if self.niri.config.borrow().mode_binds(mode).is_none() {
+                self.set_bind_mode(None);
+            }
+
+            self.niri.bind_sequence.clear();
+        }
+
         if shaders_changed {
             self.niri.update_shaders();
         }
 
+        if shader_compile_failed {
+            self.niri.config_error_notification.show_shader_error();
+        }
+
         if cursor_inactivity_timeout_changed {
             // Force reset due to timeout change.
             self.niri.pointer_inactivity_timer_got_reset = false;
@@ -1580,6 +1835,7 @@ impl State {
     pub fn reload_output_config(&mut self) {
         let mut resized_outputs = vec![];
         let mut recolored_outputs = vec![];
+        let mut color_managed_outputs = vec![];
 
         for output in self.niri.global_space.outputs() {
             let name = output.user_data().get::<OutputName>().unwrap();
@@ -1630,6 +1886,14 @@ impl State {
             backdrop_color[3] = 1.;
             let backdrop_color = Color32F::from(backdrop_color);
 
+            let color_description = self
+                .niri
+                .icc_profile_cache
+                .load(config.and_then(|c| c.icc_profile.as_deref()));
+            if set_output_color_description(output, color_description) {
+                color_managed_outputs.push(output.clone());
+            }
+
             if let Some(state) = self.niri.output_state.get_mut(output) {
                 if state.background_buffer.color() != background_color {
                     state.background_buffer.set_color(background_color);
@@ -1650,6 +1914,16 @@ impl State {
             self.niri.queue_redraw(&output);
         }
 
+        for output in color_managed_outputs {
+            self.niri
+                .color_management_state
+                .output_description_changed(&output);
+            if let Some(state) = self.niri.output_state.get_mut(&output) {
+                state.color_description_damage.damage_all();
+            }
+            self.niri.queue_redraw(&output);
+        }
+
         self.backend.on_output_config_changed(&mut self.niri);
 
         self.niri.reposition_outputs(None);
@@ -1705,24 +1979,48 @@ impl State {
         fun(config);
     }
 
+    pub fn set_output_powered(&mut self, output: &Output, powered: bool) {
+        self.backend
+            .set_output_powered(&mut self.niri, output, powered);
+
+        // Report the actual state, since not every backend can power off outputs.
+        let powered = self.backend.is_output_powered(output);
+        self.niri
+            .output_power_management_state
+            .output_power_changed(output, powered);
+
+        if powered {
+            self.niri.queue_redraw(output);
+        }
+    }
+
     pub fn apply_transient_output_config(&mut self, name: &str, action: niri_ipc::OutputAction) {
-        self.modify_output_config(name, move |config| match action {
-            niri_ipc::OutputAction::Off => config.off = true,
-            niri_ipc::OutputAction::On => config.off = false,
-            niri_ipc::OutputAction::Mode { mode } => {
+        let update: Box<dyn FnOnce(&mut niri_config::Output)> = match action {
+            // Power is not part of the output config, it only lasts until the output disconnects.
+            niri_ipc::OutputAction::Power { power } => {
+                if let Some(output) = self.niri.output_by_name_match(name).cloned() {
+                    self.set_output_powered(&output, power == niri_ipc::OutputPower::On);
+                }
+                return;
+            }
+            niri_ipc::OutputAction::Off => Box::new(|config| config.off = true),
+            niri_ipc::OutputAction::On => Box::new(|config| config.off = false),
+            niri_ipc::OutputAction::Mode { mode } => Box::new(move |config| {
                 config.mode = match mode {
                     niri_ipc::ModeToSet::Automatic => None,
                     niri_ipc::ModeToSet::Specific(mode) => Some(mode),
                 }
-            }
-            niri_ipc::OutputAction::Scale { scale } => {
+            }),
+            niri_ipc::OutputAction::Scale { scale } => Box::new(move |config| {
                 config.scale = match scale {
                     niri_ipc::ScaleToSet::Automatic => None,
                     niri_ipc::ScaleToSet::Specific(scale) => Some(FloatOrInt(scale)),
                 }
+            }),
+            niri_ipc::OutputAction::Transform { transform } => {
+                Box::new(move |config| config.transform = transform)
             }
-            niri_ipc::OutputAction::Transform { transform } => config.transform = transform,
-            niri_ipc::OutputAction::Position { position } => {
+            niri_ipc::OutputAction::Position { position } => Box::new(move |config| {
                 config.position = match position {
                     niri_ipc::PositionToSet::Automatic => None,
                     niri_ipc::PositionToSet::Specific(position) => Some(niri_config::Position {
@@ -1730,8 +2028,8 @@ impl State {
                         y: position.y,
                     }),
                 }
-            }
-            niri_ipc::OutputAction::Vrr { vrr } => {
+            }),
+            niri_ipc::OutputAction::Vrr { vrr } => Box::new(move |config| {
                 config.variable_refresh_rate = if vrr.vrr {
                     Some(niri_config::Vrr {
                         on_demand: vrr.on_demand,
@@ -1739,12 +2037,20 @@ impl State {
                 } else {
                     None
                 }
-            }
-        });
+            }),
+        };
 
+        self.modify_output_config(name, update);
         self.reload_output_config();
     }
 
+    /// Renders pending ext-image-copy-capture frames of windows that aren't on any output.
+    pub fn render_off_output_image_copy_captures(&mut self) {
+        self.backend.with_primary_renderer(|renderer| {
+            self.niri.render_for_image_copy_capture(renderer, None);
+        });
+    }
+
     pub fn refresh_ipc_outputs(&mut self) {
         if !self.niri.ipc_outputs_changed {
             return;
@@ -2367,6 +2673,7 @@ impl Niri {
         );
         let presentation_state =
             PresentationState::new::<State>(&display_handle, Monotonic::ID as u32);
+        let tearing_control_state = TearingControlState::new::<State>(&display_handle);
         let security_context_state =
             SecurityContextState::new::<State, _>(&display_handle, client_is_unrestricted);
 
@@ -2388,6 +2695,10 @@ impl Niri {
         output_management_state.on_config_changed(config_.outputs.clone());
         let screencopy_state =
             ScreencopyManagerState::new::<State, _>(&display_handle, client_is_unrestricted);
+        let ext_foreign_toplevel_state =
+            ExtForeignToplevelState::new(&display_handle, client_is_unrestricted);
+        let image_copy_capture_state =
+            ImageCopyCaptureState::new::<State, _>(&display_handle, client_is_unrestricted);
 
         let bevy_renderer = match BevyRenderer::new() {
             Ok(mut renderer) => {
@@ -2414,6 +2725,14 @@ impl Niri {
             GammaControlManagerState::new::<State, _>(&display_handle, move |client| {
                 is_tty && !client.get_data::<ClientState>().unwrap().restricted
             });
+        let output_power_management_state =
+            OutputPowerManagementState::new::<State, _>(&display_handle, |client| {
+                !client.get_data::<ClientState>().unwrap().restricted
+            });
+        let color_management_state =
+            ColorManagementState::new::<State, _>(&display_handle, |client| {
+                !client.get_data::<ClientState>().unwrap().restricted
+            });
         let activation_state = XdgActivationState::new::<State>(&display_handle);
         event_loop
             .insert_source(
@@ -2430,6 +2749,10 @@ impl Niri {
         let mutter_x11_interop_state =
             MutterX11InteropManagerState::new::<State, _>(&display_handle, move |_| true);
 
+        let xdg_toplevel_tag_state = XdgToplevelTagManagerState::new::<State>(&display_handle);
+        let xdg_dialog_state = XdgDialogState::new::<State>(&display_handle);
+        let background_effect_state = BackgroundEffectState::new::<State>(&display_handle);
+
         #[cfg(test)]
         let single_pixel_buffer_state = SinglePixelBufferState::new::<State>(&display_handle);
 
@@ -2466,9 +2789,9 @@ impl Niri {
             CursorManager::new(&config_.cursor.xcursor_theme, config_.cursor.xcursor_size);
 
         let mod_key = backend.mod_key(&config.borrow());
-        let mods_with_mouse_binds = mods_with_mouse_binds(mod_key, &config_.binds);
-        let mods_with_wheel_binds = mods_with_wheel_binds(mod_key, &config_.binds);
-        let mods_with_finger_scroll_binds = mods_with_finger_scroll_binds(mod_key, &config_.binds);
+        let mods_with_mouse_binds = mods_with_mouse_binds(mod_key, config_.binds());
+        let mods_with_wheel_binds = mods_with_wheel_binds(mod_key, config_.binds());
+        let mods_with_finger_scroll_binds = mods_with_finger_scroll_binds(mod_key, config_.binds());
 
         let screenshot_ui = ScreenshotUi::new(animation_clock.clone(), config.clone());
         let config_error_notification =
@@ -2576,6 +2899,7 @@ impl Niri {
             sorted_outputs: Vec::default(),
             output_state: HashMap::new(),
             unmapped_windows: HashMap::new(),
+            session_restore: SessionRestore::default(),
             unmapped_layer_surfaces: HashSet::new(),
             mapped_layer_surfaces: HashMap::new(),
             root_surface: HashMap::new(),
@@ -2596,9 +2920,11 @@ impl Niri {
             layer_shell_state,
             session_lock_state,
             foreign_toplevel_state,
+            ext_foreign_toplevel_state,
             ext_workspace_state,
             output_management_state,
             screencopy_state,
+            image_copy_capture_state,
             bevy_renderer,
             bevy_texture_cache,
             viewporter_state,
@@ -2629,11 +2955,22 @@ impl Niri {
             suppressed_buttons: HashSet::new(),
             bind_cooldown_timers: HashMap::new(),
             bind_repeat_timer: Option::default(),
+            app_keyboard_layouts: HashMap::new(),
+            bind_mode: None,
+            bind_sequence: Vec::new(),
+            bind_sequence_timer: None,
             presentation_state,
+            tearing_control_state,
             security_context_state,
             gamma_control_manager_state,
+            output_power_management_state,
+            color_management_state,
+            icc_profile_cache: IccProfileCache::default(),
             activation_state,
             mutter_x11_interop_state,
+            xdg_toplevel_tag_state,
+            xdg_dialog_state,
+            background_effect_state,
             #[cfg(test)]
             single_pixel_buffer_state,
 
@@ -2654,9 +2991,13 @@ impl Niri {
             pointer_inactivity_timer: None,
             pointer_inactivity_timer_got_reset: false,
             notified_activity_this_iteration: false,
-            pointer_inside_hot_corner: false,
+            hot_corner: None,
+            pointer_speed: PointerSpeed::default(),
             tablet_cursor_location: None,
-            gesture_swipe_3f_cumulative: None,
+            touchpad_swipe: None,
+            touchpad_pinch: None,
+            touchpad_hold: None,
+            touchscreen_swipe: TouchscreenSwipe::default(),
             overview_scroll_swipe_gesture: ScrollSwipeGesture::new(),
             vertical_wheel_tracker: ScrollTracker::new(120),
             horizontal_wheel_tracker: ScrollTracker::new(120),
@@ -2912,12 +3253,18 @@ impl Niri {
             .to_array_unpremul();
         backdrop_color[3] = 1.;
 
+        let color_description = self
+            .icc_profile_cache
+            .load(c.and_then(|c| c.icc_profile.as_deref()));
+
         // FIXME: fix winit damage on other transforms.
         if name.connector == "winit" {
             transform = Transform::Flipped180;
         }
         drop(config);
 
+        set_output_color_description(&output, color_description);
+
         // Set scale and transform before adding to the layout since that will read the output size.
         output.change_current_state(
             None,
@@ -2940,6 +3287,7 @@ impl Niri {
             global,
             redraw_state: RedrawState::Idle,
             on_demand_vrr_enabled: false,
+            tearing: false,
             unfinished_animations_remain: false,
             frame_clock: FrameClock::new(refresh_interval, vrr),
             last_drm_sequence: None,
@@ -2949,8 +3297,17 @@ impl Niri {
             lock_render_state,
             lock_surface: None,
             lock_color_buffer: SolidColorBuffer::new(size, CLEAR_COLOR_LOCKED),
+            color_description_damage: {
+                let mut damage = ExtraDamage::new();
+                damage.set_size(size);
+                damage
+            },
             screen_transition: None,
+            magnifier: Magnifier::new(self.clock.clone(), &self.config.borrow()),
             debug_damage_tracker: OutputDamageTracker::from_output(&output),
+            workspace_switch_buffers: Default::default(),
+            post_process_buffer: OffscreenBuffer::default(),
+            offscreen_data: RefCell::new(Vec::new()),
         };
         let rv = self.output_state.insert(output.clone(), state);
         assert!(rv.is_none(), "output was already tracked");
@@ -2968,6 +3325,8 @@ impl Niri {
         self.global_space.unmap_output(output);
         self.reposition_outputs(None);
         self.gamma_control_manager_state.output_removed(output);
+        self.output_power_management_state.output_removed(output);
+        self.color_management_state.output_removed(output);
 
         let state = self.output_state.remove(output).unwrap();
 
@@ -3062,6 +3421,7 @@ impl Niri {
             state.backdrop_buffer.resize(output_size);
 
             state.lock_color_buffer.resize(output_size);
+            state.color_description_damage.set_size(output_size);
             if let Some(lock_surface) = &state.lock_surface {
                 configure_lock_surface(lock_surface, output);
             }
@@ -3163,12 +3523,8 @@ impl Niri {
             return false;
         }
 
-        let hot_corners = self.config.borrow().gestures.hot_corners;
-        if !hot_corners.off {
-            let hot_corner = Rectangle::from_size(Size::from((1., 1.)));
-            if hot_corner.contains(pos_within_output) {
-                return true;
-            }
+        if self.is_hot_corner_under(output, pos_within_output) {
+            return true;
         }
 
         if layer_popup_under(Layer::Top) || layer_toplevel_under(Layer::Top) {
@@ -3178,6 +3534,64 @@ impl Niri {
         false
     }
 
+    /// Returns the active hot corner or edge at the position within the output.
+    fn hot_corner_at(
+        &self,
+        output: &Output,
+        pos_within_output: Point<f64, Logical>,
+    ) -> Option<(HotCorner, HotCornerAction)> {
+        let corner = hot_corners::corner_at(output_size(output), pos_within_output)?;
+
+        // Edges shared with another output would trigger when moving between the outputs.
+        if let Some(normal) = hot_corners::edge_normal(corner) {
+            let output_loc = self.global_space.output_geometry(output).unwrap().loc;
+            let outside = pos_within_output + output_loc.to_f64() + normal;
+            if self.global_space.output_under(outside).next().is_some() {
+                return None;
+            }
+        }
+
+        let config = self.config.borrow();
+        let name = output.user_data().get::<OutputName>().unwrap();
+        let hot_corners = config
+            .outputs
+            .find(name)
+            .and_then(|c| c.hot_corners.as_ref())
+            .unwrap_or(&config.gestures.hot_corners);
+        let action = hot_corners.action(corner)?;
+
+        Some((corner, action))
+    }
+
+    /// Returns whether a hot corner takes the pointer away from the contents under it.
+    ///
+    /// Edges leave the contents alone so that they remain usable along the whole output edge.
+    fn is_hot_corner_under(&self, output: &Output, pos_within_output: Point<f64, Logical>) -> bool {
+        self.hot_corner_at(output, pos_within_output)
+            .is_some_and(|(corner, _)| hot_corners::edge_normal(corner).is_none())
+    }
+
+    /// Returns the active hot corner or edge under the position.
+    pub fn hot_corner_under(
+        &self,
+        pos: Point<f64, Logical>,
+    ) -> Option<(Output, HotCorner, HotCornerAction)> {
+        if self.screenshot_ui.is_open() {
+            return None;
+        }
+
+        let (output, pos_within_output) = self.output_under(pos)?;
+
+        // Hot corners don't work over fullscreen windows.
+        let mon = self.layout.monitor_for_output(output)?;
+        if mon.render_above_top_layer() {
+            return None;
+        }
+
+        let (corner, action) = self.hot_corner_at(output, pos_within_output)?;
+        Some((output.clone(), corner, action))
+    }
+
     pub fn is_layout_obscured_under(
         &self,
         output: &Output,
@@ -3438,12 +3852,8 @@ impl Niri {
                 .or_else(|| layer_toplevel_under(Layer::Bottom))
                 .or_else(|| layer_toplevel_under(Layer::Background));
         } else {
-            let hot_corners = self.config.borrow().gestures.hot_corners;
-            if !hot_corners.off {
-                let hot_corner = Rectangle::from_size(Size::from((1., 1.)));
-                if hot_corner.contains(pos_within_output) {
-                    return rv;
-                }
+            if self.is_hot_corner_under(output, pos_within_output) {
+                return rv;
             }
 
             under = under
@@ -3720,6 +4130,7 @@ impl Niri {
         let render_cursor = self.cursor_manager.get_render_cursor(cursor_scale);
 
         let output_scale = Scale::from(output.current_scale().fractional_scale());
+        let color_target = output_color_description(output);
 
         let mut pointer_elements = match render_cursor {
             RenderCursor::Hidden => vec![],
@@ -3734,6 +4145,7 @@ impl Niri {
                     output_scale,
                     1.,
                     Kind::Cursor,
+                    &color_target,
                 )
             }
             RenderCursor::Named {
@@ -3781,6 +4193,7 @@ impl Niri {
                 output_scale,
                 1.,
                 Kind::Unspecified,
+                &color_target,
             ));
         }
 
@@ -3975,16 +4388,68 @@ impl Niri {
         drop(config);
     }
 
+    /// Keeps modal dialogs together with their parent windows.
+    ///
+    /// Modal dialogs follow their parent across workspaces and monitors, and take the focus from
+    /// it. The parent is dimmed while the dialog is open.
+    ///
+    /// Should be called when a dialog maps, unmaps, changes its parent or its modal state, and
+    /// when a window with an open dialog gets focused.
+    pub fn refresh_modal_dialogs(&mut self) {
+        let _span = tracy_client::span!("Niri::refresh_modal_dialogs");
+
+        let mut modals = Vec::new();
+        for (_, mapped) in self.layout.windows() {
+            if !mapped.is_modal() {
+                continue;
+            }
+
+            let Some(parent) = mapped.toplevel().parent() else {
+                continue;
+            };
+            if let Some((parent, _)) = self.layout.find_window_and_output(&parent) {
+                modals.push((mapped.window.clone(), parent.window.clone()));
+            }
+        }
+
+        for (modal, parent) in &modals {
+            self.layout.move_to_workspace_of(modal, parent);
+        }
+
+        // Focusing the parent focuses its dialog instead.
+        let focus = self.layout.focus().map(|mapped| mapped.window.clone());
+        let focused_modal = modals
+            .iter()
+            .find(|(_, parent)| Some(parent) == focus.as_ref());
+        if let Some((modal, _)) = focused_modal {
+            self.layout.activate_window(modal);
+        }
+
+        let mut outputs = HashSet::new();
+        self.layout.with_windows_mut(|mapped, output| {
+            let is_blocked = modals.iter().any(|(_, parent)| *parent == mapped.window);
+            if mapped.set_blocked_by_modal(is_blocked) {
+                if let Some(output) = output {
+                    outputs.insert(output.clone());
+                }
+            }
+        });
+        for output in outputs {
+            self.queue_redraw(&output);
+        }
+    }
+
     pub fn refresh_window_rules(&mut self) {
         let _span = tracy_client::span!("Niri::refresh_window_rules");
 
         let config = self.config.borrow();
         let window_rules = &config.window_rules;
 
+        let ctx = self.window_match_context();
         let mut windows = vec![];
         let mut outputs = HashSet::new();
         self.layout.with_windows_mut(|mapped, output| {
-            if mapped.recompute_window_rules_if_needed(window_rules, self.is_at_startup) {
+            if mapped.recompute_window_rules_if_needed(window_rules, ctx) {
                 windows.push(mapped.window.clone());
 
                 if let Some(output) = output {
@@ -4083,12 +4548,18 @@ impl Niri {
                     state.screen_transition = None;
                 }
             }
+
+            state.magnifier.advance_animations();
         }
     }
 
     pub fn update_render_elements(&mut self, output: Option<&Output>) {
         self.layout.update_render_elements(output);
 
+        let pointer_pos = self
+            .tablet_cursor_location
+            .unwrap_or_else(|| self.seat.get_pointer().unwrap().current_location());
+
         for (out, state) in self.output_state.iter_mut() {
             if output.map_or(true, |output| out == output) {
                 let scale = Scale::from(out.current_scale().fractional_scale());
@@ -4098,6 +4569,12 @@ impl Niri {
                     transition.update_render_elements(scale, transform);
                 }
 
+                let output_geo = self.global_space.output_geometry(out).unwrap().to_f64();
+                let pointer = output_geo
+                    .contains(pointer_pos)
+                    .then_some(pointer_pos - output_geo.loc);
+                state.magnifier.update(output_geo.size, pointer);
+
                 let layer_map = layer_map_for_output(out);
                 for surface in layer_map.layers() {
                     let Some(mapped) = self.mapped_layer_surfaces.get_mut(surface) else {
@@ -4174,10 +4651,11 @@ impl Niri {
                 elements.extend(render_elements_from_surface_tree(
                     renderer,
                     surface.wl_surface(),
-                    (0, 0),
+                    Point::from((0., 0.)),
                     output_scale,
                     1.,
                     Kind::Unspecified,
+                    &output_color_description(output),
                 ));
             }
 
@@ -4191,6 +4669,7 @@ impl Niri {
                 )
                 .into(),
             );
+            elements.push(state.color_description_damage.clone().into());
 
             if self.debug_draw_opaque_regions {
                 draw_opaque_regions(&mut elements, output_scale);
@@ -4226,6 +4705,7 @@ impl Niri {
 
             // Add the backdrop for outputs that were connected while the screenshot UI was open.
             elements.push(backdrop);
+            elements.push(state.color_description_damage.clone().into());
 
             if self.debug_draw_opaque_regions {
                 draw_opaque_regions(&mut elements, output_scale);
@@ -4242,13 +4722,23 @@ impl Niri {
         // workspaces, since the interactively-moved window already has a focus ring.
         let focus_ring = !self.layout.interactive_move_is_moving_above_output(output);
 
+        // During a workspace switch, the workspaces may go through the custom shader.
+        let workspace_switch = self.render_workspace_switch(renderer, output, target, focus_ring);
+
         // Get monitor elements.
         let mon = self.layout.monitor_for_output(output).unwrap();
         let zoom = mon.overview_zoom();
-        let monitor_elements = Vec::from_iter(
-            mon.render_elements(renderer, target, focus_ring)
-                .map(|(geo, iter)| (geo, Vec::from_iter(iter))),
-        );
+        let monitor_elements = if workspace_switch.is_some() {
+            Vec::new()
+        } else {
+            Vec::from_iter(
+                mon.render_elements(renderer, target, focus_ring)
+                    .map(|(geo, iter)| (geo, Vec::from_iter(iter))),
+            )
+        };
+        // Sticky windows are drawn above all workspaces, including during a workspace switch.
+        let sticky_elements =
+            Vec::from_iter(mon.render_sticky_elements(renderer, target, focus_ring));
         let workspace_shadow_elements = Vec::from_iter(mon.render_workspace_shadows(renderer));
         let insert_hint_elements = mon.render_insert_hint_between_workspaces(renderer);
         let int_move_elements: Vec<_> = self
@@ -4258,9 +4748,18 @@ impl Niri {
 
         // Get layer-shell elements.
         let layer_map = layer_map_for_output(output);
+        let color_target = output_color_description(output);
         let mut extend_from_layer =
             |elements: &mut SplitElements<LayerSurfaceRenderElement<R>>, layer, for_backdrop| {
-                self.render_layer(renderer, target, &layer_map, layer, elements, for_backdrop);
+                self.render_layer(
+                    renderer,
+                    target,
+                    &layer_map,
+                    layer,
+                    elements,
+                    for_backdrop,
+                    &color_target,
+                );
             };
 
         // The overlay layer elements go next.
@@ -4286,6 +4785,7 @@ impl Niri {
                     .into_iter()
                     .map(OutputRenderElements::from),
             );
+            elements.extend(sticky_elements.into_iter().map(OutputRenderElements::from));
             elements.extend(
                 insert_hint_elements
                     .into_iter()
@@ -4316,6 +4816,7 @@ impl Niri {
                     .into_iter()
                     .map(OutputRenderElements::from),
             );
+            elements.extend(sticky_elements.into_iter().map(OutputRenderElements::from));
 
             elements.extend(
                 insert_hint_elements
@@ -4323,6 +4824,10 @@ impl Niri {
                     .map(OutputRenderElements::from),
             );
 
+            if let Some(elem) = workspace_switch {
+                elements.push(OutputRenderElements::from(elem));
+            }
+
             for (ws_geo, ws_elements) in monitor_elements {
                 // Collect all other layer-shell elements.
                 let mut layer_elems = SplitElements::default();
@@ -4367,6 +4872,7 @@ impl Niri {
         elements.extend(layer_elems.into_iter().map(OutputRenderElements::from));
 
         elements.push(backdrop);
+        elements.push(state.color_description_damage.clone().into());
 
         if self.debug_draw_opaque_regions {
             draw_opaque_regions(&mut elements, output_scale);
@@ -4375,41 +4881,335 @@ impl Niri {
         elements
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn render_layer<R: NiriRenderer>(
+    /// Renders the output contents for presenting them on the output itself.
+    ///
+    /// On top of [`Niri::render()`], this applies the magnifier and the output's custom
+    /// post-processing shader.
+    pub fn render_output<R: NiriRenderer>(
         &self,
         renderer: &mut R,
-        target: RenderTarget,
-        layer_map: &LayerMap,
-        layer: Layer,
-        elements: &mut SplitElements<LayerSurfaceRenderElement<R>>,
-        for_backdrop: bool,
+        output: &Output,
+    ) -> Vec<OutputRenderElements<R>> {
+        let state = self.output_state.get(output).unwrap();
+        state.offscreen_data.borrow_mut().clear();
+
+        if let Some(elem) = self.render_post_processed(renderer, output) {
+            // Keep the pointer itself out of the post-processing, but zoom it along with the
+            // contents so that it stays in place.
+            let mut elements = self.pointer_element(renderer, output);
+            self.magnify(output, &mut elements);
+            elements.push(OutputRenderElements::from(elem));
+            return elements;
+        }
+
+        let mut elements = self.render(renderer, output, true, RenderTarget::Output);
+        self.magnify(output, &mut elements);
+        self.update_blur_damage(output, &elements);
+        elements
+    }
+
+    /// Lets the background blurs on the output check whether the contents below them changed.
+    fn update_blur_damage<R: NiriRenderer>(
+        &self,
+        output: &Output,
+        elements: &[OutputRenderElements<R>],
     ) {
-        // LayerMap returns layers in reverse stacking order.
-        let iter = layer_map.layers_on(layer).rev().filter_map(|surface| {
-            let mapped = self.mapped_layer_surfaces.get(surface)?;
+        let _span = tracy_client::span!("Niri::update_blur_damage");
 
-            if for_backdrop != mapped.place_within_backdrop() {
-                return None;
-            }
+        for mapped in self.layout.windows_for_output(output) {
+            mapped.blur().update_damage(output, elements);
+        }
 
-            let geo = layer_map.layer_geometry(surface)?;
-            Some((mapped, geo))
-        });
-        for (mapped, geo) in iter {
-            elements.extend(mapped.render(renderer, geo.loc.to_f64(), target));
+        for surface in layer_map_for_output(output).layers() {
+            if let Some(mapped) = self.mapped_layer_surfaces.get(surface) {
+                mapped.blur().update_damage(output, elements);
+            }
         }
     }
 
-    fn redraw(&mut self, backend: &mut Backend, output: &Output) {
-        let _span = tracy_client::span!("Niri::redraw");
+    fn render_post_processed<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+    ) -> Option<ShaderRenderElement> {
+        let key = {
+            let name = output.user_data().get::<OutputName>().unwrap();
+            let config = self.config.borrow();
+            let section = config.outputs.find(name)?;
+            section.custom_shader.as_ref()?;
+            section.name.clone()
+        };
+        Shaders::get(renderer).post_process_program(&key)?;
 
-        // Verify our invariant.
-        let state = self.output_state.get_mut(output).unwrap();
-        assert!(matches!(
-            state.redraw_state,
-            RedrawState::Queued | RedrawState::WaitingForEstimatedVBlankAndQueued(_)
-        ));
+        let _span = tracy_client::span!("Niri::render_post_processed");
+
+        let renderer = renderer.as_gles_renderer();
+        let mut elements = self.render(renderer, output, false, RenderTarget::Output);
+        self.magnify(output, &mut elements);
+        self.update_blur_damage(output, &elements);
+
+        let state = self.output_state.get(output).unwrap();
+        let scale = Scale::from(output.current_scale().fractional_scale());
+        let (elem, _sync_point, mut data) =
+            match state.post_process_buffer.render(renderer, scale, &elements) {
+                Ok(rv) => rv,
+                Err(err) => {
+                    warn!("error rendering output to offscreen for post-processing: {err:?}");
+                    return None;
+                }
+            };
+
+        let output_size = output_size(output);
+        let output_size_vec = Vec2::new(output_size.w as f32, output_size.h as f32);
+        let geo_to_tex = offscreen_geo_to_tex(&elem, Point::from((0., 0.)), output_size, scale);
+
+        let elem = ShaderRenderElement::new(
+            ProgramType::PostProcess,
+            output_size,
+            None,
+            scale.x as f32,
+            1.,
+            vec![
+                Uniform::new("niri_output_size", output_size_vec.to_array()),
+                Uniform::new("niri_cursor_position", self.cursor_position_on(output)),
+                mat3_uniform("niri_geo_to_tex", geo_to_tex),
+            ],
+            HashMap::from([(String::from("niri_tex"), elem.texture().clone())]),
+            Kind::Unspecified,
+        )
+        .with_program_key(key);
+
+        // We're drawing the shader, not the offscreen itself.
+        data.id = elem.id().clone();
+        state.offscreen_data.borrow_mut().push(data);
+
+        Some(elem)
+    }
+
+    /// Renders the two workspaces visible during a switch through the custom shader.
+    ///
+    /// The shader blends exactly two workspaces. When the switch has only one workspace in view,
+    /// such as right at its start and end, or more of them, such as in the overview, the
+    /// workspaces are rendered as usual.
+    ///
+    /// Returns `None` if the workspaces should be rendered as usual.
+    fn render_workspace_switch<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        output: &Output,
+        target: RenderTarget,
+        focus_ring: bool,
+    ) -> Option<ShaderRenderElement> {
+        let mon = self.layout.monitor_for_output(output)?;
+        if !mon.is_switching_workspaces() {
+            return None;
+        }
+
+        Shaders::get(renderer).program(ProgramType::WorkspaceSwitch)?;
+
+        let _span = tracy_client::span!("Niri::render_workspace_switch");
+
+        let renderer = renderer.as_gles_renderer();
+        let mut workspaces = Vec::from_iter(
+            mon.render_elements(renderer, target, focus_ring)
+                .map(|(geo, iter)| (geo, Vec::from_iter(iter))),
+        );
+        // The shader needs a workspace on either side. Outside the overview, workspaces are as
+        // tall as the output, so there are never more than two in view.
+        if workspaces.len() != 2 {
+            return None;
+        }
+        workspaces.sort_by(|(a, _), (b, _)| a.loc.y.total_cmp(&b.loc.y));
+
+        let state = self.output_state.get(output).unwrap();
+        let scale = Scale::from(output.current_scale().fractional_scale());
+        let zoom = mon.overview_zoom();
+        let output_size = output_size(output);
+        let background = SolidColorRenderElement::from_buffer(
+            &state.background_buffer,
+            (0., 0.),
+            1.,
+            Kind::Unspecified,
+        );
+        let layer_map = layer_map_for_output(output);
+        let color_target = output_color_description(output);
+
+        let mut textures = Vec::new();
+        for ((ws_geo, ws_elements), buffer) in zip(workspaces, &state.workspace_switch_buffers) {
+            let mut layer_elems = SplitElements::default();
+            for layer in [Layer::Bottom, Layer::Background] {
+                self.render_layer(
+                    renderer,
+                    target,
+                    &layer_map,
+                    layer,
+                    &mut layer_elems,
+                    false,
+                    &color_target,
+                );
+            }
+
+            let mut elements: Vec<OutputRenderElements<GlesRenderer>> = Vec::new();
+            elements.extend(
+                layer_elems
+                    .popups
+                    .into_iter()
+                    .filter_map(|elem| scale_relocate_crop(elem, scale, zoom, ws_geo))
+                    .map(OutputRenderElements::from),
+            );
+            elements.extend(ws_elements.into_iter().map(OutputRenderElements::from));
+            elements.extend(
+                layer_elems
+                    .normal
+                    .into_iter()
+                    .filter_map(|elem| scale_relocate_crop(elem, scale, zoom, ws_geo))
+                    .map(OutputRenderElements::from),
+            );
+            elements.extend(
+                scale_relocate_crop(background.clone(), scale, zoom, ws_geo)
+                    .map(OutputRenderElements::from),
+            );
+
+            // Blurs inside the offscreen never show up in the output elements.
+            if target == RenderTarget::Output {
+                self.update_blur_damage(output, &elements);
+            }
+
+            match buffer.render(renderer, scale, &elements) {
+                Ok((elem, _sync_point, data)) => {
+                    let geo_to_tex = offscreen_geo_to_tex(&elem, ws_geo.loc, output_size, scale);
+                    textures.push((ws_geo, elem.texture().clone(), geo_to_tex, data));
+                }
+                Err(err) => {
+                    warn!("error rendering workspace to offscreen for the switch shader: {err:?}");
+                    return None;
+                }
+            }
+        }
+
+        let [prev, next] = <[_; 2]>::try_from(textures).ok()?;
+        let (prev_geo, prev_tex, prev_geo_to_tex, mut prev_data) = prev;
+        let (next_geo, next_tex, next_geo_to_tex, mut next_data) = next;
+
+        // Goes from 0 with the upper workspace in view to 1 with the lower workspace in view.
+        let progress = (-prev_geo.loc.y / (next_geo.loc.y - prev_geo.loc.y)).clamp(0., 1.);
+        let output_size_vec = Vec2::new(output_size.w as f32, output_size.h as f32);
+
+        let elem = ShaderRenderElement::new(
+            ProgramType::WorkspaceSwitch,
+            output_size,
+            None,
+            scale.x as f32,
+            1.,
+            vec![
+                Uniform::new("niri_output_size", output_size_vec.to_array()),
+                Uniform::new("niri_cursor_position", self.cursor_position_on(output)),
+                mat3_uniform("niri_geo_to_tex_prev", prev_geo_to_tex),
+                mat3_uniform("niri_geo_to_tex_next", next_geo_to_tex),
+                Uniform::new("niri_progress", progress as f32),
+            ],
+            HashMap::from([
+                (String::from("niri_tex_prev"), prev_tex),
+                (String::from("niri_tex_next"), next_tex),
+            ]),
+            Kind::Unspecified,
+        );
+
+        if target == RenderTarget::Output {
+            // We're drawing the shader, not the offscreens themselves.
+            prev_data.id = elem.id().clone();
+            next_data.id = elem.id().clone();
+
+            let mut offscreen_data = state.offscreen_data.borrow_mut();
+            offscreen_data.push(prev_data);
+            offscreen_data.push(next_data);
+        }
+
+        Some(elem)
+    }
+
+    /// Returns the cursor position in output-local logical coordinates.
+    fn cursor_position_on(&self, output: &Output) -> [f32; 2] {
+        let output_geo = self.global_space.output_geometry(output).unwrap();
+        let pos = self.seat.get_pointer().unwrap().current_location() - output_geo.loc.to_f64();
+        [pos.x as f32, pos.y as f32]
+    }
+
+    /// Zooms in on the rendered output contents if the output magnifier is active.
+    ///
+    /// Only used when rendering to the output itself, so screencasts and screenshots show the
+    /// unmagnified contents.
+    pub fn magnify<R: NiriRenderer>(
+        &self,
+        output: &Output,
+        elements: &mut Vec<OutputRenderElements<R>>,
+    ) {
+        let magnifier = &self.output_state.get(output).unwrap().magnifier;
+        if !magnifier.is_active() {
+            return;
+        }
+
+        let scale = Scale::from(output.current_scale().fractional_scale());
+        let unmagnified = mem::take(elements);
+        elements.extend(
+            magnifier
+                .render(unmagnified, scale)
+                .map(OutputRenderElements::from),
+        );
+    }
+
+    /// Changes the magnifier of the output that the user is looking at.
+    pub fn update_magnifier_under_cursor(&mut self, f: impl FnOnce(&mut Magnifier)) {
+        let Some(output) = self
+            .output_under_cursor()
+            .or_else(|| self.layout.active_output().cloned())
+        else {
+            return;
+        };
+        let Some(state) = self.output_state.get_mut(&output) else {
+            return;
+        };
+
+        f(&mut state.magnifier);
+        self.queue_redraw(&output);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn render_layer<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        target: RenderTarget,
+        layer_map: &LayerMap,
+        layer: Layer,
+        elements: &mut SplitElements<LayerSurfaceRenderElement<R>>,
+        for_backdrop: bool,
+        color_target: &ColorDescription,
+    ) {
+        // LayerMap returns layers in reverse stacking order.
+        let iter = layer_map.layers_on(layer).rev().filter_map(|surface| {
+            let mapped = self.mapped_layer_surfaces.get(surface)?;
+
+            if for_backdrop != mapped.place_within_backdrop() {
+                return None;
+            }
+
+            let geo = layer_map.layer_geometry(surface)?;
+            Some((mapped, geo))
+        });
+        for (mapped, geo) in iter {
+            elements.extend(mapped.render(renderer, geo.loc.to_f64(), target, color_target));
+        }
+    }
+
+    fn redraw(&mut self, backend: &mut Backend, output: &Output) {
+        let _span = tracy_client::span!("Niri::redraw");
+
+        // Verify our invariant.
+        let state = self.output_state.get_mut(output).unwrap();
+        assert!(matches!(
+            state.redraw_state,
+            RedrawState::Queued | RedrawState::WaitingForEstimatedVBlankAndQueued(_)
+        ));
 
         let target_presentation_time = state.frame_clock.next_presentation_time();
 
@@ -4418,14 +5218,18 @@ impl Niri {
 
         self.update_render_elements(Some(output));
 
+        // The output can also be powered off on its own.
+        let is_active = self.monitors_active && backend.is_output_powered(output);
+
         let mut res = RenderResult::Skipped;
-        if self.monitors_active {
+        if is_active {
             let state = self.output_state.get_mut(output).unwrap();
             state.unfinished_animations_remain = self.layout.are_animations_ongoing(Some(output));
             state.unfinished_animations_remain |=
                 self.config_error_notification.are_animations_ongoing();
             state.unfinished_animations_remain |= self.screenshot_ui.are_animations_ongoing();
             state.unfinished_animations_remain |= state.screen_transition.is_some();
+            state.unfinished_animations_remain |= state.magnifier.are_animations_ongoing();
 
             // Also keep redrawing if the current cursor is animated.
             state.unfinished_animations_remain |= self
@@ -4462,7 +5266,7 @@ impl Niri {
         // Update the lock render state on successful render, or if monitors are inactive. When
         // monitors are inactive on a TTY, they have no framebuffer attached, so no sensitive data
         // from a last render will be visible.
-        if res != RenderResult::Skipped || !self.monitors_active {
+        if res != RenderResult::Skipped || !is_active {
             state.lock_render_state = if is_locked {
                 LockRenderState::Locked
             } else {
@@ -4525,6 +5329,7 @@ impl Niri {
             }
 
             self.render_for_screencopy_with_damage(renderer, output);
+            self.render_for_image_copy_capture(renderer, Some(output));
         });
     }
 
@@ -4559,11 +5364,36 @@ impl Niri {
         backend.set_output_on_demand_vrr(self, output, current);
     }
 
+    /// Returns the surface that may present with tearing on this output.
+    ///
+    /// This is the surface of a fullscreen window that matches the allow-tearing window rule and
+    /// asks for async presentation.
+    pub fn tearing_surface(&self, output: &Output) -> Option<WlSurface> {
+        let mon = self.layout.monitor_for_output(output)?;
+        let mapped = mon.active_window()?;
+        if mapped.rules().allow_tearing != Some(true) || !mapped.is_fullscreen() {
+            return None;
+        }
+
+        let surface = mapped.toplevel().wl_surface();
+        let is_async = with_states(surface, |states| {
+            let mut guard = states
+                .cached_state
+                .get::<TearingControlSurfaceCachedState>();
+            *guard.current().presentation_hint() == wp_tearing_control_v1::PresentationHint::Async
+        });
+        is_async.then(|| surface.clone())
+    }
+
     pub fn update_primary_scanout_output(
         &self,
         output: &Output,
         render_element_states: &RenderElementStates,
     ) {
+        // Surfaces rendered through the output offscreens are only in the offscreen states.
+        let merged_states = self.merge_offscreen_states(output, render_element_states);
+        let render_element_states = merged_states.as_ref().unwrap_or(render_element_states);
+
         // FIXME: potentially tweak the compare function. The default one currently always prefers a
         // higher refresh-rate output, which is not always desirable (i.e. with a very small
         // overlap).
@@ -4692,6 +5522,32 @@ impl Niri {
         }
     }
 
+    fn merge_offscreen_states(
+        &self,
+        output: &Output,
+        render_element_states: &RenderElementStates,
+    ) -> Option<RenderElementStates> {
+        let offscreen_data = self.output_state.get(output)?.offscreen_data.borrow();
+        if offscreen_data.is_empty() {
+            return None;
+        }
+
+        let mut merged = render_element_states.clone();
+        // Offscreens are recorded innermost first, so go in reverse to resolve nested ones.
+        for data in offscreen_data.iter().rev() {
+            // If the offscreen itself wasn't presented, then neither were its surfaces.
+            if !merged.states.contains_key(&data.id) {
+                continue;
+            }
+
+            for (id, state) in &data.states.states {
+                merged.states.insert(id.clone(), *state);
+            }
+        }
+
+        Some(merged)
+    }
+
     pub fn send_dmabuf_feedbacks(
         &self,
         output: &Output,
@@ -5144,14 +6000,15 @@ impl Niri {
         for queue in screencopy_state.queues_mut() {
             let (damage_tracker, screencopy) = queue.split();
             if let Some(screencopy) = screencopy {
-                if screencopy.output() == output {
+                if screencopy.output() == Some(output) {
                     let elements = elements.get_or_init(|| {
                         self.render(renderer, output, true, RenderTarget::ScreenCapture)
                     });
                     // FIXME: skip elements if not including pointers
                     let render_result = Self::render_for_screencopy_internal(
                         renderer,
-                        output,
+                        output.current_scale().fractional_scale().into(),
+                        output.current_transform(),
                         elements,
                         true,
                         damage_tracker,
@@ -5201,7 +6058,7 @@ impl Niri {
     ) -> anyhow::Result<()> {
         let _span = tracy_client::span!("Niri::render_for_screencopy");
 
-        let output = screencopy.output();
+        let output = screencopy.output().context("screencopy output missing")?;
         ensure!(
             self.output_state.contains_key(output),
             "screencopy output missing"
@@ -5222,7 +6079,8 @@ impl Niri {
 
         let render_result = Self::render_for_screencopy_internal(
             renderer,
-            output,
+            output.current_scale().fractional_scale().into(),
+            output.current_transform(),
             &elements,
             false,
             damage_tracker,
@@ -5240,11 +6098,12 @@ impl Niri {
         res
     }
 
-    #[allow(clippy::type_complexity)]
+    #[allow(clippy::type_complexity, clippy::too_many_arguments)]
     fn render_for_screencopy_internal<'a>(
         renderer: &mut GlesRenderer,
-        output: &Output,
-        elements: &[OutputRenderElements<GlesRenderer>],
+        scale: Scale<f64>,
+        transform: Transform,
+        elements: &[impl RenderElement<GlesRenderer>],
         with_damage: bool,
         damage_tracker: &'a mut OutputDamageTracker,
         screencopy: &Screencopy,
@@ -5259,8 +6118,6 @@ impl Niri {
         };
 
         let size = screencopy.buffer_size();
-        let scale: Scale<f64> = output.current_scale().fractional_scale().into();
-        let transform = output.current_transform();
 
         if size != last_size || scale != last_scale || transform != last_transform {
             *damage_tracker = OutputDamageTracker::new(size, scale, transform);
@@ -5303,6 +6160,353 @@ impl Niri {
         Ok((sync, damages))
     }
 
+    /// Renders pending ext-image-copy-capture frames on the output.
+    ///
+    /// With `None`, renders the frames of windows that aren't on any output.
+    pub fn render_for_image_copy_capture(
+        &mut self,
+        renderer: &mut GlesRenderer,
+        output: Option<&Output>,
+    ) {
+        let _span = tracy_client::span!("Niri::render_for_image_copy_capture");
+
+        let mut state = mem::take(&mut self.image_copy_capture_state);
+        if output.is_some() {
+            state.update_cursor_sessions(|source| self.cursor_position_in_capture_source(source));
+        }
+
+        let output_scale = output.map_or(Scale::from(1.), |output| {
+            Scale::from(output.current_scale().fractional_scale())
+        });
+        let output_transform = output.map_or(Transform::Normal, Output::current_transform);
+        let elements_with_pointer = OnceCell::new();
+        let elements_without_pointer = OnceCell::new();
+
+        for (session, queue) in state.queues_mut() {
+            let (damage_tracker, screencopy) = queue.split();
+            let Some(screencopy) = screencopy else {
+                continue;
+            };
+
+            let (render_result, transform) = match screencopy.source() {
+                ScreencopySource::Output => {
+                    let Some(output) = output.filter(|o| screencopy.output() == Some(*o)) else {
+                        continue;
+                    };
+
+                    let include_pointer = screencopy.overlay_cursor();
+                    let elements = if include_pointer {
+                        &elements_with_pointer
+                    } else {
+                        &elements_without_pointer
+                    };
+                    let elements = elements.get_or_init(|| {
+                        self.render(
+                            renderer,
+                            output,
+                            include_pointer,
+                            RenderTarget::ScreenCapture,
+                        )
+                    });
+
+                    let render_result = Self::render_for_screencopy_internal(
+                        renderer,
+                        output_scale,
+                        output_transform,
+                        elements,
+                        true,
+                        damage_tracker,
+                        screencopy,
+                    );
+                    (render_result, output_transform)
+                }
+                ScreencopySource::Window(id) => {
+                    let mapped = match output {
+                        Some(output) => self
+                            .layout
+                            .windows_for_output(output)
+                            .find(|win| win.id() == id),
+                        None => self
+                            .layout
+                            .windows()
+                            .find(|(mon, win)| mon.is_none() && win.id() == id)
+                            .map(|(_, win)| win),
+                    };
+                    let Some(mapped) = mapped else {
+                        continue;
+                    };
+
+                    let bbox = mapped
+                        .window
+                        .bbox_with_popups()
+                        .to_physical_precise_up(output_scale);
+                    if bbox.size != screencopy.buffer_size() {
+                        // The window was resized, so the client needs to reallocate its buffer.
+                        queue.pop().fail_buffer_constraints();
+                        image_copy_capture::send_buffer_size(session, bbox.size);
+                        continue;
+                    }
+
+                    // Move the pointer from the output into the window buffer, if it's over the
+                    // window.
+                    let mut elements: Vec<WindowCaptureRenderElements<_>> = Vec::new();
+                    if let Some(output) = output.filter(|_| screencopy.overlay_cursor()) {
+                        let source = ImageCaptureSource::Toplevel(id);
+                        let pointer_pos = self.cursor_position_on_output(output);
+                        let in_window = self.cursor_position_in_capture_source(&source);
+                        if let (Some(pointer_pos), Some((pos, _))) = (pointer_pos, in_window) {
+                            let offset = pos - pointer_pos.to_physical_precise_round(output_scale);
+                            for elem in self.pointer_element(renderer, output) {
+                                let elem = RelocateRenderElement::from_element(
+                                    elem,
+                                    offset,
+                                    Relocate::Relative,
+                                );
+                                elements.push(elem.into());
+                            }
+                        }
+                    }
+                    elements.extend(
+                        mapped
+                            .render_for_screen_cast(renderer, output_scale)
+                            .map(WindowCaptureRenderElements::from),
+                    );
+
+                    let render_result = Self::render_for_screencopy_internal(
+                        renderer,
+                        output_scale,
+                        Transform::Normal,
+                        &elements,
+                        true,
+                        damage_tracker,
+                        screencopy,
+                    );
+                    (render_result, Transform::Normal)
+                }
+                ScreencopySource::Cursor => {
+                    let Some(output) = output.filter(|o| screencopy.output() == Some(*o)) else {
+                        continue;
+                    };
+
+                    let Some(pointer_pos) = self.cursor_position_on_output(output) else {
+                        continue;
+                    };
+                    let Some((size, hotspot)) = self.cursor_image_geometry(output) else {
+                        continue;
+                    };
+                    if size != screencopy.buffer_size() {
+                        // The cursor image changed size.
+                        queue.pop().fail_buffer_constraints();
+                        image_copy_capture::send_buffer_size(session, size);
+                        continue;
+                    }
+
+                    // Move the cursor image to the origin of the buffer.
+                    let offset = hotspot - pointer_pos.to_physical_precise_round(output_scale);
+                    let elements: Vec<_> = self
+                        .pointer_element(renderer, output)
+                        .into_iter()
+                        .map(|elem| {
+                            RelocateRenderElement::from_element(elem, offset, Relocate::Relative)
+                        })
+                        .collect();
+
+                    let render_result = Self::render_for_screencopy_internal(
+                        renderer,
+                        output_scale,
+                        Transform::Normal,
+                        &elements,
+                        true,
+                        damage_tracker,
+                        screencopy,
+                    );
+                    (render_result, Transform::Normal)
+                }
+            };
+
+            match render_result {
+                Ok((sync, damages)) => {
+                    if let Some(damages) = damages {
+                        // Convert from Physical coordinates back to Buffer coordinates.
+                        let physical_size = transform.transform_size(screencopy.buffer_size());
+                        let damages = damages.iter().map(|dmg| {
+                            dmg.to_logical(1).to_buffer(
+                                1,
+                                transform.invert(),
+                                &physical_size.to_logical(1),
+                            )
+                        });
+
+                        screencopy.damage(damages);
+                        queue.pop().submit_after_sync(false, sync, &self.event_loop);
+                    } else {
+                        trace!("no damage found, waiting till next redraw");
+                    }
+                }
+                Err(err) => {
+                    // Recreate damage tracker to report full damage next check.
+                    *damage_tracker = OutputDamageTracker::new((0, 0), 1.0, Transform::Normal);
+                    queue.pop();
+                    warn!("error rendering for image copy capture: {err:?}");
+                }
+            }
+        }
+
+        self.image_copy_capture_state = state;
+    }
+
+    /// Returns the current parameters for capturing an ext-image-copy-capture source.
+    pub fn image_capture_constraints(
+        &self,
+        source: &ImageCaptureSource,
+        cursor: bool,
+    ) -> Option<CaptureConstraints> {
+        let (output, buffer_size, transform) = match source {
+            ImageCaptureSource::Output(output) => {
+                if !self.output_state.contains_key(output) {
+                    return None;
+                }
+
+                let size = output.current_mode()?.size;
+                (Some(output.clone()), size, output.current_transform())
+            }
+            ImageCaptureSource::Toplevel(id) => {
+                let (mon, mapped) = self.layout.windows().find(|(_, win)| win.id() == *id)?;
+                // Windows that aren't on any output are captured at scale 1.
+                let output = mon.map(|mon| mon.output().clone());
+                let scale = output.as_ref().map_or(Scale::from(1.), |output| {
+                    Scale::from(output.current_scale().fractional_scale())
+                });
+                let bbox = mapped
+                    .window
+                    .bbox_with_popups()
+                    .to_physical_precise_up(scale);
+                (output, bbox.size, Transform::Normal)
+            }
+        };
+
+        if cursor {
+            // While the cursor is hidden, advertise the default cursor size so that the client
+            // can allocate a buffer in advance.
+            let buffer_size = output
+                .as_ref()
+                .and_then(|output| self.cursor_image_geometry(output))
+                .map(|(size, _)| size)
+                .unwrap_or_else(|| {
+                    let output_scale = output
+                        .as_ref()
+                        .map_or(smithay::output::Scale::Integer(1), Output::current_scale);
+                    let cursor_scale = output_scale.integer_scale();
+                    let cursor = self.cursor_manager.get_default_cursor(cursor_scale);
+                    let (size, _) = xcursor_geometry(
+                        &cursor.frames()[0],
+                        cursor_scale,
+                        output_scale.fractional_scale().into(),
+                    );
+                    size
+                });
+
+            return Some(CaptureConstraints {
+                output,
+                buffer_size,
+                transform: Transform::Normal,
+            });
+        }
+
+        Some(CaptureConstraints {
+            output,
+            buffer_size,
+            transform,
+        })
+    }
+
+    /// Returns the cursor position within the output, if the cursor is on it.
+    fn cursor_position_on_output(&self, output: &Output) -> Option<Point<f64, Logical>> {
+        let pos = self
+            .tablet_cursor_location
+            .unwrap_or_else(|| self.seat.get_pointer().unwrap().current_location());
+        let (under, pos_within_output) = self.output_under(pos)?;
+        (under == output).then_some(pos_within_output)
+    }
+
+    /// Returns the size and the hotspot of the cursor image as rendered on the output.
+    fn cursor_image_geometry(
+        &self,
+        output: &Output,
+    ) -> Option<(Size<i32, Physical>, Point<i32, Physical>)> {
+        if !self.pointer_visibility.is_visible() {
+            return None;
+        }
+
+        let output_scale = output.current_scale();
+        let scale = Scale::from(output_scale.fractional_scale());
+
+        match self
+            .cursor_manager
+            .get_render_cursor(output_scale.integer_scale())
+        {
+            RenderCursor::Hidden => None,
+            RenderCursor::Surface { surface, hotspot } => {
+                let bbox = bbox_from_surface_tree(&surface, (0, 0));
+                let size = bbox.size.to_physical_precise_up(scale);
+                let hotspot = hotspot.to_f64().to_physical_precise_round(scale);
+                Some((size, hotspot))
+            }
+            RenderCursor::Named {
+                scale: cursor_scale,
+                cursor,
+                ..
+            } => {
+                let (_, frame) = cursor.frame(self.start_time.elapsed().as_millis() as u32);
+                Some(xcursor_geometry(frame, cursor_scale, scale))
+            }
+        }
+    }
+
+    /// Returns the cursor position and hotspot in the buffer coordinates of a capture source.
+    fn cursor_position_in_capture_source(
+        &self,
+        source: &ImageCaptureSource,
+    ) -> Option<(Point<i32, Physical>, Point<i32, Physical>)> {
+        match source {
+            ImageCaptureSource::Output(output) => {
+                let pos = self.cursor_position_on_output(output)?;
+                let (_, hotspot) = self.cursor_image_geometry(output)?;
+
+                let scale = Scale::from(output.current_scale().fractional_scale());
+                let transform = output.current_transform();
+                let size = transform.transform_size(output.current_mode()?.size);
+                let pos = transform
+                    .invert()
+                    .transform_point_in(pos.to_physical_precise_round(scale), &size);
+                Some((pos, hotspot))
+            }
+            ImageCaptureSource::Toplevel(id) => {
+                let (mon, _) = self.layout.windows().find(|(_, win)| win.id() == *id)?;
+                let output = mon?.output();
+                let pos = self.cursor_position_on_output(output)?;
+
+                let (mapped, HitType::Input { win_pos }) = self.layout.window_under(output, pos)?
+                else {
+                    return None;
+                };
+                if mapped.id() != *id {
+                    return None;
+                }
+
+                let (_, hotspot) = self.cursor_image_geometry(output)?;
+
+                let scale = Scale::from(output.current_scale().fractional_scale());
+                let bbox = mapped
+                    .window
+                    .bbox_with_popups()
+                    .to_physical_precise_up(scale);
+                let pos = (pos - win_pos).to_physical_precise_round(scale) - bbox.loc;
+                Some((pos, hotspot))
+            }
+        }
+    }
+
     #[cfg(feature = "xdp-gnome-screencast")]
     fn stop_cast(&mut self, session_id: usize) {
         let _span = tracy_client::span!("Niri::stop_cast");
@@ -5375,6 +6579,7 @@ impl Niri {
         for queue in self.screencopy_state.queues_mut() {
             queue.remove_output(output);
         }
+        self.image_copy_capture_state.remove_output(output);
     }
 
     pub fn debug_toggle_damage(&mut self) {
@@ -5686,6 +6891,13 @@ impl Niri {
         Ok(())
     }
 
+    pub fn window_match_context(&self) -> MatchContext {
+        MatchContext {
+            is_at_startup: self.is_at_startup,
+            satellite_pid: self.satellite.as_ref().and_then(Satellite::pid),
+        }
+    }
+
     pub fn is_locked(&self) -> bool {
         match self.lock_state {
             LockState::Unlocked | LockState::WaitingForSurfaces { .. } => false,
@@ -6170,23 +7382,30 @@ impl Niri {
     pub fn recompute_window_rules(&mut self) {
         let _span = tracy_client::span!("Niri::recompute_window_rules");
 
+        let ctx = self.window_match_context();
         let changed = {
             let window_rules = &self.config.borrow().window_rules;
 
             for unmapped in self.unmapped_windows.values_mut() {
-                let new_rules = ResolvedWindowRules::compute(
-                    window_rules,
-                    WindowRef::Unmapped(unmapped),
-                    self.is_at_startup,
-                );
-                if let InitialConfigureState::Configured { rules, .. } = &mut unmapped.state {
+                let new_rules =
+                    ResolvedWindowRules::compute(window_rules, WindowRef::Unmapped(unmapped), ctx);
+                if let InitialConfigureState::Configured {
+                    rules,
+                    session_placement,
+                    ..
+                } = &mut unmapped.state
+                {
                     *rules = new_rules;
+
+                    if let Some(placement) = session_placement {
+                        placement.apply_to_rules(rules);
+                    }
                 }
             }
 
             let mut windows = vec![];
             self.layout.with_windows_mut(|mapped, _| {
-                if mapped.recompute_window_rules(window_rules, self.is_at_startup) {
+                if mapped.recompute_window_rules(window_rules, ctx) {
                     windows.push(mapped.window.clone());
                 }
             });
@@ -6335,6 +7554,45 @@ impl ClientData for ClientState {
     fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
 }
 
+/// Returns the size and the hotspot of an xcursor image as rendered at the given scale.
+fn xcursor_geometry(
+    image: &Image,
+    cursor_scale: i32,
+    scale: Scale<f64>,
+) -> (Size<i32, Physical>, Point<i32, Physical>) {
+    let size = Size::<i32, Physical>::from((image.width as i32, image.height as i32))
+        .to_logical(cursor_scale)
+        .to_f64()
+        .to_physical_precise_up(scale);
+    let hotspot = XCursor::hotspot(image)
+        .to_logical(cursor_scale)
+        .to_f64()
+        .to_physical_precise_round(scale);
+    (size, hotspot)
+}
+
+/// Computes the matrix mapping geometry coordinates to the offscreen texture coordinates.
+///
+/// `geo_loc` is where the geometry starts in the coordinates of the offscreened elements.
+fn offscreen_geo_to_tex(
+    elem: &OffscreenRenderElement,
+    geo_loc: Point<f64, Logical>,
+    geo_size: Size<f64, Logical>,
+    scale: Scale<f64>,
+) -> Mat3 {
+    // OffscreenBuffer renders with Transform::Normal and the scale that we passed, so we can
+    // assume that below.
+    let offset = elem.offset() - geo_loc;
+    let texture = elem.texture();
+
+    let geo_size = Vec2::new(geo_size.w as f32, geo_size.h as f32);
+    let tex_scale = Vec2::new(scale.x as f32, scale.y as f32);
+    let tex_loc = Vec2::new(offset.x as f32, offset.y as f32);
+    let tex_size = Vec2::new(texture.width() as f32, texture.height() as f32) / tex_scale;
+
+    Mat3::from_translation(-tex_loc / tex_size) * Mat3::from_scale(geo_size / tex_size)
+}
+
 fn scale_relocate_crop<E: Element>(
     elem: E,
     output_scale: Scale<f64>,
@@ -6356,6 +7614,7 @@ niri_render_elements! {
             LayerSurfaceRenderElement<R>
         >>>,
         Wayland = WaylandSurfaceRenderElement<R>,
+        ColorTransform = ColorTransformRenderElement<R>,
         NamedPointer = MemoryRenderBufferRenderElement<R>,
         SolidColor = SolidColorRenderElement,
         RelocatedSolidColor = CropRenderElement<RelocateRenderElement<RescaleRenderElement<
@@ -6365,5 +7624,17 @@ niri_render_elements! {
         Texture = PrimaryGpuTextureRenderElement,
         // Used for the CPU-rendered panels.
         RelocatedMemoryBuffer = RelocateRenderElement<MemoryRenderBufferRenderElement<R>>,
+        Magnified = RelocateRenderElement<RescaleRenderElement<
+            BoxedRenderElement<OutputRenderElements<R>>
+        >>,
+        Shader = ShaderRenderElement,
+        ExtraDamage = ExtraDamage,
+    }
+}
+
+niri_render_elements! {
+    WindowCaptureRenderElements<R> => {
+        Window = WindowCastRenderElements<R>,
+        Pointer = RelocateRenderElement<OutputRenderElements<R>>,
     }
 }
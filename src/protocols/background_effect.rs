@@ -0,0 +1,196 @@
+use std::sync::Mutex;
+
+use ext_background_effect_manager_v1::{Capability, ExtBackgroundEffectManagerV1};
+use ext_background_effect_surface_v1::ExtBackgroundEffectSurfaceV1;
+use smithay::reexports::wayland_server::backend::ClientId;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, Weak,
+};
+use smithay::wayland::compositor::{self, Cacheable, RegionAttributes, SurfaceData};
+
+use super::raw::ext_background_effect::v1::server::{
+    ext_background_effect_manager_v1, ext_background_effect_surface_v1,
+};
+
+const VERSION: u32 = 1;
+
+pub struct BackgroundEffectState {}
+
+/// Background effects set on a surface, double-buffered.
+#[derive(Debug, Default, Clone)]
+pub struct BackgroundEffectCachedState {
+    /// Region to blur behind, in surface-local coordinates.
+    pub blur_region: Option<RegionAttributes>,
+}
+
+impl Cacheable for BackgroundEffectCachedState {
+    fn commit(&mut self, _dh: &DisplayHandle) -> Self {
+        self.clone()
+    }
+
+    fn merge_into(self, into: &mut Self, _dh: &DisplayHandle) {
+        *into = self;
+    }
+}
+
+/// Marks surfaces that already have a background effect object.
+#[derive(Default)]
+struct BackgroundEffectMarker(Mutex<bool>);
+
+/// Returns the blur region set on a surface, if any.
+pub fn surface_blur_region(states: &SurfaceData) -> Option<RegionAttributes> {
+    states
+        .cached_state
+        .get::<BackgroundEffectCachedState>()
+        .current()
+        .blur_region
+        .clone()
+}
+
+impl BackgroundEffectState {
+    pub fn new<D>(display: &DisplayHandle) -> Self
+    where
+        D: GlobalDispatch<ExtBackgroundEffectManagerV1, ()>,
+        D: Dispatch<ExtBackgroundEffectManagerV1, ()>,
+        D: Dispatch<ExtBackgroundEffectSurfaceV1, Weak<WlSurface>>,
+        D: 'static,
+    {
+        display.create_global::<D, ExtBackgroundEffectManagerV1, _>(VERSION, ());
+
+        Self {}
+    }
+}
+
+impl<D> GlobalDispatch<ExtBackgroundEffectManagerV1, (), D> for BackgroundEffectState
+where
+    D: GlobalDispatch<ExtBackgroundEffectManagerV1, ()>,
+    D: Dispatch<ExtBackgroundEffectManagerV1, ()>,
+    D: Dispatch<ExtBackgroundEffectSurfaceV1, Weak<WlSurface>>,
+    D: 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        manager: New<ExtBackgroundEffectManagerV1>,
+        _manager_state: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let manager = data_init.init(manager, ());
+        manager.capabilities(Capability::Blur);
+    }
+}
+
+impl<D> Dispatch<ExtBackgroundEffectManagerV1, (), D> for BackgroundEffectState
+where
+    D: Dispatch<ExtBackgroundEffectManagerV1, ()>,
+    D: Dispatch<ExtBackgroundEffectSurfaceV1, Weak<WlSurface>>,
+    D: 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        resource: &ExtBackgroundEffectManagerV1,
+        request: <ExtBackgroundEffectManagerV1 as Resource>::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_background_effect_manager_v1::Request::GetBackgroundEffect { id, surface } => {
+                let exists = compositor::with_states(&surface, |states| {
+                    let marker = states
+                        .data_map
+                        .get_or_insert_threadsafe(BackgroundEffectMarker::default);
+                    let mut marker = marker.0.lock().unwrap();
+                    std::mem::replace(&mut *marker, true)
+                });
+                if exists {
+                    resource.post_error(
+                        ext_background_effect_manager_v1::Error::BackgroundEffectExists,
+                        "the surface already has a background effect object",
+                    );
+                    return;
+                }
+
+                data_init.init(id, surface.downgrade());
+            }
+            ext_background_effect_manager_v1::Request::Destroy => (),
+        }
+    }
+}
+
+impl<D> Dispatch<ExtBackgroundEffectSurfaceV1, Weak<WlSurface>, D> for BackgroundEffectState
+where
+    D: Dispatch<ExtBackgroundEffectSurfaceV1, Weak<WlSurface>>,
+    D: 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        resource: &ExtBackgroundEffectSurfaceV1,
+        request: <ExtBackgroundEffectSurfaceV1 as Resource>::Request,
+        surface: &Weak<WlSurface>,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let region = match request {
+            ext_background_effect_surface_v1::Request::SetBlurRegion { region } => {
+                region.as_ref().map(compositor::get_region_attributes)
+            }
+            ext_background_effect_surface_v1::Request::Destroy => return,
+        };
+
+        let Ok(surface) = surface.upgrade() else {
+            resource.post_error(
+                ext_background_effect_surface_v1::Error::SurfaceDestroyed,
+                "the surface was destroyed",
+            );
+            return;
+        };
+
+        compositor::with_states(&surface, |states| {
+            let mut cached = states.cached_state.get::<BackgroundEffectCachedState>();
+            cached.pending().blur_region = region;
+        });
+    }
+
+    fn destroyed(
+        _state: &mut D,
+        _client: ClientId,
+        _resource: &ExtBackgroundEffectSurfaceV1,
+        surface: &Weak<WlSurface>,
+    ) {
+        // Destroying the object removes the effects on the next commit.
+        let Ok(surface) = surface.upgrade() else {
+            return;
+        };
+
+        compositor::with_states(&surface, |states| {
+            if let Some(marker) = states.data_map.get::<BackgroundEffectMarker>() {
+                *marker.0.lock().unwrap() = false;
+            }
+
+            let mut cached = states.cached_state.get::<BackgroundEffectCachedState>();
+            cached.pending().blur_region = None;
+        });
+    }
+}
+
+#[macro_export]
+macro_rules! delegate_background_effect {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::protocols::raw::ext_background_effect::v1::server::ext_background_effect_manager_v1::ExtBackgroundEffectManagerV1: ()
+        ] => $crate::protocols::background_effect::BackgroundEffectState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::protocols::raw::ext_background_effect::v1::server::ext_background_effect_manager_v1::ExtBackgroundEffectManagerV1: ()
+        ] => $crate::protocols::background_effect::BackgroundEffectState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::protocols::raw::ext_background_effect::v1::server::ext_background_effect_surface_v1::ExtBackgroundEffectSurfaceV1: smithay::reexports::wayland_server::Weak<smithay::reexports::wayland_server::protocol::wl_surface::WlSurface>
+        ] => $crate::protocols::background_effect::BackgroundEffectState);
+    };
+}
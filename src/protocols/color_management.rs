@@ -0,0 +1,863 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+
+use smithay::output::Output;
+use smithay::reexports::wayland_protocols::wp::color_management::v1::server::{
+    wp_color_management_output_v1, wp_color_management_surface_feedback_v1,
+    wp_color_management_surface_v1, wp_color_manager_v1, wp_image_description_creator_params_v1,
+    wp_image_description_info_v1, wp_image_description_v1,
+};
+use smithay::reexports::wayland_server::backend::ClientId;
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::reexports::wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, WEnum, Weak,
+};
+use smithay::wayland::compositor::{self, Cacheable, SurfaceData};
+use wp_color_management_output_v1::WpColorManagementOutputV1;
+use wp_color_management_surface_feedback_v1::WpColorManagementSurfaceFeedbackV1;
+use wp_color_management_surface_v1::WpColorManagementSurfaceV1;
+use wp_color_manager_v1::{Feature, RenderIntent, WpColorManagerV1};
+use wp_image_description_creator_params_v1::WpImageDescriptionCreatorParamsV1;
+use wp_image_description_info_v1::WpImageDescriptionInfoV1;
+use wp_image_description_v1::WpImageDescriptionV1;
+
+use crate::utils::color::{
+    output_color_description, Chromaticity, ColorDescription, Luminances, MasteringDisplay,
+    Primaries, TransferFunction,
+};
+
+const VERSION: u32 = 1;
+
+/// Named transfer functions that we can convert from.
+const SUPPORTED_TFS: [wp_color_manager_v1::TransferFunction; 6] = [
+    wp_color_manager_v1::TransferFunction::Srgb,
+    wp_color_manager_v1::TransferFunction::Gamma22,
+    wp_color_manager_v1::TransferFunction::Gamma28,
+    wp_color_manager_v1::TransferFunction::Bt1886,
+    wp_color_manager_v1::TransferFunction::ExtLinear,
+    wp_color_manager_v1::TransferFunction::St2084Pq,
+];
+
+/// Named primaries that we can convert from.
+const SUPPORTED_PRIMARIES: [wp_color_manager_v1::Primaries; 4] = [
+    wp_color_manager_v1::Primaries::Srgb,
+    wp_color_manager_v1::Primaries::Bt2020,
+    wp_color_manager_v1::Primaries::DisplayP3,
+    wp_color_manager_v1::Primaries::AdobeRgb,
+];
+
+static NEXT_IDENTITY: AtomicU32 = AtomicU32::new(1);
+
+pub struct ColorManagementState {
+    outputs: Vec<WpColorManagementOutputV1>,
+    feedbacks: Vec<WpColorManagementSurfaceFeedbackV1>,
+    /// Identities of the current output image descriptions.
+    output_identities: HashMap<Output, u32>,
+}
+
+pub struct ColorManagementGlobalData {
+    filter: Box<dyn for<'c> Fn(&'c Client) -> bool + Send + Sync>,
+}
+
+pub trait ColorManagementHandler {
+    fn color_management_state(&mut self) -> &mut ColorManagementState;
+    /// Returns the output whose color space the surface should preferably use.
+    fn preferred_output(&mut self, surface: &WlSurface) -> Option<Output>;
+}
+
+/// Image description set on a surface, double-buffered.
+#[derive(Debug, Default, Clone)]
+pub struct ColorManagementSurfaceCachedState {
+    pub description: Option<Arc<ColorDescription>>,
+}
+
+impl Cacheable for ColorManagementSurfaceCachedState {
+    fn commit(&mut self, _dh: &DisplayHandle) -> Self {
+        self.clone()
+    }
+
+    fn merge_into(self, into: &mut Self, _dh: &DisplayHandle) {
+        *into = self;
+    }
+}
+
+/// Marks surfaces that already have a color management surface object.
+#[derive(Default)]
+struct ColorManagementSurfaceMarker(Mutex<bool>);
+
+pub struct ImageDescriptionData {
+    /// `None` if creating the description failed.
+    description: Option<Arc<ColorDescription>>,
+    /// Whether the client can get information about this description.
+    allows_info: bool,
+}
+
+/// Parameters of an image description being created.
+#[derive(Default)]
+pub struct ImageDescriptionParams {
+    tf: Option<TransferFunction>,
+    primaries: Option<Primaries>,
+    luminances: Option<Luminances>,
+    mastering_primaries: Option<Primaries>,
+    mastering_luminance: Option<(f32, f32)>,
+    max_cll: Option<f32>,
+    max_fall: Option<f32>,
+}
+
+/// Returns the image description set on a surface, if any.
+pub fn surface_color_description(states: &SurfaceData) -> Option<Arc<ColorDescription>> {
+    states
+        .cached_state
+        .get::<ColorManagementSurfaceCachedState>()
+        .current()
+        .description
+        .clone()
+}
+
+fn next_identity() -> u32 {
+    NEXT_IDENTITY.fetch_add(1, Ordering::Relaxed)
+}
+
+impl ColorManagementState {
+    pub fn new<D, F>(display: &DisplayHandle, filter: F) -> Self
+    where
+        D: GlobalDispatch<WpColorManagerV1, ColorManagementGlobalData>,
+        D: Dispatch<WpColorManagerV1, ()>,
+        D: Dispatch<WpColorManagementOutputV1, Option<Output>>,
+        D: Dispatch<WpColorManagementSurfaceV1, Weak<WlSurface>>,
+        D: Dispatch<WpColorManagementSurfaceFeedbackV1, Weak<WlSurface>>,
+        D: Dispatch<WpImageDescriptionCreatorParamsV1, Mutex<ImageDescriptionParams>>,
+        D: Dispatch<WpImageDescriptionV1, ImageDescriptionData>,
+        D: Dispatch<WpImageDescriptionInfoV1, ()>,
+        D: ColorManagementHandler,
+        D: 'static,
+        F: for<'c> Fn(&'c Client) -> bool + Send + Sync + 'static,
+    {
+        let global_data = ColorManagementGlobalData {
+            filter: Box::new(filter),
+        };
+        display.create_global::<D, WpColorManagerV1, _>(VERSION, global_data);
+
+        Self {
+            outputs: Vec::new(),
+            feedbacks: Vec::new(),
+            output_identities: HashMap::new(),
+        }
+    }
+
+    /// Notifies clients that the color description of an output changed.
+    pub fn output_description_changed(&mut self, output: &Output) {
+        let identity = next_identity();
+        self.output_identities.insert(output.clone(), identity);
+
+        for resource in &self.outputs {
+            if resource.data::<Option<Output>>() == Some(&Some(output.clone())) {
+                resource.image_description_changed();
+            }
+        }
+
+        // We don't track which output each surface prefers, so just let everyone re-query.
+        for feedback in &self.feedbacks {
+            feedback.preferred_changed(identity);
+        }
+    }
+
+    pub fn output_removed(&mut self, output: &Output) {
+        self.output_identities.remove(output);
+    }
+
+    fn output_identity(&mut self, output: &Output) -> u32 {
+        *self
+            .output_identities
+            .entry(output.clone())
+            .or_insert_with(next_identity)
+    }
+}
+
+impl<D> GlobalDispatch<WpColorManagerV1, ColorManagementGlobalData, D> for ColorManagementState
+where
+    D: GlobalDispatch<WpColorManagerV1, ColorManagementGlobalData>,
+    D: Dispatch<WpColorManagerV1, ()>,
+    D: ColorManagementHandler,
+    D: 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        manager: New<WpColorManagerV1>,
+        _manager_state: &ColorManagementGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let manager = data_init.init(manager, ());
+
+        manager.supported_intent(RenderIntent::Perceptual);
+        for feature in [
+            Feature::Parametric,
+            Feature::SetPrimaries,
+            Feature::SetTfPower,
+            Feature::SetLuminances,
+            Feature::SetMasteringDisplayPrimaries,
+        ] {
+            manager.supported_feature(feature);
+        }
+        for tf in SUPPORTED_TFS {
+            manager.supported_tf_named(tf);
+        }
+        for primaries in SUPPORTED_PRIMARIES {
+            manager.supported_primaries_named(primaries);
+        }
+        manager.done();
+    }
+
+    fn can_view(client: Client, global_data: &ColorManagementGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D> Dispatch<WpColorManagerV1, (), D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagerV1, ()>,
+    D: Dispatch<WpColorManagementOutputV1, Option<Output>>,
+    D: Dispatch<WpColorManagementSurfaceV1, Weak<WlSurface>>,
+    D: Dispatch<WpColorManagementSurfaceFeedbackV1, Weak<WlSurface>>,
+    D: Dispatch<WpImageDescriptionCreatorParamsV1, Mutex<ImageDescriptionParams>>,
+    D: Dispatch<WpImageDescriptionV1, ImageDescriptionData>,
+    D: ColorManagementHandler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        resource: &WpColorManagerV1,
+        request: <WpColorManagerV1 as Resource>::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_color_manager_v1::Request::GetOutput { id, output } => {
+                let output = Output::from_resource(&output);
+                let resource = data_init.init(id, output);
+                state.color_management_state().outputs.push(resource);
+            }
+            wp_color_manager_v1::Request::GetSurface { id, surface } => {
+                let exists = compositor::with_states(&surface, |states| {
+                    let marker = states
+                        .data_map
+                        .get_or_insert_threadsafe(ColorManagementSurfaceMarker::default);
+                    let mut marker = marker.0.lock().unwrap();
+                    std::mem::replace(&mut *marker, true)
+                });
+                if exists {
+                    resource.post_error(
+                        wp_color_manager_v1::Error::SurfaceExists,
+                        "the surface already has a color management surface",
+                    );
+                    return;
+                }
+
+                data_init.init(id, surface.downgrade());
+            }
+            wp_color_manager_v1::Request::GetSurfaceFeedback { id, surface } => {
+                let feedback = data_init.init(id, surface.downgrade());
+                state.color_management_state().feedbacks.push(feedback);
+            }
+            wp_color_manager_v1::Request::CreateParametricCreator { obj } => {
+                data_init.init(obj, Mutex::new(ImageDescriptionParams::default()));
+            }
+            wp_color_manager_v1::Request::CreateIccCreator { .. } => {
+                resource.post_error(
+                    wp_color_manager_v1::Error::UnsupportedFeature,
+                    "ICC image descriptions are not supported",
+                );
+            }
+            wp_color_manager_v1::Request::CreateWindowsScrgb { .. } => {
+                resource.post_error(
+                    wp_color_manager_v1::Error::UnsupportedFeature,
+                    "Windows scRGB is not supported",
+                );
+            }
+            wp_color_manager_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<WpColorManagementOutputV1, Option<Output>, D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagementOutputV1, Option<Output>>,
+    D: Dispatch<WpImageDescriptionV1, ImageDescriptionData>,
+    D: ColorManagementHandler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _resource: &WpColorManagementOutputV1,
+        request: <WpColorManagementOutputV1 as Resource>::Request,
+        output: &Option<Output>,
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_color_management_output_v1::Request::GetImageDescription { image_description } => {
+                let Some(output) = output else {
+                    let data = ImageDescriptionData {
+                        description: None,
+                        allows_info: false,
+                    };
+                    data_init.init(image_description, data).failed(
+                        wp_image_description_v1::Cause::NoOutput,
+                        "the output is gone".to_owned(),
+                    );
+                    return;
+                };
+
+                let identity = state.color_management_state().output_identity(output);
+                let data = ImageDescriptionData {
+                    description: Some(output_color_description(output)),
+                    allows_info: true,
+                };
+                data_init.init(image_description, data).ready(identity);
+            }
+            wp_color_management_output_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        resource: &WpColorManagementOutputV1,
+        _data: &Option<Output>,
+    ) {
+        let outputs = &mut state.color_management_state().outputs;
+        outputs.retain(|x| x != resource);
+    }
+}
+
+impl<D> Dispatch<WpColorManagementSurfaceV1, Weak<WlSurface>, D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagementSurfaceV1, Weak<WlSurface>>,
+    D: ColorManagementHandler,
+    D: 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        resource: &WpColorManagementSurfaceV1,
+        request: <WpColorManagementSurfaceV1 as Resource>::Request,
+        surface: &Weak<WlSurface>,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let description = match request {
+            wp_color_management_surface_v1::Request::SetImageDescription {
+                image_description,
+                render_intent,
+            } => {
+                if render_intent != WEnum::Value(RenderIntent::Perceptual) {
+                    resource.post_error(
+                        wp_color_management_surface_v1::Error::RenderIntent,
+                        "unsupported render intent",
+                    );
+                    return;
+                }
+
+                let data = image_description.data::<ImageDescriptionData>();
+                let Some(description) = data.and_then(|data| data.description.clone()) else {
+                    resource.post_error(
+                        wp_color_management_surface_v1::Error::ImageDescription,
+                        "the image description is not ready",
+                    );
+                    return;
+                };
+                Some(description)
+            }
+            wp_color_management_surface_v1::Request::UnsetImageDescription => None,
+            wp_color_management_surface_v1::Request::Destroy => return,
+            _ => unreachable!(),
+        };
+
+        let Ok(surface) = surface.upgrade() else {
+            resource.post_error(
+                wp_color_management_surface_v1::Error::Inert,
+                "the surface was destroyed",
+            );
+            return;
+        };
+
+        compositor::with_states(&surface, |states| {
+            let mut cached = states
+                .cached_state
+                .get::<ColorManagementSurfaceCachedState>();
+            cached.pending().description = description;
+        });
+    }
+
+    fn destroyed(
+        _state: &mut D,
+        _client: ClientId,
+        _resource: &WpColorManagementSurfaceV1,
+        surface: &Weak<WlSurface>,
+    ) {
+        // Destroying the object unsets the image description on the next commit.
+        let Ok(surface) = surface.upgrade() else {
+            return;
+        };
+
+        compositor::with_states(&surface, |states| {
+            if let Some(marker) = states.data_map.get::<ColorManagementSurfaceMarker>() {
+                *marker.0.lock().unwrap() = false;
+            }
+
+            let mut cached = states
+                .cached_state
+                .get::<ColorManagementSurfaceCachedState>();
+            cached.pending().description = None;
+        });
+    }
+}
+
+impl<D> Dispatch<WpColorManagementSurfaceFeedbackV1, Weak<WlSurface>, D> for ColorManagementState
+where
+    D: Dispatch<WpColorManagementSurfaceFeedbackV1, Weak<WlSurface>>,
+    D: Dispatch<WpImageDescriptionV1, ImageDescriptionData>,
+    D: ColorManagementHandler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        resource: &WpColorManagementSurfaceFeedbackV1,
+        request: <WpColorManagementSurfaceFeedbackV1 as Resource>::Request,
+        surface: &Weak<WlSurface>,
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let image_description = match request {
+            wp_color_management_surface_feedback_v1::Request::GetPreferred {
+                image_description,
+            }
+            | wp_color_management_surface_feedback_v1::Request::GetPreferredParametric {
+                image_description,
+            } => image_description,
+            wp_color_management_surface_feedback_v1::Request::Destroy => return,
+            _ => unreachable!(),
+        };
+
+        let Ok(surface) = surface.upgrade() else {
+            resource.post_error(
+                wp_color_management_surface_feedback_v1::Error::Inert,
+                "the surface was destroyed",
+            );
+            return;
+        };
+
+        let (description, identity) = match state.preferred_output(&surface) {
+            Some(output) => (
+                output_color_description(&output),
+                state.color_management_state().output_identity(&output),
+            ),
+            None => (Arc::new(ColorDescription::srgb()), next_identity()),
+        };
+
+        let data = ImageDescriptionData {
+            description: Some(description),
+            allows_info: true,
+        };
+        data_init.init(image_description, data).ready(identity);
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        resource: &WpColorManagementSurfaceFeedbackV1,
+        _data: &Weak<WlSurface>,
+    ) {
+        let feedbacks = &mut state.color_management_state().feedbacks;
+        feedbacks.retain(|x| x != resource);
+    }
+}
+
+impl<D> Dispatch<WpImageDescriptionCreatorParamsV1, Mutex<ImageDescriptionParams>, D>
+    for ColorManagementState
+where
+    D: Dispatch<WpImageDescriptionCreatorParamsV1, Mutex<ImageDescriptionParams>>,
+    D: Dispatch<WpImageDescriptionV1, ImageDescriptionData>,
+    D: ColorManagementHandler,
+    D: 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        resource: &WpImageDescriptionCreatorParamsV1,
+        request: <WpImageDescriptionCreatorParamsV1 as Resource>::Request,
+        params: &Mutex<ImageDescriptionParams>,
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        use wp_image_description_creator_params_v1::{Error, Request};
+
+        let mut params = params.lock().unwrap();
+
+        let already_set = |resource: &WpImageDescriptionCreatorParamsV1| {
+            resource.post_error(Error::AlreadySet, "the property was already set");
+        };
+
+        match request {
+            Request::Create { image_description } => {
+                let (Some(tf), Some(primaries)) = (params.tf, params.primaries) else {
+                    resource.post_error(
+                        Error::IncompleteSet,
+                        "the transfer function and primaries must be set",
+                    );
+                    return;
+                };
+
+                let luminances = params
+                    .luminances
+                    .unwrap_or_else(|| Luminances::default_for(tf));
+                let description = ColorDescription {
+                    primaries,
+                    tf,
+                    luminances,
+                    mastering_display: MasteringDisplay {
+                        primaries: params.mastering_primaries,
+                        luminance: params.mastering_luminance,
+                    },
+                    max_cll: params.max_cll,
+                    max_fall: params.max_fall,
+                };
+
+                let data = ImageDescriptionData {
+                    description: Some(Arc::new(description)),
+                    allows_info: false,
+                };
+                data_init
+                    .init(image_description, data)
+                    .ready(next_identity());
+            }
+            Request::SetTfNamed { tf } => {
+                if params.tf.is_some() {
+                    already_set(resource);
+                    return;
+                }
+
+                let tf = match tf {
+                    WEnum::Value(wp_color_manager_v1::TransferFunction::Srgb) => {
+                        TransferFunction::Srgb
+                    }
+                    WEnum::Value(wp_color_manager_v1::TransferFunction::Gamma22) => {
+                        TransferFunction::Power(2.2)
+                    }
+                    WEnum::Value(wp_color_manager_v1::TransferFunction::Gamma28) => {
+                        TransferFunction::Power(2.8)
+                    }
+                    // BT.1886 with a zero black level is a pure 2.4 power function.
+                    WEnum::Value(wp_color_manager_v1::TransferFunction::Bt1886) => {
+                        TransferFunction::Power(2.4)
+                    }
+                    WEnum::Value(wp_color_manager_v1::TransferFunction::ExtLinear) => {
+                        TransferFunction::Power(1.)
+                    }
+                    WEnum::Value(wp_color_manager_v1::TransferFunction::St2084Pq) => {
+                        TransferFunction::Pq
+                    }
+                    _ => {
+                        resource.post_error(Error::InvalidTf, "unsupported transfer function");
+                        return;
+                    }
+                };
+                params.tf = Some(tf);
+            }
+            Request::SetTfPower { eexp } => {
+                if params.tf.is_some() {
+                    already_set(resource);
+                    return;
+                }
+
+                if !(10000..=100000).contains(&eexp) {
+                    resource.post_error(Error::InvalidTf, "exponent must be between 1 and 10");
+                    return;
+                }
+                params.tf = Some(TransferFunction::Power(eexp as f32 / 10000.));
+            }
+            Request::SetPrimariesNamed { primaries } => {
+                if params.primaries.is_some() {
+                    already_set(resource);
+                    return;
+                }
+
+                let primaries = match primaries {
+                    WEnum::Value(wp_color_manager_v1::Primaries::Srgb) => Primaries::SRGB,
+                    WEnum::Value(wp_color_manager_v1::Primaries::Bt2020) => Primaries::BT2020,
+                    WEnum::Value(wp_color_manager_v1::Primaries::DisplayP3) => {
+                        Primaries::DISPLAY_P3
+                    }
+                    WEnum::Value(wp_color_manager_v1::Primaries::AdobeRgb) => Primaries::ADOBE_RGB,
+                    _ => {
+                        resource.post_error(
+                            Error::InvalidPrimariesNamed,
+                            "unsupported named primaries",
+                        );
+                        return;
+                    }
+                };
+                params.primaries = Some(primaries);
+            }
+            Request::SetPrimaries {
+                r_x,
+                r_y,
+                g_x,
+                g_y,
+                b_x,
+                b_y,
+                w_x,
+                w_y,
+            } => {
+                if params.primaries.is_some() {
+                    already_set(resource);
+                    return;
+                }
+
+                params.primaries = Some(primaries_from_wire([
+                    r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y,
+                ]));
+            }
+            Request::SetLuminances {
+                min_lum,
+                max_lum,
+                reference_lum,
+            } => {
+                if params.luminances.is_some() {
+                    already_set(resource);
+                    return;
+                }
+
+                let min = min_lum as f32 / 10000.;
+                let max = max_lum as f32;
+                let reference = reference_lum as f32;
+                if max <= min || reference <= min {
+                    resource.post_error(Error::InvalidLuminance, "invalid luminances");
+                    return;
+                }
+                params.luminances = Some(Luminances {
+                    min,
+                    max,
+                    reference,
+                });
+            }
+            Request::SetMasteringDisplayPrimaries {
+                r_x,
+                r_y,
+                g_x,
+                g_y,
+                b_x,
+                b_y,
+                w_x,
+                w_y,
+            } => {
+                if params.mastering_primaries.is_some() {
+                    already_set(resource);
+                    return;
+                }
+
+                params.mastering_primaries = Some(primaries_from_wire([
+                    r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y,
+                ]));
+            }
+            Request::SetMasteringLuminance { min_lum, max_lum } => {
+                if params.mastering_luminance.is_some() {
+                    already_set(resource);
+                    return;
+                }
+
+                let min = min_lum as f32 / 10000.;
+                let max = max_lum as f32;
+                if max <= min {
+                    resource.post_error(Error::InvalidLuminance, "invalid mastering luminance");
+                    return;
+                }
+                params.mastering_luminance = Some((min, max));
+            }
+            Request::SetMaxCll { max_cll } => {
+                if params.max_cll.is_some() {
+                    already_set(resource);
+                    return;
+                }
+                params.max_cll = Some(max_cll as f32);
+            }
+            Request::SetMaxFall { max_fall } => {
+                if params.max_fall.is_some() {
+                    already_set(resource);
+                    return;
+                }
+                params.max_fall = Some(max_fall as f32);
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<WpImageDescriptionV1, ImageDescriptionData, D> for ColorManagementState
+where
+    D: Dispatch<WpImageDescriptionV1, ImageDescriptionData>,
+    D: Dispatch<WpImageDescriptionInfoV1, ()>,
+    D: ColorManagementHandler,
+    D: 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        resource: &WpImageDescriptionV1,
+        request: <WpImageDescriptionV1 as Resource>::Request,
+        data: &ImageDescriptionData,
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wp_image_description_v1::Request::GetInformation { information } => {
+                let Some(description) = &data.description else {
+                    resource.post_error(
+                        wp_image_description_v1::Error::NotReady,
+                        "the image description failed",
+                    );
+                    return;
+                };
+                if !data.allows_info {
+                    resource.post_error(
+                        wp_image_description_v1::Error::NoInformation,
+                        "the image description has no information",
+                    );
+                    return;
+                }
+
+                let info = data_init.init(information, ());
+                send_information(&info, description);
+            }
+            wp_image_description_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<WpImageDescriptionInfoV1, (), D> for ColorManagementState
+where
+    D: Dispatch<WpImageDescriptionInfoV1, ()>,
+    D: 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _resource: &WpImageDescriptionInfoV1,
+        _request: <WpImageDescriptionInfoV1 as Resource>::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        // The info object has no requests.
+    }
+}
+
+fn primaries_from_wire(values: [i32; 8]) -> Primaries {
+    let xy = |x: i32, y: i32| Chromaticity {
+        x: x as f32 / 1_000_000.,
+        y: y as f32 / 1_000_000.,
+    };
+    Primaries {
+        red: xy(values[0], values[1]),
+        green: xy(values[2], values[3]),
+        blue: xy(values[4], values[5]),
+        white: xy(values[6], values[7]),
+    }
+}
+
+fn primaries_to_wire(primaries: &Primaries) -> [i32; 8] {
+    let c = |value: f32| (value * 1_000_000.).round() as i32;
+    [
+        c(primaries.red.x),
+        c(primaries.red.y),
+        c(primaries.green.x),
+        c(primaries.green.y),
+        c(primaries.blue.x),
+        c(primaries.blue.y),
+        c(primaries.white.x),
+        c(primaries.white.y),
+    ]
+}
+
+fn send_information(info: &WpImageDescriptionInfoV1, description: &ColorDescription) {
+    let [r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y] = primaries_to_wire(&description.primaries);
+    info.primaries(r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y);
+
+    match description.tf {
+        TransferFunction::Srgb => info.tf_named(wp_color_manager_v1::TransferFunction::Srgb),
+        TransferFunction::Pq => info.tf_named(wp_color_manager_v1::TransferFunction::St2084Pq),
+        TransferFunction::Power(exp) => info.tf_power((exp * 10000.).round() as u32),
+    }
+
+    let Luminances {
+        min,
+        max,
+        reference,
+    } = description.luminances;
+    info.luminances((min * 10000.).round() as u32, max as u32, reference as u32);
+
+    // Without mastering display metadata, the target color volume is the primary one.
+    let mastering = &description.mastering_display;
+    let target = mastering.primaries.unwrap_or(description.primaries);
+    let [r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y] = primaries_to_wire(&target);
+    info.target_primaries(r_x, r_y, g_x, g_y, b_x, b_y, w_x, w_y);
+
+    let (min, max) = mastering.luminance.unwrap_or((min, max));
+    info.target_luminance((min * 10000.).round() as u32, max as u32);
+
+    if let Some(max_cll) = description.max_cll {
+        info.target_max_cll(max_cll as u32);
+    }
+    if let Some(max_fall) = description.max_fall {
+        info.target_max_fall(max_fall as u32);
+    }
+
+    info.done();
+}
+
+#[macro_export]
+macro_rules! delegate_color_management {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_color_manager_v1::WpColorManagerV1: $crate::protocols::color_management::ColorManagementGlobalData
+        ] => $crate::protocols::color_management::ColorManagementState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_color_manager_v1::WpColorManagerV1: ()
+        ] => $crate::protocols::color_management::ColorManagementState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_color_management_output_v1::WpColorManagementOutputV1: Option<smithay::output::Output>
+        ] => $crate::protocols::color_management::ColorManagementState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_color_management_surface_v1::WpColorManagementSurfaceV1: smithay::reexports::wayland_server::Weak<smithay::reexports::wayland_server::protocol::wl_surface::WlSurface>
+        ] => $crate::protocols::color_management::ColorManagementState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_color_management_surface_feedback_v1::WpColorManagementSurfaceFeedbackV1: smithay::reexports::wayland_server::Weak<smithay::reexports::wayland_server::protocol::wl_surface::WlSurface>
+        ] => $crate::protocols::color_management::ColorManagementState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_image_description_creator_params_v1::WpImageDescriptionCreatorParamsV1: std::sync::Mutex<$crate::protocols::color_management::ImageDescriptionParams>
+        ] => $crate::protocols::color_management::ColorManagementState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_image_description_v1::WpImageDescriptionV1: $crate::protocols::color_management::ImageDescriptionData
+        ] => $crate::protocols::color_management::ColorManagementState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::wp::color_management::v1::server::wp_image_description_info_v1::WpImageDescriptionInfoV1: ()
+        ] => $crate::protocols::color_management::ColorManagementState);
+    };
+}
@@ -0,0 +1,103 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+use smithay::reexports::wayland_protocols::ext::foreign_toplevel_list::v1::server::ext_foreign_toplevel_handle_v1::ExtForeignToplevelHandleV1;
+use smithay::reexports::wayland_server::{Client, DisplayHandle};
+use smithay::wayland::foreign_toplevel_list::{ForeignToplevelHandle, ForeignToplevelListState};
+
+use crate::niri::State;
+use crate::utils::with_toplevel_role;
+use crate::window::mapped::MappedId;
+
+/// ext-foreign-toplevel-list handles for mapped windows.
+///
+/// The protocol itself is implemented in Smithay. This keeps the list in sync with the layout, and
+/// lets other protocols, like the toplevel image capture source, find the window for a handle.
+pub struct ExtForeignToplevelState {
+    list: ForeignToplevelListState,
+    handles: HashMap<MappedId, ForeignToplevelHandle>,
+}
+
+impl ExtForeignToplevelState {
+    pub fn new<F>(display: &DisplayHandle, filter: F) -> Self
+    where
+        F: for<'c> Fn(&'c Client) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            list: ForeignToplevelListState::new_with_filter::<State>(display, filter),
+            handles: HashMap::new(),
+        }
+    }
+
+    pub fn list_state(&mut self) -> &mut ForeignToplevelListState {
+        &mut self.list
+    }
+
+    /// Returns the window that the handle refers to, if it's still open.
+    pub fn window_for_handle(&self, resource: &ExtForeignToplevelHandleV1) -> Option<MappedId> {
+        let handle = ForeignToplevelHandle::from_resource(resource)?;
+        let identifier = handle.identifier();
+        self.handles
+            .iter()
+            .find(|(_, h)| h.identifier() == identifier)
+            .map(|(id, _)| *id)
+    }
+}
+
+pub fn refresh(state: &mut State) {
+    let _span = tracy_client::span!("ext_foreign_toplevel::refresh");
+
+    let protocol_state = &mut state.niri.ext_foreign_toplevel_state;
+
+    // Handle new and existing windows.
+    let mut alive = HashSet::new();
+    state.niri.layout.with_windows(|mapped, _, _| {
+        alive.insert(mapped.id());
+
+        with_toplevel_role(mapped.toplevel(), |role| {
+            let title = role.title.clone().unwrap_or_default();
+            let app_id = role.app_id.clone().unwrap_or_default();
+
+            match protocol_state.handles.entry(mapped.id()) {
+                Entry::Occupied(entry) => {
+                    let handle = entry.get();
+
+                    let mut changed = false;
+                    if handle.title() != title {
+                        handle.send_title(&title);
+                        changed = true;
+                    }
+                    if handle.app_id() != app_id {
+                        handle.send_app_id(&app_id);
+                        changed = true;
+                    }
+
+                    if changed {
+                        handle.send_done();
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    let handle = protocol_state.list.new_toplevel::<State>(title, app_id);
+                    entry.insert(handle);
+                }
+            }
+        });
+    });
+
+    // Handle closed windows.
+    let mut closed = Vec::new();
+    protocol_state.handles.retain(|id, handle| {
+        if alive.contains(id) {
+            return true;
+        }
+
+        // This sends the closed event.
+        protocol_state.list.remove_toplevel(handle);
+        closed.push(*id);
+        false
+    });
+
+    for id in closed {
+        state.niri.image_copy_capture_state.stop_window_sessions(id);
+    }
+}
@@ -0,0 +1,785 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use smithay::output::Output;
+use smithay::reexports::wayland_protocols::ext::foreign_toplevel_list::v1::server::ext_foreign_toplevel_handle_v1::ExtForeignToplevelHandleV1;
+use smithay::reexports::wayland_protocols::ext::image_capture_source::v1::server::ext_foreign_toplevel_image_capture_source_manager_v1::ExtForeignToplevelImageCaptureSourceManagerV1;
+use smithay::reexports::wayland_protocols::ext::image_capture_source::v1::server::ext_image_capture_source_v1::ExtImageCaptureSourceV1;
+use smithay::reexports::wayland_protocols::ext::image_capture_source::v1::server::ext_output_image_capture_source_manager_v1::ExtOutputImageCaptureSourceManagerV1;
+use smithay::reexports::wayland_protocols::ext::image_capture_source::v1::server::{
+    ext_foreign_toplevel_image_capture_source_manager_v1, ext_image_capture_source_v1,
+    ext_output_image_capture_source_manager_v1,
+};
+use smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_cursor_session_v1::ExtImageCopyCaptureCursorSessionV1;
+use smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_frame_v1::{
+    ExtImageCopyCaptureFrameV1, FailureReason,
+};
+use smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_manager_v1::{
+    ExtImageCopyCaptureManagerV1, Options,
+};
+use smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_session_v1::ExtImageCopyCaptureSessionV1;
+use smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::{
+    ext_image_copy_capture_cursor_session_v1, ext_image_copy_capture_frame_v1,
+    ext_image_copy_capture_manager_v1, ext_image_copy_capture_session_v1,
+};
+use smithay::reexports::wayland_server::backend::ClientId;
+use smithay::reexports::wayland_server::protocol::wl_buffer::WlBuffer;
+use smithay::reexports::wayland_server::protocol::wl_shm::Format;
+use smithay::reexports::wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, WEnum,
+};
+use smithay::utils::{Physical, Point, Size, Transform};
+
+use super::screencopy::{
+    Screencopy, ScreencopyBuffer, ScreencopyFrameInfo, ScreencopyQueue, ScreencopySource,
+};
+use crate::window::mapped::MappedId;
+
+const VERSION: u32 = 1;
+
+/// What an image capture source refers to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageCaptureSource {
+    Output(Output),
+    Toplevel(MappedId),
+}
+
+/// Current parameters for capturing a source.
+pub struct CaptureConstraints {
+    /// Output that the source is currently on, or `None` for a window that isn't on any output.
+    pub output: Option<Output>,
+    /// Size of the buffer to capture into.
+    pub buffer_size: Size<i32, Physical>,
+    /// Transform of the captured contents.
+    pub transform: Transform,
+}
+
+#[derive(Default)]
+pub struct ImageCopyCaptureState {
+    /// Frames waiting for damage, one queue and damage tracker per capture session.
+    queues: HashMap<ExtImageCopyCaptureSessionV1, ScreencopyQueue>,
+    cursor_sessions: Vec<CursorSession>,
+}
+
+struct CursorSession {
+    session: ExtImageCopyCaptureCursorSessionV1,
+    source: ImageCaptureSource,
+    /// Last sent cursor position and hotspot, if the cursor is over the source.
+    sent: Option<(Point<i32, Physical>, Point<i32, Physical>)>,
+}
+
+pub struct ImageCopyCaptureGlobalData {
+    filter: Box<dyn for<'c> Fn(&'c Client) -> bool + Send + Sync>,
+}
+
+pub struct ImageCaptureSourceData {
+    /// The source, or `None` if it was already gone when created.
+    source: Option<ImageCaptureSource>,
+}
+
+pub struct ImageCopyCaptureSessionData {
+    source: Option<ImageCaptureSource>,
+    /// Whether this session captures the cursor image rather than the source itself.
+    cursor: bool,
+    paint_cursors: bool,
+    inner: Mutex<SessionInner>,
+}
+
+struct SessionInner {
+    /// Last sent buffer size, or `None` if the session is stopped.
+    buffer_size: Option<Size<i32, Physical>>,
+    has_frame: bool,
+}
+
+pub struct ImageCopyCaptureFrameData {
+    session: ExtImageCopyCaptureSessionV1,
+    inner: Mutex<FrameInner>,
+}
+
+#[derive(Default)]
+struct FrameInner {
+    buffer: Option<WlBuffer>,
+    captured: bool,
+}
+
+pub struct ImageCopyCaptureCursorSessionData {
+    source: Option<ImageCaptureSource>,
+    has_capture_session: AtomicBool,
+}
+
+/// Handler trait for ext-image-copy-capture.
+pub trait ImageCopyCaptureHandler:
+    GlobalDispatch<ExtImageCopyCaptureManagerV1, ImageCopyCaptureGlobalData>
+    + GlobalDispatch<ExtOutputImageCaptureSourceManagerV1, ImageCopyCaptureGlobalData>
+    + GlobalDispatch<ExtForeignToplevelImageCaptureSourceManagerV1, ImageCopyCaptureGlobalData>
+    + Dispatch<ExtImageCopyCaptureManagerV1, ()>
+    + Dispatch<ExtOutputImageCaptureSourceManagerV1, ()>
+    + Dispatch<ExtForeignToplevelImageCaptureSourceManagerV1, ()>
+    + Dispatch<ExtImageCaptureSourceV1, ImageCaptureSourceData>
+    + Dispatch<ExtImageCopyCaptureSessionV1, ImageCopyCaptureSessionData>
+    + Dispatch<ExtImageCopyCaptureFrameV1, ImageCopyCaptureFrameData>
+    + Dispatch<ExtImageCopyCaptureCursorSessionV1, ImageCopyCaptureCursorSessionData>
+    + 'static
+{
+    fn image_copy_capture_state(&mut self) -> &mut ImageCopyCaptureState;
+
+    /// Returns the window that a foreign toplevel handle refers to.
+    fn window_for_toplevel_handle(
+        &mut self,
+        handle: &ExtForeignToplevelHandleV1,
+    ) -> Option<MappedId>;
+
+    /// Returns the current parameters for capturing the source or its cursor.
+    ///
+    /// `None` means that the source cannot be captured anymore.
+    fn capture_constraints(
+        &mut self,
+        source: &ImageCaptureSource,
+        cursor: bool,
+    ) -> Option<CaptureConstraints>;
+
+    /// Handles a new frame waiting to be captured.
+    fn frame(&mut self, session: &ExtImageCopyCaptureSessionV1, screencopy: Screencopy);
+}
+
+impl ImageCopyCaptureState {
+    pub fn new<D, F>(display: &DisplayHandle, filter: F) -> Self
+    where
+        D: ImageCopyCaptureHandler,
+        F: for<'c> Fn(&'c Client) -> bool + Send + Sync + Clone + 'static,
+    {
+        let global_data = |filter: F| ImageCopyCaptureGlobalData {
+            filter: Box::new(filter),
+        };
+        display.create_global::<D, ExtImageCopyCaptureManagerV1, _>(
+            VERSION,
+            global_data(filter.clone()),
+        );
+        display.create_global::<D, ExtOutputImageCaptureSourceManagerV1, _>(
+            VERSION,
+            global_data(filter.clone()),
+        );
+        display.create_global::<D, ExtForeignToplevelImageCaptureSourceManagerV1, _>(
+            VERSION,
+            global_data(filter),
+        );
+
+        Self {
+            queues: HashMap::new(),
+            cursor_sessions: Vec::new(),
+        }
+    }
+
+    pub fn get_queue_mut(
+        &mut self,
+        session: &ExtImageCopyCaptureSessionV1,
+    ) -> Option<&mut ScreencopyQueue> {
+        self.queues.get_mut(session)
+    }
+
+    pub fn queues_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (&ExtImageCopyCaptureSessionV1, &mut ScreencopyQueue)> {
+        self.queues.iter_mut()
+    }
+
+    pub fn remove_output(&mut self, output: &Output) {
+        self.stop_sessions(|source| matches!(source, ImageCaptureSource::Output(o) if o == output));
+
+        for queue in self.queues.values_mut() {
+            queue.remove_output(output);
+        }
+    }
+
+    pub fn stop_window_sessions(&mut self, id: MappedId) {
+        self.stop_sessions(|source| *source == ImageCaptureSource::Toplevel(id));
+    }
+
+    fn stop_sessions(&mut self, matches: impl Fn(&ImageCaptureSource) -> bool) {
+        self.queues.retain(|session, queue| {
+            let data = session.data::<ImageCopyCaptureSessionData>().unwrap();
+            if !data.source.as_ref().is_some_and(&matches) {
+                return true;
+            }
+
+            for screencopy in queue.drain() {
+                screencopy.stop();
+            }
+
+            data.inner.lock().unwrap().buffer_size = None;
+            session.stopped();
+            false
+        });
+
+        self.cursor_sessions
+            .retain(|cursor| !matches(&cursor.source));
+    }
+
+    /// Sends cursor position updates to cursor sessions.
+    ///
+    /// The callback returns the cursor position and hotspot in the source's buffer coordinates,
+    /// or `None` if the cursor isn't over the source.
+    pub fn update_cursor_sessions(
+        &mut self,
+        mut cursor: impl FnMut(
+            &ImageCaptureSource,
+        ) -> Option<(Point<i32, Physical>, Point<i32, Physical>)>,
+    ) {
+        for session in &mut self.cursor_sessions {
+            let new = cursor(&session.source);
+            if session.sent == new {
+                continue;
+            }
+
+            match (session.sent, new) {
+                (_, None) => session.session.leave(),
+                (old, Some((position, hotspot))) => {
+                    if old.is_none() {
+                        session.session.enter();
+                    }
+                    if old.map(|(p, _)| p) != Some(position) {
+                        session.session.position(position.x, position.y);
+                    }
+                    if old.map(|(_, h)| h) != Some(hotspot) {
+                        session.session.hotspot(hotspot.x, hotspot.y);
+                    }
+                }
+            }
+
+            session.sent = new;
+        }
+    }
+}
+
+/// Sends new buffer constraints to a session, for example after the source was resized.
+pub fn send_buffer_size(session: &ExtImageCopyCaptureSessionV1, buffer_size: Size<i32, Physical>) {
+    let data = session.data::<ImageCopyCaptureSessionData>().unwrap();
+    let mut inner = data.inner.lock().unwrap();
+    if inner.buffer_size.is_none() || inner.buffer_size == Some(buffer_size) {
+        return;
+    }
+
+    inner.buffer_size = Some(buffer_size);
+    send_constraints(session, buffer_size);
+}
+
+fn send_constraints(session: &ExtImageCopyCaptureSessionV1, buffer_size: Size<i32, Physical>) {
+    session.buffer_size(buffer_size.w as u32, buffer_size.h as u32);
+    // FIXME: advertise DMA-BUF constraints. Until then, DMA-BUFs are accepted if they match the
+    // same constraints as in wlr-screencopy.
+    session.shm_format(Format::Xrgb8888);
+    session.done();
+}
+
+fn init_session<D: ImageCopyCaptureHandler>(
+    state: &mut D,
+    data_init: &mut DataInit<'_, D>,
+    session: New<ExtImageCopyCaptureSessionV1>,
+    source: Option<ImageCaptureSource>,
+    cursor: bool,
+    paint_cursors: bool,
+) {
+    let constraints = source
+        .as_ref()
+        .and_then(|source| state.capture_constraints(source, cursor));
+
+    let data = ImageCopyCaptureSessionData {
+        source,
+        cursor,
+        paint_cursors,
+        inner: Mutex::new(SessionInner {
+            buffer_size: constraints.as_ref().map(|c| c.buffer_size),
+            has_frame: false,
+        }),
+    };
+    let session = data_init.init(session, data);
+
+    match constraints {
+        Some(constraints) => {
+            send_constraints(&session, constraints.buffer_size);
+            state
+                .image_copy_capture_state()
+                .queues
+                .insert(session, ScreencopyQueue::new());
+        }
+        None => {
+            trace!("image copy capture source is gone");
+            session.stopped();
+        }
+    }
+}
+
+impl<D: ImageCopyCaptureHandler>
+    GlobalDispatch<ExtImageCopyCaptureManagerV1, ImageCopyCaptureGlobalData, D>
+    for ImageCopyCaptureState
+{
+    fn bind(
+        _state: &mut D,
+        _display: &DisplayHandle,
+        _client: &Client,
+        manager: New<ExtImageCopyCaptureManagerV1>,
+        _global_data: &ImageCopyCaptureGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(manager, ());
+    }
+
+    fn can_view(client: Client, global_data: &ImageCopyCaptureGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D: ImageCopyCaptureHandler>
+    GlobalDispatch<ExtOutputImageCaptureSourceManagerV1, ImageCopyCaptureGlobalData, D>
+    for ImageCopyCaptureState
+{
+    fn bind(
+        _state: &mut D,
+        _display: &DisplayHandle,
+        _client: &Client,
+        manager: New<ExtOutputImageCaptureSourceManagerV1>,
+        _global_data: &ImageCopyCaptureGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(manager, ());
+    }
+
+    fn can_view(client: Client, global_data: &ImageCopyCaptureGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D: ImageCopyCaptureHandler>
+    GlobalDispatch<ExtForeignToplevelImageCaptureSourceManagerV1, ImageCopyCaptureGlobalData, D>
+    for ImageCopyCaptureState
+{
+    fn bind(
+        _state: &mut D,
+        _display: &DisplayHandle,
+        _client: &Client,
+        manager: New<ExtForeignToplevelImageCaptureSourceManagerV1>,
+        _global_data: &ImageCopyCaptureGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(manager, ());
+    }
+
+    fn can_view(client: Client, global_data: &ImageCopyCaptureGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D: ImageCopyCaptureHandler> Dispatch<ExtOutputImageCaptureSourceManagerV1, (), D>
+    for ImageCopyCaptureState
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _manager: &ExtOutputImageCaptureSourceManagerV1,
+        request: ext_output_image_capture_source_manager_v1::Request,
+        _data: &(),
+        _display: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_output_image_capture_source_manager_v1::Request::CreateSource {
+                source,
+                output,
+            } => {
+                let source_ = Output::from_resource(&output).map(ImageCaptureSource::Output);
+                data_init.init(source, ImageCaptureSourceData { source: source_ });
+            }
+            ext_output_image_capture_source_manager_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D: ImageCopyCaptureHandler> Dispatch<ExtForeignToplevelImageCaptureSourceManagerV1, (), D>
+    for ImageCopyCaptureState
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _manager: &ExtForeignToplevelImageCaptureSourceManagerV1,
+        request: ext_foreign_toplevel_image_capture_source_manager_v1::Request,
+        _data: &(),
+        _display: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_foreign_toplevel_image_capture_source_manager_v1::Request::CreateSource {
+                source,
+                toplevel_handle,
+            } => {
+                let source_ = state
+                    .window_for_toplevel_handle(&toplevel_handle)
+                    .map(ImageCaptureSource::Toplevel);
+                data_init.init(source, ImageCaptureSourceData { source: source_ });
+            }
+            ext_foreign_toplevel_image_capture_source_manager_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D: ImageCopyCaptureHandler> Dispatch<ExtImageCaptureSourceV1, ImageCaptureSourceData, D>
+    for ImageCopyCaptureState
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _source: &ExtImageCaptureSourceV1,
+        request: ext_image_capture_source_v1::Request,
+        _data: &ImageCaptureSourceData,
+        _display: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_image_capture_source_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D: ImageCopyCaptureHandler> Dispatch<ExtImageCopyCaptureManagerV1, (), D>
+    for ImageCopyCaptureState
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _manager: &ExtImageCopyCaptureManagerV1,
+        request: ext_image_copy_capture_manager_v1::Request,
+        _data: &(),
+        _display: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_image_copy_capture_manager_v1::Request::CreateSession {
+                session,
+                source,
+                options,
+            } => {
+                let source = source
+                    .data::<ImageCaptureSourceData>()
+                    .unwrap()
+                    .source
+                    .clone();
+                let paint_cursors = match options {
+                    WEnum::Value(options) => options.contains(Options::PaintCursors),
+                    WEnum::Unknown(_) => false,
+                };
+                init_session(state, data_init, session, source, false, paint_cursors);
+            }
+            ext_image_copy_capture_manager_v1::Request::CreatePointerCursorSession {
+                session,
+                source,
+                pointer: _,
+            } => {
+                let source = source
+                    .data::<ImageCaptureSourceData>()
+                    .unwrap()
+                    .source
+                    .clone();
+                let data = ImageCopyCaptureCursorSessionData {
+                    source: source.clone(),
+                    has_capture_session: AtomicBool::new(false),
+                };
+                let session = data_init.init(session, data);
+
+                if let Some(source) = source {
+                    state
+                        .image_copy_capture_state()
+                        .cursor_sessions
+                        .push(CursorSession {
+                            session,
+                            source,
+                            sent: None,
+                        });
+                }
+            }
+            ext_image_copy_capture_manager_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D: ImageCopyCaptureHandler>
+    Dispatch<ExtImageCopyCaptureCursorSessionV1, ImageCopyCaptureCursorSessionData, D>
+    for ImageCopyCaptureState
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        cursor_session: &ExtImageCopyCaptureCursorSessionV1,
+        request: ext_image_copy_capture_cursor_session_v1::Request,
+        data: &ImageCopyCaptureCursorSessionData,
+        _display: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_image_copy_capture_cursor_session_v1::Request::GetCaptureSession { session } => {
+                if data.has_capture_session.swap(true, Ordering::SeqCst) {
+                    cursor_session.post_error(
+                        ext_image_copy_capture_cursor_session_v1::Error::DuplicateSession,
+                        "capture session was already requested",
+                    );
+                    return;
+                }
+
+                init_session(state, data_init, session, data.source.clone(), true, false);
+            }
+            ext_image_copy_capture_cursor_session_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        cursor_session: &ExtImageCopyCaptureCursorSessionV1,
+        _data: &ImageCopyCaptureCursorSessionData,
+    ) {
+        state
+            .image_copy_capture_state()
+            .cursor_sessions
+            .retain(|x| x.session != *cursor_session);
+    }
+}
+
+impl<D: ImageCopyCaptureHandler>
+    Dispatch<ExtImageCopyCaptureSessionV1, ImageCopyCaptureSessionData, D>
+    for ImageCopyCaptureState
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        session: &ExtImageCopyCaptureSessionV1,
+        request: ext_image_copy_capture_session_v1::Request,
+        data: &ImageCopyCaptureSessionData,
+        _display: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_image_copy_capture_session_v1::Request::CreateFrame { frame } => {
+                let mut inner = data.inner.lock().unwrap();
+                if inner.has_frame {
+                    session.post_error(
+                        ext_image_copy_capture_session_v1::Error::DuplicateFrame,
+                        "session already has a frame",
+                    );
+                    return;
+                }
+                inner.has_frame = true;
+
+                data_init.init(
+                    frame,
+                    ImageCopyCaptureFrameData {
+                        session: session.clone(),
+                        inner: Mutex::new(FrameInner::default()),
+                    },
+                );
+            }
+            ext_image_copy_capture_session_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        session: &ExtImageCopyCaptureSessionV1,
+        _data: &ImageCopyCaptureSessionData,
+    ) {
+        state.image_copy_capture_state().queues.remove(session);
+    }
+}
+
+impl<D: ImageCopyCaptureHandler> Dispatch<ExtImageCopyCaptureFrameV1, ImageCopyCaptureFrameData, D>
+    for ImageCopyCaptureState
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        frame: &ExtImageCopyCaptureFrameV1,
+        request: ext_image_copy_capture_frame_v1::Request,
+        data: &ImageCopyCaptureFrameData,
+        _display: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        let mut inner = data.inner.lock().unwrap();
+
+        match request {
+            ext_image_copy_capture_frame_v1::Request::AttachBuffer { buffer } => {
+                if inner.captured {
+                    frame.post_error(
+                        ext_image_copy_capture_frame_v1::Error::AlreadyCaptured,
+                        "frame was already captured",
+                    );
+                    return;
+                }
+
+                inner.buffer = Some(buffer);
+            }
+            ext_image_copy_capture_frame_v1::Request::DamageBuffer {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                if inner.captured {
+                    frame.post_error(
+                        ext_image_copy_capture_frame_v1::Error::AlreadyCaptured,
+                        "frame was already captured",
+                    );
+                    return;
+                }
+
+                if x < 0 || y < 0 || width <= 0 || height <= 0 {
+                    frame.post_error(
+                        ext_image_copy_capture_frame_v1::Error::InvalidBufferDamage,
+                        "invalid buffer damage",
+                    );
+                    return;
+                }
+
+                // We always redraw the whole buffer, so there's no need to track its damage.
+            }
+            ext_image_copy_capture_frame_v1::Request::Capture => {
+                if inner.captured {
+                    frame.post_error(
+                        ext_image_copy_capture_frame_v1::Error::AlreadyCaptured,
+                        "frame was already captured",
+                    );
+                    return;
+                }
+
+                let Some(buffer) = inner.buffer.clone() else {
+                    frame.post_error(
+                        ext_image_copy_capture_frame_v1::Error::NoBuffer,
+                        "no buffer attached",
+                    );
+                    return;
+                };
+
+                inner.captured = true;
+                drop(inner);
+
+                let session = &data.session;
+                let session_data = session.data::<ImageCopyCaptureSessionData>().unwrap();
+
+                let Some(source) = session_data.source.as_ref() else {
+                    frame.failed(FailureReason::Stopped);
+                    return;
+                };
+
+                let Some(constraints) = state.capture_constraints(source, session_data.cursor)
+                else {
+                    frame.failed(FailureReason::Stopped);
+                    return;
+                };
+
+                let buffer_size = session_data.inner.lock().unwrap().buffer_size;
+                let Some(buffer_size) = buffer_size else {
+                    frame.failed(FailureReason::Stopped);
+                    return;
+                };
+
+                // The source may have changed size since the constraints were sent.
+                if constraints.buffer_size != buffer_size {
+                    frame.failed(FailureReason::BufferConstraints);
+                    send_buffer_size(session, constraints.buffer_size);
+                    return;
+                }
+
+                let buffer = match ScreencopyBuffer::from_wl_buffer(&buffer, buffer_size) {
+                    Ok(buffer) => buffer,
+                    Err(msg) => {
+                        trace!("image copy capture client sent {msg}");
+                        frame.failed(FailureReason::BufferConstraints);
+                        return;
+                    }
+                };
+
+                let source = if session_data.cursor {
+                    ScreencopySource::Cursor
+                } else {
+                    match source {
+                        ImageCaptureSource::Output(_) => ScreencopySource::Output,
+                        ImageCaptureSource::Toplevel(id) => ScreencopySource::Window(*id),
+                    }
+                };
+
+                let info = ScreencopyFrameInfo::new(
+                    constraints.output,
+                    source,
+                    buffer_size,
+                    session_data.paint_cursors,
+                );
+                let screencopy =
+                    Screencopy::new_ext(info, frame.clone(), constraints.transform, buffer);
+                state.frame(session, screencopy);
+            }
+            ext_image_copy_capture_frame_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        _state: &mut D,
+        _client: ClientId,
+        _frame: &ExtImageCopyCaptureFrameV1,
+        data: &ImageCopyCaptureFrameData,
+    ) {
+        if let Some(session_data) = data.session.data::<ImageCopyCaptureSessionData>() {
+            session_data.inner.lock().unwrap().has_frame = false;
+        }
+    }
+}
+
+#[allow(missing_docs)]
+#[macro_export]
+macro_rules! delegate_image_copy_capture {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_manager_v1::ExtImageCopyCaptureManagerV1: $crate::protocols::image_copy_capture::ImageCopyCaptureGlobalData
+        ] => $crate::protocols::image_copy_capture::ImageCopyCaptureState);
+
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::image_capture_source::v1::server::ext_output_image_capture_source_manager_v1::ExtOutputImageCaptureSourceManagerV1: $crate::protocols::image_copy_capture::ImageCopyCaptureGlobalData
+        ] => $crate::protocols::image_copy_capture::ImageCopyCaptureState);
+
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::image_capture_source::v1::server::ext_foreign_toplevel_image_capture_source_manager_v1::ExtForeignToplevelImageCaptureSourceManagerV1: $crate::protocols::image_copy_capture::ImageCopyCaptureGlobalData
+        ] => $crate::protocols::image_copy_capture::ImageCopyCaptureState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_manager_v1::ExtImageCopyCaptureManagerV1: ()
+        ] => $crate::protocols::image_copy_capture::ImageCopyCaptureState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::image_capture_source::v1::server::ext_output_image_capture_source_manager_v1::ExtOutputImageCaptureSourceManagerV1: ()
+        ] => $crate::protocols::image_copy_capture::ImageCopyCaptureState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::image_capture_source::v1::server::ext_foreign_toplevel_image_capture_source_manager_v1::ExtForeignToplevelImageCaptureSourceManagerV1: ()
+        ] => $crate::protocols::image_copy_capture::ImageCopyCaptureState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::image_capture_source::v1::server::ext_image_capture_source_v1::ExtImageCaptureSourceV1: $crate::protocols::image_copy_capture::ImageCaptureSourceData
+        ] => $crate::protocols::image_copy_capture::ImageCopyCaptureState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_session_v1::ExtImageCopyCaptureSessionV1: $crate::protocols::image_copy_capture::ImageCopyCaptureSessionData
+        ] => $crate::protocols::image_copy_capture::ImageCopyCaptureState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_frame_v1::ExtImageCopyCaptureFrameV1: $crate::protocols::image_copy_capture::ImageCopyCaptureFrameData
+        ] => $crate::protocols::image_copy_capture::ImageCopyCaptureState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_cursor_session_v1::ExtImageCopyCaptureCursorSessionV1: $crate::protocols::image_copy_capture::ImageCopyCaptureCursorSessionData
+        ] => $crate::protocols::image_copy_capture::ImageCopyCaptureState);
+    };
+}
@@ -1,9 +1,16 @@
+pub mod background_effect;
+pub mod color_management;
+pub mod ext_foreign_toplevel;
 pub mod ext_workspace;
 pub mod foreign_toplevel;
 pub mod gamma_control;
+pub mod image_copy_capture;
 pub mod mutter_x11_interop;
 pub mod output_management;
+pub mod output_power_management;
 pub mod screencopy;
 pub mod virtual_pointer;
+pub mod xdg_dialog;
+pub mod xdg_toplevel_tag;
 
 pub mod raw;
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use smithay::output::Output;
+use smithay::reexports::wayland_protocols_wlr;
+use smithay::reexports::wayland_server::backend::ClientId;
+use smithay::reexports::wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, WEnum,
+};
+use wayland_protocols_wlr::output_power_management::v1::server::{
+    zwlr_output_power_manager_v1, zwlr_output_power_v1,
+};
+use zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1;
+use zwlr_output_power_v1::ZwlrOutputPowerV1;
+
+const VERSION: u32 = 1;
+
+pub struct OutputPowerManagementState {
+    // Active power controls only. Failed ones are removed.
+    power_controls: HashMap<Output, ZwlrOutputPowerV1>,
+}
+
+pub struct OutputPowerManagementGlobalData {
+    filter: Box<dyn for<'c> Fn(&'c Client) -> bool + Send + Sync>,
+}
+
+pub trait OutputPowerManagementHandler {
+    fn output_power_management_state(&mut self) -> &mut OutputPowerManagementState;
+    fn is_output_powered(&mut self, output: &Output) -> bool;
+    fn set_output_powered(&mut self, output: &Output, powered: bool);
+}
+
+impl OutputPowerManagementState {
+    pub fn new<D, F>(display: &DisplayHandle, filter: F) -> Self
+    where
+        D: GlobalDispatch<ZwlrOutputPowerManagerV1, OutputPowerManagementGlobalData>,
+        D: Dispatch<ZwlrOutputPowerManagerV1, ()>,
+        D: Dispatch<ZwlrOutputPowerV1, ()>,
+        D: OutputPowerManagementHandler,
+        D: 'static,
+        F: for<'c> Fn(&'c Client) -> bool + Send + Sync + 'static,
+    {
+        let global_data = OutputPowerManagementGlobalData {
+            filter: Box::new(filter),
+        };
+        display.create_global::<D, ZwlrOutputPowerManagerV1, _>(VERSION, global_data);
+
+        Self {
+            power_controls: HashMap::new(),
+        }
+    }
+
+    pub fn output_removed(&mut self, output: &Output) {
+        if let Some(power_control) = self.power_controls.remove(output) {
+            power_control.failed();
+        }
+    }
+
+    pub fn output_power_changed(&mut self, output: &Output, powered: bool) {
+        if let Some(power_control) = self.power_controls.get(output) {
+            power_control.mode(mode(powered));
+        }
+    }
+}
+
+fn mode(powered: bool) -> zwlr_output_power_v1::Mode {
+    if powered {
+        zwlr_output_power_v1::Mode::On
+    } else {
+        zwlr_output_power_v1::Mode::Off
+    }
+}
+
+impl<D> GlobalDispatch<ZwlrOutputPowerManagerV1, OutputPowerManagementGlobalData, D>
+    for OutputPowerManagementState
+where
+    D: GlobalDispatch<ZwlrOutputPowerManagerV1, OutputPowerManagementGlobalData>,
+    D: Dispatch<ZwlrOutputPowerManagerV1, ()>,
+    D: Dispatch<ZwlrOutputPowerV1, ()>,
+    D: OutputPowerManagementHandler,
+    D: 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        manager: New<ZwlrOutputPowerManagerV1>,
+        _manager_state: &OutputPowerManagementGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(manager, ());
+    }
+
+    fn can_view(client: Client, global_data: &OutputPowerManagementGlobalData) -> bool {
+        (global_data.filter)(&client)
+    }
+}
+
+impl<D> Dispatch<ZwlrOutputPowerManagerV1, (), D> for OutputPowerManagementState
+where
+    D: Dispatch<ZwlrOutputPowerManagerV1, ()>,
+    D: Dispatch<ZwlrOutputPowerV1, ()>,
+    D: OutputPowerManagementHandler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _resource: &ZwlrOutputPowerManagerV1,
+        request: <ZwlrOutputPowerManagerV1 as Resource>::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwlr_output_power_manager_v1::Request::GetOutputPower { id, output } => {
+                let power_control = data_init.init(id, ());
+
+                let Some(output) = Output::from_resource(&output) else {
+                    power_control.failed();
+                    return;
+                };
+
+                // Only one client at a time can control the power of an output.
+                if state
+                    .output_power_management_state()
+                    .power_controls
+                    .contains_key(&output)
+                {
+                    power_control.failed();
+                    return;
+                }
+
+                power_control.mode(mode(state.is_output_powered(&output)));
+                state
+                    .output_power_management_state()
+                    .power_controls
+                    .insert(output, power_control);
+            }
+            zwlr_output_power_manager_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ZwlrOutputPowerV1, (), D> for OutputPowerManagementState
+where
+    D: Dispatch<ZwlrOutputPowerV1, ()>,
+    D: OutputPowerManagementHandler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        resource: &ZwlrOutputPowerV1,
+        request: <ZwlrOutputPowerV1 as Resource>::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwlr_output_power_v1::Request::SetMode { mode } => {
+                let powered = match mode {
+                    WEnum::Value(zwlr_output_power_v1::Mode::On) => true,
+                    WEnum::Value(zwlr_output_power_v1::Mode::Off) => false,
+                    _ => {
+                        resource.post_error(
+                            zwlr_output_power_v1::Error::InvalidMode,
+                            "invalid power mode",
+                        );
+                        return;
+                    }
+                };
+
+                let power_controls = &state.output_power_management_state().power_controls;
+                let Some((output, _)) = power_controls.iter().find(|(_, x)| *x == resource) else {
+                    return;
+                };
+                let output = output.clone();
+
+                trace!(
+                    "setting power for output {} to {}",
+                    output.name(),
+                    if powered { "on" } else { "off" }
+                );
+
+                // The handler reports the resulting mode through output_power_changed().
+                state.set_output_powered(&output, powered);
+            }
+            zwlr_output_power_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(state: &mut D, _client: ClientId, resource: &ZwlrOutputPowerV1, _data: &()) {
+        // The output keeps its power state after the control goes away.
+        let power_controls = &mut state.output_power_management_state().power_controls;
+        power_controls.retain(|_, x| x != resource);
+    }
+}
+
+#[macro_export]
+macro_rules! delegate_output_power_management {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::output_power_management::v1::server::zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1: $crate::protocols::output_power_management::OutputPowerManagementGlobalData
+        ] => $crate::protocols::output_power_management::OutputPowerManagementState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::output_power_management::v1::server::zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1: ()
+        ] => $crate::protocols::output_power_management::OutputPowerManagementState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols_wlr::output_power_management::v1::server::zwlr_output_power_v1::ZwlrOutputPowerV1: ()
+        ] => $crate::protocols::output_power_management::OutputPowerManagementState);
+    };
+}
@@ -10,6 +10,9 @@ use smithay::backend::allocator::{Buffer, Fourcc};
 use smithay::backend::renderer::damage::OutputDamageTracker;
 use smithay::backend::renderer::sync::SyncPoint;
 use smithay::output::Output;
+use smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::server::ext_image_copy_capture_frame_v1::{
+    ExtImageCopyCaptureFrameV1, FailureReason,
+};
 use smithay::reexports::wayland_protocols_wlr::screencopy::v1::server::zwlr_screencopy_frame_v1::{
     Flags, ZwlrScreencopyFrameV1,
 };
@@ -26,6 +29,7 @@ use smithay::utils::{Physical, Point, Rectangle, Size, Transform};
 use smithay::wayland::{dmabuf, shm};
 
 use crate::utils::get_monotonic_time;
+use crate::window::mapped::MappedId;
 
 const VERSION: u32 = 3;
 
@@ -64,9 +68,13 @@ impl ScreencopyQueue {
         self.screencopies.pop().unwrap()
     }
 
+    pub fn drain(&mut self) -> impl Iterator<Item = Screencopy> + '_ {
+        self.screencopies.drain(..)
+    }
+
     pub fn remove_output(&mut self, output: &Output) {
         self.screencopies
-            .retain(|screencopy| screencopy.output() != output);
+            .retain(|screencopy| screencopy.output() != Some(output));
     }
 }
 
@@ -240,7 +248,8 @@ where
         // Create the frame.
         let overlay_cursor = overlay_cursor != 0;
         let info = ScreencopyFrameInfo {
-            output,
+            output: Some(output),
+            source: ScreencopySource::Output,
             overlay_cursor,
             buffer_size,
             region_loc,
@@ -303,12 +312,32 @@ macro_rules! delegate_screencopy {
 
 #[derive(Clone)]
 pub struct ScreencopyFrameInfo {
-    output: Output,
+    /// Output to capture on, or `None` for a window that isn't on any output.
+    output: Option<Output>,
+    source: ScreencopySource,
     buffer_size: Size<i32, Physical>,
     region_loc: Point<i32, Physical>,
     overlay_cursor: bool,
 }
 
+impl ScreencopyFrameInfo {
+    /// Creates the info for capturing a whole source on the output.
+    pub fn new(
+        output: Option<Output>,
+        source: ScreencopySource,
+        buffer_size: Size<i32, Physical>,
+        overlay_cursor: bool,
+    ) -> Self {
+        Self {
+            output,
+            source,
+            buffer_size,
+            region_loc: Point::from((0, 0)),
+            overlay_cursor,
+        }
+    }
+}
+
 pub enum ScreencopyFrameState {
     Failed,
     Pending {
@@ -360,37 +389,12 @@ where
             _ => unreachable!(),
         };
 
-        let size = info.buffer_size;
-
-        let buffer = if let Ok(dmabuf) = dmabuf::get_dmabuf(&buffer) {
-            if dmabuf.format().code == Fourcc::Xrgb8888
-                && dmabuf.width() == size.w as u32
-                && dmabuf.height() == size.h as u32
-            {
-                ScreencopyBuffer::Dmabuf(dmabuf.clone())
-            } else {
-                frame.post_error(
-                    zwlr_screencopy_frame_v1::Error::InvalidBuffer,
-                    "invalid dmabuf parameters",
-                );
+        let buffer = match ScreencopyBuffer::from_wl_buffer(&buffer, info.buffer_size) {
+            Ok(buffer) => buffer,
+            Err(msg) => {
+                frame.post_error(zwlr_screencopy_frame_v1::Error::InvalidBuffer, msg);
                 return;
             }
-        } else if shm::with_buffer_contents(&buffer, |_, shm_len, buffer_data| {
-            buffer_data.format == Format::Xrgb8888
-                && buffer_data.width == size.w
-                && buffer_data.height == size.h
-                && buffer_data.stride == size.w * 4
-                && shm_len == buffer_data.stride as usize * buffer_data.height as usize
-        })
-        .unwrap_or(false)
-        {
-            ScreencopyBuffer::Shm(buffer)
-        } else {
-            frame.post_error(
-                zwlr_screencopy_frame_v1::Error::InvalidBuffer,
-                "invalid buffer",
-            );
-            return;
         };
 
         copied.store(true, Ordering::SeqCst);
@@ -399,7 +403,7 @@ where
             manager,
             Screencopy {
                 buffer,
-                frame: frame.clone(),
+                frame: ScreencopyFrame::Wlr(frame.clone()),
                 info: info.clone(),
                 with_damage,
                 submitted: false,
@@ -415,10 +419,61 @@ pub enum ScreencopyBuffer {
     Shm(WlBuffer),
 }
 
+impl ScreencopyBuffer {
+    /// Checks that a client buffer can receive a capture of the given size.
+    pub fn from_wl_buffer(
+        buffer: &WlBuffer,
+        size: Size<i32, Physical>,
+    ) -> Result<Self, &'static str> {
+        if let Ok(dmabuf) = dmabuf::get_dmabuf(buffer) {
+            if dmabuf.format().code == Fourcc::Xrgb8888
+                && dmabuf.width() == size.w as u32
+                && dmabuf.height() == size.h as u32
+            {
+                Ok(ScreencopyBuffer::Dmabuf(dmabuf.clone()))
+            } else {
+                Err("invalid dmabuf parameters")
+            }
+        } else if shm::with_buffer_contents(buffer, |_, shm_len, buffer_data| {
+            buffer_data.format == Format::Xrgb8888
+                && buffer_data.width == size.w
+                && buffer_data.height == size.h
+                && buffer_data.stride == size.w * 4
+                && shm_len == buffer_data.stride as usize * buffer_data.height as usize
+        })
+        .unwrap_or(false)
+        {
+            Ok(ScreencopyBuffer::Shm(buffer.clone()))
+        } else {
+            Err("invalid buffer")
+        }
+    }
+}
+
+/// What a screencopy captures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScreencopySource {
+    /// The output, or a region of it.
+    Output,
+    /// A single window, on its own.
+    Window(MappedId),
+    /// The cursor image, while the cursor is over the output.
+    Cursor,
+}
+
+/// Protocol object of a screencopy frame.
+enum ScreencopyFrame {
+    Wlr(ZwlrScreencopyFrameV1),
+    Ext {
+        frame: ExtImageCopyCaptureFrameV1,
+        transform: Transform,
+    },
+}
+
 /// Screencopy frame.
 pub struct Screencopy {
     info: ScreencopyFrameInfo,
-    frame: ZwlrScreencopyFrameV1,
+    frame: ScreencopyFrame,
     buffer: ScreencopyBuffer,
     with_damage: bool,
     submitted: bool,
@@ -427,12 +482,33 @@ pub struct Screencopy {
 impl Drop for Screencopy {
     fn drop(&mut self) {
         if !self.submitted {
-            self.frame.failed();
+            match &self.frame {
+                ScreencopyFrame::Wlr(frame) => frame.failed(),
+                ScreencopyFrame::Ext { frame, .. } => frame.failed(FailureReason::Unknown),
+            }
         }
     }
 }
 
 impl Screencopy {
+    /// Creates a screencopy for an ext-image-copy-capture frame.
+    ///
+    /// These frames always wait for damage, which is tracked for each capture session.
+    pub fn new_ext(
+        info: ScreencopyFrameInfo,
+        frame: ExtImageCopyCaptureFrameV1,
+        transform: Transform,
+        buffer: ScreencopyBuffer,
+    ) -> Self {
+        Self {
+            info,
+            frame: ScreencopyFrame::Ext { frame, transform },
+            buffer,
+            with_damage: true,
+            submitted: false,
+        }
+    }
+
     /// Get the target buffer to copy to.
     pub fn buffer(&self) -> &ScreencopyBuffer {
         &self.buffer
@@ -446,39 +522,80 @@ impl Screencopy {
         self.info.buffer_size
     }
 
-    pub fn output(&self) -> &Output {
-        &self.info.output
+    pub fn output(&self) -> Option<&Output> {
+        self.info.output.as_ref()
     }
 
     pub fn overlay_cursor(&self) -> bool {
         self.info.overlay_cursor
     }
 
+    pub fn source(&self) -> ScreencopySource {
+        self.info.source
+    }
+
     pub fn with_damage(&self) -> bool {
         self.with_damage
     }
 
     pub fn damage(&self, damages: impl Iterator<Item = Rectangle<i32, smithay::utils::Buffer>>) {
         for Rectangle { loc, size } in damages {
-            self.frame
-                .damage(loc.x as u32, loc.y as u32, size.w as u32, size.h as u32);
+            match &self.frame {
+                ScreencopyFrame::Wlr(frame) => {
+                    frame.damage(loc.x as u32, loc.y as u32, size.w as u32, size.h as u32)
+                }
+                ScreencopyFrame::Ext { frame, .. } => frame.damage(loc.x, loc.y, size.w, size.h),
+            }
+        }
+    }
+
+    /// Fails the frame because the buffer no longer matches the source.
+    pub fn fail_buffer_constraints(mut self) {
+        match &self.frame {
+            ScreencopyFrame::Wlr(frame) => frame.failed(),
+            ScreencopyFrame::Ext { frame, .. } => frame.failed(FailureReason::BufferConstraints),
+        }
+
+        self.submitted = true;
+    }
+
+    /// Fails the frame because its source went away.
+    pub fn stop(mut self) {
+        match &self.frame {
+            ScreencopyFrame::Wlr(frame) => frame.failed(),
+            ScreencopyFrame::Ext { frame, .. } => frame.failed(FailureReason::Stopped),
         }
+
+        self.submitted = true;
     }
 
     /// Submit the copied content.
     fn submit(mut self, y_invert: bool, timestamp: Duration) {
-        // Notify client that buffer is ordinary.
-        self.frame.flags(if y_invert {
-            Flags::YInvert
-        } else {
-            Flags::empty()
-        });
-
-        // Notify client about successful copy.
         let tv_sec_hi = (timestamp.as_secs() >> 32) as u32;
         let tv_sec_lo = (timestamp.as_secs() & 0xFFFFFFFF) as u32;
         let tv_nsec = timestamp.subsec_nanos();
-        self.frame.ready(tv_sec_hi, tv_sec_lo, tv_nsec);
+
+        match &self.frame {
+            ScreencopyFrame::Wlr(frame) => {
+                // Notify client that buffer is ordinary.
+                frame.flags(if y_invert {
+                    Flags::YInvert
+                } else {
+                    Flags::empty()
+                });
+
+                // Notify client about successful copy.
+                frame.ready(tv_sec_hi, tv_sec_lo, tv_nsec);
+            }
+            ScreencopyFrame::Ext { frame, transform } => {
+                // The ext protocol has no y-invert flag, but it's never set for our buffers.
+                debug_assert!(!y_invert);
+
+                frame.transform((*transform).into());
+                frame.presentation_time(tv_sec_hi, tv_sec_lo, tv_nsec);
+                frame.ready();
+            }
+        }
 
         // Mark frame as submitted to ensure destructor isn't run.
         self.submitted = true;
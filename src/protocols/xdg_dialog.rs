@@ -0,0 +1,133 @@
+use smithay::reexports::wayland_protocols::xdg::dialog::v1::server::xdg_dialog_v1::{
+    self, XdgDialogV1,
+};
+use smithay::reexports::wayland_protocols::xdg::dialog::v1::server::xdg_wm_dialog_v1::{
+    self, XdgWmDialogV1,
+};
+use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel::XdgToplevel;
+use smithay::reexports::wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+use wayland_backend::server::ClientId;
+
+const VERSION: u32 = 1;
+
+pub struct XdgDialogState {}
+
+pub trait XdgDialogHandler {
+    fn modal_changed(&mut self, toplevel: &XdgToplevel, is_modal: bool);
+}
+
+impl XdgDialogState {
+    pub fn new<D>(display: &DisplayHandle) -> Self
+    where
+        D: GlobalDispatch<XdgWmDialogV1, ()>,
+        D: Dispatch<XdgWmDialogV1, ()>,
+        D: Dispatch<XdgDialogV1, XdgToplevel>,
+        D: XdgDialogHandler,
+        D: 'static,
+    {
+        display.create_global::<D, XdgWmDialogV1, _>(VERSION, ());
+
+        Self {}
+    }
+}
+
+impl<D> GlobalDispatch<XdgWmDialogV1, (), D> for XdgDialogState
+where
+    D: GlobalDispatch<XdgWmDialogV1, ()>,
+    D: Dispatch<XdgWmDialogV1, ()>,
+    D: Dispatch<XdgDialogV1, XdgToplevel>,
+    D: XdgDialogHandler,
+    D: 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        manager: New<XdgWmDialogV1>,
+        _manager_state: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(manager, ());
+    }
+}
+
+impl<D> Dispatch<XdgWmDialogV1, (), D> for XdgDialogState
+where
+    D: Dispatch<XdgWmDialogV1, ()>,
+    D: Dispatch<XdgDialogV1, XdgToplevel>,
+    D: XdgDialogHandler,
+    D: 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _resource: &XdgWmDialogV1,
+        request: <XdgWmDialogV1 as Resource>::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            xdg_wm_dialog_v1::Request::GetXdgDialog { id, toplevel } => {
+                data_init.init(id, toplevel);
+            }
+            xdg_wm_dialog_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<XdgDialogV1, XdgToplevel, D> for XdgDialogState
+where
+    D: Dispatch<XdgDialogV1, XdgToplevel>,
+    D: XdgDialogHandler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _resource: &XdgDialogV1,
+        request: <XdgDialogV1 as Resource>::Request,
+        toplevel: &XdgToplevel,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            xdg_dialog_v1::Request::SetModal => state.modal_changed(toplevel, true),
+            xdg_dialog_v1::Request::UnsetModal => state.modal_changed(toplevel, false),
+            xdg_dialog_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(
+        state: &mut D,
+        _client: ClientId,
+        _resource: &XdgDialogV1,
+        toplevel: &XdgToplevel,
+    ) {
+        // Destroying the dialog object removes the modal hint.
+        if toplevel.is_alive() {
+            state.modal_changed(toplevel, false);
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! delegate_xdg_dialog {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::dialog::v1::server::xdg_wm_dialog_v1::XdgWmDialogV1: ()
+        ] => $crate::protocols::xdg_dialog::XdgDialogState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::dialog::v1::server::xdg_wm_dialog_v1::XdgWmDialogV1: ()
+        ] => $crate::protocols::xdg_dialog::XdgDialogState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::dialog::v1::server::xdg_dialog_v1::XdgDialogV1: smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel::XdgToplevel
+        ] => $crate::protocols::xdg_dialog::XdgDialogState);
+    };
+}
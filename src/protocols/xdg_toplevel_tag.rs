@@ -0,0 +1,88 @@
+use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel::XdgToplevel;
+use smithay::reexports::wayland_protocols::xdg::toplevel_tag::v1::server::xdg_toplevel_tag_manager_v1::{
+    self, XdgToplevelTagManagerV1,
+};
+use smithay::reexports::wayland_server::{
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+
+const VERSION: u32 = 1;
+
+pub struct XdgToplevelTagManagerState {}
+
+pub trait XdgToplevelTagHandler {
+    fn set_toplevel_tag(&mut self, toplevel: &XdgToplevel, tag: String);
+}
+
+impl XdgToplevelTagManagerState {
+    pub fn new<D>(display: &DisplayHandle) -> Self
+    where
+        D: GlobalDispatch<XdgToplevelTagManagerV1, ()>,
+        D: Dispatch<XdgToplevelTagManagerV1, ()>,
+        D: XdgToplevelTagHandler,
+        D: 'static,
+    {
+        display.create_global::<D, XdgToplevelTagManagerV1, _>(VERSION, ());
+
+        Self {}
+    }
+}
+
+impl<D> GlobalDispatch<XdgToplevelTagManagerV1, (), D> for XdgToplevelTagManagerState
+where
+    D: GlobalDispatch<XdgToplevelTagManagerV1, ()>,
+    D: Dispatch<XdgToplevelTagManagerV1, ()>,
+    D: XdgToplevelTagHandler,
+    D: 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        manager: New<XdgToplevelTagManagerV1>,
+        _manager_state: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(manager, ());
+    }
+}
+
+impl<D> Dispatch<XdgToplevelTagManagerV1, (), D> for XdgToplevelTagManagerState
+where
+    D: Dispatch<XdgToplevelTagManagerV1, ()>,
+    D: XdgToplevelTagHandler,
+    D: 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _resource: &XdgToplevelTagManagerV1,
+        request: <XdgToplevelTagManagerV1 as Resource>::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            xdg_toplevel_tag_manager_v1::Request::SetToplevelTag { toplevel, tag } => {
+                state.set_toplevel_tag(&toplevel, tag);
+            }
+            // We don't show descriptions anywhere.
+            xdg_toplevel_tag_manager_v1::Request::SetToplevelDescription { .. } => (),
+            xdg_toplevel_tag_manager_v1::Request::Destroy => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! delegate_xdg_toplevel_tag {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        smithay::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::toplevel_tag::v1::server::xdg_toplevel_tag_manager_v1::XdgToplevelTagManagerV1: ()
+        ] => $crate::protocols::xdg_toplevel_tag::XdgToplevelTagManagerState);
+
+        smithay::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            smithay::reexports::wayland_protocols::xdg::toplevel_tag::v1::server::xdg_toplevel_tag_manager_v1::XdgToplevelTagManagerV1: ()
+        ] => $crate::protocols::xdg_toplevel_tag::XdgToplevelTagManagerState);
+    };
+}
@@ -0,0 +1,1019 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use anyhow::Context as _;
+use glam::{Mat3, Vec2};
+use niri_config::{BlurRule, CornerRadius};
+use smithay::backend::allocator::Fourcc;
+use smithay::backend::renderer::damage::OutputDamageTracker;
+use smithay::backend::renderer::element::{Element, Id, Kind, RenderElement, UnderlyingStorage};
+use smithay::backend::renderer::gles::{
+    ffi, link_program, Capability, GlesError, GlesFrame, GlesRenderer, GlesTexture, Uniform,
+};
+use smithay::backend::renderer::utils::{CommitCounter, OpaqueRegions};
+use smithay::backend::renderer::{ContextId, Offscreen as _, Renderer as _, Texture as _};
+use smithay::output::Output;
+use smithay::utils::{Buffer, Logical, Physical, Point, Rectangle, Scale, Size, Transform};
+use smithay::wayland::compositor::{RectangleKind, RegionAttributes};
+
+use super::renderer::AsGlesFrame as _;
+use super::resources::Resources;
+use super::shader_element::ShaderRenderElement;
+use super::shaders::{mat3_uniform, ProgramType, Shaders};
+use super::RenderTarget;
+use crate::backend::tty::{TtyFrame, TtyRenderer, TtyRendererError};
+
+const DEFAULT_PASSES: u8 = 3;
+const MAX_PASSES: u8 = 8;
+const DEFAULT_RADIUS: f32 = 5.;
+
+/// Blur strength settings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlurParams {
+    /// Number of downsampling and upsampling passes.
+    pub passes: u8,
+    /// Sampling offset of every pass.
+    pub radius: f32,
+}
+
+/// Buffer that caches the background blur behind an element.
+///
+/// The blur samples whatever was already rendered below the element. The result is cached and
+/// only recomputed when the contents below change, see [`BlurBuffer::update_damage()`].
+#[derive(Debug)]
+pub struct BlurBuffer {
+    /// State for rendering on the output itself.
+    output: Rc<RefCell<Inner>>,
+    /// State for screen captures, which are always recomputed.
+    capture: Rc<RefCell<Inner>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    id: Id,
+    commit_counter: CommitCounter,
+    /// The blurred contents.
+    texture: Option<(GlesTexture, ContextId<GlesTexture>)>,
+    /// Whether the texture should be recomputed on the next draw.
+    needs_recompute: bool,
+    /// Parameters of the last render.
+    params: Option<(Size<i32, Physical>, BlurParams)>,
+    /// Physical geometry of the element on the output during the last damage update.
+    geometry: Option<Rectangle<i32, Physical>>,
+    /// Damage tracker for the contents below the element.
+    damage: Option<(Output, OutputDamageTracker)>,
+}
+
+/// Draws the cached background blur, recomputing it if necessary.
+#[derive(Debug, Clone)]
+pub struct BlurRenderElement {
+    id: Id,
+    shared: Rc<RefCell<Inner>>,
+    inner: ShaderRenderElement,
+    texture: GlesTexture,
+    /// Parts of the area to blur, relative to the area, or `None` to blur all of it.
+    region: Option<Vec<Rectangle<f64, Logical>>>,
+    params: BlurParams,
+    scale: Scale<f64>,
+}
+
+/// Where the blurred area ends up in the frame during a draw.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct AreaInFrame {
+    /// The whole area, even the parts outside the drawn `dst`.
+    full: Rectangle<i32, Physical>,
+    /// The part of the area inside `dst`.
+    visible: Rectangle<i32, Physical>,
+    /// The visible part relative to the whole area, from 0 to 1.
+    visible_frac: Rectangle<f64, Logical>,
+}
+
+/// Program for a single down- or upsampling pass.
+#[derive(Debug, Clone, Copy)]
+pub struct BlurPassProgram {
+    program: ffi::types::GLuint,
+    attrib_vert: ffi::types::GLint,
+    uniform_tex: ffi::types::GLint,
+    uniform_uv_matrix: ffi::types::GLint,
+    uniform_halfpixel: ffi::types::GLint,
+    uniform_offset: ffi::types::GLint,
+}
+
+impl BlurParams {
+    pub fn from_rule(rule: &BlurRule) -> Self {
+        Self {
+            passes: rule.passes.unwrap_or(DEFAULT_PASSES).clamp(1, MAX_PASSES),
+            radius: rule.radius.map_or(DEFAULT_RADIUS, |x| x.0 as f32),
+        }
+    }
+
+    /// Returns how far outside the element the blur samples, in physical pixels.
+    fn extent(&self) -> i32 {
+        (self.radius * f32::from(1u16 << self.passes)).ceil() as i32
+    }
+}
+
+impl BlurPassProgram {
+    pub fn compile(renderer: &mut GlesRenderer, src: &str) -> Result<Self, GlesError> {
+        renderer.with_context(move |gl| unsafe {
+            let program = link_program(gl, include_str!("shaders/blur.vert"), src)?;
+
+            Ok(Self {
+                program,
+                attrib_vert: gl.GetAttribLocation(program, c"vert".as_ptr()),
+                uniform_tex: gl.GetUniformLocation(program, c"tex".as_ptr()),
+                uniform_uv_matrix: gl.GetUniformLocation(program, c"uv_matrix".as_ptr()),
+                uniform_halfpixel: gl.GetUniformLocation(program, c"halfpixel".as_ptr()),
+                uniform_offset: gl.GetUniformLocation(program, c"offset".as_ptr()),
+            })
+        })?
+    }
+}
+
+impl Inner {
+    fn new() -> Self {
+        Self {
+            id: Id::new(),
+            commit_counter: CommitCounter::default(),
+            texture: None,
+            needs_recompute: true,
+            params: None,
+            geometry: None,
+            damage: None,
+        }
+    }
+}
+
+impl BlurBuffer {
+    pub fn new() -> Self {
+        Self {
+            output: Rc::new(RefCell::new(Inner::new())),
+            capture: Rc::new(RefCell::new(Inner::new())),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &self,
+        renderer: &mut GlesRenderer,
+        area: Rectangle<f64, Logical>,
+        corner_radius: CornerRadius,
+        region: Option<Vec<Rectangle<f64, Logical>>>,
+        params: BlurParams,
+        scale: Scale<f64>,
+        target: RenderTarget,
+    ) -> Option<BlurRenderElement> {
+        let shaders = Shaders::get(renderer);
+        if shaders.blur.is_none() || shaders.blur_down.is_none() || shaders.blur_up.is_none() {
+            return None;
+        }
+
+        let size = area.size.to_physical_precise_round(scale);
+        if size.w <= 0 || size.h <= 0 {
+            return None;
+        }
+
+        let shared = if target == RenderTarget::Output {
+            &self.output
+        } else {
+            &self.capture
+        };
+        let mut inner = shared.borrow_mut();
+        let id = inner.id.clone();
+
+        // Screen captures don't track damage below, so recompute them every time.
+        if target != RenderTarget::Output {
+            inner.needs_recompute = true;
+            inner.commit_counter.increment();
+        }
+
+        if inner.params != Some((size, params)) {
+            inner.params = Some((size, params));
+            inner.needs_recompute = true;
+            inner.commit_counter.increment();
+        }
+
+        let texture = match ensure_texture(renderer, &mut inner, size) {
+            Ok(texture) => texture,
+            Err(err) => {
+                warn!("error creating blur texture: {err:?}");
+                return None;
+            }
+        };
+        drop(inner);
+
+        let area_size = Vec2::new(area.size.w as f32, area.size.h as f32);
+        let corner_radius = corner_radius.fit_to(area_size.x, area_size.y);
+
+        let inner = ShaderRenderElement::new(
+            ProgramType::Blur,
+            area.size,
+            None,
+            scale.x as f32,
+            1.,
+            vec![
+                mat3_uniform("input_to_geo", Mat3::from_scale(area_size)),
+                Uniform::new("geo_size", area_size.to_array()),
+                Uniform::new("corner_radius", <[f32; 4]>::from(corner_radius)),
+            ],
+            HashMap::from([(String::from("niri_tex"), texture.clone())]),
+            Kind::Unspecified,
+        )
+        .with_location(area.loc);
+
+        Some(BlurRenderElement {
+            id,
+            shared: shared.clone(),
+            inner,
+            texture,
+            region,
+            params,
+            scale,
+        })
+    }
+
+    /// Marks the blur for recomputing if the contents below it changed.
+    ///
+    /// `elements` are all elements of the output, front to back, including this blur.
+    pub fn update_damage<E: Element>(&self, output: &Output, elements: &[E]) {
+        let mut inner = self.output.borrow_mut();
+        let Some(idx) = elements.iter().position(|elem| *elem.id() == inner.id) else {
+            return;
+        };
+
+        let _span = tracy_client::span!("BlurBuffer::update_damage");
+
+        let scale = Scale::from(output.current_scale().fractional_scale());
+        // The geometry includes the extent that the blur samples from.
+        let geo = elements[idx].geometry(scale);
+
+        let mut changed = inner.geometry != Some(geo);
+        inner.geometry = Some(geo);
+
+        let tracker = match &mut inner.damage {
+            Some((last_output, tracker)) if last_output == output => tracker,
+            damage => {
+                changed = true;
+                let tracker = OutputDamageTracker::from_output(output);
+                &mut damage.insert((output.clone(), tracker)).1
+            }
+        };
+
+        // Only the contents below the blur can affect it.
+        match tracker.damage_output(1, &elements[idx + 1..]) {
+            Ok((Some(damage), _)) => {
+                changed |= damage.iter().any(|rect| rect.overlaps(geo));
+            }
+            Ok((None, _)) => (),
+            Err(err) => {
+                warn!("error computing damage below blur: {err:?}");
+                changed = true;
+            }
+        }
+
+        if changed {
+            inner.needs_recompute = true;
+            inner.commit_counter.increment();
+        }
+    }
+}
+
+impl Default for BlurBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn ensure_texture(
+    renderer: &mut GlesRenderer,
+    inner: &mut Inner,
+    size: Size<i32, Physical>,
+) -> anyhow::Result<GlesTexture> {
+    let buffer_size = size.to_logical(1).to_buffer(1, Transform::Normal);
+
+    if let Some((texture, context_id)) = &inner.texture {
+        if texture.size() == buffer_size && *context_id == renderer.context_id() {
+            return Ok(texture.clone());
+        }
+    }
+
+    let texture: GlesTexture = renderer
+        .create_buffer(Fourcc::Abgr8888, buffer_size)
+        .context("error creating texture")?;
+    inner.texture = Some((texture.clone(), renderer.context_id()));
+    inner.needs_recompute = true;
+    inner.commit_counter.increment();
+
+    Ok(texture)
+}
+
+/// Expands a rectangle by `extent` on every side.
+fn expand(rect: Rectangle<i32, Physical>, extent: i32) -> Rectangle<i32, Physical> {
+    Rectangle::new(
+        rect.loc - Point::from((extent, extent)),
+        rect.size + Size::from((extent * 2, extent * 2)),
+    )
+}
+
+/// Finds the blurred area of the size `size` in the frame.
+///
+/// `src` and `dst` are what the element is drawn with, covering the area with `extent` on every
+/// side, possibly cropped and rescaled.
+fn area_in_frame(
+    src: Rectangle<f64, Buffer>,
+    dst: Rectangle<i32, Physical>,
+    size: Size<i32, Physical>,
+    extent: i32,
+) -> Option<AreaInFrame> {
+    if src.size.w <= 0. || src.size.h <= 0. || size.w <= 0 || size.h <= 0 {
+        return None;
+    }
+
+    let scale_x = f64::from(dst.size.w) / src.size.w;
+    let scale_y = f64::from(dst.size.h) / src.size.h;
+
+    let full = Rectangle::<f64, Physical>::new(
+        Point::from((
+            f64::from(dst.loc.x) + (f64::from(extent) - src.loc.x) * scale_x,
+            f64::from(dst.loc.y) + (f64::from(extent) - src.loc.y) * scale_y,
+        )),
+        Size::from((f64::from(size.w) * scale_x, f64::from(size.h) * scale_y)),
+    );
+    let full = full.to_i32_round();
+    let visible = full.intersection(dst)?;
+
+    let visible_frac = Rectangle::new(
+        Point::from((
+            f64::from(visible.loc.x - full.loc.x) / f64::from(full.size.w),
+            f64::from(visible.loc.y - full.loc.y) / f64::from(full.size.h),
+        )),
+        Size::from((
+            f64::from(visible.size.w) / f64::from(full.size.w),
+            f64::from(visible.size.h) / f64::from(full.size.h),
+        )),
+    );
+
+    Some(AreaInFrame {
+        full,
+        visible,
+        visible_frac,
+    })
+}
+
+/// Computes the damage to redraw within the visible area, relative to it.
+///
+/// `damage` is relative to `dst`, and `region` is relative to the full area, both in frame
+/// pixels.
+fn visible_damage(
+    damage: &[Rectangle<i32, Physical>],
+    dst: Rectangle<i32, Physical>,
+    area: &AreaInFrame,
+    region: Option<&[Rectangle<i32, Physical>]>,
+) -> Vec<Rectangle<i32, Physical>> {
+    let damage = damage.iter().filter_map(|rect| {
+        let mut rect = *rect;
+        rect.loc += dst.loc;
+        rect.intersection(area.visible)
+    });
+
+    let mut damage: Vec<_> = match region {
+        Some(region) => damage
+            .flat_map(|damage| {
+                region.iter().filter_map(move |rect| {
+                    let mut rect = *rect;
+                    rect.loc += area.full.loc;
+                    rect.intersection(damage)
+                })
+            })
+            .collect(),
+        None => damage.collect(),
+    };
+
+    for rect in &mut damage {
+        rect.loc -= area.visible.loc;
+    }
+    damage
+}
+
+/// Converts a surface region to rectangles relative to the surface.
+pub fn region_rects(region: &RegionAttributes) -> Vec<Rectangle<i32, Logical>> {
+    let mut rects: Vec<Rectangle<i32, Logical>> = Vec::new();
+    for (kind, rect) in &region.rects {
+        match kind {
+            RectangleKind::Add => rects.push(*rect),
+            RectangleKind::Subtract => rects = Rectangle::subtract_rects_many(rects, [*rect]),
+        }
+    }
+    rects
+}
+
+impl BlurRenderElement {
+    /// Blurs the framebuffer contents below `dst` into the cached texture.
+    ///
+    /// The element geometry includes the sampled extent, so by the time this is called, all
+    /// contents below it were freshly drawn for this frame.
+    fn recompute(
+        &self,
+        frame: &mut GlesFrame<'_, '_>,
+        dst: Rectangle<i32, Physical>,
+    ) -> Result<(), GlesError> {
+        let _span = tracy_client::span!("BlurRenderElement::recompute");
+
+        let shaders = Shaders::get_from_frame(frame);
+        let (Some(down), Some(up)) = (shaders.blur_down, shaders.blur_up) else {
+            return Ok(());
+        };
+
+        let Some(resources) = Resources::get(frame) else {
+            return Ok(());
+        };
+        let vbo = resources.borrow().vbos[0];
+        let supports_instancing = frame.capabilities().contains(&Capability::Instancing);
+
+        let BlurParams { passes, radius } = self.params;
+        let extent = self.params.extent();
+
+        let to_ndc = Mat3::from_cols_array(frame.projection());
+        let target = self.texture.tex_id();
+        let size = self.texture.size();
+        let size = Size::<i32, Physical>::from((size.w, size.h));
+
+        frame.with_context(move |gl| unsafe {
+            let mut viewport = [0; 4];
+            gl.GetIntegerv(ffi::VIEWPORT, viewport.as_mut_ptr());
+            let mut framebuffer = 0;
+            gl.GetIntegerv(ffi::FRAMEBUFFER_BINDING, &mut framebuffer);
+            let mut alpha_bits = 0;
+            gl.GetIntegerv(ffi::ALPHA_BITS, &mut alpha_bits);
+            let blend = gl.IsEnabled(ffi::BLEND) == ffi::TRUE;
+            let scissor = gl.IsEnabled(ffi::SCISSOR_TEST) == ffi::TRUE;
+
+            let vp_loc = Vec2::new(viewport[0] as f32, viewport[1] as f32);
+            let vp_size = Vec2::new(viewport[2] as f32, viewport[3] as f32);
+
+            // Maps element-local coordinates to framebuffer pixels.
+            let dst_loc = Vec2::new(dst.loc.x as f32, dst.loc.y as f32);
+            let dst_size = Vec2::new(dst.size.w as f32, dst.size.h as f32);
+            let to_window = Mat3::from_translation(vp_loc)
+                * Mat3::from_scale(vp_size / 2.)
+                * Mat3::from_translation(Vec2::ONE)
+                * to_ndc
+                * Mat3::from_translation(dst_loc)
+                * Mat3::from_scale(dst_size);
+
+            // Copy the contents below, plus the extent that the blur samples from.
+            let a = to_window.transform_point2(Vec2::ZERO);
+            let b = to_window.transform_point2(Vec2::ONE);
+            let min = (a.min(b) - extent as f32).floor().max(vp_loc);
+            let max = (a.max(b) + extent as f32).ceil().min(vp_loc + vp_size);
+            let copy_size = max - min;
+            if copy_size.x <= 0. || copy_size.y <= 0. {
+                return;
+            }
+
+            let uv_matrix =
+                Mat3::from_scale(Vec2::ONE / copy_size) * Mat3::from_translation(-min) * to_window;
+
+            let format = if alpha_bits > 0 { ffi::RGBA } else { ffi::RGB };
+            let copy = create_texture(gl, None);
+            gl.CopyTexImage2D(
+                ffi::TEXTURE_2D,
+                0,
+                format,
+                min.x as i32,
+                min.y as i32,
+                copy_size.x as i32,
+                copy_size.y as i32,
+                0,
+            );
+
+            let mut fbo = 0;
+            gl.GenFramebuffers(1, &mut fbo);
+            gl.BindFramebuffer(ffi::FRAMEBUFFER, fbo);
+            gl.Disable(ffi::BLEND);
+            gl.Disable(ffi::SCISSOR_TEST);
+
+            let levels: Vec<_> = (1..=passes)
+                .map(|i| {
+                    let size =
+                        Size::<i32, Physical>::from(((size.w >> i).max(1), (size.h >> i).max(1)));
+                    (create_texture(gl, Some(size)), size)
+                })
+                .collect();
+
+            let draw = |program: BlurPassProgram,
+                        src: ffi::types::GLuint,
+                        uv_matrix: Mat3,
+                        dst: ffi::types::GLuint,
+                        dst_size: Size<i32, Physical>| {
+                gl.FramebufferTexture2D(
+                    ffi::FRAMEBUFFER,
+                    ffi::COLOR_ATTACHMENT0,
+                    ffi::TEXTURE_2D,
+                    dst,
+                    0,
+                );
+                gl.Viewport(0, 0, dst_size.w, dst_size.h);
+
+                gl.UseProgram(program.program);
+                gl.ActiveTexture(ffi::TEXTURE0);
+                gl.BindTexture(ffi::TEXTURE_2D, src);
+                gl.Uniform1i(program.uniform_tex, 0);
+                gl.UniformMatrix3fv(
+                    program.uniform_uv_matrix,
+                    1,
+                    ffi::FALSE,
+                    uv_matrix.as_ref().as_ptr(),
+                );
+                gl.Uniform2f(
+                    program.uniform_halfpixel,
+                    0.5 / dst_size.w as f32,
+                    0.5 / dst_size.h as f32,
+                );
+                gl.Uniform1f(program.uniform_offset, radius);
+
+                gl.EnableVertexAttribArray(program.attrib_vert as u32);
+                gl.BindBuffer(ffi::ARRAY_BUFFER, vbo);
+                gl.VertexAttribPointer(
+                    program.attrib_vert as u32,
+                    2,
+                    ffi::FLOAT,
+                    ffi::FALSE,
+                    0,
+                    std::ptr::null(),
+                );
+
+                // The first vertex buffer holds a single quad either way.
+                if supports_instancing {
+                    gl.VertexAttribDivisor(program.attrib_vert as u32, 0);
+                    gl.DrawArrays(ffi::TRIANGLE_STRIP, 0, 4);
+                } else {
+                    gl.DrawArrays(ffi::TRIANGLES, 0, 6);
+                }
+
+                gl.BindBuffer(ffi::ARRAY_BUFFER, 0);
+                gl.DisableVertexAttribArray(program.attrib_vert as u32);
+            };
+
+            // Downsample, starting from the copied framebuffer contents.
+            let mut src = copy;
+            let mut src_matrix = uv_matrix;
+            for &(tex, size) in &levels {
+                draw(down, src, src_matrix, tex, size);
+                src = tex;
+                src_matrix = Mat3::IDENTITY;
+            }
+
+            // Upsample back, finishing in the cached texture.
+            for i in (0..levels.len()).rev() {
+                let (tex, size) = if i == 0 {
+                    (target, size)
+                } else {
+                    levels[i - 1]
+                };
+                draw(up, levels[i].0, Mat3::IDENTITY, tex, size);
+            }
+
+            gl.BindTexture(ffi::TEXTURE_2D, 0);
+            gl.BindFramebuffer(ffi::FRAMEBUFFER, framebuffer as u32);
+            gl.DeleteFramebuffers(1, &fbo);
+            gl.DeleteTextures(1, &copy);
+            for (tex, _) in levels {
+                gl.DeleteTextures(1, &tex);
+            }
+
+            gl.Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+            if blend {
+                gl.Enable(ffi::BLEND);
+            }
+            if scissor {
+                gl.Enable(ffi::SCISSOR_TEST);
+            }
+        })
+    }
+}
+
+/// Creates a texture for a blur pass, leaving it bound.
+unsafe fn create_texture(gl: &ffi::Gles2, size: Option<Size<i32, Physical>>) -> ffi::types::GLuint {
+    let mut tex = 0;
+    gl.GenTextures(1, &mut tex);
+    gl.BindTexture(ffi::TEXTURE_2D, tex);
+    gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_MIN_FILTER, ffi::LINEAR as i32);
+    gl.TexParameteri(ffi::TEXTURE_2D, ffi::TEXTURE_MAG_FILTER, ffi::LINEAR as i32);
+    gl.TexParameteri(
+        ffi::TEXTURE_2D,
+        ffi::TEXTURE_WRAP_S,
+        ffi::CLAMP_TO_EDGE as i32,
+    );
+    gl.TexParameteri(
+        ffi::TEXTURE_2D,
+        ffi::TEXTURE_WRAP_T,
+        ffi::CLAMP_TO_EDGE as i32,
+    );
+
+    if let Some(size) = size {
+        gl.TexImage2D(
+            ffi::TEXTURE_2D,
+            0,
+            ffi::RGBA as i32,
+            size.w,
+            size.h,
+            0,
+            ffi::RGBA,
+            ffi::UNSIGNED_BYTE,
+            std::ptr::null(),
+        );
+    }
+
+    tex
+}
+
+impl Element for BlurRenderElement {
+    fn id(&self) -> &Id {
+        &self.id
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.shared.borrow().commit_counter
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        // Include the extent that the blur samples from. This way, when the blur changes, the
+        // damage makes the contents below it redraw in full before the blur samples them,
+        // rather than leaving the previous frame there.
+        expand(self.inner.geometry(scale), self.params.extent())
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        let size = self.geometry(self.scale).size;
+        Rectangle::from_size(Size::from((f64::from(size.w), f64::from(size.h))))
+    }
+
+    fn opaque_regions(&self, _scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
+        OpaqueRegions::default()
+    }
+
+    fn alpha(&self) -> f32 {
+        self.inner.alpha()
+    }
+
+    fn kind(&self) -> Kind {
+        self.inner.kind()
+    }
+}
+
+impl RenderElement<GlesRenderer> for BlurRenderElement {
+    fn draw(
+        &self,
+        frame: &mut GlesFrame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), GlesError> {
+        let size = self.inner.geometry(self.scale).size;
+        let Some(area) = area_in_frame(src, dst, size, self.params.extent()) else {
+            return Ok(());
+        };
+
+        let needs_recompute = std::mem::take(&mut self.shared.borrow_mut().needs_recompute);
+        if needs_recompute {
+            self.recompute(frame, area.full)?;
+        }
+
+        // The area may be drawn rescaled, for example in the overview.
+        let rescale = Scale::from((
+            f64::from(area.full.size.w) / f64::from(size.w),
+            f64::from(area.full.size.h) / f64::from(size.h),
+        ));
+        let region = self.region.as_ref().map(|region| {
+            region
+                .iter()
+                .map(|rect| rect.to_physical(self.scale).upscale(rescale).to_i32_round())
+                .collect::<Vec<_>>()
+        });
+        let damage = visible_damage(damage, dst, &area, region.as_deref());
+        if damage.is_empty() {
+            return Ok(());
+        }
+
+        let inner_src = self.inner.src();
+        let inner_src = Rectangle::new(
+            inner_src.loc
+                + Point::from((
+                    area.visible_frac.loc.x * inner_src.size.w,
+                    area.visible_frac.loc.y * inner_src.size.h,
+                )),
+            Size::from((
+                area.visible_frac.size.w * inner_src.size.w,
+                area.visible_frac.size.h * inner_src.size.h,
+            )),
+        );
+
+        RenderElement::<GlesRenderer>::draw(
+            &self.inner,
+            frame,
+            inner_src,
+            area.visible,
+            &damage,
+            opaque_regions,
+        )
+    }
+
+    fn underlying_storage(&self, _renderer: &mut GlesRenderer) -> Option<UnderlyingStorage> {
+        None
+    }
+}
+
+impl<'render> RenderElement<TtyRenderer<'render>> for BlurRenderElement {
+    fn draw(
+        &self,
+        frame: &mut TtyFrame<'_, '_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), TtyRendererError<'render>> {
+        let frame = frame.as_gles_frame();
+
+        RenderElement::<GlesRenderer>::draw(self, frame, src, dst, damage, opaque_regions)?;
+
+        Ok(())
+    }
+
+    fn underlying_storage(
+        &self,
+        _renderer: &mut TtyRenderer<'render>,
+    ) -> Option<UnderlyingStorage> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use smithay::backend::renderer::element::solid::SolidColorRenderElement;
+    use smithay::backend::renderer::Color32F;
+    use smithay::output::{Mode, PhysicalProperties, Subpixel};
+
+    use super::*;
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Physical> {
+        Rectangle::new(Point::from((x, y)), Size::from((w, h)))
+    }
+
+    fn src(x: f64, y: f64, w: f64, h: f64) -> Rectangle<f64, Buffer> {
+        Rectangle::new(Point::from((x, y)), Size::from((w, h)))
+    }
+
+    fn solid(
+        id: Id,
+        geo: Rectangle<i32, Physical>,
+        commit: CommitCounter,
+    ) -> SolidColorRenderElement {
+        SolidColorRenderElement::new(
+            id,
+            geo.to_f64().to_logical(1.),
+            commit,
+            Color32F::from([0., 0., 0., 1.]),
+            Kind::Unspecified,
+        )
+    }
+
+    fn make_output() -> Output {
+        let output = Output::new(
+            String::from("output"),
+            PhysicalProperties {
+                size: Size::from((1280, 720)),
+                subpixel: Subpixel::Unknown,
+                make: String::new(),
+                model: String::new(),
+            },
+        );
+        output.change_current_state(
+            Some(Mode {
+                size: Size::from((1280, 720)),
+                refresh: 60000,
+            }),
+            None,
+            None,
+            None,
+        );
+        output
+    }
+
+    #[test]
+    fn extent() {
+        let params = |passes, radius| BlurParams { passes, radius };
+        assert_eq!(params(1, 5.).extent(), 10);
+        assert_eq!(params(3, 5.).extent(), 40);
+        assert_eq!(params(1, 0.2).extent(), 1);
+        assert_eq!(params(2, 0.).extent(), 0);
+    }
+
+    #[test]
+    fn region_rects_add_and_subtract() {
+        let logical = |x, y, w, h| Rectangle::<i32, Logical>::new((x, y).into(), (w, h).into());
+
+        let region = RegionAttributes {
+            rects: vec![
+                (RectangleKind::Add, logical(0, 0, 100, 100)),
+                (RectangleKind::Subtract, logical(50, 0, 50, 100)),
+                (RectangleKind::Add, logical(200, 0, 10, 10)),
+            ],
+        };
+        assert_eq!(
+            region_rects(&region),
+            [logical(0, 0, 50, 100), logical(200, 0, 10, 10)]
+        );
+
+        let region = RegionAttributes {
+            rects: vec![
+                (RectangleKind::Subtract, logical(0, 0, 10, 10)),
+                (RectangleKind::Add, logical(0, 0, 10, 10)),
+            ],
+        };
+        assert_eq!(region_rects(&region), [logical(0, 0, 10, 10)]);
+
+        let region = RegionAttributes {
+            rects: vec![
+                (RectangleKind::Add, logical(0, 0, 10, 10)),
+                (RectangleKind::Subtract, logical(0, 0, 10, 10)),
+            ],
+        };
+        assert!(region_rects(&region).is_empty());
+    }
+
+    #[test]
+    fn area_in_frame_full() {
+        let area = area_in_frame(
+            src(0., 0., 120., 120.),
+            rect(40, 40, 120, 120),
+            Size::from((100, 100)),
+            10,
+        )
+        .unwrap();
+        assert_eq!(area.full, rect(50, 50, 100, 100));
+        assert_eq!(area.visible, rect(50, 50, 100, 100));
+        assert_eq!(
+            area.visible_frac,
+            Rectangle::new((0., 0.).into(), (1., 1.).into())
+        );
+    }
+
+    #[test]
+    fn area_in_frame_cropped() {
+        // Cropped to the left half of the area.
+        let area = area_in_frame(
+            src(0., 0., 60., 120.),
+            rect(40, 40, 60, 120),
+            Size::from((100, 100)),
+            10,
+        )
+        .unwrap();
+        assert_eq!(area.full, rect(50, 50, 100, 100));
+        assert_eq!(area.visible, rect(50, 50, 50, 100));
+        assert_eq!(
+            area.visible_frac,
+            Rectangle::new((0., 0.).into(), (0.5, 1.).into())
+        );
+
+        // Cropped to the bottom half of the area.
+        let area = area_in_frame(
+            src(0., 60., 120., 60.),
+            rect(40, 100, 120, 60),
+            Size::from((100, 100)),
+            10,
+        )
+        .unwrap();
+        assert_eq!(area.full, rect(50, 50, 100, 100));
+        assert_eq!(area.visible, rect(50, 100, 100, 50));
+        assert_eq!(
+            area.visible_frac,
+            Rectangle::new((0., 0.5).into(), (1., 0.5).into())
+        );
+
+        // Cropped to the extent only.
+        let area = area_in_frame(
+            src(0., 0., 10., 120.),
+            rect(40, 40, 10, 120),
+            Size::from((100, 100)),
+            10,
+        );
+        assert_eq!(area, None);
+    }
+
+    #[test]
+    fn area_in_frame_rescaled() {
+        let area = area_in_frame(
+            src(0., 0., 120., 120.),
+            rect(20, 20, 60, 60),
+            Size::from((100, 100)),
+            10,
+        )
+        .unwrap();
+        assert_eq!(area.full, rect(25, 25, 50, 50));
+        assert_eq!(area.visible, rect(25, 25, 50, 50));
+    }
+
+    #[test]
+    fn visible_damage_without_region() {
+        let dst = rect(40, 40, 120, 120);
+        let area = area_in_frame(src(0., 0., 120., 120.), dst, Size::from((100, 100)), 10).unwrap();
+
+        // Damage in the extent alone doesn't draw anything.
+        assert!(visible_damage(&[rect(0, 0, 10, 120)], dst, &area, None).is_empty());
+
+        // Damage is clipped to the area and made relative to it.
+        assert_eq!(
+            visible_damage(
+                &[rect(0, 0, 20, 20), rect(60, 60, 100, 100)],
+                dst,
+                &area,
+                None
+            ),
+            [rect(0, 0, 10, 10), rect(50, 50, 50, 50)]
+        );
+    }
+
+    #[test]
+    fn visible_damage_with_region() {
+        let dst = rect(40, 40, 120, 120);
+        let area = area_in_frame(src(0., 0., 120., 120.), dst, Size::from((100, 100)), 10).unwrap();
+        let region = [rect(0, 0, 30, 30), rect(70, 70, 30, 30)];
+
+        assert_eq!(
+            visible_damage(&[rect(0, 0, 120, 120)], dst, &area, Some(&region)),
+            region
+        );
+        assert_eq!(
+            visible_damage(&[rect(20, 20, 20, 20)], dst, &area, Some(&region)),
+            [rect(10, 10, 20, 20)]
+        );
+        assert!(visible_damage(&[rect(50, 50, 20, 20)], dst, &area, Some(&region)).is_empty());
+    }
+
+    #[test]
+    fn update_damage_tracks_contents_below() {
+        let output = make_output();
+        let buffer = BlurBuffer::new();
+        let blur_id = buffer.output.borrow().id.clone();
+        let take_recompute = || std::mem::take(&mut buffer.output.borrow_mut().needs_recompute);
+
+        let above_id = Id::new();
+        let below_id = Id::new();
+        let far_id = Id::new();
+        let mut above_commit = CommitCounter::default();
+        let mut below_commit = CommitCounter::default();
+        let mut far_commit = CommitCounter::default();
+
+        let elements = |above, below, far| {
+            [
+                solid(above_id.clone(), rect(0, 0, 200, 200), above),
+                solid(
+                    blur_id.clone(),
+                    rect(100, 100, 200, 200),
+                    CommitCounter::default(),
+                ),
+                solid(below_id.clone(), rect(250, 250, 100, 100), below),
+                solid(far_id.clone(), rect(800, 500, 100, 100), far),
+            ]
+        };
+
+        take_recompute();
+        buffer.update_damage(&output, &elements(above_commit, below_commit, far_commit));
+        assert!(take_recompute(), "the first update must recompute");
+
+        buffer.update_damage(&output, &elements(above_commit, below_commit, far_commit));
+        assert!(!take_recompute(), "nothing changed");
+
+        above_commit.increment();
+        buffer.update_damage(&output, &elements(above_commit, below_commit, far_commit));
+        assert!(!take_recompute(), "contents above don't affect the blur");
+
+        far_commit.increment();
+        buffer.update_damage(&output, &elements(above_commit, below_commit, far_commit));
+        assert!(
+            !take_recompute(),
+            "contents below elsewhere don't affect the blur"
+        );
+
+        below_commit.increment();
+        buffer.update_damage(&output, &elements(above_commit, below_commit, far_commit));
+        assert!(take_recompute(), "contents below affect the blur");
+
+        // Moving the blur recomputes it.
+        let mut moved = elements(above_commit, below_commit, far_commit);
+        moved[1] = solid(
+            blur_id.clone(),
+            rect(110, 100, 200, 200),
+            CommitCounter::default(),
+        );
+        buffer.update_damage(&output, &moved);
+        assert!(take_recompute(), "the blur moved");
+
+        // Elements without the blur don't touch it.
+        buffer.update_damage(&output, &moved[2..]);
+        assert!(!take_recompute());
+    }
+}
@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use smithay::backend::renderer::element::{Element, Id, Kind, RenderElement, UnderlyingStorage};
+use smithay::backend::renderer::utils::{CommitCounter, DamageSet, OpaqueRegions};
+use smithay::backend::renderer::Renderer;
+use smithay::utils::{Buffer, Physical, Rectangle, Scale, Transform};
+
+/// Render element stored on the heap.
+///
+/// This allows an element enum to contain a wrapped version of itself, for example to rescale
+/// everything that was rendered for an output. All elements rendered together share a single
+/// allocation.
+#[derive(Debug)]
+pub struct BoxedRenderElement<E> {
+    elements: Arc<[E]>,
+    idx: usize,
+}
+
+impl<E> BoxedRenderElement<E> {
+    /// Moves the elements to the heap, returning a boxed element for each of them.
+    pub fn new_all(elements: Vec<E>) -> impl Iterator<Item = Self> {
+        let elements = Arc::<[E]>::from(elements);
+        (0..elements.len()).map(move |idx| Self {
+            elements: elements.clone(),
+            idx,
+        })
+    }
+
+    fn inner(&self) -> &E {
+        &self.elements[self.idx]
+    }
+}
+
+impl<E: Element> Element for BoxedRenderElement<E> {
+    fn id(&self) -> &Id {
+        self.inner().id()
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.inner().current_commit()
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.inner().geometry(scale)
+    }
+
+    fn transform(&self) -> Transform {
+        self.inner().transform()
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        self.inner().src()
+    }
+
+    fn damage_since(
+        &self,
+        scale: Scale<f64>,
+        commit: Option<CommitCounter>,
+    ) -> DamageSet<i32, Physical> {
+        self.inner().damage_since(scale, commit)
+    }
+
+    fn opaque_regions(&self, scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
+        self.inner().opaque_regions(scale)
+    }
+
+    fn alpha(&self) -> f32 {
+        self.inner().alpha()
+    }
+
+    fn kind(&self) -> Kind {
+        self.inner().kind()
+    }
+}
+
+impl<R: Renderer, E: RenderElement<R>> RenderElement<R> for BoxedRenderElement<E> {
+    fn draw(
+        &self,
+        frame: &mut R::Frame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), R::Error> {
+        self.inner().draw(frame, src, dst, damage, opaque_regions)
+    }
+
+    fn underlying_storage(&self, renderer: &mut R) -> Option<UnderlyingStorage<'_>> {
+        self.inner().underlying_storage(renderer)
+    }
+}
@@ -8,6 +8,7 @@ use smithay::backend::renderer::gles::{
 use smithay::backend::renderer::utils::{CommitCounter, DamageSet, OpaqueRegions};
 use smithay::utils::{Buffer, Logical, Physical, Point, Rectangle, Scale, Size, Transform};
 
+use super::color_adjust::ColorAdjust;
 use super::damage::ExtraDamage;
 use super::renderer::{AsGlesFrame as _, NiriRenderer};
 use super::shaders::{mat3_uniform, Shaders};
@@ -35,7 +36,37 @@ impl<R: NiriRenderer> ClippedSurfaceRenderElement<R> {
         geometry: Rectangle<f64, Logical>,
         program: GlesTexProgram,
         corner_radius: CornerRadius,
+        adjust: ColorAdjust,
     ) -> Self {
+        let input_to_geo = Self::input_to_geo(&elem, scale, geometry);
+
+        let mut uniforms = vec![
+            Uniform::new("niri_scale", scale.x as f32),
+            Uniform::new("geo_size", (geometry.size.w as f32, geometry.size.h as f32)),
+            Uniform::new("corner_radius", <[f32; 4]>::from(corner_radius)),
+            mat3_uniform("input_to_geo", input_to_geo),
+        ];
+        uniforms.extend(adjust.uniforms());
+
+        Self {
+            inner: elem,
+            program,
+            corner_radius,
+            geometry,
+            uniforms,
+        }
+    }
+
+    pub fn shader(renderer: &mut R) -> Option<&GlesTexProgram> {
+        Shaders::get(renderer).clipped_surface.as_ref()
+    }
+
+    /// Returns the matrix mapping the surface texture coordinates into the clip geometry.
+    pub fn input_to_geo(
+        elem: &WaylandSurfaceRenderElement<R>,
+        scale: Scale<f64>,
+        geometry: Rectangle<f64, Logical>,
+    ) -> Mat3 {
         let elem_geo = elem.geometry(scale);
 
         let elem_geo_loc = Vec2::new(elem_geo.loc.x as f32, elem_geo.loc.y as f32);
@@ -64,30 +95,11 @@ impl<R: NiriRenderer> ClippedSurfaceRenderElement<R> {
             * Mat3::from_translation(-Vec2::new(0.5, 0.5));
 
         // FIXME: y_inverted
-        let input_to_geo = transform_matrix * Mat3::from_scale(elem_geo_size / geo_size)
+        transform_matrix * Mat3::from_scale(elem_geo_size / geo_size)
             * Mat3::from_translation((elem_geo_loc - geo_loc) / elem_geo_size)
             // Apply viewporter src.
             * Mat3::from_scale(buf_size / src_size)
-            * Mat3::from_translation(-src_loc / buf_size);
-
-        let uniforms = vec![
-            Uniform::new("niri_scale", scale.x as f32),
-            Uniform::new("geo_size", (geometry.size.w as f32, geometry.size.h as f32)),
-            Uniform::new("corner_radius", <[f32; 4]>::from(corner_radius)),
-            mat3_uniform("input_to_geo", input_to_geo),
-        ];
-
-        Self {
-            inner: elem,
-            program,
-            corner_radius,
-            geometry,
-            uniforms,
-        }
-    }
-
-    pub fn shader(renderer: &mut R) -> Option<&GlesTexProgram> {
-        Shaders::get(renderer).clipped_surface.as_ref()
+            * Mat3::from_translation(-src_loc / buf_size)
     }
 
     pub fn will_clip(
@@ -111,7 +123,7 @@ impl<R: NiriRenderer> ClippedSurfaceRenderElement<R> {
         }
     }
 
-    fn rounded_corners(
+    pub fn rounded_corners(
         geo: Rectangle<f64, Logical>,
         corner_radius: CornerRadius,
     ) -> [Rectangle<f64, Logical>; 4] {
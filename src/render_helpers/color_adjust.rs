@@ -0,0 +1,184 @@
+use smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement;
+use smithay::backend::renderer::element::{Element, Id, Kind, RenderElement, UnderlyingStorage};
+use smithay::backend::renderer::gles::{
+    GlesError, GlesFrame, GlesRenderer, GlesTexProgram, Uniform, UniformName, UniformType,
+};
+use smithay::backend::renderer::utils::{CommitCounter, DamageSet, OpaqueRegions};
+use smithay::utils::{Buffer, Physical, Rectangle, Scale, Transform};
+
+use super::renderer::{AsGlesFrame as _, NiriRenderer};
+use super::shaders::Shaders;
+use crate::backend::tty::{TtyFrame, TtyRenderer, TtyRendererError};
+
+/// Color adjustments of a window, such as the dimming of inactive windows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorAdjust {
+    /// How much to darken the colors, from 0 (unchanged) to 1 (black).
+    pub dim: f32,
+    /// Color saturation, from 0 (grayscale) to 1 (unchanged).
+    pub saturation: f32,
+    /// Offset added to the colors, from -1 to 1.
+    pub brightness: f32,
+}
+
+/// Surface drawn with color adjustments.
+#[derive(Debug)]
+pub struct ColorAdjustRenderElement<R: NiriRenderer> {
+    inner: WaylandSurfaceRenderElement<R>,
+    program: GlesTexProgram,
+    uniforms: Vec<Uniform<'static>>,
+}
+
+impl ColorAdjust {
+    pub const IDENTITY: Self = Self {
+        dim: 0.,
+        saturation: 1.,
+        brightness: 0.,
+    };
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+
+    /// Interpolates from no adjustment at `0` to `self` at `1`.
+    pub fn scaled(self, amount: f32) -> Self {
+        let lerp = |from: f32, to: f32| from + (to - from) * amount;
+        Self {
+            dim: lerp(0., self.dim),
+            saturation: lerp(1., self.saturation),
+            brightness: lerp(0., self.brightness),
+        }
+    }
+
+    pub fn uniform_names() -> [UniformName<'static>; 3] {
+        [
+            UniformName::new("niri_dim", UniformType::_1f),
+            UniformName::new("niri_saturation", UniformType::_1f),
+            UniformName::new("niri_brightness", UniformType::_1f),
+        ]
+    }
+
+    pub fn uniforms(&self) -> [Uniform<'static>; 3] {
+        [
+            Uniform::new("niri_dim", self.dim),
+            Uniform::new("niri_saturation", self.saturation),
+            Uniform::new("niri_brightness", self.brightness),
+        ]
+    }
+}
+
+impl Default for ColorAdjust {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl<R: NiriRenderer> ColorAdjustRenderElement<R> {
+    pub fn new(
+        elem: WaylandSurfaceRenderElement<R>,
+        program: GlesTexProgram,
+        adjust: ColorAdjust,
+    ) -> Self {
+        Self {
+            inner: elem,
+            program,
+            uniforms: adjust.uniforms().into(),
+        }
+    }
+
+    pub fn shader(renderer: &mut R) -> Option<&GlesTexProgram> {
+        Shaders::get(renderer).color_adjust.as_ref()
+    }
+}
+
+impl<R: NiriRenderer> Element for ColorAdjustRenderElement<R> {
+    fn id(&self) -> &Id {
+        self.inner.id()
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.inner.current_commit()
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.inner.geometry(scale)
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        self.inner.src()
+    }
+
+    fn transform(&self) -> Transform {
+        self.inner.transform()
+    }
+
+    fn damage_since(
+        &self,
+        scale: Scale<f64>,
+        commit: Option<CommitCounter>,
+    ) -> DamageSet<i32, Physical> {
+        // Adjustment changes are damaged separately, see Tile::color_adjust_damage.
+        self.inner.damage_since(scale, commit)
+    }
+
+    fn opaque_regions(&self, scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
+        self.inner.opaque_regions(scale)
+    }
+
+    fn alpha(&self) -> f32 {
+        self.inner.alpha()
+    }
+
+    fn kind(&self) -> Kind {
+        self.inner.kind()
+    }
+}
+
+impl RenderElement<GlesRenderer> for ColorAdjustRenderElement<GlesRenderer> {
+    fn draw(
+        &self,
+        frame: &mut GlesFrame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), GlesError> {
+        frame.override_default_tex_program(self.program.clone(), self.uniforms.clone());
+        RenderElement::<GlesRenderer>::draw(&self.inner, frame, src, dst, damage, opaque_regions)?;
+        frame.clear_tex_program_override();
+        Ok(())
+    }
+
+    fn underlying_storage(&self, _renderer: &mut GlesRenderer) -> Option<UnderlyingStorage> {
+        // The colors change, so the buffer can't be scanned out as is.
+        None
+    }
+}
+
+impl<'render> RenderElement<TtyRenderer<'render>>
+    for ColorAdjustRenderElement<TtyRenderer<'render>>
+{
+    fn draw(
+        &self,
+        frame: &mut TtyFrame<'render, '_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), TtyRendererError<'render>> {
+        frame
+            .as_gles_frame()
+            .override_default_tex_program(self.program.clone(), self.uniforms.clone());
+        RenderElement::draw(&self.inner, frame, src, dst, damage, opaque_regions)?;
+        frame.as_gles_frame().clear_tex_program_override();
+        Ok(())
+    }
+
+    fn underlying_storage(
+        &self,
+        _renderer: &mut TtyRenderer<'render>,
+    ) -> Option<UnderlyingStorage> {
+        // The colors change, so the buffer can't be scanned out as is.
+        None
+    }
+}
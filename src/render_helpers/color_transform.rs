@@ -0,0 +1,323 @@
+use glam::Mat3;
+use niri_config::CornerRadius;
+use smithay::backend::renderer::element::surface::WaylandSurfaceRenderElement;
+use smithay::backend::renderer::element::{Element, Id, Kind, RenderElement, UnderlyingStorage};
+use smithay::backend::renderer::gles::{
+    GlesError, GlesFrame, GlesRenderer, GlesTexProgram, Uniform,
+};
+use smithay::backend::renderer::utils::{
+    CommitCounter, DamageSet, OpaqueRegions, RendererSurfaceStateUserData,
+};
+use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+use smithay::utils::{Buffer, Logical, Physical, Point, Rectangle, Scale, Transform};
+use smithay::wayland::compositor::{with_surface_tree_downward, TraversalAction};
+
+use super::clipped_surface::ClippedSurfaceRenderElement;
+use super::color_adjust::ColorAdjust;
+use super::renderer::{AsGlesFrame as _, NiriRenderer};
+use super::shaders::{mat3_uniform, Shaders};
+use crate::backend::tty::{TtyFrame, TtyRenderer, TtyRendererError};
+use crate::protocols::color_management::surface_color_description;
+use crate::utils::color::ColorDescription;
+
+/// Surface converted from its image description into the color space of the output.
+#[derive(Debug)]
+pub struct ColorTransformRenderElement<R: NiriRenderer> {
+    inner: WaylandSurfaceRenderElement<R>,
+    program: GlesTexProgram,
+    /// Geometry and corner radius to clip the surface to.
+    clip: Option<(Rectangle<f64, Logical>, CornerRadius)>,
+    conversion_uniforms: Vec<Uniform<'static>>,
+    clip_uniforms: Vec<Uniform<'static>>,
+    adjust: ColorAdjust,
+}
+
+impl<R: NiriRenderer> ColorTransformRenderElement<R> {
+    pub fn new(
+        elem: WaylandSurfaceRenderElement<R>,
+        program: GlesTexProgram,
+        from: &ColorDescription,
+        to: &ColorDescription,
+    ) -> Self {
+        let (tf_in, tf_in_exp) = from.tf.shader_params();
+        let (tf_out, tf_out_exp) = to.tf.shader_params();
+
+        let conversion_uniforms = vec![
+            Uniform::new("tf_in", tf_in),
+            Uniform::new("tf_in_exp", tf_in_exp),
+            Uniform::new("tf_out", tf_out),
+            Uniform::new("tf_out_exp", tf_out_exp),
+            mat3_uniform("conversion", from.conversion_matrix(to)),
+        ];
+
+        // The uniforms keep their values between draws, so they need setting even without clip.
+        let clip_uniforms = vec![
+            Uniform::new("niri_clip", 0f32),
+            Uniform::new("niri_scale", 1f32),
+            Uniform::new("geo_size", (1f32, 1f32)),
+            Uniform::new("corner_radius", [0f32; 4]),
+            mat3_uniform("input_to_geo", Mat3::IDENTITY),
+        ];
+
+        Self {
+            inner: elem,
+            program,
+            clip: None,
+            conversion_uniforms,
+            clip_uniforms,
+            adjust: ColorAdjust::IDENTITY,
+        }
+    }
+
+    /// Clips the surface to the geometry with rounded corners, like
+    /// [`ClippedSurfaceRenderElement`].
+    pub fn with_clip(
+        mut self,
+        scale: Scale<f64>,
+        geometry: Rectangle<f64, Logical>,
+        corner_radius: CornerRadius,
+    ) -> Self {
+        let input_to_geo = ClippedSurfaceRenderElement::input_to_geo(&self.inner, scale, geometry);
+
+        self.clip = Some((geometry, corner_radius));
+        self.clip_uniforms = vec![
+            Uniform::new("niri_clip", 1f32),
+            Uniform::new("niri_scale", scale.x as f32),
+            Uniform::new("geo_size", (geometry.size.w as f32, geometry.size.h as f32)),
+            Uniform::new("corner_radius", <[f32; 4]>::from(corner_radius)),
+            mat3_uniform("input_to_geo", input_to_geo),
+        ];
+        self
+    }
+
+    /// Adjusts the converted colors, like [`ColorAdjustRenderElement`].
+    ///
+    /// [`ColorAdjustRenderElement`]: super::color_adjust::ColorAdjustRenderElement
+    pub fn with_color_adjust(mut self, adjust: ColorAdjust) -> Self {
+        self.adjust = adjust;
+        self
+    }
+
+    fn uniforms(&self) -> Vec<Uniform<'static>> {
+        let uniforms = self.conversion_uniforms.iter().chain(&self.clip_uniforms);
+        uniforms.cloned().chain(self.adjust.uniforms()).collect()
+    }
+
+    pub fn shader(renderer: &mut R) -> Option<&GlesTexProgram> {
+        Shaders::get(renderer).color_transform.as_ref()
+    }
+}
+
+/// Renders elements from a surface tree, converting surfaces into the output color space.
+///
+/// Works like the smithay function of the same name. Surfaces without an image description are
+/// sRGB, and surfaces already in the output color space are rendered as is, so they can still be
+/// scanned out directly.
+pub fn render_elements_from_surface_tree<R, E>(
+    renderer: &mut R,
+    surface: &WlSurface,
+    location: Point<f64, Physical>,
+    scale: Scale<f64>,
+    alpha: f32,
+    kind: Kind,
+    target: &ColorDescription,
+) -> Vec<E>
+where
+    R: NiriRenderer,
+    E: From<WaylandSurfaceRenderElement<R>> + From<ColorTransformRenderElement<R>>,
+{
+    let _span = tracy_client::span!("color_transform::render_elements_from_surface_tree");
+
+    let program = ColorTransformRenderElement::shader(renderer).cloned();
+    let srgb = ColorDescription::srgb();
+    let mut rv = Vec::new();
+
+    with_surface_tree_downward(
+        surface,
+        location,
+        |_, states, location| {
+            let mut location = *location;
+            let data = states.data_map.get::<RendererSurfaceStateUserData>();
+
+            if let Some(data) = data {
+                let data = &*data.lock().unwrap();
+
+                if let Some(view) = data.view() {
+                    location += view.offset.to_f64().to_physical(scale);
+                    TraversalAction::DoChildren(location)
+                } else {
+                    TraversalAction::SkipChildren
+                }
+            } else {
+                TraversalAction::SkipChildren
+            }
+        },
+        |surface, states, location| {
+            let mut location = *location;
+            let Some(data) = states.data_map.get::<RendererSurfaceStateUserData>() else {
+                return;
+            };
+            let Some(view) = data.lock().unwrap().view() else {
+                return;
+            };
+            location += view.offset.to_f64().to_physical(scale);
+
+            let elem = match WaylandSurfaceRenderElement::from_surface(
+                renderer, surface, states, location, alpha, kind,
+            ) {
+                Ok(Some(elem)) => elem,
+                Ok(None) => return,
+                Err(err) => {
+                    warn!("failed to import surface: {err:?}");
+                    return;
+                }
+            };
+
+            let description = surface_color_description(states);
+            let description = description.as_deref().unwrap_or(&srgb);
+
+            match &program {
+                Some(program) if description.needs_conversion_to(target) => {
+                    let elem = ColorTransformRenderElement::new(
+                        elem,
+                        program.clone(),
+                        description,
+                        target,
+                    );
+                    rv.push(elem.into());
+                }
+                _ => rv.push(elem.into()),
+            }
+        },
+        |_, _, _| true,
+    );
+
+    rv
+}
+
+impl<R: NiriRenderer> Element for ColorTransformRenderElement<R> {
+    fn id(&self) -> &Id {
+        self.inner.id()
+    }
+
+    fn current_commit(&self) -> CommitCounter {
+        self.inner.current_commit()
+    }
+
+    fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+        self.inner.geometry(scale)
+    }
+
+    fn src(&self) -> Rectangle<f64, Buffer> {
+        self.inner.src()
+    }
+
+    fn transform(&self) -> Transform {
+        self.inner.transform()
+    }
+
+    fn damage_since(
+        &self,
+        scale: Scale<f64>,
+        commit: Option<CommitCounter>,
+    ) -> DamageSet<i32, Physical> {
+        // Output color description changes are damaged by the output as a whole, and adjustment
+        // changes are damaged separately, see Tile::color_adjust_damage.
+        let damage = self.inner.damage_since(scale, commit);
+
+        let Some((geometry, _)) = self.clip else {
+            return damage;
+        };
+
+        // Intersect with geometry, since we're clipping by it.
+        let mut geo = geometry.to_physical_precise_round(scale);
+        geo.loc -= self.geometry(scale).loc;
+        damage
+            .into_iter()
+            .filter_map(|rect| rect.intersection(geo))
+            .collect()
+    }
+
+    fn opaque_regions(&self, scale: Scale<f64>) -> OpaqueRegions<i32, Physical> {
+        let regions = self.inner.opaque_regions(scale);
+
+        let Some((geometry, corner_radius)) = self.clip else {
+            return regions;
+        };
+
+        // Intersect with geometry, since we're clipping by it.
+        let elem_loc = self.geometry(scale).loc;
+        let mut geo = geometry.to_physical_precise_round(scale);
+        geo.loc -= elem_loc;
+        let regions = regions
+            .into_iter()
+            .filter_map(|rect| rect.intersection(geo));
+
+        // Subtract the rounded corners.
+        let corners = ClippedSurfaceRenderElement::<R>::rounded_corners(geometry, corner_radius);
+        let corners = corners.into_iter().map(|rect| {
+            let mut rect = rect.to_physical_precise_up(scale);
+            rect.loc -= elem_loc;
+            rect
+        });
+
+        OpaqueRegions::from_slice(&Rectangle::subtract_rects_many(regions, corners))
+    }
+
+    fn alpha(&self) -> f32 {
+        self.inner.alpha()
+    }
+
+    fn kind(&self) -> Kind {
+        self.inner.kind()
+    }
+}
+
+impl RenderElement<GlesRenderer> for ColorTransformRenderElement<GlesRenderer> {
+    fn draw(
+        &self,
+        frame: &mut GlesFrame<'_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), GlesError> {
+        frame.override_default_tex_program(self.program.clone(), self.uniforms());
+        RenderElement::<GlesRenderer>::draw(&self.inner, frame, src, dst, damage, opaque_regions)?;
+        frame.clear_tex_program_override();
+        Ok(())
+    }
+
+    fn underlying_storage(&self, _renderer: &mut GlesRenderer) -> Option<UnderlyingStorage> {
+        // The buffer needs converting, so it can't be scanned out as is.
+        None
+    }
+}
+
+impl<'render> RenderElement<TtyRenderer<'render>>
+    for ColorTransformRenderElement<TtyRenderer<'render>>
+{
+    fn draw(
+        &self,
+        frame: &mut TtyFrame<'render, '_, '_>,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        opaque_regions: &[Rectangle<i32, Physical>],
+    ) -> Result<(), TtyRendererError<'render>> {
+        frame
+            .as_gles_frame()
+            .override_default_tex_program(self.program.clone(), self.uniforms());
+        RenderElement::draw(&self.inner, frame, src, dst, damage, opaque_regions)?;
+        frame.as_gles_frame().clear_tex_program_override();
+        Ok(())
+    }
+
+    fn underlying_storage(
+        &self,
+        _renderer: &mut TtyRenderer<'render>,
+    ) -> Option<UnderlyingStorage> {
+        // The buffer needs converting, so it can't be scanned out as is.
+        None
+    }
+}
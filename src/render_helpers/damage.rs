@@ -36,6 +36,12 @@ impl ExtraDamage {
         self.geometry.loc = location;
         self
     }
+
+    /// Extends the damaged area to also cover `rect`.
+    pub fn merged(mut self, rect: Rectangle<f64, Logical>) -> Self {
+        self.geometry = self.geometry.merge(rect);
+        self
+    }
 }
 
 impl Default for ExtraDamage {
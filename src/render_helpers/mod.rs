@@ -5,7 +5,7 @@ use niri_config::BlockOutFrom;
 use smithay::backend::allocator::dmabuf::Dmabuf;
 use smithay::backend::allocator::{Buffer, Fourcc};
 use smithay::backend::renderer::element::utils::{Relocate, RelocateRenderElement};
-use smithay::backend::renderer::element::{Kind, RenderElement};
+use smithay::backend::renderer::element::{Element, Id, Kind, RenderElement};
 use smithay::backend::renderer::gles::{GlesMapping, GlesRenderer, GlesTarget, GlesTexture};
 use smithay::backend::renderer::sync::SyncPoint;
 use smithay::backend::renderer::{Bind, Color32F, ExportMem, Frame, Offscreen, Renderer};
@@ -18,8 +18,12 @@ use solid_color::{SolidColorBuffer, SolidColorRenderElement};
 use self::primary_gpu_texture::PrimaryGpuTextureRenderElement;
 use self::texture::{TextureBuffer, TextureRenderElement};
 
+pub mod blur;
 pub mod border;
+pub mod boxed;
 pub mod clipped_surface;
+pub mod color_adjust;
+pub mod color_transform;
 pub mod damage;
 pub mod debug;
 pub mod memory;
@@ -167,6 +171,33 @@ pub fn encompassing_geo(
         .unwrap_or_default()
 }
 
+/// Returns whether the element with `id` is the topmost element covering the whole output.
+///
+/// Only such an element can be scanned out on the primary plane, which is all that an async page
+/// flip is able to change. Cursor elements are skipped as they go on the cursor plane.
+pub fn is_fullscreen_scanout_candidate<E: Element>(
+    elements: &[E],
+    id: &Id,
+    output_size: Size<i32, Physical>,
+    scale: Scale<f64>,
+) -> bool {
+    let output_rect = Rectangle::from_size(output_size);
+
+    for elem in elements {
+        let geo = elem.geometry(scale);
+
+        if elem.id() == id {
+            return geo.contains_rect(output_rect);
+        }
+
+        if elem.kind() != Kind::Cursor && geo.overlaps(output_rect) {
+            return false;
+        }
+    }
+
+    false
+}
+
 pub fn render_to_encompassing_texture(
     renderer: &mut GlesRenderer,
     scale: Scale<f64>,
@@ -355,3 +386,71 @@ fn render_elements(
 
     frame.finish().context("error finishing frame")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solid(buffer: &SolidColorBuffer, x: f64, y: f64, kind: Kind) -> SolidColorRenderElement {
+        SolidColorRenderElement::from_buffer(buffer, (x, y), 1., kind)
+    }
+
+    #[test]
+    fn fullscreen_scanout_candidate() {
+        let size = Size::from((100, 50));
+        let scale = Scale::from(1.);
+
+        let fullscreen = SolidColorBuffer::new((100., 50.), [1., 0., 0., 1.]);
+        let small = SolidColorBuffer::new((10., 10.), [0., 1., 0., 1.]);
+        let id = solid(&fullscreen, 0., 0., Kind::Unspecified).id().clone();
+        let check = |elements: &[SolidColorRenderElement]| {
+            is_fullscreen_scanout_candidate(elements, &id, size, scale)
+        };
+
+        // Alone on the output.
+        let elements = [solid(&fullscreen, 0., 0., Kind::Unspecified)];
+        assert!(check(&elements));
+
+        // Below a cursor.
+        let elements = [
+            solid(&small, 5., 5., Kind::Cursor),
+            solid(&fullscreen, 0., 0., Kind::Unspecified),
+        ];
+        assert!(check(&elements));
+
+        // Below something that needs compositing.
+        let elements = [
+            solid(&small, 5., 5., Kind::Unspecified),
+            solid(&fullscreen, 0., 0., Kind::Unspecified),
+        ];
+        assert!(!check(&elements));
+
+        // Above other elements.
+        let elements = [
+            solid(&fullscreen, 0., 0., Kind::Unspecified),
+            solid(&small, 5., 5., Kind::Unspecified),
+        ];
+        assert!(check(&elements));
+
+        // Elements off the output don't matter.
+        let elements = [
+            solid(&small, 200., 5., Kind::Unspecified),
+            solid(&fullscreen, 0., 0., Kind::Unspecified),
+        ];
+        assert!(check(&elements));
+
+        // Not covering the whole output.
+        let elements = [solid(&fullscreen, 10., 0., Kind::Unspecified)];
+        assert!(!check(&elements));
+
+        // Missing from the frame.
+        let elements = [solid(&small, 5., 5., Kind::Unspecified)];
+        assert!(!check(&elements));
+
+        // Fractional scale.
+        let elements = [solid(&fullscreen, 0., 0., Kind::Unspecified)];
+        let size = Size::from((150, 75));
+        let scale = Scale::from(1.5);
+        assert!(is_fullscreen_scanout_candidate(&elements, &id, size, scale));
+    }
+}
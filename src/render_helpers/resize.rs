@@ -8,6 +8,7 @@ use smithay::backend::renderer::utils::{CommitCounter, DamageSet, OpaqueRegions}
 use smithay::backend::renderer::Texture as _;
 use smithay::utils::{Buffer, Logical, Physical, Rectangle, Scale, Size, Transform};
 
+use super::color_adjust::ColorAdjust;
 use super::renderer::{AsGlesFrame, NiriRenderer};
 use super::shader_element::ShaderRenderElement;
 use super::shaders::{mat3_uniform, ProgramType, Shaders};
@@ -29,6 +30,7 @@ impl ResizeRenderElement {
         clamped_progress: f32,
         corner_radius: CornerRadius,
         clip_to_geometry: bool,
+        adjust: ColorAdjust,
         result_alpha: f32,
     ) -> Self {
         let curr_geo = area;
@@ -81,6 +83,7 @@ impl ResizeRenderElement {
 
         let corner_radius = corner_radius.fit_to(curr_geo_size.x, curr_geo_size.y);
         let clip_to_geometry = if clip_to_geometry { 1. } else { 0. };
+        let [dim, saturation, brightness] = adjust.uniforms();
 
         // Create the shader.
         Self(
@@ -101,6 +104,9 @@ impl ResizeRenderElement {
                     Uniform::new("niri_clamped_progress", clamped_progress),
                     Uniform::new("niri_corner_radius", <[f32; 4]>::from(corner_radius)),
                     Uniform::new("niri_clip_to_geometry", clip_to_geometry),
+                    dim,
+                    saturation,
+                    brightness,
                 ],
                 HashMap::from([
                     (String::from("niri_tex_prev"), texture_prev),
@@ -21,6 +21,8 @@ use crate::backend::tty::{TtyFrame, TtyRenderer, TtyRendererError};
 #[derive(Debug, Clone)]
 pub struct ShaderRenderElement {
     program: ProgramType,
+    /// Key of the custom program, for program types with several of them.
+    program_key: Option<String>,
     id: Id,
     commit_counter: CommitCounter,
     area: Rectangle<f64, Logical>,
@@ -191,6 +193,7 @@ impl ShaderRenderElement {
     ) -> Self {
         Self {
             program,
+            program_key: None,
             id: Id::new(),
             commit_counter: CommitCounter::default(),
             area: Rectangle::from_size(size),
@@ -206,6 +209,7 @@ impl ShaderRenderElement {
     pub fn empty(program: ProgramType, kind: Kind) -> Self {
         Self {
             program,
+            program_key: None,
             id: Id::new(),
             commit_counter: CommitCounter::default(),
             area: Rectangle::default(),
@@ -250,6 +254,11 @@ impl ShaderRenderElement {
         self.alpha = alpha;
         self
     }
+
+    pub fn with_program_key(mut self, key: String) -> Self {
+        self.program_key = Some(key);
+        self
+    }
 }
 
 impl Element for ShaderRenderElement {
@@ -296,7 +305,12 @@ impl RenderElement<GlesRenderer> for ShaderRenderElement {
     ) -> Result<(), GlesError> {
         let frame = frame.as_gles_frame();
 
-        let Some(shader) = Shaders::get_from_frame(frame).program(self.program) else {
+        let shaders = Shaders::get_from_frame(frame);
+        let shader = match (self.program, &self.program_key) {
+            (ProgramType::PostProcess, Some(key)) => shaders.post_process_program(key),
+            (program, _) => shaders.program(program),
+        };
+        let Some(shader) = shader else {
             return Ok(());
         };
 
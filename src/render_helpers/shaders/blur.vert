@@ -0,0 +1,10 @@
+#version 100
+
+attribute vec2 vert;
+
+varying vec2 v_coords;
+
+void main() {
+    v_coords = vert;
+    gl_Position = vec4(vert * 2.0 - 1.0, 0.0, 1.0);
+}
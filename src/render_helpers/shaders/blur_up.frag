@@ -0,0 +1,30 @@
+#version 100
+
+// Dual Kawase upsampling pass.
+//
+// Based on "Bandwidth-Efficient Rendering" by Marius Bjørge, SIGGRAPH 2015.
+
+precision highp float;
+
+uniform sampler2D tex;
+uniform mat3 uv_matrix;
+uniform vec2 halfpixel;
+uniform float offset;
+
+varying vec2 v_coords;
+
+vec4 sample_at(vec2 coords) {
+    return texture2D(tex, (uv_matrix * vec3(coords, 1.0)).st);
+}
+
+void main() {
+    vec4 sum = sample_at(v_coords + vec2(-halfpixel.x * 2.0, 0.0) * offset);
+    sum += sample_at(v_coords + vec2(-halfpixel.x, halfpixel.y) * offset) * 2.0;
+    sum += sample_at(v_coords + vec2(0.0, halfpixel.y * 2.0) * offset);
+    sum += sample_at(v_coords + vec2(halfpixel.x, halfpixel.y) * offset) * 2.0;
+    sum += sample_at(v_coords + vec2(halfpixel.x * 2.0, 0.0) * offset);
+    sum += sample_at(v_coords + vec2(halfpixel.x, -halfpixel.y) * offset) * 2.0;
+    sum += sample_at(v_coords + vec2(0.0, -halfpixel.y * 2.0) * offset);
+    sum += sample_at(v_coords + vec2(-halfpixel.x, -halfpixel.y) * offset) * 2.0;
+    gl_FragColor = sum / 12.0;
+}
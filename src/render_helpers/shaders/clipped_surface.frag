@@ -26,6 +26,18 @@ uniform vec2 geo_size;
 uniform vec4 corner_radius;
 uniform mat3 input_to_geo;
 
+uniform float niri_dim;
+uniform float niri_saturation;
+uniform float niri_brightness;
+
+vec4 adjust_color(vec4 color) {
+    // Colors are premultiplied, so the luminance and the brightness offset scale with alpha.
+    float luminance = dot(color.rgb, vec3(0.2126, 0.7152, 0.0722));
+    vec3 rgb = mix(vec3(luminance), color.rgb, niri_saturation);
+    rgb = rgb * (1.0 - niri_dim) + niri_brightness * color.a;
+    return vec4(clamp(rgb, 0.0, color.a), color.a);
+}
+
 float rounding_alpha(vec2 coords, vec2 size) {
     vec2 center;
     float radius;
@@ -60,6 +72,8 @@ void main() {
     color = vec4(color.rgb, 1.0);
 #endif
 
+    color = adjust_color(color);
+
     if (coords_geo.x < 0.0 || 1.0 < coords_geo.x || coords_geo.y < 0.0 || 1.0 < coords_geo.y) {
         // Clip outside geometry.
         color = vec4(0.0);
@@ -0,0 +1,53 @@
+#version 100
+
+//_DEFINES_
+
+#if defined(EXTERNAL)
+#extension GL_OES_EGL_image_external : require
+#endif
+
+precision highp float;
+#if defined(EXTERNAL)
+uniform samplerExternalOES tex;
+#else
+uniform sampler2D tex;
+#endif
+
+uniform float alpha;
+varying vec2 v_coords;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+uniform float niri_dim;
+uniform float niri_saturation;
+uniform float niri_brightness;
+
+vec4 adjust_color(vec4 color) {
+    // Colors are premultiplied, so the luminance and the brightness offset scale with alpha.
+    float luminance = dot(color.rgb, vec3(0.2126, 0.7152, 0.0722));
+    vec3 rgb = mix(vec3(luminance), color.rgb, niri_saturation);
+    rgb = rgb * (1.0 - niri_dim) + niri_brightness * color.a;
+    return vec4(clamp(rgb, 0.0, color.a), color.a);
+}
+
+void main() {
+    // Sample the texture.
+    vec4 color = texture2D(tex, v_coords);
+#if defined(NO_ALPHA)
+    color = vec4(color.rgb, 1.0);
+#endif
+
+    color = adjust_color(color);
+
+    // Apply final alpha and tint.
+    color = color * alpha;
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        color = vec4(0.0, 0.2, 0.0, 0.2) + color * 0.8;
+#endif
+
+    gl_FragColor = color;
+}
@@ -0,0 +1,160 @@
+#version 100
+
+//_DEFINES_
+
+#if defined(EXTERNAL)
+#extension GL_OES_EGL_image_external : require
+#endif
+
+precision highp float;
+#if defined(EXTERNAL)
+uniform samplerExternalOES tex;
+#else
+uniform sampler2D tex;
+#endif
+
+uniform float alpha;
+varying vec2 v_coords;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+// Transfer functions: 0 is sRGB, 1 is a power function with the exponent, 2 is PQ.
+uniform float tf_in;
+uniform float tf_in_exp;
+uniform float tf_out;
+uniform float tf_out_exp;
+// Converts linear light between the primaries, mapping reference white onto reference white.
+uniform mat3 conversion;
+
+// Clipping to the window geometry, same as in the clipped surface shader.
+uniform float niri_clip;
+uniform float niri_scale;
+uniform vec2 geo_size;
+uniform vec4 corner_radius;
+uniform mat3 input_to_geo;
+
+// Color adjustments, same as in the color adjust shader.
+uniform float niri_dim;
+uniform float niri_saturation;
+uniform float niri_brightness;
+
+const float PQ_M1 = 0.1593017578125;
+const float PQ_M2 = 78.84375;
+const float PQ_C1 = 0.8359375;
+const float PQ_C2 = 18.8515625;
+const float PQ_C3 = 18.6875;
+
+vec3 srgb_to_linear(vec3 color) {
+    vec3 low = color / 12.92;
+    vec3 high = pow((color + 0.055) / 1.055, vec3(2.4));
+    return mix(low, high, step(vec3(0.04045), color));
+}
+
+vec3 linear_to_srgb(vec3 color) {
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(low, high, step(vec3(0.0031308), color));
+}
+
+vec3 pq_to_linear(vec3 color) {
+    vec3 p = pow(color, vec3(1.0 / PQ_M2));
+    vec3 num = max(p - PQ_C1, vec3(0.0));
+    return pow(num / (PQ_C2 - PQ_C3 * p), vec3(1.0 / PQ_M1));
+}
+
+vec3 linear_to_pq(vec3 color) {
+    vec3 l = pow(color, vec3(PQ_M1));
+    return pow((PQ_C1 + PQ_C2 * l) / (1.0 + PQ_C3 * l), vec3(PQ_M2));
+}
+
+vec3 decode(vec3 color, float tf, float exponent) {
+    if (tf == 0.0)
+        return srgb_to_linear(color);
+    if (tf == 1.0)
+        return pow(color, vec3(exponent));
+    return pq_to_linear(color);
+}
+
+vec3 encode(vec3 color, float tf, float exponent) {
+    if (tf == 0.0)
+        return linear_to_srgb(color);
+    if (tf == 1.0)
+        return pow(color, vec3(1.0 / exponent));
+    return linear_to_pq(color);
+}
+
+vec4 adjust_color(vec4 color) {
+    // Colors are premultiplied, so the luminance and the brightness offset scale with alpha.
+    float luminance = dot(color.rgb, vec3(0.2126, 0.7152, 0.0722));
+    vec3 rgb = mix(vec3(luminance), color.rgb, niri_saturation);
+    rgb = rgb * (1.0 - niri_dim) + niri_brightness * color.a;
+    return vec4(clamp(rgb, 0.0, color.a), color.a);
+}
+
+float rounding_alpha(vec2 coords, vec2 size) {
+    vec2 center;
+    float radius;
+
+    if (coords.x < corner_radius.x && coords.y < corner_radius.x) {
+        radius = corner_radius.x;
+        center = vec2(radius, radius);
+    } else if (size.x - corner_radius.y < coords.x && coords.y < corner_radius.y) {
+        radius = corner_radius.y;
+        center = vec2(size.x - radius, radius);
+    } else if (size.x - corner_radius.z < coords.x && size.y - corner_radius.z < coords.y) {
+        radius = corner_radius.z;
+        center = vec2(size.x - radius, size.y - radius);
+    } else if (coords.x < corner_radius.w && size.y - corner_radius.w < coords.y) {
+        radius = corner_radius.w;
+        center = vec2(radius, size.y - radius);
+    } else {
+        return 1.0;
+    }
+
+    float dist = distance(coords, center);
+    float half_px = 0.5 / niri_scale;
+    return 1.0 - smoothstep(radius - half_px, radius + half_px, dist);
+}
+
+void main() {
+    // Sample the texture.
+    vec4 color = texture2D(tex, v_coords);
+#if defined(NO_ALPHA)
+    color = vec4(color.rgb, 1.0);
+#endif
+
+    // Transfer functions apply to non-premultiplied values.
+    if (color.a > 0.0) {
+        vec3 rgb = clamp(color.rgb / color.a, 0.0, 1.0);
+        rgb = conversion * decode(rgb, tf_in, tf_in_exp);
+        // FIXME: tone mapping; out of gamut and too bright colors are clipped for now.
+        rgb = encode(clamp(rgb, 0.0, 1.0), tf_out, tf_out_exp);
+        color = vec4(rgb * color.a, color.a);
+    }
+
+    color = adjust_color(color);
+
+    if (niri_clip == 1.0) {
+        vec3 coords_geo = input_to_geo * vec3(v_coords, 1.0);
+
+        if (coords_geo.x < 0.0 || 1.0 < coords_geo.x || coords_geo.y < 0.0 || 1.0 < coords_geo.y) {
+            // Clip outside geometry.
+            color = vec4(0.0);
+        } else {
+            // Apply corner rounding inside geometry.
+            color = color * rounding_alpha(coords_geo.xy * geo_size, geo_size);
+        }
+    }
+
+    // Apply final alpha and tint.
+    color = color * alpha;
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        color = vec4(0.0, 0.2, 0.0, 0.2) + color * 0.8;
+#endif
+
+    gl_FragColor = color;
+}
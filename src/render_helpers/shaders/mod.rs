@@ -1,31 +1,57 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 
 use glam::Mat3;
+use niri_config::Config;
 use smithay::backend::renderer::gles::{
     GlesError, GlesFrame, GlesRenderer, GlesTexProgram, Uniform, UniformName, UniformType,
     UniformValue,
 };
 
+use super::blur::BlurPassProgram;
+use super::color_adjust::ColorAdjust;
 use super::renderer::NiriRenderer;
 use super::shader_element::ShaderProgram;
 
 pub struct Shaders {
     pub border: Option<ShaderProgram>,
     pub shadow: Option<ShaderProgram>,
+    pub blur: Option<ShaderProgram>,
+    pub blur_down: Option<BlurPassProgram>,
+    pub blur_up: Option<BlurPassProgram>,
     pub clipped_surface: Option<GlesTexProgram>,
+    pub color_transform: Option<GlesTexProgram>,
+    pub color_adjust: Option<GlesTexProgram>,
     pub resize: Option<ShaderProgram>,
     pub custom_resize: RefCell<Option<ShaderProgram>>,
     pub custom_close: RefCell<Option<ShaderProgram>>,
     pub custom_open: RefCell<Option<ShaderProgram>>,
+    pub custom_workspace_switch: RefCell<Option<ShaderProgram>>,
+    /// Post-processing programs keyed by the name of their output config section.
+    pub custom_post_process: RefCell<HashMap<String, ShaderProgram>>,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub enum ProgramType {
     Border,
     Shadow,
+    Blur,
     Resize,
     Close,
     Open,
+    WorkspaceSwitch,
+    /// Post-processing for an output, keyed by the name of its output config section.
+    PostProcess,
+}
+
+/// Kinds of user-provided shaders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CustomShaderKind {
+    WindowResize,
+    WindowClose,
+    WindowOpen,
+    WorkspaceSwitch,
+    PostProcess,
 }
 
 impl Shaders {
@@ -75,6 +101,34 @@ impl Shaders {
         })
         .ok();
 
+        let blur = ShaderProgram::compile(
+            renderer,
+            include_str!("blur.frag"),
+            &[
+                UniformName::new("input_to_geo", UniformType::Matrix3x3),
+                UniformName::new("geo_size", UniformType::_2f),
+                UniformName::new("corner_radius", UniformType::_4f),
+            ],
+            &["niri_tex"],
+        )
+        .map_err(|err| {
+            warn!("error compiling blur shader: {err:?}");
+        })
+        .ok();
+
+        let blur_down = BlurPassProgram::compile(renderer, include_str!("blur_down.frag"))
+            .map_err(|err| {
+                warn!("error compiling blur downsample shader: {err:?}");
+            })
+            .ok();
+
+        let blur_up = BlurPassProgram::compile(renderer, include_str!("blur_up.frag"))
+            .map_err(|err| {
+                warn!("error compiling blur upsample shader: {err:?}");
+            })
+            .ok();
+
+        let [dim, saturation, brightness] = ColorAdjust::uniform_names();
         let clipped_surface = renderer
             .compile_custom_texture_shader(
                 include_str!("clipped_surface.frag"),
@@ -83,6 +137,9 @@ impl Shaders {
                     UniformName::new("geo_size", UniformType::_2f),
                     UniformName::new("corner_radius", UniformType::_4f),
                     UniformName::new("input_to_geo", UniformType::Matrix3x3),
+                    dim,
+                    saturation,
+                    brightness,
                 ],
             )
             .map_err(|err| {
@@ -90,6 +147,41 @@ impl Shaders {
             })
             .ok();
 
+        let [dim, saturation, brightness] = ColorAdjust::uniform_names();
+        let color_transform = renderer
+            .compile_custom_texture_shader(
+                include_str!("color_transform.frag"),
+                &[
+                    UniformName::new("tf_in", UniformType::_1f),
+                    UniformName::new("tf_in_exp", UniformType::_1f),
+                    UniformName::new("tf_out", UniformType::_1f),
+                    UniformName::new("tf_out_exp", UniformType::_1f),
+                    UniformName::new("conversion", UniformType::Matrix3x3),
+                    UniformName::new("niri_clip", UniformType::_1f),
+                    UniformName::new("niri_scale", UniformType::_1f),
+                    UniformName::new("geo_size", UniformType::_2f),
+                    UniformName::new("corner_radius", UniformType::_4f),
+                    UniformName::new("input_to_geo", UniformType::Matrix3x3),
+                    dim,
+                    saturation,
+                    brightness,
+                ],
+            )
+            .map_err(|err| {
+                warn!("error compiling color transform shader: {err:?}");
+            })
+            .ok();
+
+        let color_adjust = renderer
+            .compile_custom_texture_shader(
+                include_str!("color_adjust.frag"),
+                &ColorAdjust::uniform_names(),
+            )
+            .map_err(|err| {
+                warn!("error compiling color adjust shader: {err:?}");
+            })
+            .ok();
+
         let resize = compile_resize_program(renderer, include_str!("resize.frag"))
             .map_err(|err| {
                 warn!("error compiling resize shader: {err:?}");
@@ -99,11 +191,18 @@ impl Shaders {
         Self {
             border,
             shadow,
+            blur,
+            blur_down,
+            blur_up,
             clipped_surface,
+            color_transform,
+            color_adjust,
             resize,
             custom_resize: RefCell::new(None),
             custom_close: RefCell::new(None),
             custom_open: RefCell::new(None),
+            custom_workspace_switch: RefCell::new(None),
+            custom_post_process: RefCell::new(HashMap::new()),
         }
     }
 
@@ -141,10 +240,25 @@ impl Shaders {
         self.custom_open.replace(program)
     }
 
+    pub fn replace_custom_workspace_switch_program(
+        &self,
+        program: Option<ShaderProgram>,
+    ) -> Option<ShaderProgram> {
+        self.custom_workspace_switch.replace(program)
+    }
+
+    pub fn replace_custom_post_process_programs(
+        &self,
+        programs: HashMap<String, ShaderProgram>,
+    ) -> HashMap<String, ShaderProgram> {
+        self.custom_post_process.replace(programs)
+    }
+
     pub fn program(&self, program: ProgramType) -> Option<ShaderProgram> {
         match program {
             ProgramType::Border => self.border.clone(),
             ProgramType::Shadow => self.shadow.clone(),
+            ProgramType::Blur => self.blur.clone(),
             ProgramType::Resize => self
                 .custom_resize
                 .borrow()
@@ -152,8 +266,15 @@ impl Shaders {
                 .or_else(|| self.resize.clone()),
             ProgramType::Close => self.custom_close.borrow().clone(),
             ProgramType::Open => self.custom_open.borrow().clone(),
+            ProgramType::WorkspaceSwitch => self.custom_workspace_switch.borrow().clone(),
+            // Post-processing programs need a key, see post_process_program().
+            ProgramType::PostProcess => None,
         }
     }
+
+    pub fn post_process_program(&self, name: &str) -> Option<ShaderProgram> {
+        self.custom_post_process.borrow().get(name).cloned()
+    }
 }
 
 pub fn init(renderer: &mut GlesRenderer) {
@@ -168,6 +289,8 @@ fn compile_resize_program(
     renderer: &mut GlesRenderer,
     src: &str,
 ) -> Result<ShaderProgram, GlesError> {
+    let [dim, saturation, brightness] = ColorAdjust::uniform_names();
+
     let mut program = include_str!("resize_prelude.frag").to_string();
     program.push_str(src);
     program.push_str(include_str!("resize_epilogue.frag"));
@@ -186,18 +309,24 @@ fn compile_resize_program(
             UniformName::new("niri_clamped_progress", UniformType::_1f),
             UniformName::new("niri_corner_radius", UniformType::_4f),
             UniformName::new("niri_clip_to_geometry", UniformType::_1f),
+            dim,
+            saturation,
+            brightness,
         ],
         &["niri_tex_prev", "niri_tex_next"],
     )
 }
 
-pub fn set_custom_resize_program(renderer: &mut GlesRenderer, src: Option<&str>) {
+/// Sets the custom resize shader, returning `false` if it failed to compile.
+///
+/// The previous shader is kept on failure.
+pub fn set_custom_resize_program(renderer: &mut GlesRenderer, src: Option<&str>) -> bool {
     let program = if let Some(src) = src {
         match compile_resize_program(renderer, src) {
             Ok(program) => Some(program),
             Err(err) => {
                 warn!("error compiling custom resize shader: {err:?}");
-                return;
+                return false;
             }
         }
     } else {
@@ -209,6 +338,8 @@ pub fn set_custom_resize_program(renderer: &mut GlesRenderer, src: Option<&str>)
             warn!("error destroying previous custom resize shader: {err:?}");
         }
     }
+
+    true
 }
 
 fn compile_close_program(
@@ -234,13 +365,16 @@ fn compile_close_program(
     )
 }
 
-pub fn set_custom_close_program(renderer: &mut GlesRenderer, src: Option<&str>) {
+/// Sets the custom close shader, returning `false` if it failed to compile.
+///
+/// The previous shader is kept on failure.
+pub fn set_custom_close_program(renderer: &mut GlesRenderer, src: Option<&str>) -> bool {
     let program = if let Some(src) = src {
         match compile_close_program(renderer, src) {
             Ok(program) => Some(program),
             Err(err) => {
                 warn!("error compiling custom close shader: {err:?}");
-                return;
+                return false;
             }
         }
     } else {
@@ -252,6 +386,8 @@ pub fn set_custom_close_program(renderer: &mut GlesRenderer, src: Option<&str>)
             warn!("error destroying previous custom close shader: {err:?}");
         }
     }
+
+    true
 }
 
 fn compile_open_program(
@@ -277,13 +413,16 @@ fn compile_open_program(
     )
 }
 
-pub fn set_custom_open_program(renderer: &mut GlesRenderer, src: Option<&str>) {
+/// Sets the custom open shader, returning `false` if it failed to compile.
+///
+/// The previous shader is kept on failure.
+pub fn set_custom_open_program(renderer: &mut GlesRenderer, src: Option<&str>) -> bool {
     let program = if let Some(src) = src {
         match compile_open_program(renderer, src) {
             Ok(program) => Some(program),
             Err(err) => {
                 warn!("error compiling custom open shader: {err:?}");
-                return;
+                return false;
             }
         }
     } else {
@@ -295,6 +434,151 @@ pub fn set_custom_open_program(renderer: &mut GlesRenderer, src: Option<&str>) {
             warn!("error destroying previous custom open shader: {err:?}");
         }
     }
+
+    true
+}
+
+fn compile_workspace_switch_program(
+    renderer: &mut GlesRenderer,
+    src: &str,
+) -> Result<ShaderProgram, GlesError> {
+    let mut program = include_str!("workspace_switch_prelude.frag").to_string();
+    program.push_str(src);
+    program.push_str(include_str!("workspace_switch_epilogue.frag"));
+
+    ShaderProgram::compile(
+        renderer,
+        &program,
+        &[
+            UniformName::new("niri_output_size", UniformType::_2f),
+            UniformName::new("niri_cursor_position", UniformType::_2f),
+            UniformName::new("niri_geo_to_tex_prev", UniformType::Matrix3x3),
+            UniformName::new("niri_geo_to_tex_next", UniformType::Matrix3x3),
+            UniformName::new("niri_progress", UniformType::_1f),
+        ],
+        &["niri_tex_prev", "niri_tex_next"],
+    )
+}
+
+/// Sets the custom workspace switch shader, returning `false` if it failed to compile.
+///
+/// The previous shader is kept on failure.
+pub fn set_custom_workspace_switch_program(renderer: &mut GlesRenderer, src: Option<&str>) -> bool {
+    let program = if let Some(src) = src {
+        match compile_workspace_switch_program(renderer, src) {
+            Ok(program) => Some(program),
+            Err(err) => {
+                warn!("error compiling custom workspace switch shader: {err:?}");
+                return false;
+            }
+        }
+    } else {
+        None
+    };
+
+    if let Some(prev) = Shaders::get(renderer).replace_custom_workspace_switch_program(program) {
+        if let Err(err) = prev.destroy(renderer) {
+            warn!("error destroying previous custom workspace switch shader: {err:?}");
+        }
+    }
+
+    true
+}
+
+fn compile_post_process_program(
+    renderer: &mut GlesRenderer,
+    src: &str,
+) -> Result<ShaderProgram, GlesError> {
+    let mut program = include_str!("post_process_prelude.frag").to_string();
+    program.push_str(src);
+    program.push_str(include_str!("post_process_epilogue.frag"));
+
+    ShaderProgram::compile(
+        renderer,
+        &program,
+        &[
+            UniformName::new("niri_output_size", UniformType::_2f),
+            UniformName::new("niri_cursor_position", UniformType::_2f),
+            UniformName::new("niri_geo_to_tex", UniformType::Matrix3x3),
+        ],
+        &["niri_tex"],
+    )
+}
+
+/// Sets the output post-processing shaders from the output config sections.
+///
+/// Returns `false` if any of them failed to compile. Outputs with a broken shader are left
+/// without post-processing.
+pub fn set_custom_post_process_programs(
+    renderer: &mut GlesRenderer,
+    outputs: &niri_config::Outputs,
+) -> bool {
+    let mut ok = true;
+    let mut programs = HashMap::new();
+    for output in &outputs.0 {
+        let Some(src) = output.custom_shader.as_deref() else {
+            continue;
+        };
+
+        match compile_post_process_program(renderer, src) {
+            Ok(program) => {
+                programs.insert(output.name.clone(), program);
+            }
+            Err(err) => {
+                warn!(
+                    "error compiling custom post-process shader for output {:?}: {err:?}",
+                    output.name
+                );
+                ok = false;
+            }
+        }
+    }
+
+    let prev = Shaders::get(renderer).replace_custom_post_process_programs(programs);
+    for program in prev.into_values() {
+        if let Err(err) = program.destroy(renderer) {
+            warn!("error destroying previous custom post-process shader: {err:?}");
+        }
+    }
+
+    ok
+}
+
+/// Compiles all custom shaders from the config, returning `false` if any failed to compile.
+pub fn set_custom_programs(renderer: &mut GlesRenderer, config: &Config) -> bool {
+    let animations = &config.animations;
+
+    let mut ok = true;
+    if let Some(src) = animations.window_resize.custom_shader.as_deref() {
+        ok &= set_custom_resize_program(renderer, Some(src));
+    }
+    if let Some(src) = animations.window_close.custom_shader.as_deref() {
+        ok &= set_custom_close_program(renderer, Some(src));
+    }
+    if let Some(src) = animations.window_open.custom_shader.as_deref() {
+        ok &= set_custom_open_program(renderer, Some(src));
+    }
+    if let Some(src) = animations.workspace_switch.custom_shader.as_deref() {
+        ok &= set_custom_workspace_switch_program(renderer, Some(src));
+    }
+    ok &= set_custom_post_process_programs(renderer, &config.outputs);
+    ok
+}
+
+/// Compiles a user-provided shader to check it for errors.
+pub fn compile_custom_program(
+    renderer: &mut GlesRenderer,
+    kind: CustomShaderKind,
+    src: &str,
+) -> Result<(), GlesError> {
+    let program = match kind {
+        CustomShaderKind::WindowResize => compile_resize_program(renderer, src)?,
+        CustomShaderKind::WindowClose => compile_close_program(renderer, src)?,
+        CustomShaderKind::WindowOpen => compile_open_program(renderer, src)?,
+        CustomShaderKind::WorkspaceSwitch => compile_workspace_switch_program(renderer, src)?,
+        CustomShaderKind::PostProcess => compile_post_process_program(renderer, src)?,
+    };
+    program.destroy(renderer)
 }
 
 pub fn mat3_uniform(name: &str, mat: Mat3) -> Uniform {
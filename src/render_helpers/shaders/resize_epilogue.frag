@@ -4,6 +4,7 @@ void main() {
     vec3 size_curr_geo = vec3(niri_curr_geo_size, 1.0);
 
     vec4 color = resize_color(coords_curr_geo, size_curr_geo);
+    color = niri_adjust_color(color);
 
     if (niri_clip_to_geometry == 1.0) {
         if (coords_curr_geo.x < 0.0 || 1.0 < coords_curr_geo.x
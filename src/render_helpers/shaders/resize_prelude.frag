@@ -24,9 +24,20 @@ uniform float niri_clamped_progress;
 uniform vec4 niri_corner_radius;
 uniform float niri_clip_to_geometry;
 
+uniform float niri_dim;
+uniform float niri_saturation;
+uniform float niri_brightness;
+
 uniform float niri_alpha;
 uniform float niri_scale;
 
+vec4 niri_adjust_color(vec4 color) {
+    float luminance = dot(color.rgb, vec3(0.2126, 0.7152, 0.0722));
+    vec3 rgb = mix(vec3(luminance), color.rgb, niri_saturation);
+    rgb = rgb * (1.0 - niri_dim) + niri_brightness * color.a;
+    return vec4(clamp(rgb, 0.0, color.a), color.a);
+}
+
 float niri_rounding_alpha(vec2 coords, vec2 size) {
     vec2 center;
     float radius;
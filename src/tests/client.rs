@@ -4,13 +4,26 @@ use std::fmt;
 use std::fmt::Write as _;
 use std::os::unix::net::UnixStream;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use calloop::EventLoop;
 use calloop_wayland_source::WaylandSource;
 use single_pixel_buffer::v1::client::wp_single_pixel_buffer_manager_v1::WpSinglePixelBufferManagerV1;
+use smithay::reexports::wayland_protocols::ext::image_capture_source::v1::client::ext_image_capture_source_v1::ExtImageCaptureSourceV1;
+use smithay::reexports::wayland_protocols::ext::image_capture_source::v1::client::ext_output_image_capture_source_manager_v1::ExtOutputImageCaptureSourceManagerV1;
+use smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::client::ext_image_copy_capture_frame_v1::{
+    self, ExtImageCopyCaptureFrameV1, FailureReason,
+};
+use smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::client::ext_image_copy_capture_manager_v1::{
+    ExtImageCopyCaptureManagerV1, Options,
+};
+use smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::client::ext_image_copy_capture_session_v1::ExtImageCopyCaptureSessionV1;
 use smithay::reexports::wayland_protocols::wp::single_pixel_buffer;
+use smithay::reexports::wayland_protocols::wp::tearing_control::v1::client::wp_tearing_control_manager_v1::WpTearingControlManagerV1;
+use smithay::reexports::wayland_protocols::wp::tearing_control::v1::client::wp_tearing_control_v1::WpTearingControlV1;
+use smithay::reexports::wayland_protocols::xdg::dialog::v1::client::xdg_dialog_v1::XdgDialogV1;
+use smithay::reexports::wayland_protocols::xdg::dialog::v1::client::xdg_wm_dialog_v1::XdgWmDialogV1;
 use smithay::reexports::wayland_protocols::wp::viewporter::client::wp_viewport::WpViewport;
 use smithay::reexports::wayland_protocols::wp::viewporter::client::wp_viewporter::WpViewporter;
 use smithay::reexports::wayland_protocols::xdg::shell::client::xdg_surface::{self, XdgSurface};
@@ -23,6 +36,7 @@ use smithay::reexports::wayland_protocols_wlr::layer_shell::v1::client::zwlr_lay
     self, ZwlrLayerSurfaceV1,
 };
 use wayland_backend::client::Backend;
+use wayland_backend::protocol::ProtocolError;
 use wayland_client::globals::Global;
 use wayland_client::protocol::wl_buffer::{self, WlBuffer};
 use wayland_client::protocol::wl_callback::{self, WlCallback};
@@ -31,7 +45,7 @@ use wayland_client::protocol::wl_display::WlDisplay;
 use wayland_client::protocol::wl_output::{self, WlOutput};
 use wayland_client::protocol::wl_registry::{self, WlRegistry};
 use wayland_client::protocol::wl_surface::{self, WlSurface};
-use wayland_client::{Connection, Dispatch, Proxy as _, QueueHandle};
+use wayland_client::{Connection, Dispatch, Proxy as _, QueueHandle, WEnum};
 
 use crate::utils::id::IdCounter;
 
@@ -55,6 +69,10 @@ pub struct State {
     pub layer_shell: Option<ZwlrLayerShellV1>,
     pub spbm: Option<WpSinglePixelBufferManagerV1>,
     pub viewporter: Option<WpViewporter>,
+    pub image_copy_capture: Option<ExtImageCopyCaptureManagerV1>,
+    pub output_capture_source: Option<ExtOutputImageCaptureSourceManagerV1>,
+    pub xdg_wm_dialog: Option<XdgWmDialogV1>,
+    pub tearing_control: Option<WpTearingControlManagerV1>,
 
     pub windows: Vec<Window>,
     pub layers: Vec<LayerSurface>,
@@ -119,6 +137,12 @@ pub struct LayerConfigureProps {
     pub exclusive_edge: Option<zwlr_layer_surface_v1::Anchor>,
 }
 
+#[derive(Default)]
+pub struct CaptureFrameData {
+    pub ready: AtomicBool,
+    pub failed: Mutex<Option<WEnum<FailureReason>>>,
+}
+
 #[derive(Default)]
 pub struct SyncData {
     pub done: AtomicBool,
@@ -181,6 +205,10 @@ impl Client {
             layer_shell: None,
             spbm: None,
             viewporter: None,
+            image_copy_capture: None,
+            output_capture_source: None,
+            xdg_wm_dialog: None,
+            tearing_control: None,
             windows: Vec::new(),
             layers: Vec::new(),
         };
@@ -196,9 +224,15 @@ impl Client {
     }
 
     pub fn dispatch(&mut self) {
-        self.event_loop
-            .dispatch(Duration::ZERO, &mut self.state)
-            .unwrap();
+        let res = self.event_loop.dispatch(Duration::ZERO, &mut self.state);
+        // The connection breaks on protocol errors, which tests can check for.
+        if self.connection.protocol_error().is_none() {
+            res.unwrap();
+        }
+    }
+
+    pub fn protocol_error(&self) -> Option<ProtocolError> {
+        self.connection.protocol_error()
     }
 
     pub fn send_sync(&self) -> Arc<SyncData> {
@@ -229,6 +263,47 @@ impl Client {
         self.state.layer(surface)
     }
 
+    pub fn create_output_capture_session(
+        &mut self,
+        output: &WlOutput,
+    ) -> ExtImageCopyCaptureSessionV1 {
+        let state = &self.state;
+        let manager = state.output_capture_source.as_ref().unwrap();
+        let source = manager.create_source(output, &self.qh, ());
+        let session = state.image_copy_capture.as_ref().unwrap().create_session(
+            &source,
+            Options::empty(),
+            &self.qh,
+            (),
+        );
+        source.destroy();
+        session
+    }
+
+    pub fn create_capture_frame(
+        &mut self,
+        session: &ExtImageCopyCaptureSessionV1,
+    ) -> ExtImageCopyCaptureFrameV1 {
+        session.create_frame(&self.qh, Arc::new(CaptureFrameData::default()))
+    }
+
+    pub fn create_dialog(&mut self, surface: &WlSurface) -> XdgDialogV1 {
+        let manager = self.state.xdg_wm_dialog.clone().unwrap();
+        let window = self.state.window(surface);
+        manager.get_xdg_dialog(&window.xdg_toplevel, &self.qh, ())
+    }
+
+    pub fn create_tearing_control(&mut self, surface: &WlSurface) -> WpTearingControlV1 {
+        let manager = self.state.tearing_control.clone().unwrap();
+        manager.get_tearing_control(surface, &self.qh, ())
+    }
+
+    /// Creates a buffer that isn't valid for capturing into.
+    pub fn create_single_pixel_buffer(&mut self) -> WlBuffer {
+        let spbm = self.state.spbm.as_ref().unwrap();
+        spbm.create_u32_rgba_buffer(0, 0, 0, 0, &self.qh, ())
+    }
+
     pub fn output(&mut self, name: &str) -> WlOutput {
         self.state
             .outputs
@@ -357,6 +432,10 @@ impl Window {
         self.xdg_toplevel.set_title(title.to_owned());
     }
 
+    pub fn set_app_id(&self, app_id: &str) {
+        self.xdg_toplevel.set_app_id(app_id.to_owned());
+    }
+
     pub fn recent_configures(&mut self) -> impl Iterator<Item = &Configure> {
         let start = self.configures_looked_at;
         self.configures_looked_at = self.configures_received.len();
@@ -498,6 +577,21 @@ impl Dispatch<WlRegistry, ()> for State {
                 } else if interface == WpViewporter::interface().name {
                     let version = min(version, WpViewporter::interface().version);
                     state.viewporter = Some(registry.bind(name, version, qh, ()));
+                } else if interface == ExtImageCopyCaptureManagerV1::interface().name {
+                    let version = min(version, ExtImageCopyCaptureManagerV1::interface().version);
+                    state.image_copy_capture = Some(registry.bind(name, version, qh, ()));
+                } else if interface == ExtOutputImageCaptureSourceManagerV1::interface().name {
+                    let version = min(
+                        version,
+                        ExtOutputImageCaptureSourceManagerV1::interface().version,
+                    );
+                    state.output_capture_source = Some(registry.bind(name, version, qh, ()));
+                } else if interface == XdgWmDialogV1::interface().name {
+                    let version = min(version, XdgWmDialogV1::interface().version);
+                    state.xdg_wm_dialog = Some(registry.bind(name, version, qh, ()));
+                } else if interface == WpTearingControlManagerV1::interface().name {
+                    let version = min(version, WpTearingControlManagerV1::interface().version);
+                    state.tearing_control = Some(registry.bind(name, version, qh, ()));
                 } else if interface == WlOutput::interface().name {
                     let version = min(version, WlOutput::interface().version);
                     let output = registry.bind(name, version, qh, ());
@@ -755,3 +849,127 @@ impl Dispatch<WpViewport, ()> for State {
         unreachable!()
     }
 }
+
+impl Dispatch<XdgWmDialogV1, ()> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &XdgWmDialogV1,
+        _event: <XdgWmDialogV1 as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        unreachable!()
+    }
+}
+
+impl Dispatch<XdgDialogV1, ()> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &XdgDialogV1,
+        _event: <XdgDialogV1 as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        unreachable!()
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureManagerV1, ()> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ExtImageCopyCaptureManagerV1,
+        _event: <ExtImageCopyCaptureManagerV1 as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        unreachable!()
+    }
+}
+
+impl Dispatch<ExtOutputImageCaptureSourceManagerV1, ()> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ExtOutputImageCaptureSourceManagerV1,
+        _event: <ExtOutputImageCaptureSourceManagerV1 as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        unreachable!()
+    }
+}
+
+impl Dispatch<ExtImageCaptureSourceV1, ()> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ExtImageCaptureSourceV1,
+        _event: <ExtImageCaptureSourceV1 as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        unreachable!()
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureSessionV1, ()> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ExtImageCopyCaptureSessionV1,
+        _event: <ExtImageCopyCaptureSessionV1 as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ExtImageCopyCaptureFrameV1, Arc<CaptureFrameData>> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ExtImageCopyCaptureFrameV1,
+        event: <ExtImageCopyCaptureFrameV1 as wayland_client::Proxy>::Event,
+        data: &Arc<CaptureFrameData>,
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        match event {
+            ext_image_copy_capture_frame_v1::Event::Ready => {
+                data.ready.store(true, Ordering::Relaxed)
+            }
+            ext_image_copy_capture_frame_v1::Event::Failed { reason } => {
+                *data.failed.lock().unwrap() = Some(reason);
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Dispatch<WpTearingControlManagerV1, ()> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WpTearingControlManagerV1,
+        _event: <WpTearingControlManagerV1 as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        unreachable!()
+    }
+}
+
+impl Dispatch<WpTearingControlV1, ()> for State {
+    fn event(
+        _state: &mut Self,
+        _proxy: &WpTearingControlV1,
+        _event: <WpTearingControlV1 as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        unreachable!()
+    }
+}
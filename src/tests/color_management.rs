@@ -0,0 +1,47 @@
+use niri_config::Config;
+
+use super::*;
+use crate::render_helpers::color_transform::ColorTransformRenderElement;
+use crate::utils::color::{output_color_description, ColorDescription};
+
+#[test]
+fn outputs_default_to_srgb() {
+    let mut f = Fixture::new();
+    f.add_output(1, (1920, 1080));
+    let output = f.niri_output(1);
+
+    assert_eq!(*output_color_description(&output), ColorDescription::srgb());
+}
+
+#[test]
+fn missing_icc_profile_falls_back_to_srgb() {
+    let config = r##"
+output "headless-1" {
+    icc-profile "/nonexistent/profile.icc"
+}
+"##;
+    let config = Config::parse("test.kdl", config).unwrap();
+    let mut f = Fixture::with_config(config);
+    f.add_output(1, (1920, 1080));
+    let output = f.niri_output(1);
+
+    assert_eq!(*output_color_description(&output), ColorDescription::srgb());
+}
+
+#[test]
+#[ignore = "needs an EGL device"]
+fn color_transform_shader_compiles() {
+    let mut f = Fixture::new();
+    f.add_output(1, (1920, 1080));
+    f.add_renderer();
+
+    let state = f.niri_state();
+    let compiled = state
+        .backend
+        .with_primary_renderer(|renderer| ColorTransformRenderElement::shader(renderer).is_some())
+        .unwrap();
+    assert!(compiled);
+
+    // Render a frame with the renderer to make sure nothing blows up.
+    f.dispatch();
+}
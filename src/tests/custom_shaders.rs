@@ -0,0 +1,76 @@
+use niri_config::Config;
+
+use super::*;
+use crate::backend::headless::create_software_renderer;
+use crate::render_helpers::shaders::{self, CustomShaderKind, ProgramType, Shaders};
+
+const WORKSPACE_SWITCH: &str = r"
+vec4 workspace_switch_color(vec3 coords_geo, vec3 size_geo) {
+    vec3 coords_prev = niri_geo_to_tex_prev * coords_geo;
+    vec3 coords_next = niri_geo_to_tex_next * coords_geo;
+    vec4 prev = texture2D(niri_tex_prev, coords_prev.st);
+    vec4 next = texture2D(niri_tex_next, coords_next.st);
+    return mix(prev, next, niri_progress);
+}
+";
+
+const POST_PROCESS: &str = r"
+vec4 post_process_color(vec3 coords_geo, vec3 size_geo) {
+    vec3 coords_tex = niri_geo_to_tex * coords_geo;
+    vec4 color = texture2D(niri_tex, coords_tex.st);
+    return vec4(color.rgb * 0.5, color.a);
+}
+";
+
+#[test]
+#[ignore = "needs an EGL device"]
+fn validate_custom_shaders() {
+    let mut renderer = create_software_renderer().expect("error creating renderer");
+
+    let kind = CustomShaderKind::WorkspaceSwitch;
+    shaders::compile_custom_program(&mut renderer, kind, WORKSPACE_SWITCH).unwrap();
+    shaders::compile_custom_program(&mut renderer, kind, POST_PROCESS).unwrap_err();
+
+    let kind = CustomShaderKind::PostProcess;
+    shaders::compile_custom_program(&mut renderer, kind, POST_PROCESS).unwrap();
+    shaders::compile_custom_program(&mut renderer, kind, "vec4 broken(").unwrap_err();
+}
+
+#[test]
+#[ignore = "needs an EGL device"]
+fn post_process_shader_from_config() {
+    let config = format!(
+        r##"
+output "headless-1" {{
+    custom-shader r"{POST_PROCESS}"
+}}
+
+animations {{
+    workspace-switch {{
+        custom-shader "vec4 broken("
+    }}
+}}
+"##
+    );
+    let config = Config::parse("test.kdl", &config).unwrap();
+    let mut f = Fixture::with_config(config);
+    f.add_output(1, (1920, 1080));
+    f.add_renderer();
+
+    let state = f.niri_state();
+    let (post_process, workspace_switch) = state
+        .backend
+        .with_primary_renderer(|renderer| {
+            let shaders = Shaders::get(renderer);
+            (
+                shaders.post_process_program("headless-1"),
+                shaders.program(ProgramType::WorkspaceSwitch),
+            )
+        })
+        .unwrap();
+    assert!(post_process.is_some());
+    assert!(workspace_switch.is_none());
+
+    // Render a frame through the post-processing shader to make sure nothing blows up.
+    f.dispatch();
+}
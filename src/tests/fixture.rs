@@ -88,6 +88,20 @@ impl Fixture {
         state.backend.headless().add_output(niri, n, size);
     }
 
+    /// Adds a renderer to the headless backend.
+    ///
+    /// Panics when there's no EGL device to render with. Tests using this are marked `#[ignore]`
+    /// and run with `cargo test -- --ignored` on machines that can render.
+    pub fn add_renderer(&mut self) {
+        let state = self.niri_state();
+        let niri = &mut state.niri;
+        state
+            .backend
+            .headless()
+            .add_renderer(niri)
+            .expect("error adding renderer");
+    }
+
     pub fn add_client(&mut self) -> ClientId {
         let (sock1, sock2) = UnixStream::pair().unwrap();
         self.niri().insert_client(NewClient {
@@ -0,0 +1,44 @@
+use std::time::{Duration, Instant};
+
+use smithay::output::Output;
+
+use super::*;
+use crate::niri::RedrawState;
+
+fn waiting_for_vblank(f: &mut Fixture, output: &Output) -> bool {
+    matches!(
+        f.niri().output_state[output].redraw_state,
+        RedrawState::WaitingForVBlank { .. }
+    )
+}
+
+#[test]
+#[ignore = "needs an EGL device"]
+fn render_and_vblank() {
+    let mut f = Fixture::new();
+    f.add_output(1, (1920, 1080));
+    f.add_renderer();
+
+    let output = f.niri_output(1);
+
+    // Render a frame.
+    f.niri().queue_redraw(&output);
+    f.dispatch();
+    assert!(waiting_for_vblank(&mut f, &output));
+
+    // Wait for the emulated VBlank, and for any startup animations to finish.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !matches!(
+        f.niri().output_state[&output].redraw_state,
+        RedrawState::Idle
+    ) {
+        assert!(Instant::now() < deadline, "output did not become idle");
+        std::thread::sleep(Duration::from_millis(1));
+        f.dispatch();
+    }
+
+    // Nothing changed, so the next redraw has no damage and doesn't wait for a VBlank.
+    f.niri().queue_redraw(&output);
+    f.dispatch();
+    assert!(!waiting_for_vblank(&mut f, &output));
+}
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use smithay::reexports::wayland_protocols::ext::image_copy_capture::v1::client::ext_image_copy_capture_frame_v1::{
+    Error, ExtImageCopyCaptureFrameV1, FailureReason,
+};
+use wayland_backend::protocol::ProtocolError;
+use wayland_client::{Proxy as _, WEnum};
+
+use super::client::{CaptureFrameData, ClientId};
+use super::*;
+
+fn set_up() -> (Fixture, ClientId, ExtImageCopyCaptureFrameV1) {
+    let mut f = Fixture::new();
+    f.add_output(1, (1920, 1080));
+    let id = f.add_client();
+
+    let client = f.client(id);
+    let output = client.output("headless-1");
+    let session = client.create_output_capture_session(&output);
+    let frame = client.create_capture_frame(&session);
+    f.roundtrip(id);
+
+    (f, id, frame)
+}
+
+fn failure_reason(frame: &ExtImageCopyCaptureFrameV1) -> Option<WEnum<FailureReason>> {
+    let data = frame.data::<Arc<CaptureFrameData>>().unwrap();
+    *data.failed.lock().unwrap()
+}
+
+fn protocol_error(f: &mut Fixture, id: ClientId) -> ProtocolError {
+    let client = f.client(id);
+    let _ = client.connection.flush();
+    for _ in 0..100 {
+        f.dispatch();
+        if let Some(err) = f.client(id).protocol_error() {
+            return err;
+        }
+    }
+    panic!("expected a protocol error");
+}
+
+#[test]
+fn capture_into_invalid_buffer_fails() {
+    let (mut f, id, frame) = set_up();
+
+    let buffer = f.client(id).create_single_pixel_buffer();
+    frame.attach_buffer(&buffer);
+    frame.capture();
+    f.roundtrip(id);
+
+    assert_eq!(
+        failure_reason(&frame),
+        Some(WEnum::Value(FailureReason::BufferConstraints))
+    );
+}
+
+#[test]
+fn capture_twice_is_an_error() {
+    let (mut f, id, frame) = set_up();
+
+    let buffer = f.client(id).create_single_pixel_buffer();
+    frame.attach_buffer(&buffer);
+    frame.capture();
+    f.roundtrip(id);
+    assert!(failure_reason(&frame).is_some());
+
+    frame.capture();
+    let err = protocol_error(&mut f, id);
+    assert_eq!(err.code, Error::AlreadyCaptured as u32);
+}
+
+#[test]
+fn attach_after_capture_is_an_error() {
+    let (mut f, id, frame) = set_up();
+
+    let buffer = f.client(id).create_single_pixel_buffer();
+    frame.attach_buffer(&buffer);
+    frame.capture();
+    f.roundtrip(id);
+
+    frame.attach_buffer(&buffer);
+    let err = protocol_error(&mut f, id);
+    assert_eq!(err.code, Error::AlreadyCaptured as u32);
+}
+
+#[test]
+fn capture_without_buffer_is_an_error() {
+    let (mut f, id, frame) = set_up();
+
+    frame.capture();
+    let err = protocol_error(&mut f, id);
+    assert_eq!(err.code, Error::NoBuffer as u32);
+}
+
+#[test]
+fn invalid_buffer_damage_is_an_error() {
+    let (mut f, id, frame) = set_up();
+
+    frame.damage_buffer(0, 0, 0, 10);
+    let err = protocol_error(&mut f, id);
+    assert_eq!(err.code, Error::InvalidBufferDamage as u32);
+}
@@ -0,0 +1,66 @@
+use niri_config::Config;
+use smithay::utils::Point;
+
+use super::*;
+use crate::layout::tile::TileRenderElement;
+use crate::render_helpers::RenderTarget;
+
+#[test]
+#[ignore = "needs an EGL device"]
+fn inactive_tile_is_color_adjusted() {
+    let config = r##"
+layout {
+    inactive-dim 0.5
+}
+
+animations {
+    off
+}
+"##;
+    let config = Config::parse("test.kdl", config).unwrap();
+    let mut f = Fixture::with_config(config);
+    f.add_output(1, (1920, 1080));
+    let output = f.niri_output(1);
+    f.add_renderer();
+
+    let id = f.add_client();
+    let mut surfaces = Vec::new();
+    for _ in 0..2 {
+        let window = f.client(id).create_window();
+        surfaces.push(window.surface.clone());
+        window.commit();
+    }
+    f.roundtrip(id);
+
+    for surface in &surfaces {
+        let window = f.client(id).window(surface);
+        window.attach_new_buffer();
+        window.set_size(100, 100);
+        window.ack_last_and_commit();
+    }
+    f.double_roundtrip(id);
+
+    // Render a frame to update the tile states.
+    f.niri().queue_redraw(&output);
+    f.dispatch();
+
+    let state = f.niri_state();
+    let niri = &state.niri;
+    let focus = niri.layout.focus().unwrap().window.clone();
+    let ws = niri.layout.active_workspace().unwrap();
+
+    let mut seen = 0;
+    for tile in ws.tiles() {
+        let is_active = tile.window().window == focus;
+        let is_adjusted = state
+            .backend
+            .with_primary_renderer(|renderer| {
+                tile.render(renderer, Point::from((0., 0.)), false, RenderTarget::Output)
+                    .any(|elem| matches!(elem, TileRenderElement::ColorAdjust(_)))
+            })
+            .unwrap();
+        assert_eq!(is_adjusted, !is_active);
+        seen += 1;
+    }
+    assert_eq!(seen, 2);
+}
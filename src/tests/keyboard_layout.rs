@@ -0,0 +1,143 @@
+use client::ClientId;
+use niri_config::{Action, Config};
+use smithay::input::keyboard::Layout as KeyboardLayout;
+
+use super::*;
+
+fn set_up(track_layout: &str) -> Fixture {
+    let config = format!(
+        r##"
+input {{
+    keyboard {{
+        xkb {{
+            // Variants of us are always available, unlike other layouts.
+            layout "us,us"
+            variant ",dvorak"
+        }}
+        track-layout "{track_layout}"
+    }}
+}}
+
+window-rule {{
+    match app-id="^term$"
+    default-keyboard-layout "us"
+}}
+"##
+    );
+    let config = Config::parse("test.kdl", &config).unwrap();
+    let mut f = Fixture::with_config(config);
+    f.add_output(1, (1920, 1080));
+
+    let state = f.niri_state();
+    let keyboard = state.niri.seat.get_keyboard().unwrap();
+    let num_layouts = keyboard.with_xkb_state(state, |context| {
+        context.xkb().lock().unwrap().layouts().count()
+    });
+    assert_eq!(
+        num_layouts, 2,
+        "the xkb keymap fell back to the default one"
+    );
+
+    f
+}
+
+fn active_layout(f: &mut Fixture) -> KeyboardLayout {
+    let state = f.niri_state();
+    let keyboard = state.niri.seat.get_keyboard().unwrap();
+    keyboard.with_xkb_state(state, |context| {
+        context.xkb().lock().unwrap().active_layout()
+    })
+}
+
+fn set_active_layout(f: &mut Fixture, layout: KeyboardLayout) {
+    let state = f.niri_state();
+    let keyboard = state.niri.seat.get_keyboard().unwrap();
+    keyboard.with_xkb_state(state, |mut context| context.set_layout(layout));
+}
+
+fn map_window(f: &mut Fixture, id: ClientId, app_id: &str) {
+    let window = f.client(id).create_window();
+    let surface = window.surface.clone();
+    window.set_app_id(app_id);
+    window.commit();
+    f.roundtrip(id);
+
+    let window = f.client(id).window(&surface);
+    window.attach_new_buffer();
+    window.set_size(100, 100);
+    window.ack_last_and_commit();
+    f.double_roundtrip(id);
+}
+
+#[test]
+fn default_layout_rule_overrides_app_layout() {
+    let mut f = set_up("app");
+    let id = f.add_client();
+
+    map_window(&mut f, id, "term");
+    assert_eq!(active_layout(&mut f), KeyboardLayout(0));
+
+    // The app now remembers the second layout.
+    set_active_layout(&mut f, KeyboardLayout(1));
+
+    // A new window of the app still starts with the rule's layout.
+    map_window(&mut f, id, "term");
+    assert_eq!(active_layout(&mut f), KeyboardLayout(0));
+
+    // Apps without the rule start with their remembered layout.
+    map_window(&mut f, id, "editor");
+    set_active_layout(&mut f, KeyboardLayout(1));
+    map_window(&mut f, id, "term");
+    assert_eq!(active_layout(&mut f), KeyboardLayout(0));
+    map_window(&mut f, id, "editor");
+    assert_eq!(active_layout(&mut f), KeyboardLayout(1));
+}
+
+#[test]
+fn default_layout_rule_ignored_with_global_layout() {
+    let mut f = set_up("global");
+    let id = f.add_client();
+
+    set_active_layout(&mut f, KeyboardLayout(1));
+
+    // Focusing a new window with the rule keeps the global layout.
+    map_window(&mut f, id, "term");
+    assert_eq!(active_layout(&mut f), KeyboardLayout(1));
+
+    map_window(&mut f, id, "editor");
+    assert_eq!(active_layout(&mut f), KeyboardLayout(1));
+}
+
+#[test]
+fn switch_layout_for_unfocused_window_by_name() {
+    let mut f = set_up("window");
+    let id = f.add_client();
+
+    map_window(&mut f, id, "editor");
+    let (_, mapped) = f.niri().layout.windows().next().unwrap();
+    let window_id = mapped.id().get();
+
+    map_window(&mut f, id, "term");
+    assert_eq!(active_layout(&mut f), KeyboardLayout(0));
+
+    let action = Action::SwitchLayoutForWindowById {
+        id: window_id,
+        layout: String::from("English (Dvorak)"),
+    };
+    f.niri_state().do_action(action, false);
+    // The focused window keeps its layout.
+    assert_eq!(active_layout(&mut f), KeyboardLayout(0));
+
+    f.niri_state()
+        .do_action(Action::FocusWindow(window_id), false);
+    f.double_roundtrip(id);
+    assert_eq!(active_layout(&mut f), KeyboardLayout(1));
+
+    // Names from the layout setting work too.
+    let action = Action::SwitchLayoutForWindowById {
+        id: window_id,
+        layout: String::from("us"),
+    };
+    f.niri_state().do_action(action, false);
+    assert_eq!(active_layout(&mut f), KeyboardLayout(0));
+}
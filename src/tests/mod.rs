@@ -4,8 +4,17 @@ mod client;
 mod fixture;
 mod server;
 
+mod color_management;
+mod custom_shaders;
 mod floating;
 mod fullscreen;
+mod headless;
+mod image_copy_capture;
+mod inactive_dim;
+mod keyboard_layout;
 mod layer_shell;
+mod modal_dialogs;
+mod output_power;
+mod tearing;
 mod transactions;
 mod window_opening;
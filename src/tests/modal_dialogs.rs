@@ -0,0 +1,77 @@
+use smithay::desktop::Window;
+
+use super::*;
+use crate::niri::Niri;
+use crate::window::Mapped;
+
+fn mapped<'a>(niri: &'a Niri, window: &Window) -> &'a Mapped {
+    niri.layout
+        .windows()
+        .map(|(_, mapped)| mapped)
+        .find(|mapped| mapped.window == *window)
+        .unwrap()
+}
+
+fn is_focused(niri: &Niri, window: &Window) -> bool {
+    niri.layout.focus().unwrap().window == *window
+}
+
+#[test]
+fn modal_dialog_steals_focus_from_parent() {
+    let mut f = Fixture::new();
+    f.add_output(1, (1920, 1080));
+    let id = f.add_client();
+
+    // Map the parent.
+    let window = f.client(id).create_window();
+    let parent_surface = window.surface.clone();
+    let parent_toplevel = window.xdg_toplevel.clone();
+    window.commit();
+    f.roundtrip(id);
+
+    let window = f.client(id).window(&parent_surface);
+    window.attach_new_buffer();
+    window.set_size(100, 100);
+    window.ack_last_and_commit();
+    f.double_roundtrip(id);
+
+    let parent = f.niri().layout.focus().unwrap().window.clone();
+
+    // Map a modal dialog for it.
+    let window = f.client(id).create_window();
+    let child_surface = window.surface.clone();
+    window.set_parent(Some(&parent_toplevel));
+    let dialog = f.client(id).create_dialog(&child_surface);
+    dialog.set_modal();
+    f.client(id).window(&child_surface).commit();
+    f.roundtrip(id);
+
+    let window = f.client(id).window(&child_surface);
+    window.attach_new_buffer();
+    window.set_size(50, 50);
+    window.ack_last_and_commit();
+    f.double_roundtrip(id);
+
+    let niri = f.niri();
+    let child = niri.layout.focus().unwrap().window.clone();
+    assert_ne!(child, parent);
+    assert!(mapped(niri, &child).is_modal());
+    assert!(mapped(niri, &parent).is_blocked_by_modal());
+    assert!(!mapped(niri, &child).is_blocked_by_modal());
+
+    // Focusing the parent focuses the dialog instead.
+    niri.layout.activate_window(&parent);
+    assert!(is_focused(f.niri(), &parent));
+    f.double_roundtrip(id);
+    assert!(is_focused(f.niri(), &child));
+
+    // Once the dialog is no longer modal, the parent can keep the focus.
+    dialog.unset_modal();
+    f.double_roundtrip(id);
+
+    let niri = f.niri();
+    assert!(!mapped(niri, &parent).is_blocked_by_modal());
+    niri.layout.activate_window(&parent);
+    f.double_roundtrip(id);
+    assert!(is_focused(f.niri(), &parent));
+}
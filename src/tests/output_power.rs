@@ -0,0 +1,57 @@
+use niri_ipc::{OutputAction, OutputPower};
+
+use super::*;
+
+fn is_powered_off_in_ipc(f: &mut Fixture, name: &str) -> bool {
+    let ipc_outputs = f.niri_state().backend.ipc_outputs();
+    let ipc_outputs = ipc_outputs.lock().unwrap();
+    let ipc_output = ipc_outputs.values().find(|o| o.name == name).unwrap();
+    ipc_output.powered_off
+}
+
+fn set_power(f: &mut Fixture, name: &str, power: OutputPower) {
+    f.niri_state()
+        .apply_transient_output_config(name, OutputAction::Power { power });
+}
+
+#[test]
+fn power_off_single_output() {
+    let mut f = Fixture::new();
+    f.add_output(1, (1920, 1080));
+    f.add_output(2, (1280, 720));
+    let output1 = f.niri_output(1);
+    let output2 = f.niri_output(2);
+
+    set_power(&mut f, "headless-1", OutputPower::Off);
+
+    let state = f.niri_state();
+    assert!(!state.backend.is_output_powered(&output1));
+    assert!(state.backend.is_output_powered(&output2));
+    assert!(is_powered_off_in_ipc(&mut f, "headless-1"));
+    assert!(!is_powered_off_in_ipc(&mut f, "headless-2"));
+
+    // Unlike turning the output off, powering it off keeps it in the layout.
+    let niri = f.niri();
+    assert_eq!(niri.global_space.outputs().count(), 2);
+    assert!(niri.layout.monitor_for_output(&output1).is_some());
+
+    set_power(&mut f, "headless-1", OutputPower::On);
+
+    assert!(f.niri_state().backend.is_output_powered(&output1));
+    assert!(!is_powered_off_in_ipc(&mut f, "headless-1"));
+}
+
+#[test]
+fn power_off_is_independent_of_monitors_active() {
+    let mut f = Fixture::new();
+    f.add_output(1, (1920, 1080));
+    let output = f.niri_output(1);
+
+    set_power(&mut f, "headless-1", OutputPower::Off);
+
+    // Waking up all monitors doesn't power on the output that was powered off on its own.
+    let state = f.niri_state();
+    state.niri.deactivate_monitors(&mut state.backend);
+    state.niri.activate_monitors(&mut state.backend);
+    assert!(!state.backend.is_output_powered(&output));
+}
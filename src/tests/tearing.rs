@@ -0,0 +1,60 @@
+use niri_config::Config;
+use smithay::reexports::wayland_protocols::wp::tearing_control::v1::client::wp_tearing_control_v1::PresentationHint;
+
+use super::*;
+
+#[test]
+fn tearing_surface_needs_rule_fullscreen_and_async_hint() {
+    let config = r##"
+window-rule {
+    allow-tearing true
+}
+"##;
+    let config = Config::parse("test.kdl", config).unwrap();
+    let mut f = Fixture::with_config(config);
+    f.add_output(1, (1920, 1080));
+    let output = f.niri_output(1);
+
+    let id = f.add_client();
+    let window = f.client(id).create_window();
+    let surface = window.surface.clone();
+    window.commit();
+    f.roundtrip(id);
+
+    let window = f.client(id).window(&surface);
+    window.attach_new_buffer();
+    window.set_size(100, 100);
+    window.ack_last_and_commit();
+    f.double_roundtrip(id);
+
+    // Ask for async presentation.
+    let tearing_control = f.client(id).create_tearing_control(&surface);
+    tearing_control.set_presentation_hint(PresentationHint::Async);
+    f.client(id).window(&surface).commit();
+    f.double_roundtrip(id);
+
+    // Not fullscreen yet.
+    assert_eq!(f.niri().tearing_surface(&output), None);
+
+    let niri = f.niri();
+    let window_id = niri.layout.windows().next().unwrap().1.window.clone();
+    niri.layout.set_fullscreen(&window_id, true);
+    f.double_roundtrip(id);
+
+    let window = f.client(id).window(&surface);
+    window.set_size(1920, 1080);
+    window.ack_last_and_commit();
+    f.double_roundtrip(id);
+
+    let niri = f.niri();
+    let mapped = niri.layout.windows().next().unwrap().1;
+    let expected = mapped.toplevel().wl_surface().clone();
+    assert_eq!(niri.tearing_surface(&output), Some(expected));
+
+    // Going back to vsync stops tearing.
+    tearing_control.set_presentation_hint(PresentationHint::Vsync);
+    f.client(id).window(&surface).commit();
+    f.double_roundtrip(id);
+
+    assert_eq!(f.niri().tearing_surface(&output), None);
+}
@@ -7,6 +7,7 @@ use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use super::*;
 use crate::layout::LayoutElement;
 use crate::utils::with_toplevel_role;
+use crate::window::{MatchContext, ResolvedWindowRules, WindowRef};
 
 #[test]
 fn simple_no_workspaces() {
@@ -651,3 +652,89 @@ post-map configures:
     let _guard = settings.bind_to_scope();
     assert_snapshot!(snapshot);
 }
+
+#[test]
+fn window_rule_matchers() {
+    let pid = std::process::id();
+    let cases = [
+        (format!("pid={pid}"), true),
+        (String::from("pid=1"), false),
+        (String::from("is-xwayland=false"), true),
+        (String::from("is-xwayland=true"), false),
+        (String::from("has-parent=false"), true),
+        (String::from("has-parent=true"), false),
+        (String::from("is-fullscreen=false"), true),
+        (String::from("is-fullscreen=true"), false),
+        (String::from("on-workspace=\"ws-1\""), true),
+        (String::from("on-workspace=\"WS-1\""), true),
+        (String::from("on-workspace=\"ws-2\""), false),
+        (String::from("on-output=\"headless-1\""), true),
+        (String::from("on-output=\"headless-2\""), false),
+    ];
+
+    cases.into_par_iter().for_each(|(matcher, expected)| {
+        let mut f = map_window_with_matcher(&matcher);
+        let mapped = f.niri().layout.windows().next().unwrap().1;
+        let matched = mapped.rules().opacity.is_some();
+        assert_eq!(matched, expected, "match {matcher}");
+    });
+}
+
+#[test]
+fn is_xwayland_matches_satellite_pid() {
+    let mut f = map_window_with_matcher("is-xwayland=true");
+
+    let niri = f.niri();
+    let config = niri.config.borrow();
+    let mapped = niri.layout.windows().next().unwrap().1;
+    assert_eq!(mapped.rules().opacity, None);
+
+    // Pretend that our test client is xwayland-satellite.
+    let ctx = MatchContext {
+        is_at_startup: false,
+        satellite_pid: Some(std::process::id() as i32),
+    };
+    let resolved =
+        ResolvedWindowRules::compute(&config.window_rules, WindowRef::Mapped(mapped), ctx);
+    assert_eq!(resolved.opacity, Some(0.5));
+}
+
+fn map_window_with_matcher(matcher: &str) -> Fixture {
+    let config = format!(
+        r##"
+workspace "ws-1" {{
+    open-on-output "headless-1"
+}}
+
+workspace "ws-2" {{
+    open-on-output "headless-2"
+}}
+
+window-rule {{
+    open-on-workspace "ws-1"
+}}
+
+window-rule {{
+    match {matcher}
+    opacity 0.5
+}}"##
+    );
+    let config = Config::parse("config.kdl", &config).unwrap();
+
+    let mut f = Fixture::with_config(config);
+    f.add_output(1, (1280, 720));
+    f.add_output(2, (1920, 1080));
+
+    let id = f.add_client();
+    let window = f.client(id).create_window();
+    let surface = window.surface.clone();
+    window.commit();
+    f.roundtrip(id);
+
+    let window = f.client(id).window(&surface);
+    window.attach_new_buffer();
+    window.ack_last_and_commit();
+    f.double_roundtrip(id);
+
+    f
+}
@@ -1,6 +1,6 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::time::Duration;
 
@@ -23,6 +23,9 @@ use crate::utils::{output_size, to_physical_precise_round};
 const TEXT: &str = "Failed to parse the config file. \
                     Please run <span face='monospace' bgcolor='#000000'>niri validate</span> \
                     to see the errors.";
+const SHADER_ERROR_TEXT: &str = "Failed to compile a custom shader. \
+                                 Please run <span face='monospace' bgcolor='#000000'>\
+                                 niri msg validate-shader</span> to see the errors.";
 const PADDING: i32 = 8;
 const FONT: &str = "sans 14px";
 const BORDER: i32 = 4;
@@ -30,15 +33,20 @@ const BORDER: i32 = 4;
 pub struct ConfigErrorNotification {
     state: State,
     buffers: RefCell<HashMap<NotNan<f64>, Option<TextureBuffer<GlesTexture>>>>,
-
-    // If set, this is a "Created config at {path}" notification. If unset, this is a config error
-    // notification.
-    created_path: Option<PathBuf>,
+    message: Message,
 
     clock: Clock,
     config: Rc<RefCell<Config>>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+enum Message {
+    ConfigError,
+    ShaderError,
+    /// "Created config at {path}" notification.
+    Created(PathBuf),
+}
+
 enum State {
     Hidden,
     Showing(Animation),
@@ -51,7 +59,7 @@ impl ConfigErrorNotification {
         Self {
             state: State::Hidden,
             buffers: RefCell::new(HashMap::new()),
-            created_path: None,
+            message: Message::ConfigError,
             clock,
             config,
         }
@@ -68,21 +76,27 @@ impl ConfigErrorNotification {
         )
     }
 
-    pub fn show_created(&mut self, created_path: PathBuf) {
-        let created_path = Some(created_path);
-        if self.created_path != created_path {
-            self.created_path = created_path;
+    fn set_message(&mut self, message: Message) {
+        if self.message != message {
+            self.message = message;
             self.buffers.borrow_mut().clear();
         }
+    }
 
+    pub fn show_created(&mut self, created_path: PathBuf) {
+        self.set_message(Message::Created(created_path));
         self.state = State::Showing(self.animation(0., 1.));
     }
 
     pub fn show(&mut self) {
-        if self.created_path.is_some() {
-            self.created_path = None;
-            self.buffers.borrow_mut().clear();
-        }
+        self.set_message(Message::ConfigError);
+
+        // Show from scratch even if already showing to bring attention.
+        self.state = State::Showing(self.animation(0., 1.));
+    }
+
+    pub fn show_shader_error(&mut self) {
+        self.set_message(Message::ShaderError);
 
         // Show from scratch even if already showing to bring attention.
         self.state = State::Showing(self.animation(0., 1.));
@@ -101,7 +115,7 @@ impl ConfigErrorNotification {
             State::Hidden => (),
             State::Showing(anim) => {
                 if anim.is_done() {
-                    let duration = if self.created_path.is_some() {
+                    let duration = if matches!(self.message, Message::Created(_)) {
                         // Make this quite a bit longer because it comes with a monitor modeset
                         // (can take a while) and an important hotkeys popup diverting the
                         // attention.
@@ -140,12 +154,12 @@ impl ConfigErrorNotification {
 
         let scale = output.current_scale().fractional_scale();
         let output_size = output_size(output);
-        let path = self.created_path.as_deref();
+        let message = &self.message;
 
         let mut buffers = self.buffers.borrow_mut();
         let buffer = buffers
             .entry(NotNan::new(scale).unwrap())
-            .or_insert_with(move || render(renderer.as_gles_renderer(), scale, path).ok());
+            .or_insert_with(move || render(renderer.as_gles_renderer(), scale, message).ok());
         let buffer = buffer.clone()?;
 
         let size = buffer.logical_size();
@@ -176,7 +190,7 @@ impl ConfigErrorNotification {
 fn render(
     renderer: &mut GlesRenderer,
     scale: f64,
-    created_path: Option<&Path>,
+    message: &Message,
 ) -> anyhow::Result<TextureBuffer<GlesTexture>> {
     let _span = tracy_client::span!("config_error_notification::render");
 
@@ -184,13 +198,17 @@ fn render(
 
     let mut text = String::from(TEXT);
     let mut border_color = (1., 0.3, 0.3);
-    if let Some(path) = created_path {
-        text = format!(
-            "Created a default config file at \
-             <span face='monospace' bgcolor='#000000'>{path:?}</span>",
-        );
-        border_color = (0.5, 1., 0.5);
-    };
+    match message {
+        Message::ConfigError => (),
+        Message::ShaderError => text = String::from(SHADER_ERROR_TEXT),
+        Message::Created(path) => {
+            text = format!(
+                "Created a default config file at \
+                 <span face='monospace' bgcolor='#000000'>{path:?}</span>",
+            );
+            border_color = (0.5, 1., 0.5);
+        }
+    }
 
     let mut font = FontDescription::from_string(FONT);
     font.set_absolute_size(to_physical_precise_round(scale, font.size()));
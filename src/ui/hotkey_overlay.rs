@@ -4,7 +4,7 @@ use std::collections::HashMap;
 use std::iter::zip;
 use std::rc::Rc;
 
-use niri_config::{Action, Bind, Config, Key, ModKey, Modifiers, Trigger};
+use niri_config::{Action, Bind, Binds, Config, Key, ModKey, Modifiers, Trigger};
 use pangocairo::cairo::{self, ImageSurface};
 use pangocairo::pango::{AttrColor, AttrInt, AttrList, AttrString, FontDescription, Weight};
 use smithay::backend::renderer::element::Kind;
@@ -30,6 +30,8 @@ pub struct HotkeyOverlay {
     is_open: bool,
     config: Rc<RefCell<Config>>,
     mod_key: ModKey,
+    /// Active binding mode, whose binds are shown instead of the important hotkeys.
+    bind_mode: Option<String>,
     buffers: RefCell<HashMap<WeakOutput, RenderedOverlay>>,
 }
 
@@ -43,6 +45,7 @@ impl HotkeyOverlay {
             is_open: false,
             config,
             mod_key,
+            bind_mode: None,
             buffers: RefCell::new(HashMap::new()),
         }
     }
@@ -74,6 +77,17 @@ impl HotkeyOverlay {
         self.buffers.borrow_mut().clear();
     }
 
+    /// Sets the active binding mode, returning whether a redraw is needed.
+    pub fn set_bind_mode(&mut self, name: Option<String>) -> bool {
+        if self.bind_mode == name {
+            return false;
+        }
+
+        self.bind_mode = name;
+        self.buffers.borrow_mut().clear();
+        self.is_open
+    }
+
     pub fn render<R: NiriRenderer>(
         &self,
         renderer: &mut R,
@@ -101,8 +115,15 @@ impl HotkeyOverlay {
 
         let rendered = buffers.entry(weak).or_insert_with(|| {
             let renderer = renderer.as_gles_renderer();
-            render(renderer, &self.config.borrow(), self.mod_key, scale)
-                .unwrap_or_else(|_| RenderedOverlay { buffer: None })
+            let config = self.config.borrow();
+            render(
+                renderer,
+                &config,
+                self.bind_mode.as_deref(),
+                self.mod_key,
+                scale,
+            )
+            .unwrap_or_else(|_| RenderedOverlay { buffer: None })
         });
         let buffer = rendered.buffer.as_ref()?;
 
@@ -158,7 +179,7 @@ fn format_bind(binds: &[Bind], mod_key: ModKey, action: &Action) -> Option<(Stri
             title = Some(custom.clone());
         }
 
-        key_name(mod_key, &bind.key)
+        bind_key_name(mod_key, bind)
     } else {
         String::from("(not bound)")
     };
@@ -167,25 +188,8 @@ fn format_bind(binds: &[Bind], mod_key: ModKey, action: &Action) -> Option<(Stri
     Some((format!(" {key} "), title))
 }
 
-fn render(
-    renderer: &mut GlesRenderer,
-    config: &Config,
-    mod_key: ModKey,
-    scale: f64,
-) -> anyhow::Result<RenderedOverlay> {
-    let _span = tracy_client::span!("hotkey_overlay::render");
-
-    // let margin = MARGIN * scale;
-    let padding: i32 = to_physical_precise_round(scale, PADDING);
-    let line_interval: i32 = to_physical_precise_round(scale, LINE_INTERVAL);
-
-    // FIXME: if it doesn't fit, try splitting in two columns or something.
-    // let mut target_size = output_size;
-    // target_size.w -= margin * 2;
-    // target_size.h -= margin * 2;
-    // anyhow::ensure!(target_size.w > 0 && target_size.h > 0);
-
-    let binds = &config.binds.0;
+fn format_important_binds(config: &Config, mod_key: ModKey) -> Vec<(String, String)> {
+    let binds = &config.binds().0;
 
     // Collect actions that we want to show.
     let mut actions = vec![&Action::ShowHotkeyOverlay];
@@ -290,10 +294,53 @@ fn render(
         actions.retain(|&action| binds.iter().any(|bind| bind.action == *action))
     }
 
-    let strings = actions
+    actions
         .into_iter()
         .filter_map(|action| format_bind(binds, mod_key, action))
-        .collect::<Vec<_>>();
+        .collect()
+}
+
+/// Lists every bind of a binding mode, since modes usually have few of them.
+fn format_mode_binds(binds: &Binds, mod_key: ModKey) -> Vec<(String, String)> {
+    binds
+        .0
+        .iter()
+        .filter_map(|bind| {
+            let title = match &bind.hotkey_overlay_title {
+                Some(None) => return None,
+                Some(Some(custom)) => custom.clone(),
+                None => action_name(&bind.action),
+            };
+            Some((format!(" {} ", bind_key_name(mod_key, bind)), title))
+        })
+        .collect()
+}
+
+fn render(
+    renderer: &mut GlesRenderer,
+    config: &Config,
+    bind_mode: Option<&str>,
+    mod_key: ModKey,
+    scale: f64,
+) -> anyhow::Result<RenderedOverlay> {
+    let _span = tracy_client::span!("hotkey_overlay::render");
+
+    // let margin = MARGIN * scale;
+    let padding: i32 = to_physical_precise_round(scale, PADDING);
+    let line_interval: i32 = to_physical_precise_round(scale, LINE_INTERVAL);
+
+    // FIXME: if it doesn't fit, try splitting in two columns or something.
+    // let mut target_size = output_size;
+    // target_size.w -= margin * 2;
+    // target_size.h -= margin * 2;
+    // anyhow::ensure!(target_size.w > 0 && target_size.h > 0);
+
+    let mode_binds = bind_mode.and_then(|name| Some((name, config.mode_binds(Some(name))?)));
+    let (title, strings) = match mode_binds {
+        Some((name, binds)) => (format!("Mode: {name}"), format_mode_binds(binds, mod_key)),
+        None => (String::from(TITLE), format_important_binds(config, mod_key)),
+    };
+    anyhow::ensure!(!strings.is_empty(), "no binds to show");
 
     let mut font = FontDescription::from_string(FONT);
     font.set_absolute_size(to_physical_precise_round(scale, font.size()));
@@ -307,7 +354,7 @@ fn render(
     let bold = AttrList::new();
     bold.insert(AttrInt::new_weight(Weight::Bold));
     layout.set_attributes(Some(&bold));
-    layout.set_text(TITLE);
+    layout.set_text(&title);
     let title_size = layout.pixel_size();
 
     let attrs = AttrList::new();
@@ -360,7 +407,7 @@ fn render(
 
     cr.move_to(((width - title_size.0) / 2).into(), padding.into());
     layout.set_attributes(Some(&bold));
-    layout.set_text(TITLE);
+    layout.set_text(&title);
     pangocairo::functions::show_layout(&cr, &layout);
 
     cr.move_to(padding.into(), (padding + title_size.1 + padding).into());
@@ -443,6 +490,10 @@ fn action_name(action: &Action) -> String {
         }
         Action::ToggleOverview => String::from("Open the Overview"),
         Action::Screenshot(_) => String::from("Take a Screenshot"),
+        Action::SetColumnWidth(_) => String::from("Change Column Width"),
+        Action::SetWindowHeight(_) => String::from("Change Window Height"),
+        Action::EnterBindMode(name) => format!("Enter the {name} Mode"),
+        Action::ExitBindMode => String::from("Exit the Mode"),
         Action::Spawn(args) => format!(
             "Spawn <span face='monospace' bgcolor='#000000'>{}</span>",
             args.first().unwrap_or(&String::new())
@@ -451,6 +502,12 @@ fn action_name(action: &Action) -> String {
     }
 }
 
+fn bind_key_name(mod_key: ModKey, bind: &Bind) -> String {
+    let keys = bind.prefix.iter().chain([&bind.key]);
+    let names: Vec<_> = keys.map(|key| key_name(mod_key, key)).collect();
+    names.join(", ")
+}
+
 fn key_name(mod_key: ModKey, key: &Key) -> String {
     let mut name = String::new();
 
@@ -518,6 +575,15 @@ fn key_name(mod_key: ModKey, key: &Key) -> String {
         Trigger::TouchpadScrollUp => String::from("Touchpad Scroll Up"),
         Trigger::TouchpadScrollLeft => String::from("Touchpad Scroll Left"),
         Trigger::TouchpadScrollRight => String::from("Touchpad Scroll Right"),
+        Trigger::TouchpadSwipe { fingers, direction } => {
+            format!("Touchpad Swipe {fingers} {direction:?}")
+        }
+        Trigger::TouchpadPinchIn => String::from("Touchpad Pinch In"),
+        Trigger::TouchpadPinchOut => String::from("Touchpad Pinch Out"),
+        Trigger::TouchpadHold { fingers } => format!("Touchpad Hold {fingers}"),
+        Trigger::TouchscreenSwipe { fingers, direction } => {
+            format!("Touchscreen Swipe {fingers} {direction:?}")
+        }
     };
     name.push_str(&pretty);
 
@@ -558,7 +624,7 @@ mod tests {
     #[track_caller]
     fn check(config: &str, action: Action) -> String {
         let config = Config::parse("test.kdl", config).unwrap();
-        if let Some((key, title)) = format_bind(&config.binds.0, ModKey::Super, &action) {
+        if let Some((key, title)) = format_bind(&config.binds().0, ModKey::Super, &action) {
             format!("{key}: {title}")
         } else {
             String::from("None")
@@ -0,0 +1,304 @@
+use niri_config::{Config, MagnifierFollowMode};
+use smithay::backend::renderer::element::utils::{
+    Relocate, RelocateRenderElement, RescaleRenderElement,
+};
+use smithay::backend::renderer::element::Element;
+use smithay::utils::{Logical, Physical, Point, Scale, Size};
+
+use crate::animation::{Animation, Clock};
+use crate::render_helpers::boxed::BoxedRenderElement;
+
+/// Factor by which a single zoom in or zoom out changes the zoom level.
+const ZOOM_STEP: f64 = 1.5;
+
+/// Output magnifier that zooms in around the pointer.
+#[derive(Debug)]
+pub struct Magnifier {
+    /// Requested zoom level, `1.` when the magnifier is off.
+    zoom: f64,
+    /// Ongoing zoom level change.
+    zoom_anim: Option<Animation>,
+    /// Top-left corner of the magnified area in output-local coordinates.
+    view_pos: Point<f64, Logical>,
+    /// Clock to drive animations.
+    clock: Clock,
+    config: niri_config::Magnifier,
+    anim_config: niri_config::Animation,
+}
+
+impl Magnifier {
+    pub fn new(clock: Clock, config: &Config) -> Self {
+        Self {
+            zoom: 1.,
+            zoom_anim: None,
+            view_pos: Point::from((0., 0.)),
+            clock,
+            config: config.magnifier,
+            anim_config: config.animations.magnifier_zoom.0,
+        }
+    }
+
+    pub fn update_config(&mut self, config: &Config) {
+        self.config = config.magnifier;
+        self.anim_config = config.animations.magnifier_zoom.0;
+
+        // Apply a lowered max zoom right away.
+        self.set_zoom(self.zoom);
+    }
+
+    pub fn zoom_in(&mut self) {
+        self.set_zoom(self.zoom * ZOOM_STEP);
+    }
+
+    pub fn zoom_out(&mut self) {
+        self.set_zoom(self.zoom / ZOOM_STEP);
+    }
+
+    pub fn reset(&mut self) {
+        self.set_zoom(1.);
+    }
+
+    fn set_zoom(&mut self, zoom: f64) {
+        let zoom = zoom.clamp(1., self.config.max_zoom.0);
+        if zoom == self.zoom {
+            return;
+        }
+
+        let from = self.current_zoom();
+        self.zoom_anim = Some(Animation::new(
+            self.clock.clone(),
+            from,
+            zoom,
+            0.,
+            self.anim_config,
+        ));
+        self.zoom = zoom;
+    }
+
+    pub fn current_zoom(&self) -> f64 {
+        let zoom = self.zoom_anim.as_ref().map_or(self.zoom, Animation::value);
+        // Springs can overshoot below the unzoomed level.
+        zoom.max(1.)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.current_zoom() > 1.
+    }
+
+    pub fn advance_animations(&mut self) {
+        if self.zoom_anim.as_ref().is_some_and(Animation::is_done) {
+            self.zoom_anim = None;
+        }
+    }
+
+    pub fn are_animations_ongoing(&self) -> bool {
+        self.zoom_anim.is_some()
+    }
+
+    /// Moves the magnified area to follow the pointer.
+    ///
+    /// `pointer` is the output-local pointer position, or `None` if the pointer is on a different
+    /// output, in which case the magnified area stays where it was.
+    pub fn update(
+        &mut self,
+        output_size: Size<f64, Logical>,
+        pointer: Option<Point<f64, Logical>>,
+    ) {
+        let zoom = self.current_zoom();
+        let view_w = output_size.w / zoom;
+        let view_h = output_size.h / zoom;
+
+        if let Some(pointer) = pointer {
+            let pos = &mut self.view_pos;
+            match self.config.follow_mode {
+                MagnifierFollowMode::Proportional => {
+                    // This keeps the pointer at the same spot on screen as without zoom.
+                    pos.x = pointer.x * (1. - 1. / zoom);
+                    pos.y = pointer.y * (1. - 1. / zoom);
+                }
+                MagnifierFollowMode::Centered => {
+                    pos.x = pointer.x - view_w / 2.;
+                    pos.y = pointer.y - view_h / 2.;
+                }
+                MagnifierFollowMode::Push => {
+                    pos.x = pos.x.clamp(pointer.x - view_w, pointer.x);
+                    pos.y = pos.y.clamp(pointer.y - view_h, pointer.y);
+                }
+            }
+        }
+
+        self.view_pos.x = self.view_pos.x.clamp(0., output_size.w - view_w);
+        self.view_pos.y = self.view_pos.y.clamp(0., output_size.h - view_h);
+    }
+
+    /// Converts a position on the magnified screen to the output-local position shown there.
+    pub fn screen_to_output(&self, pos: Point<f64, Logical>) -> Point<f64, Logical> {
+        let zoom = self.current_zoom();
+        Point::from((
+            self.view_pos.x + pos.x / zoom,
+            self.view_pos.y + pos.y / zoom,
+        ))
+    }
+
+    /// Converts a position of an absolute device on the magnified screen to the output-local
+    /// pointer position for it.
+    pub fn screen_to_pointer(&self, pos: Point<f64, Logical>) -> Point<f64, Logical> {
+        match self.config.follow_mode {
+            // The view will follow the pointer such that it stays at its real position.
+            MagnifierFollowMode::Proportional => pos,
+            // The view will center on the pointer wherever it goes, so pointing at the contents
+            // shown at the position would move them away right after. Instead, the device covers
+            // the whole output, which is also where relative devices can move the pointer.
+            MagnifierFollowMode::Centered => pos,
+            // The pointer stays within the view, so the view won't move.
+            MagnifierFollowMode::Push => self.screen_to_output(pos),
+        }
+    }
+
+    pub fn render<E: Element>(
+        &self,
+        elements: Vec<E>,
+        scale: Scale<f64>,
+    ) -> impl Iterator<Item = RelocateRenderElement<RescaleRenderElement<BoxedRenderElement<E>>>>
+    {
+        let zoom = self.current_zoom();
+        let offset =
+            Point::<f64, Logical>::from((-self.view_pos.x * zoom, -self.view_pos.y * zoom));
+        let offset: Point<i32, Physical> = offset.to_physical_precise_round(scale);
+
+        BoxedRenderElement::new_all(elements).map(move |elem| {
+            let elem = RescaleRenderElement::from_element(elem, Point::from((0, 0)), zoom);
+            RelocateRenderElement::from_element(elem, offset, Relocate::Relative)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn magnifier(follow_mode: MagnifierFollowMode) -> Magnifier {
+        let mut config = Config::default();
+        config.magnifier.follow_mode = follow_mode;
+        config.animations.magnifier_zoom.0.off = true;
+
+        let mut magnifier = Magnifier::new(Clock::with_time(Duration::ZERO), &config);
+        magnifier.set_zoom(2.);
+        finish_zoom(&mut magnifier);
+        magnifier
+    }
+
+    fn finish_zoom(magnifier: &mut Magnifier) {
+        // Even with animations off, the zoom changes once some time passes.
+        let now = magnifier.clock.now_unadjusted();
+        magnifier
+            .clock
+            .set_unadjusted(now + Duration::from_millis(1));
+        magnifier.advance_animations();
+    }
+
+    fn size() -> Size<f64, Logical> {
+        Size::from((1000., 500.))
+    }
+
+    #[test]
+    fn proportional_keeps_pointer_in_place() {
+        let mut magnifier = magnifier(MagnifierFollowMode::Proportional);
+        let pointer = Point::from((400., 200.));
+        magnifier.update(size(), Some(pointer));
+
+        assert_eq!(magnifier.view_pos, Point::from((200., 100.)));
+        assert_eq!(magnifier.screen_to_output(pointer), pointer);
+        assert_eq!(magnifier.screen_to_pointer(pointer), pointer);
+    }
+
+    #[test]
+    fn centered_keeps_pointer_in_center() {
+        let mut magnifier = magnifier(MagnifierFollowMode::Centered);
+        let pointer = Point::from((400., 200.));
+        magnifier.update(size(), Some(pointer));
+
+        assert_eq!(magnifier.view_pos, Point::from((150., 75.)));
+        assert_eq!(
+            magnifier.screen_to_output(Point::from((500., 250.))),
+            pointer
+        );
+
+        // The view doesn't go past the output edges.
+        magnifier.update(size(), Some(Point::from((10., 490.))));
+        assert_eq!(magnifier.view_pos, Point::from((0., 250.)));
+    }
+
+    #[test]
+    fn push_moves_view_at_edges() {
+        let mut magnifier = magnifier(MagnifierFollowMode::Push);
+        magnifier.update(size(), Some(Point::from((100., 100.))));
+        assert_eq!(magnifier.view_pos, Point::from((0., 0.)));
+
+        magnifier.update(size(), Some(Point::from((800., 100.))));
+        assert_eq!(magnifier.view_pos, Point::from((300., 0.)));
+
+        // Moving back within the view doesn't move it.
+        magnifier.update(size(), Some(Point::from((700., 200.))));
+        assert_eq!(magnifier.view_pos, Point::from((300., 0.)));
+    }
+
+    #[test]
+    fn pointer_elsewhere_keeps_view() {
+        let mut magnifier = magnifier(MagnifierFollowMode::Centered);
+        magnifier.update(size(), Some(Point::from((400., 200.))));
+        magnifier.update(size(), None);
+        assert_eq!(magnifier.view_pos, Point::from((150., 75.)));
+
+        // Zooming out still keeps the view within the output.
+        magnifier.reset();
+        finish_zoom(&mut magnifier);
+        magnifier.update(size(), None);
+        assert_eq!(magnifier.view_pos, Point::from((0., 0.)));
+    }
+
+    #[test]
+    fn screen_to_output_scales_by_zoom() {
+        let mut magnifier = magnifier(MagnifierFollowMode::Push);
+        magnifier.update(size(), Some(Point::from((800., 100.))));
+
+        assert_eq!(
+            magnifier.screen_to_output(Point::from((0., 0.))),
+            Point::from((300., 0.))
+        );
+        assert_eq!(
+            magnifier.screen_to_output(Point::from((1000., 500.))),
+            Point::from((800., 250.))
+        );
+    }
+
+    #[test]
+    fn absolute_pointer_is_stable() {
+        // Pointing at the same spot again must not move the pointer, whichever way the view
+        // follows it.
+        for mode in [
+            MagnifierFollowMode::Proportional,
+            MagnifierFollowMode::Centered,
+            MagnifierFollowMode::Push,
+        ] {
+            let mut magnifier = magnifier(mode);
+            let screen = Point::from((900., 100.));
+
+            let pointer = magnifier.screen_to_pointer(screen);
+            magnifier.update(size(), Some(pointer));
+            assert_eq!(magnifier.screen_to_pointer(screen), pointer, "{mode:?}");
+        }
+    }
+
+    #[test]
+    fn centered_absolute_matches_relative() {
+        // Absolute devices reach the same pointer positions as relative ones: the whole output.
+        let mut magnifier = magnifier(MagnifierFollowMode::Centered);
+        magnifier.update(size(), Some(Point::from((400., 200.))));
+
+        let corner = Point::from((1000., 500.));
+        assert_eq!(magnifier.screen_to_pointer(corner), corner);
+    }
+}
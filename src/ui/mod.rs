@@ -1,5 +1,6 @@
 pub mod config_error_notification;
 pub mod exit_confirm_dialog;
 pub mod hotkey_overlay;
+pub mod magnifier;
 pub mod screen_transition;
 pub mod screenshot_ui;
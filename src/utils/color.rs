@@ -0,0 +1,637 @@
+//! Color spaces for color management.
+//!
+//! Surfaces and outputs are described by their primaries, transfer function and luminances.
+//! Converting between two descriptions decodes the transfer function, maps the primaries in linear
+//! light through CIE XYZ (adapting the white point), and encodes the target transfer function.
+//! The decoding and encoding happen in the color transform shader, and the primaries mapping is
+//! the matrix computed here.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use anyhow::{bail, ensure, Context};
+use glam::{Mat3, Vec3};
+use smithay::output::Output;
+
+use super::expand_home;
+
+/// Chromaticity coordinates in CIE 1931 xy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Chromaticity {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Primaries {
+    pub red: Chromaticity,
+    pub green: Chromaticity,
+    pub blue: Chromaticity,
+    pub white: Chromaticity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferFunction {
+    /// The piecewise sRGB transfer function.
+    Srgb,
+    /// Pure power function with this exponent, linear for 1.
+    Power(f32),
+    /// SMPTE ST 2084, also known as PQ.
+    Pq,
+}
+
+/// Luminances in cd/m².
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Luminances {
+    pub min: f32,
+    pub max: f32,
+    /// Luminance of the reference white.
+    pub reference: f32,
+}
+
+/// HDR metadata describing the display the content was mastered on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MasteringDisplay {
+    pub primaries: Option<Primaries>,
+    /// Minimum and maximum luminance in cd/m².
+    pub luminance: Option<(f32, f32)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorDescription {
+    pub primaries: Primaries,
+    pub tf: TransferFunction,
+    pub luminances: Luminances,
+    pub mastering_display: MasteringDisplay,
+    /// Maximum content light level in cd/m².
+    pub max_cll: Option<f32>,
+    /// Maximum frame-average light level in cd/m².
+    pub max_fall: Option<f32>,
+}
+
+/// Color description of an output, stored in its user data.
+struct OutputColorDescription(Mutex<Arc<ColorDescription>>);
+
+/// Color descriptions loaded from ICC profiles, keyed by path.
+///
+/// Output config reloads happen on every config change, so this avoids re-reading and re-parsing
+/// profiles whose files haven't been modified.
+#[derive(Debug, Default)]
+pub struct IccProfileCache {
+    profiles: HashMap<PathBuf, (SystemTime, ColorDescription)>,
+}
+
+const fn xy(x: f32, y: f32) -> Chromaticity {
+    Chromaticity { x, y }
+}
+
+pub const D50: Chromaticity = xy(0.3457, 0.3585);
+pub const D65: Chromaticity = xy(0.3127, 0.3290);
+
+/// Bradford cone response matrix, column-major.
+const BRADFORD: Mat3 = Mat3::from_cols_array(&[
+    0.8951, -0.7502, 0.0389, //
+    0.2664, 1.7135, -0.0685, //
+    -0.1614, 0.0367, 1.0296,
+]);
+
+impl Chromaticity {
+    /// Returns the CIE XYZ color with this chromaticity and a luminance of 1.
+    fn to_xyz(self) -> Vec3 {
+        Vec3::new(self.x / self.y, 1., (1. - self.x - self.y) / self.y)
+    }
+
+    fn from_xyz(xyz: Vec3) -> Self {
+        let sum = xyz.x + xyz.y + xyz.z;
+        xy(xyz.x / sum, xyz.y / sum)
+    }
+
+    /// Returns whether the chromaticities are equal within the precision of ICC profiles.
+    fn approx_eq(self, other: Self) -> bool {
+        (self.x - other.x).abs() < 1e-3 && (self.y - other.y).abs() < 1e-3
+    }
+}
+
+impl Primaries {
+    pub const SRGB: Self = Self {
+        red: xy(0.64, 0.33),
+        green: xy(0.30, 0.60),
+        blue: xy(0.15, 0.06),
+        white: D65,
+    };
+
+    pub const BT2020: Self = Self {
+        red: xy(0.708, 0.292),
+        green: xy(0.170, 0.797),
+        blue: xy(0.131, 0.046),
+        white: D65,
+    };
+
+    pub const DISPLAY_P3: Self = Self {
+        red: xy(0.680, 0.320),
+        green: xy(0.265, 0.690),
+        blue: xy(0.150, 0.060),
+        white: D65,
+    };
+
+    pub const ADOBE_RGB: Self = Self {
+        red: xy(0.64, 0.33),
+        green: xy(0.21, 0.71),
+        blue: xy(0.15, 0.06),
+        white: D65,
+    };
+
+    /// Returns whether the primaries are equal within the precision of ICC profiles.
+    fn approx_eq(&self, other: &Self) -> bool {
+        self.red.approx_eq(other.red)
+            && self.green.approx_eq(other.green)
+            && self.blue.approx_eq(other.blue)
+            && self.white.approx_eq(other.white)
+    }
+
+    /// Returns the matrix converting linear RGB with these primaries into CIE XYZ.
+    pub fn to_xyz(&self) -> Mat3 {
+        let rgb = Mat3::from_cols(self.red.to_xyz(), self.green.to_xyz(), self.blue.to_xyz());
+        // Scale the primaries so that RGB 1, 1, 1 maps to the white point.
+        let scale = rgb.inverse() * self.white.to_xyz();
+        Mat3::from_cols(
+            rgb.x_axis * scale.x,
+            rgb.y_axis * scale.y,
+            rgb.z_axis * scale.z,
+        )
+    }
+}
+
+/// Returns the Bradford chromatic adaptation matrix between two white points.
+fn chromatic_adaptation(from: Chromaticity, to: Chromaticity) -> Mat3 {
+    if from == to {
+        return Mat3::IDENTITY;
+    }
+
+    let from = BRADFORD * from.to_xyz();
+    let to = BRADFORD * to.to_xyz();
+    BRADFORD.inverse() * Mat3::from_diagonal(to / from) * BRADFORD
+}
+
+impl TransferFunction {
+    /// Returns the transfer function kind and exponent for the color transform shader.
+    pub fn shader_params(self) -> (f32, f32) {
+        match self {
+            TransferFunction::Srgb => (0., 1.),
+            TransferFunction::Power(exp) => (1., exp),
+            TransferFunction::Pq => (2., 1.),
+        }
+    }
+}
+
+impl Luminances {
+    /// Returns the default luminances for a transfer function.
+    pub fn default_for(tf: TransferFunction) -> Self {
+        match tf {
+            TransferFunction::Pq => Self {
+                min: 0.005,
+                max: 10000.,
+                reference: 203.,
+            },
+            _ => Self {
+                min: 0.2,
+                max: 80.,
+                reference: 80.,
+            },
+        }
+    }
+}
+
+impl ColorDescription {
+    pub fn new(primaries: Primaries, tf: TransferFunction) -> Self {
+        Self {
+            primaries,
+            tf,
+            luminances: Luminances::default_for(tf),
+            mastering_display: MasteringDisplay {
+                primaries: None,
+                luminance: None,
+            },
+            max_cll: None,
+            max_fall: None,
+        }
+    }
+
+    pub fn srgb() -> Self {
+        Self::new(Primaries::SRGB, TransferFunction::Srgb)
+    }
+
+    /// Returns whether pixels in this description need converting to show on `target`.
+    ///
+    /// HDR metadata doesn't change the pixels, so it doesn't matter here.
+    pub fn needs_conversion_to(&self, target: &ColorDescription) -> bool {
+        self.primaries != target.primaries
+            || self.tf != target.tf
+            || self.luminances.reference != target.luminances.reference
+    }
+
+    /// Returns the matrix converting linear light in this description into `target`.
+    ///
+    /// Reference white maps onto the reference white of the target.
+    pub fn conversion_matrix(&self, target: &ColorDescription) -> Mat3 {
+        let mut matrix = target.primaries.to_xyz().inverse()
+            * chromatic_adaptation(self.primaries.white, target.primaries.white)
+            * self.primaries.to_xyz();
+
+        // PQ encodes absolute luminance, with 1 being 10000 cd/m².
+        if self.tf == TransferFunction::Pq {
+            matrix *= 10000. / self.luminances.reference;
+        }
+        if target.tf == TransferFunction::Pq {
+            matrix *= target.luminances.reference / 10000.;
+        }
+
+        matrix
+    }
+}
+
+/// Returns the color description of an output, sRGB unless set otherwise.
+pub fn output_color_description(output: &Output) -> Arc<ColorDescription> {
+    output
+        .user_data()
+        .get::<OutputColorDescription>()
+        .map(|data| data.0.lock().unwrap().clone())
+        .unwrap_or_else(|| Arc::new(ColorDescription::srgb()))
+}
+
+/// Sets the color description of an output, returning whether it changed.
+pub fn set_output_color_description(output: &Output, description: ColorDescription) -> bool {
+    let user_data = output.user_data();
+    user_data.insert_if_missing_threadsafe(|| {
+        OutputColorDescription(Mutex::new(Arc::new(ColorDescription::srgb())))
+    });
+
+    let mut current = user_data
+        .get::<OutputColorDescription>()
+        .unwrap()
+        .0
+        .lock()
+        .unwrap();
+    if **current == description {
+        return false;
+    }
+
+    *current = Arc::new(description);
+    true
+}
+
+impl IccProfileCache {
+    /// Returns the color description for an output with this configured ICC profile.
+    ///
+    /// Falls back to sRGB if there's no profile or it fails to load.
+    pub fn load(&mut self, icc_profile: Option<&str>) -> ColorDescription {
+        let Some(path) = icc_profile else {
+            return ColorDescription::srgb();
+        };
+
+        match self.load_icc_profile(Path::new(path)) {
+            Ok(description) => description,
+            Err(err) => {
+                warn!("error loading ICC profile {path:?}: {err:?}");
+                ColorDescription::srgb()
+            }
+        }
+    }
+
+    fn load_icc_profile(&mut self, path: &Path) -> anyhow::Result<ColorDescription> {
+        let expanded = expand_home(path).context("error expanding ~")?;
+        let path = expanded.as_deref().unwrap_or(path);
+
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .context("error reading file metadata")?;
+        if let Some((cached_modified, description)) = self.profiles.get(path) {
+            if *cached_modified == modified {
+                return Ok(description.clone());
+            }
+        }
+
+        let data = fs::read(path).context("error reading file")?;
+        let description = parse_icc_profile(&data)?;
+        self.profiles
+            .insert(path.to_owned(), (modified, description.clone()));
+        Ok(description)
+    }
+}
+
+/// Parses an RGB matrix/TRC ICC profile.
+///
+/// Only the colorants and the red tone curve are used. Tone curves given as tables are
+/// approximated with a power function.
+fn parse_icc_profile(data: &[u8]) -> anyhow::Result<ColorDescription> {
+    ensure!(data.len() >= 132, "profile is too short");
+    ensure!(&data[36..40] == b"acsp", "not an ICC profile");
+    ensure!(&data[16..20] == b"RGB ", "only RGB profiles are supported");
+    ensure!(
+        &data[20..24] == b"XYZ ",
+        "only profiles with an XYZ PCS are supported"
+    );
+
+    let tag = |signature: &[u8; 4]| -> anyhow::Result<&[u8]> {
+        let count = read_u32(data, 128)? as usize;
+        for i in 0..count {
+            let entry = 132 + i * 12;
+            if data.get(entry..entry + 4) != Some(signature) {
+                continue;
+            }
+
+            let offset = read_u32(data, entry + 4)? as usize;
+            let size = read_u32(data, entry + 8)? as usize;
+            return data
+                .get(offset..offset.saturating_add(size))
+                .context("tag data out of bounds");
+        }
+
+        let signature = String::from_utf8_lossy(signature);
+        bail!("missing {signature} tag; only matrix/TRC profiles are supported")
+    };
+
+    let red = parse_xyz_tag(tag(b"rXYZ")?).context("error parsing rXYZ")?;
+    let green = parse_xyz_tag(tag(b"gXYZ")?).context("error parsing gXYZ")?;
+    let blue = parse_xyz_tag(tag(b"bXYZ")?).context("error parsing bXYZ")?;
+    let tf = parse_trc_tag(tag(b"rTRC")?).context("error parsing rTRC")?;
+
+    // The colorants are adapted to the D50 illuminant of the profile connection space. Adapt them
+    // back to the D65 white of the display, so that standard profiles match their color spaces.
+    let adaptation = chromatic_adaptation(D50, D65);
+    let primaries = Primaries {
+        red: Chromaticity::from_xyz(adaptation * red),
+        green: Chromaticity::from_xyz(adaptation * green),
+        blue: Chromaticity::from_xyz(adaptation * blue),
+        white: D65,
+    };
+
+    // Snap to the exact standard primaries, so that for example an sRGB profile doesn't make every
+    // sRGB surface go through the color transform shader.
+    let known = [
+        Primaries::SRGB,
+        Primaries::BT2020,
+        Primaries::DISPLAY_P3,
+        Primaries::ADOBE_RGB,
+    ];
+    let primaries = known
+        .into_iter()
+        .find(|known| known.approx_eq(&primaries))
+        .unwrap_or(primaries);
+
+    Ok(ColorDescription::new(primaries, tf))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> anyhow::Result<u32> {
+    let bytes = data
+        .get(offset..offset + 4)
+        .context("unexpected end of data")?;
+    Ok(u32::from_be_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_s15_fixed16(data: &[u8], offset: usize) -> anyhow::Result<f32> {
+    Ok(read_u32(data, offset)? as i32 as f32 / 65536.)
+}
+
+fn parse_xyz_tag(data: &[u8]) -> anyhow::Result<Vec3> {
+    ensure!(data.get(0..4) == Some(b"XYZ "), "wrong tag type");
+    Ok(Vec3::new(
+        read_s15_fixed16(data, 8)?,
+        read_s15_fixed16(data, 12)?,
+        read_s15_fixed16(data, 16)?,
+    ))
+}
+
+fn parse_trc_tag(data: &[u8]) -> anyhow::Result<TransferFunction> {
+    match data.get(0..4) {
+        Some(b"curv") => {
+            let count = read_u32(data, 8)? as usize;
+            match count {
+                0 => Ok(TransferFunction::Power(1.)),
+                1 => {
+                    let bytes = data.get(12..14).context("unexpected end of data")?;
+                    let gamma = f32::from(u16::from_be_bytes([bytes[0], bytes[1]])) / 256.;
+                    Ok(TransferFunction::Power(gamma))
+                }
+                _ => {
+                    // Approximate the table with a power function through its midpoint.
+                    let mid = 12 + (count / 2) * 2;
+                    let bytes = data.get(mid..mid + 2).context("unexpected end of data")?;
+                    let input = (count / 2) as f32 / (count - 1) as f32;
+                    let output = f32::from(u16::from_be_bytes([bytes[0], bytes[1]])) / 65535.;
+                    ensure!(
+                        0. < output && output < 1.,
+                        "tone curve table is not increasing"
+                    );
+                    Ok(TransferFunction::Power(output.ln() / input.ln()))
+                }
+            }
+        }
+        Some(b"para") => {
+            let bytes = data.get(8..10).context("unexpected end of data")?;
+            let function = u16::from_be_bytes([bytes[0], bytes[1]]);
+            let gamma = read_s15_fixed16(data, 12)?;
+            // Type 3 with the sRGB parameters is the sRGB curve.
+            if function == 3 && (gamma - 2.4).abs() < 0.01 {
+                let a = read_s15_fixed16(data, 16)?;
+                if (a - 1. / 1.055).abs() < 0.001 {
+                    return Ok(TransferFunction::Srgb);
+                }
+            }
+            Ok(TransferFunction::Power(gamma))
+        }
+        _ => bail!("unsupported tone curve type"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use xshell::Shell;
+
+    use super::*;
+
+    fn assert_mat3_eq(a: Mat3, b: Mat3) {
+        assert!(a.abs_diff_eq(b, 1e-3), "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn srgb_to_xyz() {
+        let expected = Mat3::from_cols_array(&[
+            0.4124, 0.2126, 0.0193, //
+            0.3576, 0.7152, 0.1192, //
+            0.1805, 0.0722, 0.9505,
+        ]);
+        assert_mat3_eq(Primaries::SRGB.to_xyz(), expected);
+    }
+
+    #[test]
+    fn same_description_is_identity() {
+        let srgb = ColorDescription::srgb();
+        assert!(!srgb.needs_conversion_to(&srgb));
+        assert_mat3_eq(srgb.conversion_matrix(&srgb), Mat3::IDENTITY);
+    }
+
+    #[test]
+    fn bt2020_to_srgb() {
+        let bt2020 = ColorDescription::new(Primaries::BT2020, TransferFunction::Srgb);
+        let srgb = ColorDescription::srgb();
+        assert!(bt2020.needs_conversion_to(&srgb));
+
+        let matrix = bt2020.conversion_matrix(&srgb);
+        // White stays white.
+        assert!((matrix * Vec3::ONE).abs_diff_eq(Vec3::ONE, 1e-3));
+        // BT.2020 red is outside of the sRGB gamut.
+        let red = matrix * Vec3::X;
+        assert!(red.x > 1. && red.y < 0. && red.z < 0.);
+    }
+
+    #[test]
+    fn pq_maps_reference_white() {
+        let pq = ColorDescription::new(Primaries::SRGB, TransferFunction::Pq);
+        let srgb = ColorDescription::srgb();
+
+        // 203 cd/m² in PQ is the sRGB reference white.
+        let matrix = pq.conversion_matrix(&srgb);
+        let white = matrix * Vec3::splat(203. / 10000.);
+        assert!(white.abs_diff_eq(Vec3::ONE, 1e-3));
+    }
+
+    #[test]
+    fn chromatic_adaptation_maps_white_points() {
+        let adaptation = chromatic_adaptation(D65, D50);
+        let white = adaptation * D65.to_xyz();
+        assert!(white.abs_diff_eq(D50.to_xyz(), 1e-3));
+    }
+
+    fn s15_fixed16(value: f32) -> [u8; 4] {
+        ((value * 65536.).round() as i32).to_be_bytes()
+    }
+
+    fn make_icc_profile(primaries: Primaries, trc: &[u8]) -> Vec<u8> {
+        let mut tags = Vec::new();
+        let matrix = chromatic_adaptation(primaries.white, D50) * primaries.to_xyz();
+        for (signature, colorant) in [
+            (b"rXYZ", matrix.x_axis),
+            (b"gXYZ", matrix.y_axis),
+            (b"bXYZ", matrix.z_axis),
+        ] {
+            let mut data = b"XYZ \0\0\0\0".to_vec();
+            for value in colorant.to_array() {
+                data.extend(s15_fixed16(value));
+            }
+            tags.push((signature, data));
+        }
+        tags.push((b"rTRC", trc.to_vec()));
+
+        let mut header = vec![0; 128];
+        header[16..20].copy_from_slice(b"RGB ");
+        header[20..24].copy_from_slice(b"XYZ ");
+        header[36..40].copy_from_slice(b"acsp");
+
+        let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+        let mut data = Vec::new();
+        let data_start = 128 + 4 + tags.len() * 12;
+        for (signature, tag) in tags {
+            table.extend(signature);
+            table.extend(((data_start + data.len()) as u32).to_be_bytes());
+            table.extend((tag.len() as u32).to_be_bytes());
+            data.extend(tag);
+        }
+
+        [header, table, data].concat()
+    }
+
+    #[test]
+    fn parse_icc_gamma_profile() {
+        let trc = [b"curv".as_slice(), &[0; 4], &1u32.to_be_bytes(), &[2, 51]].concat();
+        let profile = make_icc_profile(Primaries::DISPLAY_P3, &trc);
+        let description = parse_icc_profile(&profile).unwrap();
+
+        let TransferFunction::Power(gamma) = description.tf else {
+            panic!("wrong transfer function: {:?}", description.tf);
+        };
+        assert!((gamma - 2.2).abs() < 0.01);
+        assert_eq!(description.primaries, Primaries::DISPLAY_P3);
+
+        // Converting through the profile gives the same result as from the original primaries.
+        let p3 = ColorDescription::new(Primaries::DISPLAY_P3, description.tf);
+        let srgb = ColorDescription::srgb();
+        assert_mat3_eq(
+            srgb.conversion_matrix(&description),
+            srgb.conversion_matrix(&p3),
+        );
+    }
+
+    #[test]
+    fn parse_icc_srgb_profile() {
+        let mut trc = [b"para".as_slice(), &[0; 4], &3u16.to_be_bytes(), &[0; 2]].concat();
+        for value in [2.4, 1. / 1.055, 0.055 / 1.055, 1. / 12.92, 0.04045] {
+            trc.extend(s15_fixed16(value));
+        }
+        let profile = make_icc_profile(Primaries::SRGB, &trc);
+        let description = parse_icc_profile(&profile).unwrap();
+        assert_eq!(description.tf, TransferFunction::Srgb);
+
+        // The white point is D50 in the profile, but it's still exactly sRGB.
+        assert_eq!(description, ColorDescription::srgb());
+        assert!(!ColorDescription::srgb().needs_conversion_to(&description));
+    }
+
+    #[test]
+    fn parse_icc_keeps_custom_primaries() {
+        let primaries = Primaries {
+            red: xy(0.66, 0.32),
+            green: xy(0.28, 0.65),
+            blue: xy(0.15, 0.05),
+            white: D65,
+        };
+        let trc = [b"curv".as_slice(), &[0; 4], &0u32.to_be_bytes()].concat();
+        let profile = make_icc_profile(primaries, &trc);
+        let description = parse_icc_profile(&profile).unwrap();
+
+        assert!(description.primaries.approx_eq(&primaries));
+        assert!(ColorDescription::srgb().needs_conversion_to(&description));
+    }
+
+    #[test]
+    fn parse_icc_rejects_garbage() {
+        assert!(parse_icc_profile(&[0; 200]).is_err());
+        assert!(parse_icc_profile(b"short").is_err());
+    }
+
+    #[test]
+    fn icc_profile_cache_reloads_modified_files() {
+        let sh = Shell::new().unwrap();
+        let temp_dir = sh.create_temp_dir().unwrap();
+        let path = temp_dir.path().join("output.icc");
+        let path_str = path.to_str().unwrap();
+
+        let trc = [b"curv".as_slice(), &[0; 4], &1u32.to_be_bytes(), &[2, 51]].concat();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        let write = |primaries: Primaries, modified: SystemTime| {
+            fs::write(&path, make_icc_profile(primaries, &trc)).unwrap();
+            let file = fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(modified).unwrap();
+        };
+
+        let mut cache = IccProfileCache::default();
+
+        write(Primaries::DISPLAY_P3, modified);
+        let description = cache.load(Some(path_str));
+        assert_eq!(description.primaries, Primaries::DISPLAY_P3);
+
+        // Same modification time: the cached profile is used.
+        write(Primaries::ADOBE_RGB, modified);
+        let description = cache.load(Some(path_str));
+        assert_eq!(description.primaries, Primaries::DISPLAY_P3);
+
+        write(Primaries::ADOBE_RGB, modified + Duration::from_secs(1));
+        let description = cache.load(Some(path_str));
+        assert_eq!(description.primaries, Primaries::ADOBE_RGB);
+    }
+}
@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::cmp::{max, min};
 use std::f64;
 use std::ffi::{CString, OsStr};
@@ -32,6 +33,7 @@ use wayland_backend::server::Credentials;
 use crate::handlers::KdeDecorationsModeState;
 use crate::niri::ClientState;
 
+pub mod color;
 pub mod id;
 pub mod scale;
 pub mod signals;
@@ -279,6 +281,43 @@ pub fn with_toplevel_role<T>(
     })
 }
 
+/// Returns the app id of a toplevel surface, or `None` for other surfaces.
+pub fn toplevel_app_id(surface: &WlSurface) -> Option<String> {
+    with_states(surface, |states| {
+        let role = states.data_map.get::<XdgToplevelSurfaceData>()?;
+        role.lock().unwrap().app_id.clone()
+    })
+}
+
+/// Toplevel state that clients set through protocols other than xdg-shell.
+#[derive(Debug, Default, Clone)]
+pub struct ToplevelExtra {
+    /// Tag set through xdg-toplevel-tag.
+    pub tag: Option<String>,
+    /// Whether the toplevel is a modal dialog, set through xdg-dialog.
+    pub is_modal: bool,
+}
+
+pub fn toplevel_extra(surface: &WlSurface) -> ToplevelExtra {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .get::<RefCell<ToplevelExtra>>()
+            .map(|extra| extra.borrow().clone())
+            .unwrap_or_default()
+    })
+}
+
+pub fn with_toplevel_extra<T>(surface: &WlSurface, f: impl FnOnce(&mut ToplevelExtra) -> T) -> T {
+    with_states(surface, |states| {
+        states
+            .data_map
+            .insert_if_missing(|| RefCell::new(ToplevelExtra::default()));
+        let extra = states.data_map.get::<RefCell<ToplevelExtra>>().unwrap();
+        f(&mut extra.borrow_mut())
+    })
+}
+
 pub fn update_tiled_state(
     toplevel: &ToplevelSurface,
     prefer_no_csd: bool,
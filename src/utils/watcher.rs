@@ -1,10 +1,11 @@
 //! File modification watcher.
 
+use std::iter::zip;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{mpsc, Arc};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
 use smithay::reexports::calloop::channel::SyncSender;
 
@@ -21,15 +22,29 @@ impl Drop for Watcher {
 impl Watcher {
     pub fn new<T: Send + 'static>(
         path: PathBuf,
-        process: impl FnMut(&Path) -> T + Send + 'static,
+        mut process: impl FnMut(&Path) -> T + Send + 'static,
         changed: SyncSender<T>,
     ) -> Self {
-        Self::with_start_notification(path, process, changed, None)
+        Self::with_extra_paths(path, Vec::new(), move |path| (process(path), Vec::new()), changed)
+    }
+
+    /// Creates a watcher that also reacts to changes in additional files.
+    ///
+    /// `process` returns the new list of additional files along with its result. This is used for
+    /// files included from the config, which can change on every reload.
+    pub fn with_extra_paths<T: Send + 'static>(
+        path: PathBuf,
+        extra_paths: Vec<PathBuf>,
+        process: impl FnMut(&Path) -> (T, Vec<PathBuf>) + Send + 'static,
+        changed: SyncSender<T>,
+    ) -> Self {
+        Self::with_start_notification(path, extra_paths, process, changed, None)
     }
 
     pub fn with_start_notification<T: Send + 'static>(
         path: PathBuf,
-        mut process: impl FnMut(&Path) -> T + Send + 'static,
+        mut extra_paths: Vec<PathBuf>,
+        mut process: impl FnMut(&Path) -> (T, Vec<PathBuf>) + Send + 'static,
         changed: SyncSender<T>,
         started: Option<mpsc::SyncSender<()>>,
     ) -> Self {
@@ -48,10 +63,9 @@ impl Watcher {
                     // because, on nix practically everything is a symlink to /nix/store
                     // and due to reproducibility, /nix/store keeps no mtime (= 1970-01-01)
                     // so, symlink targets change frequently when mtime doesn't.
-                    let mut last_props = path
-                        .canonicalize()
-                        .and_then(|canon| Ok((canon.metadata()?.modified()?, canon)))
-                        .ok();
+                    let mut last_props = file_props(&path).ok();
+                    let mut last_extra_props: Vec<_> =
+                        extra_paths.iter().map(|p| file_props(p).ok()).collect();
 
                     if let Some(started) = started {
                         let _ = started.send(());
@@ -64,22 +78,37 @@ impl Watcher {
                             break;
                         }
 
-                        if let Ok(new_props) = path
-                            .canonicalize()
-                            .and_then(|canon| Ok((canon.metadata()?.modified()?, canon)))
-                        {
-                            if last_props.as_ref() != Some(&new_props) {
+                        let mut new_props = last_props.clone();
+                        let mut is_changed = false;
+                        if let Ok(props) = file_props(&path) {
+                            if last_props.as_ref() != Some(&props) {
                                 trace!("file changed: {}", path.to_string_lossy());
+                                new_props = Some(props);
+                                is_changed = true;
+                            }
+                        }
 
-                                let rv = process(&path);
+                        // Unlike the main file, a deleted extra file counts as a change, since the
+                        // config that includes it no longer loads the same.
+                        for (extra, last) in zip(&extra_paths, &last_extra_props) {
+                            if *last != file_props(extra).ok() {
+                                trace!("file changed: {}", extra.to_string_lossy());
+                                is_changed = true;
+                            }
+                        }
 
-                                if let Err(err) = changed.send(rv) {
-                                    warn!("error sending change notification: {err:?}");
-                                    break;
-                                }
+                        if is_changed {
+                            let (rv, new_extra_paths) = process(&path);
 
-                                last_props = Some(new_props);
+                            if let Err(err) = changed.send(rv) {
+                                warn!("error sending change notification: {err:?}");
+                                break;
                             }
+
+                            last_props = new_props;
+                            extra_paths = new_extra_paths;
+                            last_extra_props =
+                                extra_paths.iter().map(|p| file_props(p).ok()).collect();
                         }
                     }
 
@@ -92,6 +121,11 @@ impl Watcher {
     }
 }
 
+fn file_props(path: &Path) -> std::io::Result<(SystemTime, PathBuf)> {
+    let canon = path.canonicalize()?;
+    Ok((canon.metadata()?.modified()?, canon))
+}
+
 #[cfg(test)]
 mod tests {
     use std::error::Error;
@@ -130,8 +164,13 @@ mod tests {
 
         let (tx, rx) = sync_channel(1);
         let (started_tx, started_rx) = mpsc::sync_channel(1);
-        let _watcher =
-            Watcher::with_start_notification(config_path.clone(), |_| (), tx, Some(started_tx));
+        let _watcher = Watcher::with_start_notification(
+            config_path.clone(),
+            Vec::new(),
+            |_| ((), Vec::new()),
+            tx,
+            Some(started_tx),
+        );
         loop_handle
             .insert_source(rx, |_, _, _| {
                 changed.fetch_add(1, Ordering::SeqCst);
@@ -353,4 +392,76 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn change_extra_file() {
+        let sh = Shell::new().unwrap();
+        let temp_dir = sh.create_temp_dir().unwrap();
+        sh.change_dir(temp_dir.path());
+
+        let config_path = sh.current_dir().join("config.kdl");
+        let extra_path = sh.current_dir().join("extra.kdl");
+        sh.write_file(&config_path, "a").unwrap();
+        sh.write_file(&extra_path, "a").unwrap();
+
+        let changed = AtomicU8::new(0);
+
+        let mut event_loop = EventLoop::try_new().unwrap();
+        let loop_handle = event_loop.handle();
+
+        let (tx, rx) = sync_channel(1);
+        let (started_tx, started_rx) = mpsc::sync_channel(1);
+        let extra = extra_path.clone();
+        let _watcher = Watcher::with_start_notification(
+            config_path.clone(),
+            vec![extra_path.clone()],
+            move |_| ((), vec![extra.clone()]),
+            tx,
+            Some(started_tx),
+        );
+        loop_handle
+            .insert_source(rx, |_, _, _| {
+                changed.fetch_add(1, Ordering::SeqCst);
+            })
+            .unwrap();
+        started_rx.recv().unwrap();
+
+        // HACK: if we don't sleep, files might have the same mtime.
+        thread::sleep(Duration::from_millis(100));
+
+        sh.write_file(&extra_path, "b").unwrap();
+
+        event_loop
+            .dispatch(Duration::from_millis(750), &mut ())
+            .unwrap();
+
+        assert_eq!(changed.load(Ordering::SeqCst), 1);
+
+        // The extra path is still watched after a reload.
+        thread::sleep(Duration::from_millis(100));
+        sh.write_file(&extra_path, "c").unwrap();
+
+        event_loop
+            .dispatch(Duration::from_millis(750), &mut ())
+            .unwrap();
+
+        assert_eq!(changed.load(Ordering::SeqCst), 2);
+
+        // Deleting the extra file reloads, and so does bringing it back.
+        sh.remove_path(&extra_path).unwrap();
+
+        event_loop
+            .dispatch(Duration::from_millis(750), &mut ())
+            .unwrap();
+
+        assert_eq!(changed.load(Ordering::SeqCst), 3);
+
+        sh.write_file(&extra_path, "d").unwrap();
+
+        event_loop
+            .dispatch(Duration::from_millis(750), &mut ())
+            .unwrap();
+
+        assert_eq!(changed.load(Ordering::SeqCst), 4);
+    }
 }
@@ -19,9 +19,14 @@ pub struct Satellite {
     abstract_token: Option<RegistrationToken>,
     unix_token: Option<RegistrationToken>,
     to_main: Sender<ToMain>,
+    /// PID of the running xwayland-satellite process.
+    ///
+    /// All X11 windows show up as Wayland windows of this process.
+    pid: Option<i32>,
 }
 
 enum ToMain {
+    Spawned(i32),
     SetupWatch,
 }
 
@@ -29,6 +34,10 @@ impl Satellite {
     pub fn display_name(&self) -> &str {
         &self.x11.display_name
     }
+
+    pub fn pid(&self) -> Option<i32> {
+        self.pid
+    }
 }
 
 pub fn setup(state: &mut State) {
@@ -60,6 +69,11 @@ pub fn setup(state: &mut State) {
     event_loop
         .insert_source(rx, move |event, _, state| match event {
             calloop::channel::Event::Msg(msg) => match msg {
+                ToMain::Spawned(pid) => {
+                    if let Some(satellite) = &mut state.niri.satellite {
+                        satellite.pid = Some(pid);
+                    }
+                }
                 ToMain::SetupWatch => setup_watch(state),
             },
             calloop::channel::Event::Closed => (),
@@ -71,6 +85,7 @@ pub fn setup(state: &mut State) {
         abstract_token: None,
         unix_token: None,
         to_main,
+        pid: None,
     });
 
     setup_watch(state);
@@ -152,6 +167,9 @@ fn setup_watch(state: &mut State) {
         return;
     };
 
+    // xwayland-satellite is no longer running.
+    satellite.pid = None;
+
     let event_loop = &state.niri.event_loop;
 
     if let Some(token) = satellite.abstract_token.take() {
@@ -244,7 +262,7 @@ fn spawn(path: String, xwl: &Satellite) {
     let res = thread::Builder::new()
         .name("Xwl-s Spawner".to_owned())
         .spawn(move || {
-            spawn_and_wait(&path, process, abstract_fd, unix_fd);
+            spawn_and_wait(&path, process, abstract_fd, unix_fd, &to_main);
 
             // Once xwayland-satellite crashes or fails to spawn, re-establish our X11 socket watch
             // to try again next time.
@@ -257,7 +275,13 @@ fn spawn(path: String, xwl: &Satellite) {
     }
 }
 
-fn spawn_and_wait(path: &Path, mut process: Command, abstract_fd: OwnedFd, unix_fd: OwnedFd) {
+fn spawn_and_wait(
+    path: &Path,
+    mut process: Command,
+    abstract_fd: OwnedFd,
+    unix_fd: OwnedFd,
+    to_main: &Sender<ToMain>,
+) {
     let abstract_raw = abstract_fd.as_raw_fd();
     let unix_raw = unix_fd.as_raw_fd();
 
@@ -298,6 +322,8 @@ fn spawn_and_wait(path: &Path, mut process: Command, abstract_fd: OwnedFd, unix_
     drop(abstract_fd);
     drop(unix_fd);
 
+    let _ = to_main.send(ToMain::Spawned(child.id() as i32));
+
     let status = match child.wait() {
         Ok(status) => status,
         Err(err) => {
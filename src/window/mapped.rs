@@ -1,8 +1,8 @@
 use std::cell::{Cell, Ref, RefCell};
+use std::sync::Arc;
 use std::time::Duration;
 
 use niri_config::{Color, CornerRadius, GradientInterpolation, WindowRule};
-use smithay::backend::renderer::element::surface::render_elements_from_surface_tree;
 use smithay::backend::renderer::element::Kind;
 use smithay::backend::renderer::gles::GlesRenderer;
 use smithay::desktop::space::SpaceElement as _;
@@ -18,27 +18,34 @@ use smithay::wayland::seat::WaylandFocus;
 use smithay::wayland::shell::xdg::{SurfaceCachedState, ToplevelSurface};
 use wayland_backend::server::Credentials;
 
-use super::{ResolvedWindowRules, WindowRef};
+use super::{MatchContext, ResolvedWindowRules, WindowRef};
 use crate::handlers::KdeDecorationsModeState;
 use crate::layout::{
     ConfigureIntent, InteractiveResizeData, LayoutElement, LayoutElementRenderElement,
     LayoutElementRenderSnapshot,
 };
 use crate::niri_render_elements;
+use crate::protocols::background_effect::surface_blur_region;
+use crate::render_helpers::blur::{region_rects, BlurBuffer, BlurParams, BlurRenderElement};
 use crate::render_helpers::border::BorderRenderElement;
+use crate::render_helpers::color_transform::render_elements_from_surface_tree;
 use crate::render_helpers::offscreen::OffscreenData;
 use crate::render_helpers::renderer::NiriRenderer;
 use crate::render_helpers::snapshot::RenderSnapshot;
 use crate::render_helpers::solid_color::{SolidColorBuffer, SolidColorRenderElement};
 use crate::render_helpers::surface::render_snapshot_from_surface_tree;
 use crate::render_helpers::{BakedBuffer, RenderTarget, SplitElements};
+use crate::utils::color::{output_color_description, ColorDescription};
 use crate::utils::id::IdCounter;
 use crate::utils::transaction::Transaction;
 use crate::utils::{
-    get_credentials_for_surface, send_scale_transform, update_tiled_state, with_toplevel_role,
-    ResizeEdge,
+    get_credentials_for_surface, send_scale_transform, toplevel_extra, update_tiled_state,
+    with_toplevel_role, ResizeEdge,
 };
 
+/// Opacity of the black overlay on windows with an open modal dialog.
+const MODAL_DIM_ALPHA: f32 = 0.4;
+
 #[derive(Debug)]
 pub struct Mapped {
     pub window: Window,
@@ -89,15 +96,39 @@ pub struct Mapped {
     /// Whether this window is floating.
     is_floating: bool,
 
+    /// Whether this window is in the scratchpad.
+    is_in_scratchpad: bool,
+
+    /// Whether this window is sticky, i.e. shown on every workspace of its monitor.
+    is_sticky: bool,
+
     /// Whether this window is a target of a window cast.
     is_window_cast_target: bool,
 
+    /// Name of the named workspace that this window is on.
+    workspace_name: Option<String>,
+
+    /// Output that this window is on.
+    output: Option<Output>,
+
     /// Whether this window should ignore opacity set through window rules.
     ignore_opacity_window_rule: bool,
 
     /// Buffer to draw instead of the window when it should be blocked out.
     block_out_buffer: RefCell<SolidColorBuffer>,
 
+    /// Whether this window has an open modal dialog.
+    is_blocked_by_modal: bool,
+
+    /// Buffer to dim the window with while it has an open modal dialog.
+    modal_dim_buffer: RefCell<SolidColorBuffer>,
+
+    /// Dim with rounded corners, for windows with a corner radius.
+    modal_dim_rounded: RefCell<BorderRenderElement>,
+
+    /// Cached background blur behind the window.
+    blur: BlurBuffer,
+
     /// Whether the next configure should be animated, if the configured state changed.
     animate_next_configure: bool,
 
@@ -239,9 +270,20 @@ impl Mapped {
             is_focused: false,
             is_active_in_column: true,
             is_floating: false,
+            is_in_scratchpad: false,
+            is_sticky: false,
             is_window_cast_target: false,
+            workspace_name: None,
+            output: None,
             ignore_opacity_window_rule: false,
             block_out_buffer: RefCell::new(SolidColorBuffer::new((0., 0.), [0., 0., 0., 1.])),
+            is_blocked_by_modal: false,
+            modal_dim_buffer: RefCell::new(SolidColorBuffer::new(
+                (0., 0.),
+                [0., 0., 0., MODAL_DIM_ALPHA],
+            )),
+            modal_dim_rounded: RefCell::new(BorderRenderElement::empty()),
+            blur: BlurBuffer::new(),
             animate_next_configure: false,
             animate_serials: Vec::new(),
             animation_snapshot: None,
@@ -261,10 +303,10 @@ impl Mapped {
     }
 
     /// Recomputes the resolved window rules and returns whether they changed.
-    pub fn recompute_window_rules(&mut self, rules: &[WindowRule], is_at_startup: bool) -> bool {
+    pub fn recompute_window_rules(&mut self, rules: &[WindowRule], ctx: MatchContext) -> bool {
         self.need_to_recompute_rules = false;
 
-        let new_rules = ResolvedWindowRules::compute(rules, WindowRef::Mapped(self), is_at_startup);
+        let new_rules = ResolvedWindowRules::compute(rules, WindowRef::Mapped(self), ctx);
         if new_rules == self.rules {
             return false;
         }
@@ -282,13 +324,13 @@ impl Mapped {
     pub fn recompute_window_rules_if_needed(
         &mut self,
         rules: &[WindowRule],
-        is_at_startup: bool,
+        ctx: MatchContext,
     ) -> bool {
         if !self.need_to_recompute_rules {
             return false;
         }
 
-        self.recompute_window_rules(rules, is_at_startup)
+        self.recompute_window_rules(rules, ctx)
     }
 
     pub fn set_needs_configure(&mut self) {
@@ -307,6 +349,10 @@ impl Mapped {
         self.offscreen_data.borrow()
     }
 
+    pub fn blur(&self) -> &BlurBuffer {
+        &self.blur
+    }
+
     pub fn is_focused(&self) -> bool {
         self.is_focused
     }
@@ -319,10 +365,104 @@ impl Mapped {
         self.is_floating
     }
 
+    pub fn is_in_scratchpad(&self) -> bool {
+        self.is_in_scratchpad
+    }
+
+    pub fn is_sticky(&self) -> bool {
+        self.is_sticky
+    }
+
+    /// Returns whether the window has this app ID, with `None` matching any window.
+    pub fn has_app_id(&self, app_id: Option<&str>) -> bool {
+        let Some(app_id) = app_id else {
+            return true;
+        };
+
+        with_toplevel_role(self.toplevel(), |role| {
+            role.app_id.as_deref() == Some(app_id)
+        })
+    }
+
     pub fn is_window_cast_target(&self) -> bool {
         self.is_window_cast_target
     }
 
+    /// Returns whether this window is a modal dialog.
+    pub fn is_modal(&self) -> bool {
+        toplevel_extra(self.toplevel().wl_surface()).is_modal
+    }
+
+    pub fn is_blocked_by_modal(&self) -> bool {
+        self.is_blocked_by_modal
+    }
+
+    /// Sets whether this window has an open modal dialog, returning whether it changed.
+    pub fn set_blocked_by_modal(&mut self, value: bool) -> bool {
+        let changed = self.is_blocked_by_modal != value;
+        self.is_blocked_by_modal = value;
+        changed
+    }
+
+    fn render_modal_dim<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        location: Point<f64, Logical>,
+        scale: Scale<f64>,
+        alpha: f32,
+    ) -> Option<LayoutElementRenderElement<R>> {
+        if !self.is_blocked_by_modal {
+            return None;
+        }
+
+        let size = self.window.geometry().size.to_f64();
+
+        // Match the rounded corners of the window, like for blocked-out windows.
+        let radius = self.rules.geometry_corner_radius.unwrap_or_default();
+        if radius != CornerRadius::default() && BorderRenderElement::has_shader(renderer) {
+            let radius = radius.fit_to(size.w as f32, size.h as f32);
+            let color = Color::new_unpremul(0., 0., 0., MODAL_DIM_ALPHA);
+
+            let mut elem = self.modal_dim_rounded.borrow_mut();
+            elem.update(
+                size,
+                Rectangle::from_size(size),
+                GradientInterpolation::default(),
+                color,
+                color,
+                0.,
+                Rectangle::from_size(size),
+                0.,
+                radius,
+                scale.x as f32,
+                alpha,
+            );
+            return Some(elem.clone().with_location(location).into());
+        }
+
+        let mut buffer = self.modal_dim_buffer.borrow_mut();
+        buffer.resize(size);
+        let elem =
+            SolidColorRenderElement::from_buffer(&buffer, location, alpha, Kind::Unspecified);
+        Some(elem.into())
+    }
+
+    pub fn workspace_name(&self) -> Option<&str> {
+        self.workspace_name.as_deref()
+    }
+
+    pub fn output(&self) -> Option<&Output> {
+        self.output.as_ref()
+    }
+
+    /// Returns the color description that the window surfaces should be converted into.
+    fn target_color_description(&self) -> Arc<ColorDescription> {
+        match &self.output {
+            Some(output) => output_color_description(output),
+            None => Arc::new(ColorDescription::srgb()),
+        }
+    }
+
     pub fn toggle_ignore_opacity_window_rule(&mut self) {
         self.ignore_opacity_window_rule = !self.ignore_opacity_window_rule;
     }
@@ -576,6 +716,7 @@ impl LayoutElement for Mapped {
             rv.normal.push(elem.into());
         } else {
             let buf_pos = location - self.window.geometry().loc.to_f64();
+            let color_target = self.target_color_description();
 
             let surface = self.toplevel().wl_surface();
             for (popup, popup_offset) in PopupManager::popups_for_surface(surface) {
@@ -588,17 +729,21 @@ impl LayoutElement for Mapped {
                     scale,
                     alpha,
                     Kind::Unspecified,
+                    &color_target,
                 ));
             }
 
-            rv.normal = render_elements_from_surface_tree(
+            rv.normal
+                .extend(self.render_modal_dim(renderer, location, scale, alpha));
+            rv.normal.extend(render_elements_from_surface_tree(
                 renderer,
                 surface,
                 buf_pos.to_physical_precise_round(scale),
                 scale,
                 alpha,
                 Kind::Unspecified,
-            );
+                &color_target,
+            ));
         }
 
         rv
@@ -621,14 +766,18 @@ impl LayoutElement for Mapped {
         } else {
             let buf_pos = location - self.window.geometry().loc.to_f64();
             let surface = self.toplevel().wl_surface();
-            render_elements_from_surface_tree(
+            let dim = self.render_modal_dim(renderer, location, scale, alpha);
+            let mut rv: Vec<_> = dim.into_iter().collect();
+            rv.extend(render_elements_from_surface_tree(
                 renderer,
                 surface,
                 buf_pos.to_physical_precise_round(scale),
                 scale,
                 alpha,
                 Kind::Unspecified,
-            )
+                &self.target_color_description(),
+            ));
+            rv
         }
     }
 
@@ -646,6 +795,7 @@ impl LayoutElement for Mapped {
             let mut rv = vec![];
 
             let buf_pos = location - self.window.geometry().loc.to_f64();
+            let color_target = self.target_color_description();
             let surface = self.toplevel().wl_surface();
             for (popup, popup_offset) in PopupManager::popups_for_surface(surface) {
                 let offset = self.window.geometry().loc + popup_offset - popup.geometry().loc;
@@ -657,6 +807,7 @@ impl LayoutElement for Mapped {
                     scale,
                     alpha,
                     Kind::Unspecified,
+                    &color_target,
                 ));
             }
 
@@ -664,6 +815,42 @@ impl LayoutElement for Mapped {
         }
     }
 
+    fn render_blur<R: NiriRenderer>(
+        &self,
+        renderer: &mut R,
+        area: Rectangle<f64, Logical>,
+        radius: CornerRadius,
+        scale: Scale<f64>,
+        target: RenderTarget,
+    ) -> Option<BlurRenderElement> {
+        let rule = self.rules.blur;
+        if rule.off {
+            return None;
+        }
+
+        // Without a window rule, follow the region requested by the client.
+        let region = if rule.on {
+            None
+        } else {
+            let region = with_states(self.toplevel().wl_surface(), surface_blur_region)?;
+            let offset = self.window.geometry().loc;
+            let rects = region_rects(&region)
+                .into_iter()
+                .map(|rect| Rectangle::new((rect.loc - offset).to_f64(), rect.size.to_f64()));
+            Some(rects.collect())
+        };
+
+        self.blur.render(
+            renderer.as_gles_renderer(),
+            area,
+            radius,
+            region,
+            BlurParams::from_rule(&rule),
+            scale,
+            target,
+        )
+    }
+
     fn request_size(
         &mut self,
         size: Size<i32, Logical>,
@@ -671,6 +858,8 @@ impl LayoutElement for Mapped {
         animate: bool,
         transaction: Option<Transaction>,
     ) {
+        let was_fullscreen = self.is_pending_fullscreen();
+
         // Going into real fullscreen resets windowed fullscreen.
         if is_fullscreen {
             self.is_pending_windowed_fullscreen = false;
@@ -697,6 +886,8 @@ impl LayoutElement for Mapped {
             self.animate_next_configure = true;
         }
 
+        self.need_to_recompute_rules |= was_fullscreen != self.is_pending_fullscreen();
+
         self.request_size_once = None;
 
         // Store the transaction regardless of whether the size changed. This is because with 3+
@@ -877,6 +1068,28 @@ impl LayoutElement for Mapped {
         self.need_to_recompute_rules |= changed;
     }
 
+    fn set_in_scratchpad(&mut self, in_scratchpad: bool) {
+        let changed = self.is_in_scratchpad != in_scratchpad;
+        self.is_in_scratchpad = in_scratchpad;
+        self.need_to_recompute_rules |= changed;
+    }
+
+    fn set_sticky(&mut self, sticky: bool) {
+        self.is_sticky = sticky;
+    }
+
+    fn set_workspace(&mut self, name: Option<&str>, output: Option<&Output>) {
+        if self.workspace_name.as_deref() != name {
+            self.workspace_name = name.map(String::from);
+            self.need_to_recompute_rules = true;
+        }
+
+        if self.output.as_ref() != output {
+            self.output = output.cloned();
+            self.need_to_recompute_rules = true;
+        }
+    }
+
     fn set_bounds(&self, bounds: Size<i32, Logical>) {
         self.toplevel().with_pending_state(|state| {
             state.bounds = Some(bounds);
@@ -1144,6 +1357,7 @@ impl LayoutElement for Mapped {
             return;
         }
 
+        let was_fullscreen = self.is_pending_fullscreen();
         self.is_pending_windowed_fullscreen = value;
 
         // Set the fullscreen state to match.
@@ -1160,6 +1374,8 @@ impl LayoutElement for Mapped {
 
         // Make sure we receive a commit later to update self.is_windowed_fullscreen.
         self.needs_configure = true;
+
+        self.need_to_recompute_rules |= was_fullscreen != self.is_pending_fullscreen();
     }
 
     fn is_child_of(&self, parent: &Self) -> bool {
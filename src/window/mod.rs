@@ -1,10 +1,11 @@
 use std::cmp::{max, min};
 
 use niri_config::{
-    BlockOutFrom, BorderRule, CornerRadius, FloatingPosition, Match, PresetSize, ShadowRule,
-    TabIndicatorRule, WindowRule,
+    BlockOutFrom, BlurRule, BorderRule, CornerRadius, FloatingPosition, Match, PresetSize,
+    ShadowRule, TabIndicatorRule, WindowRule,
 };
 use niri_ipc::ColumnDisplay;
+use smithay::output::Output;
 use smithay::reexports::wayland_protocols::xdg::shell::server::xdg_toplevel;
 use smithay::utils::{Logical, Size};
 use smithay::wayland::compositor::with_states;
@@ -12,11 +13,18 @@ use smithay::wayland::shell::xdg::{
     SurfaceCachedState, ToplevelSurface, XdgToplevelSurfaceRoleAttributes,
 };
 
-use crate::utils::with_toplevel_role;
+use crate::layout::LayoutElement as _;
+use crate::utils::{
+    get_credentials_for_surface, output_matches_name, toplevel_extra, with_toplevel_role,
+    ToplevelExtra,
+};
 
 pub mod mapped;
 pub use mapped::Mapped;
 
+pub mod session;
+pub use session::{SessionPlacement, SessionRestore};
+
 pub mod unmapped;
 pub use unmapped::{InitialConfigureState, Unmapped};
 
@@ -27,6 +35,15 @@ pub enum WindowRef<'a> {
     Mapped(&'a Mapped),
 }
 
+/// Compositor state that window rules are matched against.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MatchContext {
+    /// Whether niri has just started.
+    pub is_at_startup: bool,
+    /// PID of the running xwayland-satellite, whose windows are X11 windows.
+    pub satellite_pid: Option<i32>,
+}
+
 /// Rules fully resolved for a window.
 #[derive(Debug, PartialEq)]
 pub struct ResolvedWindowRules {
@@ -65,6 +82,9 @@ pub struct ResolvedWindowRules {
     /// Whether the window should open floating.
     pub open_floating: Option<bool>,
 
+    /// Whether the window should open sticky.
+    pub open_sticky: Option<bool>,
+
     /// Whether the window should open focused.
     pub open_focused: Option<bool>,
 
@@ -85,6 +105,8 @@ pub struct ResolvedWindowRules {
     pub shadow: ShadowRule,
     /// Tab indicator overrides.
     pub tab_indicator: TabIndicatorRule,
+    /// Background blur overrides.
+    pub blur: BlurRule,
 
     /// Whether or not to draw the border with a solid background.
     ///
@@ -94,6 +116,15 @@ pub struct ResolvedWindowRules {
     /// Extra opacity to draw this window with.
     pub opacity: Option<f32>,
 
+    /// Override for how much to dim this window while inactive.
+    pub inactive_dim: Option<f64>,
+
+    /// Override for the saturation of this window while inactive.
+    pub inactive_saturation: Option<f64>,
+
+    /// Override for the brightness offset of this window while inactive.
+    pub inactive_brightness: Option<f64>,
+
     /// Corner radius to assume this window has.
     pub geometry_corner_radius: Option<CornerRadius>,
 
@@ -109,11 +140,17 @@ pub struct ResolvedWindowRules {
     /// Whether to enable VRR on this window's primary output if it is on-demand.
     pub variable_refresh_rate: Option<bool>,
 
+    /// Whether to allow tearing presentation for this window when it asks for it.
+    pub allow_tearing: Option<bool>,
+
     /// Multiplier for all scroll events sent to this window.
     pub scroll_factor: Option<f64>,
 
     /// Override whether to set the Tiled xdg-toplevel state on the window.
     pub tiled_state: Option<bool>,
+
+    /// Keyboard layout to use when this window is first focused.
+    pub default_keyboard_layout: Option<String>,
 }
 
 impl<'a> WindowRef<'a> {
@@ -160,12 +197,53 @@ impl<'a> WindowRef<'a> {
         }
     }
 
+    pub fn is_in_scratchpad(self) -> bool {
+        match self {
+            WindowRef::Unmapped(_) => false,
+            WindowRef::Mapped(mapped) => mapped.is_in_scratchpad(),
+        }
+    }
+
     pub fn is_window_cast_target(self) -> bool {
         match self {
             WindowRef::Unmapped(_) => false,
             WindowRef::Mapped(mapped) => mapped.is_window_cast_target(),
         }
     }
+
+    pub fn pid(self) -> Option<i32> {
+        match self {
+            WindowRef::Unmapped(unmapped) => {
+                get_credentials_for_surface(unmapped.toplevel().wl_surface()).map(|c| c.pid)
+            }
+            WindowRef::Mapped(mapped) => mapped.credentials().map(|c| c.pid),
+        }
+    }
+
+    pub fn is_fullscreen(self) -> bool {
+        match self {
+            // Same as is_floating(), this matcher would make a cycle with the open-fullscreen rule.
+            WindowRef::Unmapped(_) => false,
+            WindowRef::Mapped(mapped) => mapped.is_pending_fullscreen(),
+        }
+    }
+
+    /// Returns the named workspace that the window is on.
+    ///
+    /// Unmapped windows aren't on any workspace yet.
+    pub fn workspace_name(self) -> Option<&'a str> {
+        match self {
+            WindowRef::Unmapped(_) => None,
+            WindowRef::Mapped(mapped) => mapped.workspace_name(),
+        }
+    }
+
+    pub fn output(self) -> Option<&'a Output> {
+        match self {
+            WindowRef::Unmapped(_) => None,
+            WindowRef::Mapped(mapped) => mapped.output(),
+        }
+    }
 }
 
 impl ResolvedWindowRules {
@@ -180,6 +258,7 @@ impl ResolvedWindowRules {
             open_maximized: None,
             open_fullscreen: None,
             open_floating: None,
+            open_sticky: None,
             open_focused: None,
             min_width: None,
             min_height: None,
@@ -225,23 +304,37 @@ impl ResolvedWindowRules {
                 inactive_gradient: None,
                 urgent_gradient: None,
             },
+            blur: BlurRule {
+                off: false,
+                on: false,
+                passes: None,
+                radius: None,
+            },
             draw_border_with_background: None,
             opacity: None,
+            inactive_dim: None,
+            inactive_saturation: None,
+            inactive_brightness: None,
             geometry_corner_radius: None,
             clip_to_geometry: None,
             baba_is_float: None,
             block_out_from: None,
             variable_refresh_rate: None,
+            allow_tearing: None,
             scroll_factor: None,
             tiled_state: None,
+            default_keyboard_layout: None,
         }
     }
 
-    pub fn compute(rules: &[WindowRule], window: WindowRef, is_at_startup: bool) -> Self {
+    pub fn compute(rules: &[WindowRule], window: WindowRef, ctx: MatchContext) -> Self {
         let _span = tracy_client::span!("ResolvedWindowRules::compute");
 
         let mut resolved = ResolvedWindowRules::empty();
 
+        // Must be fetched outside with_toplevel_role() which locks the surface state.
+        let extra = toplevel_extra(window.toplevel().wl_surface());
+
         with_toplevel_role(window.toplevel(), |role| {
             // Ensure server_pending like in Smithay's with_pending_state().
             if role.server_pending.is_none() {
@@ -254,12 +347,12 @@ impl ResolvedWindowRules {
             for rule in rules {
                 let matches = |m: &Match| {
                     if let Some(at_startup) = m.at_startup {
-                        if at_startup != is_at_startup {
+                        if at_startup != ctx.is_at_startup {
                             return false;
                         }
                     }
 
-                    window_matches(window, role, m)
+                    window_matches(window, role, &extra, ctx, m)
                 };
 
                 if !(rule.matches.is_empty() || rule.matches.iter().any(matches)) {
@@ -306,6 +399,10 @@ impl ResolvedWindowRules {
                     resolved.open_floating = Some(x);
                 }
 
+                if let Some(x) = rule.open_sticky {
+                    resolved.open_sticky = Some(x);
+                }
+
                 if let Some(x) = rule.open_focused {
                     resolved.open_focused = Some(x);
                 }
@@ -327,6 +424,7 @@ impl ResolvedWindowRules {
                 resolved.border.merge_with(&rule.border);
                 resolved.shadow.merge_with(&rule.shadow);
                 resolved.tab_indicator.merge_with(&rule.tab_indicator);
+                resolved.blur.merge_with(&rule.blur);
 
                 if let Some(x) = rule.draw_border_with_background {
                     resolved.draw_border_with_background = Some(x);
@@ -334,6 +432,15 @@ impl ResolvedWindowRules {
                 if let Some(x) = rule.opacity {
                     resolved.opacity = Some(x);
                 }
+                if let Some(x) = rule.inactive_dim {
+                    resolved.inactive_dim = Some(x.0);
+                }
+                if let Some(x) = rule.inactive_saturation {
+                    resolved.inactive_saturation = Some(x.0);
+                }
+                if let Some(x) = rule.inactive_brightness {
+                    resolved.inactive_brightness = Some(x.0);
+                }
                 if let Some(x) = rule.geometry_corner_radius {
                     resolved.geometry_corner_radius = Some(x);
                 }
@@ -349,12 +456,18 @@ impl ResolvedWindowRules {
                 if let Some(x) = rule.variable_refresh_rate {
                     resolved.variable_refresh_rate = Some(x);
                 }
+                if let Some(x) = rule.allow_tearing {
+                    resolved.allow_tearing = Some(x);
+                }
                 if let Some(x) = rule.scroll_factor {
                     resolved.scroll_factor = Some(x.0);
                 }
                 if let Some(x) = rule.tiled_state {
                     resolved.tiled_state = Some(x);
                 }
+                if let Some(x) = rule.default_keyboard_layout.as_deref() {
+                    resolved.default_keyboard_layout = Some(x.to_owned());
+                }
             }
 
             resolved.open_on_output = open_on_output.map(|x| x.to_owned());
@@ -409,12 +522,17 @@ impl ResolvedWindowRules {
     }
 
     pub fn compute_open_floating(&self, toplevel: &ToplevelSurface) -> bool {
+        // Sticky windows are always floating.
+        if self.open_sticky == Some(true) {
+            return true;
+        }
+
         if let Some(res) = self.open_floating {
             return res;
         }
 
-        // Windows with a parent (usually dialogs) open as floating by default.
-        if toplevel.parent().is_some() {
+        // Windows with a parent (usually dialogs) and modal dialogs open as floating by default.
+        if toplevel.parent().is_some() || toplevel_extra(toplevel.wl_surface()).is_modal {
             return true;
         }
 
@@ -430,7 +548,13 @@ impl ResolvedWindowRules {
     }
 }
 
-fn window_matches(window: WindowRef, role: &XdgToplevelSurfaceRoleAttributes, m: &Match) -> bool {
+fn window_matches(
+    window: WindowRef,
+    role: &XdgToplevelSurfaceRoleAttributes,
+    extra: &ToplevelExtra,
+    ctx: MatchContext,
+    m: &Match,
+) -> bool {
     // Must be ensured by the caller.
     let server_pending = role.server_pending.as_ref().unwrap();
 
@@ -486,11 +610,76 @@ fn window_matches(window: WindowRef, role: &XdgToplevelSurfaceRoleAttributes, m:
         }
     }
 
+    if let Some(scratchpad) = m.scratchpad {
+        if window.is_in_scratchpad() != scratchpad {
+            return false;
+        }
+    }
+
     if let Some(is_window_cast_target) = m.is_window_cast_target {
         if window.is_window_cast_target() != is_window_cast_target {
             return false;
         }
     }
 
+    if let Some(tag_re) = &m.tag {
+        let Some(tag) = &extra.tag else {
+            return false;
+        };
+        if !tag_re.0.is_match(tag) {
+            return false;
+        }
+    }
+
+    if let Some(pid) = m.pid {
+        if window.pid() != Some(pid) {
+            return false;
+        }
+    }
+
+    if let Some(is_xwayland) = m.is_xwayland {
+        let is_satellite = ctx.satellite_pid.is_some() && window.pid() == ctx.satellite_pid;
+        if is_satellite != is_xwayland {
+            return false;
+        }
+    }
+
+    if let Some(is_fullscreen) = m.is_fullscreen {
+        if window.is_fullscreen() != is_fullscreen {
+            return false;
+        }
+    }
+
+    if let Some(has_parent) = m.has_parent {
+        if role.parent.is_some() != has_parent {
+            return false;
+        }
+    }
+
+    if let Some(is_modal) = m.is_modal {
+        if extra.is_modal != is_modal {
+            return false;
+        }
+    }
+
+    if let Some(name) = &m.on_workspace {
+        let Some(workspace_name) = window.workspace_name() else {
+            return false;
+        };
+        // Workspace names are case-insensitive.
+        if !workspace_name.eq_ignore_ascii_case(name) {
+            return false;
+        }
+    }
+
+    if let Some(name) = &m.on_output {
+        let Some(output) = window.output() else {
+            return false;
+        };
+        if !output_matches_name(output, name) {
+            return false;
+        }
+    }
+
     true
 }
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+
+use niri_config::{FloatOrInt, FloatingPosition, PresetSize, RelativeTo};
+use niri_ipc::{ColumnDisplay, Session, SessionColumnWidth, SessionWindow};
+
+use super::{MappedId, ResolvedWindowRules};
+
+/// Windows from a restored session that are waiting to open.
+#[derive(Debug, Default)]
+pub struct SessionRestore {
+    /// Saved windows that haven't been matched yet, in layout order.
+    pending: Vec<(SessionWindow, SessionPlacement)>,
+    /// Windows that were already placed into saved columns, by column key.
+    columns: HashMap<usize, Vec<MappedId>>,
+}
+
+/// Where a window from a restored session should go.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionPlacement {
+    /// Name of the output that the workspace was on.
+    pub output: Option<String>,
+    /// Name of the workspace.
+    pub workspace_name: Option<String>,
+    /// Index of the workspace on its output, starting from 0.
+    pub workspace_idx: usize,
+    /// Placement within the workspace.
+    pub kind: SessionPlacementKind,
+}
+
+/// Placement of a window within its workspace.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionPlacementKind {
+    Tiled {
+        /// Key of the saved column, unique within the session.
+        column: usize,
+        display: ColumnDisplay,
+        width: SessionColumnWidth,
+        is_full_width: bool,
+        /// Fixed window height, if any.
+        height: Option<f64>,
+    },
+    Floating {
+        /// Tile position relative to the working area.
+        pos: (f64, f64),
+        /// Window size.
+        size: (f64, f64),
+    },
+}
+
+impl SessionRestore {
+    pub fn new(session: Session) -> Self {
+        let mut pending = Vec::new();
+        let mut column = 0;
+
+        for ws in session.workspaces {
+            let output = ws.output;
+            let workspace_name = ws.name;
+            let workspace_idx = usize::from(ws.idx.saturating_sub(1));
+
+            for col in ws.columns {
+                for tile in col.tiles {
+                    let kind = SessionPlacementKind::Tiled {
+                        column,
+                        display: col.display,
+                        width: col.width,
+                        is_full_width: col.is_full_width,
+                        height: tile.height,
+                    };
+                    let placement = SessionPlacement {
+                        output: output.clone(),
+                        workspace_name: workspace_name.clone(),
+                        workspace_idx,
+                        kind,
+                    };
+                    pending.push((tile.window, placement));
+                }
+                column += 1;
+            }
+
+            for win in ws.floating {
+                let kind = SessionPlacementKind::Floating {
+                    pos: win.pos,
+                    size: win.size,
+                };
+                let placement = SessionPlacement {
+                    output: output.clone(),
+                    workspace_name: workspace_name.clone(),
+                    workspace_idx,
+                    kind,
+                };
+                pending.push((win.window, placement));
+            }
+        }
+
+        Self {
+            pending,
+            columns: HashMap::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Takes the placement for a newly opened window, if the session has one.
+    ///
+    /// Prefers a saved window with the same title, and otherwise falls back to the first saved
+    /// window of the same application.
+    pub fn take_placement(
+        &mut self,
+        app_id: Option<&str>,
+        title: Option<&str>,
+    ) -> Option<SessionPlacement> {
+        let same_app = |win: &SessionWindow| win.app_id.as_deref() == app_id;
+
+        let idx = self
+            .pending
+            .iter()
+            .position(|(win, _)| same_app(win) && win.title.as_deref() == title)
+            .or_else(|| self.pending.iter().position(|(win, _)| same_app(win)))?;
+
+        Some(self.pending.remove(idx).1)
+    }
+
+    /// Windows already placed into the saved column.
+    pub fn column_windows(&self, column: usize) -> &[MappedId] {
+        self.columns.get(&column).map_or(&[], |ids| &ids[..])
+    }
+
+    /// Records that a window was placed into the saved column.
+    pub fn add_to_column(&mut self, column: usize, id: MappedId) {
+        self.columns.entry(column).or_default().push(id);
+    }
+}
+
+impl SessionPlacement {
+    /// Overrides the opening rules of the window to match the placement.
+    pub fn apply_to_rules(&self, rules: &mut ResolvedWindowRules) {
+        rules.open_on_output = self.output.clone();
+        rules.open_on_workspace = self.workspace_name.clone();
+        rules.open_fullscreen = Some(false);
+
+        match self.kind {
+            SessionPlacementKind::Tiled {
+                display,
+                width,
+                is_full_width,
+                height,
+                ..
+            } => {
+                let width = match width {
+                    SessionColumnWidth::Proportion(proportion) => {
+                        PresetSize::Proportion(proportion)
+                    }
+                    SessionColumnWidth::Fixed(width) => PresetSize::Fixed(width.round() as i32),
+                };
+
+                rules.open_floating = Some(false);
+                rules.open_sticky = Some(false);
+                rules.open_maximized = Some(is_full_width);
+                rules.default_width = Some(Some(width));
+                rules.default_height = Some(height.map(|h| PresetSize::Fixed(h.round() as i32)));
+                rules.default_column_display = Some(display);
+            }
+            SessionPlacementKind::Floating { pos, size } => {
+                rules.open_floating = Some(true);
+                rules.open_maximized = Some(false);
+                rules.default_width = Some(Some(PresetSize::Fixed(size.0.round() as i32)));
+                rules.default_height = Some(Some(PresetSize::Fixed(size.1.round() as i32)));
+                rules.default_floating_position = Some(FloatingPosition {
+                    x: FloatOrInt(pos.0),
+                    y: FloatOrInt(pos.1),
+                    relative_to: RelativeTo::TopLeft,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use niri_ipc::{SessionColumn, SessionTile, SessionWorkspace};
+
+    use super::*;
+
+    fn window(app_id: &str, title: &str) -> SessionWindow {
+        SessionWindow {
+            app_id: Some(app_id.to_owned()),
+            title: Some(title.to_owned()),
+        }
+    }
+
+    fn session() -> Session {
+        let tile = |app_id, title| SessionTile {
+            window: window(app_id, title),
+            height: None,
+        };
+
+        Session {
+            workspaces: vec![SessionWorkspace {
+                idx: 2,
+                name: None,
+                output: Some(String::from("DP-1")),
+                columns: vec![
+                    SessionColumn {
+                        display: ColumnDisplay::Tabbed,
+                        width: SessionColumnWidth::Proportion(0.5),
+                        is_full_width: false,
+                        tiles: vec![tile("term", "vim"), tile("term", "htop")],
+                    },
+                    SessionColumn {
+                        display: ColumnDisplay::Normal,
+                        width: SessionColumnWidth::Fixed(800.),
+                        is_full_width: false,
+                        tiles: vec![tile("browser", "docs")],
+                    },
+                ],
+                floating: vec![],
+            }],
+        }
+    }
+
+    #[test]
+    fn prefers_matching_title() {
+        let mut restore = SessionRestore::new(session());
+
+        let placement = restore.take_placement(Some("term"), Some("htop")).unwrap();
+        assert_eq!(placement.workspace_idx, 1);
+        assert!(matches!(
+            placement.kind,
+            SessionPlacementKind::Tiled { column: 0, .. }
+        ));
+
+        // Falls back to the remaining window of the same app.
+        let placement = restore.take_placement(Some("term"), Some("zsh")).unwrap();
+        assert!(matches!(
+            placement.kind,
+            SessionPlacementKind::Tiled { column: 0, .. }
+        ));
+
+        assert_eq!(restore.take_placement(Some("term"), None), None);
+        assert!(!restore.is_empty());
+    }
+
+    #[test]
+    fn columns_get_distinct_keys() {
+        let mut restore = SessionRestore::new(session());
+
+        let placement = restore.take_placement(Some("browser"), None).unwrap();
+        assert!(matches!(
+            placement.kind,
+            SessionPlacementKind::Tiled { column: 1, .. }
+        ));
+    }
+}
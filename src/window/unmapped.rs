@@ -4,7 +4,7 @@ use smithay::output::Output;
 use smithay::wayland::shell::xdg::ToplevelSurface;
 use smithay::wayland::xdg_activation::XdgActivationTokenData;
 
-use super::ResolvedWindowRules;
+use super::{ResolvedWindowRules, SessionPlacement};
 
 #[derive(Debug)]
 pub struct Unmapped {
@@ -64,6 +64,9 @@ pub enum InitialConfigureState {
 
         /// Workspace to open this window on.
         workspace_name: Option<String>,
+
+        /// Placement from a restored session.
+        session_placement: Option<SessionPlacement>,
     },
 }
 
@@ -0,0 +1,82 @@
+// Your shader must contain one function (see the bottom of this file).
+//
+// It should not contain any uniform definitions or anything else, as niri
+// provides them for you.
+//
+// All symbols defined by niri will have a niri_ prefix, so don't use it for
+// your own variables and functions.
+
+// The function that you must define looks like this:
+vec4 post_process_color(vec3 coords_geo, vec3 size_geo) {
+    vec4 color = /* ...compute the color... */;
+    return color;
+}
+
+// It takes as input:
+//
+// * coords_geo: coordinates of the current pixel relative to the output.
+//
+// These are homogeneous (the Z component is equal to 1) and scaled in such a
+// way that the 0 to 1 coordinates cover the whole output.
+//
+// * size_geo: size of the output in logical pixels.
+//
+// It is homogeneous (the Z component is equal to 1).
+//
+// The function must return the color of the pixel (with premultiplied alpha).
+
+// Now let's go over the uniforms that niri defines.
+//
+// You should only rely on the uniforms documented here. Any other uniforms can
+// change or be removed without notice.
+
+// The texture with everything that niri rendered on the output, except for the
+// cursor, which is drawn on top without post-processing.
+uniform sampler2D niri_tex;
+
+// Matrix that converts output coordinates into the texture coordinates.
+uniform mat3 niri_geo_to_tex;
+
+// Size of the output in logical pixels.
+uniform vec2 niri_output_size;
+
+// Cursor position relative to the output in logical pixels.
+uniform vec2 niri_cursor_position;
+
+// Now let's look at some examples. You can copy everything below this line
+// into your custom-shader to experiment.
+
+// Example: make everything grayscale.
+vec4 grayscale(vec3 coords_geo, vec3 size_geo) {
+    vec3 coords_tex = niri_geo_to_tex * coords_geo;
+    vec4 color = texture2D(niri_tex, coords_tex.st);
+
+    // Colors are premultiplied, so the luminance stays premultiplied too.
+    float luminance = dot(color.rgb, vec3(0.2126, 0.7152, 0.0722));
+    return vec4(vec3(luminance), color.a);
+}
+
+// Example: darken the corners of the output.
+vec4 vignette(vec3 coords_geo, vec3 size_geo) {
+    vec3 coords_tex = niri_geo_to_tex * coords_geo;
+    vec4 color = texture2D(niri_tex, coords_tex.st);
+
+    float dist = distance(coords_geo.xy, vec2(0.5));
+    return color * (1.0 - smoothstep(0.4, 0.8, dist) * 0.6);
+}
+
+// Example: dim everything except for a spotlight around the cursor.
+vec4 spotlight(vec3 coords_geo, vec3 size_geo) {
+    vec3 coords_tex = niri_geo_to_tex * coords_geo;
+    vec4 color = texture2D(niri_tex, coords_tex.st);
+
+    float dist = distance(coords_geo.xy * size_geo.xy, niri_cursor_position);
+    float dim = smoothstep(200.0, 300.0, dist) * 0.7;
+    return vec4(color.rgb * (1.0 - dim), color.a);
+}
+
+// This is the function that you must define.
+vec4 post_process_color(vec3 coords_geo, vec3 size_geo) {
+    // You can pick one of the example functions or write your own.
+    return vignette(coords_geo, size_geo);
+}
@@ -0,0 +1,111 @@
+// Your shader must contain one function (see the bottom of this file).
+//
+// It should not contain any uniform definitions or anything else, as niri
+// provides them for you.
+//
+// All symbols defined by niri will have a niri_ prefix, so don't use it for
+// your own variables and functions.
+
+// The function that you must define looks like this:
+vec4 workspace_switch_color(vec3 coords_geo, vec3 size_geo) {
+    vec4 color = /* ...compute the color... */;
+    return color;
+}
+
+// It takes as input:
+//
+// * coords_geo: coordinates of the current pixel relative to the output.
+//
+// These are homogeneous (the Z component is equal to 1) and scaled in such a
+// way that the 0 to 1 coordinates cover the whole output.
+//
+// * size_geo: size of the output in logical pixels.
+//
+// It is homogeneous (the Z component is equal to 1).
+//
+// The function must return the color of the pixel (with premultiplied alpha).
+
+// Now let's go over the uniforms that niri defines.
+//
+// You should only rely on the uniforms documented here. Any other uniforms can
+// change or be removed without notice.
+
+// The texture of the upper of the two workspaces in view.
+uniform sampler2D niri_tex_prev;
+
+// Matrix that converts output coordinates into the upper workspace texture
+// coordinates, as if that workspace filled the output.
+uniform mat3 niri_geo_to_tex_prev;
+
+// The texture of the lower of the two workspaces in view.
+uniform sampler2D niri_tex_next;
+
+// Matrix that converts output coordinates into the lower workspace texture
+// coordinates, as if that workspace filled the output.
+uniform mat3 niri_geo_to_tex_next;
+
+// Progress of the switch between the two workspaces.
+//
+// Goes from 0 with the upper workspace in view to 1 with the lower workspace
+// in view. Follows the workspace-switch animation and touchpad gestures in
+// either direction.
+uniform float niri_progress;
+
+// Size of the output in logical pixels.
+uniform vec2 niri_output_size;
+
+// Cursor position relative to the output in logical pixels.
+uniform vec2 niri_cursor_position;
+
+// Now let's look at some examples. You can copy everything below this line
+// into your custom-shader to experiment.
+
+// Samples a workspace texture, returning transparency outside of it.
+vec4 sample_workspace(sampler2D tex, mat3 geo_to_tex, vec3 coords_geo) {
+    if (coords_geo.x < 0.0 || 1.0 < coords_geo.x
+            || coords_geo.y < 0.0 || 1.0 < coords_geo.y)
+        return vec4(0.0);
+
+    vec3 coords_tex = geo_to_tex * coords_geo;
+    return texture2D(tex, coords_tex.st);
+}
+
+// Example: slide the workspaces vertically, equivalent to the default
+// workspace switch.
+vec4 default_switch(vec3 coords_geo, vec3 size_geo) {
+    vec3 coords_prev = vec3(coords_geo.x, coords_geo.y + niri_progress, 1.0);
+    vec3 coords_next = vec3(coords_geo.x, coords_geo.y + niri_progress - 1.0, 1.0);
+
+    vec4 prev = sample_workspace(niri_tex_prev, niri_geo_to_tex_prev, coords_prev);
+    vec4 next = sample_workspace(niri_tex_next, niri_geo_to_tex_next, coords_next);
+    return prev + next;
+}
+
+// Example: cross-fade between the workspaces without moving them.
+vec4 cross_fade(vec3 coords_geo, vec3 size_geo) {
+    vec4 prev = sample_workspace(niri_tex_prev, niri_geo_to_tex_prev, coords_geo);
+    vec4 next = sample_workspace(niri_tex_next, niri_geo_to_tex_next, coords_geo);
+    return mix(prev, next, niri_progress);
+}
+
+// Example: reveal the lower workspace in a circle growing from the cursor.
+vec4 circle_from_cursor(vec3 coords_geo, vec3 size_geo) {
+    vec2 coords = coords_geo.xy * size_geo.xy;
+
+    // Grow the circle until it covers the farthest output corner.
+    float max_radius = length(max(niri_cursor_position, size_geo.xy - niri_cursor_position));
+    float radius = niri_progress * max_radius;
+
+    vec4 prev = sample_workspace(niri_tex_prev, niri_geo_to_tex_prev, coords_geo);
+    vec4 next = sample_workspace(niri_tex_next, niri_geo_to_tex_next, coords_geo);
+
+    // Smooth out the circle edge a bit.
+    float dist = distance(coords, niri_cursor_position);
+    return mix(next, prev, smoothstep(radius - 2.0, radius, dist));
+}
+
+// This is the function that you must define.
+vec4 workspace_switch_color(vec3 coords_geo, vec3 size_geo) {
+    // You can pick one of the example functions or write your own.
+    return circle_from_cursor(coords_geo, size_geo);
+}